@@ -0,0 +1,427 @@
+//! Agent progress runner
+//!
+//! Runs a long command so it shows up in the overlay like an agent's task:
+//!
+//! ```text
+//! $ ap-run -- cargo build --release
+//! $ ap-run --label "Fetch dataset" -- wget https://example.com/data.tar.gz
+//! ```
+//!
+//! `task_started` is appended to the events file once the command is running,
+//! and `task_complete` or `task_error` when it exits. Output passes through
+//! unchanged; progress printed by tools like pip, cargo, wget or tqdm is
+//! written to the task's file in `downloads/` next to the events file, which
+//! the overlay shows as a progress bar. The command's exit code is ours.
+
+use std::fs::{self, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// How often the progress file is rewritten while output streams in
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(200);
+
+const USAGE: &str = "\
+Usage: ap-run [options] -- <command> [args...]
+
+Options:
+  --label <text>      Description shown in the overlay (default: the command)
+  --tool <name>       Tool name shown in the overlay (default: Bash)
+  --session <id>      Session to group the task under
+  --events <file>     Events file to write to (default: progress-events.jsonl
+                      in $CLAUDE_CONFIG_DIR, or ~/.claude)
+  -h, --help          Show this help";
+
+#[derive(Debug, PartialEq)]
+struct Options {
+    command: Vec<String>,
+    label: Option<String>,
+    tool: String,
+    session: Option<String>,
+    events_file: PathBuf,
+}
+
+fn home_dir() -> PathBuf {
+    std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("."))
+}
+
+/// Claude's config directory, where the overlay reads events
+fn claude_dir() -> PathBuf {
+    std::env::var_os("CLAUDE_CONFIG_DIR")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| home_dir().join(".claude"))
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options {
+        command: Vec::new(),
+        label: None,
+        tool: "Bash".to_string(),
+        session: None,
+        events_file: claude_dir().join("progress-events.jsonl"),
+    };
+
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("{} needs a value", arg));
+        match arg.as_str() {
+            "--label" => options.label = Some(value()?),
+            "--tool" => options.tool = value()?,
+            "--session" => options.session = Some(value()?),
+            "--events" => options.events_file = PathBuf::from(value()?),
+            "-h" | "--help" => return Err(USAGE.to_string()),
+            "--" => {
+                options.command = args.collect();
+                break;
+            }
+            other => return Err(format!("Unknown option: {}\n\n{}", other, USAGE)),
+        }
+    }
+
+    if options.command.is_empty() {
+        return Err(format!("No command given\n\n{}", USAGE));
+    }
+    Ok(options)
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0)
+}
+
+/// `text` as a JSON string literal
+fn json_string(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + 2);
+    out.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// A JSON object from fields already written as JSON values; `None` values are left out
+fn json_object(fields: &[(&str, Option<String>)]) -> String {
+    let fields: Vec<String> = fields
+        .iter()
+        .filter_map(|(name, value)| value.as_ref().map(|v| format!("{}:{}", json_string(name), v)))
+        .collect();
+    format!("{{{}}}", fields.join(","))
+}
+
+/// Progress found in one line of output
+#[derive(Debug, Clone, PartialEq)]
+struct Progress {
+    percent: f64,
+    speed: Option<String>,
+    eta: Option<String>,
+}
+
+/// Whether a line draws a progress bar, so a `done/total` count in it is progress
+fn has_bar(line: &str) -> bool {
+    line.contains('━') || line.contains('█') || (line.contains('[') && (line.contains('=') || line.contains('#')))
+}
+
+/// Read progress from a line such as `45%|████` (tqdm), ` 45% [===>  ] 1.2M 300KB/s eta 5s`
+/// (wget), `━━━ 12.3/45.6 MB 3.1 MB/s eta 0:00:10` (pip) or `Building [==>  ] 120/300: serde` (cargo)
+fn parse_progress(line: &str) -> Option<Progress> {
+    let tokens: Vec<&str> = line.split_whitespace().collect();
+
+    let percent = tokens.iter().find_map(|token| {
+        let (number, _) = token.split_once('%')?;
+        let digits = number.trim_start_matches(|c: char| !c.is_ascii_digit());
+        digits.parse::<f64>().ok().filter(|p| (0.0..=100.0).contains(p))
+    });
+    let percent = percent.or_else(|| {
+        if !has_bar(line) {
+            return None;
+        }
+        tokens.iter().find_map(|token| {
+            let (done, total) = token.trim_end_matches(':').split_once('/')?;
+            let (done, total) = (done.parse::<f64>().ok()?, total.parse::<f64>().ok()?);
+            (total > 0.0 && done <= total).then(|| done / total * 100.0)
+        })
+    })?;
+
+    let speed = tokens.iter().position(|token| token.ends_with("/s")).map(|i| {
+        if tokens[i].starts_with(|c: char| c.is_ascii_digit()) || i == 0 {
+            tokens[i].to_string()
+        } else {
+            format!("{} {}", tokens[i - 1], tokens[i])
+        }
+    });
+    let eta = tokens
+        .iter()
+        .position(|token| token.eq_ignore_ascii_case("eta"))
+        .and_then(|i| tokens.get(i + 1))
+        .map(|eta| eta.to_string());
+
+    Some(Progress { percent, speed, eta })
+}
+
+/// Output split into lines at `\n` and at the `\r` progress bars redraw with
+#[derive(Default)]
+struct Lines {
+    partial: Vec<u8>,
+}
+
+impl Lines {
+    /// Complete lines in `bytes`, after any partial line left from the last call
+    fn push(&mut self, bytes: &[u8]) -> Vec<String> {
+        let mut lines = Vec::new();
+        for &byte in bytes {
+            if byte == b'\n' || byte == b'\r' {
+                if !self.partial.is_empty() {
+                    lines.push(String::from_utf8_lossy(&self.partial).into_owned());
+                    self.partial.clear();
+                }
+            } else {
+                self.partial.push(byte);
+            }
+        }
+        lines
+    }
+}
+
+/// Writes the task's progress file for the overlay, at most every `PROGRESS_INTERVAL`
+struct Reporter {
+    task_id: String,
+    path: PathBuf,
+    last_write: Option<Instant>,
+}
+
+impl Reporter {
+    fn new(downloads_dir: &Path, task_id: &str) -> Self {
+        Self {
+            task_id: task_id.to_string(),
+            path: downloads_dir.join(format!("{}.json", task_id)),
+            last_write: None,
+        }
+    }
+
+    fn report(&mut self, progress: &Progress) {
+        let finished = progress.percent >= 100.0;
+        if self.last_write.is_some_and(|at| at.elapsed() < PROGRESS_INTERVAL) && !finished {
+            return;
+        }
+        self.last_write = Some(Instant::now());
+
+        let body = json_object(&[
+            ("task_id", Some(json_string(&self.task_id))),
+            ("percent", Some(format!("{:.1}", progress.percent))),
+            ("speed", Some(progress.speed.as_deref().map_or("null".to_string(), json_string))),
+            ("eta", Some(progress.eta.as_deref().map_or("null".to_string(), json_string))),
+            ("timestamp", Some(now_ms().to_string())),
+        ]);
+        // Written beside the file and renamed over it, so the overlay never reads half of it
+        let temp = self.path.with_extension("json.tmp");
+        let written = fs::create_dir_all(self.path.parent().unwrap_or(Path::new(".")))
+            .and_then(|_| fs::write(&temp, body))
+            .and_then(|_| fs::rename(&temp, &self.path));
+        if let Err(e) = written {
+            eprintln!("ap-run: couldn't write progress to {}: {}", self.path.display(), e);
+        }
+    }
+
+    fn finish(&self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Append one event line to the events file
+fn append_event(path: &Path, event: &str) {
+    let result = path
+        .parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|_| OpenOptions::new().create(true).append(true).open(path))
+        .and_then(|mut file| file.write_all(format!("{}\n", event).as_bytes()));
+    if let Err(e) = result {
+        eprintln!("ap-run: couldn't write to {}: {}", path.display(), e);
+    }
+}
+
+/// Copy a child's output stream to ours, reporting any progress in it
+fn relay_output(mut from: impl Read, mut to: impl Write, reporter: Arc<Mutex<Reporter>>) {
+    let mut lines = Lines::default();
+    let mut buffer = [0u8; 8192];
+    loop {
+        let read = match from.read(&mut buffer) {
+            Ok(0) | Err(_) => return,
+            Ok(read) => read,
+        };
+        let _ = to.write_all(&buffer[..read]);
+        let _ = to.flush();
+
+        if let Some(progress) = lines.push(&buffer[..read]).iter().rev().find_map(|line| parse_progress(line)) {
+            if let Ok(mut reporter) = reporter.lock() {
+                reporter.report(&progress);
+            }
+        }
+    }
+}
+
+fn run(options: Options) -> i32 {
+    let mut child = match Command::new(&options.command[0])
+        .args(&options.command[1..])
+        .stdin(Stdio::inherit())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(e) => {
+            eprintln!("ap-run: couldn't run {}: {}", options.command[0], e);
+            return 127;
+        }
+    };
+
+    let started_at = now_ms();
+    let task_id = format!("ap-run-{}-{}", std::process::id(), started_at);
+    let description = options.label.clone().unwrap_or_else(|| options.command.join(" "));
+    let project_path = std::env::current_dir().ok().map(|dir| dir.to_string_lossy().into_owned());
+    append_event(
+        &options.events_file,
+        &json_object(&[
+            ("type", Some(json_string("task_started"))),
+            ("task_id", Some(json_string(&task_id))),
+            ("tool", Some(json_string(&options.tool))),
+            ("description", Some(json_string(&description))),
+            ("timestamp", Some(started_at.to_string())),
+            ("session_id", options.session.as_deref().map(json_string)),
+            ("project_path", project_path.as_deref().map(json_string)),
+            ("source", Some(json_string("ap-run"))),
+            ("pid", Some(child.id().to_string())),
+        ]),
+    );
+
+    let downloads_dir = options.events_file.parent().unwrap_or(Path::new(".")).join("downloads");
+    let reporter = Arc::new(Mutex::new(Reporter::new(&downloads_dir, &task_id)));
+    let stdout = child.stdout.take().map(|out| {
+        let reporter = reporter.clone();
+        std::thread::spawn(move || relay_output(out, io::stdout(), reporter))
+    });
+    let stderr = child.stderr.take().map(|err| {
+        let reporter = reporter.clone();
+        std::thread::spawn(move || relay_output(err, io::stderr(), reporter))
+    });
+    for relay in stdout.into_iter().chain(stderr) {
+        let _ = relay.join();
+    }
+
+    let status = child.wait();
+    if let Ok(reporter) = reporter.lock() {
+        reporter.finish();
+    }
+
+    let ended_at = now_ms();
+    let succeeded = status.as_ref().is_ok_and(|s| s.success());
+    append_event(
+        &options.events_file,
+        &json_object(&[
+            ("type", Some(json_string(if succeeded { "task_complete" } else { "task_error" }))),
+            ("task_id", Some(json_string(&task_id))),
+            ("duration_ms", Some(ended_at.saturating_sub(started_at).to_string())),
+            ("timestamp", Some(ended_at.to_string())),
+        ]),
+    );
+
+    match status {
+        Ok(status) => status.code().unwrap_or(1),
+        Err(e) => {
+            eprintln!("ap-run: couldn't wait for {}: {}", options.command[0], e);
+            1
+        }
+    }
+}
+
+fn main() {
+    match parse_args(std::env::args().skip(1)) {
+        Ok(options) => std::process::exit(run(options)),
+        Err(message) => {
+            eprintln!("{}", message);
+            std::process::exit(2);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> impl Iterator<Item = String> {
+        list.iter().map(|s| s.to_string()).collect::<Vec<_>>().into_iter()
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let options = parse_args(args(&["--label", "Build", "--", "cargo", "build", "--label"])).unwrap();
+        assert_eq!(options.command, ["cargo", "build", "--label"]);
+        assert_eq!(options.label.as_deref(), Some("Build"));
+        assert_eq!(options.tool, "Bash");
+
+        assert!(parse_args(args(&["--label", "Build"])).unwrap_err().contains("No command"));
+        assert!(parse_args(args(&["--tool"])).unwrap_err().contains("needs a value"));
+        assert!(parse_args(args(&["cargo"])).unwrap_err().contains("Unknown option"));
+    }
+
+    #[test]
+    fn test_parse_progress() {
+        let tqdm = parse_progress(" 45%|████▌     | 45/100 [00:05<00:06,  8.91it/s]").unwrap();
+        assert_eq!(tqdm.percent, 45.0);
+
+        let wget = parse_progress("data.tar.gz  62%[=======>     ]  1.20M  300KB/s    eta 5s").unwrap();
+        assert_eq!(wget, Progress { percent: 62.0, speed: Some("300KB/s".to_string()), eta: Some("5s".to_string()) });
+
+        let pip = parse_progress("   ━━━━━━━━━━━━━━━━━━━━ 11.4/45.6 MB 3.1 MB/s eta 0:00:12").unwrap();
+        assert_eq!(pip.percent, 25.0);
+        assert_eq!(pip.speed.as_deref(), Some("3.1 MB/s"));
+        assert_eq!(pip.eta.as_deref(), Some("0:00:12"));
+
+        let cargo = parse_progress("    Building [=====>        ] 75/300: serde_json").unwrap();
+        assert_eq!(cargo.percent, 25.0);
+
+        // A count without a bar isn't progress, and neither are percentages over 100
+        assert!(parse_progress("Merged 3/4 files").is_none());
+        assert!(parse_progress("CPU at 250%").is_none());
+    }
+
+    #[test]
+    fn test_lines_split_at_carriage_returns() {
+        let mut lines = Lines::default();
+        assert_eq!(lines.push(b"10%\r20%\r3"), ["10%", "20%"]);
+        assert_eq!(lines.push(b"0%\r\ndone\n"), ["30%", "done"]);
+    }
+
+    #[test]
+    fn test_json() {
+        assert_eq!(json_string("a \"b\"\n\\"), r#""a \"b\"\n\\""#);
+        let object = json_object(&[("type", Some(json_string("task_error"))), ("session_id", None), ("timestamp", Some("5".to_string()))]);
+        assert_eq!(object, r#"{"type":"task_error","timestamp":5}"#);
+    }
+
+    #[test]
+    fn test_reporter_writes_progress_file() {
+        let dir = std::env::temp_dir().join(format!("ap-run-downloads-{}", std::process::id()));
+        let mut reporter = Reporter::new(&dir, "t1");
+        reporter.report(&Progress { percent: 40.0, speed: None, eta: Some("5s".to_string()) });
+
+        let written = fs::read_to_string(dir.join("t1.json")).unwrap();
+        assert!(written.starts_with(r#"{"task_id":"t1","percent":40.0,"speed":null,"eta":"5s","timestamp":"#));
+
+        reporter.finish();
+        assert!(!dir.join("t1.json").exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,286 @@
+//! Agent progress relay
+//!
+//! Runs on a remote dev box, tails the agent events file, and forwards each new
+//! event to the desktop overlay's relay listener. The listener only binds to
+//! localhost on the desktop, so the connection is carried over an SSH tunnel:
+//!
+//! ```text
+//! desktop$ ssh -R 47616:127.0.0.1:47616 devbox
+//! devbox$  AGENT_PROGRESS_RELAY_TOKEN=... agent-progress-relay
+//! ```
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::net::TcpStream;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use serde_json::{json, Value};
+
+/// Must match the overlay's relay protocol version
+const PROTOCOL_VERSION: u32 = 1;
+const DEFAULT_ADDRESS: &str = "127.0.0.1:47616";
+const TOKEN_ENV: &str = "AGENT_PROGRESS_RELAY_TOKEN";
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+const USAGE: &str = "\
+Usage: agent-progress-relay [options]
+
+Options:
+  --events <file>     Events file to tail (default: progress-events.jsonl in
+                      $CLAUDE_CONFIG_DIR, or ~/.claude)
+  --connect <addr>    Overlay relay address (default: 127.0.0.1:47616)
+  --token <token>     Relay token (default: $AGENT_PROGRESS_RELAY_TOKEN)
+  --host <name>       Name shown in the overlay's logs (default: hostname)
+  --from-start        Forward events already in the file
+  -h, --help          Show this help";
+
+#[derive(Debug, PartialEq)]
+struct Options {
+    events_file: PathBuf,
+    address: String,
+    token: String,
+    host: String,
+    from_start: bool,
+}
+
+fn home_dir() -> PathBuf {
+    std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("."))
+}
+
+/// Claude's config directory, where the hooks write events
+fn claude_dir() -> PathBuf {
+    std::env::var_os("CLAUDE_CONFIG_DIR")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| home_dir().join(".claude"))
+}
+
+fn hostname() -> String {
+    std::env::var("HOSTNAME")
+        .ok()
+        .or_else(|| std::fs::read_to_string("/etc/hostname").ok())
+        .map(|h| h.trim().to_string())
+        .filter(|h| !h.is_empty())
+        .unwrap_or_else(|| "remote".to_string())
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>, env_token: Option<String>) -> Result<Options, String> {
+    let mut options = Options {
+        events_file: claude_dir().join("progress-events.jsonl"),
+        address: DEFAULT_ADDRESS.to_string(),
+        token: env_token.unwrap_or_default(),
+        host: hostname(),
+        from_start: false,
+    };
+
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("{} needs a value", arg));
+        match arg.as_str() {
+            "--events" => options.events_file = PathBuf::from(value()?),
+            "--connect" => options.address = value()?,
+            "--token" => options.token = value()?,
+            "--host" => options.host = value()?,
+            "--from-start" => options.from_start = true,
+            "-h" | "--help" => return Err(USAGE.to_string()),
+            other => return Err(format!("Unknown option: {}\n\n{}", other, USAGE)),
+        }
+    }
+
+    if options.token.is_empty() {
+        return Err(format!("A relay token is required (--token or ${})", TOKEN_ENV));
+    }
+    Ok(options)
+}
+
+/// Read position in the events file, advanced only once lines are delivered
+struct Tail {
+    path: PathBuf,
+    position: u64,
+}
+
+impl Tail {
+    fn new(path: PathBuf, from_start: bool) -> Self {
+        let position = if from_start {
+            0
+        } else {
+            std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0)
+        };
+        Self { path, position }
+    }
+
+    /// Complete lines appended since the last commit, with the position after them
+    fn read_new(&mut self) -> io::Result<(Vec<String>, u64)> {
+        let mut file = File::open(&self.path)?;
+        let len = file.metadata()?.len();
+
+        // The file was truncated or replaced
+        if len < self.position {
+            self.position = 0;
+        }
+        if len == self.position {
+            return Ok((Vec::new(), self.position));
+        }
+
+        file.seek(SeekFrom::Start(self.position))?;
+        let mut buffer = String::new();
+        file.take(len - self.position).read_to_string(&mut buffer)?;
+
+        // A trailing partial line is left for the next read
+        let complete = buffer.rfind('\n').map(|i| i + 1).unwrap_or(0);
+        let lines = buffer[..complete]
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty())
+            .map(str::to_string)
+            .collect();
+        Ok((lines, self.position + complete as u64))
+    }
+
+    fn commit(&mut self, position: u64) {
+        self.position = position;
+    }
+}
+
+/// Connect and complete the handshake
+fn connect(options: &Options) -> Result<TcpStream, String> {
+    let mut stream = TcpStream::connect(&options.address).map_err(|e| e.to_string())?;
+    stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT)).map_err(|e| e.to_string())?;
+
+    let hello = json!({
+        "type": "hello",
+        "version": PROTOCOL_VERSION,
+        "role": "relay",
+        "host": options.host,
+        "token": options.token,
+    });
+    writeln!(stream, "{}", hello).map_err(|e| e.to_string())?;
+
+    let mut reply = String::new();
+    BufReader::new(&stream).read_line(&mut reply).map_err(|e| e.to_string())?;
+    let reply: Value = serde_json::from_str(&reply).map_err(|_| "No reply from overlay".to_string())?;
+
+    match reply["type"].as_str() {
+        Some("welcome") => Ok(stream),
+        _ => Err(reply["message"].as_str().unwrap_or("Handshake rejected").to_string()),
+    }
+}
+
+/// Forward a batch of event lines; lines that aren't JSON are skipped
+fn send_lines(stream: &mut TcpStream, lines: &[String]) -> io::Result<()> {
+    let mut batch = String::new();
+    for line in lines {
+        match serde_json::from_str::<Value>(line) {
+            Ok(event) => {
+                batch.push_str(&json!({ "type": "event", "event": event }).to_string());
+                batch.push('\n');
+            }
+            Err(e) => eprintln!("Skipping invalid event line: {}", e),
+        }
+    }
+    stream.write_all(batch.as_bytes())?;
+    stream.flush()
+}
+
+fn run(options: Options) {
+    let mut tail = Tail::new(options.events_file.clone(), options.from_start);
+    let mut stream: Option<TcpStream> = None;
+    let mut backoff = Duration::from_secs(1);
+
+    eprintln!("Relaying {} to {}", options.events_file.display(), options.address);
+
+    loop {
+        let Some(connection) = stream.as_mut() else {
+            match connect(&options) {
+                Ok(s) => {
+                    eprintln!("Connected to overlay at {}", options.address);
+                    stream = Some(s);
+                    backoff = Duration::from_secs(1);
+                }
+                Err(e) => {
+                    eprintln!("Can't reach overlay ({}); retrying in {}s", e, backoff.as_secs());
+                    std::thread::sleep(backoff);
+                    backoff = (backoff * 2).min(MAX_BACKOFF);
+                }
+            }
+            continue;
+        };
+
+        match tail.read_new() {
+            Ok((lines, position)) if !lines.is_empty() => match send_lines(connection, &lines) {
+                Ok(()) => tail.commit(position),
+                Err(e) => {
+                    eprintln!("Lost connection to overlay: {}", e);
+                    stream = None;
+                    continue;
+                }
+            },
+            Ok((_, position)) => tail.commit(position),
+            // The agent hasn't created the file yet
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => eprintln!("Failed to read {}: {}", options.events_file.display(), e),
+        }
+
+        std::thread::sleep(POLL_INTERVAL);
+    }
+}
+
+fn main() {
+    match parse_args(std::env::args().skip(1), std::env::var(TOKEN_ENV).ok()) {
+        Ok(options) => run(options),
+        Err(message) => {
+            eprintln!("{}", message);
+            std::process::exit(2);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> impl Iterator<Item = String> {
+        list.iter().map(|s| s.to_string()).collect::<Vec<_>>().into_iter()
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let options = parse_args(args(&["--connect", "127.0.0.1:9000", "--host", "devbox"]), Some("secret".to_string())).unwrap();
+        assert_eq!(options.address, "127.0.0.1:9000");
+        assert_eq!(options.host, "devbox");
+        assert_eq!(options.token, "secret");
+        assert!(!options.from_start);
+
+        assert!(parse_args(args(&[]), None).unwrap_err().contains("token is required"));
+        assert!(parse_args(args(&["--connect"]), Some("t".to_string())).unwrap_err().contains("needs a value"));
+    }
+
+    #[test]
+    fn test_tail_keeps_partial_lines() {
+        let path = std::env::temp_dir().join(format!("relay-tail-{}.jsonl", std::process::id()));
+        std::fs::write(&path, "{\"old\":1}\n").unwrap();
+        let mut tail = Tail::new(path.clone(), false);
+
+        let mut file = std::fs::OpenOptions::new().append(true).open(&path).unwrap();
+        write!(file, "{{\"a\":1}}\n{{\"b\":").unwrap();
+        let (lines, position) = tail.read_new().unwrap();
+        assert_eq!(lines, ["{\"a\":1}"]);
+        tail.commit(position);
+
+        writeln!(file, "2}}").unwrap();
+        let (lines, position) = tail.read_new().unwrap();
+        assert_eq!(lines, ["{\"b\":2}"]);
+        tail.commit(position);
+
+        // Truncation starts over from the beginning
+        std::fs::write(&path, "{\"c\":3}\n").unwrap();
+        assert_eq!(tail.read_new().unwrap().0, ["{\"c\":3}"]);
+
+        let _ = std::fs::remove_file(&path);
+    }
+}
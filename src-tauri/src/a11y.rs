@@ -0,0 +1,108 @@
+//! Screen reader announcements
+//!
+//! The notification windows are transparent and never take focus, so screen
+//! readers don't notice them. Task starts and finishes are also announced
+//! through the platform accessibility API: an announcement request on macOS
+//! (VoiceOver) and a UI Automation notification on Windows (Narrator, NVDA).
+//! Elsewhere the text goes to a live region in the main window, which
+//! WebKitGTK reports to AT-SPI screen readers such as Orca.
+
+use tauri::AppHandle;
+#[cfg(not(any(windows, target_os = "macos")))]
+use tauri::Emitter;
+
+use crate::locale::{Locale, Message};
+
+/// What to announce for a task event, if anything
+pub fn announcement(event_type: &str, label: &str, locale: Locale) -> Option<String> {
+    let message = match event_type {
+        "task_started" => Message::TaskStarted,
+        "task_complete" => Message::TaskFinished,
+        "task_error" => Message::TaskFailed,
+        _ => return None,
+    };
+    Some(locale.format(message, &[("task", &label)]))
+}
+
+/// Have the screen reader speak `text`
+pub fn announce(app: &AppHandle, text: &str) {
+    if let Err(e) = post(app, text) {
+        tracing::debug!("Failed to post accessibility announcement: {}", e);
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn post(app: &AppHandle, text: &str) -> Result<(), String> {
+    let text = text.to_string();
+    // AppKit is only usable from the main thread
+    app.run_on_main_thread(move || {
+        use objc2::runtime::AnyObject;
+        use objc2_app_kit::{
+            NSAccessibilityAnnouncementKey, NSAccessibilityAnnouncementRequestedNotification,
+            NSAccessibilityPostNotificationWithUserInfo, NSAccessibilityPriorityKey, NSAccessibilityPriorityLevel,
+            NSApplication,
+        };
+        use objc2_foundation::{MainThreadMarker, NSDictionary, NSNumber, NSString};
+
+        let Some(mtm) = MainThreadMarker::new() else {
+            return;
+        };
+        let application = NSApplication::sharedApplication(mtm);
+        let announcement = NSString::from_str(&text);
+        let priority = NSNumber::new_isize(NSAccessibilityPriorityLevel::High.0);
+        let values: [&AnyObject; 2] = [&announcement, &priority];
+        unsafe {
+            let info = NSDictionary::from_slices(&[NSAccessibilityAnnouncementKey, NSAccessibilityPriorityKey], &values);
+            NSAccessibilityPostNotificationWithUserInfo(
+                &application,
+                NSAccessibilityAnnouncementRequestedNotification,
+                Some(&info),
+            );
+        }
+    })
+    .map_err(|e| e.to_string())
+}
+
+#[cfg(windows)]
+fn post(app: &AppHandle, text: &str) -> Result<(), String> {
+    use tauri::Manager;
+    use windows::core::BSTR;
+    use windows::Win32::UI::Accessibility::{
+        NotificationKind_Other, NotificationProcessing_ImportantMostRecent, UiaHostProviderFromHwnd,
+        UiaRaiseNotificationEvent,
+    };
+
+    // Lets Narrator drop an unread announcement when the next one arrives
+    const ACTIVITY_ID: &str = "agent-progress-task";
+
+    let window = app.get_webview_window("main").ok_or("Main window not found")?;
+    let hwnd = window.hwnd().map_err(|e| e.to_string())?;
+    unsafe {
+        let provider = UiaHostProviderFromHwnd(hwnd).map_err(|e| e.to_string())?;
+        UiaRaiseNotificationEvent(
+            &provider,
+            NotificationKind_Other,
+            NotificationProcessing_ImportantMostRecent,
+            &BSTR::from(text),
+            &BSTR::from(ACTIVITY_ID),
+        )
+        .map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(not(any(windows, target_os = "macos")))]
+fn post(app: &AppHandle, text: &str) -> Result<(), String> {
+    app.emit_to("main", "a11y-announcement", text).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_announcement() {
+        assert_eq!(announcement("task_started", "npm test", Locale::En).as_deref(), Some("Started: npm test"));
+        assert_eq!(announcement("task_error", "npm test", Locale::En).as_deref(), Some("Failed: npm test"));
+        assert_eq!(announcement("task_canceled", "npm test", Locale::En), None);
+    }
+}
@@ -0,0 +1,180 @@
+//! Aider chat history adapter
+//!
+//! Aider appends a markdown transcript to `.aider.chat.history.md` in each repo.
+//! Session headers start a new session, and the `> Applied edit to ...` and
+//! `> Commit ...` notices become completed Edit and Git tasks.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use super::{expand_home, finished_task, now_ms, LineParser};
+use crate::watcher::TaskEvent;
+
+pub const HISTORY_FILE: &str = ".aider.chat.history.md";
+const SOURCE: &str = "aider";
+
+/// A line of the chat history that maps to an overlay event
+#[derive(Debug, Clone, PartialEq)]
+pub enum AiderLine {
+    /// `# aider chat started at <time>`
+    SessionStarted(String),
+    /// `#### <prompt>`
+    Prompt(String),
+    /// `> Applied edit to <file>`
+    Edit(String),
+    /// `> Commit <hash> <message>`
+    Commit { hash: String, message: String },
+}
+
+/// Classify one history line; everything else is chat content
+pub fn parse_line(line: &str) -> Option<AiderLine> {
+    let line = line.trim();
+
+    if let Some(started) = line.strip_prefix("# aider chat started at ") {
+        return Some(AiderLine::SessionStarted(started.trim().to_string()));
+    }
+    if let Some(prompt) = line.strip_prefix("#### ") {
+        return Some(AiderLine::Prompt(prompt.trim().to_string()));
+    }
+
+    let notice = line.strip_prefix("> ")?.trim();
+    if let Some(file) = notice.strip_prefix("Applied edit to ") {
+        return Some(AiderLine::Edit(file.trim().to_string()));
+    }
+    if let Some(rest) = notice.strip_prefix("Commit ") {
+        let (hash, message) = rest.split_once(' ').unwrap_or((rest, ""));
+        if hash.len() >= 7 && hash.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Some(AiderLine::Commit {
+                hash: hash.to_string(),
+                message: message.trim().to_string(),
+            });
+        }
+    }
+
+    None
+}
+
+/// Paths to watch for the configured history files or project directories
+pub fn targets(history_files: &[String]) -> Vec<PathBuf> {
+    history_files
+        .iter()
+        .map(|p| {
+            let path = expand_home(p);
+            if path.is_dir() { path.join(HISTORY_FILE) } else { path }
+        })
+        .collect()
+}
+
+/// Per-file session tracking
+#[derive(Default)]
+struct HistoryState {
+    session_id: Option<String>,
+    next_task: u64,
+}
+
+#[derive(Default)]
+pub struct AiderParser {
+    files: HashMap<PathBuf, HistoryState>,
+}
+
+impl AiderParser {
+    fn session_event(&self, event_type: &str, session_id: &str, timestamp: u64) -> TaskEvent {
+        let mut event = TaskEvent::new(event_type, &format!("{}-{}", session_id, event_type), timestamp);
+        event.session_id = Some(session_id.to_string());
+        event.source = Some(SOURCE.to_string());
+        event
+    }
+}
+
+impl LineParser for AiderParser {
+    fn accepts(&self, path: &Path) -> bool {
+        path.file_name().is_some_and(|n| n == HISTORY_FILE)
+    }
+
+    fn parse_line(&mut self, path: &Path, line: &str) -> Vec<TaskEvent> {
+        let Some(parsed) = parse_line(line) else {
+            return Vec::new();
+        };
+        let timestamp = now_ms();
+        let project_path = path.parent().map(|p| p.to_string_lossy().to_string());
+        let mut events = Vec::new();
+
+        let previous = self.files.get(path).and_then(|s| s.session_id.clone());
+        let (tool, description) = match parsed {
+            AiderLine::SessionStarted(started) => {
+                if let Some(previous) = previous {
+                    events.push(self.session_event("session_stopped", &previous, timestamp));
+                }
+                let digits: String = started.chars().filter(|c| c.is_ascii_digit()).collect();
+                let state = self.files.entry(path.to_path_buf()).or_default();
+                state.session_id = Some(format!("aider-{}-{}", digits, timestamp % 1_000_000));
+                state.next_task = 0;
+                return events;
+            }
+            AiderLine::Prompt(_) => return events,
+            AiderLine::Edit(file) => ("Edit", file),
+            AiderLine::Commit { hash, message } => ("Git", format!("commit {} {}", hash, message).trim().to_string()),
+        };
+
+        // History that predates the first header we saw still belongs to some session
+        let state = self.files.entry(path.to_path_buf()).or_default();
+        let session_id = state
+            .session_id
+            .get_or_insert_with(|| format!("aider-{}", timestamp))
+            .clone();
+        state.next_task += 1;
+
+        let mut started = TaskEvent::new("task_started", &format!("{}-{}", session_id, state.next_task), timestamp);
+        started.session_id = Some(session_id);
+        started.tool = Some(tool.to_string());
+        started.description = Some(description);
+        started.project_path = project_path;
+        started.source = Some(SOURCE.to_string());
+
+        events.extend(finished_task(started, "task_complete", 0));
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_history_lines() {
+        assert_eq!(
+            parse_line("# aider chat started at 2024-05-01 10:15:02"),
+            Some(AiderLine::SessionStarted("2024-05-01 10:15:02".to_string()))
+        );
+        assert_eq!(parse_line("#### add a login form"), Some(AiderLine::Prompt("add a login form".to_string())));
+        assert_eq!(parse_line("> Applied edit to src/app.py"), Some(AiderLine::Edit("src/app.py".to_string())));
+        assert_eq!(
+            parse_line("> Commit 1a2b3c4 feat: Add login form"),
+            Some(AiderLine::Commit { hash: "1a2b3c4".to_string(), message: "feat: Add login form".to_string() })
+        );
+        assert_eq!(parse_line("> Commit to the change? (Y)es/(N)o"), None);
+        assert_eq!(parse_line("Sure, here is the change:"), None);
+    }
+
+    #[test]
+    fn test_parser_emits_session_tasks() {
+        let path = PathBuf::from("/work/app").join(HISTORY_FILE);
+        let mut parser = AiderParser::default();
+
+        assert!(parser.parse_line(&path, "# aider chat started at 2024-05-01 10:15:02").is_empty());
+        let events = parser.parse_line(&path, "> Applied edit to src/app.py");
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].event_type, "task_started");
+        assert_eq!(events[0].tool.as_deref(), Some("Edit"));
+        assert_eq!(events[0].project_path.as_deref(), Some("/work/app"));
+        assert_eq!(events[1].event_type, "task_complete");
+        assert_eq!(events[1].task_id, events[0].task_id);
+        assert!(events.iter().all(|e| e.source.as_deref() == Some("aider")));
+
+        let first_session = events[0].session_id.clone();
+        let events = parser.parse_line(&path, "# aider chat started at 2024-05-01 11:00:00");
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].event_type, "session_stopped");
+        assert_eq!(events[0].session_id, first_session);
+    }
+}
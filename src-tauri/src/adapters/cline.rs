@@ -0,0 +1,193 @@
+//! Cline (VS Code extension) adapter
+//!
+//! Cline keeps each task's UI transcript in
+//! `<VS Code globalStorage>/saoudrizwan.claude-dev/tasks/<task id>/ui_messages.json`
+//! and rewrites the whole array as messages stream in. Tool and command
+//! messages open a task that the next message closes; approval prompts and
+//! follow-up questions also raise `awaiting_input`.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+
+use super::{expand_home, DocumentParser};
+use crate::watcher::TaskEvent;
+
+pub const MESSAGES_FILE: &str = "ui_messages.json";
+const EXTENSION_ID: &str = "saoudrizwan.claude-dev";
+const SOURCE: &str = "cline";
+
+/// Task directory to watch; an empty setting means VS Code's default global storage
+pub fn tasks_dir(configured: &str) -> PathBuf {
+    if !configured.is_empty() {
+        return expand_home(configured);
+    }
+    dirs::config_dir()
+        .unwrap_or_else(|| expand_home("~/.config"))
+        .join("Code")
+        .join("User")
+        .join("globalStorage")
+        .join(EXTENSION_ID)
+        .join("tasks")
+}
+
+/// Per-transcript progress
+#[derive(Default)]
+struct TranscriptState {
+    /// Messages already translated
+    processed: usize,
+    /// Task opened by the last tool or command message, with its start time
+    open: Option<(String, u64)>,
+}
+
+#[derive(Default)]
+pub struct ClineParser {
+    transcripts: HashMap<PathBuf, TranscriptState>,
+}
+
+/// Overlay tool and description for the JSON body of a `tool` message
+fn describe_tool(text: &str) -> (String, Option<String>) {
+    let body: Value = serde_json::from_str(text).unwrap_or_default();
+    let field = |key: &str| body[key].as_str().map(str::to_string);
+
+    let tool = match body["tool"].as_str().unwrap_or("") {
+        "editedExistingFile" => "Edit",
+        "newFileCreated" => "Write",
+        "readFile" => "Read",
+        "listFilesTopLevel" | "listFilesRecursive" => "LS",
+        "searchFiles" => return ("Grep".to_string(), field("regex")),
+        "listCodeDefinitionNames" => "Glob",
+        "webFetch" => return ("WebFetch".to_string(), field("url").or_else(|| field("path"))),
+        other => return (other.to_string(), field("path")),
+    };
+    (tool.to_string(), field("path"))
+}
+
+impl ClineParser {
+    fn translate(session_id: &str, state: &mut TranscriptState, message: &Value) -> Vec<TaskEvent> {
+        let ts = message["ts"].as_u64().unwrap_or(0);
+        let kind = message["type"].as_str().unwrap_or("");
+        let sub = message[kind].as_str().unwrap_or("");
+        let text = message["text"].as_str().unwrap_or("");
+        let mut events = Vec::new();
+
+        // Command output belongs to the command that is still running
+        if sub == "command_output" {
+            return events;
+        }
+
+        if let Some((task_id, started_at)) = state.open.take() {
+            let end_type = if sub == "error" { "task_error" } else { "task_complete" };
+            let mut end = TaskEvent::new(end_type, &task_id, ts);
+            end.session_id = Some(session_id.to_string());
+            end.duration_ms = Some(ts.saturating_sub(started_at));
+            end.source = Some(SOURCE.to_string());
+            events.push(end);
+        }
+
+        let (tool, description) = match sub {
+            "tool" => describe_tool(text),
+            "command" => ("Bash".to_string(), Some(text.to_string())),
+            "use_mcp_server" => {
+                let body: Value = serde_json::from_str(text).unwrap_or_default();
+                let name = body["toolName"].as_str().or_else(|| body["uri"].as_str()).unwrap_or("MCP");
+                ("MCP".to_string(), Some(name.to_string()))
+            }
+            "followup" if kind == "ask" => {
+                // Questions may be a JSON document with suggested answers
+                let body: Value = serde_json::from_str(text).unwrap_or_default();
+                let question = body["question"].as_str().unwrap_or(text);
+                ("Question".to_string(), Some(question.to_string()))
+            }
+            _ => return events,
+        };
+
+        let task_id = format!("{}-{}", session_id, ts);
+        let mut started = TaskEvent::new("task_started", &task_id, ts);
+        started.session_id = Some(session_id.to_string());
+        started.tool = Some(tool);
+        started.description = description;
+        started.source = Some(SOURCE.to_string());
+
+        if kind == "ask" {
+            let mut waiting = started.clone();
+            waiting.event_type = "awaiting_input".to_string();
+            events.push(started);
+            events.push(waiting);
+        } else {
+            events.push(started);
+        }
+
+        state.open = Some((task_id, ts));
+        events
+    }
+}
+
+impl DocumentParser for ClineParser {
+    fn accepts(&self, path: &Path) -> bool {
+        path.file_name().is_some_and(|n| n == MESSAGES_FILE)
+    }
+
+    fn parse_document(&mut self, path: &Path, contents: &str) -> Vec<TaskEvent> {
+        let Ok(messages) = serde_json::from_str::<Vec<Value>>(contents) else {
+            return Vec::new();
+        };
+        let task_dir = path.parent().and_then(|p| p.file_name()).map(|n| n.to_string_lossy().to_string());
+        let session_id = format!("cline-{}", task_dir.unwrap_or_default());
+
+        let state = self.transcripts.entry(path.to_path_buf()).or_default();
+        // A shorter transcript means the task was reset
+        if messages.len() < state.processed {
+            *state = TranscriptState::default();
+        }
+
+        let mut events = Vec::new();
+        for message in &messages[state.processed..] {
+            // Streaming messages are rewritten in place until complete
+            if message["partial"] == Value::Bool(true) {
+                break;
+            }
+            events.extend(Self::translate(&session_id, state, message));
+            state.processed += 1;
+        }
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transcript_updates() {
+        let path = PathBuf::from("/storage/tasks/1700000000000").join(MESSAGES_FILE);
+        let mut parser = ClineParser::default();
+
+        let first = r#"[
+            {"ts": 1000, "type": "say", "say": "text", "text": "Let me look"},
+            {"ts": 1100, "type": "say", "say": "tool", "text": "{\"tool\":\"readFile\",\"path\":\"src/app.ts\"}"},
+            {"ts": 1500, "type": "ask", "ask": "command", "text": "npm test", "partial": true}
+        ]"#;
+        let events = parser.parse_document(&path, first);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].event_type, "task_started");
+        assert_eq!(events[0].tool.as_deref(), Some("Read"));
+        assert_eq!(events[0].session_id.as_deref(), Some("cline-1700000000000"));
+
+        let second = r#"[
+            {"ts": 1000, "type": "say", "say": "text", "text": "Let me look"},
+            {"ts": 1100, "type": "say", "say": "tool", "text": "{\"tool\":\"readFile\",\"path\":\"src/app.ts\"}"},
+            {"ts": 1500, "type": "ask", "ask": "command", "text": "npm test"},
+            {"ts": 1600, "type": "say", "say": "command_output", "text": "ok"},
+            {"ts": 2500, "type": "say", "say": "error", "text": "failed"}
+        ]"#;
+        let events = parser.parse_document(&path, second);
+        let types: Vec<&str> = events.iter().map(|e| e.event_type.as_str()).collect();
+        assert_eq!(types, ["task_complete", "task_started", "awaiting_input", "task_error"]);
+        assert_eq!(events[0].duration_ms, Some(400));
+        assert_eq!(events[1].description.as_deref(), Some("npm test"));
+        assert_eq!(events[3].duration_ms, Some(1000));
+        assert!(events.iter().all(|e| e.source.as_deref() == Some("cline")));
+    }
+}
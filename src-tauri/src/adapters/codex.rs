@@ -0,0 +1,230 @@
+//! OpenAI Codex CLI adapter
+//!
+//! Codex records each session as a JSONL "rollout" under
+//! `~/.codex/sessions/YYYY/MM/DD/rollout-*.jsonl` (or `$CODEX_HOME/sessions`).
+//! Tool calls and their outputs become tasks, and approval requests become
+//! `awaiting_input` events for the call waiting on the user.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+
+use super::{expand_home, now_ms, LineParser};
+use crate::watcher::TaskEvent;
+
+const SOURCE: &str = "codex";
+
+/// Sessions directory to watch; an empty setting means the Codex default
+pub fn sessions_dir(configured: &str) -> PathBuf {
+    if !configured.is_empty() {
+        return expand_home(configured);
+    }
+    match std::env::var("CODEX_HOME") {
+        Ok(home) if !home.is_empty() => PathBuf::from(home).join("sessions"),
+        _ => expand_home("~/.codex").join("sessions"),
+    }
+}
+
+/// Per-rollout session tracking
+#[derive(Default)]
+struct RolloutState {
+    session_id: Option<String>,
+    cwd: Option<String>,
+}
+
+#[derive(Default)]
+pub struct CodexParser {
+    files: HashMap<PathBuf, RolloutState>,
+}
+
+impl CodexParser {
+    /// Translate one rollout record, given the session it belongs to
+    fn translate(&mut self, path: &Path, record: &Value, timestamp: u64) -> Option<TaskEvent> {
+        // Current rollouts wrap items as {"type", "payload"}; older ones store items bare
+        let (kind, item) = match record.get("payload") {
+            Some(payload) => (record["type"].as_str().unwrap_or(""), payload),
+            // The header of an older rollout carries the session id
+            None if record.get("instructions").is_some() => ("session_meta", record),
+            None => ("response_item", record),
+        };
+
+        let state = self.files.entry(path.to_path_buf()).or_default();
+        if kind == "session_meta" {
+            state.session_id = item["id"].as_str().map(str::to_string);
+            state.cwd = item["cwd"].as_str().map(str::to_string);
+            return None;
+        }
+
+        let call_id = item["call_id"].as_str()?;
+        let (event_type, tool, description) = match (kind, item["type"].as_str()?) {
+            ("response_item", "function_call" | "custom_tool_call" | "local_shell_call") => {
+                let (tool, description) = describe_call(item);
+                ("task_started", Some(tool), description)
+            }
+            ("response_item", "function_call_output" | "custom_tool_call_output") => {
+                let event_type = if call_failed(&item["output"]) { "task_error" } else { "task_complete" };
+                (event_type, None, None)
+            }
+            ("event_msg", "exec_approval_request") => {
+                let command = command_line(&item["command"]);
+                ("awaiting_input", Some("Bash".to_string()), Some(format!("Approve: {}", command)))
+            }
+            ("event_msg", "apply_patch_approval_request") => {
+                let files: Vec<&str> = item["changes"]
+                    .as_object()
+                    .map(|c| c.keys().map(String::as_str).collect())
+                    .unwrap_or_default();
+                ("awaiting_input", Some("Edit".to_string()), Some(format!("Approve edit: {}", files.join(", "))))
+            }
+            _ => return None,
+        };
+
+        let session_id = state.session_id.clone().or_else(|| {
+            path.file_stem().map(|s| s.to_string_lossy().trim_start_matches("rollout-").to_string())
+        });
+
+        let mut event = TaskEvent::new(event_type, call_id, timestamp);
+        event.session_id = session_id;
+        event.tool = tool;
+        event.description = description;
+        event.source = Some(SOURCE.to_string());
+        if event_type == "task_started" {
+            event.project_path = state.cwd.clone();
+        }
+        Some(event)
+    }
+}
+
+/// Overlay tool name and description for a tool call item
+fn describe_call(item: &Value) -> (String, Option<String>) {
+    let name = item["name"].as_str().unwrap_or("shell");
+
+    if item["type"] == "local_shell_call" {
+        return ("Bash".to_string(), Some(command_line(&item["action"]["command"])));
+    }
+
+    match name {
+        "shell" | "container.exec" | "exec_command" => {
+            // Arguments are a JSON document encoded as a string
+            let args: Value = item["arguments"]
+                .as_str()
+                .and_then(|a| serde_json::from_str(a).ok())
+                .unwrap_or_default();
+            let command = if args["command"].is_null() { &args["cmd"] } else { &args["command"] };
+            ("Bash".to_string(), Some(command_line(command)))
+        }
+        "apply_patch" => {
+            let patch = item["input"].as_str().or_else(|| item["arguments"].as_str()).unwrap_or("");
+            ("Edit".to_string(), patched_files(patch))
+        }
+        "update_plan" => ("TodoWrite".to_string(), None),
+        other => (other.to_string(), None),
+    }
+}
+
+/// Shell command from an argv array or a plain string, minus the `bash -lc` wrapper
+fn command_line(command: &Value) -> String {
+    match command {
+        Value::Array(parts) => {
+            let parts: Vec<&str> = parts.iter().filter_map(Value::as_str).collect();
+            match parts.as_slice() {
+                [shell, flag, script] if shell.ends_with("sh") && flag.starts_with('-') => script.to_string(),
+                _ => parts.join(" "),
+            }
+        }
+        Value::String(s) => s.clone(),
+        _ => String::new(),
+    }
+}
+
+/// Files touched by an apply_patch envelope
+fn patched_files(patch: &str) -> Option<String> {
+    let files: Vec<&str> = patch
+        .lines()
+        .filter_map(|l| {
+            l.strip_prefix("*** Update File: ")
+                .or_else(|| l.strip_prefix("*** Add File: "))
+                .or_else(|| l.strip_prefix("*** Delete File: "))
+        })
+        .map(str::trim)
+        .collect();
+    (!files.is_empty()).then(|| files.join(", "))
+}
+
+/// Whether a tool output reports a non-zero exit code
+fn call_failed(output: &Value) -> bool {
+    let text = match output {
+        Value::String(s) => s.as_str(),
+        Value::Object(_) => return output["success"] == Value::Bool(false),
+        _ => return false,
+    };
+
+    // Shell output is usually itself JSON with metadata; otherwise "Exit code: N" text
+    if let Ok(parsed) = serde_json::from_str::<Value>(text) {
+        if let Some(code) = parsed["metadata"]["exit_code"].as_i64() {
+            return code != 0;
+        }
+    }
+    text.lines()
+        .find_map(|l| l.trim().strip_prefix("Exit code: "))
+        .and_then(|c| c.trim().parse::<i64>().ok())
+        .is_some_and(|code| code != 0)
+}
+
+impl LineParser for CodexParser {
+    fn accepts(&self, path: &Path) -> bool {
+        let name = path.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+        name.starts_with("rollout-") && name.ends_with(".jsonl")
+    }
+
+    fn parse_line(&mut self, path: &Path, line: &str) -> Vec<TaskEvent> {
+        match serde_json::from_str::<Value>(line) {
+            Ok(record) => self.translate(path, &record, now_ms()).into_iter().collect(),
+            Err(e) => {
+                tracing::debug!("Skipping unparseable Codex record: {}", e);
+                Vec::new()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rollout_lifecycle() {
+        let path = PathBuf::from("/home/dev/.codex/sessions/2025/09/01/rollout-2025-09-01T10-00-00-abc.jsonl");
+        let mut parser = CodexParser::default();
+        assert!(parser.accepts(&path));
+
+        let lines = [
+            r#"{"type":"session_meta","payload":{"id":"sess-1","cwd":"/work/app"}}"#,
+            r#"{"type":"response_item","payload":{"type":"function_call","name":"shell","arguments":"{\"command\":[\"bash\",\"-lc\",\"cargo test\"]}","call_id":"call_1"}}"#,
+            r#"{"type":"event_msg","payload":{"type":"exec_approval_request","call_id":"call_1","command":["bash","-lc","cargo test"]}}"#,
+            r#"{"type":"response_item","payload":{"type":"function_call_output","call_id":"call_1","output":"{\"output\":\"failed\",\"metadata\":{\"exit_code\":101}}"}}"#,
+        ];
+        let events: Vec<TaskEvent> = lines.iter().flat_map(|l| parser.parse_line(&path, l)).collect();
+
+        let types: Vec<&str> = events.iter().map(|e| e.event_type.as_str()).collect();
+        assert_eq!(types, ["task_started", "awaiting_input", "task_error"]);
+        assert_eq!(events[0].tool.as_deref(), Some("Bash"));
+        assert_eq!(events[0].description.as_deref(), Some("cargo test"));
+        assert_eq!(events[0].project_path.as_deref(), Some("/work/app"));
+        assert!(events.iter().all(|e| e.task_id == "call_1" && e.session_id.as_deref() == Some("sess-1")));
+        assert!(events.iter().all(|e| e.source.as_deref() == Some("codex")));
+    }
+
+    #[test]
+    fn test_patch_calls_and_exit_codes() {
+        let item: Value = serde_json::from_str(
+            r#"{"type":"custom_tool_call","name":"apply_patch","call_id":"c2","input":"*** Begin Patch\n*** Update File: src/main.rs\n@@\n*** Add File: src/new.rs\n*** End Patch"}"#,
+        ).unwrap();
+        assert_eq!(describe_call(&item), ("Edit".to_string(), Some("src/main.rs, src/new.rs".to_string())));
+
+        assert!(call_failed(&Value::String("Exit code: 1\nWall time: 0.2 seconds".to_string())));
+        assert!(!call_failed(&Value::String("Exit code: 0".to_string())));
+        assert!(!call_failed(&Value::String("Done!".to_string())));
+    }
+}
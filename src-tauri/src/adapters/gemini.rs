@@ -0,0 +1,188 @@
+//! Gemini CLI telemetry adapter
+//!
+//! With `telemetry.target = "local"` and an `outfile`, Gemini CLI writes its
+//! OpenTelemetry log records to a file as pretty-printed JSON objects. Each
+//! `gemini_cli.tool_call` record is logged once the call finishes and becomes a
+//! completed (or failed) task.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+
+use super::{expand_home, finished_task, now_ms, LineParser};
+use crate::watcher::TaskEvent;
+
+pub const DEFAULT_TELEMETRY_FILE: &str = "~/.gemini/telemetry.log";
+const SOURCE: &str = "gemini";
+const TOOL_CALL_EVENT: &str = "gemini_cli.tool_call";
+
+/// Telemetry file to watch; an empty setting means the default location
+pub fn telemetry_file(configured: &str) -> PathBuf {
+    expand_home(if configured.is_empty() { DEFAULT_TELEMETRY_FILE } else { configured })
+}
+
+/// Reassembles JSON objects that span several lines
+#[derive(Default)]
+struct ObjectBuffer {
+    text: String,
+    depth: usize,
+    in_string: bool,
+    escaped: bool,
+}
+
+impl ObjectBuffer {
+    /// Add a line, returning the object it completes if any
+    fn push(&mut self, line: &str) -> Option<String> {
+        if self.depth == 0 && !line.starts_with('{') {
+            // Between objects; skip stray output
+            return None;
+        }
+
+        for c in line.chars() {
+            if self.in_string {
+                match c {
+                    _ if self.escaped => self.escaped = false,
+                    '\\' => self.escaped = true,
+                    '"' => self.in_string = false,
+                    _ => {}
+                }
+            } else {
+                match c {
+                    '"' => self.in_string = true,
+                    '{' => self.depth += 1,
+                    '}' => self.depth = self.depth.saturating_sub(1),
+                    _ => {}
+                }
+            }
+        }
+        self.text.push_str(line);
+        self.text.push('\n');
+
+        if self.depth == 0 {
+            Some(std::mem::take(&mut self.text))
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct GeminiParser {
+    buffers: HashMap<PathBuf, ObjectBuffer>,
+}
+
+/// Translate a tool call log record into a start/end pair
+fn tool_call_events(record: &Value) -> Vec<TaskEvent> {
+    let attributes = &record["attributes"];
+    if attributes["event.name"] != TOOL_CALL_EVENT {
+        return Vec::new();
+    }
+
+    let name = attributes["function_name"].as_str().unwrap_or("unknown");
+    // Arguments are logged as a JSON document encoded as a string
+    let args: Value = attributes["function_args"]
+        .as_str()
+        .and_then(|a| serde_json::from_str(a).ok())
+        .unwrap_or_default();
+    let (tool, description) = describe_call(name, &args);
+
+    let duration_ms = attributes["duration_ms"].as_u64().unwrap_or(0);
+    let ended_at = record["hrTime"]
+        .as_array()
+        .and_then(|t| Some(t.first()?.as_u64()? * 1000 + t.get(1)?.as_u64()? / 1_000_000))
+        .unwrap_or_else(now_ms);
+    let started_at = ended_at.saturating_sub(duration_ms);
+
+    let session_id = attributes["session.id"].as_str().unwrap_or("gemini");
+    let call_id = attributes["prompt_id"]
+        .as_str()
+        .map(|p| format!("{}-{}-{}", p, name, started_at))
+        .unwrap_or_else(|| format!("{}-{}-{}", session_id, name, started_at));
+
+    let mut started = TaskEvent::new("task_started", &call_id, started_at);
+    started.session_id = Some(session_id.to_string());
+    started.tool = Some(tool.to_string());
+    started.description = description;
+    started.source = Some(SOURCE.to_string());
+
+    let end_type = if attributes["success"] == Value::Bool(false) { "task_error" } else { "task_complete" };
+    finished_task(started, end_type, duration_ms)
+}
+
+/// Overlay tool name and description for a Gemini CLI tool
+fn describe_call(name: &str, args: &Value) -> (&'static str, Option<String>) {
+    let arg = |key: &str| args[key].as_str().map(str::to_string);
+    match name {
+        "run_shell_command" => ("Bash", arg("command")),
+        "replace" | "edit" => ("Edit", arg("file_path")),
+        "write_file" => ("Write", arg("file_path")),
+        "read_file" | "read_many_files" => ("Read", arg("absolute_path").or_else(|| arg("file_path"))),
+        "glob" => ("Glob", arg("pattern")),
+        "search_file_content" => ("Grep", arg("pattern")),
+        "list_directory" => ("LS", arg("path")),
+        "web_fetch" => ("WebFetch", arg("prompt")),
+        "google_web_search" => ("WebSearch", arg("query")),
+        "save_memory" => ("Memory", arg("fact")),
+        _ => ("Unknown", Some(name.to_string())),
+    }
+}
+
+impl LineParser for GeminiParser {
+    fn accepts(&self, _path: &Path) -> bool {
+        // The telemetry file is the only target
+        true
+    }
+
+    fn parse_line(&mut self, path: &Path, line: &str) -> Vec<TaskEvent> {
+        let buffer = self.buffers.entry(path.to_path_buf()).or_default();
+        let Some(object) = buffer.push(line) else {
+            return Vec::new();
+        };
+
+        match serde_json::from_str::<Value>(&object) {
+            Ok(record) => tool_call_events(&record),
+            Err(e) => {
+                tracing::debug!("Skipping unparseable Gemini telemetry record: {}", e);
+                Vec::new()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_multiline_tool_call_record() {
+        let record = r#"{
+  "hrTime": [1735689600, 500000000],
+  "body": "Tool call: run_shell_command. Decision: accept. Success: false.",
+  "attributes": {
+    "session.id": "g-1",
+    "event.name": "gemini_cli.tool_call",
+    "function_name": "run_shell_command",
+    "function_args": "{\"command\":\"echo \\\"}\\\"\"}",
+    "duration_ms": 1500,
+    "success": false,
+    "prompt_id": "p-1"
+  }
+}"#;
+        let path = PathBuf::from("/tmp/telemetry.log");
+        let mut parser = GeminiParser::default();
+
+        assert!(parser.parse_line(&path, "stray output").is_empty());
+        let events: Vec<TaskEvent> = record.lines().flat_map(|l| parser.parse_line(&path, l.trim())).collect();
+
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].event_type, "task_started");
+        assert_eq!(events[0].tool.as_deref(), Some("Bash"));
+        assert_eq!(events[0].description.as_deref(), Some("echo \"}\""));
+        assert_eq!(events[0].session_id.as_deref(), Some("g-1"));
+        assert_eq!(events[0].source.as_deref(), Some("gemini"));
+        assert_eq!(events[1].event_type, "task_error");
+        assert_eq!(events[1].timestamp, 1_735_689_600_500);
+        assert_eq!(events[0].timestamp, 1_735_689_599_000);
+    }
+}
@@ -0,0 +1,164 @@
+//! Progress read from tools' own log files
+//!
+//! Each `[[sources.log_progress]]` entry names a log file and a regex. Lines
+//! appended to the file that match become a progress bar through the regex's
+//! named groups: `percent`, or `done` and `total`, and `message`. That covers
+//! tools that only write logs, like Terraform or `docker build`.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use regex::Regex;
+
+use super::{existing_files, expand_home, TargetWatch};
+use crate::config::LogProgressConfig;
+use crate::watcher::{self, EventSender, FileState, GenericProgress, Source, WatcherError, WatcherEvent};
+
+/// One configured log file and the pattern its progress lines match
+pub struct LogRule {
+    file: PathBuf,
+    regex: Regex,
+    id: String,
+    label: String,
+}
+
+impl LogRule {
+    /// `None` when the pattern isn't a valid regex, which is logged
+    pub fn new(config: &LogProgressConfig) -> Option<Self> {
+        let regex = match Regex::new(&config.pattern) {
+            Ok(regex) => regex,
+            Err(e) => {
+                tracing::error!("Invalid log progress pattern for {}: {}", config.file, e);
+                return None;
+            }
+        };
+        let file = expand_home(&config.file);
+        let label = if config.label.is_empty() {
+            file.file_name().unwrap_or_default().to_string_lossy().into_owned()
+        } else {
+            config.label.clone()
+        };
+        Some(Self { id: format!("log:{}:{}", config.file, label), file, regex, label })
+    }
+
+    /// Progress from a line that matches the pattern
+    pub fn parse_line(&self, line: &str) -> Option<GenericProgress> {
+        let captures = self.regex.captures(line)?;
+        let number = |name: &str| captures.name(name).and_then(|m| m.as_str().trim().parse::<f64>().ok());
+
+        let percent = number("percent").or_else(|| {
+            let (done, total) = (number("done")?, number("total")?);
+            (total > 0.0).then(|| done / total * 100.0)
+        });
+        let message = captures
+            .name("message")
+            .map(|m| m.as_str().trim().to_string())
+            .filter(|m| !m.is_empty());
+
+        Some(GenericProgress {
+            id: self.id.clone(),
+            label: Some(self.label.clone()),
+            percent: percent.map(|p| p.clamp(0.0, 100.0)),
+            message,
+        })
+    }
+}
+
+/// Source that follows the log files in `[[sources.log_progress]]`
+///
+/// Files that already exist are read from their current end; files that
+/// appear later are read from the start.
+pub struct LogProgressSource {
+    rules: Vec<LogRule>,
+    debounce_ms: u64,
+}
+
+impl LogProgressSource {
+    pub fn new(configs: &[LogProgressConfig], debounce_ms: u64) -> Self {
+        Self { rules: configs.iter().filter_map(LogRule::new).collect(), debounce_ms }
+    }
+}
+
+impl Source for LogProgressSource {
+    fn name(&self) -> &str {
+        "log_progress"
+    }
+
+    fn start(self: Box<Self>, tx: EventSender) -> Result<(), WatcherError> {
+        let Self { rules, debounce_ms } = *self;
+
+        let mut targets: Vec<PathBuf> = rules.iter().map(|rule| rule.file.clone()).collect();
+        targets.sort();
+        targets.dedup();
+        let mut states: HashMap<PathBuf, FileState> = HashMap::new();
+        for target in &targets {
+            for file in existing_files(target, |_| true) {
+                let state = FileState::at_end(&file);
+                states.insert(file, state);
+            }
+        }
+
+        let mut watch = TargetWatch::new("log_progress", targets, debounce_ms)?;
+        tokio::spawn(async move {
+            while let Some(path) = watch.next_change().await {
+                let matching: Vec<&LogRule> = rules.iter().filter(|rule| rule.file == path).collect();
+                if matching.is_empty() {
+                    continue;
+                }
+                let state = states.entry(path.clone()).or_insert_with(FileState::at_start);
+                for line in watcher::read_new_lines(&path, state).await {
+                    for progress in matching.iter().filter_map(|rule| rule.parse_line(&line)) {
+                        let _ = tx.send(WatcherEvent::GenericProgress(progress));
+                    }
+                }
+            }
+        });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(pattern: &str, label: &str) -> Option<LogRule> {
+        LogRule::new(&LogProgressConfig {
+            file: "/var/log/build.log".to_string(),
+            pattern: pattern.to_string(),
+            label: label.to_string(),
+        })
+    }
+
+    #[test]
+    fn test_step_counts() {
+        let docker = rule(r"^Step (?P<done>\d+)/(?P<total>\d+) : (?P<message>.*)", "").unwrap();
+        let progress = docker.parse_line("Step 3/12 : RUN cargo build --release").unwrap();
+        assert_eq!(progress.percent, Some(25.0));
+        assert_eq!(progress.message.as_deref(), Some("RUN cargo build --release"));
+        assert_eq!(progress.label.as_deref(), Some("build.log"));
+        assert!(docker.parse_line(" ---> Running in 1f2e3d").is_none());
+    }
+
+    #[test]
+    fn test_percent_and_message_only() {
+        let download = rule(r"(?P<percent>[\d.]+)% complete", "Sync").unwrap();
+        assert_eq!(download.parse_line("sync: 42.5% complete").unwrap().percent, Some(42.5));
+
+        let terraform = rule(r"^(?P<message>\S+: Still creating\.\.\. \[.*\])", "Terraform").unwrap();
+        let progress = terraform.parse_line("aws_instance.web: Still creating... [40s elapsed]").unwrap();
+        assert_eq!(progress.percent, None);
+        assert_eq!(progress.message.as_deref(), Some("aws_instance.web: Still creating... [40s elapsed]"));
+        assert_eq!(progress.label.as_deref(), Some("Terraform"));
+        assert_ne!(progress.id, download.parse_line("1% complete").unwrap().id);
+    }
+
+    #[test]
+    fn test_invalid_pattern_is_skipped() {
+        assert!(rule(r"(?P<percent>\d+", "").is_none());
+        let source = LogProgressSource::new(
+            &[LogProgressConfig { file: "/tmp/a.log".to_string(), pattern: "(".to_string(), label: String::new() }],
+            100,
+        );
+        assert!(source.rules.is_empty());
+    }
+}
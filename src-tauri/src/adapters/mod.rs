@@ -0,0 +1,308 @@
+//! Input adapters for agent tools other than Claude Code
+//!
+//! Each adapter tails the tool's own log or history files (or re-reads the JSON
+//! documents it rewrites) and translates what it finds into [`TaskEvent`]s
+//! tagged with a `source`. Each one is a [`Source`] started alongside Claude
+//! Code's by the file watcher. The `logs` adapter turns lines in other tools'
+//! logs into progress bars instead.
+
+pub mod aider;
+pub mod cline;
+pub mod codex;
+pub mod gemini;
+pub mod logs;
+pub mod opencode;
+
+use notify::{RecommendedWatcher, RecursiveMode};
+use notify_debouncer_mini::{new_debouncer, DebounceEventResult, Debouncer};
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::sync::mpsc::{self, UnboundedReceiver};
+
+use crate::config::SourcesConfig;
+use crate::watcher::{self, EventSender, FileState, Source, TaskEvent, WatcherError, WatcherEvent};
+
+/// Turns lines appended to an agent's files into task events
+pub(crate) trait LineParser: Send + 'static {
+    /// Whether a file under the watched targets belongs to this adapter
+    fn accepts(&self, path: &Path) -> bool;
+
+    /// Translate one new line from `path`
+    fn parse_line(&mut self, path: &Path, line: &str) -> Vec<TaskEvent>;
+}
+
+/// Turns the latest contents of a document an agent rewrites into task events
+pub(crate) trait DocumentParser: Send + 'static {
+    /// Whether a file under the watched targets belongs to this adapter
+    fn accepts(&self, path: &Path) -> bool;
+
+    /// Events for whatever changed in `path` since it was last parsed
+    fn parse_document(&mut self, path: &Path, contents: &str) -> Vec<TaskEvent>;
+}
+
+/// The sources enabled in `[sources]`
+pub fn enabled_sources(sources: &SourcesConfig, debounce_ms: u64) -> Vec<Box<dyn Source>> {
+    let mut enabled: Vec<Box<dyn Source>> = Vec::new();
+
+    if sources.aider.enabled {
+        enabled.push(Box::new(TailSource {
+            name: "aider",
+            targets: aider::targets(&sources.aider.history_files),
+            debounce_ms,
+            parser: aider::AiderParser::default(),
+        }));
+    }
+
+    if sources.codex.enabled {
+        enabled.push(Box::new(TailSource {
+            name: "codex",
+            targets: vec![codex::sessions_dir(&sources.codex.sessions_dir)],
+            debounce_ms,
+            parser: codex::CodexParser::default(),
+        }));
+    }
+
+    if sources.gemini.enabled {
+        enabled.push(Box::new(TailSource {
+            name: "gemini",
+            targets: vec![gemini::telemetry_file(&sources.gemini.telemetry_file)],
+            debounce_ms,
+            parser: gemini::GeminiParser::default(),
+        }));
+    }
+
+    if sources.cline.enabled {
+        enabled.push(Box::new(DocumentSource {
+            name: "cline",
+            targets: vec![cline::tasks_dir(&sources.cline.tasks_dir)],
+            debounce_ms,
+            parser: cline::ClineParser::default(),
+        }));
+    }
+
+    if sources.opencode.enabled {
+        enabled.push(Box::new(DocumentSource {
+            name: "opencode",
+            targets: vec![opencode::storage_dir(&sources.opencode.storage_dir).join("part")],
+            debounce_ms,
+            parser: opencode::OpenCodeParser::default(),
+        }));
+    }
+
+    if !sources.log_progress.is_empty() {
+        enabled.push(Box::new(logs::LogProgressSource::new(&sources.log_progress, debounce_ms)));
+    }
+
+    enabled
+}
+
+/// Source that feeds lines appended to its targets to a parser
+///
+/// Files that already exist are read from their current end; files that appear
+/// later are read from the start.
+pub(crate) struct TailSource<P> {
+    name: &'static str,
+    targets: Vec<PathBuf>,
+    debounce_ms: u64,
+    parser: P,
+}
+
+impl<P: LineParser> Source for TailSource<P> {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn start(self: Box<Self>, tx: EventSender) -> Result<(), WatcherError> {
+        let Self { name, targets, debounce_ms, mut parser } = *self;
+
+        let mut states: HashMap<PathBuf, FileState> = HashMap::new();
+        for target in &targets {
+            for file in existing_files(target, |p| parser.accepts(p)) {
+                let state = FileState::at_end(&file);
+                states.insert(file, state);
+            }
+        }
+
+        let mut watch = TargetWatch::new(name, targets, debounce_ms)?;
+        tokio::spawn(async move {
+            while let Some(path) = watch.next_change().await {
+                if !parser.accepts(&path) {
+                    continue;
+                }
+                let state = states.entry(path.clone()).or_insert_with(FileState::at_start);
+                for line in watcher::read_new_lines(&path, state).await {
+                    for task_event in parser.parse_line(&path, &line) {
+                        let _ = tx.send(WatcherEvent::TaskEvent(task_event));
+                    }
+                }
+            }
+        });
+        Ok(())
+    }
+}
+
+/// Source that hands whole documents to a parser whenever they change
+///
+/// For tools that rewrite their JSON files in place. Existing documents are
+/// primed at startup so only later changes produce events.
+pub(crate) struct DocumentSource<P> {
+    name: &'static str,
+    targets: Vec<PathBuf>,
+    debounce_ms: u64,
+    parser: P,
+}
+
+impl<P: DocumentParser> Source for DocumentSource<P> {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn start(self: Box<Self>, tx: EventSender) -> Result<(), WatcherError> {
+        let Self { name, targets, debounce_ms, mut parser } = *self;
+
+        let mut watch = TargetWatch::new(name, targets.clone(), debounce_ms)?;
+        tokio::spawn(async move {
+            // Storage directories can be large, so walk them on the blocking pool
+            let walk = tokio::task::spawn_blocking(move || {
+                targets.iter().flat_map(|target| existing_files(target, |_| true)).collect::<Vec<_>>()
+            });
+            for file in walk.await.unwrap_or_default() {
+                if !parser.accepts(&file) {
+                    continue;
+                }
+                if let Ok(contents) = tokio::fs::read_to_string(&file).await {
+                    parser.parse_document(&file, &contents);
+                }
+            }
+
+            while let Some(path) = watch.next_change().await {
+                if !parser.accepts(&path) {
+                    continue;
+                }
+                // Removed files and half-written documents are picked up on the next change
+                let Ok(contents) = tokio::fs::read_to_string(&path).await else {
+                    continue;
+                };
+                for task_event in parser.parse_document(&path, &contents) {
+                    let _ = tx.send(WatcherEvent::TaskEvent(task_event));
+                }
+            }
+        });
+        Ok(())
+    }
+}
+
+/// Change notifications for a set of files and directories
+pub(crate) struct TargetWatch {
+    name: &'static str,
+    targets: Vec<PathBuf>,
+    // Dropping the debouncer stops the notifications
+    _debouncer: Debouncer<RecommendedWatcher>,
+    rx: UnboundedReceiver<DebounceEventResult>,
+    /// Changed paths from the last batch not yet handed out
+    pending: VecDeque<PathBuf>,
+}
+
+impl TargetWatch {
+    pub(crate) fn new(name: &'static str, targets: Vec<PathBuf>, debounce_ms: u64) -> Result<Self, WatcherError> {
+        let (notify_tx, rx) = mpsc::unbounded_channel();
+        let mut debouncer = new_debouncer(Duration::from_millis(debounce_ms), move |result| {
+            let _ = notify_tx.send(result);
+        })
+        .map_err(|e| WatcherError::WatchError(e.to_string()))?;
+
+        for target in &targets {
+            let (watch_path, mode) = if target.is_dir() {
+                (target.as_path(), RecursiveMode::Recursive)
+            } else {
+                (target.parent().unwrap_or(Path::new(".")), RecursiveMode::NonRecursive)
+            };
+            // The tool may not have created its files yet; keep the other targets
+            if let Err(e) = debouncer.watcher().watch(watch_path, mode) {
+                tracing::warn!("{} source can't watch {:?}: {}", name, watch_path, e);
+            }
+        }
+
+        Ok(Self { name, targets, _debouncer: debouncer, rx, pending: VecDeque::new() })
+    }
+
+    /// Wait for the next changed path in scope; `None` once notifications stop
+    pub(crate) async fn next_change(&mut self) -> Option<PathBuf> {
+        loop {
+            if let Some(path) = self.pending.pop_front() {
+                return Some(path);
+            }
+
+            let events = match self.rx.recv().await? {
+                Ok(events) => events,
+                Err(e) => {
+                    tracing::warn!("{} source watch error: {:?}", self.name, e);
+                    continue;
+                }
+            };
+
+            let targets = &self.targets;
+            self.pending.extend(
+                events
+                    .into_iter()
+                    .map(|event| event.path)
+                    .filter(|path| targets.iter().any(|t| path == t || (t.is_dir() && path.starts_with(t)))),
+            );
+        }
+    }
+}
+
+/// Accepted files under a target that exist right now
+pub(crate) fn existing_files(target: &Path, accepts: impl Fn(&Path) -> bool) -> Vec<PathBuf> {
+    if target.is_file() {
+        return if accepts(target) { vec![target.to_path_buf()] } else { Vec::new() };
+    }
+
+    let mut files = Vec::new();
+    let mut pending = vec![target.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        for entry in fs::read_dir(&dir).into_iter().flatten().flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                pending.push(path);
+            } else if accepts(&path) {
+                files.push(path);
+            }
+        }
+    }
+    files
+}
+
+/// Expand a leading `~` to the home directory
+pub(crate) fn expand_home(path: &str) -> PathBuf {
+    match path.strip_prefix("~") {
+        Some(rest) => dirs::home_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join(rest.trim_start_matches(['/', '\\'])),
+        None => PathBuf::from(path),
+    }
+}
+
+/// Current time in milliseconds since the Unix epoch
+pub(crate) fn now_ms() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// A start/end pair for an action that had already finished when it was logged
+///
+/// `started.timestamp` is when the action began; the end event is stamped
+/// `duration_ms` later.
+pub(crate) fn finished_task(mut started: TaskEvent, end_type: &str, duration_ms: u64) -> Vec<TaskEvent> {
+    let mut end = TaskEvent::new(end_type, &started.task_id, started.timestamp + duration_ms);
+    end.session_id = started.session_id.clone();
+    end.source = started.source.clone();
+    end.duration_ms = Some(duration_ms);
+
+    started.event_type = "task_started".to_string();
+    vec![started, end]
+}
@@ -0,0 +1,165 @@
+//! OpenCode adapter
+//!
+//! OpenCode stores each message part as its own JSON file under
+//! `~/.local/share/opencode/storage/part/<session>/<message>/<part>.json` and
+//! rewrites a tool part as its state moves through pending, running, and
+//! completed or error.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+
+use super::{expand_home, now_ms, DocumentParser};
+use crate::watcher::TaskEvent;
+
+pub const DEFAULT_STORAGE_DIR: &str = "~/.local/share/opencode/storage";
+const SOURCE: &str = "opencode";
+
+/// Storage directory to watch; an empty setting means the default location
+pub fn storage_dir(configured: &str) -> PathBuf {
+    expand_home(if configured.is_empty() { DEFAULT_STORAGE_DIR } else { configured })
+}
+
+#[derive(Default)]
+pub struct OpenCodeParser {
+    /// Last state seen per tool call id
+    statuses: HashMap<String, String>,
+    /// Project directory per session id, looked up on first use
+    directories: HashMap<String, Option<String>>,
+}
+
+impl OpenCodeParser {
+    /// Project directory from `storage/session/<project>/<session>.json`
+    fn session_directory(&mut self, part_path: &Path, session_id: &str) -> Option<String> {
+        if let Some(directory) = self.directories.get(session_id) {
+            return directory.clone();
+        }
+
+        let storage = part_path.ancestors().find(|p| p.file_name().is_some_and(|n| n == "part"))?.parent()?;
+        let file_name = format!("{}.json", session_id);
+        let directory = fs::read_dir(storage.join("session"))
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|project| project.path().join(&file_name))
+            .find(|p| p.is_file())
+            .and_then(|p| fs::read_to_string(p).ok())
+            .and_then(|s| serde_json::from_str::<Value>(&s).ok())
+            .and_then(|session| session["directory"].as_str().map(str::to_string));
+
+        self.directories.insert(session_id.to_string(), directory.clone());
+        directory
+    }
+}
+
+/// Overlay tool name for an OpenCode tool id
+fn tool_name(tool: &str) -> String {
+    match tool {
+        "bash" => "Bash".to_string(),
+        "edit" | "patch" | "multiedit" => "Edit".to_string(),
+        "write" => "Write".to_string(),
+        "read" => "Read".to_string(),
+        "glob" => "Glob".to_string(),
+        "grep" => "Grep".to_string(),
+        "list" => "LS".to_string(),
+        "webfetch" => "WebFetch".to_string(),
+        "todowrite" | "todoread" => "TodoWrite".to_string(),
+        "task" => "Task".to_string(),
+        other => other.to_string(),
+    }
+}
+
+/// Short description of a tool call from its title or input
+fn describe(state: &Value) -> Option<String> {
+    if let Some(title) = state["title"].as_str().filter(|t| !t.is_empty()) {
+        return Some(title.to_string());
+    }
+    let input = &state["input"];
+    ["description", "command", "filePath", "pattern", "url"]
+        .iter()
+        .find_map(|key| input[*key].as_str())
+        .map(str::to_string)
+}
+
+impl DocumentParser for OpenCodeParser {
+    fn accepts(&self, path: &Path) -> bool {
+        path.extension().is_some_and(|e| e == "json") && path.components().any(|c| c.as_os_str() == "part")
+    }
+
+    fn parse_document(&mut self, path: &Path, contents: &str) -> Vec<TaskEvent> {
+        let Ok(part) = serde_json::from_str::<Value>(contents) else {
+            return Vec::new();
+        };
+        if part["type"] != "tool" {
+            return Vec::new();
+        }
+        let (Some(call_id), Some(status)) = (part["callID"].as_str(), part["state"]["status"].as_str()) else {
+            return Vec::new();
+        };
+
+        let previous = self.statuses.insert(call_id.to_string(), status.to_string());
+        if previous.as_deref() == Some(status) {
+            return Vec::new();
+        }
+
+        let state = &part["state"];
+        let session_id = part["sessionID"].as_str().unwrap_or("opencode").to_string();
+        let started_at = state["time"]["start"].as_u64().unwrap_or_else(now_ms);
+        let mut events = Vec::new();
+
+        // Parts can skip straight to a final state between two rewrites
+        if previous.is_none() {
+            let mut started = TaskEvent::new("task_started", call_id, started_at);
+            started.session_id = Some(session_id.clone());
+            started.tool = Some(tool_name(part["tool"].as_str().unwrap_or("unknown")));
+            started.description = describe(state);
+            started.project_path = self.session_directory(path, &session_id);
+            started.source = Some(SOURCE.to_string());
+            events.push(started);
+        }
+
+        let end_type = match status {
+            "completed" => "task_complete",
+            "error" => "task_error",
+            _ => return events,
+        };
+        let ended_at = state["time"]["end"].as_u64().unwrap_or_else(now_ms);
+        let mut end = TaskEvent::new(end_type, call_id, ended_at);
+        end.session_id = Some(session_id);
+        end.duration_ms = Some(ended_at.saturating_sub(started_at));
+        end.source = Some(SOURCE.to_string());
+        events.push(end);
+
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tool_part_states() {
+        let path = PathBuf::from("/storage/part/ses_1/msg_1/prt_1.json");
+        let mut parser = OpenCodeParser::default();
+        assert!(parser.accepts(&path));
+
+        let running = r#"{"type":"tool","callID":"call_1","sessionID":"ses_1","tool":"bash",
+            "state":{"status":"running","input":{"command":"ls","description":"List files"},"time":{"start":1000}}}"#;
+        let events = parser.parse_document(&path, running);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].tool.as_deref(), Some("Bash"));
+        assert_eq!(events[0].description.as_deref(), Some("List files"));
+        assert!(parser.parse_document(&path, running).is_empty());
+
+        let completed = r#"{"type":"tool","callID":"call_1","sessionID":"ses_1","tool":"bash",
+            "state":{"status":"completed","title":"ls","time":{"start":1000,"end":1800}}}"#;
+        let events = parser.parse_document(&path, completed);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].event_type, "task_complete");
+        assert_eq!(events[0].duration_ms, Some(800));
+        assert_eq!(events[0].source.as_deref(), Some("opencode"));
+    }
+}
@@ -0,0 +1,185 @@
+//! Old sessions moved out of the history database into dated archives
+//!
+//! Every task, todo and sample ever recorded stays in the history database
+//! unless `database.retention_days` deletes it, and a large database makes
+//! every query slower. `archive_sessions` instead moves sessions that started
+//! before a date, with everything recorded for them, into
+//! `archives/history-archive-<date>.db` next to the database, and shrinks the
+//! database afterwards. An archive is a history database in its own right, so
+//! it can be opened and searched when an old session is needed again.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::NaiveDate;
+use serde::Serialize;
+
+use crate::config::Config;
+use crate::store::{ArchiveReport, EventStore, StoredTask, TaskFilter};
+use crate::timezone::Zone;
+
+const ARCHIVES_DIR_NAME: &str = "archives";
+const FILE_PREFIX: &str = "history-archive-";
+const FILE_SUFFIX: &str = ".db";
+
+/// An archive file on disk
+#[derive(Debug, Clone, Serialize)]
+pub struct ArchiveInfo {
+    /// The file name, which `search_archive` takes
+    pub name: String,
+    pub path: PathBuf,
+    /// Sessions before this date are in it, as YYYY-MM-DD
+    pub before_date: String,
+    pub size_bytes: u64,
+}
+
+/// What archiving moved, and where to
+#[derive(Debug, Clone, Serialize)]
+pub struct ArchiveResult {
+    pub path: PathBuf,
+    #[serde(flatten)]
+    pub moved: ArchiveReport,
+}
+
+/// The folder archives are kept in, next to the history database
+pub fn archives_dir(config: &Config) -> PathBuf {
+    config
+        .database_path()
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_else(std::env::temp_dir)
+        .join(ARCHIVES_DIR_NAME)
+}
+
+fn parse_date(date: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d").map_err(|_| format!("\"{}\" isn't a date like 2025-01-31", date))
+}
+
+/// Move sessions that started before local midnight on `before_date` (YYYY-MM-DD) into its archive
+///
+/// Archiving up to the same date again adds to the same file.
+pub fn archive_sessions(store: &EventStore, config: &Config, before_date: &str) -> Result<ArchiveResult, String> {
+    let date = parse_date(before_date)?;
+    let before_ms = Zone::from_config(&config.behavior.timezone).start_of_day(date);
+
+    let dir = archives_dir(config);
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    let path = dir.join(format!("{}{}{}", FILE_PREFIX, date.format("%Y-%m-%d"), FILE_SUFFIX));
+    // Opening the archive as a store creates or migrates its schema
+    drop(EventStore::new(&path).map_err(|e| e.to_string())?);
+
+    let moved = store.archive_sessions(before_ms, &path).map_err(|e| e.to_string())?;
+    tracing::info!(
+        "Archived {} sessions with {} tasks and {} todos to {:?}",
+        moved.sessions, moved.tasks, moved.todos, path
+    );
+    Ok(ArchiveResult { path, moved })
+}
+
+/// Archives on disk, oldest first
+pub fn list_archives(config: &Config) -> Result<Vec<ArchiveInfo>, String> {
+    let dir = archives_dir(config);
+    let entries = match fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("Failed to read {}: {}", dir.display(), e)),
+    };
+
+    let mut archives: Vec<ArchiveInfo> = entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let date = name.strip_prefix(FILE_PREFIX)?.strip_suffix(FILE_SUFFIX)?;
+            parse_date(date).ok()?;
+            Some(ArchiveInfo {
+                before_date: date.to_string(),
+                path: entry.path(),
+                size_bytes: entry.metadata().map(|m| m.len()).unwrap_or(0),
+                name,
+            })
+        })
+        .collect();
+    archives.sort_by(|a, b| a.before_date.cmp(&b.before_date));
+    Ok(archives)
+}
+
+/// The path of the archive called `name`, which must be one `list_archives` returns
+fn archive_path(config: &Config, name: &str) -> Result<PathBuf, String> {
+    list_archives(config)?
+        .into_iter()
+        .find(|archive| archive.name == name)
+        .map(|archive| archive.path)
+        .ok_or_else(|| format!("No archive named \"{}\"", name))
+}
+
+/// Tasks in the archive called `name`, most recent first; all of them, or those matching `query`
+pub fn search_archive(
+    config: &Config,
+    name: &str,
+    query: &str,
+    filter: &TaskFilter,
+    limit: usize,
+) -> Result<Vec<StoredTask>, String> {
+    let archive = EventStore::new(&archive_path(config, name)?).map_err(|e| e.to_string())?;
+    let tasks = if query.trim().is_empty() {
+        archive.get_recent_tasks(filter, limit)
+    } else {
+        archive.search_tasks(query, filter, limit)
+    };
+    tasks.map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(dir: &Path) -> Config {
+        let mut config = Config::default();
+        config.paths.database_file = dir.join("history.db").to_string_lossy().to_string();
+        config.behavior.timezone = "UTC".to_string();
+        config
+    }
+
+    fn task(id: &str, started_at: i64) -> StoredTask {
+        StoredTask {
+            status: "completed".to_string(),
+            ended_at: Some(started_at + 1_000),
+            duration_ms: Some(1_000),
+            ..StoredTask::test(id, started_at)
+        }
+    }
+
+    #[test]
+    fn test_archive_and_search() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = config(dir.path());
+        let store = EventStore::new(&config.database_path()).unwrap();
+        // 2025-01-01 and 2025-03-01, UTC
+        store.insert_task(&task("old", 1_735_689_600_000)).unwrap();
+        store.insert_task(&task("new", 1_740_787_200_000)).unwrap();
+
+        let result = archive_sessions(&store, &config, "2025-02-01").unwrap();
+        assert_eq!((result.moved.sessions, result.moved.tasks), (1, 1));
+        assert_eq!(store.get_tasks_since(0).unwrap().len(), 1);
+
+        let archives = list_archives(&config).unwrap();
+        assert_eq!(archives.len(), 1);
+        assert_eq!(archives[0].name, "history-archive-2025-02-01.db");
+        assert_eq!(archives[0].before_date, "2025-02-01");
+
+        let found = search_archive(&config, &archives[0].name, "old", &TaskFilter::default(), 10).unwrap();
+        assert_eq!(found[0].id, "old");
+        let all = search_archive(&config, &archives[0].name, "", &TaskFilter::default(), 10).unwrap();
+        assert_eq!(all.len(), 1);
+    }
+
+    #[test]
+    fn test_bad_dates_and_names() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = config(dir.path());
+        let store = EventStore::in_memory().unwrap();
+        assert!(archive_sessions(&store, &config, "last week").is_err());
+        assert!(list_archives(&config).unwrap().is_empty());
+        assert!(search_archive(&config, "../history.db", "", &TaskFilter::default(), 10).is_err());
+    }
+}
@@ -0,0 +1,141 @@
+//! Native backdrop effects behind the overlay on Windows
+//!
+//! `window.backdrop` puts Windows 11's mica or acrylic material, or the
+//! older blur, behind the overlay, which then draws its background
+//! translucent so the material shows through. Mica needs Windows 11; where
+//! an effect isn't available the next simpler one is tried, down to none.
+//! Other platforms keep the plain background.
+
+use std::sync::OnceLock;
+
+use tauri::WebviewWindow;
+
+use crate::theme::Theme;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backdrop {
+    None,
+    Mica,
+    Acrylic,
+    Blur,
+}
+
+impl Backdrop {
+    pub fn from_config(value: &str) -> Self {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "mica" => Backdrop::Mica,
+            "acrylic" => Backdrop::Acrylic,
+            "blur" => Backdrop::Blur,
+            "" | "none" => Backdrop::None,
+            other => {
+                tracing::warn!("Unknown backdrop \"{}\", using none", other);
+                Backdrop::None
+            }
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Backdrop::None => "none",
+            Backdrop::Mica => "mica",
+            Backdrop::Acrylic => "acrylic",
+            Backdrop::Blur => "blur",
+        }
+    }
+
+    /// The effect to try when this one isn't available
+    fn fallback(self) -> Self {
+        match self {
+            Backdrop::Mica => Backdrop::Acrylic,
+            Backdrop::Acrylic => Backdrop::Blur,
+            Backdrop::Blur | Backdrop::None => Backdrop::None,
+        }
+    }
+}
+
+static APPLIED: OnceLock<Backdrop> = OnceLock::new();
+
+/// The effect in use, for the window to draw its background to match
+pub fn applied() -> Backdrop {
+    APPLIED.get().copied().unwrap_or(Backdrop::None)
+}
+
+/// Put the configured effect, or the closest available one, behind `window`
+pub fn apply(window: &WebviewWindow, wanted: Backdrop, theme: &Theme) {
+    let mut backdrop = wanted;
+    if !cfg!(windows) && backdrop != Backdrop::None {
+        tracing::warn!("window.backdrop only works on Windows");
+        backdrop = Backdrop::None;
+    }
+    while backdrop != Backdrop::None {
+        match platform::apply(window, backdrop, tint(theme)) {
+            Ok(()) => break,
+            Err(e) => {
+                tracing::warn!("The {} backdrop isn't available: {}", backdrop.name(), e);
+                backdrop = backdrop.fallback();
+            }
+        }
+    }
+    if backdrop != Backdrop::None {
+        tracing::info!("Using the {} backdrop", backdrop.name());
+    }
+    let _ = APPLIED.set(backdrop);
+}
+
+/// The theme's background as an RGBA tint for acrylic, half see-through
+fn tint(theme: &Theme) -> (u8, u8, u8, u8) {
+    let value = u32::from_str_radix(theme.colors.bg.trim_start_matches('#'), 16).unwrap_or(0);
+    ((value >> 16) as u8, (value >> 8) as u8, value as u8, 128)
+}
+
+#[cfg(windows)]
+mod platform {
+    use tauri::window::Color;
+    use tauri::WebviewWindow;
+
+    use super::Backdrop;
+
+    pub fn apply(window: &WebviewWindow, backdrop: Backdrop, tint: (u8, u8, u8, u8)) -> Result<(), String> {
+        let applied = match backdrop {
+            Backdrop::Mica => window_vibrancy::apply_mica(window, Some(true)),
+            Backdrop::Acrylic => window_vibrancy::apply_acrylic(window, Some(tint)),
+            Backdrop::Blur => window_vibrancy::apply_blur(window, Some(tint)),
+            Backdrop::None => return Ok(()),
+        };
+        applied.map_err(|e| e.to_string())?;
+        // The webview paints over the material unless its own background is clear
+        window.set_background_color(Some(Color(0, 0, 0, 0))).map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(not(windows))]
+mod platform {
+    use tauri::WebviewWindow;
+
+    use super::Backdrop;
+
+    pub fn apply(_window: &WebviewWindow, _backdrop: Backdrop, _tint: (u8, u8, u8, u8)) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backdrop_from_config() {
+        assert_eq!(Backdrop::from_config("Mica"), Backdrop::Mica);
+        assert_eq!(Backdrop::from_config(""), Backdrop::None);
+        assert_eq!(Backdrop::from_config("glass"), Backdrop::None);
+        assert_eq!(Backdrop::Mica.fallback(), Backdrop::Acrylic);
+    }
+
+    #[test]
+    fn test_tint_from_theme() {
+        let theme = Theme::from_name("dark").unwrap();
+        let (r, g, b, a) = tint(&theme);
+        assert_eq!(format!("#{:02x}{:02x}{:02x}", r, g, b), theme.colors.bg);
+        assert_eq!(a, 128);
+    }
+}
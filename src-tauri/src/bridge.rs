@@ -0,0 +1,433 @@
+//! Editor bridge - local socket for companion extensions
+//!
+//! Serves newline-delimited JSON on `127.0.0.1:<port>` so editor extensions
+//! (e.g. a VS Code status bar item or a JetBrains plugin with several projects
+//! open) can follow progress and run a few actions.
+//! The protocol is documented in `docs/bridge-protocol.md`.
+
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::tcp::OwnedWriteHalf;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::config::{self, BridgeConfig};
+use crate::router::{AppEvent, EventRouter};
+use crate::rules::EventContext;
+use crate::secret;
+use crate::store::{EventStore, TaskFilter};
+
+/// Bumped on incompatible protocol changes
+pub const PROTOCOL_VERSION: u32 = 1;
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+/// Actions that don't need the store and are handed to the app
+const WINDOW_ACTIONS: &[&str] = &["show_overlay", "hide_overlay", "toggle_overlay"];
+const STORE_ACTIONS: &[&str] = &["get_active_tasks", "get_recent_tasks", "get_stats", "get_session_summary"];
+
+/// Runs a window action by name, returning whether it was handled
+pub type WindowActionHandler = Arc<dyn Fn(&str) -> bool + Send + Sync>;
+
+/// Messages sent by a client
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    Hello {
+        version: u32,
+        #[serde(default)]
+        client: Option<String>,
+        #[serde(default)]
+        token: Option<String>,
+    },
+    /// Add or replace a subscription; clients scoping by project give each one an `id`
+    Subscribe {
+        #[serde(default)]
+        id: Option<String>,
+        #[serde(default)]
+        workspaces: Vec<String>,
+        #[serde(default)]
+        events: Vec<String>,
+    },
+    /// Drop one subscription, or all of them without an `id`
+    Unsubscribe {
+        #[serde(default)]
+        id: Option<String>,
+    },
+    Action {
+        id: Value,
+        action: String,
+        #[serde(default)]
+        params: Value,
+    },
+    Ping,
+}
+
+/// Messages sent to a client
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage {
+    Welcome {
+        protocol_version: u32,
+        app_version: &'static str,
+        actions: Vec<&'static str>,
+    },
+    Event {
+        event: BridgeEvent,
+        /// Ids of the named subscriptions the event matched
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        subscriptions: Vec<String>,
+    },
+    Result {
+        id: Value,
+        ok: bool,
+        #[serde(skip_serializing_if = "Value::is_null")]
+        data: Value,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+    },
+    Error {
+        message: String,
+    },
+    Pong,
+}
+
+/// Router event as seen by bridge clients
+#[derive(Debug, Clone, Serialize)]
+pub struct BridgeEvent {
+    #[serde(flatten)]
+    pub context: EventContext,
+    pub project_path: Option<String>,
+}
+
+/// One filter a client asked to receive events for
+#[derive(Debug, Default)]
+pub struct Subscription {
+    workspaces: Vec<String>,
+    events: Vec<String>,
+}
+
+impl Subscription {
+    /// Whether an event passes the client's workspace and event type filters
+    ///
+    /// With a workspace filter, events whose project is unknown are dropped.
+    pub fn matches(&self, event: &BridgeEvent) -> bool {
+        if !self.events.is_empty() && !self.events.iter().any(|e| e == &event.context.event_type) {
+            return false;
+        }
+        if self.workspaces.is_empty() {
+            return true;
+        }
+        event.project_path.as_deref().is_some_and(|path| {
+            self.workspaces.iter().any(|w| !w.is_empty() && Path::new(path).starts_with(w))
+        })
+    }
+}
+
+/// A client's subscriptions, keyed by optional id
+#[derive(Debug, Default)]
+pub struct Subscriptions {
+    entries: Vec<(Option<String>, Subscription)>,
+}
+
+impl Subscriptions {
+    /// Add a subscription, replacing any with the same id
+    pub fn set(&mut self, id: Option<String>, subscription: Subscription) {
+        match self.entries.iter_mut().find(|(existing, _)| *existing == id) {
+            Some(entry) => entry.1 = subscription,
+            None => self.entries.push((id, subscription)),
+        }
+    }
+
+    /// Remove the subscription with `id`, or every subscription when `None`
+    pub fn remove(&mut self, id: Option<&str>) {
+        match id {
+            Some(id) => self.entries.retain(|(existing, _)| existing.as_deref() != Some(id)),
+            None => self.entries.clear(),
+        }
+    }
+
+    /// Ids of the named subscriptions matching an event, or `None` if nothing matches
+    pub fn matching(&self, event: &BridgeEvent) -> Option<Vec<String>> {
+        let mut matched = false;
+        let mut ids = Vec::new();
+        for (id, subscription) in &self.entries {
+            if subscription.matches(event) {
+                matched = true;
+                ids.extend(id.clone());
+            }
+        }
+        matched.then_some(ids)
+    }
+}
+
+struct BridgeContext {
+    config: BridgeConfig,
+    /// The configured token, or else the generated one
+    token: String,
+    router: Arc<EventRouter>,
+    store: Arc<EventStore>,
+    window_actions: WindowActionHandler,
+}
+
+impl BridgeContext {
+    /// Translate a router event, resolving its project through the session
+    fn translate(&self, event: &AppEvent) -> Option<BridgeEvent> {
+        let context = EventContext::from_event(event, &self.store)?;
+        let project_path = match event {
+            AppEvent::TaskStarted(e) | AppEvent::AwaitingInput(e) if e.project_path.is_some() => e.project_path.clone(),
+            _ => context.session_id.as_deref()
+                .and_then(|id| self.store.get_session(id).ok().flatten())
+                .and_then(|s| s.project_path),
+        };
+        Some(BridgeEvent { context, project_path })
+    }
+
+    /// Run an action, returning its data or an error message
+    fn run_action(&self, action: &str, params: &Value) -> Result<Value, String> {
+        let to_json = |value: Result<Value, serde_json::Error>| value.map_err(|e| e.to_string());
+
+        match action {
+            "get_active_tasks" => {
+                let filter = TaskFilter {
+                    status: Some("active".to_string()),
+                    project_path: params["workspace"].as_str().map(str::to_string),
+                    ..Default::default()
+                };
+                let tasks = self.store.get_recent_tasks(&filter, 100).map_err(|e| e.to_string())?;
+                to_json(serde_json::to_value(tasks))
+            }
+            "get_recent_tasks" => {
+                let limit = params["limit"].as_u64().unwrap_or(20).min(500) as usize;
+                let filter: TaskFilter = serde_json::from_value(params["filter"].clone()).unwrap_or_default();
+                let tasks = self.store.get_recent_tasks(&filter, limit).map_err(|e| e.to_string())?;
+                to_json(serde_json::to_value(tasks))
+            }
+            "get_stats" => {
+                let stats = self.store.get_task_stats().map_err(|e| e.to_string())?;
+                to_json(serde_json::to_value(stats))
+            }
+            "get_session_summary" => {
+                let session_id = params["session_id"].as_str().ok_or("session_id is required")?;
+                let summary = self.store.get_session_summary(session_id).map_err(|e| e.to_string())?;
+                to_json(serde_json::to_value(summary))
+            }
+            _ if WINDOW_ACTIONS.contains(&action) => {
+                if (self.window_actions)(action) {
+                    Ok(Value::Null)
+                } else {
+                    Err(format!("{} is unavailable", action))
+                }
+            }
+            _ => Err(format!("Unknown action: {}", action)),
+        }
+    }
+}
+
+/// Start the bridge on the current runtime
+pub fn start(config: BridgeConfig, router: Arc<EventRouter>, store: Arc<EventStore>, window_actions: WindowActionHandler) {
+    let token = match config.token.trim() {
+        "" => match secret::load_or_create(&config::get_bridge_token_path()) {
+            Ok(token) => token,
+            Err(e) => {
+                tracing::error!("Not starting the bridge; failed to read its token: {}", e);
+                return;
+            }
+        },
+        token => token.to_string(),
+    };
+    let ctx = Arc::new(BridgeContext { config, token, router, store, window_actions });
+
+    tokio::spawn(async move {
+        let listener = match TcpListener::bind(("127.0.0.1", ctx.config.port)).await {
+            Ok(l) => l,
+            Err(e) => {
+                tracing::error!("Failed to bind bridge on port {}: {}", ctx.config.port, e);
+                return;
+            }
+        };
+        tracing::info!("Editor bridge listening on 127.0.0.1:{}", ctx.config.port);
+
+        loop {
+            match listener.accept().await {
+                Ok((stream, _)) => {
+                    let ctx = ctx.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = handle_client(stream, ctx).await {
+                            tracing::debug!("Bridge client disconnected: {}", e);
+                        }
+                    });
+                }
+                Err(e) => tracing::warn!("Bridge accept failed: {}", e),
+            }
+        }
+    });
+}
+
+/// Every client must present the token; there is no open mode
+fn token_accepted(given: Option<&str>, expected: &str) -> bool {
+    !expected.is_empty() && given.is_some_and(|given| secret::tokens_match(given, expected))
+}
+
+async fn send(writer: &mut OwnedWriteHalf, message: &ServerMessage) -> Result<(), String> {
+    let mut line = serde_json::to_string(message).map_err(|e| e.to_string())?;
+    line.push('\n');
+    writer.write_all(line.as_bytes()).await.map_err(|e| e.to_string())
+}
+
+async fn handle_client(stream: TcpStream, ctx: Arc<BridgeContext>) -> Result<(), String> {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+
+    // The first message must be a compatible hello
+    let first = tokio::time::timeout(HANDSHAKE_TIMEOUT, lines.next_line())
+        .await
+        .map_err(|_| "handshake timed out".to_string())?
+        .map_err(|e| e.to_string())?
+        .ok_or("closed before handshake")?;
+
+    match serde_json::from_str::<ClientMessage>(&first) {
+        Ok(ClientMessage::Hello { version, client, token }) => {
+            if version != PROTOCOL_VERSION {
+                let message = format!("Unsupported protocol version {}; expected {}", version, PROTOCOL_VERSION);
+                send(&mut writer, &ServerMessage::Error { message: message.clone() }).await?;
+                return Err(message);
+            }
+            if !token_accepted(token.as_deref(), &ctx.token) {
+                send(&mut writer, &ServerMessage::Error { message: "Invalid token".to_string() }).await?;
+                return Err("invalid token".to_string());
+            }
+            tracing::info!("Bridge client connected: {}", client.as_deref().unwrap_or("unknown"));
+        }
+        _ => {
+            send(&mut writer, &ServerMessage::Error { message: "Expected hello".to_string() }).await?;
+            return Err("missing hello".to_string());
+        }
+    }
+
+    send(&mut writer, &ServerMessage::Welcome {
+        protocol_version: PROTOCOL_VERSION,
+        app_version: env!("CARGO_PKG_VERSION"),
+        actions: STORE_ACTIONS.iter().chain(WINDOW_ACTIONS).copied().collect(),
+    }).await?;
+
+    let mut events = ctx.router.subscribe();
+    let mut subscriptions = Subscriptions::default();
+
+    loop {
+        tokio::select! {
+            line = lines.next_line() => {
+                let Some(line) = line.map_err(|e| e.to_string())? else {
+                    return Ok(());
+                };
+                if line.trim().is_empty() {
+                    continue;
+                }
+
+                let reply = match serde_json::from_str::<ClientMessage>(&line) {
+                    Ok(ClientMessage::Subscribe { id, workspaces, events }) => {
+                        subscriptions.set(id, Subscription { workspaces, events });
+                        None
+                    }
+                    Ok(ClientMessage::Unsubscribe { id }) => {
+                        subscriptions.remove(id.as_deref());
+                        None
+                    }
+                    Ok(ClientMessage::Action { id, action, params }) => Some(match ctx.run_action(&action, &params) {
+                        Ok(data) => ServerMessage::Result { id, ok: true, data, error: None },
+                        Err(error) => ServerMessage::Result { id, ok: false, data: Value::Null, error: Some(error) },
+                    }),
+                    Ok(ClientMessage::Ping) => Some(ServerMessage::Pong),
+                    Ok(ClientMessage::Hello { .. }) => Some(ServerMessage::Error { message: "Already connected".to_string() }),
+                    Err(e) => Some(ServerMessage::Error { message: format!("Invalid message: {}", e) }),
+                };
+
+                if let Some(reply) = reply {
+                    send(&mut writer, &reply).await?;
+                }
+            }
+            event = events.recv() => {
+                match event {
+                    Ok(logged) => {
+                        if subscriptions.entries.is_empty() {
+                            continue;
+                        }
+                        let Some(event) = ctx.translate(&logged.event) else { continue };
+                        if let Some(ids) = subscriptions.matching(&event) {
+                            send(&mut writer, &ServerMessage::Event { event, subscriptions: ids }).await?;
+                        }
+                    }
+                    Err(RecvError::Lagged(skipped)) => {
+                        tracing::warn!("Bridge client lagged, skipped {} events", skipped);
+                        send(&mut writer, &ServerMessage::Error { message: format!("Dropped {} events", skipped) }).await?;
+                    }
+                    Err(RecvError::Closed) => return Ok(()),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn event(event_type: &str, project_path: Option<&str>) -> BridgeEvent {
+        BridgeEvent {
+            context: EventContext { event_type: event_type.to_string(), ..Default::default() },
+            project_path: project_path.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_client_messages() {
+        let hello: ClientMessage = serde_json::from_str(r#"{"type":"hello","version":1,"client":"vscode"}"#).unwrap();
+        assert!(matches!(hello, ClientMessage::Hello { version: 1, token: None, .. }));
+        assert!(!token_accepted(None, "secret"));
+        assert!(!token_accepted(Some(""), ""));
+        assert!(!token_accepted(Some("guess"), "secret"));
+        assert!(token_accepted(Some("secret"), "secret"));
+
+        let action: ClientMessage = serde_json::from_str(r#"{"type":"action","id":7,"action":"get_stats"}"#).unwrap();
+        assert!(matches!(action, ClientMessage::Action { ref action, .. } if action == "get_stats"));
+
+        let reply = serde_json::to_value(ServerMessage::Result { id: json!(7), ok: true, data: Value::Null, error: None }).unwrap();
+        assert_eq!(reply, json!({ "type": "result", "id": 7, "ok": true }));
+    }
+
+    #[test]
+    fn test_subscription_filters() {
+        let subscription = Subscription {
+            workspaces: vec!["/work/app".to_string()],
+            events: vec!["task_started".to_string()],
+        };
+        assert!(subscription.matches(&event("task_started", Some("/work/app/packages/api"))));
+        assert!(!subscription.matches(&event("task_started", Some("/work/application"))));
+        assert!(!subscription.matches(&event("task_started", None)));
+        assert!(!subscription.matches(&event("task_complete", Some("/work/app"))));
+        assert!(Subscription::default().matches(&event("task_complete", None)));
+    }
+
+    #[test]
+    fn test_per_project_subscriptions() {
+        let project = |path: &str| Subscription { workspaces: vec![path.to_string()], events: Vec::new() };
+        let mut subscriptions = Subscriptions::default();
+        subscriptions.set(Some("api".to_string()), project("/work/api"));
+        subscriptions.set(Some("web".to_string()), project("/work/web"));
+        subscriptions.set(Some("web".to_string()), project("/work/site"));
+
+        assert_eq!(subscriptions.matching(&event("task_started", Some("/work/api/src"))), Some(vec!["api".to_string()]));
+        assert_eq!(subscriptions.matching(&event("task_started", Some("/work/web"))), None);
+        assert_eq!(subscriptions.matching(&event("task_started", Some("/work/site"))), Some(vec!["web".to_string()]));
+
+        subscriptions.remove(Some("api"));
+        assert_eq!(subscriptions.matching(&event("task_started", Some("/work/api"))), None);
+
+        let unsubscribe: ClientMessage = serde_json::from_str(r#"{"type":"unsubscribe"}"#).unwrap();
+        assert!(matches!(unsubscribe, ClientMessage::Unsubscribe { id: None }));
+    }
+}
@@ -0,0 +1,65 @@
+//! Time source for code that compares against "now"
+//!
+//! The router's stale-task check and the maintenance jobs read the time
+//! through a [`Clock`] rather than the system clock directly, so tests can
+//! step a [`ManualClock`] past a threshold instead of sleeping.
+
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::time::Duration;
+
+pub trait Clock: Send + Sync {
+    /// Milliseconds since the Unix epoch
+    fn now_ms(&self) -> i64;
+}
+
+/// The real wall clock
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now_ms(&self) -> i64 {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_millis() as i64)
+            .unwrap_or(0)
+    }
+}
+
+/// A clock that only moves when told to
+pub struct ManualClock {
+    now_ms: AtomicI64,
+}
+
+impl ManualClock {
+    pub fn new(now_ms: i64) -> Self {
+        Self { now_ms: AtomicI64::new(now_ms) }
+    }
+
+    pub fn set(&self, now_ms: i64) {
+        self.now_ms.store(now_ms, Ordering::SeqCst);
+    }
+
+    pub fn advance(&self, by: Duration) {
+        self.now_ms.fetch_add(by.as_millis() as i64, Ordering::SeqCst);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now_ms(&self) -> i64 {
+        self.now_ms.load(Ordering::SeqCst)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manual_clock() {
+        let clock = ManualClock::new(1_000);
+        clock.advance(Duration::from_secs(2));
+        assert_eq!(clock.now_ms(), 3_000);
+        clock.set(10);
+        assert_eq!(clock.now_ms(), 10);
+        assert!(SystemClock.now_ms() > 1_600_000_000_000);
+    }
+}
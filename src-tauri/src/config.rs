@@ -1,74 +1,1028 @@
 //! Configuration system for the Agent Progress Overlay
 //!
-//! Loads settings from ~/.claude/overlay-config.toml with sensible defaults.
+//! Loads settings from the overlay's config file with sensible defaults. The
+//! overlay's own files (this config and the history database) live under the
+//! platform's config and data directories; Claude's files are read from
+//! Claude's config directory (`~/.claude` or `CLAUDE_CONFIG_DIR`), where
+//! Claude puts them.
 
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+
+const ALLOWED_ROOTS_ENV: &str = "AGENT_PROGRESS_ALLOWED_ROOTS";
+/// Set by users who moved Claude's config directory; Claude Code reads it too
+const CLAUDE_CONFIG_DIR_ENV: &str = "CLAUDE_CONFIG_DIR";
+
+/// Directory for the overlay's files under the platform's config and data directories
+const APP_DIR_NAME: &str = "agent-progress";
+const CONFIG_FILE_NAME: &str = "config.toml";
+const DATABASE_FILE_NAME: &str = "history.db";
+const CORE_TOKEN_FILE_NAME: &str = "core-token";
+const BRIDGE_TOKEN_FILE_NAME: &str = "bridge-token";
+/// Command-line flag for portable mode
+pub const PORTABLE_FLAG: &str = "--portable";
+/// A file with this name next to the executable turns on portable mode
+const PORTABLE_MARKER: &str = "portable";
+/// Directory next to the executable that holds the overlay's files in portable mode
+const PORTABLE_DATA_DIR: &str = "data";
+/// Names the same files had when everything lived in `~/.claude`
+const LEGACY_CONFIG_FILE_NAME: &str = "overlay-config.toml";
+const LEGACY_DATABASE_FILE_NAME: &str = "overlay-history.db";
 
 /// Main configuration structure
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
     #[serde(default)]
-    pub window: WindowConfig,
+    pub window: WindowConfig,
+    #[serde(default)]
+    pub behavior: BehaviorConfig,
+    #[serde(default)]
+    pub shortcuts: ShortcutsConfig,
+    #[serde(default)]
+    pub paths: PathsConfig,
+    #[serde(default)]
+    pub plugins: PluginsConfig,
+    #[serde(default)]
+    pub rules: Vec<RuleConfig>,
+    #[serde(default)]
+    pub sources: SourcesConfig,
+    #[serde(default)]
+    pub bridge: BridgeConfig,
+    #[serde(default)]
+    pub relay: RelayConfig,
+    #[serde(default)]
+    pub core: CoreConfig,
+    #[serde(default)]
+    pub sync: SyncConfig,
+    #[serde(default)]
+    pub redaction: RedactionConfig,
+    #[serde(default)]
+    pub database: DatabaseConfig,
+    #[serde(default)]
+    pub context_usage: ContextUsageConfig,
+    #[serde(default)]
+    pub usage_warnings: UsageWarningsConfig,
+    /// Prices used to estimate what each model cost
+    #[serde(default = "default_model_prices")]
+    pub pricing: Vec<ModelPrice>,
+    #[serde(default)]
+    pub overdue: OverdueConfig,
+    #[serde(default)]
+    pub awaiting_input: AwaitingInputConfig,
+    #[serde(default)]
+    pub attention: AttentionConfig,
+    #[serde(default)]
+    pub priority: PriorityConfig,
+    #[serde(default)]
+    pub processes: ProcessesConfig,
+    #[serde(default)]
+    pub storage: StorageConfig,
+    #[serde(default)]
+    pub openers: OpenersConfig,
+    #[serde(default)]
+    pub updates: UpdatesConfig,
+    #[serde(default)]
+    pub logging: LoggingConfig,
+    /// Configs written before onboarding existed count as set up
+    #[serde(default = "default_onboarding")]
+    pub onboarding: OnboardingConfig,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WindowConfig {
+    #[serde(default = "default_width")]
+    pub width: u32,
+    #[serde(default = "default_height")]
+    pub height: u32,
+    #[serde(default = "default_position")]
+    pub position: String,
+    #[serde(default = "default_true")]
+    pub always_on_top: bool,
+    #[serde(default = "default_opacity")]
+    pub opacity: f64,
+    #[serde(default = "default_theme")]
+    pub theme: String,
+    /// Show the overlay on every workspace rather than the one it opened on
+    #[serde(default)]
+    pub all_workspaces: bool,
+    /// Linux under X11: only show the overlay on these workspaces, counting from 1
+    #[serde(default)]
+    pub workspaces: Vec<u32>,
+    /// Linux: mark the overlay as a utility window, so tiling window managers float it
+    #[serde(default = "default_true")]
+    pub float_in_tiling_wms: bool,
+    /// Wayland: show the overlay and popups as layer-shell surfaces where the compositor supports them
+    #[serde(default = "default_true")]
+    pub layer_shell: bool,
+    /// macOS: float over fullscreen Spaces without taking focus, at the cost of the dock icon
+    #[serde(default)]
+    pub macos_panel: bool,
+    /// Windows: "mica", "acrylic" or "blur" behind the overlay, or "none"
+    #[serde(default = "default_backdrop")]
+    pub backdrop: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BehaviorConfig {
+    #[serde(default = "default_max_recent_tasks")]
+    pub max_recent_tasks: usize,
+    #[serde(default)]
+    pub auto_hide: bool,
+    #[serde(default = "default_auto_hide_delay")]
+    pub auto_hide_delay_ms: u64,
+    #[serde(default = "default_stale_threshold")]
+    pub stale_task_threshold_ms: u64,
+    #[serde(default = "default_notification_duration")]
+    pub notification_duration_ms: u64,
+    #[serde(default = "default_debounce")]
+    pub file_watch_debounce_ms: u64,
+    /// "auto" (native notifications with a polling fallback), "notify", or "poll"
+    #[serde(default = "default_watch_mode")]
+    pub watch_mode: String,
+    /// Todo files not modified for this many days are hidden (0, the default, keeps them all)
+    #[serde(default)]
+    pub todo_max_age_days: u64,
+    /// Move ignored todo files whose todos are all completed into `<todos_dir>/archive`
+    #[serde(default)]
+    pub archive_completed_todos: bool,
+    /// Show a todo that several sessions share once, with the number of sessions
+    #[serde(default)]
+    pub collapse_duplicate_todos: bool,
+    /// IANA timezone for daily/hourly history and quiet hours, e.g. "Europe/Berlin" (empty uses the system's)
+    #[serde(default)]
+    pub timezone: String,
+    /// Local time window with no notification popups, e.g. "22:00-07:00" (empty disables)
+    #[serde(default)]
+    pub quiet_hours: String,
+    /// Minutes without keyboard or mouse input after which popups are held
+    /// back and summarized on return (0 disables)
+    #[serde(default = "default_idle_after_mins")]
+    pub idle_after_mins: u64,
+    /// Announce task starts and finishes to screen readers
+    #[serde(default = "default_true")]
+    pub screen_reader_announcements: bool,
+    /// Turn off animations in every window, whatever the OS setting
+    #[serde(default)]
+    pub reduced_motion: bool,
+    /// Language of the tray, diagnostics, announcements and reports: "en",
+    /// "de", "ja" or "zh" (empty uses the system's)
+    #[serde(default)]
+    pub locale: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShortcutsConfig {
+    #[serde(default = "default_toggle_shortcut")]
+    pub toggle_visibility: String,
+    #[serde(default = "default_clear_shortcut")]
+    pub clear_tasks: String,
+    #[serde(default = "default_settings_shortcut")]
+    pub open_settings: String,
+    /// Global shortcut closing the newest notification popup (empty disables)
+    #[serde(default = "default_dismiss_notification_shortcut")]
+    pub dismiss_notification: String,
+    /// Global shortcut closing every notification popup (empty disables)
+    #[serde(default = "default_dismiss_all_notifications_shortcut")]
+    pub dismiss_all_notifications: String,
+    /// Global shortcut moving focus to the next notification popup (empty disables)
+    #[serde(default = "default_cycle_notifications_shortcut")]
+    pub cycle_notifications: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PathsConfig {
+    /// Claude's config directory; empty uses `CLAUDE_CONFIG_DIR`, then `~/.claude`
+    #[serde(default)]
+    pub claude_dir: String,
+    #[serde(default = "default_events_file")]
+    pub events_file: String,
+    #[serde(default = "default_todos_dir")]
+    pub todos_dir: String,
+    #[serde(default = "default_database_file")]
+    pub database_file: String,
+    /// Further Claude config directories watched alongside the main one
+    #[serde(default)]
+    pub profiles: Vec<ProfileConfig>,
+}
+
+/// A separate Claude config directory, e.g. for a work account
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProfileConfig {
+    /// Tag put on the profile's events and todos
+    pub name: String,
+    pub claude_dir: String,
+}
+
+/// Named event-matching rule; all conditions must hold
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuleConfig {
+    pub name: String,
+    /// Event types to match, e.g. "task_error"; empty matches any
+    #[serde(default)]
+    pub events: Vec<String>,
+    /// Tool names to match (case-insensitive); empty matches any
+    #[serde(default)]
+    pub tools: Vec<String>,
+    /// Case-insensitive substring the description must contain
+    #[serde(default)]
+    pub description_contains: Option<String>,
+    #[serde(default)]
+    pub min_duration_ms: Option<u64>,
+    /// Lowest priority to match, e.g. "high"; events without one, such as overdue tasks, don't match
+    #[serde(default)]
+    pub min_priority: Option<String>,
+    /// Types of `custom` events to match (case-insensitive); empty matches any
+    #[serde(default)]
+    pub custom_types: Vec<String>,
+    /// Values a custom event's payload must hold, by key; nested keys are
+    /// dotted, e.g. "build.target", and "*" only requires the key to be there
+    #[serde(default)]
+    pub payload: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginsConfig {
+    #[serde(default)]
+    pub git_commits: GitCommitsPluginConfig,
+    #[serde(default)]
+    pub issue_tracker: IssueTrackerPluginConfig,
+    #[serde(default)]
+    pub todoist: TodoistPluginConfig,
+    #[serde(default)]
+    pub notion: NotionPluginConfig,
+    #[serde(default)]
+    pub github_issues: GitHubIssuesPluginConfig,
+    #[serde(default)]
+    pub automation: AutomationPluginConfig,
+    #[serde(default)]
+    pub transcripts: TranscriptsPluginConfig,
+    #[serde(default)]
+    pub tts: TtsPluginConfig,
+    /// Capabilities granted to each plugin, by plugin name, over the built-in grants
+    #[serde(default)]
+    pub permissions: HashMap<String, Vec<Capability>>,
+    /// Delivery limits by plugin name; a `default` entry applies to unlisted plugins
+    #[serde(default)]
+    pub limits: HashMap<String, PluginLimitConfig>,
+}
+
+/// Something a plugin may do with task data beyond its own memory
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Capability {
+    /// Make network requests
+    Network,
+    /// Read or write files outside the app's own data
+    Filesystem,
+    /// Run external programs
+    Shell,
+}
+
+impl PluginsConfig {
+    /// Capabilities granted to a plugin: its `[plugins.permissions]` entry, or else its built-in grant
+    pub fn granted(&self, name: &str) -> Vec<Capability> {
+        match self.permissions.get(name) {
+            Some(granted) => granted.clone(),
+            None => default_plugin_permissions().remove(name).unwrap_or_default(),
+        }
+    }
+
+    /// Delivery limits for a plugin
+    pub fn limit_for(&self, name: &str) -> PluginLimitConfig {
+        self.limits.get(name)
+            .or_else(|| self.limits.get("default"))
+            .cloned()
+            .unwrap_or_default()
+    }
+}
+
+/// Timeout and token-bucket rate limit applied to each plugin's event deliveries
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginLimitConfig {
+    #[serde(default = "default_plugin_timeout_ms")]
+    pub timeout_ms: u64,
+    /// Sustained deliveries per second
+    #[serde(default = "default_plugin_rate_per_sec")]
+    pub rate_per_sec: f64,
+    /// Deliveries allowed in a burst before the rate applies
+    #[serde(default = "default_plugin_burst")]
+    pub burst: u32,
+}
+
+impl Default for PluginLimitConfig {
+    fn default() -> Self {
+        Self {
+            timeout_ms: default_plugin_timeout_ms(),
+            rate_per_sec: default_plugin_rate_per_sec(),
+            burst: default_plugin_burst(),
+        }
+    }
+}
+
+impl Default for PluginsConfig {
+    fn default() -> Self {
+        Self {
+            git_commits: GitCommitsPluginConfig::default(),
+            issue_tracker: IssueTrackerPluginConfig::default(),
+            todoist: TodoistPluginConfig::default(),
+            notion: NotionPluginConfig::default(),
+            github_issues: GitHubIssuesPluginConfig::default(),
+            automation: AutomationPluginConfig::default(),
+            transcripts: TranscriptsPluginConfig::default(),
+            tts: TtsPluginConfig::default(),
+            permissions: HashMap::new(),
+            limits: HashMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GitCommitsPluginConfig {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+}
+
+/// Adds each task's prompt and the assistant's reply from Claude's session transcripts
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TranscriptsPluginConfig {
+    #[serde(default)]
+    pub enabled: bool,
+}
+
+/// Reads events aloud with the platform's speech engine
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TtsPluginConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// What to say for each event type; unlisted event types are not spoken
+    #[serde(default = "default_tts_templates")]
+    pub templates: HashMap<String, String>,
+    /// Keep speaking during `behavior.quiet_hours`
+    #[serde(default)]
+    pub speak_during_quiet_hours: bool,
+}
+
+impl Default for TtsPluginConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            templates: default_tts_templates(),
+            speak_during_quiet_hours: false,
+        }
+    }
+}
+
+/// Mirrors todos into Linear or Jira issues
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IssueTrackerPluginConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// "linear" or "jira"
+    #[serde(default = "default_issue_tracker_provider")]
+    pub provider: String,
+    #[serde(default)]
+    pub api_token: String,
+    /// Jira site URL, e.g. https://team.atlassian.net
+    #[serde(default)]
+    pub base_url: String,
+    /// Jira account email the API token belongs to
+    #[serde(default)]
+    pub email: String,
+    #[serde(default)]
+    pub projects: Vec<IssueTrackerProject>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IssueTrackerProject {
+    /// Project root; sessions under this path are synced
+    pub path: String,
+    /// Linear team ID or Jira project key
+    pub key: String,
+}
+
+/// Mirrors todos into a Todoist project
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TodoistPluginConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub api_token: String,
+    /// Target project; empty means the Inbox
+    #[serde(default)]
+    pub project_id: String,
+    /// "remote" keeps changes made in Todoist, "local" re-applies the agent's state
+    #[serde(default = "default_conflict_policy")]
+    pub conflict_policy: String,
+}
+
+/// Appends session summaries to a Notion database
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotionPluginConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Internal integration secret
+    #[serde(default)]
+    pub api_token: String,
+    #[serde(default)]
+    pub database_id: String,
+    /// Name of the database's title property
+    #[serde(default = "default_notion_title_property")]
+    pub title_property: String,
+}
+
+/// Tracks todos as GitHub issues
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GitHubIssuesPluginConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub token: String,
+    /// Target repository as "owner/name"
+    #[serde(default)]
+    pub repo: String,
+    /// "session" for one checklist issue per session, "todo" for one issue per todo
+    #[serde(default = "default_github_issues_mode")]
+    pub mode: String,
+    /// Only sync sessions under this project root; empty syncs every session
+    #[serde(default)]
+    pub project_path: String,
+    #[serde(default)]
+    pub labels: Vec<String>,
+}
+
+/// Outbound webhooks fired by rule matches
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AutomationPluginConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub triggers: Vec<AutomationTrigger>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AutomationTrigger {
+    /// Name of the rule that fires this trigger
+    pub rule: String,
+    /// "ifttt" or "http"
+    #[serde(default = "default_trigger_kind")]
+    pub kind: String,
+    #[serde(default)]
+    pub ifttt_event: String,
+    #[serde(default)]
+    pub ifttt_key: String,
+    #[serde(default = "default_trigger_method")]
+    pub method: String,
+    /// URL template; placeholder values are URL-encoded
+    #[serde(default)]
+    pub url: String,
+    /// Body template; placeholder values are JSON-escaped when `content_type` is JSON. Empty sends no body
+    #[serde(default)]
+    pub body: String,
+    #[serde(default = "default_trigger_content_type")]
+    pub content_type: String,
+}
+
+/// Local socket for editor extensions
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BridgeConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_bridge_port")]
+    pub port: u16,
+    /// Shared secret clients must send in their hello; empty uses the one generated in `bridge-token`
+    #[serde(default)]
+    pub token: String,
+}
+
+impl Default for BridgeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: default_bridge_port(),
+            token: String::new(),
+        }
+    }
+}
+
+/// Listener for events forwarded by `agent-progress-relay` on remote machines
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RelayConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_relay_port")]
+    pub port: u16,
+    /// Shared secret relays must send in their hello; required when enabled
+    #[serde(default)]
+    pub token: String,
+}
+
+impl Default for RelayConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: default_relay_port(),
+            token: String::new(),
+        }
+    }
+}
+
+/// Socket a headless core serves its events on, for overlays to attach to
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CoreConfig {
+    #[serde(default = "default_core_port")]
+    pub port: u16,
+}
+
+impl Default for CoreConfig {
+    fn default() -> Self {
+        Self { port: default_core_port() }
+    }
+}
+
+/// Masking of secrets in task descriptions before they are stored or shown
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RedactionConfig {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// Apply the built-in rules (AWS keys, bearer tokens, secret env assignments, ...)
+    #[serde(default = "default_true")]
+    pub default_rules: bool,
+    /// Additional rules, applied after the built-in ones
+    #[serde(default)]
+    pub rules: Vec<RedactionRuleConfig>,
+}
+
+impl Default for RedactionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            default_rules: true,
+            rules: Vec::new(),
+        }
+    }
+}
+
+/// Regex replaced wherever it matches; the replacement may use `${1}` capture groups
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RedactionRuleConfig {
+    pub name: String,
+    pub pattern: String,
+    #[serde(default = "default_redaction_replacement")]
+    pub replacement: String,
+}
+
+/// History sync between machines through a shared folder
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Folder replicated between machines (Dropbox, Syncthing, a network share, ...)
+    #[serde(default)]
+    pub folder: String,
+    /// Name of this machine's change file; defaults to the hostname
+    #[serde(default)]
+    pub machine_id: String,
+    #[serde(default = "default_sync_interval_secs")]
+    pub interval_secs: u64,
+}
+
+impl Default for SyncConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            folder: String::new(),
+            machine_id: String::new(),
+            interval_secs: default_sync_interval_secs(),
+        }
+    }
+}
+
+/// History database maintenance
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DatabaseConfig {
+    /// How often maintenance runs (0 disables checkpointing and cleanup)
+    #[serde(default = "default_checkpoint_interval_secs")]
+    pub checkpoint_interval_secs: u64,
+    /// Checkpoint once the WAL is this large and no tasks are active
+    #[serde(default = "default_wal_idle_checkpoint_mb")]
+    pub wal_idle_checkpoint_mb: u64,
+    /// Checkpoint once the WAL is this large even while tasks are running
+    #[serde(default = "default_wal_max_mb")]
+    pub wal_max_mb: u64,
+    /// Finished tasks older than this many days are deleted (0 keeps them all)
+    #[serde(default)]
+    pub retention_days: u64,
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        Self {
+            checkpoint_interval_secs: default_checkpoint_interval_secs(),
+            wal_idle_checkpoint_mb: default_wal_idle_checkpoint_mb(),
+            wal_max_mb: default_wal_max_mb(),
+            retention_days: 0,
+        }
+    }
+}
+
+/// Context window tracking from Claude's session transcripts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContextUsageConfig {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// Tokens the model's context window holds
+    #[serde(default = "default_context_window_tokens")]
+    pub window_tokens: u64,
+    /// Warn once a session's context is this full, ahead of auto-compaction
+    #[serde(default = "default_context_warning_percent")]
+    pub warning_percent: f64,
+}
+
+impl Default for ContextUsageConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_true(),
+            window_tokens: default_context_window_tokens(),
+            warning_percent: default_context_warning_percent(),
+        }
+    }
+}
+
+/// Rate-limit and usage-limit warnings, from hook events and session transcripts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageWarningsConfig {
+    /// Look for limit errors in session transcripts; `rate_limited` hook events are always shown
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// How long the warning popup stays up, in seconds
+    #[serde(default = "default_usage_warning_display_secs")]
+    pub display_secs: u64,
+}
+
+impl Default for UsageWarningsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_true(),
+            display_secs: default_usage_warning_display_secs(),
+        }
+    }
+}
+
+/// Checking for new releases of the overlay
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdatesConfig {
+    /// Check in the background; `check_for_updates` works either way
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// "stable", or "beta" for pre-releases as well
+    #[serde(default = "default_update_channel")]
+    pub channel: String,
+    #[serde(default = "default_update_check_interval_hours")]
+    pub check_interval_hours: u64,
+}
+
+impl Default for UpdatesConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            channel: default_update_channel(),
+            check_interval_hours: default_update_check_interval_hours(),
+        }
+    }
+}
+
+/// Log level and log files
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoggingConfig {
+    /// Most verbose level logged: "error", "warn", "info", "debug" or "trace"
+    #[serde(default = "default_log_level")]
+    pub level: String,
+    /// The overlay's log file; empty for `logs/overlay.log` beside the history
+    /// database. The core writes `core.log` in the same folder.
+    #[serde(default)]
+    pub file: String,
+    /// Size a log file is moved aside at
+    #[serde(default = "default_log_max_size_mb")]
+    pub max_size_mb: u64,
+    /// Moved-aside files kept, `overlay.log.1` being the newest
+    #[serde(default = "default_log_retained_files")]
+    pub retained_files: usize,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            level: default_log_level(),
+            file: String::new(),
+            max_size_mb: default_log_max_size_mb(),
+            retained_files: default_log_retained_files(),
+        }
+    }
+}
+
+/// The first-run setup
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OnboardingConfig {
+    /// Set once the user finishes or skips the setup, which then isn't shown again
+    #[serde(default)]
+    pub completed: bool,
+}
+
+/// Popups for an agent blocked on a permission prompt or question
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AwaitingInputConfig {
+    /// Play a sound when an agent starts waiting
+    #[serde(default = "default_true")]
+    pub sound: bool,
+}
+
+impl Default for AwaitingInputConfig {
+    fn default() -> Self {
+        Self { sound: true }
+    }
+}
+
+/// Taskbar flashes, dock bounces and urgency hints while the overlay is hidden
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AttentionConfig {
+    /// By event type: "critical" keeps asking until the overlay is brought
+    /// up, "informational" asks briefly and "off" doesn't ask
+    #[serde(default = "default_attention_events")]
+    pub events: HashMap<String, String>,
+}
+
+impl Default for AttentionConfig {
+    fn default() -> Self {
+        Self { events: default_attention_events() }
+    }
+}
+
+/// CPU and memory use of the processes background shells run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProcessesConfig {
+    /// Sample background tasks that report a process id
+    #[serde(default = "default_true")]
+    pub monitor: bool,
+    #[serde(default = "default_process_sample_interval_secs")]
+    pub sample_interval_secs: u64,
+}
+
+impl Default for ProcessesConfig {
+    fn default() -> Self {
+        Self {
+            monitor: true,
+            sample_interval_secs: default_process_sample_interval_secs(),
+        }
+    }
+}
+
+/// How urgent each event is, and what each priority gets
+///
+/// Priorities are "low", "normal", "high" and "urgent".
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PriorityConfig {
+    /// By event type, for events that don't carry a priority or match a rule; others are "normal"
+    #[serde(default = "default_priority_events")]
+    pub events: HashMap<String, String>,
+    /// Named `[[rules]]` that set the priority of matching events; the first match wins
+    #[serde(default)]
+    pub rules: Vec<PriorityRuleConfig>,
+    /// Lowest priority that pops up; below it events are only recorded
+    #[serde(default = "default_priority_popup")]
+    pub popup: String,
+    /// Lowest priority that pops up through quiet hours and away time and keeps asking for attention
+    #[serde(default = "default_priority_push")]
+    pub push: String,
+}
+
+impl Default for PriorityConfig {
+    fn default() -> Self {
+        Self {
+            events: default_priority_events(),
+            rules: Vec::new(),
+            popup: default_priority_popup(),
+            push: default_priority_push(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PriorityRuleConfig {
+    /// Name of a rule under `[[rules]]`
+    pub rule: String,
+    pub priority: String,
+}
+
+/// Disk space taken by Claude's files and the history database
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StorageConfig {
+    /// How often it is added up (0 turns the check off)
+    #[serde(default = "default_storage_check_interval_mins")]
+    pub check_interval_mins: u64,
+    /// Suggest a cleanup once it all takes this much (0 never warns)
+    #[serde(default = "default_storage_warn_mb")]
+    pub warn_mb: u64,
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self {
+            check_interval_mins: default_storage_check_interval_mins(),
+            warn_mb: default_storage_warn_mb(),
+        }
+    }
+}
+
+/// Alerts for tasks that run much longer than they should
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OverdueConfig {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// Play a sound when a task becomes overdue
+    #[serde(default)]
+    pub sound: bool,
+    /// Thresholds by tool name; a `default` entry applies to unlisted tools
+    #[serde(default = "default_overdue_thresholds")]
+    pub thresholds: HashMap<String, OverdueThreshold>,
+}
+
+impl OverdueConfig {
+    /// Thresholds for a tool, if any apply to it
+    pub fn threshold_for(&self, tool: &str) -> Option<&OverdueThreshold> {
+        self.thresholds.get(tool).or_else(|| self.thresholds.get("default"))
+    }
+}
+
+impl Default for OverdueConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_true(),
+            sound: false,
+            thresholds: default_overdue_thresholds(),
+        }
+    }
+}
+
+/// How long a task may run before it is flagged, then escalated
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OverdueThreshold {
+    /// Restyle the task's notification and send `task-overdue` (0 disables)
+    #[serde(default = "default_overdue_warn_secs")]
+    pub warn_secs: u64,
+    /// Also pass the task on to plugins, for push or webhook alerts (0 disables)
+    #[serde(default = "default_overdue_escalate_secs")]
+    pub escalate_secs: u64,
+}
+
+impl Default for OverdueThreshold {
+    fn default() -> Self {
+        Self {
+            warn_secs: default_overdue_warn_secs(),
+            escalate_secs: default_overdue_escalate_secs(),
+        }
+    }
+}
+
+/// Commands that open a task's project folder, per platform
+///
+/// Each command is a program followed by its arguments. `{path}` is replaced
+/// with the folder, which is also the working directory the command runs in.
+/// An empty command uses the built-in one.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OpenersConfig {
+    #[serde(default)]
+    pub linux: OpenerCommands,
+    #[serde(default)]
+    pub macos: OpenerCommands,
+    #[serde(default)]
+    pub windows: OpenerCommands,
+}
+
+impl OpenersConfig {
+    /// Commands for the platform this is running on
+    pub fn current(&self) -> &OpenerCommands {
+        if cfg!(target_os = "macos") {
+            &self.macos
+        } else if cfg!(windows) {
+            &self.windows
+        } else {
+            &self.linux
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OpenerCommands {
+    /// Shows the folder in the file manager
+    #[serde(default)]
+    pub file_manager: Vec<String>,
+    /// Starts a terminal in the folder
+    #[serde(default)]
+    pub terminal: Vec<String>,
+}
+
+/// What a model charges, in US dollars per million tokens
+///
+/// Cache reads are charged a tenth of the input price and cache writes a
+/// quarter more than it, as Anthropic does.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModelPrice {
+    /// Matched against model ids containing it, e.g. "sonnet"
+    pub model: String,
+    pub input_per_mtok: f64,
+    pub output_per_mtok: f64,
+}
+
+impl ModelPrice {
+    /// The first price whose name appears in `model`
+    pub fn find<'a>(prices: &'a [ModelPrice], model: &str) -> Option<&'a ModelPrice> {
+        let model = model.to_lowercase();
+        prices.iter().find(|price| !price.model.is_empty() && model.contains(&price.model.to_lowercase()))
+    }
+
+    /// Estimated cost in dollars of the given token counts
+    pub fn cost(&self, input: i64, output: i64, cache_read: i64, cache_creation: i64) -> f64 {
+        let input = input as f64 + cache_read as f64 * 0.1 + cache_creation as f64 * 1.25;
+        (input * self.input_per_mtok + output as f64 * self.output_per_mtok) / 1_000_000.0
+    }
+}
+
+/// Input adapters for agent tools other than Claude Code
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SourcesConfig {
+    #[serde(default)]
+    pub aider: AiderSourceConfig,
     #[serde(default)]
-    pub behavior: BehaviorConfig,
+    pub codex: CodexSourceConfig,
     #[serde(default)]
-    pub shortcuts: ShortcutsConfig,
+    pub gemini: GeminiSourceConfig,
     #[serde(default)]
-    pub paths: PathsConfig,
+    pub cline: ClineSourceConfig,
+    #[serde(default)]
+    pub opencode: OpenCodeSourceConfig,
+    /// Log files whose matching lines become progress bars
+    #[serde(default)]
+    pub log_progress: Vec<LogProgressConfig>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct WindowConfig {
-    #[serde(default = "default_width")]
-    pub width: u32,
-    #[serde(default = "default_height")]
-    pub height: u32,
-    #[serde(default = "default_position")]
-    pub position: String,
-    #[serde(default = "default_true")]
-    pub always_on_top: bool,
-    #[serde(default = "default_opacity")]
-    pub opacity: f64,
-    #[serde(default = "default_theme")]
-    pub theme: String,
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AiderSourceConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// `.aider.chat.history.md` files, or the project directories containing them
+    #[serde(default)]
+    pub history_files: Vec<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct BehaviorConfig {
-    #[serde(default = "default_max_recent_tasks")]
-    pub max_recent_tasks: usize,
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CodexSourceConfig {
     #[serde(default)]
-    pub auto_hide: bool,
-    #[serde(default = "default_auto_hide_delay")]
-    pub auto_hide_delay_ms: u64,
-    #[serde(default = "default_stale_threshold")]
-    pub stale_task_threshold_ms: u64,
-    #[serde(default = "default_notification_duration")]
-    pub notification_duration_ms: u64,
-    #[serde(default = "default_debounce")]
-    pub file_watch_debounce_ms: u64,
+    pub enabled: bool,
+    /// Rollout directory; empty uses `$CODEX_HOME/sessions` or `~/.codex/sessions`
+    #[serde(default)]
+    pub sessions_dir: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ShortcutsConfig {
-    #[serde(default = "default_toggle_shortcut")]
-    pub toggle_visibility: String,
-    #[serde(default = "default_clear_shortcut")]
-    pub clear_tasks: String,
-    #[serde(default = "default_settings_shortcut")]
-    pub open_settings: String,
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GeminiSourceConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Telemetry `outfile` from Gemini CLI settings; empty uses `~/.gemini/telemetry.log`
+    #[serde(default)]
+    pub telemetry_file: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct PathsConfig {
-    #[serde(default = "default_events_file")]
-    pub events_file: String,
-    #[serde(default = "default_todos_dir")]
-    pub todos_dir: String,
-    #[serde(default = "default_database_file")]
-    pub database_file: String,
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ClineSourceConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Cline task directory; empty uses VS Code's global storage
+    #[serde(default)]
+    pub tasks_dir: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OpenCodeSourceConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// OpenCode storage directory; empty uses `~/.local/share/opencode/storage`
+    #[serde(default)]
+    pub storage_dir: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LogProgressConfig {
+    /// Log file to follow; a leading `~` is the home directory
+    pub file: String,
+    /// Regex with named groups `percent`, or `done` and `total`, and `message`
+    pub pattern: String,
+    /// Name shown on the bar; empty uses the file name
+    #[serde(default)]
+    pub label: String,
 }
 
 // Default value functions
@@ -78,32 +1032,140 @@ fn default_position() -> String { "bottom-right".to_string() }
 fn default_true() -> bool { true }
 fn default_opacity() -> f64 { 0.95 }
 fn default_theme() -> String { "dark".to_string() }
+fn default_backdrop() -> String { "none".to_string() }
 fn default_max_recent_tasks() -> usize { 10 }
 fn default_auto_hide_delay() -> u64 { 3000 }
 fn default_stale_threshold() -> u64 { 300_000 } // 5 minutes
+fn default_idle_after_mins() -> u64 { 10 }
 fn default_notification_duration() -> u64 { 2000 }
 fn default_debounce() -> u64 { 100 }
+fn default_watch_mode() -> String { "auto".to_string() }
 fn default_toggle_shortcut() -> String { "Ctrl+Shift+P".to_string() }
 fn default_clear_shortcut() -> String { "Ctrl+Shift+C".to_string() }
 fn default_settings_shortcut() -> String { "Ctrl+,".to_string() }
+fn default_dismiss_notification_shortcut() -> String { "Ctrl+Shift+D".to_string() }
+fn default_dismiss_all_notifications_shortcut() -> String { "Ctrl+Shift+X".to_string() }
+fn default_cycle_notifications_shortcut() -> String { "Ctrl+Shift+N".to_string() }
+fn default_issue_tracker_provider() -> String { "linear".to_string() }
+fn default_conflict_policy() -> String { "remote".to_string() }
+fn default_notion_title_property() -> String { "Name".to_string() }
+fn default_github_issues_mode() -> String { "session".to_string() }
+fn default_trigger_kind() -> String { "http".to_string() }
+fn default_trigger_method() -> String { "POST".to_string() }
+fn default_trigger_content_type() -> String { "application/json".to_string() }
+fn default_bridge_port() -> u16 { 47615 }
+fn default_relay_port() -> u16 { 47616 }
+fn default_core_port() -> u16 { 47617 }
+fn default_update_channel() -> String { "stable".to_string() }
+fn default_update_check_interval_hours() -> u64 { 24 }
+fn default_log_level() -> String { "debug".to_string() }
+fn default_log_max_size_mb() -> u64 { 10 }
+fn default_log_retained_files() -> usize { 3 }
+fn default_onboarding() -> OnboardingConfig { OnboardingConfig { completed: true } }
+
+fn default_attention_events() -> HashMap<String, String> {
+    HashMap::from([
+        ("task_error".to_string(), "informational".to_string()),
+        ("awaiting_input".to_string(), "critical".to_string()),
+    ])
+}
+fn default_priority_events() -> HashMap<String, String> {
+    HashMap::from([
+        ("task_error".to_string(), "high".to_string()),
+        ("awaiting_input".to_string(), "urgent".to_string()),
+    ])
+}
+fn default_priority_popup() -> String { "normal".to_string() }
+fn default_priority_push() -> String { "urgent".to_string() }
+fn default_process_sample_interval_secs() -> u64 { 5 }
+fn default_storage_check_interval_mins() -> u64 { 60 }
+fn default_storage_warn_mb() -> u64 { 2048 }
+fn default_sync_interval_secs() -> u64 { 300 }
+fn default_checkpoint_interval_secs() -> u64 { 60 }
+fn default_wal_idle_checkpoint_mb() -> u64 { 4 }
+fn default_wal_max_mb() -> u64 { 64 }
+fn default_context_window_tokens() -> u64 { 200_000 }
+fn default_context_warning_percent() -> f64 { 80.0 }
+fn default_usage_warning_display_secs() -> u64 { 30 }
+fn default_overdue_warn_secs() -> u64 { 120 }
+fn default_overdue_escalate_secs() -> u64 { 240 }
+fn default_overdue_thresholds() -> HashMap<String, OverdueThreshold> {
+    HashMap::from([("default".to_string(), OverdueThreshold::default())])
+}
+fn default_model_prices() -> Vec<ModelPrice> {
+    [("opus", 15.0, 75.0), ("sonnet", 3.0, 15.0), ("haiku", 1.0, 5.0)]
+        .into_iter()
+        .map(|(model, input_per_mtok, output_per_mtok)| ModelPrice {
+            model: model.to_string(),
+            input_per_mtok,
+            output_per_mtok,
+        })
+        .collect()
+}
+fn default_redaction_replacement() -> String { "[REDACTED]".to_string() }
+fn default_plugin_timeout_ms() -> u64 { 10_000 }
+fn default_plugin_rate_per_sec() -> f64 { 5.0 }
+fn default_plugin_burst() -> u32 { 20 }
+
+fn default_tts_templates() -> HashMap<String, String> {
+    [
+        ("task_error", "{{tool}} failed: {{description}}"),
+        ("session_stopped", "Session complete, {{tasks}} tasks"),
+    ]
+    .into_iter()
+    .map(|(event, template)| (event.to_string(), template.to_string()))
+    .collect()
+}
+
+/// Built-in plugins are granted what they need out of the box
+fn default_plugin_permissions() -> HashMap<String, Vec<Capability>> {
+    use Capability::{Filesystem, Network, Shell};
+    [
+        ("git-commits", vec![Filesystem]),
+        ("issue-tracker", vec![Network]),
+        ("todoist", vec![Network]),
+        ("notion", vec![Network, Filesystem]),
+        ("github-issues", vec![Network, Filesystem]),
+        ("automation", vec![Network]),
+        ("transcripts", vec![Filesystem]),
+        ("tts", vec![Shell]),
+    ]
+    .into_iter()
+    .map(|(name, capabilities)| (name.to_string(), capabilities))
+    .collect()
+}
+
+/// Where the hooks write events, inside a Claude config directory
+pub const EVENTS_FILE_NAME: &str = "progress-events.jsonl";
+/// Where the overlay writes requests for hooks and agents, inside Claude's config directory
+pub const REQUESTS_FILE_NAME: &str = "overlay-requests.jsonl";
+/// Where Claude keeps todo lists, inside its config directory
+pub const TODOS_DIR_NAME: &str = "todos";
+/// Where scripts write one download progress file per task, inside a Claude config directory
+pub const DOWNLOADS_DIR_NAME: &str = "downloads";
+/// Where any script can append progress lines, inside a Claude config directory
+pub const PROGRESS_DIR_NAME: &str = "progress";
 
 fn default_events_file() -> String {
     get_claude_dir()
-        .join("progress-events.jsonl")
+        .join(EVENTS_FILE_NAME)
         .to_string_lossy()
         .to_string()
 }
 
 fn default_todos_dir() -> String {
     get_claude_dir()
-        .join("todos")
+        .join(TODOS_DIR_NAME)
         .to_string_lossy()
         .to_string()
 }
 
 fn default_database_file() -> String {
-    get_claude_dir()
-        .join("overlay-history.db")
+    // Relative so a portable config keeps working when the drive letter changes
+    if portable_dir().is_some() {
+        return DATABASE_FILE_NAME.to_string();
+    }
+    get_database_path()
         .to_string_lossy()
         .to_string()
 }
@@ -115,6 +1177,28 @@ impl Default for Config {
             behavior: BehaviorConfig::default(),
             shortcuts: ShortcutsConfig::default(),
             paths: PathsConfig::default(),
+            plugins: PluginsConfig::default(),
+            rules: Vec::new(),
+            sources: SourcesConfig::default(),
+            bridge: BridgeConfig::default(),
+            relay: RelayConfig::default(),
+            core: CoreConfig::default(),
+            sync: SyncConfig::default(),
+            redaction: RedactionConfig::default(),
+            database: DatabaseConfig::default(),
+            context_usage: ContextUsageConfig::default(),
+            usage_warnings: UsageWarningsConfig::default(),
+            pricing: default_model_prices(),
+            overdue: OverdueConfig::default(),
+            awaiting_input: AwaitingInputConfig::default(),
+            attention: AttentionConfig::default(),
+            priority: PriorityConfig::default(),
+            processes: ProcessesConfig::default(),
+            storage: StorageConfig::default(),
+            openers: OpenersConfig::default(),
+            updates: UpdatesConfig::default(),
+            logging: LoggingConfig::default(),
+            onboarding: OnboardingConfig::default(),
         }
     }
 }
@@ -128,6 +1212,12 @@ impl Default for WindowConfig {
             always_on_top: default_true(),
             opacity: default_opacity(),
             theme: default_theme(),
+            all_workspaces: false,
+            workspaces: Vec::new(),
+            float_in_tiling_wms: default_true(),
+            layer_shell: default_true(),
+            macos_panel: false,
+            backdrop: default_backdrop(),
         }
     }
 }
@@ -141,6 +1231,16 @@ impl Default for BehaviorConfig {
             stale_task_threshold_ms: default_stale_threshold(),
             notification_duration_ms: default_notification_duration(),
             file_watch_debounce_ms: default_debounce(),
+            watch_mode: default_watch_mode(),
+            todo_max_age_days: 0,
+            archive_completed_todos: false,
+            collapse_duplicate_todos: false,
+            timezone: String::new(),
+            quiet_hours: String::new(),
+            idle_after_mins: default_idle_after_mins(),
+            screen_reader_announcements: true,
+            reduced_motion: false,
+            locale: String::new(),
         }
     }
 }
@@ -151,6 +1251,65 @@ impl Default for ShortcutsConfig {
             toggle_visibility: default_toggle_shortcut(),
             clear_tasks: default_clear_shortcut(),
             open_settings: default_settings_shortcut(),
+            dismiss_notification: default_dismiss_notification_shortcut(),
+            dismiss_all_notifications: default_dismiss_all_notifications_shortcut(),
+            cycle_notifications: default_cycle_notifications_shortcut(),
+        }
+    }
+}
+
+impl Default for GitCommitsPluginConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_true(),
+        }
+    }
+}
+
+impl Default for IssueTrackerPluginConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            provider: default_issue_tracker_provider(),
+            api_token: String::new(),
+            base_url: String::new(),
+            email: String::new(),
+            projects: Vec::new(),
+        }
+    }
+}
+
+impl Default for TodoistPluginConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            api_token: String::new(),
+            project_id: String::new(),
+            conflict_policy: default_conflict_policy(),
+        }
+    }
+}
+
+impl Default for NotionPluginConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            api_token: String::new(),
+            database_id: String::new(),
+            title_property: default_notion_title_property(),
+        }
+    }
+}
+
+impl Default for GitHubIssuesPluginConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            token: String::new(),
+            repo: String::new(),
+            mode: default_github_issues_mode(),
+            project_path: String::new(),
+            labels: Vec::new(),
         }
     }
 }
@@ -158,23 +1317,265 @@ impl Default for ShortcutsConfig {
 impl Default for PathsConfig {
     fn default() -> Self {
         Self {
+            claude_dir: String::new(),
             events_file: default_events_file(),
             todos_dir: default_todos_dir(),
             database_file: default_database_file(),
+            profiles: Vec::new(),
         }
     }
 }
 
-/// Get the .claude directory path
+/// Claude's config directory: `CLAUDE_CONFIG_DIR` if set, otherwise `~/.claude`
+///
+/// `Config::claude_dir` also applies the config file's override.
 pub fn get_claude_dir() -> PathBuf {
+    std::env::var_os(CLAUDE_CONFIG_DIR_ENV)
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(home_claude_dir)
+}
+
+/// `~/.claude`, where Claude's files are unless moved and where older
+/// versions of the overlay kept their own
+fn home_claude_dir() -> PathBuf {
     dirs::home_dir()
         .unwrap_or_else(|| PathBuf::from("."))
         .join(".claude")
 }
 
-/// Get the config file path
+/// Where the overlay's config file lives
+///
+/// `$XDG_CONFIG_HOME/agent-progress/config.toml` (usually under `~/.config`)
+/// on Linux, `%APPDATA%\agent-progress\config.toml` on Windows, and
+/// `~/.claude/overlay-config.toml` on macOS.
 pub fn get_config_path() -> PathBuf {
-    get_claude_dir().join("overlay-config.toml")
+    if let Some(dir) = portable_dir() {
+        return dir.join(CONFIG_FILE_NAME);
+    }
+    match platform_dir(dirs::config_dir()) {
+        Some(dir) => dir.join(CONFIG_FILE_NAME),
+        None => home_claude_dir().join(LEGACY_CONFIG_FILE_NAME),
+    }
+}
+
+/// Default location of the history database
+///
+/// `$XDG_DATA_HOME/agent-progress/history.db` (usually under `~/.local/share`)
+/// on Linux, `%APPDATA%\agent-progress\history.db` on Windows, and
+/// `~/.claude/overlay-history.db` on macOS.
+pub fn get_database_path() -> PathBuf {
+    if let Some(dir) = portable_dir() {
+        return dir.join(DATABASE_FILE_NAME);
+    }
+    match platform_dir(dirs::data_dir()) {
+        Some(dir) => dir.join(DATABASE_FILE_NAME),
+        None => legacy_database_path(),
+    }
+}
+
+/// The file holding the token frontends present to the core
+pub fn get_core_token_path() -> PathBuf {
+    data_file(CORE_TOKEN_FILE_NAME)
+}
+
+/// The file holding the token editor extensions present to the bridge, unless `bridge.token` is set
+pub fn get_bridge_token_path() -> PathBuf {
+    data_file(BRIDGE_TOKEN_FILE_NAME)
+}
+
+/// A file of the overlay's own, next to the default history database
+fn data_file(name: &str) -> PathBuf {
+    get_database_path()
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_else(std::env::temp_dir)
+        .join(name)
+}
+
+/// Where the overlay keeps its files when running portable: `data` next to the executable
+///
+/// Portable mode is on when the app is started with `--portable` or a file
+/// named `portable` sits next to the executable, e.g. on a USB stick.
+pub fn portable_dir() -> Option<PathBuf> {
+    static PORTABLE_DIR: OnceLock<Option<PathBuf>> = OnceLock::new();
+    PORTABLE_DIR
+        .get_or_init(|| {
+            let exe = std::env::current_exe().ok()?;
+            portable_dir_for(std::env::args().skip(1), exe.parent()?)
+        })
+        .clone()
+}
+
+fn portable_dir_for(mut args: impl Iterator<Item = String>, exe_dir: &Path) -> Option<PathBuf> {
+    let portable = args.any(|arg| arg == PORTABLE_FLAG) || exe_dir.join(PORTABLE_MARKER).is_file();
+    portable.then(|| exe_dir.join(PORTABLE_DATA_DIR))
+}
+
+/// A configured path as a usable path; relative ones are relative to the
+/// portable data directory in portable mode, so they move with the drive
+fn resolve_path(path: &str) -> PathBuf {
+    match portable_dir() {
+        Some(dir) if Path::new(path).is_relative() => dir.join(path),
+        _ => PathBuf::from(path),
+    }
+}
+
+/// The overlay's directory under a platform base directory, on platforms that use one
+fn platform_dir(base: Option<PathBuf>) -> Option<PathBuf> {
+    // `dirs` gives the XDG directories on Linux and the roaming AppData folder on Windows
+    base.filter(|_| cfg!(any(target_os = "linux", windows))).map(|base| base.join(APP_DIR_NAME))
+}
+
+/// Where the overlay's files were before they moved to the platform directories
+fn legacy_database_path() -> PathBuf {
+    home_claude_dir().join(LEGACY_DATABASE_FILE_NAME)
+}
+
+/// Move the config and database out of `~/.claude` into the platform directories
+///
+/// Runs before anything opens them. A file is only moved if nothing is at the
+/// new location yet, so this is a no-op after the first run.
+pub fn migrate_legacy_files() {
+    // A portable copy starts fresh rather than taking this machine's files with it
+    if portable_dir().is_some() {
+        return;
+    }
+    let legacy_config = home_claude_dir().join(LEGACY_CONFIG_FILE_NAME);
+    let legacy_database = legacy_database_path();
+    let database = get_database_path();
+
+    let mut moves = vec![(legacy_config, get_config_path())];
+    // The write-ahead log and shared memory files travel with the database
+    for suffix in ["", "-wal", "-shm"] {
+        moves.push((
+            PathBuf::from(format!("{}{}", legacy_database.display(), suffix)),
+            PathBuf::from(format!("{}{}", database.display(), suffix)),
+        ));
+    }
+    migrate_files(&moves);
+}
+
+fn migrate_files(moves: &[(PathBuf, PathBuf)]) {
+    for (from, to) in moves {
+        if from == to || !from.exists() || to.exists() {
+            continue;
+        }
+        match move_file(from, to) {
+            Ok(()) => tracing::info!("Moved {:?} to {:?}", from, to),
+            Err(e) => tracing::warn!("Failed to move {:?} to {:?}: {}", from, to, e),
+        }
+    }
+}
+
+/// Rename, falling back to copy and delete across filesystems
+fn move_file(from: &Path, to: &Path) -> std::io::Result<()> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    fs::copy(from, to)?;
+    fs::remove_file(from)
+}
+
+/// Directories configured files must live in: the home directory, Claude's
+/// directory, and the overlay's own directories, plus any listed in
+/// `AGENT_PROGRESS_ALLOWED_ROOTS`
+///
+/// Extra roots come from the environment rather than the config file so that
+/// editing the config alone can't widen them.
+pub fn allowed_roots() -> Vec<PathBuf> {
+    let mut roots: Vec<PathBuf> = dirs::home_dir().into_iter().collect();
+    roots.push(get_claude_dir());
+    for file in [get_config_path(), get_database_path()] {
+        if let Some(dir) = file.parent() {
+            // Canonicalizing below needs the directory to exist
+            let _ = fs::create_dir_all(dir);
+            roots.push(dir.to_path_buf());
+        }
+    }
+    if cfg!(windows) {
+        // OneDrive can redirect Documents and Desktop outside the profile, even to another drive
+        roots.extend(dirs::document_dir());
+        roots.extend(dirs::desktop_dir());
+        for var in ONEDRIVE_ENVS {
+            roots.extend(std::env::var_os(var).map(PathBuf::from).filter(|p| p.is_absolute()));
+        }
+    }
+    if let Some(extra) = std::env::var_os(ALLOWED_ROOTS_ENV) {
+        roots.extend(std::env::split_paths(&extra).filter(|p| p.is_absolute()));
+    }
+    roots.iter().filter_map(|root| root.canonicalize().ok()).collect()
+}
+
+/// Variables the OneDrive client sets to the folders it syncs
+const ONEDRIVE_ENVS: [&str; 3] = ["OneDrive", "OneDriveConsumer", "OneDriveCommercial"];
+
+/// Paths at least this long need the `\\?\` prefix on Windows
+const MAX_PATH: usize = 260;
+
+/// `path` without a Windows `\\?\` prefix, so it compares equal to the same path written normally
+///
+/// `canonicalize` adds the prefix on Windows, and users pasting long paths may too.
+fn strip_verbatim(path: &str) -> String {
+    if let Some(unc) = path.strip_prefix(r"\\?\UNC\") {
+        format!(r"\\{}", unc)
+    } else if let Some(rest) = path.strip_prefix(r"\\?\") {
+        rest.to_string()
+    } else {
+        path.to_string()
+    }
+}
+
+/// `path` with the `\\?\` prefix if it's too long for the Windows APIs SQLite and the watcher use
+fn long_path(path: &Path) -> PathBuf {
+    if cfg!(windows) {
+        PathBuf::from(to_verbatim(&path.to_string_lossy()))
+    } else {
+        path.to_path_buf()
+    }
+}
+
+fn to_verbatim(path: &str) -> String {
+    let is_absolute = path.as_bytes().get(1) == Some(&b':') || path.starts_with(r"\\");
+    if path.len() < MAX_PATH || !is_absolute || path.starts_with(r"\\?\") {
+        return path.to_string();
+    }
+    // Verbatim paths skip normalization, so separators must already be backslashes
+    let path = path.replace('/', r"\");
+    match path.strip_prefix(r"\\") {
+        Some(unc) => format!(r"\\?\UNC\{}", unc),
+        None => format!(r"\\?\{}", path),
+    }
+}
+
+/// Whether `path` resolves inside one of `roots`, which must already be canonical
+///
+/// Symlinks and `..` are resolved as far as the path exists, so a file that
+/// hasn't been created yet is judged by its nearest existing parent.
+fn path_allowed(path: &Path, roots: &[PathBuf]) -> bool {
+    let absolute = match std::env::current_dir() {
+        Ok(cwd) if path.is_relative() => cwd.join(path),
+        _ => path.to_path_buf(),
+    };
+
+    let mut existing = absolute.as_path();
+    let mut missing = Vec::new();
+    let resolved = loop {
+        if let Ok(canonical) = existing.canonicalize() {
+            break missing.iter().rev().fold(canonical, |path, part| path.join(part));
+        }
+        // `..` below a missing directory can't be resolved safely
+        let (Some(name), Some(parent)) = (existing.file_name(), existing.parent()) else {
+            return false;
+        };
+        missing.push(name);
+        existing = parent;
+    };
+
+    roots.iter().any(|root| resolved.starts_with(root))
 }
 
 impl Config {
@@ -186,8 +1587,15 @@ impl Config {
             let content = fs::read_to_string(&path)
                 .map_err(|e| ConfigError::ReadError(e.to_string()))?;
 
-            let config: Config = toml::from_str(&content)
+            let mut config: Config = toml::from_str(&content)
                 .map_err(|e| ConfigError::ParseError(e.to_string()))?;
+            for path in [&mut config.paths.events_file, &mut config.paths.todos_dir, &mut config.paths.database_file] {
+                *path = strip_verbatim(path);
+            }
+            config.relocate_legacy_paths();
+            // Sandboxed first, so paths are never rebased into a directory that isn't allowed
+            config.sandbox_paths(&allowed_roots());
+            config.rebase_claude_paths();
 
             tracing::info!("Loaded config from {:?}", path);
             Ok(config)
@@ -204,6 +1612,7 @@ impl Config {
 
     /// Save configuration to file
     pub fn save(&self) -> Result<(), ConfigError> {
+        self.check_paths()?;
         let path = get_config_path();
 
         // Ensure parent directory exists
@@ -222,19 +1631,165 @@ impl Config {
         Ok(())
     }
 
+    /// Fail if a configured file path resolves outside the allowed roots
+    pub fn check_paths(&self) -> Result<(), ConfigError> {
+        let roots = allowed_roots();
+        let paths = [
+            &self.paths.claude_dir,
+            &self.paths.events_file,
+            &self.paths.todos_dir,
+            &self.paths.database_file,
+            &self.logging.file,
+        ];
+        let profiles = self.paths.profiles.iter().map(|profile| &profile.claude_dir);
+        for path in paths.into_iter().chain(profiles).filter(|path| !path.is_empty()) {
+            if !path_allowed(&resolve_path(path), &roots) {
+                return Err(ConfigError::PathNotAllowed(path.clone()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Point a database path saved before the move to the platform directories at the new default
+    ///
+    /// Saved configs spell out every path, so one written by an older version
+    /// names the old database location even though the file has been moved.
+    fn relocate_legacy_paths(&mut self) {
+        let legacy = legacy_database_path();
+        if portable_dir().is_none() && Path::new(&self.paths.database_file) == legacy && !legacy.exists() {
+            self.paths.database_file = default_database_file();
+        }
+    }
+
+    /// Claude's config directory, from the config file, `CLAUDE_CONFIG_DIR`, or `~/.claude`
+    pub fn claude_dir(&self) -> PathBuf {
+        if self.paths.claude_dir.trim().is_empty() {
+            get_claude_dir()
+        } else {
+            resolve_path(self.paths.claude_dir.trim())
+        }
+    }
+
+    /// Move paths under `~/.claude` into Claude's actual directory when it has been relocated
+    ///
+    /// A saved config spells out the default paths, so without this it would
+    /// keep reading `~/.claude` after Claude's files moved elsewhere.
+    fn rebase_claude_paths(&mut self) {
+        let (from, to) = (home_claude_dir(), self.claude_dir());
+        if from == to {
+            return;
+        }
+        for path in [&mut self.paths.events_file, &mut self.paths.todos_dir] {
+            if let Ok(rest) = Path::new(path.as_str()).strip_prefix(&from) {
+                *path = to.join(rest).to_string_lossy().to_string();
+            }
+        }
+    }
+
+    /// Replace file paths outside `roots` with their defaults
+    fn sandbox_paths(&mut self, roots: &[PathBuf]) {
+        let paths = &mut self.paths;
+        if !paths.claude_dir.trim().is_empty() && !path_allowed(&resolve_path(paths.claude_dir.trim()), roots) {
+            tracing::error!("Ignoring Claude directory {:?} outside allowed roots; using {:?}", paths.claude_dir, get_claude_dir());
+            paths.claude_dir.clear();
+        }
+        for (path, default) in [
+            (&mut paths.events_file, default_events_file as fn() -> String),
+            (&mut paths.todos_dir, default_todos_dir),
+            (&mut paths.database_file, default_database_file),
+        ] {
+            if !path_allowed(&resolve_path(path), roots) {
+                let fallback = default();
+                tracing::error!("Ignoring configured path {:?} outside allowed roots; using {:?}", path, fallback);
+                *path = fallback;
+            }
+        }
+        let log_file = &mut self.logging.file;
+        if !log_file.trim().is_empty() && !path_allowed(&resolve_path(log_file), roots) {
+            tracing::error!("Ignoring log file {:?} outside allowed roots; using the default", log_file);
+            log_file.clear();
+        }
+        paths.profiles.retain(|profile| {
+            let allowed = path_allowed(&resolve_path(&profile.claude_dir), roots);
+            if !allowed {
+                tracing::error!("Ignoring profile {:?}: {:?} is outside allowed roots", profile.name, profile.claude_dir);
+            }
+            allowed
+        });
+    }
+
+    /// The main Claude directory followed by each profile's
+    pub fn claude_dirs(&self) -> Vec<PathBuf> {
+        let mut dirs = vec![self.claude_dir()];
+        dirs.extend(self.profile_dirs().into_iter().map(|(_, dir)| dir));
+        dirs
+    }
+
+    /// Each profile's name and Claude directory, skipping blank entries
+    ///
+    /// A profile repeating an earlier name or directory, including the main
+    /// Claude directory, is skipped so nothing is watched twice.
+    pub fn profile_dirs(&self) -> Vec<(String, PathBuf)> {
+        let mut names = HashSet::new();
+        let mut dirs = HashSet::from([long_path(&self.claude_dir())]);
+        let mut profiles = Vec::new();
+        for profile in &self.paths.profiles {
+            let (name, dir) = (profile.name.trim(), profile.claude_dir.trim());
+            if name.is_empty() || dir.is_empty() {
+                continue;
+            }
+            let dir = long_path(&resolve_path(dir));
+            if !names.insert(name.to_string()) || !dirs.insert(dir.clone()) {
+                tracing::warn!("Skipping profile {:?}: its name or directory is already watched", name);
+                continue;
+            }
+            profiles.push((name.to_string(), dir));
+        }
+        profiles
+    }
+
     /// Get events file path as PathBuf
     pub fn events_path(&self) -> PathBuf {
-        PathBuf::from(&self.paths.events_file)
+        long_path(&resolve_path(&self.paths.events_file))
     }
 
     /// Get todos directory path as PathBuf
     pub fn todos_path(&self) -> PathBuf {
-        PathBuf::from(&self.paths.todos_dir)
+        long_path(&resolve_path(&self.paths.todos_dir))
+    }
+
+    /// Todos directory of the main Claude directory, or of the named profile
+    pub fn todos_path_for(&self, profile: Option<&str>) -> Option<PathBuf> {
+        match profile {
+            None => Some(self.todos_path()),
+            Some(name) => self.profile_dirs()
+                .into_iter()
+                .find(|(profile, _)| profile == name)
+                .map(|(_, dir)| dir.join(TODOS_DIR_NAME)),
+        }
     }
 
     /// Get database file path as PathBuf
     pub fn database_path(&self) -> PathBuf {
-        PathBuf::from(&self.paths.database_file)
+        long_path(&resolve_path(&self.paths.database_file))
+    }
+
+    /// The overlay's log file
+    pub fn log_path(&self) -> PathBuf {
+        match self.logging.file.trim() {
+            "" => get_database_path()
+                .parent()
+                .map(Path::to_path_buf)
+                .unwrap_or_else(std::env::temp_dir)
+                .join("logs")
+                .join("overlay.log"),
+            file => long_path(&resolve_path(file)),
+        }
+    }
+
+    /// File the overlay appends re-run and other requests to
+    pub fn requests_path(&self) -> PathBuf {
+        long_path(&self.claude_dir().join(REQUESTS_FILE_NAME))
     }
 }
 
@@ -244,6 +1799,7 @@ pub enum ConfigError {
     WriteError(String),
     ParseError(String),
     SerializeError(String),
+    PathNotAllowed(String),
 }
 
 impl std::fmt::Display for ConfigError {
@@ -253,6 +1809,7 @@ impl std::fmt::Display for ConfigError {
             ConfigError::WriteError(e) => write!(f, "Failed to write config: {}", e),
             ConfigError::ParseError(e) => write!(f, "Failed to parse config: {}", e),
             ConfigError::SerializeError(e) => write!(f, "Failed to serialize config: {}", e),
+            ConfigError::PathNotAllowed(e) => write!(f, "Path not allowed: {}", e),
         }
     }
 }
@@ -278,4 +1835,147 @@ mod tests {
         let parsed: Config = toml::from_str(&toml_str).unwrap();
         assert_eq!(parsed.window.width, config.window.width);
     }
+
+    #[test]
+    fn test_existing_configs_skip_onboarding() {
+        assert!(!Config::default().onboarding.completed);
+        let parsed: Config = toml::from_str("[window]\nwidth = 300\n").unwrap();
+        assert!(parsed.onboarding.completed);
+    }
+
+    #[test]
+    fn test_plugin_grants_merge_over_defaults() {
+        let parsed: Config = toml::from_str("[plugins.permissions]\ntts = []\nmy-plugin = [\"network\"]\n").unwrap();
+        assert!(parsed.plugins.granted("tts").is_empty());
+        assert_eq!(parsed.plugins.granted("my-plugin"), [Capability::Network]);
+        // Plugins left out keep what they are granted by default
+        assert_eq!(parsed.plugins.granted("todoist"), [Capability::Network]);
+        assert!(parsed.plugins.granted("unknown").is_empty());
+    }
+
+    #[test]
+    fn test_path_allowed() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        let roots = [root.clone()];
+        std::fs::create_dir(root.join("claude")).unwrap();
+
+        assert!(path_allowed(&root.join("claude").join("events.jsonl"), &roots));
+        assert!(path_allowed(&root.join("new").join("dir").join("history.db"), &roots));
+        assert!(!path_allowed(&root.join("claude").join("..").join("..").join("etc"), &roots));
+        assert!(!path_allowed(&root.join("new").join("..").join("..").join("x"), &roots));
+        assert!(!path_allowed(Path::new("/etc/passwd"), &roots));
+    }
+
+    #[test]
+    fn test_sandbox_claude_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        let mut config = Config::default();
+        config.paths.claude_dir = root.join(".claude").to_string_lossy().to_string();
+        config.paths.events_file = root.join("events.jsonl").to_string_lossy().to_string();
+        config.paths.todos_dir = root.join("todos").to_string_lossy().to_string();
+        config.paths.database_file = root.join("history.db").to_string_lossy().to_string();
+
+        config.sandbox_paths(&[root.clone()]);
+        assert_eq!(config.claude_dir(), root.join(".claude"));
+
+        config.paths.claude_dir = outside.path().to_string_lossy().to_string();
+        config.sandbox_paths(&[root]);
+        assert!(config.paths.claude_dir.is_empty());
+        assert_eq!(config.claude_dir(), get_claude_dir());
+    }
+
+    #[test]
+    fn test_windows_long_paths() {
+        assert_eq!(strip_verbatim(r"\\?\C:\Users\me\history.db"), r"C:\Users\me\history.db");
+        assert_eq!(strip_verbatim(r"\\?\UNC\server\share\x"), r"\\server\share\x");
+        assert_eq!(strip_verbatim("/home/me/history.db"), "/home/me/history.db");
+
+        let long = format!(r"C:\Users\me\{}/history.db", "nested\\".repeat(40));
+        assert_eq!(to_verbatim(&long), format!(r"\\?\{}", long.replace('/', r"\")));
+        assert_eq!(to_verbatim(r"C:\short.db"), r"C:\short.db");
+        assert_eq!(to_verbatim(&format!(r"\\server\share\{}", "x".repeat(300))), format!(r"\\?\UNC\server\share\{}", "x".repeat(300)));
+    }
+
+    #[test]
+    fn test_rebase_claude_paths() {
+        let mut config = Config::default();
+        let home_claude = home_claude_dir();
+        config.paths.claude_dir = "/work/claude-profile".to_string();
+        config.paths.events_file = home_claude.join("progress-events.jsonl").to_string_lossy().to_string();
+        config.paths.todos_dir = "/elsewhere/todos".to_string();
+
+        config.rebase_claude_paths();
+        assert_eq!(config.events_path(), Path::new("/work/claude-profile/progress-events.jsonl"));
+        // Paths the user pointed somewhere else are left alone
+        assert_eq!(config.paths.todos_dir, "/elsewhere/todos");
+    }
+
+    #[test]
+    fn test_profile_dirs() {
+        let mut config = Config::default();
+        config.paths.claude_dir = "/home/me/.claude".to_string();
+        config.paths.profiles = toml::from_str::<PathsConfig>(r#"
+            [[profiles]]
+            name = "work"
+            claude_dir = "/home/me/.claude-work"
+
+            [[profiles]]
+            name = "work"
+            claude_dir = "/home/me/.claude-other"
+
+            [[profiles]]
+            name = "main-again"
+            claude_dir = "/home/me/.claude"
+
+            [[profiles]]
+            name = ""
+            claude_dir = "/home/me/.claude-blank"
+        "#).unwrap().profiles;
+
+        assert_eq!(config.profile_dirs(), [("work".to_string(), PathBuf::from("/home/me/.claude-work"))]);
+
+        // Profiles survive a save and reload
+        let parsed: Config = toml::from_str(&toml::to_string_pretty(&config).unwrap()).unwrap();
+        assert_eq!(parsed.paths.profiles.len(), 4);
+    }
+
+    #[test]
+    fn test_portable_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let args = |list: &[&str]| list.iter().map(|a| a.to_string()).collect::<Vec<_>>().into_iter();
+
+        assert_eq!(portable_dir_for(args(&[]), dir.path()), None);
+        assert_eq!(portable_dir_for(args(&["--portable"]), dir.path()), Some(dir.path().join("data")));
+
+        std::fs::write(dir.path().join(PORTABLE_MARKER), "").unwrap();
+        assert_eq!(portable_dir_for(args(&[]), dir.path()), Some(dir.path().join("data")));
+    }
+
+    #[test]
+    fn test_migrate_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let legacy = dir.path().join(".claude");
+        let data = dir.path().join("share").join(APP_DIR_NAME);
+        std::fs::create_dir(&legacy).unwrap();
+        std::fs::write(legacy.join(LEGACY_DATABASE_FILE_NAME), "db").unwrap();
+        std::fs::write(legacy.join(LEGACY_CONFIG_FILE_NAME), "old").unwrap();
+        std::fs::create_dir_all(&data).unwrap();
+        std::fs::write(data.join(CONFIG_FILE_NAME), "new").unwrap();
+
+        migrate_files(&[
+            (legacy.join(LEGACY_DATABASE_FILE_NAME), data.join(DATABASE_FILE_NAME)),
+            (legacy.join(LEGACY_CONFIG_FILE_NAME), data.join(CONFIG_FILE_NAME)),
+            (legacy.join("missing-wal"), data.join("history.db-wal")),
+        ]);
+
+        // Moved where nothing was in the way; an existing file is left alone
+        assert_eq!(std::fs::read_to_string(data.join(DATABASE_FILE_NAME)).unwrap(), "db");
+        assert!(!legacy.join(LEGACY_DATABASE_FILE_NAME).exists());
+        assert_eq!(std::fs::read_to_string(data.join(CONFIG_FILE_NAME)).unwrap(), "new");
+        assert!(legacy.join(LEGACY_CONFIG_FILE_NAME).exists());
+        assert!(!data.join("history.db-wal").exists());
+    }
 }
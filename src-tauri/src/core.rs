@@ -0,0 +1,485 @@
+//! The overlay's core: router, plugins, bridge, sync, maintenance, process sampling and the file watcher
+//!
+//! The core runs as a process of its own, `--headless`, which keeps recording
+//! while no overlay is open. Overlays attach to it over [`crate::ipc`] and
+//! start it if it isn't running; the login service installed by
+//! [`crate::service`] keeps it running from login instead. Whatever runs the
+//! core passes a [`CoreSink`] for what it produces. An overlay runs the core
+//! itself only when it reads events from `--stdin` or can't start the daemon.
+
+use std::collections::HashMap;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+use crate::bridge;
+use crate::clock::Clock;
+use crate::config::{self, Config};
+use crate::ipc;
+use crate::logs;
+use crate::maintenance;
+use crate::plugins;
+use crate::processes;
+use crate::router::{EventRouter, OverdueTask, PluginManager, ReconciledSnapshot};
+use crate::storage::{self, StorageUsage};
+use crate::store::{self, EventStore};
+use crate::supervisor;
+use crate::sync;
+use crate::watcher::{FileWatcher, TodoGroup, WatcherEvent};
+
+/// Command-line flag that runs the core without windows
+const HEADLESS_FLAG: &str = "--headless";
+/// Command-line flag that asks a running core to shut down
+const STOP_FLAG: &str = "--stop-core";
+
+/// Key the watcher's events-file offset is saved under at a clean exit
+const EVENTS_OFFSET_KEY: &str = "watcher:events_offset";
+
+/// How long shutdown waits for queued events to be stored, and again for plugins to receive them
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How often to look for tasks that have been active too long
+const STALE_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Whether the app was started with `--headless`
+pub fn headless_requested(mut args: impl Iterator<Item = String>) -> bool {
+    args.any(|arg| arg == HEADLESS_FLAG)
+}
+
+/// Whether the app was started with `--stop-core`
+pub fn stop_requested(mut args: impl Iterator<Item = String>) -> bool {
+    args.any(|arg| arg == STOP_FLAG)
+}
+
+/// Start this executable as a headless core that outlives the overlay
+pub fn spawn_headless() -> std::io::Result<()> {
+    let mut command = Command::new(std::env::current_exe()?);
+    command.arg(HEADLESS_FLAG).stdin(Stdio::null()).stdout(Stdio::null()).stderr(Stdio::null());
+    // The core shares the overlay's files, wherever those are
+    if config::portable_dir().is_some() {
+        command.arg(config::PORTABLE_FLAG);
+    }
+    // Detached, so closing the overlay or its terminal doesn't take the core with it
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        command.process_group(0);
+    }
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        const DETACHED_PROCESS: u32 = 0x0000_0008;
+        const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+        command.creation_flags(DETACHED_PROCESS | CREATE_NO_WINDOW);
+    }
+    let mut child = command.spawn()?;
+    // Reap it if it exits while the overlay is still running
+    std::thread::spawn(move || child.wait());
+    Ok(())
+}
+
+/// Where the core's output goes
+pub trait CoreSink: Send + Sync + 'static {
+    /// An event the router has processed, ready to show
+    fn event(&self, event: WatcherEvent);
+
+    /// Every session's todos, grouped, right after the `TodosUpdated` they came from
+    fn todo_groups(&self, groups: Vec<TodoGroup>);
+
+    /// What startup reconciliation found
+    fn reconciled(&self, snapshot: &ReconciledSnapshot);
+
+    /// A task that just ran past its tool's threshold
+    fn overdue(&self, task: &OverdueTask);
+
+    /// Claude's files and the history just grew past `storage.warn_mb`
+    fn storage_warning(&self, usage: &StorageUsage);
+
+    /// Show, hide or toggle the overlay for the editor bridge, returning whether it was handled
+    fn window_action(&self, action: &str) -> bool;
+}
+
+/// Key for a profile's events-file offset; the main directory's is [`EVENTS_OFFSET_KEY`]
+fn events_offset_key(profile: Option<&str>) -> String {
+    match profile {
+        Some(profile) => format!("{}:{}", EVENTS_OFFSET_KEY, profile),
+        None => EVENTS_OFFSET_KEY.to_string(),
+    }
+}
+
+/// The offsets saved by the last clean exit, by profile
+///
+/// They are cleared once read, so after a crash the watcher starts from the
+/// end of each file rather than replaying from an old position.
+pub fn take_events_offsets(store: &EventStore, config: &Config) -> HashMap<Option<String>, u64> {
+    let profiles = config.profile_dirs().into_iter().map(|(name, _)| Some(name));
+    std::iter::once(None)
+        .chain(profiles)
+        .filter_map(|profile| {
+            let offset = take_events_offset(store, &events_offset_key(profile.as_deref()))?;
+            Some((profile, offset))
+        })
+        .collect()
+}
+
+fn take_events_offset(store: &EventStore, key: &str) -> Option<u64> {
+    let offset = store.get_sync_state(key).unwrap_or_else(|e| {
+        tracing::error!("Failed to load watcher offset: {}", e);
+        None
+    });
+    if offset.is_some() {
+        if let Err(e) = store.set_sync_state(key, -1) {
+            tracing::error!("Failed to clear watcher offset: {}", e);
+        }
+    }
+    offset.filter(|offset| *offset >= 0).map(|offset| offset as u64)
+}
+
+/// The running core, kept so exit can stop it cleanly
+pub struct Core {
+    store: Arc<EventStore>,
+    clock: Arc<dyn Clock>,
+    events_offsets: Vec<(Option<String>, Arc<AtomicU64>)>,
+    stop_consumer: watch::Sender<bool>,
+    consumer: Option<JoinHandle<()>>,
+    stop_plugins: watch::Sender<bool>,
+    plugins: JoinHandle<()>,
+}
+
+impl Core {
+    /// Start the router, plugins, bridge, sync, and file watcher on the current runtime
+    pub fn start(
+        config: Arc<Config>,
+        store: Arc<EventStore>,
+        resume_at: HashMap<Option<String>, u64>,
+        sink: Arc<dyn CoreSink>,
+    ) -> Self {
+        // Create event router
+        let router = Arc::new(EventRouter::new(store.clone(), config.clone()));
+
+        // Start plugins, rebuilding them from the config if the manager fails
+        let (stop_plugins, plugins_stopped) = watch::channel(false);
+        let plugin_router = router.clone();
+        let plugin_config = config.clone();
+        let plugin_store = store.clone();
+        let plugins = tokio::spawn(supervisor::supervise("plugins", supervisor::RestartPolicy::default(), move || {
+            let mut plugin_manager = PluginManager::new(&plugin_router);
+            plugins::register_builtin(&mut plugin_manager, &plugin_config, plugin_store.clone());
+            let stopped = plugins_stopped.clone();
+            async move {
+                if let Err(e) = plugin_manager.init_all().await {
+                    tracing::error!("Failed to initialize plugins: {}", e);
+                }
+                plugin_manager.run(stopped).await;
+                Ok(())
+            }
+        }));
+
+        // Start the editor bridge
+        if config.bridge.enabled {
+            let window_sink = sink.clone();
+            let window_actions: bridge::WindowActionHandler = Arc::new(move |action| window_sink.window_action(action));
+            bridge::start(config.bridge.clone(), router.clone(), store.clone(), window_actions);
+        }
+
+        // Start history sync
+        if config.sync.enabled {
+            sync::start(config.sync.clone(), store.clone());
+        }
+
+        // Keep the write-ahead log from growing unbounded
+        maintenance::start(config.database.clone(), store.clone(), router.clock());
+
+        // Keep background shells' resource use current on their tasks
+        processes::start(config.processes.clone(), store.clone(), router.clock());
+
+        // Suggest a cleanup when Claude's files and the history take too much disk
+        storage::start(config.clone(), sink.clone(), router.clock());
+
+        // Reconcile what a previous run left behind, then keep flagging overdue
+        // tasks and canceling those whose end event never arrived
+        let stale_router = router.clone();
+        let stale_sink = sink.clone();
+        tokio::spawn(async move {
+            let router = stale_router.clone();
+            match tokio::task::spawn_blocking(move || router.reconcile()).await {
+                Ok(snapshot) => {
+                    tracing::info!(
+                        "Reconciled history: {} active, {} stale",
+                        snapshot.active.len(),
+                        snapshot.expired.len()
+                    );
+                    stale_sink.reconciled(&snapshot);
+                }
+                Err(e) => tracing::error!("Startup reconciliation failed: {}", e),
+            }
+
+            let mut ticker = tokio::time::interval(STALE_CHECK_INTERVAL);
+            // The first tick fires immediately, and reconciling just covered it
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                // Overdue tasks go first, so one is flagged before it is given up on
+                let router = stale_router.clone();
+                match tokio::task::spawn_blocking(move || router.check_overdue_tasks()).await {
+                    Ok(overdue) => {
+                        for task in overdue {
+                            stale_sink.overdue(&task);
+                        }
+                    }
+                    Err(e) => tracing::error!("Overdue task check failed: {}", e),
+                }
+
+                let router = stale_router.clone();
+                match tokio::task::spawn_blocking(move || router.expire_stale_tasks()).await {
+                    Ok(expired) => {
+                        for task_event in expired {
+                            stale_sink.event(WatcherEvent::TaskEvent(task_event));
+                        }
+                    }
+                    Err(e) => tracing::error!("Stale task check failed: {}", e),
+                }
+            }
+        });
+
+        // Start file watcher
+        let watcher = FileWatcher::new(&config, &resume_at);
+        let events_offsets = watcher.events_offsets();
+        let (stop_consumer, mut consumer_stopped) = watch::channel(false);
+        let clock = router.clock();
+
+        let consumer = match watcher.start() {
+            Ok(mut rx) => {
+                tracing::info!("File watcher started successfully");
+
+                Some(tokio::spawn(async move {
+                    loop {
+                        let event = tokio::select! {
+                            event = rx.recv() => match event {
+                                Some(event) => event,
+                                None => break,
+                            },
+                            _ = consumer_stopped.changed() => {
+                                // Store what's already queued; the core is going away
+                                while let Ok(event) = rx.try_recv() {
+                                    let router = router.clone();
+                                    let _ = tokio::task::spawn_blocking(move || router.process_watcher_event(event)).await;
+                                }
+                                break;
+                            }
+                        };
+
+                        // Process through router (redacts and stores to DB); SQLite
+                        // writes block, so they go to the blocking pool in order
+                        let router = router.clone();
+                        let processed = tokio::task::spawn_blocking(move || {
+                            // Groups show every session's todos; only the flat list is collapsed
+                            match router.process_watcher_event(event) {
+                                WatcherEvent::TodosUpdated(todos) => {
+                                    let groups = router.todo_groups(&todos);
+                                    (WatcherEvent::TodosUpdated(router.collapse_todos(todos)), Some(groups))
+                                }
+                                event => (event, None),
+                            }
+                        }).await;
+                        let (event, groups) = match processed {
+                            Ok(processed) => processed,
+                            Err(e) => {
+                                tracing::error!("Failed to process event: {}", e);
+                                continue;
+                            }
+                        };
+
+                        sink.event(event);
+                        if let Some(groups) = groups {
+                            sink.todo_groups(groups);
+                        }
+                    }
+                }))
+            }
+            Err(e) => {
+                tracing::error!("Failed to start file watcher: {}", e);
+                None
+            }
+        };
+
+        Self {
+            store,
+            clock,
+            events_offsets,
+            stop_consumer,
+            consumer,
+            stop_plugins,
+            plugins,
+        }
+    }
+
+    /// Store queued events, let plugins receive them, and close out the store
+    pub async fn shutdown(self) {
+        tracing::info!("Shutting down");
+
+        // Everything read up to here is either stored already or still queued,
+        // and the queue is stored below
+        let offsets: Vec<(String, u64)> = self.events_offsets
+            .iter()
+            .map(|(profile, offset)| (events_offset_key(profile.as_deref()), offset.load(Ordering::SeqCst)))
+            .collect();
+
+        let _ = self.stop_consumer.send(true);
+        if let Some(consumer) = self.consumer {
+            if tokio::time::timeout(SHUTDOWN_TIMEOUT, consumer).await.is_err() {
+                tracing::warn!("Timed out storing queued events");
+            }
+        }
+
+        let _ = self.stop_plugins.send(true);
+        let plugins = self.plugins;
+        let abort = plugins.abort_handle();
+        if tokio::time::timeout(SHUTDOWN_TIMEOUT, plugins).await.is_err() {
+            tracing::warn!("Timed out waiting for plugin deliveries");
+            abort.abort();
+        }
+
+        let (store, now) = (self.store, self.clock.now_ms());
+        let flushed = tokio::task::spawn_blocking(move || {
+            for (key, offset) in offsets {
+                store.set_sync_state(&key, offset as i64)?;
+            }
+            let ended = store.end_open_sessions(now)?;
+            store.close_open_notifications(now)?;
+            store.wal_checkpoint()?;
+            Ok::<_, store::StoreError>(ended)
+        }).await;
+        match flushed {
+            Ok(Ok(ended)) => tracing::info!("Shutdown complete; ended {} open sessions", ended),
+            Ok(Err(e)) => tracing::error!("Failed to flush store on exit: {}", e),
+            Err(e) => tracing::error!("Store flush task failed: {}", e),
+        }
+    }
+}
+
+/// Run the core without windows until the process is asked to stop
+///
+/// The socket is bound before anything else, so an overlay that started the
+/// core can attach right away, and a second core exits without touching the
+/// history.
+pub fn run_headless(config: Config) {
+    let config = Arc::new(config);
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .thread_name("core-runtime")
+        .build()
+        .expect("failed to start async runtime");
+
+    runtime.block_on(async move {
+        let token = match ipc::core_token() {
+            Ok(token) => token,
+            Err(e) => {
+                tracing::error!("Failed to read the core token: {}", e);
+                return;
+            }
+        };
+        let sink = match ipc::serve(config.core.port, token).await {
+            Ok(sink) => Arc::new(sink),
+            Err(e) => {
+                // Most likely another core already has the port
+                tracing::error!("Failed to start the core socket: {}", e);
+                return;
+            }
+        };
+
+        let log_sink = sink.clone();
+        logs::set_listener(move |line| log_sink.log(line));
+
+        let path = config.database_path();
+        let opened = tokio::task::spawn_blocking(move || EventStore::new(&path))
+            .await
+            .map_err(|e| e.to_string())
+            .and_then(|result| result.map_err(|e| e.to_string()));
+        // Unlike the overlay, the core has nothing to show without its history
+        let store = match opened {
+            Ok(store) => Arc::new(store),
+            Err(e) => {
+                tracing::error!("Failed to open the history database: {}", e);
+                return;
+            }
+        };
+
+        let (resume_store, resume_config) = (store.clone(), config.clone());
+        let resume_at = tokio::task::spawn_blocking(move || take_events_offsets(&resume_store, &resume_config))
+            .await
+            .unwrap_or_default();
+        let core = Core::start(config, store, resume_at, sink.clone());
+        tracing::info!("Core running headless");
+
+        tokio::select! {
+            _ = wait_for_signal() => {}
+            _ = sink.stop_requested() => {}
+        }
+        core.shutdown().await;
+    });
+}
+
+/// Ask the running core to shut down, for `--stop-core`
+pub fn stop_running(config: &Config) -> Result<String, String> {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .map_err(|e| e.to_string())?;
+    let token = ipc::core_token().map_err(|e| format!("Failed to read the core token: {}", e))?;
+    match runtime.block_on(ipc::request_stop(config.core.port, &token)) {
+        Ok(true) => Ok("Asked the core to stop".to_string()),
+        Ok(false) => Ok("The core isn't running".to_string()),
+        Err(e) => Err(format!("Failed to reach the core: {}", e)),
+    }
+}
+
+/// Wait for Ctrl+C, or SIGTERM from a service manager
+async fn wait_for_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {}
+                    _ = terminate.recv() => {}
+                }
+                return;
+            }
+            Err(e) => tracing::warn!("Can't listen for SIGTERM: {}", e),
+        }
+    }
+    if let Err(e) = tokio::signal::ctrl_c().await {
+        tracing::error!("Can't listen for Ctrl+C: {}", e);
+        std::future::pending::<()>().await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_headless_requested() {
+        let args = |list: &[&str]| list.iter().map(|s| s.to_string()).collect::<Vec<_>>().into_iter();
+        assert!(headless_requested(args(&["--headless", "--stdin"])));
+        assert!(!headless_requested(args(&["--portable"])));
+        assert!(stop_requested(args(&["--stop-core"])));
+    }
+
+    #[test]
+    fn test_events_offsets_are_taken_once() {
+        let store = EventStore::in_memory().unwrap();
+        store.set_sync_state(&events_offset_key(None), 1_234).unwrap();
+
+        let config = Config::default();
+        assert_eq!(take_events_offsets(&store, &config).get(&None), Some(&1_234));
+        // Cleared once read, so a crash after this doesn't replay from it
+        assert!(take_events_offsets(&store, &config).is_empty());
+    }
+}
@@ -0,0 +1,209 @@
+//! Diagnostics bundles - what a bug report needs, in one zip
+//!
+//! `generate` collects the recent log lines, the core's too, the config with
+//! secrets masked, the watcher's state, the history database's integrity check
+//! and platform details. The user attaches the zip to a bug report; nothing is sent
+//! anywhere by the overlay.
+
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use serde_json::json;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use crate::config::{self, Config};
+use crate::health;
+use crate::locale;
+use crate::logs;
+use crate::store::EventStore;
+
+/// Config keys whose values are masked in the bundle
+const SECRET_KEYS: &[&str] = &["token", "api_token", "ifttt_key", "url", "base_url", "email", "body"];
+
+const REDACTED: &str = "<redacted>";
+
+/// Where bundles are saved: the downloads folder, or the overlay's data folder
+pub fn default_dir() -> PathBuf {
+    dirs::download_dir()
+        .or_else(|| config::get_database_path().parent().map(Path::to_path_buf))
+        .unwrap_or_else(std::env::temp_dir)
+}
+
+/// Write a bundle into `dir`, returning its path
+///
+/// `attached` says whether the overlay is attached to a headless core rather
+/// than running the core itself.
+pub fn generate(config: &Config, store: &EventStore, attached: bool, dir: &Path) -> Result<PathBuf, String> {
+    std::fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    let name = format!("agent-progress-diagnostics-{}.zip", chrono::Local::now().format("%Y%m%d-%H%M%S"));
+    let path = dir.join(name);
+
+    let file = File::create(&path).map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+    let mut zip = ZipWriter::new(file);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    for (name, contents) in contents(config, store, attached) {
+        zip.start_file(name, options).map_err(|e| e.to_string())?;
+        zip.write_all(contents.as_bytes()).map_err(|e| e.to_string())?;
+    }
+    zip.finish().map_err(|e| e.to_string())?;
+
+    tracing::info!("Saved diagnostics bundle to {:?}", path);
+    Ok(path)
+}
+
+/// The bundle's files, by name
+fn contents(config: &Config, store: &EventStore, attached: bool) -> Vec<(&'static str, String)> {
+    // The core logs to its own file when it runs headless
+    let mut logs = if attached {
+        logs::tail(config, logs::RECENT_LINES, None)
+            .into_iter()
+            .map(|line| format!("[{}] {}", line.source, line.text))
+            .collect::<Vec<_>>()
+    } else {
+        logs::recent()
+    }
+    .join("\n");
+    logs.push('\n');
+    vec![
+        ("logs.txt", logs),
+        ("config.toml", redacted_config(config)),
+        ("watcher.json", pretty(&watcher_state(config, attached))),
+        ("database.txt", database_report(store)),
+        ("platform.json", pretty(&platform(config))),
+    ]
+}
+
+fn pretty(value: &serde_json::Value) -> String {
+    serde_json::to_string_pretty(value).unwrap_or_default()
+}
+
+/// The config as TOML, with tokens, webhook URLs and the like masked
+fn redacted_config(config: &Config) -> String {
+    let mut value = match toml::Value::try_from(config) {
+        Ok(value) => value,
+        Err(e) => return format!("# Failed to serialize config: {}\n", e),
+    };
+    redact(&mut value);
+    toml::to_string_pretty(&value).unwrap_or_else(|e| format!("# Failed to serialize config: {}\n", e))
+}
+
+fn redact(value: &mut toml::Value) {
+    match value {
+        toml::Value::Table(table) => {
+            for (key, value) in table.iter_mut() {
+                let secret = SECRET_KEYS.contains(&key.as_str())
+                    && value.as_str().is_some_and(|text| !text.is_empty());
+                if secret {
+                    *value = toml::Value::String(REDACTED.to_string());
+                } else {
+                    redact(value);
+                }
+            }
+        }
+        toml::Value::Array(items) => items.iter_mut().for_each(redact),
+        _ => {}
+    }
+}
+
+/// Whether a watched path is there, and its size and last change
+fn file_state(path: &Path) -> serde_json::Value {
+    match std::fs::metadata(path) {
+        Ok(metadata) => json!({
+            "path": path,
+            "exists": true,
+            "is_dir": metadata.is_dir(),
+            "size": metadata.len(),
+            "modified_ms": metadata
+                .modified()
+                .ok()
+                .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+                .map(|since| since.as_millis() as u64),
+        }),
+        Err(e) => json!({ "path": path, "exists": false, "error": e.to_string() }),
+    }
+}
+
+fn watcher_state(config: &Config, attached: bool) -> serde_json::Value {
+    let profiles: Vec<_> = config
+        .profile_dirs()
+        .into_iter()
+        .map(|(name, dir)| json!({ "name": name, "dir": file_state(&dir) }))
+        .collect();
+    json!({
+        "core": if attached { "attached" } else { "in_process" },
+        "watch_mode": config.behavior.watch_mode,
+        "debounce_ms": config.behavior.file_watch_debounce_ms,
+        "claude_dir": file_state(&config.claude_dir()),
+        "events_file": file_state(&config.events_path()),
+        "todos_dir": file_state(&config.todos_path()),
+        "database": file_state(&config.database_path()),
+        "profiles": profiles,
+        "health": health::core_snapshot(),
+    })
+}
+
+fn database_report(store: &EventStore) -> String {
+    let version = match store.schema_version() {
+        Ok(version) => version.to_string(),
+        Err(e) => format!("unknown ({})", e),
+    };
+    let integrity = match store.integrity_check() {
+        Ok(problems) => problems.join("\n"),
+        Err(e) => format!("failed: {}", e),
+    };
+    let wal = store.wal_size().map(|size| size.to_string()).unwrap_or_else(|e| e.to_string());
+    format!("Schema version: {}\nWAL size: {}\nIntegrity check:\n{}\n", version, wal, integrity)
+}
+
+fn platform(config: &Config) -> serde_json::Value {
+    json!({
+        "app_version": env!("CARGO_PKG_VERSION"),
+        "os": std::env::consts::OS,
+        "arch": std::env::consts::ARCH,
+        "family": std::env::consts::FAMILY,
+        "portable": config::portable_dir().is_some(),
+        "locale": format!("{:?}", locale::current()),
+        "session_type": std::env::var("XDG_SESSION_TYPE").ok(),
+        "desktop": std::env::var("XDG_CURRENT_DESKTOP").ok(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn test_bundle_masks_secrets() {
+        let mut config = Config::default();
+        config.plugins.todoist.api_token = "todoist-secret".to_string();
+        config.bridge.token = "bridge-secret".to_string();
+        let store = EventStore::in_memory().unwrap();
+        let dir = tempfile::tempdir().unwrap();
+
+        let path = generate(&config, &store, true, dir.path()).unwrap();
+        let mut zip = zip::ZipArchive::new(File::open(&path).unwrap()).unwrap();
+        let mut names: Vec<&str> = zip.file_names().collect();
+        names.sort();
+        assert_eq!(names, ["config.toml", "database.txt", "logs.txt", "platform.json", "watcher.json"]);
+
+        let mut config_toml = String::new();
+        zip.by_name("config.toml").unwrap().read_to_string(&mut config_toml).unwrap();
+        assert!(!config_toml.contains("secret"));
+        assert!(config_toml.contains(REDACTED));
+        // Empty secrets stay empty, so the bundle shows what isn't set
+        assert!(config_toml.contains("token = \"\""));
+
+        let mut database = String::new();
+        zip.by_name("database.txt").unwrap().read_to_string(&mut database).unwrap();
+        assert!(database.contains("Integrity check:\nok"));
+
+        let mut watcher = String::new();
+        zip.by_name("watcher.json").unwrap().read_to_string(&mut watcher).unwrap();
+        let watcher: serde_json::Value = serde_json::from_str(&watcher).unwrap();
+        assert_eq!(watcher["core"], "attached");
+    }
+}
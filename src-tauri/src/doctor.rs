@@ -0,0 +1,235 @@
+//! Setup health - a checklist of what the overlay needs to work
+//!
+//! `run` checks the hooks, the files the overlay reads and writes, the
+//! history database and the file watcher, and says what to do about each
+//! problem, for a "Setup health" screen. Nothing is changed while checking.
+
+use std::fs::OpenOptions;
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::config::Config;
+use crate::health;
+use crate::hooks::{self, HookState};
+use crate::store;
+use crate::watcher::WatchMode;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckStatus {
+    Ok,
+    /// Works, but not as well as it could
+    Warning,
+    /// Keeps the overlay from showing tasks
+    Error,
+}
+
+/// One line of the checklist
+#[derive(Debug, Clone, Serialize)]
+pub struct Check {
+    /// Stable name, e.g. "hooks" or "database", for the UI to key on
+    pub id: &'static str,
+    pub title: String,
+    pub status: CheckStatus,
+    pub detail: String,
+    /// What to do about a warning or error
+    pub fix: Option<String>,
+}
+
+impl Check {
+    fn new(id: &'static str, title: impl Into<String>, status: CheckStatus, detail: impl Into<String>) -> Self {
+        Self { id, title: title.into(), status, detail: detail.into(), fix: None }
+    }
+
+    fn fix(mut self, fix: impl Into<String>) -> Self {
+        self.fix = Some(fix.into());
+        self
+    }
+}
+
+/// Run every check; `exe` is the executable the hooks should run
+pub fn run(config: &Config, exe: &Path) -> Vec<Check> {
+    let mut checks: Vec<Check> = std::iter::once((None, config.claude_dir()))
+        .chain(config.profile_dirs().into_iter().map(|(name, dir)| (Some(name), dir)))
+        .map(|(profile, dir)| check_hooks(profile.as_deref(), &dir, exe))
+        .collect();
+    checks.push(check_events_file(&config.events_path()));
+    checks.push(check_todos_dir(&config.todos_path()));
+    checks.extend(check_database(&config.database_path()));
+    checks.push(check_watcher(config, health::core_snapshot().watch_mode.as_deref()));
+    checks
+}
+
+fn check_hooks(profile: Option<&str>, claude_dir: &Path, exe: &Path) -> Check {
+    let title = match profile {
+        Some(name) => format!("Claude Code hooks ({})", name),
+        None => "Claude Code hooks".to_string(),
+    };
+    match hooks::status(claude_dir, exe) {
+        Ok(status) => match status.state {
+            HookState::Installed => {
+                Check::new("hooks", title, CheckStatus::Ok, format!("Installed in {}", status.settings.display()))
+            }
+            HookState::Outdated => Check::new(
+                "hooks",
+                title,
+                CheckStatus::Warning,
+                format!("Out of date for {}: {}", status.settings.display(), status.missing.join(", ")),
+            )
+            .fix("Repair the hooks so they run this copy of the overlay"),
+            HookState::NotInstalled => Check::new(
+                "hooks",
+                title,
+                CheckStatus::Error,
+                format!("Not installed in {}", status.settings.display()),
+            )
+            .fix("Install the hooks so Claude Code reports its tasks"),
+        },
+        Err(e) => Check::new("hooks", title, CheckStatus::Error, e)
+            .fix("Fix or remove the broken settings file; Claude Code can't read it either"),
+    }
+}
+
+fn check_events_file(path: &Path) -> Check {
+    let title = "Events file";
+    if path.exists() {
+        return match OpenOptions::new().append(true).open(path) {
+            Ok(_) => Check::new("events_file", title, CheckStatus::Ok, format!("{} is writable", path.display())),
+            Err(e) => {
+                let detail = format!("Can't write {}: {}", path.display(), e);
+                Check::new("events_file", title, CheckStatus::Error, detail).fix("Give your user write access to the file")
+            }
+        };
+    }
+    match path.parent().map(std::fs::metadata) {
+        Some(Ok(dir)) if dir.is_dir() && !dir.permissions().readonly() => Check::new(
+            "events_file",
+            title,
+            CheckStatus::Warning,
+            format!("{} doesn't exist yet", path.display()),
+        )
+        .fix("It is created when the hooks report the first task"),
+        _ => Check::new("events_file", title, CheckStatus::Error, format!("Can't create {}", path.display()))
+            .fix("Check that Claude's config directory exists and is writable"),
+    }
+}
+
+fn check_todos_dir(path: &Path) -> Check {
+    let title = "Todos folder";
+    if path.is_dir() {
+        Check::new("todos_dir", title, CheckStatus::Ok, format!("{} exists", path.display()))
+    } else {
+        Check::new("todos_dir", title, CheckStatus::Warning, format!("{} doesn't exist yet", path.display()))
+            .fix("Claude Code creates it the first time it writes a todo list")
+    }
+}
+
+const MOVE_DATABASE_ASIDE: &str = "Move the file aside; the overlay starts a new history";
+
+/// The integrity check and the migrations check
+fn check_database(path: &Path) -> Vec<Check> {
+    let title = "History database";
+    if !path.exists() {
+        let detail = format!("{} doesn't exist yet", path.display());
+        return vec![Check::new("database", title, CheckStatus::Warning, detail).fix("It is created when the overlay starts")];
+    }
+    let check = match store::check_database(path) {
+        Ok(check) => check,
+        Err(e) => {
+            let detail = format!("Can't open {}: {}", path.display(), e);
+            return vec![Check::new("database", title, CheckStatus::Error, detail).fix(MOVE_DATABASE_ASIDE)];
+        }
+    };
+
+    let integrity = if check.integrity == ["ok"] {
+        Check::new("database", title, CheckStatus::Ok, "Integrity check passed")
+    } else {
+        Check::new("database", title, CheckStatus::Error, check.integrity.join("; ")).fix(MOVE_DATABASE_ASIDE)
+    };
+    let migrations = match check.pending_migrations {
+        0 => Check::new(
+            "migrations",
+            "Database schema",
+            CheckStatus::Ok,
+            format!("Up to date (version {})", check.schema_version),
+        ),
+        pending if pending > 0 => Check::new(
+            "migrations",
+            "Database schema",
+            CheckStatus::Warning,
+            format!("{} migrations pending", pending),
+        )
+        .fix("Restart the overlay to apply them"),
+        _ => Check::new(
+            "migrations",
+            "Database schema",
+            CheckStatus::Error,
+            format!("Written by a newer version (version {})", check.schema_version),
+        )
+        .fix("Update the overlay"),
+    };
+    vec![integrity, migrations]
+}
+
+/// `active` is the mode the running watcher reported, if it is running
+fn check_watcher(config: &Config, active: Option<&str>) -> Check {
+    let title = "File watcher";
+    let configured = WatchMode::from_config(&config.behavior.watch_mode);
+    match active {
+        None => Check::new("watcher", title, CheckStatus::Error, "Not running")
+            .fix("Restart the overlay; if that doesn't help, save a diagnostics bundle"),
+        Some("poll") if configured == WatchMode::Auto => Check::new(
+            "watcher",
+            title,
+            CheckStatus::Warning,
+            "Polling, because native file notifications failed or missed updates",
+        )
+        .fix("Updates may arrive a little late; set watch_mode = \"poll\" to make this the default"),
+        Some(mode) => Check::new("watcher", title, CheckStatus::Ok, format!("Using {} mode", mode)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::EventStore;
+
+    #[test]
+    fn test_checks() {
+        let dir = tempfile::tempdir().unwrap();
+        let claude = dir.path().join("claude");
+        std::fs::create_dir_all(&claude).unwrap();
+        let mut config = Config::default();
+        config.paths.claude_dir = claude.to_string_lossy().to_string();
+        config.paths.events_file = claude.join("progress-events.jsonl").to_string_lossy().to_string();
+        config.paths.todos_dir = claude.join("todos").to_string_lossy().to_string();
+        config.paths.database_file = dir.path().join("history.db").to_string_lossy().to_string();
+        config.paths.profiles.clear();
+
+        let checks = run(&config, Path::new("/opt/overlay"));
+        let status = |checks: &[Check], id: &str| checks.iter().find(|check| check.id == id).unwrap().status;
+        assert_eq!(status(&checks, "hooks"), CheckStatus::Error);
+        assert_eq!(status(&checks, "events_file"), CheckStatus::Warning);
+        assert_eq!(status(&checks, "todos_dir"), CheckStatus::Warning);
+        assert_eq!(status(&checks, "database"), CheckStatus::Warning);
+        assert!(checks.iter().all(|check| check.status == CheckStatus::Ok || check.fix.is_some()));
+
+        std::fs::write(claude.join("progress-events.jsonl"), "").unwrap();
+        std::fs::create_dir_all(claude.join("todos")).unwrap();
+        drop(EventStore::new(&config.database_path()).unwrap());
+        let checks = run(&config, Path::new("/opt/overlay"));
+        assert_eq!(status(&checks, "events_file"), CheckStatus::Ok);
+        assert_eq!(status(&checks, "todos_dir"), CheckStatus::Ok);
+        assert_eq!(status(&checks, "database"), CheckStatus::Ok);
+        assert_eq!(status(&checks, "migrations"), CheckStatus::Ok);
+    }
+
+    #[test]
+    fn test_watcher_fallback_is_a_warning() {
+        let config = Config::default();
+        assert_eq!(check_watcher(&config, Some("poll")).status, CheckStatus::Warning);
+        assert_eq!(check_watcher(&config, Some("notify")).status, CheckStatus::Ok);
+        assert_eq!(check_watcher(&config, None).status, CheckStatus::Error);
+    }
+}
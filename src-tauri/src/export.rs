@@ -0,0 +1,211 @@
+//! Task and session history as Parquet files, for analytics tools
+//!
+//! DuckDB, pandas and Polars read Parquet with its column types intact:
+//! times are UTC timestamps, durations integers and flags booleans, where a
+//! CSV would hand back strings to parse. `export_parquet` writes
+//! `tasks.parquet` and `sessions.parquet` into a new dated folder. Writing
+//! Parquet needs arrow, which is large, so it is only built in with the
+//! `parquet` feature.
+
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::diagnostics;
+use crate::store::EventStore;
+
+const UNAVAILABLE: &str = "This build can't write Parquet; build it with `--features parquet`";
+
+/// What an export wrote
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportReport {
+    /// The folder holding the files
+    pub dir: PathBuf,
+    pub tasks: usize,
+    pub sessions: usize,
+}
+
+/// Whether this build can write Parquet
+pub fn available() -> bool {
+    cfg!(feature = "parquet")
+}
+
+/// Where exports are saved: the same folder as diagnostics bundles
+pub fn default_dir() -> PathBuf {
+    diagnostics::default_dir()
+}
+
+/// Write tasks and sessions started since `since_ms` into a new folder in `dir`
+pub fn export_parquet(store: &EventStore, dir: &Path, since_ms: i64) -> Result<ExportReport, String> {
+    if !available() {
+        return Err(UNAVAILABLE.to_string());
+    }
+    let tasks = store.get_tasks_since(since_ms).map_err(|e| e.to_string())?;
+    let sessions = store.get_sessions_since(since_ms).map_err(|e| e.to_string())?;
+
+    let name = format!("agent-progress-history-{}", chrono::Local::now().format("%Y%m%d-%H%M%S"));
+    let dir = dir.join(name);
+    std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    writer::write_tasks(&dir.join("tasks.parquet"), &tasks)?;
+    writer::write_sessions(&dir.join("sessions.parquet"), &sessions)?;
+
+    tracing::info!("Exported {} tasks and {} sessions to {:?}", tasks.len(), sessions.len(), dir);
+    Ok(ExportReport { dir, tasks: tasks.len(), sessions: sessions.len() })
+}
+
+#[cfg(feature = "parquet")]
+mod writer {
+    use std::fs::File;
+    use std::path::Path;
+    use std::sync::Arc;
+
+    use arrow_array::{ArrayRef, BooleanArray, Int64Array, RecordBatch, StringArray, TimestampMillisecondArray};
+    use arrow_schema::{DataType, Field, Schema, TimeUnit};
+    use parquet::arrow::ArrowWriter;
+    use parquet::basic::Compression;
+    use parquet::file::properties::WriterProperties;
+
+    use crate::store::{StoredSession, StoredTask};
+
+    const TIME_ZONE: &str = "UTC";
+
+    fn timestamp() -> DataType {
+        DataType::Timestamp(TimeUnit::Millisecond, Some(TIME_ZONE.into()))
+    }
+
+    fn timestamps(values: Vec<Option<i64>>) -> ArrayRef {
+        Arc::new(TimestampMillisecondArray::from(values).with_timezone(TIME_ZONE))
+    }
+
+    pub fn write_tasks(path: &Path, tasks: &[StoredTask]) -> Result<(), String> {
+        let schema = Schema::new(vec![
+            Field::new("id", DataType::Utf8, false),
+            Field::new("session_id", DataType::Utf8, false),
+            Field::new("tool", DataType::Utf8, false),
+            Field::new("description", DataType::Utf8, true),
+            Field::new("status", DataType::Utf8, false),
+            Field::new("started_at", timestamp(), true),
+            Field::new("ended_at", timestamp(), true),
+            Field::new("duration_ms", DataType::Int64, true),
+            Field::new("duration_suspect", DataType::Boolean, false),
+            Field::new("is_background", DataType::Boolean, false),
+            Field::new("subagent_type", DataType::Utf8, true),
+            Field::new("source", DataType::Utf8, false),
+            Field::new("reviewed", DataType::Boolean, false),
+            Field::new("pinned", DataType::Boolean, false),
+        ]);
+        let columns: Vec<ArrayRef> = vec![
+            Arc::new(StringArray::from_iter_values(tasks.iter().map(|t| &t.id))),
+            Arc::new(StringArray::from_iter_values(tasks.iter().map(|t| &t.session_id))),
+            Arc::new(StringArray::from_iter_values(tasks.iter().map(|t| &t.tool))),
+            Arc::new(StringArray::from(tasks.iter().map(|t| t.description.as_deref()).collect::<Vec<_>>())),
+            Arc::new(StringArray::from_iter_values(tasks.iter().map(|t| &t.status))),
+            timestamps(tasks.iter().map(|t| Some(t.started_at)).collect()),
+            timestamps(tasks.iter().map(|t| t.ended_at).collect()),
+            Arc::new(Int64Array::from(tasks.iter().map(|t| t.duration_ms).collect::<Vec<_>>())),
+            Arc::new(BooleanArray::from(tasks.iter().map(|t| t.duration_suspect).collect::<Vec<_>>())),
+            Arc::new(BooleanArray::from(tasks.iter().map(|t| t.is_background).collect::<Vec<_>>())),
+            Arc::new(StringArray::from(tasks.iter().map(|t| t.subagent_type.as_deref()).collect::<Vec<_>>())),
+            Arc::new(StringArray::from_iter_values(tasks.iter().map(|t| &t.source))),
+            Arc::new(BooleanArray::from(tasks.iter().map(|t| t.reviewed).collect::<Vec<_>>())),
+            Arc::new(BooleanArray::from(tasks.iter().map(|t| t.pinned).collect::<Vec<_>>())),
+        ];
+        write(path, schema, columns)
+    }
+
+    pub fn write_sessions(path: &Path, sessions: &[StoredSession]) -> Result<(), String> {
+        let schema = Schema::new(vec![
+            Field::new("id", DataType::Utf8, false),
+            Field::new("started_at", timestamp(), true),
+            Field::new("ended_at", timestamp(), true),
+            Field::new("project_path", DataType::Utf8, true),
+            Field::new("branch", DataType::Utf8, true),
+        ]);
+        let columns: Vec<ArrayRef> = vec![
+            Arc::new(StringArray::from_iter_values(sessions.iter().map(|s| &s.id))),
+            timestamps(sessions.iter().map(|s| Some(s.started_at)).collect()),
+            timestamps(sessions.iter().map(|s| s.ended_at).collect()),
+            Arc::new(StringArray::from(sessions.iter().map(|s| s.project_path.as_deref()).collect::<Vec<_>>())),
+            Arc::new(StringArray::from(sessions.iter().map(|s| s.branch.as_deref()).collect::<Vec<_>>())),
+        ];
+        write(path, schema, columns)
+    }
+
+    fn write(path: &Path, schema: Schema, columns: Vec<ArrayRef>) -> Result<(), String> {
+        let schema = Arc::new(schema);
+        let batch = RecordBatch::try_new(schema.clone(), columns).map_err(|e| e.to_string())?;
+        let file = File::create(path).map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+        let properties = WriterProperties::builder().set_compression(Compression::SNAPPY).build();
+        let mut writer = ArrowWriter::try_new(file, schema, Some(properties)).map_err(|e| e.to_string())?;
+        writer.write(&batch).map_err(|e| e.to_string())?;
+        writer.close().map_err(|e| e.to_string())?;
+        Ok(())
+    }
+}
+
+#[cfg(not(feature = "parquet"))]
+mod writer {
+    use std::path::Path;
+
+    use super::UNAVAILABLE;
+    use crate::store::{StoredSession, StoredTask};
+
+    pub fn write_tasks(_path: &Path, _tasks: &[StoredTask]) -> Result<(), String> {
+        Err(UNAVAILABLE.to_string())
+    }
+
+    pub fn write_sessions(_path: &Path, _sessions: &[StoredSession]) -> Result<(), String> {
+        Err(UNAVAILABLE.to_string())
+    }
+}
+
+#[cfg(all(test, feature = "parquet"))]
+mod tests {
+    use super::*;
+    use crate::store::StoredTask;
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+
+    #[test]
+    fn test_export_parquet() {
+        let store = EventStore::in_memory().unwrap();
+        store.insert_task(&StoredTask {
+            id: "task-1".to_string(),
+            session_id: "session-1".to_string(),
+            tool: "Bash".to_string(),
+            description: Some("cargo test".to_string()),
+            status: "completed".to_string(),
+            started_at: 5_000,
+            ended_at: Some(6_500),
+            duration_ms: Some(1_500),
+            duration_suspect: false,
+            is_background: false,
+            subagent_type: None,
+            source: "claude-code".to_string(),
+            reviewed: false,
+            pinned: true,
+        }).unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let report = export_parquet(&store, dir.path(), 0).unwrap();
+        assert_eq!((report.tasks, report.sessions), (1, 1));
+
+        let file = std::fs::File::open(report.dir.join("tasks.parquet")).unwrap();
+        let reader = SerializedFileReader::new(file).unwrap();
+        assert_eq!(reader.metadata().file_metadata().num_rows(), 1);
+        let columns: Vec<&str> = reader.metadata().file_metadata().schema_descr().columns().iter().map(|c| c.name()).collect();
+        assert!(columns.contains(&"duration_ms"));
+        assert_eq!(export_parquet(&store, dir.path(), 10_000).unwrap().tasks, 0);
+    }
+}
+
+#[cfg(all(test, not(feature = "parquet")))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_export_needs_the_feature() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(export_parquet(&EventStore::in_memory().unwrap(), dir.path(), 0).is_err());
+        assert!(std::fs::read_dir(dir.path()).unwrap().next().is_none());
+    }
+}
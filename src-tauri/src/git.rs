@@ -0,0 +1,151 @@
+//! Lightweight git repository inspection
+//!
+//! Reads repository metadata (reflog, HEAD) straight from the `.git` directory
+//! so no git binary or libgit2 dependency is required.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A single entry from a reflog file
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReflogEntry {
+    pub old_hash: String,
+    pub new_hash: String,
+    /// Seconds since the Unix epoch
+    pub timestamp: i64,
+    pub message: String,
+}
+
+impl ReflogEntry {
+    /// Whether this entry records a newly created commit
+    pub fn is_commit(&self) -> bool {
+        self.message.starts_with("commit")
+    }
+
+    /// Commit subject without the reflog action prefix (e.g. "commit (amend): ")
+    pub fn subject(&self) -> &str {
+        self.message
+            .split_once(": ")
+            .map(|(_, subject)| subject)
+            .unwrap_or(&self.message)
+    }
+}
+
+/// Find the git directory for a path, walking up through parent directories
+///
+/// Handles worktrees and submodules where `.git` is a file pointing elsewhere.
+pub fn find_git_dir(start: &Path) -> Option<PathBuf> {
+    let mut current = Some(start);
+
+    while let Some(dir) = current {
+        let candidate = dir.join(".git");
+        if candidate.is_dir() {
+            return Some(candidate);
+        }
+        if candidate.is_file() {
+            let content = fs::read_to_string(&candidate).ok()?;
+            let target = content.trim().strip_prefix("gitdir:")?.trim();
+            let target = PathBuf::from(target);
+            return Some(if target.is_absolute() { target } else { dir.join(target) });
+        }
+        current = dir.parent();
+    }
+
+    None
+}
+
+/// Name of the currently checked-out branch, or `None` when HEAD is detached
+pub fn current_branch(git_dir: &Path) -> Option<String> {
+    let head = fs::read_to_string(git_dir.join("HEAD")).ok()?;
+    head.trim()
+        .strip_prefix("ref: refs/heads/")
+        .map(|branch| branch.to_string())
+}
+
+/// Read the HEAD reflog of a repository
+pub fn read_head_reflog(git_dir: &Path) -> Vec<ReflogEntry> {
+    fs::read_to_string(git_dir.join("logs").join("HEAD"))
+        .map(|content| content.lines().filter_map(parse_reflog_line).collect())
+        .unwrap_or_default()
+}
+
+/// Commits created in the given window (inclusive, seconds since epoch), oldest first
+pub fn commits_between(git_dir: &Path, start_secs: i64, end_secs: i64) -> Vec<ReflogEntry> {
+    let mut commits: Vec<ReflogEntry> = Vec::new();
+
+    for entry in read_head_reflog(git_dir) {
+        if entry.is_commit()
+            && entry.timestamp >= start_secs
+            && entry.timestamp <= end_secs
+            && !commits.iter().any(|c| c.new_hash == entry.new_hash)
+        {
+            commits.push(entry);
+        }
+    }
+
+    commits
+}
+
+/// Parse one reflog line: `<old> <new> <name> <<email>> <timestamp> <tz>\t<message>`
+fn parse_reflog_line(line: &str) -> Option<ReflogEntry> {
+    let (header, message) = line.split_once('\t').unwrap_or((line, ""));
+
+    let mut parts = header.splitn(3, ' ');
+    let old_hash = parts.next()?.to_string();
+    let new_hash = parts.next()?.to_string();
+    let identity = parts.next()?;
+
+    // Timestamp and timezone follow the closing bracket of the email
+    let after_email = &identity[identity.rfind('>')? + 1..];
+    let timestamp = after_email.split_whitespace().next()?.parse().ok()?;
+
+    Some(ReflogEntry {
+        old_hash,
+        new_hash,
+        timestamp,
+        message: message.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    const REFLOG: &str = "\
+0000000000000000000000000000000000000000 1111111111111111111111111111111111111111 Dev <dev@example.com> 1000 +0000\tcommit (initial): Initial commit
+1111111111111111111111111111111111111111 2222222222222222222222222222222222222222 Dev <dev@example.com> 2000 +0100\tcheckout: moving from main to feature
+2222222222222222222222222222222222222222 3333333333333333333333333333333333333333 Dev <dev@example.com> 3000 -0500\tcommit: Add pagination
+3333333333333333333333333333333333333333 4444444444444444444444444444444444444444 Dev <dev@example.com> 4000 +0000\tcommit (amend): Add pagination tests
+";
+
+    #[test]
+    fn test_parse_reflog_line() {
+        let entry = parse_reflog_line(REFLOG.lines().nth(2).unwrap()).unwrap();
+        assert_eq!(entry.new_hash, "3333333333333333333333333333333333333333");
+        assert_eq!(entry.timestamp, 3000);
+        assert!(entry.is_commit());
+        assert_eq!(entry.subject(), "Add pagination");
+    }
+
+    #[test]
+    fn test_commits_between() {
+        let dir = tempdir().unwrap();
+        let git_dir = dir.path().join(".git");
+        fs::create_dir_all(git_dir.join("logs")).unwrap();
+        fs::write(git_dir.join("logs").join("HEAD"), REFLOG).unwrap();
+
+        let nested = dir.path().join("src").join("deep");
+        fs::create_dir_all(&nested).unwrap();
+        let found = find_git_dir(&nested).unwrap();
+        assert_eq!(found, git_dir);
+
+        fs::write(git_dir.join("HEAD"), "ref: refs/heads/feature/pagination\n").unwrap();
+        assert_eq!(current_branch(&found).as_deref(), Some("feature/pagination"));
+
+        let commits = commits_between(&found, 1500, 4000);
+        assert_eq!(commits.len(), 2);
+        assert_eq!(commits[0].subject(), "Add pagination");
+        assert_eq!(commits[1].subject(), "Add pagination tests");
+    }
+}
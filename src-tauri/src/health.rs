@@ -0,0 +1,306 @@
+//! Internal health metrics
+//!
+//! Counters for the event pipeline, recorded wherever the work happens and
+//! read back by `get_health_metrics` and the tray's diagnostics section. They
+//! answer "why is the overlay laggy" without turning on debug logging: a high
+//! event rate, a slow plugin, or slow database writes each show up here, as
+//! do supervised tasks that keep failing. An overlay attached to a core in
+//! another process shows the core's metrics, which it reports every few seconds.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::locale::{Locale, Message};
+
+/// Window the event rate is averaged over
+const RATE_WINDOW: Duration = Duration::from_secs(10);
+
+/// Process-wide metrics
+pub fn metrics() -> &'static HealthMetrics {
+    static METRICS: OnceLock<HealthMetrics> = OnceLock::new();
+    METRICS.get_or_init(HealthMetrics::new)
+}
+
+/// The last metrics the attached core reported, when the core runs in another process
+static CORE_SNAPSHOT: Mutex<Option<HealthSnapshot>> = Mutex::new(None);
+
+/// Keep the metrics an attached core reported
+pub fn set_core_snapshot(snapshot: HealthSnapshot) {
+    if let Ok(mut latest) = CORE_SNAPSHOT.lock() {
+        *latest = Some(snapshot);
+    }
+}
+
+/// Metrics of the event pipeline, wherever the core runs
+///
+/// An attached core's last report, with this process's emit failures, since
+/// events are emitted to the windows here; otherwise this process's own.
+pub fn core_snapshot() -> HealthSnapshot {
+    let local = metrics().snapshot();
+    match CORE_SNAPSHOT.lock().ok().and_then(|latest| latest.clone()) {
+        Some(core) => HealthSnapshot { emit_failures: local.emit_failures, ..core },
+        None => local,
+    }
+}
+
+/// Count, mean, and worst case of a timed operation
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LatencyStats {
+    pub count: u64,
+    pub avg_ms: f64,
+    pub max_ms: f64,
+    pub last_ms: f64,
+}
+
+impl LatencyStats {
+    fn record(&mut self, elapsed: Duration) {
+        let ms = elapsed.as_secs_f64() * 1000.0;
+        self.avg_ms = (self.avg_ms * self.count as f64 + ms) / (self.count + 1) as f64;
+        self.count += 1;
+        self.max_ms = self.max_ms.max(ms);
+        self.last_ms = ms;
+    }
+}
+
+/// Delivery timings and failures for one plugin
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PluginHealth {
+    pub name: String,
+    pub latency: LatencyStats,
+    pub failures: u64,
+}
+
+/// Point-in-time copy of the metrics
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HealthSnapshot {
+    pub uptime_secs: u64,
+    pub events_total: u64,
+    pub events_per_sec: f64,
+    pub parse_errors: u64,
+    pub emit_failures: u64,
+    pub db_writes: LatencyStats,
+    pub plugins: Vec<PluginHealth>,
+    /// Times each supervised task has been restarted
+    pub restarts: BTreeMap<String, u64>,
+    /// Supervised tasks that keep failing
+    pub degraded: Vec<String>,
+    /// How the file watcher is following the files: "notify" or "poll"
+    #[serde(default)]
+    pub watch_mode: Option<String>,
+}
+
+impl HealthSnapshot {
+    /// One line per metric, for the tray's diagnostics menu
+    pub fn summary_lines(&self, locale: Locale) -> [String; 6] {
+        let plugins = match self.plugins.iter().max_by(|a, b| a.latency.avg_ms.total_cmp(&b.latency.avg_ms)) {
+            Some(slowest) => locale.format(Message::SlowestPlugin, &[
+                ("name", &slowest.name),
+                ("avg", &format!("{:.0}", slowest.latency.avg_ms)),
+                ("failures", &slowest.failures),
+            ]),
+            None => locale.text(Message::NoPlugins).to_string(),
+        };
+        let status = if self.degraded.is_empty() {
+            locale.text(Message::StatusOk).to_string()
+        } else {
+            locale.format(Message::StatusDegraded, &[("tasks", &self.degraded.join(", "))])
+        };
+        [
+            status,
+            locale.format(Message::EventsRate, &[
+                ("rate", &format!("{:.1}", self.events_per_sec)),
+                ("total", &self.events_total),
+            ]),
+            locale.format(Message::ParseErrors, &[("count", &self.parse_errors)]),
+            locale.format(Message::EmitFailures, &[("count", &self.emit_failures)]),
+            locale.format(Message::DbWrites, &[
+                ("avg", &format!("{:.1}", self.db_writes.avg_ms)),
+                ("max", &format!("{:.1}", self.db_writes.max_ms)),
+            ]),
+            plugins,
+        ]
+    }
+}
+
+pub struct HealthMetrics {
+    started: Instant,
+    events_total: AtomicU64,
+    parse_errors: AtomicU64,
+    emit_failures: AtomicU64,
+    recent_events: Mutex<VecDeque<Instant>>,
+    db_writes: Mutex<LatencyStats>,
+    plugins: Mutex<HashMap<String, PluginHealth>>,
+    restarts: Mutex<BTreeMap<String, u64>>,
+    degraded: Mutex<BTreeSet<String>>,
+    watch_mode: Mutex<Option<&'static str>>,
+}
+
+impl HealthMetrics {
+    pub fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            events_total: AtomicU64::new(0),
+            parse_errors: AtomicU64::new(0),
+            emit_failures: AtomicU64::new(0),
+            recent_events: Mutex::new(VecDeque::new()),
+            db_writes: Mutex::new(LatencyStats::default()),
+            plugins: Mutex::new(HashMap::new()),
+            restarts: Mutex::new(BTreeMap::new()),
+            degraded: Mutex::new(BTreeSet::new()),
+            watch_mode: Mutex::new(None),
+        }
+    }
+
+    /// An event entered the router
+    pub fn record_event(&self) {
+        self.record_event_at(Instant::now());
+    }
+
+    fn record_event_at(&self, now: Instant) {
+        self.events_total.fetch_add(1, Ordering::Relaxed);
+        if let Ok(mut recent) = self.recent_events.lock() {
+            recent.push_back(now);
+            prune(&mut recent, now);
+        }
+    }
+
+    /// An input line couldn't be parsed
+    pub fn record_parse_error(&self) {
+        self.parse_errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// An event couldn't be emitted to the frontend
+    pub fn record_emit_failure(&self) {
+        self.emit_failures.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// A store write took `elapsed`
+    pub fn record_db_write(&self, elapsed: Duration) {
+        if let Ok(mut stats) = self.db_writes.lock() {
+            stats.record(elapsed);
+        }
+    }
+
+    /// A plugin delivery took `elapsed`; `failed` covers both errors and timeouts
+    pub fn record_plugin(&self, name: &str, elapsed: Duration, failed: bool) {
+        if let Ok(mut plugins) = self.plugins.lock() {
+            let plugin = plugins.entry(name.to_string()).or_insert_with(|| PluginHealth {
+                name: name.to_string(),
+                ..Default::default()
+            });
+            plugin.latency.record(elapsed);
+            if failed {
+                plugin.failures += 1;
+            }
+        }
+    }
+
+    /// A supervised task is being restarted
+    pub fn record_restart(&self, name: &str) {
+        if let Ok(mut restarts) = self.restarts.lock() {
+            *restarts.entry(name.to_string()).or_default() += 1;
+        }
+    }
+
+    /// Mark a supervised task as failing repeatedly, or as recovered
+    pub fn set_degraded(&self, name: &str, degraded: bool) {
+        if let Ok(mut set) = self.degraded.lock() {
+            if degraded {
+                set.insert(name.to_string());
+            } else {
+                set.remove(name);
+            }
+        }
+    }
+
+    /// The file watcher is now using native notifications ("notify") or polling ("poll")
+    pub fn set_watch_mode(&self, mode: &'static str) {
+        if let Ok(mut current) = self.watch_mode.lock() {
+            *current = Some(mode);
+        }
+    }
+
+    pub fn snapshot(&self) -> HealthSnapshot {
+        self.snapshot_at(Instant::now())
+    }
+
+    fn snapshot_at(&self, now: Instant) -> HealthSnapshot {
+        let recent = match self.recent_events.lock() {
+            Ok(mut recent) => {
+                prune(&mut recent, now);
+                recent.len()
+            }
+            Err(_) => 0,
+        };
+
+        let mut plugins: Vec<PluginHealth> = self.plugins.lock()
+            .map(|plugins| plugins.values().cloned().collect())
+            .unwrap_or_default();
+        plugins.sort_by(|a, b| a.name.cmp(&b.name));
+
+        HealthSnapshot {
+            uptime_secs: now.saturating_duration_since(self.started).as_secs(),
+            events_total: self.events_total.load(Ordering::Relaxed),
+            events_per_sec: recent as f64 / RATE_WINDOW.as_secs_f64(),
+            parse_errors: self.parse_errors.load(Ordering::Relaxed),
+            emit_failures: self.emit_failures.load(Ordering::Relaxed),
+            db_writes: self.db_writes.lock().map(|s| s.clone()).unwrap_or_default(),
+            plugins,
+            restarts: self.restarts.lock().map(|r| r.clone()).unwrap_or_default(),
+            degraded: self.degraded.lock().map(|d| d.iter().cloned().collect()).unwrap_or_default(),
+            watch_mode: self.watch_mode.lock().ok().and_then(|mode| mode.map(str::to_string)),
+        }
+    }
+}
+
+impl Default for HealthMetrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Drop events that have left the rate window
+fn prune(recent: &mut VecDeque<Instant>, now: Instant) {
+    while recent.front().is_some_and(|t| now.saturating_duration_since(*t) > RATE_WINDOW) {
+        recent.pop_front();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_health_snapshot() {
+        let metrics = HealthMetrics::new();
+        let start = Instant::now();
+        for i in 0..20 {
+            metrics.record_event_at(start + Duration::from_millis(i * 100));
+        }
+        metrics.record_parse_error();
+        metrics.record_db_write(Duration::from_millis(2));
+        metrics.record_db_write(Duration::from_millis(4));
+        metrics.record_plugin("todoist", Duration::from_millis(30), false);
+        metrics.record_plugin("todoist", Duration::from_millis(10), true);
+
+        let snapshot = metrics.snapshot_at(start + Duration::from_secs(2));
+        assert_eq!(snapshot.events_total, 20);
+        assert_eq!(snapshot.events_per_sec, 2.0);
+        assert_eq!(snapshot.parse_errors, 1);
+        assert_eq!(snapshot.db_writes.count, 2);
+        assert_eq!(snapshot.db_writes.avg_ms, 3.0);
+        assert_eq!(snapshot.db_writes.max_ms, 4.0);
+        assert_eq!(snapshot.plugins[0].failures, 1);
+        assert_eq!(snapshot.plugins[0].latency.avg_ms, 20.0);
+
+        // The rate falls off once events leave the window; the total doesn't
+        let later = metrics.snapshot_at(start + Duration::from_secs(30));
+        assert_eq!(later.events_per_sec, 0.0);
+        assert_eq!(later.events_total, 20);
+        assert_eq!(later.summary_lines(Locale::En)[5], "Slowest plugin: todoist (20 ms avg, 1 failed)");
+    }
+}
@@ -0,0 +1,542 @@
+//! Claude Code hooks - the entries in Claude's `settings.json` that report tasks
+//!
+//! Claude Code runs a command for each hook event it has one configured for,
+//! with the event as JSON on its stdin. The overlay's hooks all run
+//! `<this executable> --progress-hook`, which turns the event into a line of
+//! the events file and exits; the flag is also how the overlay's hooks are
+//! told apart from the user's own hooks in the same file. `install` adds the
+//! hooks that are missing, `uninstall` removes every hook of the overlay's
+//! and `repair` swaps them for current ones; all three leave everything else
+//! in the file alone.
+
+use std::fs::OpenOptions;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::clock::{Clock, SystemClock};
+use crate::config::{Config, EVENTS_FILE_NAME};
+use crate::text;
+use crate::watcher::TaskEvent;
+
+/// Flag the hook command runs the overlay with
+pub const HOOK_FLAG: &str = "--progress-hook";
+
+/// Claude's settings file, inside a Claude config directory
+pub const SETTINGS_FILE_NAME: &str = "settings.json";
+
+/// Hook events the overlay listens to
+pub const HOOK_EVENTS: [&str; 4] = ["PreToolUse", "PostToolUse", "Notification", "SessionEnd"];
+/// Hook events that take a tool matcher; the overlay's match every tool
+const TOOL_EVENTS: [&str; 2] = ["PreToolUse", "PostToolUse"];
+
+/// Where a copy of the settings is kept before the overlay changes them
+const BACKUP_SUFFIX: &str = "agent-progress.bak";
+
+/// Whether the app was started as a hook
+pub fn hook_requested(mut args: impl Iterator<Item = String>) -> bool {
+    args.any(|arg| arg == HOOK_FLAG)
+}
+
+/// Whether the overlay's hooks are set up in one Claude config directory
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HookState {
+    /// Every hook is there and runs this executable
+    Installed,
+    /// Some of the overlay's hooks are there, but not all, or they run
+    /// another copy of the overlay, e.g. one that has since moved
+    Outdated,
+    NotInstalled,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HookStatus {
+    pub settings: PathBuf,
+    pub state: HookState,
+    /// Hook events without a current overlay hook
+    pub missing: Vec<String>,
+}
+
+pub fn settings_path(claude_dir: &Path) -> PathBuf {
+    claude_dir.join(SETTINGS_FILE_NAME)
+}
+
+/// The command each hook runs
+pub fn hook_command(exe: &Path) -> String {
+    format!("\"{}\" {}", exe.display(), HOOK_FLAG)
+}
+
+/// Whether a hook command is one of the overlay's, for any copy of the overlay
+pub fn is_overlay_command(command: &str) -> bool {
+    command.trim_end().ends_with(HOOK_FLAG)
+}
+
+/// Read Claude's settings; a missing file reads as empty
+pub fn read_settings(path: &Path) -> Result<Value, String> {
+    match std::fs::read_to_string(path) {
+        Ok(text) if text.trim().is_empty() => Ok(Value::Object(Default::default())),
+        Ok(text) => serde_json::from_str(&text).map_err(|e| format!("{} isn't valid JSON: {}", path.display(), e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Value::Object(Default::default())),
+        Err(e) => Err(format!("Failed to read {}: {}", path.display(), e)),
+    }
+}
+
+/// Write Claude's settings, keeping a copy of the previous file beside it
+fn write_settings(path: &Path, settings: &Value) -> Result<(), String> {
+    if path.exists() {
+        let backup = PathBuf::from(format!("{}.{}", path.display(), BACKUP_SUFFIX));
+        std::fs::copy(path, &backup).map_err(|e| format!("Failed to back up {}: {}", path.display(), e))?;
+    } else if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    let mut text = serde_json::to_string_pretty(settings).map_err(|e| e.to_string())?;
+    text.push('\n');
+    // Replace the file in one step, so Claude never reads half of it
+    let temp = path.with_extension("json.tmp");
+    std::fs::write(&temp, text).map_err(|e| format!("Failed to write {}: {}", temp.display(), e))?;
+    std::fs::rename(&temp, path).map_err(|e| format!("Failed to replace {}: {}", path.display(), e))
+}
+
+/// Commands configured for a hook event
+fn commands<'a>(settings: &'a Value, event: &str) -> impl Iterator<Item = &'a str> {
+    settings
+        .get("hooks")
+        .and_then(|hooks| hooks.get(event))
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|group| group.get("hooks").and_then(Value::as_array))
+        .flatten()
+        .filter_map(|hook| hook.get("command").and_then(Value::as_str))
+}
+
+/// Check the overlay's hooks in `claude_dir` against this executable
+pub fn status(claude_dir: &Path, exe: &Path) -> Result<HookStatus, String> {
+    let path = settings_path(claude_dir);
+    let settings = read_settings(&path)?;
+    Ok(status_of(&settings, path, &hook_command(exe)))
+}
+
+/// Add the overlay's hooks to `claude_dir`'s settings where they are missing
+pub fn install(claude_dir: &Path, exe: &Path) -> Result<HookStatus, String> {
+    let path = settings_path(claude_dir);
+    let mut settings = read_settings(&path)?;
+    if add_hooks(&mut settings, &hook_command(exe))? {
+        write_settings(&path, &settings)?;
+        tracing::info!("Installed hooks in {:?}", path);
+    }
+    Ok(status_of(&settings, path, &hook_command(exe)))
+}
+
+/// Remove the overlay's hooks from `claude_dir`'s settings, including ones
+/// for other copies of the overlay
+pub fn uninstall(claude_dir: &Path, exe: &Path) -> Result<HookStatus, String> {
+    let path = settings_path(claude_dir);
+    let mut settings = read_settings(&path)?;
+    if remove_hooks(&mut settings) {
+        write_settings(&path, &settings)?;
+        tracing::info!("Removed hooks from {:?}", path);
+    }
+    Ok(status_of(&settings, path, &hook_command(exe)))
+}
+
+/// Replace the overlay's hooks in `claude_dir`'s settings with ones for this executable
+pub fn repair(claude_dir: &Path, exe: &Path) -> Result<HookStatus, String> {
+    let path = settings_path(claude_dir);
+    let original = read_settings(&path)?;
+    let mut settings = original.clone();
+    remove_hooks(&mut settings);
+    add_hooks(&mut settings, &hook_command(exe))?;
+    if settings != original {
+        write_settings(&path, &settings)?;
+        tracing::info!("Repaired hooks in {:?}", path);
+    }
+    Ok(status_of(&settings, path, &hook_command(exe)))
+}
+
+/// Remove every overlay hook, then the groups and events left without hooks;
+/// false if there were none
+fn remove_hooks(settings: &mut Value) -> bool {
+    let Some(hooks) = settings.get_mut("hooks").and_then(Value::as_object_mut) else {
+        return false;
+    };
+    let mut removed = false;
+    let mut emptied = Vec::new();
+    for (event, groups) in hooks.iter_mut() {
+        let Some(groups) = groups.as_array_mut() else {
+            continue;
+        };
+        let had_groups = !groups.is_empty();
+        for group in groups.iter_mut() {
+            let Some(entries) = group.get_mut("hooks").and_then(Value::as_array_mut) else {
+                continue;
+            };
+            let before = entries.len();
+            entries.retain(|hook| !hook.get("command").and_then(Value::as_str).is_some_and(is_overlay_command));
+            if entries.len() < before {
+                removed = true;
+                // Only groups emptied here go; the user's empty ones stay as they were
+                if entries.is_empty() {
+                    *group = Value::Null;
+                }
+            }
+        }
+        groups.retain(|group| !group.is_null());
+        // Likewise only events emptied here go
+        if had_groups && groups.is_empty() {
+            emptied.push(event.clone());
+        }
+    }
+    if removed {
+        hooks.retain(|event, _| !emptied.contains(event));
+        if hooks.is_empty() {
+            if let Some(root) = settings.as_object_mut() {
+                root.remove("hooks");
+            }
+        }
+    }
+    removed
+}
+
+/// Add a hook running `command` to each hook event that lacks one; false if none did
+fn add_hooks(settings: &mut Value, command: &str) -> Result<bool, String> {
+    let not_object = |what: &str| format!("\"{}\" in Claude's settings isn't a JSON object", what);
+    let root = settings.as_object_mut().ok_or_else(|| not_object("settings"))?;
+    let hooks = root.entry("hooks").or_insert_with(|| json!({})).as_object_mut().ok_or_else(|| not_object("hooks"))?;
+    let mut added = false;
+    for event in HOOK_EVENTS {
+        let groups = hooks
+            .entry(event)
+            .or_insert_with(|| json!([]))
+            .as_array_mut()
+            .ok_or_else(|| format!("\"hooks.{}\" in Claude's settings isn't a list", event))?;
+        let present = groups
+            .iter()
+            .filter_map(|group| group.get("hooks").and_then(Value::as_array))
+            .flatten()
+            .any(|hook| hook.get("command").and_then(Value::as_str) == Some(command));
+        if present {
+            continue;
+        }
+        let mut group = json!({ "hooks": [{ "type": "command", "command": command }] });
+        if TOOL_EVENTS.contains(&event) {
+            group["matcher"] = json!("*");
+        }
+        groups.push(group);
+        added = true;
+    }
+    Ok(added)
+}
+
+fn status_of(settings: &Value, path: PathBuf, expected: &str) -> HookStatus {
+    let mut any = false;
+    let mut missing = Vec::new();
+    for event in HOOK_EVENTS {
+        let ours: Vec<&str> = commands(settings, event).filter(|command| is_overlay_command(command)).collect();
+        any |= !ours.is_empty();
+        if !ours.contains(&expected) {
+            missing.push(event.to_string());
+        }
+    }
+    let state = match (any, missing.is_empty()) {
+        (_, true) => HookState::Installed,
+        (true, false) => HookState::Outdated,
+        (false, false) => HookState::NotInstalled,
+    };
+    HookStatus { settings: path, state, missing }
+}
+
+// ============================================================================
+// Running as a hook
+// ============================================================================
+
+/// Report the hook event on stdin in the events file
+///
+/// A hook that fails or exits non-zero can hold Claude up, so problems are
+/// only written to stderr, which Claude shows in its verbose output.
+pub fn run_hook(config: &Config) {
+    let mut input = String::new();
+    if let Err(e) = std::io::stdin().read_to_string(&mut input) {
+        eprintln!("agent-progress: failed to read the hook event: {}", e);
+        return;
+    }
+    let payload: Value = match serde_json::from_str(&input) {
+        Ok(payload) => payload,
+        Err(e) => {
+            eprintln!("agent-progress: the hook event isn't valid JSON: {}", e);
+            return;
+        }
+    };
+    let Some(event) = event_for(&payload, SystemClock.now_ms() as u64) else {
+        return;
+    };
+    let path = hook_events_path(config);
+    if let Err(e) = append_event(&path, &event) {
+        eprintln!("agent-progress: failed to write {}: {}", path.display(), e);
+    }
+}
+
+/// The events file for the Claude directory the hook runs for
+///
+/// Claude passes `CLAUDE_CONFIG_DIR` on to hooks, so a profile's hooks write
+/// to the profile's own events file.
+fn hook_events_path(config: &Config) -> PathBuf {
+    match std::env::var_os("CLAUDE_CONFIG_DIR").filter(|dir| !dir.is_empty()) {
+        Some(dir) if Path::new(&dir) != config.claude_dir() => Path::new(&dir).join(EVENTS_FILE_NAME),
+        _ => config.events_path(),
+    }
+}
+
+/// Append `event` as one line, in a single write so concurrent hooks don't interleave
+pub fn append_event(path: &Path, event: &TaskEvent) -> std::io::Result<()> {
+    let mut line = serde_json::to_string(event).map_err(std::io::Error::other)?;
+    line.push('\n');
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    file.write_all(line.as_bytes())
+}
+
+/// The event a hook payload reports, if any
+fn event_for(payload: &Value, now_ms: u64) -> Option<TaskEvent> {
+    let field = |name: &str| payload.get(name).and_then(Value::as_str).map(str::to_string);
+    let session_id = field("session_id");
+    let mut event = match field("hook_event_name")?.as_str() {
+        "PreToolUse" => {
+            let input = payload.get("tool_input");
+            let input_field = |name: &str| input.and_then(|input| input.get(name));
+            let mut event = TaskEvent::new("task_started", &field("tool_use_id")?, now_ms);
+            event.tool = field("tool_name");
+            event.description = describe(input);
+            event.background = input_field("run_in_background").and_then(Value::as_bool);
+            event.subagent_type = input_field("subagent_type").and_then(Value::as_str).map(str::to_string);
+            event.project_path = field("cwd");
+            event
+        }
+        "PostToolUse" => {
+            let response = payload.get("tool_response");
+            let exit_code = ["exit_code", "exitCode"]
+                .iter()
+                .find_map(|key| response.and_then(|response| response.get(*key)).and_then(Value::as_i64))
+                .map(|code| code as i32);
+            let failed = response.and_then(|response| response.get("is_error")).and_then(Value::as_bool) == Some(true)
+                || response.and_then(|response| response.get("error")).is_some_and(|error| !error.is_null())
+                || exit_code.is_some_and(|code| code != 0);
+            let kind = if failed { "task_error" } else { "task_complete" };
+            let mut event = TaskEvent::new(kind, &field("tool_use_id")?, now_ms);
+            event.exit_code = exit_code;
+            event
+        }
+        "Notification" => {
+            // Only the prompts that hold the agent up until the user answers
+            let message = field("message")?;
+            if !message.to_lowercase().contains("permission") && !message.to_lowercase().contains("waiting for your input") {
+                return None;
+            }
+            let mut event = TaskEvent::new("awaiting_input", &format!("{}-input", session_id.as_deref()?), now_ms);
+            event.description = Some(message);
+            event
+        }
+        "SessionEnd" => {
+            let mut event = TaskEvent::new("session_stopped", "", now_ms);
+            event.project_path = field("cwd");
+            event
+        }
+        _ => return None,
+    };
+    event.session_id = session_id;
+    Some(event)
+}
+
+/// A short description of what a tool call does, from its input
+fn describe(input: Option<&Value>) -> Option<String> {
+    let input = input?;
+    let summary = ["description", "command", "file_path", "pattern", "url", "query", "prompt"]
+        .iter()
+        .find_map(|key| input.get(*key).and_then(Value::as_str))?;
+    Some(text::clean_description(summary))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings_with(events: &[&str], command: &str) -> Value {
+        let mut hooks = serde_json::Map::new();
+        for event in events {
+            hooks.insert(event.to_string(), json!([{ "matcher": "*", "hooks": [{ "type": "command", "command": command }] }]));
+        }
+        // Someone else's hook, which doesn't count
+        hooks.insert("Stop".to_string(), json!([{ "hooks": [{ "type": "command", "command": "notify-send done" }] }]));
+        json!({ "model": "opus", "hooks": hooks })
+    }
+
+    #[test]
+    fn test_status() {
+        let exe = Path::new("/opt/overlay/agent-progress-overlay");
+        let expected = hook_command(exe);
+        let path = PathBuf::from("settings.json");
+
+        let status = status_of(&settings_with(&HOOK_EVENTS, &expected), path.clone(), &expected);
+        assert_eq!(status.state, HookState::Installed);
+
+        let status = status_of(&settings_with(&["PreToolUse"], &expected), path.clone(), &expected);
+        assert_eq!(status.state, HookState::Outdated);
+        assert_eq!(status.missing, ["PostToolUse", "Notification", "SessionEnd"]);
+
+        let moved = hook_command(Path::new("/old/agent-progress-overlay"));
+        let status = status_of(&settings_with(&HOOK_EVENTS, &moved), path.clone(), &expected);
+        assert_eq!(status.state, HookState::Outdated);
+
+        let status = status_of(&json!({}), path, &expected);
+        assert_eq!(status.state, HookState::NotInstalled);
+    }
+
+    #[test]
+    fn test_install_keeps_other_settings() {
+        let dir = tempfile::tempdir().unwrap();
+        let exe = Path::new("/opt/overlay/agent-progress-overlay");
+        let original = settings_with(&[], "unused");
+        std::fs::write(settings_path(dir.path()), original.to_string()).unwrap();
+
+        let status = install(dir.path(), exe).unwrap();
+        assert_eq!(status.state, HookState::Installed);
+        let settings = read_settings(&settings_path(dir.path())).unwrap();
+        assert_eq!(settings["model"], "opus");
+        assert_eq!(settings["hooks"]["Stop"], original["hooks"]["Stop"]);
+        assert_eq!(settings["hooks"]["PreToolUse"][0]["matcher"], "*");
+        assert!(settings["hooks"]["SessionEnd"][0].get("matcher").is_none());
+        assert!(dir.path().join("settings.json.agent-progress.bak").is_file());
+
+        // Installing again changes nothing
+        install(dir.path(), exe).unwrap();
+        assert_eq!(read_settings(&settings_path(dir.path())).unwrap(), settings);
+    }
+
+    #[test]
+    fn test_install_keeps_key_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let exe = Path::new("/opt/overlay/agent-progress-overlay");
+        std::fs::write(settings_path(dir.path()), r#"{"theme": "dark", "model": "opus", "env": {"Z": "1", "A": "2"}}"#).unwrap();
+
+        install(dir.path(), exe).unwrap();
+        let settings = read_settings(&settings_path(dir.path())).unwrap();
+        let keys: Vec<&String> = settings.as_object().unwrap().keys().collect();
+        assert_eq!(keys, ["theme", "model", "env", "hooks"]);
+        let env: Vec<&String> = settings["env"].as_object().unwrap().keys().collect();
+        assert_eq!(env, ["Z", "A"]);
+    }
+
+    #[test]
+    fn test_uninstall_keeps_other_hooks() {
+        let dir = tempfile::tempdir().unwrap();
+        let exe = Path::new("/opt/overlay/agent-progress-overlay");
+        let original = json!({
+            "model": "opus",
+            "hooks": {
+                "PreToolUse": [{ "matcher": "Bash", "hooks": [{ "type": "command", "command": "audit.sh" }] }],
+            },
+        });
+        std::fs::write(settings_path(dir.path()), original.to_string()).unwrap();
+        install(dir.path(), exe).unwrap();
+
+        let status = uninstall(dir.path(), exe).unwrap();
+        assert_eq!(status.state, HookState::NotInstalled);
+        assert_eq!(read_settings(&settings_path(dir.path())).unwrap(), original);
+
+        // The user's own empty events stay
+        let with_empty = json!({ "hooks": { "Stop": [], "UserPromptSubmit": [{ "hooks": [] }] } });
+        std::fs::write(settings_path(dir.path()), with_empty.to_string()).unwrap();
+        install(dir.path(), exe).unwrap();
+        uninstall(dir.path(), exe).unwrap();
+        assert_eq!(read_settings(&settings_path(dir.path())).unwrap(), with_empty);
+
+        // Without other hooks the "hooks" key goes too
+        std::fs::write(settings_path(dir.path()), json!({ "model": "opus" }).to_string()).unwrap();
+        install(dir.path(), exe).unwrap();
+        uninstall(dir.path(), exe).unwrap();
+        assert_eq!(read_settings(&settings_path(dir.path())).unwrap(), json!({ "model": "opus" }));
+    }
+
+    #[test]
+    fn test_repair_replaces_stale_hooks() {
+        let dir = tempfile::tempdir().unwrap();
+        let exe = Path::new("/opt/overlay/agent-progress-overlay");
+        let stale = hook_command(Path::new("/old/place/agent-progress-overlay"));
+        std::fs::write(settings_path(dir.path()), settings_with(&["PreToolUse", "PostToolUse"], &stale).to_string())
+            .unwrap();
+        assert_eq!(status(dir.path(), exe).unwrap().state, HookState::Outdated);
+
+        let status = repair(dir.path(), exe).unwrap();
+        assert_eq!(status.state, HookState::Installed);
+        let settings = read_settings(&settings_path(dir.path())).unwrap();
+        assert!(!settings.to_string().contains("/old/place"));
+        assert_eq!(settings["hooks"]["PreToolUse"].as_array().unwrap().len(), 1);
+        assert_eq!(settings["hooks"]["Stop"][0]["hooks"][0]["command"], "notify-send done");
+    }
+
+    #[test]
+    fn test_hook_payloads_become_events() {
+        let started = event_for(&json!({
+            "hook_event_name": "PreToolUse",
+            "session_id": "abc",
+            "cwd": "/home/dev/api",
+            "tool_name": "Bash",
+            "tool_use_id": "toolu_01",
+            "tool_input": { "command": "cargo test", "run_in_background": true },
+        }), 1000).unwrap();
+        assert_eq!(started.event_type, "task_started");
+        assert_eq!(started.task_id, "toolu_01");
+        assert_eq!(started.description.as_deref(), Some("cargo test"));
+        assert_eq!(started.background, Some(true));
+        assert_eq!(started.session_id.as_deref(), Some("abc"));
+        assert_eq!(started.project_path.as_deref(), Some("/home/dev/api"));
+
+        let failed = event_for(&json!({
+            "hook_event_name": "PostToolUse",
+            "session_id": "abc",
+            "tool_use_id": "toolu_01",
+            "tool_response": { "is_error": true },
+        }), 2000).unwrap();
+        assert_eq!(failed.event_type, "task_error");
+
+        let exited = event_for(&json!({
+            "hook_event_name": "PostToolUse",
+            "session_id": "abc",
+            "tool_use_id": "toolu_02",
+            "tool_response": { "stdout": "", "exit_code": 2 },
+        }), 2000).unwrap();
+        assert_eq!(exited.event_type, "task_error");
+        assert_eq!(exited.exit_code, Some(2));
+
+        let waiting = event_for(&json!({
+            "hook_event_name": "Notification",
+            "session_id": "abc",
+            "message": "Claude needs your permission to use Bash",
+        }), 3000).unwrap();
+        assert_eq!(waiting.event_type, "awaiting_input");
+        assert!(event_for(&json!({ "hook_event_name": "Notification", "session_id": "abc", "message": "Done" }), 0).is_none());
+
+        let ended = event_for(&json!({ "hook_event_name": "SessionEnd", "session_id": "abc" }), 4000).unwrap();
+        assert_eq!(ended.event_type, "session_stopped");
+        assert!(event_for(&json!({ "hook_event_name": "Stop" }), 0).is_none());
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("progress-events.jsonl");
+        append_event(&path, &started).unwrap();
+        append_event(&path, &ended).unwrap();
+        let text = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<TaskEvent> = text.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[1].event_type, "session_stopped");
+    }
+
+    #[test]
+    fn test_missing_settings_read_as_empty() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(read_settings(&dir.path().join("settings.json")).unwrap(), json!({}));
+        std::fs::write(dir.path().join("settings.json"), "{ not json").unwrap();
+        assert!(read_settings(&dir.path().join("settings.json")).is_err());
+    }
+}
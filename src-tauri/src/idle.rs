@@ -0,0 +1,159 @@
+//! Noticing when the user has stepped away
+//!
+//! Popups raised while nobody is at the machine are stale by the time anyone
+//! sees them. While the user is idle, finished tasks are collected instead
+//! and shown as one summary when they come back.
+//!
+//! Idle time is read from the OS on Windows and macOS. Elsewhere it isn't
+//! available, and the user is never considered away.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use serde::Serialize;
+
+/// Tasks named in a summary; the rest are only counted
+const SUMMARY_RECENT_TASKS: usize = 3;
+
+/// Time since the last keyboard or mouse input, if the OS reports it
+#[cfg(any(windows, target_os = "macos"))]
+pub fn idle_time() -> Option<Duration> {
+    match user_idle::UserIdle::get_time() {
+        Ok(idle) => Some(Duration::from_secs(idle.as_seconds())),
+        Err(e) => {
+            tracing::debug!("Failed to read idle time: {:?}", e);
+            None
+        }
+    }
+}
+
+/// Time since the last keyboard or mouse input, if the OS reports it
+#[cfg(not(any(windows, target_os = "macos")))]
+pub fn idle_time() -> Option<Duration> {
+    None
+}
+
+/// What finished while the user was away
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct AwaySummary {
+    /// When the user went idle, in millis
+    pub since_ms: i64,
+    pub completed: usize,
+    pub failed: usize,
+    /// The last few tasks to finish, newest last
+    pub recent: Vec<String>,
+}
+
+impl AwaySummary {
+    pub fn is_empty(&self) -> bool {
+        self.completed == 0 && self.failed == 0
+    }
+}
+
+/// Whether the user is at the machine, worked out from their idle time
+pub struct Presence {
+    /// Idle this long counts as away; zero turns detection off
+    threshold: Duration,
+    away: Option<AwaySummary>,
+    /// What each active task is called, for the summary
+    labels: HashMap<String, String>,
+}
+
+impl Presence {
+    pub fn new(threshold: Duration) -> Self {
+        Self {
+            threshold,
+            away: None,
+            labels: HashMap::new(),
+        }
+    }
+
+    pub fn is_away(&self) -> bool {
+        self.away.is_some()
+    }
+
+    /// Update from the current idle time
+    ///
+    /// Returns what finished while the user was away when they come back.
+    pub fn observe(&mut self, idle: Duration, now_ms: i64) -> Option<AwaySummary> {
+        let idle_ms = idle.as_millis() as i64;
+        if !self.threshold.is_zero() && idle >= self.threshold {
+            self.away.get_or_insert_with(|| AwaySummary {
+                since_ms: now_ms - idle_ms,
+                ..AwaySummary::default()
+            });
+            None
+        } else {
+            self.away.take()
+        }
+    }
+
+    pub fn task_started(&mut self, task_id: &str, label: &str) {
+        self.labels.insert(task_id.to_string(), label.to_string());
+    }
+
+    /// Forget a task, counting it toward the summary if the user is away
+    ///
+    /// Returns what the task was called, if it was seen starting.
+    pub fn task_ended(&mut self, task_id: &str, failed: Option<bool>) -> Option<String> {
+        let label = self.labels.remove(task_id);
+        let (Some(away), Some(failed)) = (&mut self.away, failed) else {
+            return label;
+        };
+        if failed {
+            away.failed += 1;
+        } else {
+            away.completed += 1;
+        }
+        if let Some(label) = &label {
+            away.recent.push(label.clone());
+            if away.recent.len() > SUMMARY_RECENT_TASKS {
+                away.recent.remove(0);
+            }
+        }
+        label
+    }
+
+    /// Forget every active task, as when the session stops
+    pub fn clear_tasks(&mut self) {
+        self.labels.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_presence() {
+        let mut presence = Presence::new(Duration::from_secs(300));
+        presence.task_started("before", "npm test");
+        presence.task_started("canceled", "sleep 100");
+
+        assert_eq!(presence.observe(Duration::from_secs(10), 1_000_000), None);
+        assert!(!presence.is_away());
+        presence.task_ended("canceled", None);
+
+        // Away from the start of the idle period, not from when it was noticed
+        assert_eq!(presence.observe(Duration::from_secs(300), 1_000_000), None);
+        assert!(presence.is_away());
+        presence.task_ended("before", Some(false));
+        for index in 0..4 {
+            let id = format!("task-{}", index);
+            presence.task_started(&id, &format!("step {}", index));
+            presence.task_ended(&id, Some(index == 3));
+        }
+
+        let summary = presence.observe(Duration::ZERO, 2_000_000).unwrap();
+        assert_eq!(summary.since_ms, 700_000);
+        assert_eq!((summary.completed, summary.failed), (4, 1));
+        assert_eq!(summary.recent, ["step 1", "step 2", "step 3"]);
+        assert!(!presence.is_away());
+        assert_eq!(presence.observe(Duration::ZERO, 2_000_000), None);
+
+        // A zero threshold never counts as away
+        let mut off = Presence::new(Duration::ZERO);
+        assert_eq!(off.observe(Duration::from_secs(86_400), 0), None);
+        assert!(!off.is_away());
+    }
+}
@@ -0,0 +1,493 @@
+//! Core socket - how overlays attach to the core
+//!
+//! The core serves newline-delimited JSON on `127.0.0.1:<core.port>`. Each
+//! overlay that connects first sends a `hello` with the install's core token
+//! (see `secret`), then gets a `welcome` with the current todo groups, then
+//! every message the core produces. Any number of overlays can attach; the
+//! history database is shared, so they read it directly. The protocol is
+//! documented in `docs/core-protocol.md`.
+
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::sync::Notify;
+
+use crate::config::{self, Config};
+use crate::core::{self, CoreSink};
+use crate::health::{self, HealthSnapshot};
+use crate::logs::{self, LogLine};
+use crate::router::{OverdueTask, ReconciledSnapshot};
+use crate::secret;
+use crate::storage::StorageUsage;
+use crate::watcher::{TodoGroup, WatcherEvent};
+
+/// Bumped on incompatible protocol changes
+pub const PROTOCOL_VERSION: u32 = 2;
+/// Messages held for a slow overlay before it starts missing them
+const CHANNEL_CAPACITY: usize = 1024;
+/// How long an overlay waits to find out whether a core is listening
+const CONNECT_TIMEOUT: Duration = Duration::from_millis(500);
+/// How often an attached overlay retries after the core goes away
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(2);
+/// How often the core reports its health metrics to attached overlays
+const HEALTH_INTERVAL: Duration = Duration::from_secs(5);
+/// How long an overlay waits for a core it started to start listening
+const SPAWN_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long the core waits for a connecting overlay's hello
+const HELLO_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Messages sent by the core
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CoreMessage {
+    Welcome {
+        protocol_version: u32,
+        app_version: String,
+        todo_groups: Vec<TodoGroup>,
+    },
+    Event {
+        event: WatcherEvent,
+    },
+    TodoGroups {
+        groups: Vec<TodoGroup>,
+    },
+    Reconciled {
+        snapshot: ReconciledSnapshot,
+    },
+    Overdue {
+        task: OverdueTask,
+    },
+    StorageWarning {
+        usage: StorageUsage,
+    },
+    /// An editor asked over the bridge to show, hide or toggle the overlay
+    WindowAction {
+        action: String,
+    },
+    /// The core's health metrics, sent every few seconds
+    Health {
+        snapshot: HealthSnapshot,
+    },
+    /// A line the core logged
+    Log {
+        line: LogLine,
+    },
+}
+
+/// Messages sent by an overlay
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum OverlayMessage {
+    /// The first message on every connection; the core closes it unless the token matches
+    Hello { token: String },
+    /// Shut the core down, as `--stop-core` does
+    Stop,
+    /// Apply the `[logging]` settings saved in the config
+    ReloadLogging,
+}
+
+/// Sends the core's output to every attached overlay
+pub struct IpcSink {
+    tx: broadcast::Sender<Arc<str>>,
+    /// The last todo groups, so an overlay attaching later starts with them
+    todo_groups: Arc<Mutex<Vec<TodoGroup>>>,
+    stop: Arc<Notify>,
+}
+
+impl IpcSink {
+    fn send(&self, message: &CoreMessage) {
+        match serde_json::to_string(message) {
+            // No receivers just means no overlay is attached
+            Ok(line) => {
+                let _ = self.tx.send(line.into());
+            }
+            Err(e) => tracing::error!("Failed to encode core message: {}", e),
+        }
+    }
+
+    /// Send a logged line to attached overlays
+    pub fn log(&self, line: LogLine) {
+        if self.tx.receiver_count() > 0 {
+            self.send(&CoreMessage::Log { line });
+        }
+    }
+
+    /// Wait until an overlay asks the core to stop
+    pub async fn stop_requested(&self) {
+        self.stop.notified().await;
+    }
+}
+
+impl CoreSink for IpcSink {
+    fn event(&self, event: WatcherEvent) {
+        self.send(&CoreMessage::Event { event });
+    }
+
+    fn todo_groups(&self, groups: Vec<TodoGroup>) {
+        if let Ok(mut latest) = self.todo_groups.lock() {
+            latest.clone_from(&groups);
+        }
+        self.send(&CoreMessage::TodoGroups { groups });
+    }
+
+    fn reconciled(&self, snapshot: &ReconciledSnapshot) {
+        self.send(&CoreMessage::Reconciled { snapshot: snapshot.clone() });
+    }
+
+    fn overdue(&self, task: &OverdueTask) {
+        self.send(&CoreMessage::Overdue { task: task.clone() });
+    }
+
+    fn storage_warning(&self, usage: &StorageUsage) {
+        self.send(&CoreMessage::StorageWarning { usage: usage.clone() });
+    }
+
+    /// Handled if any overlay is attached to carry it out
+    fn window_action(&self, action: &str) -> bool {
+        self.send(&CoreMessage::WindowAction { action: action.to_string() });
+        self.tx.receiver_count() > 0
+    }
+}
+
+/// The token overlays present to the core, created on first use
+pub fn core_token() -> io::Result<String> {
+    secret::load_or_create(&config::get_core_token_path())
+}
+
+/// Listen for overlays presenting `token` on `port`; fails if the port is taken, e.g. by another core
+pub async fn serve(port: u16, token: String) -> io::Result<IpcSink> {
+    let listener = TcpListener::bind(("127.0.0.1", port)).await?;
+    tracing::info!("Core listening on 127.0.0.1:{}", port);
+
+    let (tx, _) = broadcast::channel(CHANNEL_CAPACITY);
+    let sink = IpcSink {
+        tx: tx.clone(),
+        todo_groups: Arc::new(Mutex::new(Vec::new())),
+        stop: Arc::new(Notify::new()),
+    };
+    let (todo_groups, stop) = (sink.todo_groups.clone(), sink.stop.clone());
+    let token: Arc<str> = token.into();
+    tokio::spawn(async move {
+        loop {
+            match listener.accept().await {
+                Ok((stream, addr)) => {
+                    tracing::info!("Overlay connected from {}", addr);
+                    let welcome = CoreMessage::Welcome {
+                        protocol_version: PROTOCOL_VERSION,
+                        app_version: env!("CARGO_PKG_VERSION").to_string(),
+                        todo_groups: todo_groups.lock().map(|groups| groups.clone()).unwrap_or_default(),
+                    };
+                    tokio::spawn(serve_overlay(stream, token.clone(), welcome, tx.subscribe(), stop.clone()));
+                }
+                Err(e) => tracing::error!("Failed to accept overlay connection: {}", e),
+            }
+        }
+    });
+
+    let health_tx = tx.clone();
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(HEALTH_INTERVAL);
+        loop {
+            ticker.tick().await;
+            if health_tx.receiver_count() == 0 {
+                continue;
+            }
+            match serde_json::to_string(&CoreMessage::Health { snapshot: health::metrics().snapshot() }) {
+                Ok(line) => {
+                    let _ = health_tx.send(line.into());
+                }
+                Err(e) => tracing::error!("Failed to encode core health: {}", e),
+            }
+        }
+    });
+    Ok(sink)
+}
+
+/// Whether the first line from an overlay is a hello with the right token
+fn check_hello(line: &str, token: &str) -> bool {
+    matches!(
+        serde_json::from_str::<OverlayMessage>(line),
+        Ok(OverlayMessage::Hello { token: given }) if secret::tokens_match(&given, token)
+    )
+}
+
+async fn serve_overlay(
+    stream: TcpStream,
+    token: Arc<str>,
+    welcome: CoreMessage,
+    mut rx: broadcast::Receiver<Arc<str>>,
+    stop: Arc<Notify>,
+) {
+    let welcome = match serde_json::to_string(&welcome) {
+        Ok(line) => line,
+        Err(e) => {
+            tracing::error!("Failed to encode core welcome: {}", e);
+            return;
+        }
+    };
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+    match tokio::time::timeout(HELLO_TIMEOUT, lines.next_line()).await {
+        Ok(Ok(Some(line))) if check_hello(&line, &token) => {}
+        _ => {
+            tracing::warn!("Closed a connection to the core that didn't present the core token");
+            return;
+        }
+    }
+    if write_line(&mut writer, &welcome).await.is_err() {
+        return;
+    }
+    tracing::info!("Overlay attached");
+    loop {
+        tokio::select! {
+            message = rx.recv() => match message {
+                Ok(line) => {
+                    if write_line(&mut writer, &line).await.is_err() {
+                        break;
+                    }
+                }
+                Err(RecvError::Lagged(missed)) => tracing::warn!("Overlay fell behind; {} messages dropped", missed),
+                Err(RecvError::Closed) => return,
+            },
+            line = lines.next_line() => match line {
+                Ok(Some(line)) => match serde_json::from_str::<OverlayMessage>(&line) {
+                    Ok(OverlayMessage::Hello { .. }) => {}
+                    Ok(OverlayMessage::Stop) => {
+                        tracing::info!("Overlay asked the core to stop");
+                        stop.notify_one();
+                    }
+                    Ok(OverlayMessage::ReloadLogging) => {
+                        let config = Config::load().unwrap_or_default();
+                        match logs::configure(&config) {
+                            Ok(()) => tracing::info!("Log level is now {}", config.logging.level),
+                            Err(e) => tracing::warn!("Failed to apply logging settings: {}", e),
+                        }
+                    }
+                    Err(e) => tracing::warn!("Invalid message from an overlay: {}", e),
+                },
+                _ => break,
+            },
+        }
+    }
+    tracing::info!("Overlay detached");
+}
+
+async fn write_line(writer: &mut (impl AsyncWriteExt + Unpin), line: &str) -> io::Result<()> {
+    writer.write_all(line.as_bytes()).await?;
+    writer.write_all(b"\n").await
+}
+
+/// Connect to the core on `port` and present `token`, if a core is running
+pub async fn connect(port: u16, token: &str) -> Option<TcpStream> {
+    let mut stream = match tokio::time::timeout(CONNECT_TIMEOUT, TcpStream::connect(("127.0.0.1", port))).await {
+        Ok(Ok(stream)) => stream,
+        _ => return None,
+    };
+    let hello = serde_json::to_string(&OverlayMessage::Hello { token: token.to_string() }).ok()?;
+    write_line(&mut stream, &hello).await.ok()?;
+    Some(stream)
+}
+
+/// Connect to the core on `port`, starting it first if it isn't running
+pub async fn connect_or_spawn(port: u16, token: &str) -> Option<TcpStream> {
+    if let Some(stream) = connect(port, token).await {
+        return Some(stream);
+    }
+    if let Err(e) = core::spawn_headless() {
+        tracing::error!("Failed to start the core: {}", e);
+        return None;
+    }
+    let started = tokio::time::Instant::now();
+    while started.elapsed() < SPAWN_TIMEOUT {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        if let Some(stream) = connect(port, token).await {
+            tracing::info!("Started the core on port {}", port);
+            return Some(stream);
+        }
+    }
+    tracing::error!("The core didn't start listening on port {}", port);
+    None
+}
+
+/// Ask the core on `port` to shut down; `false` if none is running
+pub async fn request_stop(port: u16, token: &str) -> io::Result<bool> {
+    request(port, token, &OverlayMessage::Stop).await
+}
+
+/// Ask the running core to apply changed logging settings; false if it isn't running
+pub async fn request_reload_logging(port: u16, token: &str) -> io::Result<bool> {
+    request(port, token, &OverlayMessage::ReloadLogging).await
+}
+
+async fn request(port: u16, token: &str, message: &OverlayMessage) -> io::Result<bool> {
+    let Some(mut stream) = connect(port, token).await else {
+        return Ok(false);
+    };
+    let line = serde_json::to_string(message).map_err(io::Error::other)?;
+    write_line(&mut stream, &line).await?;
+    stream.shutdown().await?;
+    Ok(true)
+}
+
+/// Pass the core's messages to `sink`, restarting the core whenever it goes away
+pub async fn attach(port: u16, token: String, mut stream: TcpStream, sink: Arc<dyn CoreSink>) {
+    loop {
+        follow(stream, sink.as_ref()).await;
+        tracing::warn!("Lost the core; reconnecting");
+        stream = loop {
+            tokio::time::sleep(RECONNECT_INTERVAL).await;
+            if let Some(stream) = connect_or_spawn(port, &token).await {
+                break stream;
+            }
+        };
+        tracing::info!("Reattached to the core");
+    }
+}
+
+/// Pass each message on `stream` to `sink` until the core closes it
+async fn follow(stream: TcpStream, sink: &dyn CoreSink) {
+    let mut lines = BufReader::new(stream).lines();
+    loop {
+        let line = match lines.next_line().await {
+            Ok(Some(line)) => line,
+            Ok(None) => return,
+            Err(e) => {
+                tracing::error!("Failed to read from the core: {}", e);
+                return;
+            }
+        };
+        match serde_json::from_str::<CoreMessage>(&line) {
+            Ok(message) => deliver(message, sink),
+            Err(e) => tracing::warn!("Invalid message from the core: {}", e),
+        }
+    }
+}
+
+fn deliver(message: CoreMessage, sink: &dyn CoreSink) {
+    match message {
+        CoreMessage::Welcome { protocol_version, app_version, todo_groups } => {
+            if protocol_version != PROTOCOL_VERSION {
+                tracing::warn!(
+                    "Core speaks protocol {}, this overlay {}; update whichever is older",
+                    protocol_version,
+                    PROTOCOL_VERSION
+                );
+            } else if app_version != env!("CARGO_PKG_VERSION") {
+                tracing::info!("Attached to core version {}; restart it with --stop-core to update it", app_version);
+            }
+            sink.todo_groups(todo_groups);
+        }
+        CoreMessage::Event { event } => sink.event(event),
+        CoreMessage::TodoGroups { groups } => sink.todo_groups(groups),
+        CoreMessage::Reconciled { snapshot } => sink.reconciled(&snapshot),
+        CoreMessage::Overdue { task } => sink.overdue(&task),
+        CoreMessage::StorageWarning { usage } => sink.storage_warning(&usage),
+        CoreMessage::WindowAction { action } => {
+            sink.window_action(&action);
+        }
+        CoreMessage::Health { snapshot } => health::set_core_snapshot(snapshot),
+        CoreMessage::Log { line } => logs::relay(line),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::watcher::GenericProgress;
+
+    #[derive(Default)]
+    struct Recorder(Mutex<Vec<String>>);
+
+    impl CoreSink for Recorder {
+        fn event(&self, event: WatcherEvent) {
+            self.0.lock().unwrap().push(format!("event {:?}", event));
+        }
+
+        fn todo_groups(&self, groups: Vec<TodoGroup>) {
+            self.0.lock().unwrap().push(format!("groups {}", groups.len()));
+        }
+
+        fn reconciled(&self, snapshot: &ReconciledSnapshot) {
+            self.0.lock().unwrap().push(format!("reconciled {:?}", snapshot.expired));
+        }
+
+        fn overdue(&self, task: &OverdueTask) {
+            self.0.lock().unwrap().push(format!("overdue {}", task.task_id));
+        }
+
+        fn storage_warning(&self, usage: &StorageUsage) {
+            self.0.lock().unwrap().push(format!("storage {}", usage.total_bytes));
+        }
+
+        fn window_action(&self, action: &str) -> bool {
+            self.0.lock().unwrap().push(format!("window {}", action));
+            true
+        }
+    }
+
+    #[test]
+    fn test_attached_overlay_receives_messages() {
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+        runtime.block_on(attached_overlay_receives_messages());
+    }
+
+    async fn attached_overlay_receives_messages() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        drop(listener);
+
+        let sink = serve(port, "secret".to_string()).await.unwrap();
+        // Without the token the core closes the connection before its welcome
+        let mut stranger = BufReader::new(connect(port, "guess").await.expect("core should accept")).lines();
+        assert!(stranger.next_line().await.unwrap().is_none());
+
+        let stream = connect(port, "secret").await.expect("core should accept");
+        let recorder = Arc::new(Recorder::default());
+        let following = tokio::spawn({
+            let recorder = recorder.clone();
+            async move { follow(stream, recorder.as_ref()).await }
+        });
+
+        // Wait for the welcome, so the overlay is subscribed before anything is sent
+        while recorder.0.lock().unwrap().is_empty() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        sink.event(WatcherEvent::GenericProgress(GenericProgress {
+            id: "build".to_string(),
+            label: None,
+            percent: Some(50.0),
+            message: None,
+        }));
+        sink.reconciled(&ReconciledSnapshot { active: Vec::new(), expired: vec!["t1".to_string()] });
+        assert!(sink.window_action("toggle_overlay"));
+        while recorder.0.lock().unwrap().len() < 4 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        following.abort();
+
+        let received = recorder.0.lock().unwrap().clone();
+        assert_eq!(received[0], "groups 0");
+        assert!(received[1].starts_with("event GenericProgress"));
+        assert_eq!(received[2], r#"reconciled ["t1"]"#);
+        assert_eq!(received[3], "window toggle_overlay");
+
+        // Any overlay with the token can shut the core down
+        assert!(request_stop(port, "secret").await.unwrap());
+        tokio::time::timeout(Duration::from_secs(5), sink.stop_requested()).await.expect("stop should arrive");
+    }
+
+    #[test]
+    fn test_event_round_trip() {
+        let line = serde_json::to_string(&CoreMessage::Event { event: WatcherEvent::Error("boom".to_string()) }).unwrap();
+        assert_eq!(line, r#"{"type":"event","event":{"type":"error","data":"boom"}}"#);
+        let Ok(CoreMessage::Event { event: WatcherEvent::Error(message) }) = serde_json::from_str(&line) else {
+            panic!("expected an error event");
+        };
+        assert_eq!(message, "boom");
+    }
+}
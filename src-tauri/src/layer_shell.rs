@@ -0,0 +1,106 @@
+//! Wayland layer-shell surfaces
+//!
+//! Wayland doesn't let ordinary windows place themselves or stay above
+//! fullscreen and tiled windows. On compositors with wlr-layer-shell (Sway,
+//! Hyprland, KDE Plasma and others), the overlay and its popups become
+//! surfaces on the overlay layer instead. They are anchored to the screen's
+//! top-left corner and placed by their margins, so the positions worked out
+//! for normal windows still apply. Everywhere else, and with
+//! `window.layer_shell` off, they stay normal windows.
+
+use std::sync::OnceLock;
+
+use tauri::WebviewWindow;
+
+use crate::config::WindowConfig;
+
+static ENABLED: OnceLock<bool> = OnceLock::new();
+
+/// Decide once, at startup, whether windows become layer surfaces
+pub fn init(config: &WindowConfig) {
+    let enabled = config.layer_shell && platform::supported();
+    if enabled {
+        tracing::info!("Showing the overlay as a layer-shell surface");
+    }
+    let _ = ENABLED.set(enabled);
+}
+
+pub fn enabled() -> bool {
+    ENABLED.get().copied().unwrap_or(false)
+}
+
+/// Turn a window into a layer surface; `interactive` ones can take keyboard focus when clicked
+pub fn attach(window: &WebviewWindow, interactive: bool) {
+    if !enabled() {
+        return;
+    }
+    let target = window.clone();
+    // GTK is only usable from the main thread
+    let _ = window.run_on_main_thread(move || platform::attach(&target, interactive));
+}
+
+/// Move a layer surface to a logical position; false for normal windows, which move themselves
+pub fn place(window: &WebviewWindow, x: f64, y: f64) -> bool {
+    if !enabled() {
+        return false;
+    }
+    let target = window.clone();
+    let _ = window.run_on_main_thread(move || platform::place(&target, x, y));
+    true
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use gtk::prelude::*;
+    use gtk_layer_shell::{Edge, KeyboardMode, Layer, LayerShell};
+    use tauri::WebviewWindow;
+
+    pub fn supported() -> bool {
+        gtk_layer_shell::is_supported()
+    }
+
+    pub fn attach(window: &WebviewWindow, interactive: bool) {
+        let Ok(gtk_window) = window.gtk_window() else {
+            return;
+        };
+        if gtk_window.is_layer_window() {
+            return;
+        }
+        // A window can only become a layer surface before it is realized
+        let visible = gtk_window.is_visible();
+        gtk_window.hide();
+        gtk_window.unrealize();
+        gtk_window.init_layer_shell();
+        gtk_window.set_namespace("agent-progress-overlay");
+        gtk_window.set_layer(Layer::Overlay);
+        gtk_window.set_anchor(Edge::Top, true);
+        gtk_window.set_anchor(Edge::Left, true);
+        gtk_window.set_keyboard_mode(if interactive { KeyboardMode::OnDemand } else { KeyboardMode::None });
+        if visible {
+            gtk_window.show();
+        }
+    }
+
+    pub fn place(window: &WebviewWindow, x: f64, y: f64) {
+        let Ok(gtk_window) = window.gtk_window() else {
+            return;
+        };
+        if gtk_window.is_layer_window() {
+            gtk_window.set_layer_shell_margin(Edge::Left, x.round() as i32);
+            gtk_window.set_layer_shell_margin(Edge::Top, y.round() as i32);
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod platform {
+    use tauri::WebviewWindow;
+
+    pub fn supported() -> bool {
+        false
+    }
+
+    pub fn attach(_window: &WebviewWindow, _interactive: bool) {}
+
+    pub fn place(_window: &WebviewWindow, _x: f64, _y: f64) {}
+}
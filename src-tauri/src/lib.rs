@@ -8,21 +8,104 @@
 //! - **Store**: SQLite for task history persistence
 //! - **Watcher**: Event-driven file watching (notify crate)
 //! - **Router**: Central event hub with plugin support
+//! - **Plugins**: Built-in plugins driven by router events
+//! - **Bridge**: Local socket protocol for editor extensions
+//! - **Relay**: Listener for events forwarded from remote machines
+//! - **Stdin**: Events piped into the process with `--stdin`
+//! - **Core**: The windowless core, run as its own process with `--headless`
+//! - **IPC**: Socket overlays attach to the core through
+//! - **Service**: Installs the headless core as a login service
+//! - **Updates**: New releases from the stable or beta channel
+//! - **Logs**: Rotating log files and a live feed of log lines
+//! - **Diagnostics**: Zip bundles of logs, config and state for bug reports
+//! - **Hooks**: The Claude Code hooks that report tasks, and running as one
+//! - **Doctor**: A checklist of what the overlay needs to work
+//! - **Onboarding**: The first-run setup
+//! - **Workspace**: Which workspaces the overlay shows on
+//! - **Layer shell**: The overlay as a Wayland layer-shell surface
+//! - **Panel**: The overlay as a non-activating macOS panel
+//! - **Backdrop**: Mica and acrylic behind the overlay on Windows
+//! - **Sync**: History merged between machines through a shared folder
+//! - **Maintenance**: Periodic upkeep of the history database
+//! - **Processes**: CPU and memory of background shells
+//! - **Storage**: Disk space taken by Claude's files and the history
+//! - **Priority**: How urgent each event is, and whether it pops up
+//! - **Export**: History as Parquet files for analytics tools
+//! - **Archive**: Old sessions moved into dated archive databases
+//! - **Health**: Internal counters for diagnosing a slow overlay
+//! - **Clock**: Injectable time source for time-based checks
+//! - **Supervisor**: Restarts the watcher and plugins when they fail
+//! - **Timezone**: Local-time bucketing and quiet hours over UTC timestamps
+//! - **Text**: Unicode normalization and grapheme-safe truncation
+//! - **Transcript**: Reads Claude Code session transcripts
+//! - **Idle**: Holds popups back while the user is away from the machine
+//! - **A11y**: Screen reader announcements of task progress
+//! - **Theme**: Built-in color themes served to the windows
+//! - **Locale**: Translations of backend-generated text
 
 pub mod config;
 pub mod store;
 pub mod watcher;
 pub mod router;
-
-use std::collections::HashMap;
+pub mod git;
+pub mod plugins;
+pub mod rules;
+pub mod adapters;
+pub mod bridge;
+pub mod redact;
+pub mod relay;
+pub mod sync;
+pub mod maintenance;
+pub mod health;
+pub mod clock;
+pub mod supervisor;
+pub mod timezone;
+pub mod text;
+pub mod transcript;
+pub mod idle;
+pub mod a11y;
+pub mod theme;
+pub mod locale;
+pub mod summary;
+pub mod opener;
+pub mod requests;
+pub mod stdin;
+pub mod core;
+pub mod ipc;
+pub mod secret;
+pub mod service;
+pub mod updates;
+pub mod logs;
+pub mod diagnostics;
+pub mod hooks;
+pub mod doctor;
+pub mod onboarding;
+pub mod workspace;
+pub mod layer_shell;
+pub mod panel;
+pub mod backdrop;
+pub mod processes;
+pub mod storage;
+pub mod priority;
+pub mod archive;
+pub mod export;
+
+use std::collections::{BTreeMap, HashMap};
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
-use tauri::{AppHandle, Emitter, Manager, WebviewWindow, WebviewUrl, WebviewWindowBuilder};
+use tauri::{AppHandle, Emitter, Manager, RunEvent, State, UserAttentionType, WebviewWindow, WebviewUrl, WebviewWindowBuilder};
 
+use clock::Clock;
+use crate::core::{Core, CoreSink};
+use idle::{AwaySummary, Presence};
 use config::Config;
-use store::EventStore;
-use watcher::{FileWatcher, WatcherEvent, TaskEvent};
-use router::EventRouter;
+use store::{CustomEvent, EventStore, UsageWarning};
+use watcher::{WatcherEvent, TaskEvent};
+use router::{OverdueTask, ReconciledSnapshot};
+use timezone::{BucketSize, QuietHours, Zone};
+use theme::Theme;
+use priority::{Delivery, Priority, PriorityPolicy};
+use locale::Message;
 
 // ============================================================================
 // Notification Window Management (kept from v1 for UI compatibility)
@@ -31,13 +114,74 @@ use router::EventRouter;
 struct NotificationManager {
     active_windows: HashMap<String, (String, Instant)>,
     window_counter: u64,
+    quiet_hours: Option<QuietHours>,
+    /// How long a usage warning stays up
+    warning_display: Duration,
+    presence: Presence,
+    /// Also tell screen readers about task starts and finishes
+    announce: bool,
+    /// Open popups with their animations off
+    reduced_motion: bool,
+    /// Chime when an agent starts waiting for the user
+    input_sound: bool,
+    /// How each event type asks for attention while the overlay is hidden
+    attention: HashMap<String, UserAttentionType>,
+    /// Which priorities pop up, and which push through quiet hours
+    priorities: PriorityPolicy,
+    theme: Theme,
+    log: NotificationLog,
+    clock: Arc<dyn Clock>,
 }
 
 impl NotificationManager {
-    fn new() -> Self {
+    fn new(config: &Config, presence: Presence, log: NotificationLog, clock: Arc<dyn Clock>) -> Self {
+        let zone = Zone::from_config(&config.behavior.timezone);
+        let quiet_hours = QuietHours::parse(&config.behavior.quiet_hours, zone).unwrap_or_else(|e| {
+            tracing::warn!("{}; quiet hours are off", e);
+            None
+        });
         Self {
             active_windows: HashMap::new(),
             window_counter: 0,
+            quiet_hours,
+            warning_display: Duration::from_secs(config.usage_warnings.display_secs),
+            presence,
+            announce: config.behavior.screen_reader_announcements,
+            reduced_motion: config.behavior.reduced_motion,
+            input_sound: config.awaiting_input.sound,
+            attention: attention_types(&config.attention),
+            priorities: PriorityPolicy::new(&config.priority, &config.rules),
+            theme: Theme::from_config(&config.window.theme),
+            log,
+            clock,
+        }
+    }
+
+    /// Stop tracking the popup under `key`, recording why it went away
+    fn remove(&mut self, key: &str, how: &'static str) -> Option<(String, Instant)> {
+        let removed = self.active_windows.remove(key);
+        if let Some((label, _)) = &removed {
+            self.log.dismissed(label, self.clock.now_ms(), how);
+        }
+        removed
+    }
+
+    /// Whether popups are held back right now
+    fn is_quiet(&self) -> bool {
+        self.presence.is_away() || self.quiet_hours.is_some_and(|quiet| quiet.contains(self.clock.now_ms()))
+    }
+
+    /// How a task event reaches the user, by its priority
+    fn delivery(&self, event: &TaskEvent) -> Delivery {
+        self.priorities.delivery(Priority::of(event))
+    }
+
+    /// Whether an event delivered this way pops up right now
+    fn shows(&self, delivery: Delivery) -> bool {
+        match delivery {
+            Delivery::Push => true,
+            Delivery::Popup => !self.is_quiet(),
+            Delivery::Silent => false,
         }
     }
 
@@ -47,28 +191,322 @@ impl NotificationManager {
     }
 }
 
+/// A change to a popup, for the notification history
+enum NotificationLogEntry {
+    Shown { window: String, task_id: String, kind: String, at: i64 },
+    Dismissed { window: String, at: i64, how: &'static str },
+}
+
+/// Records popups in the notification history, in order, on the blocking pool
+#[derive(Clone)]
+struct NotificationLog(tokio::sync::mpsc::UnboundedSender<NotificationLogEntry>);
+
+impl NotificationLog {
+    fn start(store: Arc<EventStore>, clock: Arc<dyn Clock>) -> Self {
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<NotificationLogEntry>();
+        tokio::spawn(async move {
+            // Popups a crash left open went away with it
+            let (closing, now) = (store.clone(), clock.now_ms());
+            if let Ok(Err(e)) = tokio::task::spawn_blocking(move || closing.close_open_notifications(now)).await {
+                tracing::error!("Failed to close leftover notifications: {}", e);
+            }
+            while let Some(entry) = rx.recv().await {
+                let store = store.clone();
+                let written = tokio::task::spawn_blocking(move || match entry {
+                    NotificationLogEntry::Shown { window, task_id, kind, at } => {
+                        store.insert_notification(&window, &task_id, &kind, at)
+                    }
+                    NotificationLogEntry::Dismissed { window, at, how } => store.dismiss_notification(&window, at, how),
+                }).await;
+                if let Ok(Err(e)) = written {
+                    tracing::error!("Failed to record notification: {}", e);
+                }
+            }
+        });
+        Self(tx)
+    }
+
+    fn shown(&self, window: &str, task_id: &str, kind: &str, at: i64) {
+        let _ = self.0.send(NotificationLogEntry::Shown {
+            window: window.to_string(),
+            task_id: task_id.to_string(),
+            kind: kind.to_string(),
+            at,
+        });
+    }
+
+    fn dismissed(&self, window: &str, at: i64, how: &'static str) {
+        let _ = self.0.send(NotificationLogEntry::Dismissed { window: window.to_string(), at, how });
+    }
+}
+
 const NOTIFICATION_WIDTH: f64 = 320.0;
 const NOTIFICATION_HEIGHT: f64 = 70.0;
 const NOTIFICATION_PADDING: f64 = 10.0;
 const NOTIFICATION_GAP: f64 = 8.0;
 const TASKBAR_HEIGHT: f64 = 40.0;
 const MIN_NOTIFICATION_DISPLAY_MS: u64 = 2000;
+/// Longest description passed to a notification window, which shows two lines at most
+const NOTIFICATION_DESCRIPTION_GRAPHEMES: usize = 120;
 
 fn create_notification_window(
     app: &AppHandle,
     manager: &mut NotificationManager,
     event: &TaskEvent,
 ) -> Option<String> {
-    let label = manager.next_label();
-
     let task_data = serde_json::json!({
         "task_id": event.task_id,
         "tool": event.tool,
-        "description": event.description,
+        "description": event.description.as_deref().map(|d| text::truncate(d, NOTIFICATION_DESCRIPTION_GRAPHEMES)),
         "subagent_type": event.subagent_type,
         "background": event.background,
+        "eta_ms": event.eta_ms,
+    });
+    open_notification_window(app, manager, &event.task_id, &task_data)
+}
+
+/// Start of the keys usage warning popups are tracked under
+const USAGE_WARNING_KEY_PREFIX: &str = "usage-warning:";
+
+/// Key a session's usage warning popup is tracked under, so a repeat replaces it
+fn usage_warning_key(warning: &UsageWarning) -> String {
+    format!("{}{}", USAGE_WARNING_KEY_PREFIX, warning.session_id.as_deref().unwrap_or("unknown"))
+}
+
+/// Pop up a usage warning, replacing the session's previous one
+///
+/// A warning explains why the agent stopped, so it is shown during quiet
+/// hours too, and it closes on its own after a while since no end event
+/// will come for it.
+fn create_usage_warning_window(
+    app: &AppHandle,
+    manager: &Arc<Mutex<NotificationManager>>,
+    warning: &UsageWarning,
+) {
+    let key = usage_warning_key(warning);
+    let warning_data = serde_json::json!({
+        "kind": "usage_warning",
+        "task_id": key,
+        "tool": warning.kind,
+        "description": text::truncate(&warning.message, NOTIFICATION_DESCRIPTION_GRAPHEMES),
+        "resets_at": warning.resets_at,
+    });
+
+    let (label, display) = {
+        let Ok(mut nm) = manager.lock() else {
+            return;
+        };
+        if let Some((label, _)) = nm.remove(&key, "replaced") {
+            if let Some(window) = app.get_webview_window(&label) {
+                let _ = window.close();
+            }
+        }
+        (open_notification_window(app, &mut nm, &key, &warning_data), nm.warning_display)
+    };
+
+    if let Some(label) = label {
+        close_after(app, manager, key, label, display);
+    }
+}
+
+/// How each event type asks for attention, skipping the ones turned off
+fn attention_types(config: &config::AttentionConfig) -> HashMap<String, UserAttentionType> {
+    config
+        .events
+        .iter()
+        .filter_map(|(event_type, kind)| {
+            let kind = match kind.trim().to_ascii_lowercase().as_str() {
+                "critical" => UserAttentionType::Critical,
+                "informational" => UserAttentionType::Informational,
+                "" | "off" => return None,
+                other => {
+                    tracing::warn!("Unknown attention type \"{}\" for {} events, not asking", other, event_type);
+                    return None;
+                }
+            };
+            Some((event_type.clone(), kind))
+        })
+        .collect()
+}
+
+/// Ask for the user's attention about an event while the overlay is hidden
+///
+/// That flashes the taskbar button on Windows, bounces the dock icon on macOS
+/// and sets the urgency hint on Linux; the request ends when the overlay is
+/// brought up. Silent events never ask, and pushed ones always keep asking.
+fn request_attention(app: &AppHandle, manager: &NotificationManager, event_type: &str, delivery: Delivery) {
+    let kind = match delivery {
+        Delivery::Silent => return,
+        Delivery::Push => UserAttentionType::Critical,
+        Delivery::Popup => match manager.attention.get(event_type) {
+            Some(kind) => *kind,
+            None => return,
+        },
+    };
+    let Some(main) = app.get_webview_window("main") else {
+        return;
+    };
+    let hidden = !main.is_visible().unwrap_or(false) || main.is_minimized().unwrap_or(false);
+    if hidden {
+        if let Err(e) = main.request_user_attention(Some(kind)) {
+            tracing::warn!("Failed to request attention: {}", e);
+        }
+    }
+}
+
+/// Start of the keys popups for agents waiting on the user are tracked under
+const AWAITING_INPUT_KEY_PREFIX: &str = "awaiting-input:";
+
+/// Key a blocked agent's popup is tracked under: one per session, or per task without one
+fn awaiting_input_key(event: &TaskEvent) -> String {
+    format!("{}{}", AWAITING_INPUT_KEY_PREFIX, event.session_id.as_deref().unwrap_or(&event.task_id))
+}
+
+/// Pop up an agent blocked on the user, replacing the session's previous popup
+///
+/// The agent can't go on until the user answers, so by default its priority
+/// is urgent and the popup is shown during quiet hours and while the user is
+/// away too. It stays up until the agent moves again or the popup is clicked.
+fn create_awaiting_input_window(app: &AppHandle, manager: &mut NotificationManager, event: &TaskEvent) {
+    let key = awaiting_input_key(event);
+    let label = event.description.as_deref().or(event.tool.as_deref()).unwrap_or("Input needed");
+    let label = text::truncate(label, NOTIFICATION_DESCRIPTION_GRAPHEMES);
+    let input_data = serde_json::json!({
+        "kind": "awaiting_input",
+        "task_id": key,
+        "tool": event.tool.as_deref().unwrap_or_default(),
+        "description": &label,
+        "sound": manager.input_sound,
+    });
+
+    if let Some((label, _)) = manager.remove(&key, "replaced") {
+        if let Some(window) = app.get_webview_window(&label) {
+            let _ = window.close();
+        }
+    }
+    open_notification_window(app, manager, &key, &input_data);
+    app.state::<AwaitingInput>().insert(key, event.task_id.clone(), label);
+    refresh_tray_tooltip(app);
+}
+
+/// Key the summary shown on the user's return is tracked under
+const AWAY_SUMMARY_KEY: &str = "away-summary";
+/// How long the summary shown on the user's return stays up
+const AWAY_SUMMARY_DISPLAY: Duration = Duration::from_secs(20);
+
+/// Pop up what finished while the user was away, in place of the popups they missed
+fn create_away_summary_window(app: &AppHandle, manager: &Arc<Mutex<NotificationManager>>, summary: &AwaySummary) {
+    let summary_data = serde_json::json!({
+        "kind": "away_summary",
+        "task_id": AWAY_SUMMARY_KEY,
+        "tool": "",
+        "description": summary.recent.last().map(|label| text::truncate(label, NOTIFICATION_DESCRIPTION_GRAPHEMES)),
+        "completed": summary.completed,
+        "failed": summary.failed,
+        "since_ms": summary.since_ms,
+    });
+
+    let label = {
+        let Ok(mut nm) = manager.lock() else {
+            return;
+        };
+        if let Some((label, _)) = nm.remove(AWAY_SUMMARY_KEY, "replaced") {
+            if let Some(window) = app.get_webview_window(&label) {
+                let _ = window.close();
+            }
+        }
+        open_notification_window(app, &mut nm, AWAY_SUMMARY_KEY, &summary_data)
+    };
+    if let Some(label) = label {
+        close_after(app, manager, AWAY_SUMMARY_KEY.to_string(), label, AWAY_SUMMARY_DISPLAY);
+    }
+}
+
+/// Key the disk usage popup is tracked under
+const STORAGE_WARNING_KEY: &str = "storage-warning";
+/// How long the disk usage popup stays up
+const STORAGE_WARNING_DISPLAY: Duration = Duration::from_secs(20);
+
+/// Pop up that Claude's files and the history take a lot of disk, with the biggest cleanup
+///
+/// Nothing is stalled by it, so it waits out quiet hours and the user being
+/// away; the main window still gets `storage-warning`.
+fn create_storage_warning_window(app: &AppHandle, manager: &Arc<Mutex<NotificationManager>>, usage: &storage::StorageUsage) {
+    let warning_data = serde_json::json!({
+        "kind": "storage_warning",
+        "task_id": STORAGE_WARNING_KEY,
+        "tool": "",
+        "description": usage.suggestions.first(),
+        "total": storage::format_size(usage.total_bytes),
+    });
+
+    let label = {
+        let Ok(mut nm) = manager.lock() else {
+            return;
+        };
+        if nm.is_quiet() {
+            return;
+        }
+        if let Some((label, _)) = nm.remove(STORAGE_WARNING_KEY, "replaced") {
+            if let Some(window) = app.get_webview_window(&label) {
+                let _ = window.close();
+            }
+        }
+        open_notification_window(app, &mut nm, STORAGE_WARNING_KEY, &warning_data)
+    };
+    if let Some(label) = label {
+        close_after(app, manager, STORAGE_WARNING_KEY.to_string(), label, STORAGE_WARNING_DISPLAY);
+    }
+}
+
+/// Close the notification tracked under `key` after `display`, unless it has been replaced by then
+fn close_after(app: &AppHandle, manager: &Arc<Mutex<NotificationManager>>, key: String, label: String, display: Duration) {
+    let app = app.clone();
+    let manager = manager.clone();
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(display).await;
+        let Ok(mut nm) = manager.lock() else {
+            return;
+        };
+        // A newer notification under the key has its own timer
+        if nm.active_windows.get(&key).is_some_and(|(current, _)| *current == label) {
+            close_notification_window(&app, &mut nm, &key, "timeout");
+        }
+    });
+}
+
+/// How often the user's idle time is checked
+const IDLE_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Follow the user's idle time, showing a summary of what finished when they come back
+fn watch_presence(app: AppHandle, manager: Arc<Mutex<NotificationManager>>, clock: Arc<dyn Clock>) {
+    tauri::async_runtime::spawn(async move {
+        let mut ticker = tokio::time::interval(IDLE_CHECK_INTERVAL);
+        loop {
+            ticker.tick().await;
+            let idle = idle::idle_time().unwrap_or_default();
+            let summary = match manager.lock() {
+                Ok(mut nm) => nm.presence.observe(idle, clock.now_ms()),
+                Err(_) => return,
+            };
+            if let Some(summary) = summary.filter(|summary| !summary.is_empty()) {
+                tracing::info!("User is back: {} tasks finished, {} failed", summary.completed, summary.failed);
+                create_away_summary_window(&app, &manager, &summary);
+            }
+        }
     });
-    let task_data_str = task_data.to_string();
+}
+
+/// Open a notification window showing `data`, tracked under `key`
+fn open_notification_window(
+    app: &AppHandle,
+    manager: &mut NotificationManager,
+    key: &str,
+    data: &serde_json::Value,
+) -> Option<String> {
+    let label = manager.next_label();
+
+    let task_data_str = data.to_string();
     let encoded = urlencoding::encode(&task_data_str);
 
     let stack_index = manager.active_windows.len();
@@ -91,7 +529,15 @@ fn create_notification_window(
         (800.0, 400.0)
     };
 
-    let url = format!("/notification.html?task={}", encoded);
+    let mut url = format!("/notification.html?task={}", encoded);
+    // Read before the first paint, so the popup never starts sliding in
+    if manager.reduced_motion {
+        url.push_str("&motion=reduced");
+    }
+    if let Ok(theme) = serde_json::to_string(&manager.theme) {
+        url.push_str("&theme=");
+        url.push_str(&urlencoding::encode(&theme));
+    }
 
     match WebviewWindowBuilder::new(app, &label, WebviewUrl::App(url.into()))
         .title("Task")
@@ -106,12 +552,17 @@ fn create_notification_window(
         .visible(false)
         .build()
     {
-        Ok(_) => {
+        Ok(window) => {
+            layer_shell::attach(&window, false);
+            layer_shell::place(&window, x, y);
+            panel::attach(&window);
             manager.active_windows.insert(
-                event.task_id.clone(),
+                key.to_string(),
                 (label.clone(), Instant::now()),
             );
-            tracing::debug!("Created notification window: {} for task {}", label, event.task_id);
+            let kind = data.get("kind").and_then(|kind| kind.as_str()).unwrap_or("task");
+            manager.log.shown(&label, key, kind, manager.clock.now_ms());
+            tracing::debug!("Created notification window: {} for task {}", label, key);
             Some(label)
         }
         Err(e) => {
@@ -125,16 +576,17 @@ fn close_notification_window(
     app: &AppHandle,
     manager: &mut NotificationManager,
     task_id: &str,
+    how: &'static str,
 ) {
-    if let Some((label, created_at)) = manager.active_windows.remove(task_id) {
+    if let Some((label, created_at)) = manager.remove(task_id, how) {
         let elapsed = created_at.elapsed().as_millis() as u64;
 
         if elapsed < MIN_NOTIFICATION_DISPLAY_MS {
             let remaining = MIN_NOTIFICATION_DISPLAY_MS - elapsed;
             let app_clone = app.clone();
             let label_clone = label.clone();
-            std::thread::spawn(move || {
-                std::thread::sleep(Duration::from_millis(remaining));
+            tauri::async_runtime::spawn(async move {
+                tokio::time::sleep(Duration::from_millis(remaining)).await;
                 if let Some(window) = app_clone.get_webview_window(&label_clone) {
                     let _ = window.close();
                 }
@@ -167,13 +619,141 @@ fn reposition_notification_windows(app: &AppHandle, manager: &NotificationManage
             let x = screen_width - NOTIFICATION_WIDTH - NOTIFICATION_PADDING;
             let y = screen_height - TASKBAR_HEIGHT - NOTIFICATION_PADDING
                 - ((index + 1) as f64 * (NOTIFICATION_HEIGHT + NOTIFICATION_GAP));
-            let _ = window.set_position(tauri::Position::Logical(
-                tauri::LogicalPosition::new(x, y),
-            ));
+            if !layer_shell::place(&window, x, y) {
+                let _ = window.set_position(tauri::Position::Logical(
+                    tauri::LogicalPosition::new(x, y),
+                ));
+            }
+        }
+    }
+}
+
+// ============================================================================
+// Keyboard Control of Notifications
+// ============================================================================
+
+/// What a notification shortcut does
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum NotificationAction {
+    DismissNewest,
+    DismissAll,
+    FocusNext,
+}
+
+impl NotificationManager {
+    /// Keys of the open popups, newest first
+    fn keys_newest_first(&self) -> Vec<String> {
+        let mut windows: Vec<(&String, &Instant)> = self.active_windows
+            .iter()
+            .map(|(key, (_, created_at))| (key, created_at))
+            .collect();
+        windows.sort_by(|a, b| b.1.cmp(a.1));
+        windows.into_iter().map(|(key, _)| key.clone()).collect()
+    }
+}
+
+impl NotificationAction {
+    /// Apply the action to the open popups; false if there were none
+    fn apply(self, app: &AppHandle, manager: &mut NotificationManager) -> bool {
+        let keys = manager.keys_newest_first();
+        if keys.is_empty() {
+            return false;
+        }
+        match self {
+            // Dismissed popups close at once, without the minimum display time
+            NotificationAction::DismissNewest => {
+                dismiss_notification_window(app, manager, &keys[0]);
+                reposition_notification_windows(app, manager);
+            }
+            NotificationAction::DismissAll => {
+                for key in &keys {
+                    dismiss_notification_window(app, manager, key);
+                }
+            }
+            NotificationAction::FocusNext => {
+                let windows: Vec<WebviewWindow> = keys.iter()
+                    .filter_map(|key| manager.active_windows.get(key))
+                    .filter_map(|(label, _)| app.get_webview_window(label))
+                    .collect();
+                let Some(first) = windows.first() else {
+                    return false;
+                };
+                let next = windows.iter()
+                    .position(|window| window.is_focused().unwrap_or(false))
+                    .and_then(|focused| windows.get(focused + 1))
+                    .unwrap_or(first);
+                return next.set_focus().is_ok();
+            }
         }
+        true
     }
 }
 
+/// Close the popup tracked under `key` right away
+fn dismiss_notification_window(app: &AppHandle, manager: &mut NotificationManager, key: &str) {
+    if let Some((label, _)) = manager.remove(key, "user") {
+        if let Some(window) = app.get_webview_window(&label) {
+            let _ = window.close();
+        }
+    }
+}
+
+/// Apply a notification action, once the services have started
+fn run_notification_action(app: &AppHandle, action: NotificationAction) -> bool {
+    let Some(manager) = app.state::<ServicesState>().notifications() else {
+        return false;
+    };
+    let Ok(mut nm) = manager.lock() else {
+        return false;
+    };
+    action.apply(app, &mut nm)
+}
+
+/// Bind the configured notification shortcuts system-wide
+///
+/// A shortcut that doesn't parse or that another app already holds is
+/// skipped, so one bad binding doesn't stop the others or the app.
+fn setup_notification_shortcuts(app: &tauri::App, shortcuts: &config::ShortcutsConfig) -> Result<(), Box<dyn std::error::Error>> {
+    use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, ShortcutState};
+
+    let bindings: Vec<(Shortcut, NotificationAction)> = [
+        (&shortcuts.dismiss_notification, NotificationAction::DismissNewest),
+        (&shortcuts.dismiss_all_notifications, NotificationAction::DismissAll),
+        (&shortcuts.cycle_notifications, NotificationAction::FocusNext),
+    ]
+    .into_iter()
+    .filter(|(keys, _)| !keys.trim().is_empty())
+    .filter_map(|(keys, action)| match keys.trim().parse::<Shortcut>() {
+        Ok(shortcut) => Some((shortcut, action)),
+        Err(e) => {
+            tracing::warn!("Ignoring shortcut {:?}: {}", keys, e);
+            None
+        }
+    })
+    .collect();
+
+    let handled = bindings.clone();
+    app.handle().plugin(
+        tauri_plugin_global_shortcut::Builder::new()
+            .with_handler(move |app, shortcut, event| {
+                if event.state() != ShortcutState::Pressed {
+                    return;
+                }
+                if let Some((_, action)) = handled.iter().find(|(bound, _)| bound == shortcut) {
+                    run_notification_action(app, *action);
+                }
+            })
+            .build(),
+    )?;
+
+    for (shortcut, action) in &bindings {
+        if let Err(e) = app.global_shortcut().register(*shortcut) {
+            tracing::warn!("Failed to register shortcut for {:?}: {}", action, e);
+        }
+    }
+    Ok(())
+}
+
 // ============================================================================
 // Window Positioning
 // ============================================================================
@@ -202,9 +782,11 @@ fn position_window(window: &WebviewWindow, position: &str) {
                 ),
             };
 
-            let _ = window.set_position(tauri::Position::Logical(
-                tauri::LogicalPosition::new(x, y),
-            ));
+            if !layer_shell::place(window, x, y) {
+                let _ = window.set_position(tauri::Position::Logical(
+                    tauri::LogicalPosition::new(x, y),
+                ));
+            }
         }
     }
 }
@@ -243,6 +825,54 @@ fn show_notification_ready(window: WebviewWindow) {
     let _ = window.show();
 }
 
+/// Close the newest notification popup; false if none was open
+#[tauri::command]
+fn dismiss_notification(app: AppHandle) -> bool {
+    run_notification_action(&app, NotificationAction::DismissNewest)
+}
+
+/// Close every notification popup; false if none was open
+#[tauri::command]
+fn dismiss_all_notifications(app: AppHandle) -> bool {
+    run_notification_action(&app, NotificationAction::DismissAll)
+}
+
+/// Move focus to the next notification popup, newest first; false if none was open
+#[tauri::command]
+fn focus_next_notification(app: AppHandle) -> bool {
+    run_notification_action(&app, NotificationAction::FocusNext)
+}
+
+/// A popup was clicked: close it and bring up the overlay on what it was about
+#[tauri::command]
+fn open_notification(app: AppHandle, window: WebviewWindow) {
+    let _ = window.close();
+    let Some(manager) = app.state::<ServicesState>().notifications() else {
+        return;
+    };
+    let Ok(mut nm) = manager.lock() else {
+        return;
+    };
+    let key = nm.active_windows
+        .iter()
+        .find(|(_, (label, _))| label == window.label())
+        .map(|(key, _)| key.clone());
+    let Some(key) = key else {
+        return;
+    };
+    nm.remove(&key, "clicked");
+    reposition_notification_windows(&app, &nm);
+
+    if let Some(main) = app.get_webview_window("main") {
+        let _ = main.show();
+        let _ = main.set_focus();
+        if let Err(e) = main.emit("notification-opened", &key) {
+            tracing::error!("Failed to emit opened notification: {}", e);
+            health::metrics().record_emit_failure();
+        }
+    }
+}
+
 #[tauri::command]
 fn close_app(app: AppHandle) {
     app.exit(0);
@@ -269,206 +899,1666 @@ fn clear_events(_app: AppHandle) -> Result<(), String> {
 
 // New v2 commands
 
+/// The history store, opened in the background once the window is up
+///
+/// Commands that reach it first open it themselves, so history is only read
+/// when something asks for it.
+struct StoreState {
+    path: std::path::PathBuf,
+    store: tokio::sync::OnceCell<Arc<EventStore>>,
+}
+
+impl StoreState {
+    fn new(path: std::path::PathBuf) -> Self {
+        Self { path, store: tokio::sync::OnceCell::new() }
+    }
+
+    /// The shared store, opening it if it isn't open yet
+    async fn get(&self) -> Arc<EventStore> {
+        self.store
+            .get_or_init(|| async {
+                let path = self.path.clone();
+                let opened = tokio::task::spawn_blocking(move || EventStore::new(&path))
+                    .await
+                    .map_err(|e| e.to_string())
+                    .and_then(|result| result.map_err(|e| e.to_string()));
+                match opened {
+                    Ok(store) => Arc::new(store),
+                    Err(e) => {
+                        tracing::error!("Failed to initialize store: {}", e);
+                        // Continue without persistence
+                        Arc::new(EventStore::in_memory().unwrap())
+                    }
+                }
+            })
+            .await
+            .clone()
+    }
+
+    /// Run a query on the blocking pool
+    async fn query<T, F>(&self, query: F) -> Result<T, String>
+    where
+        T: Send + 'static,
+        F: FnOnce(&EventStore) -> Result<T, store::StoreError> + Send + 'static,
+    {
+        let store = self.get().await;
+        tokio::task::spawn_blocking(move || query(&store))
+            .await
+            .map_err(|e| e.to_string())?
+            .map_err(|e| e.to_string())
+    }
+}
+
 #[tauri::command]
-fn get_task_stats(_app: AppHandle) -> Result<store::TaskStats, String> {
-    let config = Config::load().map_err(|e| e.to_string())?;
-    let store = EventStore::new(&config.database_path()).map_err(|e| e.to_string())?;
-    store.get_task_stats().map_err(|e| e.to_string())
+async fn get_task_stats(state: State<'_, StoreState>) -> Result<store::TaskStats, String> {
+    state.query(|store| store.get_task_stats()).await
 }
 
+/// Most buckets `get_task_history` returns, a year of days
+const MAX_HISTORY_BUCKETS: usize = 366;
+
+/// Task statistics for the last `count` local days or hours, oldest first
 #[tauri::command]
-fn search_tasks(_app: AppHandle, query: String, limit: usize) -> Result<Vec<store::StoredTask>, String> {
+async fn get_task_history(
+    state: State<'_, StoreState>,
+    bucket: BucketSize,
+    count: usize,
+) -> Result<Vec<store::BucketStats>, String> {
     let config = Config::load().map_err(|e| e.to_string())?;
-    let store = EventStore::new(&config.database_path()).map_err(|e| e.to_string())?;
-    store.search_tasks(&query, limit).map_err(|e| e.to_string())
+    let zone = Zone::from_config(&config.behavior.timezone);
+    let buckets = zone.buckets(bucket, count.min(MAX_HISTORY_BUCKETS), clock::SystemClock.now_ms());
+    state.query(move |store| store.get_bucketed_stats(&buckets)).await
 }
 
 #[tauri::command]
-fn get_recent_tasks(_app: AppHandle, limit: usize) -> Result<Vec<store::StoredTask>, String> {
-    let config = Config::load().map_err(|e| e.to_string())?;
-    let store = EventStore::new(&config.database_path()).map_err(|e| e.to_string())?;
-    store.get_recent_tasks(limit).map_err(|e| e.to_string())
+async fn search_tasks(
+    state: State<'_, StoreState>,
+    query: String,
+    limit: usize,
+    filter: Option<store::TaskFilter>,
+) -> Result<Vec<store::StoredTask>, String> {
+    state.query(move |store| store.search_tasks(&query, &filter.unwrap_or_default(), limit)).await
 }
 
 #[tauri::command]
-fn get_config() -> Result<Config, String> {
-    Config::load().map_err(|e| e.to_string())
+async fn get_recent_tasks(
+    state: State<'_, StoreState>,
+    limit: usize,
+    filter: Option<store::TaskFilter>,
+) -> Result<Vec<store::StoredTask>, String> {
+    state.query(move |store| store.get_recent_tasks(&filter.unwrap_or_default(), limit)).await
 }
 
+/// Mark a task as reviewed, or clear the mark with `reviewed: false`
 #[tauri::command]
-fn save_config(config: Config) -> Result<(), String> {
-    config.save().map_err(|e| e.to_string())
+async fn mark_reviewed(state: State<'_, StoreState>, task_id: String, reviewed: Option<bool>) -> Result<bool, String> {
+    let reviewed = reviewed.unwrap_or(true);
+    let changed = state.query(move |store| store.set_reviewed(&[task_id], reviewed, clock::SystemClock.now_ms())).await?;
+    Ok(changed > 0)
 }
 
-// ============================================================================
-// Application Entry Point
-// ============================================================================
+/// Pin a task to the top of the history, or unpin it with `pinned: false`
+#[tauri::command]
+async fn pin_task(state: State<'_, StoreState>, task_id: String, pinned: Option<bool>) -> Result<bool, String> {
+    let pinned = pinned.unwrap_or(true);
+    state.query(move |store| store.set_pinned(&task_id, pinned, clock::SystemClock.now_ms())).await
+}
 
-pub fn run() {
-    // Initialize logging
-    tracing_subscriber::fmt()
-        .with_env_filter(
-            tracing_subscriber::EnvFilter::from_default_env()
-                .add_directive("agent_progress_overlay=debug".parse().unwrap())
-                .add_directive("progress_overlay_lib=debug".parse().unwrap())
-        )
-        .init();
+/// Pinned tasks, most recently pinned first
+#[tauri::command]
+async fn list_pinned(state: State<'_, StoreState>) -> Result<Vec<store::StoredTask>, String> {
+    state.query(|store| store.get_pinned_tasks()).await
+}
 
-    tracing::info!("Starting Agent Progress Overlay v2");
+/// Copy a task's summary to the clipboard as "markdown" (the default) or "json", returning the text
+#[tauri::command]
+async fn copy_task_summary(
+    app: AppHandle,
+    state: State<'_, StoreState>,
+    task_id: String,
+    format: Option<String>,
+) -> Result<String, String> {
+    use tauri_plugin_clipboard_manager::ClipboardExt;
+
+    let format = summary::SummaryFormat::from_name(format.as_deref().unwrap_or("markdown"))?;
+    let (task, context) = state
+        .query(move |store| Ok((store.get_task(&task_id)?, store.get_task_context(&task_id)?)))
+        .await?;
+    let task = task.ok_or_else(|| "Task not found".to_string())?;
+
+    let text = summary::task_summary(&task, context.as_ref(), format);
+    app.clipboard().write_text(text.clone()).map_err(|e| e.to_string())?;
+    Ok(text)
+}
 
-    // Load configuration
-    let config = Config::load().unwrap_or_else(|e| {
-        tracing::warn!("Failed to load config: {}, using defaults", e);
-        Config::default()
-    });
+/// Open the project folder of a task's session in the file manager (the default),
+/// or a terminal there with `target: "terminal"`
+#[tauri::command]
+async fn open_task_location(
+    state: State<'_, StoreState>,
+    task_id: String,
+    target: Option<String>,
+) -> Result<(), String> {
+    let location = opener::Location::from_name(target.as_deref().unwrap_or("folder"))?;
+    let session = state
+        .query(move |store| match store.get_task(&task_id)? {
+            Some(task) => store.get_session(&task.session_id),
+            None => Ok(None),
+        })
+        .await?;
+    let path = session
+        .and_then(|session| session.project_path)
+        .ok_or_else(|| "Task has no project folder".to_string())?;
 
-    let config = Arc::new(config);
+    let config = Config::load().map_err(|e| e.to_string())?;
+    opener::open(&config.openers, location, std::path::Path::new(&path))
+}
 
-    tauri::Builder::default()
-        .plugin(tauri_plugin_shell::init())
-        .invoke_handler(tauri::generate_handler![
-            // v1 commands (backward compatible)
-            hide_window,
-            show_window,
-            show_notification_ready,
-            close_app,
-            clear_events,
-            toggle_devtools,
-            set_window_position,
-            set_always_on_top,
-            set_opacity,
-            // v2 commands
-            get_task_stats,
-            search_tasks,
-            get_recent_tasks,
-            get_config,
-            save_config,
-        ])
-        .setup(move |app| {
-            let window = app.get_webview_window("main").unwrap();
+/// Ask hooks and agents to run a finished task again, by appending a request
+/// to the requests file; the overlay doesn't run anything itself
+#[tauri::command]
+async fn request_rerun(state: State<'_, StoreState>, task_id: String) -> Result<requests::OverlayRequest, String> {
+    let (task, session) = state
+        .query(move |store| match store.get_task(&task_id)? {
+            Some(task) => {
+                let session = store.get_session(&task.session_id)?;
+                Ok((Some(task), session))
+            }
+            None => Ok((None, None)),
+        })
+        .await?;
+    let task = task.ok_or_else(|| "Task not found".to_string())?;
+    if task.status == "active" {
+        return Err("Task is still running".to_string());
+    }
 
-            // Position window based on config
-            position_window(&window, &config.window.position);
+    let project_path = session.and_then(|session| session.project_path);
+    let request = requests::OverlayRequest::rerun(&task, project_path, clock::SystemClock.now_ms());
+    let path = Config::load().map_err(|e| e.to_string())?.requests_path();
+    tokio::task::spawn_blocking({
+        let request = request.clone();
+        move || requests::append(&path, &request)
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+    Ok(request)
+}
 
-            // Set always on top
-            if config.window.always_on_top {
-                let _ = window.set_always_on_top(true);
+/// Ask hooks and agents to cancel a running task, by appending a request to the
+/// requests file. With `signal: true`, a background shell whose process was
+/// recorded is also asked to stop. The UI gets `task-cancel-pending` now and
+/// `task-cancel-resolved` once the task ends.
+#[tauri::command]
+async fn request_cancel(
+    app: AppHandle,
+    state: State<'_, StoreState>,
+    pending: State<'_, PendingCancels>,
+    task_id: String,
+    signal: Option<bool>,
+) -> Result<requests::OverlayRequest, String> {
+    let (task, session, pid) = state
+        .query(move |store| match store.get_task(&task_id)? {
+            Some(task) => {
+                let session = store.get_session(&task.session_id)?;
+                let pid = store.get_active_task_pid(&task.id)?;
+                Ok((Some(task), session, pid))
             }
+            None => Ok((None, None, None)),
+        })
+        .await?;
+    let task = task.ok_or_else(|| "Task not found".to_string())?;
+    if task.status != "active" {
+        return Err("Task is not running".to_string());
+    }
 
-            // Create system tray
-            setup_system_tray(app)?;
+    // A stored pid may since have been reused by an unrelated process, which
+    // must be neither signalled nor handed to hooks
+    let started_at = task.started_at;
+    let pid = match pid {
+        Some(pid) => tokio::task::spawn_blocking(move || processes::is_task_process(pid, started_at))
+            .await
+            .map_err(|e| e.to_string())?
+            .then_some(pid),
+        None => None,
+    };
 
-            // Initialize store
-            let store = match EventStore::new(&config.database_path()) {
-                Ok(s) => Arc::new(s),
-                Err(e) => {
-                    tracing::error!("Failed to initialize store: {}", e);
-                    // Continue without persistence
-                    Arc::new(EventStore::in_memory().unwrap())
-                }
-            };
+    let project_path = session.and_then(|session| session.project_path);
+    let request = requests::OverlayRequest::cancel(&task, project_path, pid, clock::SystemClock.now_ms());
+    let path = Config::load().map_err(|e| e.to_string())?.requests_path();
+    tokio::task::spawn_blocking({
+        let request = request.clone();
+        move || requests::append(&path, &request)
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+
+    if let Some(pid) = pid.filter(|_| signal.unwrap_or(false)) {
+        // The request is written either way, so a failed signal is only logged
+        match tokio::task::spawn_blocking(move || requests::signal(pid)).await {
+            Ok(Err(e)) => tracing::warn!("Failed to signal task {}: {}", task.id, e),
+            Err(e) => tracing::warn!("Failed to signal task {}: {}", task.id, e),
+            Ok(Ok(())) => {}
+        }
+    }
 
-            // Create event router
-            let router = Arc::new(EventRouter::new(store.clone(), config.clone()));
+    pending.insert(task.id.clone(), task.session_id.clone());
+    let pending_event = serde_json::json!({ "task_id": task.id, "requested_at": request.requested_at });
+    if let Err(e) = app.emit("task-cancel-pending", pending_event) {
+        tracing::error!("Failed to emit cancel request: {}", e);
+    }
+    Ok(request)
+}
 
-            // Start file watcher
-            let watcher = FileWatcher::new(&config);
-            let app_handle = app.handle().clone();
-            let notification_manager = Arc::new(Mutex::new(NotificationManager::new()));
-
-            std::thread::spawn(move || {
-                match watcher.start() {
-                    Ok(rx) => {
-                        tracing::info!("File watcher started successfully");
-
-                        for event in rx {
-                            // Process through router (stores to DB)
-                            router.process_watcher_event(event.clone());
-
-                            // Emit to frontend and handle notifications
-                            match event {
-                                WatcherEvent::TaskEvent(ref task_event) => {
-                                    // Emit to frontend
-                                    if let Err(e) = app_handle.emit("task-event", task_event) {
-                                        tracing::error!("Failed to emit task event: {}", e);
-                                    }
-
-                                    // Handle notification windows
-                                    if let Ok(mut nm) = notification_manager.lock() {
-                                        match task_event.event_type.as_str() {
-                                            "task_started" => {
-                                                create_notification_window(&app_handle, &mut nm, task_event);
-                                            }
-                                            "task_complete" | "task_error" => {
-                                                close_notification_window(&app_handle, &mut nm, &task_event.task_id);
-                                            }
-                                            "task_canceled" => {
-                                                if let Some((label, _)) = nm.active_windows.remove(&task_event.task_id) {
-                                                    if let Some(window) = app_handle.get_webview_window(&label) {
-                                                        let _ = window.close();
-                                                    }
-                                                    reposition_notification_windows(&app_handle, &nm);
-                                                }
-                                            }
-                                            "session_stopped" => {
-                                                let labels: Vec<String> = nm.active_windows.values()
-                                                    .map(|(label, _)| label.clone())
-                                                    .collect();
-                                                for label in labels {
-                                                    if let Some(window) = app_handle.get_webview_window(&label) {
-                                                        let _ = window.close();
-                                                    }
-                                                }
-                                                nm.active_windows.clear();
-                                            }
-                                            _ => {}
-                                        }
-                                    }
-                                }
-                                WatcherEvent::TodosUpdated(ref todos) => {
-                                    if let Err(e) = app_handle.emit("todos-update", todos) {
-                                        tracing::error!("Failed to emit todos: {}", e);
-                                    }
-                                }
-                                WatcherEvent::DownloadProgress(ref progress) => {
-                                    // Emit to all windows
-                                    for window in app_handle.webview_windows().values() {
-                                        let _ = window.emit("download-progress", progress);
-                                    }
-                                }
-                                WatcherEvent::Error(e) => {
-                                    tracing::error!("Watcher error: {}", e);
-                                }
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        tracing::error!("Failed to start file watcher: {}", e);
+/// Stop a background task's process and everything it started, such as a
+/// dev server the agent left running
+///
+/// Nothing is killed without `confirm_pid`: the process is described for the
+/// user to confirm instead. Calling again with its pid kills the tree, unless
+/// the task's process has changed in between, which asks again. The UI gets
+/// `task-process-killed` once it is gone.
+#[tauri::command]
+async fn kill_background_task(
+    app: AppHandle,
+    state: State<'_, StoreState>,
+    task_id: String,
+    confirm_pid: Option<u32>,
+) -> Result<processes::KillOutcome, String> {
+    let (task, pid) = state
+        .query(move |store| match store.get_task(&task_id)? {
+            Some(task) => {
+                let pid = store.get_active_task_pid(&task.id)?;
+                Ok((Some(task), pid))
+            }
+            None => Ok((None, None)),
+        })
+        .await?;
+    let task = task.ok_or_else(|| "Task not found".to_string())?;
+    if !task.is_background {
+        return Err("Only background tasks can be killed".to_string());
+    }
+    let pid = pid.ok_or_else(|| "No running process is recorded for the task".to_string())?;
+    let started_at = task.started_at;
+
+    if confirm_pid != Some(pid) {
+        let usage = tokio::task::spawn_blocking(move || processes::sample(pid, started_at, clock::SystemClock.now_ms()))
+            .await
+            .map_err(|e| e.to_string())?;
+        if usage.is_none() {
+            return Err("The task's process has already exited".to_string());
+        }
+        return Ok(processes::KillOutcome::ConfirmationNeeded {
+            task_id: task.id,
+            pid,
+            description: task.description,
+            usage,
+        });
+    }
+
+    let forced = tokio::task::spawn_blocking(move || processes::kill_tree(pid, started_at))
+        .await
+        .map_err(|e| e.to_string())??;
+    tracing::info!("Killed process {} of task {}", pid, task.id);
+
+    let killed_id = task.id.clone();
+    state.query(move |store| store.set_task_process(&killed_id, None, clock::SystemClock.now_ms())).await?;
+    let killed_event = serde_json::json!({ "task_id": task.id, "pid": pid, "forced": forced });
+    if let Err(e) = app.emit("task-process-killed", killed_event) {
+        tracing::error!("Failed to emit process kill: {}", e);
+    }
+    Ok(processes::KillOutcome::Killed { task_id: task.id, pid, forced })
+}
+
+/// Router events after `cursor`, for a window catching up after a reload or a lost connection
+///
+/// Without a cursor, returns none and the cursor to start from. Events may
+/// repeat ones already received live.
+#[tauri::command]
+async fn events_since(
+    state: State<'_, StoreState>,
+    cursor: Option<i64>,
+    limit: Option<usize>,
+) -> Result<router::EventPage, String> {
+    let limit = limit.unwrap_or(router::EVENT_PAGE_LIMIT);
+    state.query(move |store| router::events_since(store, cursor, limit)).await
+}
+
+/// How much disk the events file, todos, transcripts and history database take, measured now
+#[tauri::command]
+async fn get_storage_usage() -> Result<storage::StorageUsage, String> {
+    let config = Config::load().map_err(|e| e.to_string())?;
+    tokio::task::spawn_blocking(move || storage::measure(&config, clock::SystemClock.now_ms()))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Todos directory of the main Claude directory or a profile, from a fresh config
+fn todos_dir(profile: Option<&str>) -> Result<std::path::PathBuf, String> {
+    let config = Config::load().map_err(|e| e.to_string())?;
+    config.todos_path_for(profile).ok_or_else(|| format!("Unknown profile \"{}\"", profile.unwrap_or_default()))
+}
+
+/// Set a todo's status in its session's todo file, so Claude sees the change too
+///
+/// Returns false if the session has no todo with this content.
+#[tauri::command]
+async fn set_todo_status(
+    session_id: String,
+    content: String,
+    status: String,
+    profile: Option<String>,
+) -> Result<bool, String> {
+    if !watcher::TODO_STATUSES.contains(&status.as_str()) {
+        return Err(format!("Unknown todo status \"{}\"", status));
+    }
+    let dir = todos_dir(profile.as_deref())?;
+    tokio::task::spawn_blocking(move || watcher::set_todo_status(&dir, &session_id, &content, &status))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+/// Queue a follow-up for the agent by adding a pending todo to a session's todo file
+///
+/// Returns false if the session already has a todo with this content.
+#[tauri::command]
+async fn add_todo(session_id: String, content: String, profile: Option<String>) -> Result<bool, String> {
+    let content = content.trim().to_string();
+    if content.is_empty() {
+        return Err("Todo is empty".to_string());
+    }
+    let dir = todos_dir(profile.as_deref())?;
+    tokio::task::spawn_blocking(move || watcher::add_todo(&dir, &session_id, &content))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+/// Save the order of the todo list, given as the todos' contents first to last
+///
+/// Later updates come in this order; todos left out follow in file order.
+#[tauri::command]
+async fn reorder_todos(state: State<'_, StoreState>, contents: Vec<String>) -> Result<(), String> {
+    let hashes: Vec<String> = contents.iter().map(|content| watcher::todo_order_key(content)).collect();
+    state.query(move |store| store.set_todo_order(&hashes, clock::SystemClock.now_ms())).await
+}
+
+/// Completed and remaining todos over a session, one point per change, for a burndown chart
+#[tauri::command]
+async fn get_todo_burndown(state: State<'_, StoreState>, session_id: String) -> Result<Vec<store::BurndownPoint>, String> {
+    state.query(move |store| store.get_todo_burndown(&session_id)).await
+}
+
+/// Current and past todos as GitHub checklists grouped by session, for one
+/// session or, without `session_id`, all of them
+#[tauri::command]
+async fn export_todos_markdown(
+    state: State<'_, StoreState>,
+    session_id: Option<String>,
+    profile: Option<String>,
+) -> Result<String, String> {
+    let config = Config::load().map_err(|e| e.to_string())?;
+    let dir = config.todos_path_for(profile.as_deref())
+        .ok_or_else(|| format!("Unknown profile \"{}\"", profile.unwrap_or_default()))?;
+    let redactor = redact::Redactor::new(&config.redaction);
+
+    let sessions = tokio::task::spawn_blocking(move || match session_id {
+        Some(id) => {
+            let todos = watcher::read_session_todos(&dir, &id);
+            std::collections::BTreeMap::from([(id, todos)])
+        }
+        None => watcher::read_all_todos(&dir),
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+
+    // Sessions are headed by the project they ran in, where it is known
+    let groups = state
+        .query(move |store| {
+            sessions
+                .into_iter()
+                .map(|(id, mut todos)| {
+                    for todo in &mut todos {
+                        todo.content = redactor.redact(&todo.content).into_owned();
                     }
+                    let heading = match store.get_session(&id)?.and_then(|session| session.project_path) {
+                        Some(path) => format!("{} ({})", path, id),
+                        None => id,
+                    };
+                    Ok((heading, todos))
+                })
+                .collect::<Result<Vec<_>, store::StoreError>>()
+        })
+        .await?;
+
+    Ok(summary::todos_markdown(&groups))
+}
+
+/// Active todos grouped by session, each labeled with its project; also sent as `todos-grouped`
+#[tauri::command]
+fn get_todo_groups(groups: State<'_, LatestTodoGroups>) -> Vec<watcher::TodoGroup> {
+    groups.get()
+}
+
+/// Todos completed between `since_ms` (default: a week ago) and `until_ms` (default: now), most recent first
+#[tauri::command]
+async fn get_completed_todos(
+    state: State<'_, StoreState>,
+    since_ms: Option<i64>,
+    until_ms: Option<i64>,
+) -> Result<Vec<store::StoredTodo>, String> {
+    let now = clock::SystemClock.now_ms();
+    let since = since_ms.unwrap_or(now - 7 * 24 * 60 * 60 * 1000);
+    let until = until_ms.unwrap_or(i64::MAX);
+    state.query(move |store| store.get_completed_todos(since, until)).await
+}
+
+/// All tags, by name
+#[tauri::command]
+async fn list_tags(state: State<'_, StoreState>) -> Result<Vec<store::Tag>, String> {
+    state.query(|store| store.list_tags()).await
+}
+
+/// Create a tag, or return the existing one with that name
+#[tauri::command]
+async fn create_tag(state: State<'_, StoreState>, name: String) -> Result<store::Tag, String> {
+    state.query(move |store| store.create_tag(&name, clock::SystemClock.now_ms())).await
+}
+
+#[tauri::command]
+async fn rename_tag(state: State<'_, StoreState>, id: i64, name: String) -> Result<bool, String> {
+    state.query(move |store| store.rename_tag(id, &name)).await
+}
+
+/// Delete a tag and take it off everything it labels
+#[tauri::command]
+async fn delete_tag(state: State<'_, StoreState>, id: i64) -> Result<bool, String> {
+    state.query(move |store| store.delete_tag(id)).await
+}
+
+/// Tag a session (the default) or untag it with `tagged: false`; unknown tags are created
+#[tauri::command]
+async fn tag_session(
+    state: State<'_, StoreState>,
+    session_id: String,
+    tag: String,
+    tagged: Option<bool>,
+) -> Result<bool, String> {
+    let tagged = tagged.unwrap_or(true);
+    state.query(move |store| store.set_session_tag(&session_id, &tag, tagged, clock::SystemClock.now_ms())).await
+}
+
+/// Tag a task (the default) or untag it with `tagged: false`; unknown tags are created
+#[tauri::command]
+async fn tag_task(
+    state: State<'_, StoreState>,
+    task_id: String,
+    tag: String,
+    tagged: Option<bool>,
+) -> Result<bool, String> {
+    let tagged = tagged.unwrap_or(true);
+    state.query(move |store| store.set_task_tag(&task_id, &tag, tagged, clock::SystemClock.now_ms())).await
+}
+
+#[tauri::command]
+async fn get_session_tags(state: State<'_, StoreState>, session_id: String) -> Result<Vec<store::Tag>, String> {
+    state.query(move |store| store.get_session_tags(&session_id)).await
+}
+
+/// Tags on a task, including those it inherits from its session
+#[tauri::command]
+async fn get_task_tags(state: State<'_, StoreState>, task_id: String) -> Result<Vec<store::Tag>, String> {
+    state.query(move |store| store.get_task_tags(&task_id)).await
+}
+
+/// Task statistics sliced by tag
+#[tauri::command]
+async fn get_tag_stats(state: State<'_, StoreState>) -> Result<Vec<store::TagStats>, String> {
+    state.query(|store| store.get_tag_stats()).await
+}
+
+/// Mark several tasks as reviewed, or clear their marks, returning how many changed
+#[tauri::command]
+async fn mark_tasks_reviewed(
+    state: State<'_, StoreState>,
+    task_ids: Vec<String>,
+    reviewed: Option<bool>,
+) -> Result<usize, String> {
+    let reviewed = reviewed.unwrap_or(true);
+    state.query(move |store| store.set_reviewed(&task_ids, reviewed, clock::SystemClock.now_ms())).await
+}
+
+/// Mark every finished task matching the filter as reviewed, e.g. all failures, returning how many changed
+#[tauri::command]
+async fn mark_all_reviewed(state: State<'_, StoreState>, filter: Option<store::TaskFilter>) -> Result<usize, String> {
+    state.query(move |store| store.mark_all_reviewed(&filter.unwrap_or_default(), clock::SystemClock.now_ms())).await
+}
+
+#[tauri::command]
+async fn get_active_tasks(state: State<'_, StoreState>) -> Result<Vec<store::StoredTask>, String> {
+    state.query(|store| store.get_active_tasks()).await
+}
+
+#[tauri::command]
+async fn get_session_summary(
+    state: State<'_, StoreState>,
+    session_id: String,
+) -> Result<Option<store::SessionSummary>, String> {
+    state.query(move |store| store.get_session_summary(&session_id)).await
+}
+
+/// A background task's process: CPU and memory as last sampled, whether it is still running, and its exit code
+#[tauri::command]
+async fn get_process_info(
+    state: State<'_, StoreState>,
+    task_id: String,
+) -> Result<Option<store::ProcessInfo>, String> {
+    state.query(move |store| store.get_task_process(&task_id)).await
+}
+
+/// The prompt a task ran for and the assistant's reply, if the transcripts plugin found them
+#[tauri::command]
+async fn get_task_context(
+    state: State<'_, StoreState>,
+    task_id: String,
+) -> Result<Option<store::TaskContext>, String> {
+    state.query(move |store| store.get_task_context(&task_id)).await
+}
+
+/// How full a session's context window was after each reply, oldest first
+#[tauri::command]
+async fn get_context_usage(
+    state: State<'_, StoreState>,
+    session_id: String,
+) -> Result<Vec<store::ContextSample>, String> {
+    state.query(move |store| store.get_context_samples(&session_id)).await
+}
+
+/// Tasks, tokens and estimated cost per model since `since_ms` (all history by default)
+#[tauri::command]
+async fn get_model_usage(
+    state: State<'_, StoreState>,
+    since_ms: Option<i64>,
+) -> Result<Vec<store::ModelUsage>, String> {
+    let config = Config::load().map_err(|e| e.to_string())?;
+    let mut usage = state.query(move |store| store.get_model_usage(since_ms.unwrap_or(0))).await?;
+    for entry in &mut usage {
+        entry.cost_usd = entry.model.as_deref()
+            .and_then(|model| config::ModelPrice::find(&config.pricing, model))
+            .map(|price| price.cost(entry.input_tokens, entry.output_tokens, entry.cache_read_tokens, entry.cache_creation_tokens));
+    }
+    Ok(usage)
+}
+
+/// Start a focus session, ending any that is running; it ends on its own after `minutes` if given
+#[tauri::command]
+async fn start_focus_session(
+    app: AppHandle,
+    state: State<'_, StoreState>,
+    label: Option<String>,
+    minutes: Option<u64>,
+) -> Result<store::FocusSession, String> {
+    let planned_ms = minutes.filter(|m| *m > 0).map(|m| (m * 60_000) as i64);
+    let session = state
+        .query(move |store| store.start_focus_session(label.as_deref(), planned_ms, clock::SystemClock.now_ms()))
+        .await?;
+    emit_focus_session(&app, &session);
+
+    if let Some(planned_ms) = planned_ms {
+        let store = state.get().await;
+        let id = session.id;
+        tauri::async_runtime::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(planned_ms as u64)).await;
+            let stopped = tokio::task::spawn_blocking(move || {
+                // Only if this session is still the one running
+                match store.get_active_focus_session()? {
+                    Some(active) if active.id == id => store.stop_focus_session(clock::SystemClock.now_ms()),
+                    _ => Ok(None),
                 }
+            }).await;
+            match stopped {
+                Ok(Ok(Some(session))) => emit_focus_session(&app, &session),
+                Ok(Err(e)) => tracing::error!("Failed to end focus session: {}", e),
+                _ => {}
+            }
+        });
+    }
+    Ok(session)
+}
+
+/// End the running focus session, returning it with a summary of the tasks finished during it
+#[tauri::command]
+async fn stop_focus_session(app: AppHandle, state: State<'_, StoreState>) -> Result<Option<store::FocusSession>, String> {
+    let session = state.query(|store| store.stop_focus_session(clock::SystemClock.now_ms())).await?;
+    if let Some(session) = &session {
+        emit_focus_session(&app, session);
+    }
+    Ok(session)
+}
+
+/// Recent focus sessions, newest first; the running one has no `ended_at`
+#[tauri::command]
+async fn get_focus_sessions(state: State<'_, StoreState>, limit: Option<i64>) -> Result<Vec<store::FocusSession>, String> {
+    state.query(move |store| store.get_focus_sessions(limit.unwrap_or(20))).await
+}
+
+/// Tell every window a focus session started or ended
+fn emit_focus_session(app: &AppHandle, session: &store::FocusSession) {
+    if let Err(e) = app.emit("focus-session", session) {
+        tracing::error!("Failed to emit focus session: {}", e);
+        health::metrics().record_emit_failure();
+    }
+}
+
+/// Notification popups shown since `since_ms` (all history by default), newest first
+#[tauri::command]
+async fn get_notification_history(
+    state: State<'_, StoreState>,
+    since_ms: Option<i64>,
+    limit: Option<i64>,
+) -> Result<Vec<store::NotificationRecord>, String> {
+    state.query(move |store| store.get_notification_history(since_ms.unwrap_or(0), limit.unwrap_or(100))).await
+}
+
+/// How often popups of each kind were clicked, dismissed or went away on their own since `since_ms`
+#[tauri::command]
+async fn get_notification_stats(
+    state: State<'_, StoreState>,
+    since_ms: Option<i64>,
+) -> Result<Vec<store::NotificationStats>, String> {
+    state.query(move |store| store.get_notification_stats(since_ms.unwrap_or(0))).await
+}
+
+/// Rate-limit and usage-limit warnings, newest first
+#[tauri::command]
+async fn get_usage_warnings(
+    state: State<'_, StoreState>,
+    limit: Option<i64>,
+) -> Result<Vec<UsageWarning>, String> {
+    state.query(move |store| store.get_usage_warnings(limit.unwrap_or(50))).await
+}
+
+/// Write tasks and sessions started since `since_ms` (all history by default) as Parquet files
+///
+/// Only builds with the `parquet` feature can; others return an error saying so.
+#[tauri::command]
+async fn export_history_parquet(
+    state: State<'_, StoreState>,
+    since_ms: Option<i64>,
+) -> Result<export::ExportReport, String> {
+    let store = state.get().await;
+    tokio::task::spawn_blocking(move || export::export_parquet(&store, &export::default_dir(), since_ms.unwrap_or(0)))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// Move sessions started before `before_date` (YYYY-MM-DD, local time) into a dated archive database
+#[tauri::command]
+async fn archive_sessions(state: State<'_, StoreState>, before_date: String) -> Result<archive::ArchiveResult, String> {
+    let config = Config::load().map_err(|e| e.to_string())?;
+    let store = state.get().await;
+    tokio::task::spawn_blocking(move || archive::archive_sessions(&store, &config, &before_date))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+async fn list_archives() -> Result<Vec<archive::ArchiveInfo>, String> {
+    let config = Config::load().map_err(|e| e.to_string())?;
+    archive::list_archives(&config)
+}
+
+/// Tasks in an archive from `list_archives`, matching `query` or, when it's empty, the most recent
+#[tauri::command]
+async fn search_archive(
+    name: String,
+    query: String,
+    limit: usize,
+    filter: Option<store::TaskFilter>,
+) -> Result<Vec<store::StoredTask>, String> {
+    let config = Config::load().map_err(|e| e.to_string())?;
+    tokio::task::spawn_blocking(move || {
+        archive::search_archive(&config, &name, &query, &filter.unwrap_or_default(), limit)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Events of types the user's hooks made up, of one type or all, newest first
+#[tauri::command]
+async fn get_custom_events(
+    state: State<'_, StoreState>,
+    custom_type: Option<String>,
+    limit: Option<i64>,
+) -> Result<Vec<CustomEvent>, String> {
+    state.query(move |store| store.get_custom_events(custom_type.as_deref(), limit.unwrap_or(50))).await
+}
+
+#[tauri::command]
+async fn sync_history(state: State<'_, StoreState>) -> Result<sync::SyncReport, String> {
+    let config = Config::load().map_err(|e| e.to_string())?;
+    let sync = sync::HistorySync::new(&config.sync, state.get().await).map_err(|e| e.to_string())?;
+    tokio::task::spawn_blocking(move || sync.run_once())
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+/// Merge a history database copied from another machine; `origin` names that machine
+#[tauri::command]
+async fn merge_database(
+    state: State<'_, StoreState>,
+    path: String,
+    origin: Option<String>,
+) -> Result<store::MergeReport, String> {
+    let store = state.get().await;
+    tokio::task::spawn_blocking(move || sync::merge_database(&store, std::path::Path::new(&path), origin.as_deref()))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn get_plugin_permissions(state: State<'_, StoreState>) -> Result<Vec<router::PluginPermissions>, String> {
+    let config = Config::load().map_err(|e| e.to_string())?;
+    Ok(plugins::permissions(&config, state.get().await))
+}
+
+#[tauri::command]
+async fn get_dead_letters(
+    state: State<'_, StoreState>,
+    plugin: Option<String>,
+    limit: usize,
+) -> Result<Vec<store::DeadLetter>, String> {
+    state.query(move |store| store.get_dead_letters(plugin.as_deref(), limit)).await
+}
+
+#[tauri::command]
+fn get_health_metrics() -> health::HealthSnapshot {
+    health::core_snapshot()
+}
+
+/// Palette and status indicators of the configured theme
+#[tauri::command]
+fn get_theme() -> Result<Theme, String> {
+    Config::load().map(|config| Theme::from_config(&config.window.theme)).map_err(|e| e.to_string())
+}
+
+/// The backdrop effect behind the overlay, "none" when there is none
+#[tauri::command]
+fn get_backdrop() -> &'static str {
+    backdrop::applied().name()
+}
+
+/// Whether windows should turn their animations off
+#[tauri::command]
+fn get_reduced_motion() -> Result<bool, String> {
+    Config::load().map(|config| config.behavior.reduced_motion).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_config() -> Result<Config, String> {
+    Config::load().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn save_config(config: Config) -> Result<(), String> {
+    config.save().map_err(|e| e.to_string())
+}
+
+/// The last `lines` lines the overlay and the core logged, oldest first, at
+/// `level_filter` (e.g. "warn") or more severe
+#[tauri::command]
+async fn tail_logs(lines: usize, level_filter: Option<String>) -> Result<Vec<logs::LogLine>, String> {
+    let filter = logs::parse_level(level_filter.as_deref().unwrap_or(""))?;
+    let config = Config::load().map_err(|e| e.to_string())?;
+    tokio::task::spawn_blocking(move || logs::tail(&config, lines, filter))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Change the log level and log files, here and in the core, and save them
+#[tauri::command]
+async fn set_logging(logging: config::LoggingConfig) -> Result<(), String> {
+    let mut config = Config::load().map_err(|e| e.to_string())?;
+    config.logging = logging;
+    // Check the log file is somewhere allowed before writing to it
+    config.check_paths().map_err(|e| e.to_string())?;
+    logs::configure(&config)?;
+    config.save().map_err(|e| e.to_string())?;
+    let token = ipc::core_token().map_err(|e| e.to_string())?;
+    ipc::request_reload_logging(config.core.port, &token).await.map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Check the overlay's setup, for the "Setup health" screen
+#[tauri::command]
+async fn run_doctor() -> Result<Vec<doctor::Check>, String> {
+    let config = Config::load().map_err(|e| e.to_string())?;
+    let exe = std::env::current_exe().map_err(|e| e.to_string())?;
+    tokio::task::spawn_blocking(move || doctor::run(&config, &exe))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Where the first-run setup stands
+#[tauri::command]
+fn get_onboarding_status() -> Result<onboarding::OnboardingStatus, String> {
+    let config = Config::load().map_err(|e| e.to_string())?;
+    let exe = std::env::current_exe().map_err(|e| e.to_string())?;
+    onboarding::status(&config, &exe)
+}
+
+/// Add the overlay's hooks to Claude's settings, for each profile too
+#[tauri::command]
+fn install_hooks() -> Result<Vec<hooks::HookStatus>, String> {
+    change_hooks(hooks::install)
+}
+
+/// Remove the overlay's hooks from Claude's settings, leaving the user's own
+#[tauri::command]
+fn uninstall_hooks() -> Result<Vec<hooks::HookStatus>, String> {
+    change_hooks(hooks::uninstall)
+}
+
+/// Point the overlay's hooks at this copy of the overlay and add missing ones
+#[tauri::command]
+fn repair_hooks() -> Result<Vec<hooks::HookStatus>, String> {
+    change_hooks(hooks::repair)
+}
+
+/// Apply `change` to the hooks of each Claude directory, profiles included
+fn change_hooks(
+    change: fn(&std::path::Path, &std::path::Path) -> Result<hooks::HookStatus, String>,
+) -> Result<Vec<hooks::HookStatus>, String> {
+    let config = Config::load().map_err(|e| e.to_string())?;
+    let exe = std::env::current_exe().map_err(|e| e.to_string())?;
+    config.claude_dirs().iter().map(|dir| change(dir, &exe)).collect()
+}
+
+/// Move the overlay to a corner and switch its theme, and keep both
+#[tauri::command]
+fn set_appearance(window: WebviewWindow, position: String, theme: String) -> Result<(), String> {
+    onboarding::check_corner(&position)?;
+    let palette = Theme::from_name(&theme)?;
+    let mut config = Config::load().map_err(|e| e.to_string())?;
+    config.window.position = position;
+    config.window.theme = theme;
+    config.save().map_err(|e| e.to_string())?;
+    position_window(&window, &config.window.position);
+    let _ = window.emit("theme-changed", palette);
+    Ok(())
+}
+
+/// Write a short test task to the events file, returning its task id
+///
+/// It goes through the watcher like Claude's tasks, so it shows up only if
+/// the overlay can read the file the hooks write to.
+#[tauri::command]
+async fn send_test_event() -> Result<String, String> {
+    const TEST_DURATION_MS: u64 = 2000;
+    let config = Config::load().map_err(|e| e.to_string())?;
+    let path = config.events_path();
+    let now_ms = clock::SystemClock.now_ms() as u64;
+    let (started, complete) = onboarding::test_events(now_ms, TEST_DURATION_MS);
+    let task_id = started.task_id.clone();
+    hooks::append_event(&path, &started).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(TEST_DURATION_MS)).await;
+        if let Err(e) = hooks::append_event(&path, &complete) {
+            tracing::warn!("Failed to finish the test task: {}", e);
+        }
+    });
+    Ok(task_id)
+}
+
+/// Finish or skip the first-run setup, so it isn't shown again
+#[tauri::command]
+fn complete_onboarding() -> Result<(), String> {
+    let mut config = Config::load().map_err(|e| e.to_string())?;
+    config.onboarding.completed = true;
+    config.save().map_err(|e| e.to_string())
+}
+
+/// Save a diagnostics bundle for a bug report, returning where it was saved
+#[tauri::command]
+async fn generate_diagnostics(app: AppHandle) -> Result<String, String> {
+    save_diagnostics(&app).await.map(|path| path.to_string_lossy().into_owned())
+}
+
+async fn save_diagnostics(app: &AppHandle) -> Result<std::path::PathBuf, String> {
+    let config = Config::load().map_err(|e| e.to_string())?;
+    let store = app.state::<StoreState>().get().await;
+    let attached = app.state::<ServicesState>().attached().unwrap_or(false);
+    tokio::task::spawn_blocking(move || {
+        diagnostics::generate(&config, &store, attached, &diagnostics::default_dir())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Ask the configured release channel for a newer build; `None` when up to date
+#[tauri::command]
+async fn check_for_updates(app: AppHandle) -> Result<Option<updates::UpdateInfo>, String> {
+    updates::check(&app).await
+}
+
+/// Install the update the last check found and restart into it
+#[tauri::command]
+async fn install_update(app: AppHandle) -> Result<(), String> {
+    let port = Config::load().unwrap_or_default().core.port;
+    updates::install(&app, port).await
+}
+
+// ============================================================================
+// Application Entry Point
+// ============================================================================
+
+pub fn run() {
+    // Claude runs the hooks for every tool call, so they skip everything else
+    if hooks::hook_requested(std::env::args().skip(1)) {
+        hooks::run_hook(&Config::load().unwrap_or_default());
+        return;
+    }
+
+    // Initialize logging; the configured level and log file apply once the config is read
+    logs::init(if core::headless_requested(std::env::args().skip(1)) { logs::CORE } else { logs::OVERLAY });
+
+    if let Some(action) = service::requested(std::env::args().skip(1)) {
+        match service::run(action) {
+            Ok(done) => println!("{}", done),
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    tracing::info!("Starting Agent Progress Overlay v2");
+
+    // Files from before the overlay used the platform directories
+    config::migrate_legacy_files();
+
+    // Load configuration
+    let config = Config::load().unwrap_or_else(|e| {
+        tracing::warn!("Failed to load config: {}, using defaults", e);
+        Config::default()
+    });
+
+    if let Err(e) = logs::configure(&config) {
+        tracing::warn!("Failed to apply logging settings: {}", e);
+    }
+
+    locale::set_current(locale::Locale::from_config(&config.behavior.locale));
+
+    if core::stop_requested(std::env::args().skip(1)) {
+        match core::stop_running(&config) {
+            Ok(done) => println!("{}", done),
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    if core::headless_requested(std::env::args().skip(1)) {
+        core::run_headless(config);
+        return;
+    }
+
+    let config = Arc::new(config);
+
+    // One runtime for the watcher, store writes, plugins, bridge, relay, and
+    // sync; tauri uses it too, so commands and background work share threads
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .thread_name("overlay-runtime")
+        .build()
+        .expect("failed to start async runtime");
+    tauri::async_runtime::set(runtime.handle().clone());
+
+    tauri::Builder::default()
+        .plugin(tauri_plugin_shell::init())
+        .plugin(tauri_plugin_clipboard_manager::init())
+        .plugin(tauri_plugin_updater::Builder::new().build())
+        .manage(StoreState::new(config.database_path()))
+        .manage(ServicesState::default())
+        .manage(PendingCancels::default())
+        .manage(AwaitingInput::default())
+        .manage(LatestTodoGroups::default())
+        .invoke_handler(tauri::generate_handler![
+            // v1 commands (backward compatible)
+            hide_window,
+            show_window,
+            show_notification_ready,
+            close_app,
+            clear_events,
+            toggle_devtools,
+            set_window_position,
+            set_always_on_top,
+            set_opacity,
+            // v2 commands
+            get_task_stats,
+            get_task_history,
+            search_tasks,
+            get_recent_tasks,
+            get_active_tasks,
+            mark_reviewed,
+            mark_tasks_reviewed,
+            mark_all_reviewed,
+            pin_task,
+            list_pinned,
+            copy_task_summary,
+            open_task_location,
+            request_rerun,
+            request_cancel,
+            set_todo_status,
+            add_todo,
+            reorder_todos,
+            get_todo_burndown,
+            export_todos_markdown,
+            get_todo_groups,
+            get_completed_todos,
+            list_tags,
+            create_tag,
+            rename_tag,
+            delete_tag,
+            tag_session,
+            tag_task,
+            get_session_tags,
+            get_task_tags,
+            get_tag_stats,
+            get_session_summary,
+            get_task_context,
+            get_process_info,
+            kill_background_task,
+            get_storage_usage,
+            events_since,
+            get_context_usage,
+            get_usage_warnings,
+            get_custom_events,
+            export_history_parquet,
+            archive_sessions,
+            list_archives,
+            search_archive,
+            get_notification_history,
+            get_notification_stats,
+            get_model_usage,
+            start_focus_session,
+            stop_focus_session,
+            get_focus_sessions,
+            dismiss_notification,
+            dismiss_all_notifications,
+            focus_next_notification,
+            open_notification,
+            sync_history,
+            merge_database,
+            get_plugin_permissions,
+            get_dead_letters,
+            get_health_metrics,
+            get_reduced_motion,
+            get_backdrop,
+            get_theme,
+            get_config,
+            save_config,
+            run_doctor,
+            get_onboarding_status,
+            install_hooks,
+            uninstall_hooks,
+            repair_hooks,
+            set_appearance,
+            send_test_event,
+            complete_onboarding,
+            tail_logs,
+            set_logging,
+            generate_diagnostics,
+            check_for_updates,
+            install_update,
+        ])
+        .setup(move |app| {
+            let window = app.get_webview_window("main").unwrap();
+            layer_shell::init(&config.window);
+            layer_shell::attach(&window, true);
+            panel::init(app, &config.window);
+            panel::attach(&window);
+            backdrop::apply(
+                &window,
+                backdrop::Backdrop::from_config(&config.window.backdrop),
+                &Theme::from_config(&config.window.theme),
+            );
+
+            // Position window based on config
+            position_window(&window, &config.window.position);
+
+            // Set always on top
+            if config.window.always_on_top {
+                let _ = window.set_always_on_top(true);
+            }
+            workspace::apply(app.handle(), &window, &config.window);
+
+            // Create system tray
+            setup_system_tray(app)?;
+
+            setup_notification_shortcuts(app, &config.shortcuts)?;
+
+            updates::start(app.handle().clone(), &config.updates);
+
+            // Feed the overlay's log lines, and an attached core's, to debug windows
+            let log_handle = app.handle().clone();
+            logs::set_listener(move |line| {
+                // Not logged on failure, which would only feed back here
+                let _ = log_handle.emit("log-line", &line);
+            });
+
+            // Open the store and start watching off the startup path, so the
+            // window appears without waiting on a large history database
+            let app_handle = app.handle().clone();
+            let config = config.clone();
+            tauri::async_runtime::spawn(async move {
+                let store = app_handle.state::<StoreState>().get().await;
+                let services = start_services(app_handle.clone(), config, store).await;
+                app_handle.state::<ServicesState>().set(services);
             });
 
             Ok(())
         })
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| match event {
+            RunEvent::ExitRequested { code, api, .. } => {
+                // Hold the exit until the services have flushed; the second
+                // request finds nothing left to stop and goes through
+                let Some(services) = app.state::<ServicesState>().take() else {
+                    return;
+                };
+                api.prevent_exit();
+                let app = app.clone();
+                tauri::async_runtime::spawn(async move {
+                    services.shutdown().await;
+                    app.exit(code.unwrap_or(0));
+                });
+            }
+            RunEvent::Exit => {
+                // Exits that skip the request, e.g. the OS ending the session
+                if let Some(services) = app.state::<ServicesState>().take() {
+                    tauri::async_runtime::block_on(services.shutdown());
+                }
+            }
+            _ => {}
+        });
+}
+
+/// The running background services, kept so exit can stop them cleanly
+struct Services {
+    /// The core, unless the overlay attached to a headless one
+    core: Option<Core>,
+    store: Arc<EventStore>,
+    clock: Arc<dyn Clock>,
+    notifications: Arc<Mutex<NotificationManager>>,
+}
+
+impl Services {
+    /// Stop the core if it runs here; an attached overlay only closes out its popups
+    async fn shutdown(self) {
+        if let Some(core) = self.core {
+            core.shutdown().await;
+            return;
+        }
+        let (store, now) = (self.store, self.clock.now_ms());
+        match tokio::task::spawn_blocking(move || store.close_open_notifications(now)).await {
+            Ok(Ok(_)) => {}
+            Ok(Err(e)) => tracing::error!("Failed to close notifications on exit: {}", e),
+            Err(e) => tracing::error!("Notification close task failed: {}", e),
+        }
+    }
+}
+
+/// Agents waiting on the user, by popup key, with the task and what it waits for
+///
+/// Listed in the tray's tooltip until the agent moves again.
+#[derive(Default)]
+struct AwaitingInput(Mutex<BTreeMap<String, (String, String)>>);
+
+impl AwaitingInput {
+    fn insert(&self, key: String, task_id: String, label: String) {
+        if let Ok(mut waiting) = self.0.lock() {
+            waiting.insert(key, (task_id, label));
+        }
+    }
+
+    /// Keys of the agents `event` shows are no longer waiting
+    ///
+    /// Any further progress in the session means the prompt was answered;
+    /// without a session only the waiting task itself ending does.
+    fn resolve(&self, event: &TaskEvent) -> Vec<String> {
+        if event.event_type == "awaiting_input" {
+            return Vec::new();
+        }
+        let Ok(mut waiting) = self.0.lock() else {
+            return Vec::new();
+        };
+        let session_key = event.session_id.as_ref().map(|_| awaiting_input_key(event));
+        let answered: Vec<String> = waiting
+            .iter()
+            .filter(|(key, (task_id, _))| session_key.as_ref() == Some(*key) || *task_id == event.task_id)
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in &answered {
+            waiting.remove(key);
+        }
+        answered
+    }
+
+    fn labels(&self) -> Vec<String> {
+        self.0.lock().map(|waiting| waiting.values().map(|(_, label)| label.clone()).collect()).unwrap_or_default()
+    }
+}
+
+/// Tasks with a cancel request out, by task id, with their session
+///
+/// The UI shows them as canceling until the task ends, however it ends.
+#[derive(Default)]
+struct PendingCancels(Mutex<HashMap<String, String>>);
+
+impl PendingCancels {
+    fn insert(&self, task_id: String, session_id: String) {
+        if let Ok(mut pending) = self.0.lock() {
+            pending.insert(task_id, session_id);
+        }
+    }
+
+    /// Task ids whose pending cancel is settled by `event`
+    fn resolve(&self, event: &TaskEvent) -> Vec<String> {
+        let Ok(mut pending) = self.0.lock() else {
+            return Vec::new();
+        };
+        match event.event_type.as_str() {
+            "task_complete" | "task_error" | "task_canceled" => {
+                pending.remove(&event.task_id).map(|_| event.task_id.clone()).into_iter().collect()
+            }
+            "session_stopped" => {
+                let ended: Vec<String> = pending.iter()
+                    .filter(|(_, session_id)| event.session_id.as_deref() == Some(session_id.as_str()))
+                    .map(|(task_id, _)| task_id.clone())
+                    .collect();
+                for task_id in &ended {
+                    pending.remove(task_id);
+                }
+                ended
+            }
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// The todos last sent to the UI, grouped by session
+#[derive(Default)]
+struct LatestTodoGroups(Mutex<Vec<watcher::TodoGroup>>);
+
+impl LatestTodoGroups {
+    fn set(&self, groups: Vec<watcher::TodoGroup>) {
+        if let Ok(mut latest) = self.0.lock() {
+            *latest = groups;
+        }
+    }
+
+    fn get(&self) -> Vec<watcher::TodoGroup> {
+        self.0.lock().map(|latest| latest.clone()).unwrap_or_default()
+    }
+}
+
+/// Services once they are started; taken by whichever exit path stops them
+#[derive(Default)]
+struct ServicesState(Mutex<Option<Services>>);
+
+impl ServicesState {
+    fn set(&self, services: Services) {
+        if let Ok(mut slot) = self.0.lock() {
+            *slot = Some(services);
+        }
+    }
+
+    fn take(&self) -> Option<Services> {
+        self.0.lock().ok().and_then(|mut slot| slot.take())
+    }
+
+    /// Whether the overlay attached to a headless core, while the services run
+    fn attached(&self) -> Option<bool> {
+        let slot = self.0.lock().ok()?;
+        slot.as_ref().map(|services| services.core.is_none())
+    }
+
+    /// The notification popups, while the services run
+    fn notifications(&self) -> Option<Arc<Mutex<NotificationManager>>> {
+        let slot = self.0.lock().ok()?;
+        slot.as_ref().map(|services| services.notifications.clone())
+    }
+}
+
+/// Shows the core's output in the overlay's windows
+struct WindowSink {
+    app_handle: AppHandle,
+    notifications: Arc<Mutex<NotificationManager>>,
+}
+
+impl CoreSink for WindowSink {
+    fn event(&self, event: WatcherEvent) {
+        present_event(&self.app_handle, &self.notifications, event);
+    }
+
+    fn todo_groups(&self, groups: Vec<watcher::TodoGroup>) {
+        present_todo_groups(&self.app_handle, groups);
+    }
+
+    fn reconciled(&self, snapshot: &ReconciledSnapshot) {
+        close_orphan_notifications(&self.app_handle, &self.notifications);
+        if let Err(e) = self.app_handle.emit("tasks-reconciled", snapshot) {
+            tracing::error!("Failed to emit reconciled tasks: {}", e);
+            health::metrics().record_emit_failure();
+        }
+    }
+
+    fn overdue(&self, task: &OverdueTask) {
+        if let Err(e) = self.app_handle.emit("task-overdue", task) {
+            tracing::error!("Failed to emit overdue task: {}", e);
+            health::metrics().record_emit_failure();
+        }
+    }
+
+    fn storage_warning(&self, usage: &storage::StorageUsage) {
+        if let Err(e) = self.app_handle.emit("storage-warning", usage) {
+            tracing::error!("Failed to emit storage warning: {}", e);
+            health::metrics().record_emit_failure();
+        }
+        create_storage_warning_window(&self.app_handle, &self.notifications, usage);
+    }
+
+    fn window_action(&self, action: &str) -> bool {
+        let Some(window) = self.app_handle.get_webview_window("main") else {
+            return false;
+        };
+        let show = match action {
+            "show_overlay" => true,
+            "hide_overlay" => false,
+            _ => !window.is_visible().unwrap_or(false),
+        };
+        if show { window.show().is_ok() } else { window.hide().is_ok() }
+    }
+}
+
+/// Attach to the core, starting it if it isn't running
+async fn start_services(app_handle: AppHandle, config: Arc<Config>, store: Arc<EventStore>) -> Services {
+    let clock: Arc<dyn Clock> = Arc::new(clock::SystemClock);
+    let idle_after = Duration::from_secs(config.behavior.idle_after_mins * 60);
+    let notifications = Arc::new(Mutex::new(NotificationManager::new(
+        &config,
+        Presence::new(idle_after),
+        NotificationLog::start(store.clone(), clock.clone()),
+        clock.clone(),
+    )));
+    if !idle_after.is_zero() && idle::idle_time().is_some() {
+        watch_presence(app_handle.clone(), notifications.clone(), clock.clone());
+    }
+
+    let sink: Arc<dyn CoreSink> = Arc::new(WindowSink {
+        app_handle: app_handle.clone(),
+        notifications: notifications.clone(),
+    });
+
+    // Stdin belongs to this process, so a core reading it has to run here
+    if !stdin::requested(std::env::args().skip(1)) {
+        match ipc::core_token() {
+            Ok(token) => {
+                if let Some(stream) = ipc::connect_or_spawn(config.core.port, &token).await {
+                    tracing::info!("Attached to the core on port {}", config.core.port);
+                    tauri::async_runtime::spawn(ipc::attach(config.core.port, token, stream, sink));
+                    return Services { core: None, store, clock, notifications };
+                }
+            }
+            Err(e) => tracing::error!("Failed to read the core token: {}", e),
+        }
+        tracing::warn!("Running the core in the overlay; events stop being recorded when it closes");
+    }
+
+    let (resume_store, resume_config) = (store.clone(), config.clone());
+    let resume_at = tokio::task::spawn_blocking(move || core::take_events_offsets(&resume_store, &resume_config))
+        .await
+        .unwrap_or_default();
+    let core = Core::start(config, store.clone(), resume_at, sink);
+    Services { core: Some(core), store, clock, notifications }
+}
+
+/// Close notification windows the manager isn't tracking
+///
+/// These are left over from before the manager was set up, e.g. a window the
+/// webview restored, and would otherwise sit on screen with nothing to close them.
+fn close_orphan_notifications(app_handle: &AppHandle, notification_manager: &Mutex<NotificationManager>) {
+    let Ok(nm) = notification_manager.lock() else {
+        return;
+    };
+    let tracked: Vec<&str> = nm.active_windows.values().map(|(label, _)| label.as_str()).collect();
+    for (label, window) in app_handle.webview_windows() {
+        if label.starts_with("notification-") && !tracked.contains(&label.as_str()) {
+            tracing::info!("Closing orphaned notification window {}", label);
+            let _ = window.close();
+        }
+    }
+}
+
+/// Emit a processed event to the frontend and update notification windows
+/// Tell screen readers about a task starting or finishing, if enabled
+fn announce_task(app_handle: &AppHandle, manager: &NotificationManager, event_type: &str, label: &str) {
+    if !manager.announce {
+        return;
+    }
+    if let Some(text) = a11y::announcement(event_type, label, locale::current()) {
+        a11y::announce(app_handle, &text);
+    }
+}
+
+/// Send the todos grouped by session, right after the flat list they came from
+fn present_todo_groups(app_handle: &AppHandle, groups: Vec<watcher::TodoGroup>) {
+    if let Err(e) = app_handle.emit("todos-grouped", &groups) {
+        tracing::error!("Failed to emit todo groups: {}", e);
+    }
+    app_handle.state::<LatestTodoGroups>().set(groups);
+}
+
+fn present_event(app_handle: &AppHandle, notification_manager: &Arc<Mutex<NotificationManager>>, event: WatcherEvent) {
+    match event {
+        WatcherEvent::TaskEvent(ref task_event) if task_event.event_type == "custom" => {
+            // Not progress on any task, so no popup, and waiting agents stay waiting
+            if let Err(e) = app_handle.emit("custom-event", task_event) {
+                tracing::error!("Failed to emit custom event: {}", e);
+                health::metrics().record_emit_failure();
+            }
+        }
+        WatcherEvent::TaskEvent(ref task_event) => {
+            // Emit to frontend
+            if let Err(e) = app_handle.emit("task-event", task_event) {
+                tracing::error!("Failed to emit task event: {}", e);
+                health::metrics().record_emit_failure();
+            }
+            for task_id in app_handle.state::<PendingCancels>().resolve(task_event) {
+                let resolved = serde_json::json!({ "task_id": task_id, "outcome": task_event.event_type });
+                let _ = app_handle.emit("task-cancel-resolved", resolved);
+            }
+
+            // Handle notification windows
+            if let Ok(mut nm) = notification_manager.lock() {
+                let answered = app_handle.state::<AwaitingInput>().resolve(task_event);
+                for key in &answered {
+                    close_notification_window(app_handle, &mut nm, key, "answered");
+                }
+                if !answered.is_empty() {
+                    refresh_tray_tooltip(app_handle);
+                }
+                let delivery = nm.delivery(task_event);
+                match task_event.event_type.as_str() {
+                    "task_started" => {
+                        let label = task_event.description.as_deref()
+                            .or(task_event.tool.as_deref())
+                            .unwrap_or("Task");
+                        let label = text::truncate(label, NOTIFICATION_DESCRIPTION_GRAPHEMES);
+                        nm.presence.task_started(&task_event.task_id, &label);
+                        if nm.shows(delivery) {
+                            create_notification_window(app_handle, &mut nm, task_event);
+                            announce_task(app_handle, &nm, &task_event.event_type, &label);
+                        }
+                    }
+                    "task_complete" | "task_error" => {
+                        let label = nm.presence.task_ended(&task_event.task_id, Some(task_event.event_type == "task_error"));
+                        close_notification_window(app_handle, &mut nm, &task_event.task_id, "finished");
+                        if let Some(label) = label.filter(|_| nm.shows(delivery)) {
+                            announce_task(app_handle, &nm, &task_event.event_type, &label);
+                        }
+                    }
+                    "task_canceled" => {
+                        nm.presence.task_ended(&task_event.task_id, None);
+                        if let Some((label, _)) = nm.remove(&task_event.task_id, "finished") {
+                            if let Some(window) = app_handle.get_webview_window(&label) {
+                                let _ = window.close();
+                            }
+                            reposition_notification_windows(app_handle, &nm);
+                        }
+                    }
+                    "session_stopped" => {
+                        nm.presence.clear_tasks();
+                        // Usage warnings outlive the turn that hit the limit
+                        let keys: Vec<String> = nm.active_windows.keys()
+                            .filter(|key| !key.starts_with(USAGE_WARNING_KEY_PREFIX))
+                            .cloned()
+                            .collect();
+                        for key in keys {
+                            if let Some((label, _)) = nm.remove(&key, "finished") {
+                                if let Some(window) = app_handle.get_webview_window(&label) {
+                                    let _ = window.close();
+                                }
+                            }
+                        }
+                        reposition_notification_windows(app_handle, &nm);
+                    }
+                    "awaiting_input" if nm.shows(delivery) => create_awaiting_input_window(app_handle, &mut nm, task_event),
+                    _ => {}
+                }
+                request_attention(app_handle, &nm, &task_event.event_type, delivery);
+            }
+        }
+        WatcherEvent::TodosUpdated(ref todos) => {
+            if let Err(e) = app_handle.emit("todos-update", todos) {
+                tracing::error!("Failed to emit todos: {}", e);
+                health::metrics().record_emit_failure();
+            }
+        }
+        WatcherEvent::TodosChanged(ref diff) => {
+            if let Err(e) = app_handle.emit("todos-diff", diff) {
+                tracing::error!("Failed to emit todos diff: {}", e);
+                health::metrics().record_emit_failure();
+            }
+        }
+        WatcherEvent::DownloadProgress(ref progress) => {
+            // Emit to all windows
+            for window in app_handle.webview_windows().values() {
+                if window.emit("download-progress", progress).is_err() {
+                    health::metrics().record_emit_failure();
+                }
+            }
+        }
+        WatcherEvent::GenericProgress(ref progress) => {
+            if let Err(e) = app_handle.emit("generic-progress", progress) {
+                tracing::error!("Failed to emit progress: {}", e);
+                health::metrics().record_emit_failure();
+            }
+        }
+        WatcherEvent::ContextUsage(ref usage) => {
+            if let Err(e) = app_handle.emit("context-usage", usage) {
+                tracing::error!("Failed to emit context usage: {}", e);
+                health::metrics().record_emit_failure();
+            }
+        }
+        // Only kept for model usage statistics
+        WatcherEvent::TokenUsage(_) => {}
+        WatcherEvent::UsageWarning(ref warning) => {
+            if let Err(e) = app_handle.emit("usage-warning", warning) {
+                tracing::error!("Failed to emit usage warning: {}", e);
+                health::metrics().record_emit_failure();
+            }
+            create_usage_warning_window(app_handle, notification_manager, warning);
+        }
+        WatcherEvent::Error(e) => {
+            tracing::error!("Watcher error: {}", e);
+        }
+    }
+}
+
+/// How often the tray's diagnostics section is refreshed
+const DIAGNOSTICS_REFRESH: Duration = Duration::from_secs(5);
+
+const TRAY_ID: &str = "main";
+const TRAY_TOOLTIP: &str = "Agent Progress Overlay";
+
+/// Windows cuts tray tooltips off at 127 UTF-16 code units
+const TRAY_TOOLTIP_MAX_UTF16: usize = 127;
+
+/// Show in the tray's tooltip what needs attention: failing tasks, agents waiting on the user and a ready update
+fn refresh_tray_tooltip(app: &AppHandle) {
+    let Some(tray) = app.tray_by_id(TRAY_ID) else {
+        return;
+    };
+    let locale = locale::current();
+    let degraded = health::core_snapshot().degraded;
+    let mut tooltip = if degraded.is_empty() {
+        TRAY_TOOLTIP.to_string()
+    } else {
+        locale.format(Message::TooltipDegraded, &[("app", &TRAY_TOOLTIP), ("tasks", &degraded.join(", "))])
+    };
+    let waiting = app.try_state::<AwaitingInput>().map(|state| state.labels()).unwrap_or_default();
+    if !waiting.is_empty() {
+        tooltip = locale.format(Message::TooltipAwaitingInput, &[("app", &tooltip), ("tasks", &waiting.join(", "))]);
+    }
+    if let Some(version) = app.try_state::<updates::UpdateState>().and_then(|state| state.ready_version()) {
+        tooltip = locale.format(Message::TooltipUpdate, &[("app", &tooltip), ("version", &version)]);
+    }
+    let _ = tray.set_tooltip(Some(text::truncate_utf16(&tooltip, TRAY_TOOLTIP_MAX_UTF16)));
 }
 
 fn setup_system_tray(app: &tauri::App) -> Result<(), Box<dyn std::error::Error>> {
-    use tauri::menu::{Menu, MenuItem};
+    use tauri::menu::{IsMenuItem, Menu, MenuItem, PredefinedMenuItem, Submenu};
     use tauri::tray::{TrayIconBuilder, TrayIconEvent, MouseButton, MouseButtonState};
 
-    let show_item = MenuItem::with_id(app, "show", "Show", true, None::<&str>)?;
-    let hide_item = MenuItem::with_id(app, "hide", "Hide", true, None::<&str>)?;
-    let quit_item = MenuItem::with_id(app, "quit", "Quit", true, None::<&str>)?;
-    let menu = Menu::with_items(app, &[&show_item, &hide_item, &quit_item])?;
+    let locale = locale::current();
+    let show_item = MenuItem::with_id(app, "show", locale.text(Message::TrayShow), true, None::<&str>)?;
+    let hide_item = MenuItem::with_id(app, "hide", locale.text(Message::TrayHide), true, None::<&str>)?;
+    let quit_item = MenuItem::with_id(app, "quit", locale.text(Message::TrayQuit), true, None::<&str>)?;
+    // Reads "Install update ..." once a check finds one
+    let update_item = MenuItem::with_id(app, "update", locale.text(Message::TrayCheckUpdates), true, None::<&str>)?;
+    app.manage(updates::UpdateState::new(update_item.clone()));
+
+    // Read-only lines showing the health metrics, kept current while the app runs
+    let diagnostics_items = health::metrics()
+        .snapshot()
+        .summary_lines(locale)
+        .iter()
+        .map(|line| MenuItem::new(app, line, false, None::<&str>))
+        .collect::<Result<Vec<_>, _>>()?;
+    let separator = PredefinedMenuItem::separator(app)?;
+    let save_diagnostics_item =
+        MenuItem::with_id(app, "save_diagnostics", locale.text(Message::TraySaveDiagnostics), true, None::<&str>)?;
+    let mut diagnostics_refs: Vec<&dyn IsMenuItem<tauri::Wry>> =
+        diagnostics_items.iter().map(|item| item as &dyn IsMenuItem<tauri::Wry>).collect();
+    diagnostics_refs.push(&separator);
+    diagnostics_refs.push(&save_diagnostics_item);
+    let diagnostics = Submenu::with_items(app, locale.text(Message::TrayDiagnostics), true, &diagnostics_refs)?;
+
+    let menu = Menu::with_items(app, &[&show_item, &hide_item, &diagnostics, &update_item, &quit_item])?;
 
     let icon = app.default_window_icon().cloned().expect("no default icon");
 
-    let _tray = TrayIconBuilder::new()
+    TrayIconBuilder::with_id(TRAY_ID)
         .icon(icon)
         .menu(&menu)
-        .tooltip("Agent Progress Overlay")
+        .tooltip(TRAY_TOOLTIP)
         .on_menu_event(|app, event| {
             match event.id.as_ref() {
                 "show" => {
@@ -482,6 +2572,36 @@ fn setup_system_tray(app: &tauri::App) -> Result<(), Box<dyn std::error::Error>>
                         let _ = window.hide();
                     }
                 }
+                "save_diagnostics" => {
+                    let app = app.clone();
+                    tauri::async_runtime::spawn(async move {
+                        match save_diagnostics(&app).await {
+                            Ok(path) => {
+                                // Show the bundle so it can be attached to a report
+                                let config = Config::load().unwrap_or_default();
+                                let dir = path.parent().unwrap_or(&path);
+                                if let Err(e) = opener::open(&config.openers, opener::Location::Folder, dir) {
+                                    tracing::warn!("Failed to open {:?}: {}", dir, e);
+                                }
+                            }
+                            Err(e) => tracing::error!("Failed to save diagnostics: {}", e),
+                        }
+                    });
+                }
+                "update" => {
+                    let app = app.clone();
+                    tauri::async_runtime::spawn(async move {
+                        let result = if app.state::<updates::UpdateState>().ready_version().is_some() {
+                            let port = Config::load().unwrap_or_default().core.port;
+                            updates::install(&app, port).await
+                        } else {
+                            updates::check(&app).await.map(|_| ())
+                        };
+                        if let Err(e) = result {
+                            tracing::error!("Update failed: {}", e);
+                        }
+                    });
+                }
                 "quit" => {
                     app.exit(0);
                 }
@@ -504,5 +2624,29 @@ fn setup_system_tray(app: &tauri::App) -> Result<(), Box<dyn std::error::Error>>
         })
         .build(app)?;
 
+    // Refresh the diagnostics, and flag supervised tasks that keep failing in
+    // the tooltip and the window
+    let app_handle = app.handle().clone();
+    tauri::async_runtime::spawn(async move {
+        let mut ticker = tokio::time::interval(DIAGNOSTICS_REFRESH);
+        let mut degraded: Vec<String> = Vec::new();
+        loop {
+            ticker.tick().await;
+            let snapshot = health::core_snapshot();
+            for (item, line) in diagnostics_items.iter().zip(snapshot.summary_lines(locale).iter()) {
+                let _ = item.set_text(line);
+            }
+
+            if snapshot.degraded != degraded {
+                degraded = snapshot.degraded;
+                refresh_tray_tooltip(&app_handle);
+                if let Err(e) = app_handle.emit("health-status", &degraded) {
+                    tracing::error!("Failed to emit health status: {}", e);
+                    health::metrics().record_emit_failure();
+                }
+            }
+        }
+    });
+
     Ok(())
 }
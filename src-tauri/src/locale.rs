@@ -0,0 +1,317 @@
+//! Translations of the text the backend shows to people
+//!
+//! Tray menu labels and tooltips, the diagnostics lines, screen reader
+//! announcements and the session reports plugins write all come from the
+//! catalog here, in the language `behavior.locale` selects. Placeholders
+//! are written `{name}` and filled in by [`Locale::format`], so word order
+//! is up to each translation.
+
+use std::fmt::Display;
+use std::sync::OnceLock;
+
+/// A language the catalog covers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Locale {
+    #[default]
+    En,
+    De,
+    Ja,
+    Zh,
+}
+
+/// A user-facing string in the catalog
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Message {
+    TrayShow,
+    TrayHide,
+    TrayQuit,
+    TrayDiagnostics,
+    TraySaveDiagnostics,
+    TrayCheckUpdates,
+    TrayInstallUpdate,
+    TooltipDegraded,
+    TooltipUpdate,
+    TooltipAwaitingInput,
+    StatusOk,
+    StatusDegraded,
+    EventsRate,
+    ParseErrors,
+    EmitFailures,
+    DbWrites,
+    SlowestPlugin,
+    NoPlugins,
+    TaskStarted,
+    TaskFinished,
+    TaskFailed,
+    ReportTitle,
+    ReportTitleBranch,
+    ReportSession,
+    ReportSummary,
+    ReportProject,
+    ReportSessionLength,
+    ReportTaskCounts,
+    ReportErrors,
+    ReportCommits,
+    ReportTodos,
+    ReportTasks,
+    ReportMore,
+}
+
+static CURRENT: OnceLock<Locale> = OnceLock::new();
+
+/// Set the app's locale; only the first call has an effect
+pub fn set_current(locale: Locale) {
+    let _ = CURRENT.set(locale);
+}
+
+/// The app's locale, English until one is set
+pub fn current() -> Locale {
+    CURRENT.get().copied().unwrap_or_default()
+}
+
+impl Locale {
+    /// Parse a language tag such as "de", "ja-JP" or "zh_CN.UTF-8"
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        let language = name.trim().split(['-', '_', '.']).next().unwrap_or("").to_ascii_lowercase();
+        match language.as_str() {
+            "en" | "c" | "posix" => Ok(Locale::En),
+            "de" => Ok(Locale::De),
+            "ja" => Ok(Locale::Ja),
+            "zh" => Ok(Locale::Zh),
+            _ => Err(format!("Unsupported locale \"{}\"", name.trim())),
+        }
+    }
+
+    /// The configured locale; empty uses the system's language where it is known
+    pub fn from_config(name: &str) -> Self {
+        if name.trim().is_empty() {
+            return ["LC_ALL", "LC_MESSAGES", "LANG"]
+                .iter()
+                .filter_map(|var| std::env::var(var).ok())
+                .find(|value| !value.is_empty())
+                .and_then(|value| Self::from_name(&value).ok())
+                .unwrap_or_default();
+        }
+        Self::from_name(name).unwrap_or_else(|e| {
+            tracing::warn!("{}, using English", e);
+            Locale::En
+        })
+    }
+
+    /// The translation of a message, placeholders unfilled
+    pub fn text(self, message: Message) -> &'static str {
+        match self {
+            Locale::En => en(message),
+            Locale::De => de(message),
+            Locale::Ja => ja(message),
+            Locale::Zh => zh(message),
+        }
+    }
+
+    /// The translation with its `{name}` placeholders filled in
+    pub fn format(self, message: Message, args: &[(&str, &dyn Display)]) -> String {
+        let mut output = self.text(message).to_string();
+        for (name, value) in args {
+            output = output.replace(&format!("{{{}}}", name), &value.to_string());
+        }
+        output
+    }
+}
+
+fn en(message: Message) -> &'static str {
+    use Message::*;
+    match message {
+        TrayShow => "Show",
+        TrayHide => "Hide",
+        TrayQuit => "Quit",
+        TrayDiagnostics => "Diagnostics",
+        TraySaveDiagnostics => "Save Diagnostics Bundle",
+        TrayCheckUpdates => "Check for Updates",
+        TrayInstallUpdate => "Install Update {version}",
+        TooltipDegraded => "{app} (degraded: {tasks})",
+        TooltipUpdate => "{app} (update {version} ready)",
+        TooltipAwaitingInput => "{app} (waiting for input: {tasks})",
+        StatusOk => "Status: OK",
+        StatusDegraded => "Status: degraded ({tasks})",
+        EventsRate => "Events: {rate}/s ({total} total)",
+        ParseErrors => "Parse errors: {count}",
+        EmitFailures => "Emit failures: {count}",
+        DbWrites => "DB writes: {avg} ms avg, {max} ms max",
+        SlowestPlugin => "Slowest plugin: {name} ({avg} ms avg, {failures} failed)",
+        NoPlugins => "Plugins: none",
+        TaskStarted => "Started: {task}",
+        TaskFinished => "Finished: {task}",
+        TaskFailed => "Failed: {task}",
+        ReportTitle => "{project} - {count} tasks",
+        ReportTitleBranch => "{project} ({branch}) - {count} tasks",
+        ReportSession => "Session",
+        ReportSummary => "Summary",
+        ReportProject => "Project: {path}",
+        ReportSessionLength => "Session length: {duration}",
+        ReportTaskCounts => "Tasks: {total} total, {completed} completed, {errors} errors ({duration} of tool time)",
+        ReportErrors => "Errors",
+        ReportCommits => "Commits",
+        ReportTodos => "Todos",
+        ReportTasks => "Tasks",
+        ReportMore => "...and {count} more",
+    }
+}
+
+fn de(message: Message) -> &'static str {
+    use Message::*;
+    match message {
+        TrayShow => "Anzeigen",
+        TrayHide => "Ausblenden",
+        TrayQuit => "Beenden",
+        TrayDiagnostics => "Diagnose",
+        TraySaveDiagnostics => "Diagnosepaket speichern",
+        TrayCheckUpdates => "Nach Updates suchen",
+        TrayInstallUpdate => "Update {version} installieren",
+        TooltipDegraded => "{app} (eingeschränkt: {tasks})",
+        TooltipUpdate => "{app} (Update {version} bereit)",
+        TooltipAwaitingInput => "{app} (wartet auf Eingabe: {tasks})",
+        StatusOk => "Status: OK",
+        StatusDegraded => "Status: eingeschränkt ({tasks})",
+        EventsRate => "Ereignisse: {rate}/s ({total} insgesamt)",
+        ParseErrors => "Lesefehler: {count}",
+        EmitFailures => "Sendefehler: {count}",
+        DbWrites => "DB-Schreibzugriffe: {avg} ms im Schnitt, {max} ms max.",
+        SlowestPlugin => "Langsamstes Plugin: {name} ({avg} ms im Schnitt, {failures} fehlgeschlagen)",
+        NoPlugins => "Plugins: keine",
+        TaskStarted => "Gestartet: {task}",
+        TaskFinished => "Fertig: {task}",
+        TaskFailed => "Fehlgeschlagen: {task}",
+        ReportTitle => "{project} - {count} Aufgaben",
+        ReportTitleBranch => "{project} ({branch}) - {count} Aufgaben",
+        ReportSession => "Sitzung",
+        ReportSummary => "Zusammenfassung",
+        ReportProject => "Projekt: {path}",
+        ReportSessionLength => "Sitzungsdauer: {duration}",
+        ReportTaskCounts => "Aufgaben: {total} insgesamt, {completed} erledigt, {errors} Fehler ({duration} Werkzeugzeit)",
+        ReportErrors => "Fehler",
+        ReportCommits => "Commits",
+        ReportTodos => "To-dos",
+        ReportTasks => "Aufgaben",
+        ReportMore => "...und {count} weitere",
+    }
+}
+
+fn ja(message: Message) -> &'static str {
+    use Message::*;
+    match message {
+        TrayShow => "表示",
+        TrayHide => "隠す",
+        TrayQuit => "終了",
+        TrayDiagnostics => "診断",
+        TraySaveDiagnostics => "診断パッケージを保存",
+        TrayCheckUpdates => "アップデートを確認",
+        TrayInstallUpdate => "アップデート {version} をインストール",
+        TooltipDegraded => "{app}（低下: {tasks}）",
+        TooltipUpdate => "{app}（アップデート {version} の準備完了）",
+        TooltipAwaitingInput => "{app}（入力待ち: {tasks}）",
+        StatusOk => "状態: 正常",
+        StatusDegraded => "状態: 低下（{tasks}）",
+        EventsRate => "イベント: {rate}/秒（合計 {total}）",
+        ParseErrors => "解析エラー: {count}",
+        EmitFailures => "送信失敗: {count}",
+        DbWrites => "DB書き込み: 平均 {avg} ms、最大 {max} ms",
+        SlowestPlugin => "最も遅いプラグイン: {name}（平均 {avg} ms、失敗 {failures}）",
+        NoPlugins => "プラグイン: なし",
+        TaskStarted => "開始: {task}",
+        TaskFinished => "完了: {task}",
+        TaskFailed => "失敗: {task}",
+        ReportTitle => "{project} - タスク {count} 件",
+        ReportTitleBranch => "{project}（{branch}）- タスク {count} 件",
+        ReportSession => "セッション",
+        ReportSummary => "概要",
+        ReportProject => "プロジェクト: {path}",
+        ReportSessionLength => "セッション時間: {duration}",
+        ReportTaskCounts => "タスク: 合計 {total}、完了 {completed}、エラー {errors}（ツール時間 {duration}）",
+        ReportErrors => "エラー",
+        ReportCommits => "コミット",
+        ReportTodos => "ToDo",
+        ReportTasks => "タスク",
+        ReportMore => "…ほか {count} 件",
+    }
+}
+
+fn zh(message: Message) -> &'static str {
+    use Message::*;
+    match message {
+        TrayShow => "显示",
+        TrayHide => "隐藏",
+        TrayQuit => "退出",
+        TrayDiagnostics => "诊断",
+        TraySaveDiagnostics => "保存诊断包",
+        TrayCheckUpdates => "检查更新",
+        TrayInstallUpdate => "安装更新 {version}",
+        TooltipDegraded => "{app}（降级：{tasks}）",
+        TooltipUpdate => "{app}（更新 {version} 已就绪）",
+        TooltipAwaitingInput => "{app}（等待输入：{tasks}）",
+        StatusOk => "状态：正常",
+        StatusDegraded => "状态：降级（{tasks}）",
+        EventsRate => "事件：{rate}/秒（共 {total} 个）",
+        ParseErrors => "解析错误：{count}",
+        EmitFailures => "发送失败：{count}",
+        DbWrites => "数据库写入：平均 {avg} ms，最长 {max} ms",
+        SlowestPlugin => "最慢的插件：{name}（平均 {avg} ms，失败 {failures} 次）",
+        NoPlugins => "插件：无",
+        TaskStarted => "已开始：{task}",
+        TaskFinished => "已完成：{task}",
+        TaskFailed => "已失败：{task}",
+        ReportTitle => "{project} - {count} 个任务",
+        ReportTitleBranch => "{project}（{branch}）- {count} 个任务",
+        ReportSession => "会话",
+        ReportSummary => "摘要",
+        ReportProject => "项目：{path}",
+        ReportSessionLength => "会话时长：{duration}",
+        ReportTaskCounts => "任务：共 {total} 个，完成 {completed} 个，出错 {errors} 个（工具用时 {duration}）",
+        ReportErrors => "错误",
+        ReportCommits => "提交",
+        ReportTodos => "待办事项",
+        ReportTasks => "任务",
+        ReportMore => "……另有 {count} 个",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL_MESSAGES: [Message; 33] = [
+        Message::TrayShow, Message::TrayHide, Message::TrayQuit, Message::TrayDiagnostics,
+        Message::TraySaveDiagnostics, Message::TrayCheckUpdates, Message::TrayInstallUpdate, Message::TooltipDegraded,
+        Message::TooltipUpdate, Message::StatusOk, Message::StatusDegraded, Message::EventsRate,
+        Message::ParseErrors, Message::EmitFailures, Message::DbWrites, Message::SlowestPlugin,
+        Message::NoPlugins, Message::TaskStarted, Message::TaskFinished, Message::TaskFailed,
+        Message::ReportTitle, Message::ReportTitleBranch, Message::ReportSession, Message::ReportSummary,
+        Message::ReportProject, Message::ReportSessionLength, Message::ReportTaskCounts, Message::ReportErrors,
+        Message::ReportCommits, Message::ReportTodos, Message::ReportTasks, Message::ReportMore,
+        Message::TooltipAwaitingInput,
+    ];
+
+    fn placeholders(text: &str) -> Vec<&str> {
+        let mut names: Vec<&str> = text.split('{').skip(1).filter_map(|s| s.split_once('}')).map(|(name, _)| name).collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_locale_catalog() {
+        assert_eq!(Locale::from_name("ja-JP").unwrap(), Locale::Ja);
+        assert_eq!(Locale::from_name("zh_CN.UTF-8").unwrap(), Locale::Zh);
+        assert!(Locale::from_name("fr").is_err());
+        assert_eq!(Locale::from_config("klingon"), Locale::En);
+
+        assert_eq!(Locale::De.format(Message::TaskFailed, &[("task", &"npm test")]), "Fehlgeschlagen: npm test");
+        let title = Locale::Ja.format(Message::ReportTitleBranch, &[("project", &"app"), ("branch", &"main"), ("count", &3)]);
+        assert_eq!(title, "app（main）- タスク 3 件");
+
+        // Every translation keeps the placeholders of the English text
+        for locale in [Locale::De, Locale::Ja, Locale::Zh] {
+            for message in ALL_MESSAGES {
+                assert_eq!(placeholders(locale.text(message)), placeholders(Locale::En.text(message)), "{:?} {:?}", locale, message);
+            }
+        }
+    }
+}
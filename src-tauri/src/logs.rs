@@ -0,0 +1,383 @@
+//! Log output - a rotating log file, recent lines for diagnostics, and a live feed
+//!
+//! [`init`] starts logging before the config is read; [`configure`] then
+//! applies the `[logging]` settings, and applies them again whenever they
+//! change. Log lines still go to stdout. [`LogWriter`] also appends them to a
+//! log file that is rotated at the configured size, keeps the last
+//! [`RECENT_LINES`] in memory for diagnostics bundles, and hands each line to
+//! the listener set with [`set_listener`], so a debug window can follow the
+//! backend without the user having started it from a terminal.
+//!
+//! The overlay writes `overlay.log` and a headless core `core.log` beside it;
+//! an attached overlay's feed carries the core's lines too.
+
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Mutex, OnceLock};
+
+use serde::{Deserialize, Serialize};
+use tracing::Level;
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::filter::Directive;
+use tracing_subscriber::EnvFilter;
+
+use crate::config::{Config, LoggingConfig};
+
+/// How many log lines are kept in memory
+pub const RECENT_LINES: usize = 2000;
+/// Most lines `tail` returns
+pub const MAX_TAIL_LINES: usize = 10_000;
+
+/// Which process a line came from
+pub const OVERLAY: &str = "overlay";
+pub const CORE: &str = "core";
+
+/// Crates whose logging the configured level applies to; others only log errors
+const TARGETS: [&str; 2] = ["agent_progress_overlay", "progress_overlay_lib"];
+
+type SetFilter = Box<dyn Fn(EnvFilter) -> Result<(), String> + Send + Sync>;
+
+static RECENT: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+static FILE: Mutex<Option<RotatingFile>> = Mutex::new(None);
+static SOURCE: OnceLock<&'static str> = OnceLock::new();
+static LISTENER: OnceLock<Box<dyn Fn(LogLine) + Send + Sync>> = OnceLock::new();
+static SET_FILTER: OnceLock<SetFilter> = OnceLock::new();
+
+thread_local! {
+    /// Set while the listener runs, so anything it logs isn't fed back to it
+    static IN_LISTENER: Cell<bool> = const { Cell::new(false) };
+}
+
+/// A log line, as sent in `log-line` events and returned by `tail`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LogLine {
+    /// "overlay" or "core"
+    pub source: String,
+    /// "ERROR", "WARN", "INFO", "DEBUG" or "TRACE"; none for continuation lines
+    pub level: Option<String>,
+    pub text: String,
+}
+
+impl LogLine {
+    fn new(source: &str, text: String) -> Self {
+        let level = level_of(&text).map(|level| level.to_string());
+        Self { source: source.to_string(), level, text }
+    }
+
+    /// Whether the line is at least as severe as `filter`
+    fn passes(&self, filter: Option<Level>) -> bool {
+        let Some(filter) = filter else {
+            return true;
+        };
+        self.level
+            .as_deref()
+            .and_then(|level| Level::from_str(level).ok())
+            .is_some_and(|level| level <= filter)
+    }
+}
+
+/// The level of a formatted line: the word after its timestamp
+fn level_of(text: &str) -> Option<Level> {
+    text.split_whitespace().nth(1).and_then(|word| Level::from_str(word).ok())
+}
+
+/// Parse a level filter such as "warn"; empty means every level
+pub fn parse_level(name: &str) -> Result<Option<Level>, String> {
+    if name.trim().is_empty() {
+        return Ok(None);
+    }
+    Level::from_str(name.trim())
+        .map(Some)
+        .map_err(|_| format!("Unknown log level \"{}\"", name.trim()))
+}
+
+/// Where `source` writes its log: the configured file for the overlay, `core.log` beside it for the core
+pub fn file_path(config: &Config, source: &str) -> PathBuf {
+    let overlay = config.log_path();
+    match source {
+        CORE => overlay.with_file_name("core.log"),
+        _ => overlay,
+    }
+}
+
+/// The filter for `level`, with `RUST_LOG` directives on top
+fn filter(level: &str) -> Result<EnvFilter, String> {
+    let Some(level) = parse_level(level)? else {
+        return Err("No log level given".to_string());
+    };
+    let mut filter = EnvFilter::from_default_env();
+    for target in TARGETS {
+        let directive = format!("{}={}", target, level.as_str().to_ascii_lowercase())
+            .parse::<Directive>()
+            .map_err(|e| e.to_string())?;
+        filter = filter.add_directive(directive);
+    }
+    Ok(filter)
+}
+
+/// Start logging at the default level, for `source` ([`OVERLAY`] or [`CORE`])
+pub fn init(source: &'static str) {
+    let _ = SOURCE.set(source);
+    let defaults = LoggingConfig::default();
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter(&defaults.level).expect("default log level is valid"))
+        .with_writer(LogWriter)
+        .with_filter_reloading();
+    let handle = builder.reload_handle();
+    let _ = SET_FILTER.set(Box::new(move |filter| handle.reload(filter).map_err(|e| e.to_string())));
+    builder.init();
+}
+
+/// Apply the `[logging]` settings: the level, and the log file and its rotation
+pub fn configure(config: &Config) -> Result<(), String> {
+    let settings = &config.logging;
+    let filter = filter(&settings.level)?;
+    if let Some(set_filter) = SET_FILTER.get() {
+        set_filter(filter)?;
+    }
+
+    let path = file_path(config, SOURCE.get().copied().unwrap_or(OVERLAY));
+    let file = RotatingFile::open(path.clone(), settings.max_size_mb * 1024 * 1024, settings.retained_files)
+        .map_err(|e| format!("Failed to open log file {}: {}", path.display(), e))?;
+    if let Ok(mut slot) = FILE.lock() {
+        *slot = Some(file);
+    }
+    Ok(())
+}
+
+/// Call `listener` with every line logged from now on; only the first call has an effect
+pub fn set_listener(listener: impl Fn(LogLine) + Send + Sync + 'static) {
+    let _ = LISTENER.set(Box::new(listener));
+}
+
+/// Hand a line from another process, i.e. the core, to the listener
+pub fn relay(line: LogLine) {
+    notify(line);
+}
+
+fn notify(line: LogLine) {
+    let Some(listener) = LISTENER.get() else {
+        return;
+    };
+    if IN_LISTENER.with(|busy| busy.replace(true)) {
+        return;
+    }
+    listener(line);
+    IN_LISTENER.with(|busy| busy.set(false));
+}
+
+/// The kept log lines, oldest first
+pub fn recent() -> Vec<String> {
+    RECENT.lock().map(|recent| recent.iter().cloned().collect()).unwrap_or_default()
+}
+
+/// The last `lines` lines of the overlay's and the core's log files at
+/// `filter` or more severe, oldest first
+pub fn tail(config: &Config, lines: usize, filter: Option<Level>) -> Vec<LogLine> {
+    let lines = lines.min(MAX_TAIL_LINES);
+    // Flush first so the overlay's own latest lines are in its file
+    if let Ok(mut slot) = FILE.lock() {
+        if let Some(file) = slot.as_mut() {
+            let _ = file.file.flush();
+        }
+    }
+    let mut tail: Vec<LogLine> = [OVERLAY, CORE]
+        .into_iter()
+        .flat_map(|source| tail_file(&file_path(config, source), source, lines, filter))
+        .collect();
+    // Lines start with an RFC 3339 timestamp in UTC, which sorts as text
+    tail.sort_by(|a, b| a.text.split(' ').next().cmp(&b.text.split(' ').next()));
+    let skip = tail.len().saturating_sub(lines);
+    tail.split_off(skip)
+}
+
+fn tail_file(path: &Path, source: &str, lines: usize, filter: Option<Level>) -> Vec<LogLine> {
+    let Ok(file) = File::open(path) else {
+        return Vec::new();
+    };
+    let mut tail = VecDeque::with_capacity(lines);
+    for text in BufReader::new(file).lines().map_while(Result::ok) {
+        let line = LogLine::new(source, text);
+        if !line.passes(filter) {
+            continue;
+        }
+        if tail.len() == lines {
+            tail.pop_front();
+        }
+        if lines > 0 {
+            tail.push_back(line);
+        }
+    }
+    tail.into()
+}
+
+/// A log file that is moved aside once it grows past `max_size` bytes
+struct RotatingFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+    max_size: u64,
+    /// Moved-aside files kept
+    retained: usize,
+}
+
+impl RotatingFile {
+    fn open(path: PathBuf, max_size: u64, retained: usize) -> io::Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let size = file.metadata()?.len();
+        Ok(Self { path, file, size, max_size, retained })
+    }
+
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        // 0 turns rotation off
+        if self.max_size > 0 && self.size >= self.max_size {
+            self.rotate()?;
+        }
+        writeln!(self.file, "{}", line)?;
+        self.size += line.len() as u64 + 1;
+        Ok(())
+    }
+
+    /// `overlay.log` becomes `overlay.log.1`, `.1` becomes `.2`, and the oldest is dropped
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        let rotated = |index: usize| PathBuf::from(format!("{}.{}", self.path.display(), index));
+        let _ = std::fs::remove_file(rotated(self.retained));
+        for index in (1..self.retained).rev() {
+            let _ = std::fs::rename(rotated(index), rotated(index + 1));
+        }
+        if self.retained > 0 {
+            std::fs::rename(&self.path, rotated(1))?;
+        } else {
+            std::fs::remove_file(&self.path)?;
+        }
+        *self = Self::open(self.path.clone(), self.max_size, self.retained)?;
+        Ok(())
+    }
+}
+
+fn keep(line: &str) {
+    let line = strip_ansi(line);
+    if let Ok(mut slot) = FILE.lock() {
+        if let Some(file) = slot.as_mut() {
+            // Nowhere left to report a failure to; stdout still has the line
+            let _ = file.write_line(&line);
+        }
+    }
+    if let Ok(mut recent) = RECENT.lock() {
+        if recent.len() == RECENT_LINES {
+            recent.pop_front();
+        }
+        recent.push_back(line.clone());
+    }
+    notify(LogLine::new(SOURCE.get().copied().unwrap_or(OVERLAY), line));
+}
+
+/// Remove the color codes the terminal output carries
+fn strip_ansi(line: &str) -> String {
+    let mut plain = String::with_capacity(line.len());
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // Skip to the end of the escape sequence, a letter
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            plain.push(c);
+        }
+    }
+    plain
+}
+
+/// Writes log output to stdout and keeps a copy of each line
+pub struct LogWriter;
+
+impl<'a> MakeWriter<'a> for LogWriter {
+    type Writer = LineWriter;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        LineWriter { buffer: Vec::new() }
+    }
+}
+
+/// One log event's output, kept when the event is done
+pub struct LineWriter {
+    buffer: Vec<u8>,
+}
+
+impl Write for LineWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+        io::stdout().write_all(buf)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        io::stdout().flush()
+    }
+}
+
+impl Drop for LineWriter {
+    fn drop(&mut self) {
+        for line in String::from_utf8_lossy(&self.buffer).lines() {
+            keep(line);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lines_are_kept_without_colors() {
+        let mut writer = LogWriter.make_writer();
+        writer.write_all(b"\x1b[2m2026-01-01T00:00:00Z\x1b[0m \x1b[32m INFO\x1b[0m test line one\nline two\n").unwrap();
+        drop(writer);
+
+        let recent = recent();
+        let tail = &recent[recent.len() - 2..];
+        assert_eq!(tail, ["2026-01-01T00:00:00Z  INFO test line one", "line two"]);
+    }
+
+    #[test]
+    fn test_levels() {
+        let line = LogLine::new(CORE, "2026-01-01T00:00:00Z  WARN core: slow".to_string());
+        assert_eq!(line.level.as_deref(), Some("WARN"));
+        assert!(line.passes(None));
+        assert!(line.passes(parse_level("info").unwrap()));
+        assert!(!line.passes(parse_level("error").unwrap()));
+        assert!(!LogLine::new(CORE, "continued".to_string()).passes(Some(Level::TRACE)));
+        assert!(parse_level("loud").is_err());
+        assert!(filter("warn").is_ok());
+        assert!(filter("").is_err());
+    }
+
+    #[test]
+    fn test_file_rotates_and_tails() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("core.log");
+        let mut file = RotatingFile::open(path.clone(), 64, 1).unwrap();
+        file.write_line("2026-01-01T00:00:01Z  INFO first, long enough to pass the limit").unwrap();
+        file.write_line("2026-01-01T00:00:02Z ERROR second").unwrap();
+        file.write_line("2026-01-01T00:00:03Z DEBUG third").unwrap();
+        drop(file);
+
+        assert!(dir.path().join("core.log.1").is_file());
+        assert!(!dir.path().join("core.log.2").exists());
+        let tail = tail_file(&path, CORE, 1, Some(Level::INFO));
+        assert_eq!(tail.len(), 1);
+        assert_eq!(tail[0].text, "2026-01-01T00:00:02Z ERROR second");
+        assert_eq!(tail_file(&path, CORE, 5, None).len(), 2);
+    }
+}
@@ -0,0 +1,131 @@
+//! Background upkeep for the history database
+//!
+//! SQLite only checkpoints the write-ahead log when a write happens to cross
+//! its page threshold, and never shrinks the file, so the `-wal` file beside
+//! the history database can grow to hundreds of MB over long sessions. This
+//! checks its size periodically and truncates it, preferring moments when no
+//! tasks are active so the checkpoint doesn't compete with event writes.
+//! It also deletes finished tasks older than `retention_days`, when set.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::clock::Clock;
+use crate::config::DatabaseConfig;
+use crate::store::EventStore;
+
+const MB: u64 = 1024 * 1024;
+const DAY_MS: i64 = 24 * 60 * 60 * 1000;
+
+/// Whether a WAL of `wal_bytes` should be checkpointed now
+fn should_checkpoint(config: &DatabaseConfig, wal_bytes: u64, idle: bool) -> bool {
+    if wal_bytes == 0 {
+        return false;
+    }
+    wal_bytes >= config.wal_max_mb * MB || (idle && wal_bytes >= config.wal_idle_checkpoint_mb * MB)
+}
+
+/// One maintenance pass: drop expired history, then checkpoint if the WAL has grown too large
+fn run_once(config: &DatabaseConfig, store: &EventStore, clock: &dyn Clock) -> Result<(), String> {
+    if config.retention_days > 0 {
+        let cutoff = clock.now_ms() - config.retention_days as i64 * DAY_MS;
+        let deleted = store.cleanup_old_tasks(cutoff).map_err(|e| e.to_string())?;
+        if deleted > 0 {
+            tracing::info!("Deleted {} tasks older than {} days", deleted, config.retention_days);
+        }
+    }
+
+    let wal_bytes = store.wal_size().map_err(|e| e.to_string())?;
+    let idle = store.get_active_task_count().map_err(|e| e.to_string())? == 0;
+    if !should_checkpoint(config, wal_bytes, idle) {
+        return Ok(());
+    }
+
+    let complete = store.wal_checkpoint().map_err(|e| e.to_string())?;
+    if complete {
+        tracing::info!("Checkpointed {} MB write-ahead log", wal_bytes / MB);
+    } else {
+        tracing::debug!("WAL checkpoint of {} MB was partial; retrying later", wal_bytes / MB);
+    }
+    Ok(())
+}
+
+/// Run maintenance every `checkpoint_interval_secs` on the current runtime
+pub fn start(config: DatabaseConfig, store: Arc<EventStore>, clock: Arc<dyn Clock>) {
+    if config.checkpoint_interval_secs == 0 {
+        return;
+    }
+    let interval = Duration::from_secs(config.checkpoint_interval_secs.max(5));
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        // The first tick fires immediately; there's nothing to do at startup
+        ticker.tick().await;
+        loop {
+            ticker.tick().await;
+            let (config, store, clock) = (config.clone(), store.clone(), clock.clone());
+            match tokio::task::spawn_blocking(move || run_once(&config, &store, clock.as_ref())).await {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => tracing::warn!("Database maintenance failed: {}", e),
+                Err(e) => tracing::error!("Database maintenance task failed: {}", e),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::{ManualClock, SystemClock};
+    use crate::store::StoredTask;
+    use tempfile::tempdir;
+
+    fn task(id: &str, status: &str, started_at: i64) -> StoredTask {
+        StoredTask {
+            session_id: "session-1".to_string(),
+            description: Some("x".repeat(1000)),
+            status: status.to_string(),
+            ..StoredTask::test(id, started_at)
+        }
+    }
+
+    #[test]
+    fn test_checkpoint_thresholds() {
+        let config = DatabaseConfig::default();
+        assert!(!should_checkpoint(&config, 0, true));
+        assert!(!should_checkpoint(&config, MB, true));
+        assert!(should_checkpoint(&config, config.wal_idle_checkpoint_mb * MB, true));
+        assert!(!should_checkpoint(&config, config.wal_idle_checkpoint_mb * MB, false));
+        assert!(should_checkpoint(&config, config.wal_max_mb * MB, false));
+    }
+
+    #[test]
+    fn test_checkpoint_truncates_wal() {
+        let dir = tempdir().unwrap();
+        let store = EventStore::new(&dir.path().join("history.db")).unwrap();
+        for i in 0..200 {
+            store.insert_task(&task(&format!("task-{}", i), "completed", i)).unwrap();
+        }
+        assert!(store.wal_size().unwrap() > 0);
+
+        let config = DatabaseConfig { wal_idle_checkpoint_mb: 0, ..Default::default() };
+        run_once(&config, &store, &SystemClock).unwrap();
+        assert_eq!(store.wal_size().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_retention_cleanup() {
+        let store = EventStore::in_memory().unwrap();
+        store.insert_task(&task("old", "completed", 0)).unwrap();
+        store.insert_task(&task("old-active", "active", 0)).unwrap();
+        store.insert_task(&task("recent", "completed", 9 * DAY_MS)).unwrap();
+
+        let config = DatabaseConfig { retention_days: 7, ..Default::default() };
+        let clock = ManualClock::new(10 * DAY_MS);
+        run_once(&config, &store, &clock).unwrap();
+
+        assert!(store.get_task("old").unwrap().is_none());
+        assert!(store.get_task("old-active").unwrap().is_some());
+        assert!(store.get_task("recent").unwrap().is_some());
+    }
+}
@@ -0,0 +1,124 @@
+//! First-run setup - what the onboarding screens ask the backend
+//!
+//! A new install starts with `onboarding.completed` unset. The setup checks
+//! that Claude Code is there, installs the hooks, lets the user pick a corner
+//! and a theme, and sends a test task through the events file, so the user
+//! sees the overlay pick it up the way it will pick up Claude's.
+
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::config::Config;
+use crate::hooks::{self, HookStatus};
+use crate::watcher::TaskEvent;
+
+/// Corners the overlay can sit in, as `window.position` takes them
+pub const CORNERS: [&str; 4] = ["top-left", "top-right", "bottom-left", "bottom-right"];
+
+/// Session the test task belongs to
+pub const TEST_SESSION: &str = "onboarding";
+
+/// What was found of Claude Code
+#[derive(Debug, Clone, Serialize)]
+pub struct ClaudeInstall {
+    pub claude_dir: PathBuf,
+    /// Claude Code has run at least once for this user
+    pub dir_exists: bool,
+    /// The `claude` command, if it is on the PATH
+    pub cli: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OnboardingStatus {
+    /// The setup hasn't been finished or skipped yet
+    pub first_run: bool,
+    pub claude: ClaudeInstall,
+    /// One per Claude config directory, profiles included
+    pub hooks: Vec<HookStatus>,
+}
+
+/// Where the setup stands; `exe` is the executable the hooks should run
+pub fn status(config: &Config, exe: &Path) -> Result<OnboardingStatus, String> {
+    let hooks = config
+        .claude_dirs()
+        .iter()
+        .map(|dir| hooks::status(dir, exe))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(OnboardingStatus { first_run: !config.onboarding.completed, claude: find_claude(config), hooks })
+}
+
+pub fn find_claude(config: &Config) -> ClaudeInstall {
+    let claude_dir = config.claude_dir();
+    ClaudeInstall { dir_exists: claude_dir.is_dir(), claude_dir, cli: find_on_path("claude") }
+}
+
+/// The first `name` on the PATH, trying the extensions Windows runs it with
+fn find_on_path(name: &str) -> Option<PathBuf> {
+    let names = [name.to_string(), format!("{}.exe", name), format!("{}.cmd", name)];
+    std::env::split_paths(&std::env::var_os("PATH")?)
+        .flat_map(|dir| names.iter().map(move |name| dir.join(name)))
+        .find(|path| path.is_file())
+}
+
+pub fn check_corner(position: &str) -> Result<(), String> {
+    if CORNERS.contains(&position) {
+        Ok(())
+    } else {
+        Err(format!("Unknown position \"{}\"; expected one of {}", position, CORNERS.join(", ")))
+    }
+}
+
+/// The test task's start and finish, `duration_ms` apart
+pub fn test_events(now_ms: u64, duration_ms: u64) -> (TaskEvent, TaskEvent) {
+    let task_id = format!("{}-{}", TEST_SESSION, now_ms);
+    let mut started = TaskEvent::new("task_started", &task_id, now_ms);
+    started.tool = Some("Setup".to_string());
+    started.description = Some("Test task from the overlay setup".to_string());
+    started.session_id = Some(TEST_SESSION.to_string());
+
+    let mut complete = TaskEvent::new("task_complete", &task_id, now_ms + duration_ms);
+    complete.session_id = Some(TEST_SESSION.to_string());
+    complete.duration_ms = Some(duration_ms);
+    (started, complete)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = Config::default();
+        config.paths.claude_dir = dir.path().to_string_lossy().to_string();
+        config.paths.profiles.clear();
+        let exe = Path::new("/opt/overlay/agent-progress-overlay");
+
+        let status = status(&config, exe).unwrap();
+        assert!(status.first_run);
+        assert!(status.claude.dir_exists);
+        assert_eq!(status.hooks.len(), 1);
+        assert_eq!(status.hooks[0].state, hooks::HookState::NotInstalled);
+
+        hooks::install(dir.path(), exe).unwrap();
+        config.onboarding.completed = true;
+        let status = super::status(&config, exe).unwrap();
+        assert!(!status.first_run);
+        assert_eq!(status.hooks[0].state, hooks::HookState::Installed);
+    }
+
+    #[test]
+    fn test_corners() {
+        assert!(check_corner("top-left").is_ok());
+        assert!(check_corner("center").is_err());
+    }
+
+    #[test]
+    fn test_test_events() {
+        let (started, complete) = test_events(1000, 2000);
+        assert_eq!(started.task_id, complete.task_id);
+        assert_eq!(complete.timestamp, 3000);
+        assert_eq!(complete.session_id.as_deref(), Some(TEST_SESSION));
+    }
+}
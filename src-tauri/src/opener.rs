@@ -0,0 +1,140 @@
+//! Opening a task's project folder in the file manager or a terminal
+//!
+//! The commands come from `[openers.<platform>]` in the config. Where none is
+//! set, the built-in ones are tried in order until one is installed, so a
+//! Linux desktop without `x-terminal-emulator` still gets a terminal.
+
+use std::path::Path;
+use std::process::Command;
+
+use crate::config::OpenersConfig;
+
+/// Where to open a project folder
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Location {
+    Folder,
+    Terminal,
+}
+
+impl Location {
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "folder" | "file_manager" => Ok(Location::Folder),
+            "terminal" => Ok(Location::Terminal),
+            _ => Err(format!("Unsupported location \"{}\"", name.trim())),
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn default_commands(location: Location) -> &'static [&'static [&'static str]] {
+    match location {
+        Location::Folder => &[&["open", "{path}"]],
+        Location::Terminal => &[&["open", "-a", "Terminal", "{path}"]],
+    }
+}
+
+#[cfg(windows)]
+fn default_commands(location: Location) -> &'static [&'static [&'static str]] {
+    match location {
+        Location::Folder => &[&["explorer", "{path}"]],
+        // `start` opens a new console window, which inherits the working directory
+        Location::Terminal => &[&["cmd", "/C", "start", "cmd"]],
+    }
+}
+
+#[cfg(not(any(windows, target_os = "macos")))]
+fn default_commands(location: Location) -> &'static [&'static [&'static str]] {
+    match location {
+        Location::Folder => &[&["xdg-open", "{path}"]],
+        Location::Terminal => &[
+            &["x-terminal-emulator"],
+            &["gnome-terminal"],
+            &["konsole"],
+            &["xterm"],
+        ],
+    }
+}
+
+/// Command lines to try for a location, with `{path}` filled in
+pub fn command_lines(config: &OpenersConfig, location: Location, path: &str) -> Vec<Vec<String>> {
+    let commands = config.current();
+    let configured = match location {
+        Location::Folder => &commands.file_manager,
+        Location::Terminal => &commands.terminal,
+    };
+
+    let fill = |arg: &str| arg.replace("{path}", path);
+    if configured.is_empty() {
+        default_commands(location)
+            .iter()
+            .map(|command| command.iter().map(|arg| fill(arg)).collect())
+            .collect()
+    } else {
+        vec![configured.iter().map(|arg| fill(arg)).collect()]
+    }
+}
+
+/// Open `path` in the file manager or a terminal, without waiting for it to close
+pub fn open(config: &OpenersConfig, location: Location, path: &Path) -> Result<(), String> {
+    if !path.is_dir() {
+        return Err(format!("Project folder {} doesn't exist", path.display()));
+    }
+
+    let mut last_error = "No opener command configured".to_string();
+    for command in command_lines(config, location, &path.to_string_lossy()) {
+        let Some((program, args)) = command.split_first() else {
+            continue;
+        };
+        match Command::new(program).args(args).current_dir(path).spawn() {
+            Ok(mut child) => {
+                // Reap the opener when it exits, so it doesn't linger as a zombie
+                std::thread::spawn(move || child.wait());
+                return Ok(());
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                last_error = format!("{} is not installed", program);
+            }
+            Err(e) => return Err(format!("Couldn't run {}: {}", program, e)),
+        }
+    }
+    Err(last_error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::OpenerCommands;
+
+    #[test]
+    fn test_configured_command() {
+        let commands = OpenerCommands {
+            file_manager: vec![],
+            terminal: vec!["alacritty".to_string(), "--working-directory".to_string(), "{path}".to_string()],
+        };
+        let config = OpenersConfig { linux: commands.clone(), macos: commands.clone(), windows: commands };
+
+        assert_eq!(
+            command_lines(&config, Location::Terminal, "/repo"),
+            [["alacritty", "--working-directory", "/repo"]]
+        );
+
+        // An empty command falls back to the built-in ones
+        let defaults = command_lines(&config, Location::Folder, "/repo");
+        assert!(!defaults.is_empty());
+        assert!(defaults.iter().all(|command| command.contains(&"/repo".to_string())));
+    }
+
+    #[test]
+    fn test_location_names() {
+        assert_eq!(Location::from_name("Terminal").unwrap(), Location::Terminal);
+        assert_eq!(Location::from_name("folder").unwrap(), Location::Folder);
+        assert!(Location::from_name("browser").is_err());
+    }
+
+    #[test]
+    fn test_missing_folder() {
+        let err = open(&OpenersConfig::default(), Location::Folder, Path::new("/no/such/project")).unwrap_err();
+        assert!(err.contains("doesn't exist"));
+    }
+}
@@ -0,0 +1,93 @@
+//! macOS panels
+//!
+//! A normal macOS window can't float over another app's fullscreen Space,
+//! and clicking it activates the app, pulling the user out of what they were
+//! doing. With `window.macos_panel` on, the overlay and its popups are turned
+//! into non-activating panels that join every Space, fullscreen ones
+//! included, and the app runs as an accessory without a dock icon.
+
+use std::sync::OnceLock;
+
+use tauri::{App, WebviewWindow};
+
+use crate::config::WindowConfig;
+
+static ENABLED: OnceLock<bool> = OnceLock::new();
+
+/// Decide once, at startup, whether windows become panels
+pub fn init(app: &mut App, config: &WindowConfig) {
+    let enabled = cfg!(target_os = "macos") && config.macos_panel;
+    if enabled {
+        platform::become_accessory(app);
+    }
+    let _ = ENABLED.set(enabled);
+}
+
+pub fn enabled() -> bool {
+    ENABLED.get().copied().unwrap_or(false)
+}
+
+/// Turn a window into a non-activating panel
+pub fn attach(window: &WebviewWindow) {
+    if !enabled() {
+        return;
+    }
+    let target = window.clone();
+    // AppKit is only usable from the main thread
+    let _ = window.run_on_main_thread(move || {
+        if let Err(e) = platform::attach(&target) {
+            tracing::warn!("Failed to make {} a panel: {}", target.label(), e);
+        }
+    });
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use objc2::runtime::{AnyClass, AnyObject};
+    use objc2::ClassType;
+    use objc2_app_kit::{NSPanel, NSWindowCollectionBehavior, NSWindowStyleMask};
+    use tauri::{App, WebviewWindow};
+
+    /// NSStatusWindowLevel, above normal and floating windows
+    const STATUS_WINDOW_LEVEL: isize = 25;
+
+    pub fn become_accessory(app: &mut App) {
+        // Only accessory apps' windows can join another app's fullscreen Space
+        app.set_activation_policy(tauri::ActivationPolicy::Accessory);
+    }
+
+    pub fn attach(window: &WebviewWindow) -> Result<(), String> {
+        let ns_window = window.ns_window().map_err(|e| e.to_string())? as *mut AnyObject;
+        if ns_window.is_null() {
+            return Err("no native window".to_string());
+        }
+        unsafe {
+            // NSPanel adds no state to NSWindow, so the window can change class in place
+            objc2::ffi::object_setClass(ns_window, NSPanel::class() as *const AnyClass);
+            let panel = &*(ns_window as *const NSPanel);
+            panel.setStyleMask(panel.styleMask() | NSWindowStyleMask::NonactivatingPanel);
+            panel.setFloatingPanel(true);
+            panel.setBecomesKeyOnlyIfNeeded(true);
+            panel.setHidesOnDeactivate(false);
+            panel.setLevel(STATUS_WINDOW_LEVEL);
+            panel.setCollectionBehavior(
+                NSWindowCollectionBehavior::CanJoinAllSpaces
+                    | NSWindowCollectionBehavior::FullScreenAuxiliary
+                    | NSWindowCollectionBehavior::Stationary
+                    | NSWindowCollectionBehavior::IgnoresCycle,
+            );
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+mod platform {
+    use tauri::{App, WebviewWindow};
+
+    pub fn become_accessory(_app: &mut App) {}
+
+    pub fn attach(_window: &WebviewWindow) -> Result<(), String> {
+        Ok(())
+    }
+}
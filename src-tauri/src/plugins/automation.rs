@@ -0,0 +1,158 @@
+//! Outbound automation triggers
+//!
+//! Fires IFTTT Webhooks or generic templated HTTP requests when an event
+//! matches a named rule from `[[rules]]`. Templates may use `{{event}}`,
+//! `{{task_id}}`, `{{session_id}}`, `{{tool}}`, `{{description}}`,
+//! `{{duration_ms}}`, `{{timestamp}}`, `{{source}}` and `{{priority}}`, and
+//! for custom events `{{custom_type}}` and `{{payload.<key>}}`. Values are
+//! URL-encoded in URLs and JSON-escaped in JSON bodies.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde_json::json;
+
+use crate::config::{AutomationTrigger, Capability, RuleConfig};
+use crate::plugins::Grants;
+use crate::router::{AppEvent, Plugin, PluginError};
+use crate::rules::{self, Escape, EventContext};
+use crate::store::EventStore;
+
+pub const NAME: &str = "automation";
+const IFTTT_URL: &str = "https://maker.ifttt.com/trigger";
+
+/// Values in a JSON body are escaped so quotes and newlines in them keep it valid
+fn body_escape(content_type: &str) -> Escape {
+    let media_type = content_type.split(';').next().unwrap_or_default().trim().to_ascii_lowercase();
+    if media_type == "application/json" || media_type.ends_with("+json") {
+        Escape::Json
+    } else {
+        Escape::None
+    }
+}
+
+pub struct AutomationPlugin {
+    triggers: Vec<AutomationTrigger>,
+    rules: Vec<RuleConfig>,
+    store: Arc<EventStore>,
+    client: reqwest::Client,
+    grants: Grants,
+}
+
+impl AutomationPlugin {
+    pub fn new(triggers: Vec<AutomationTrigger>, rules: Vec<RuleConfig>, store: Arc<EventStore>, grants: Grants) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()
+            .unwrap_or_default();
+
+        Self { triggers, rules, store, client, grants }
+    }
+
+    async fn fire(&self, trigger: &AutomationTrigger, ctx: &EventContext) -> Result<(), PluginError> {
+        self.grants.require(Capability::Network)?;
+        let request = match trigger.kind.as_str() {
+            "ifttt" => {
+                let url = format!(
+                    "{}/{}/with/key/{}",
+                    IFTTT_URL,
+                    urlencoding::encode(&trigger.ifttt_event),
+                    urlencoding::encode(&trigger.ifttt_key),
+                );
+                // IFTTT passes value1..value3 through to the applet
+                self.client.post(url).json(&json!({
+                    "value1": ctx.event_type,
+                    "value2": ctx.tool,
+                    "value3": ctx.description,
+                }))
+            }
+            _ => {
+                let url = rules::render_template(&trigger.url, ctx, Escape::Url);
+                let method = reqwest::Method::from_bytes(trigger.method.to_ascii_uppercase().as_bytes())
+                    .map_err(|e| PluginError::EventError(e.to_string()))?;
+
+                let mut request = self.client.request(method, url);
+                if !trigger.body.is_empty() {
+                    request = request
+                        .header("Content-Type", &trigger.content_type)
+                        .body(rules::render_template(&trigger.body, ctx, body_escape(&trigger.content_type)));
+                }
+                request
+            }
+        };
+
+        request.send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| PluginError::EventError(e.to_string()))?;
+
+        tracing::debug!("Fired automation trigger for rule {}", trigger.rule);
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl Plugin for AutomationPlugin {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn version(&self) -> &str {
+        "1.0.0"
+    }
+
+    fn capabilities(&self) -> &[Capability] {
+        &[Capability::Network]
+    }
+
+    async fn on_init(&mut self) -> Result<(), PluginError> {
+        for trigger in &self.triggers {
+            if rules::find(&self.rules, &trigger.rule).is_none() {
+                return Err(PluginError::InitError(format!("Trigger references unknown rule: {}", trigger.rule)));
+            }
+        }
+        Ok(())
+    }
+
+    async fn on_event(&self, event: &AppEvent) -> Result<(), PluginError> {
+        let ctx = match EventContext::from_event(event, &self.store) {
+            Some(ctx) => ctx,
+            None => return Ok(()),
+        };
+
+        for trigger in &self.triggers {
+            let matched = rules::find(&self.rules, &trigger.rule).is_some_and(|rule| rules::matches(rule, &ctx));
+            if matched {
+                if let Err(e) = self.fire(trigger, &ctx).await {
+                    tracing::warn!("Automation trigger for rule {} failed: {}", trigger.rule, e);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn on_shutdown(&self) -> Result<(), PluginError> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_bodies_are_escaped() {
+        assert_eq!(body_escape("application/json"), Escape::Json);
+        assert_eq!(body_escape("application/vnd.api+json; charset=utf-8"), Escape::Json);
+        assert_eq!(body_escape("text/plain"), Escape::None);
+
+        let ctx = EventContext {
+            event_type: "task_error".to_string(),
+            description: Some("echo \"done\"".to_string()),
+            ..Default::default()
+        };
+        let body = rules::render_template(r#"{"value1": "{{description}}"}"#, &ctx, body_escape("application/json"));
+        assert_eq!(serde_json::from_str::<serde_json::Value>(&body).unwrap()["value1"], "echo \"done\"");
+    }
+}
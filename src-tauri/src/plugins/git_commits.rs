@@ -0,0 +1,96 @@
+//! Git commit correlation plugin
+//!
+//! When a session ends, scans the project repository's HEAD reflog for commits
+//! created inside the session window and records them in the store.
+
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::config::Capability;
+use crate::git;
+use crate::plugins::Grants;
+use crate::router::{AppEvent, Plugin, PluginError};
+use crate::store::{EventStore, SessionCommit};
+
+pub const NAME: &str = "git-commits";
+
+pub struct GitCommitsPlugin {
+    store: Arc<EventStore>,
+    grants: Grants,
+}
+
+impl GitCommitsPlugin {
+    pub fn new(store: Arc<EventStore>, grants: Grants) -> Self {
+        Self { store, grants }
+    }
+
+    /// Look up the session's repository and record commits made during it
+    fn record_session_commits(&self, session_id: &str) -> Result<usize, PluginError> {
+        let session = match self.store.get_session(session_id)
+            .map_err(|e| PluginError::EventError(e.to_string()))?
+        {
+            Some(s) => s,
+            None => return Ok(0),
+        };
+
+        self.grants.require(Capability::Filesystem)?;
+        let git_dir = match session.project_path.as_deref().and_then(|p| git::find_git_dir(Path::new(p))) {
+            Some(d) => d,
+            None => return Ok(0),
+        };
+
+        let ended_at = session.ended_at.unwrap_or(session.started_at);
+        let commits: Vec<SessionCommit> = git::commits_between(
+            &git_dir,
+            session.started_at / 1000,
+            // Round up so commits in the final partial second are included
+            (ended_at + 999) / 1000,
+        )
+        .into_iter()
+        .map(|entry| SessionCommit {
+            session_id: session_id.to_string(),
+            message: entry.subject().to_string(),
+            commit_hash: entry.new_hash,
+            committed_at: entry.timestamp * 1000,
+        })
+        .collect();
+
+        self.store.insert_session_commits(&commits)
+            .map_err(|e| PluginError::EventError(e.to_string()))?;
+
+        Ok(commits.len())
+    }
+}
+
+#[async_trait::async_trait]
+impl Plugin for GitCommitsPlugin {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn version(&self) -> &str {
+        "1.0.0"
+    }
+
+    fn capabilities(&self) -> &[Capability] {
+        &[Capability::Filesystem]
+    }
+
+    async fn on_init(&mut self) -> Result<(), PluginError> {
+        Ok(())
+    }
+
+    async fn on_event(&self, event: &AppEvent) -> Result<(), PluginError> {
+        if let AppEvent::SessionStopped { session_id: Some(session_id) } = event {
+            let count = self.record_session_commits(session_id)?;
+            if count > 0 {
+                tracing::info!("Recorded {} commits for session {}", count, session_id);
+            }
+        }
+        Ok(())
+    }
+
+    async fn on_shutdown(&self) -> Result<(), PluginError> {
+        Ok(())
+    }
+}
@@ -0,0 +1,347 @@
+//! GitHub Issues todo sync plugin
+//!
+//! Gives asynchronous reviewers visibility into what the agent is doing. Two modes:
+//!
+//! - `"session"` (default): one tracking issue per session whose body is a task
+//!   list, checked off as todos complete and closed once everything is done or
+//!   the session's todo file is removed; reopened if the session starts a new
+//!   todo list
+//! - `"todo"`: one issue per todo, closed when the todo completes or leaves the
+//!   live list
+
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde_json::{json, Value};
+use tokio::sync::Mutex;
+
+use crate::config::{Capability, GitHubIssuesPluginConfig};
+use crate::plugins::todo_sync::{self, TodoChange};
+use crate::plugins::Grants;
+use crate::router::{AppEvent, Plugin, PluginError};
+use crate::store::{EventStore, TodoLink};
+use crate::watcher::{self, GlobalTodoItem, TodoItem};
+
+pub const NAME: &str = "github-issues";
+const API_URL: &str = "https://api.github.com";
+/// Link service for per-todo issues
+const TODO_SERVICE: &str = "github";
+/// Link service for per-session issues; links use an empty `content`
+const SESSION_SERVICE: &str = "github-session";
+
+pub struct GitHubIssuesPlugin {
+    config: GitHubIssuesPluginConfig,
+    store: Arc<EventStore>,
+    todos_dir: PathBuf,
+    client: reqwest::Client,
+    /// Last body pushed per session issue, to skip redundant updates
+    bodies: Mutex<HashMap<String, String>>,
+    grants: Grants,
+}
+
+impl GitHubIssuesPlugin {
+    pub fn new(config: GitHubIssuesPluginConfig, store: Arc<EventStore>, todos_dir: PathBuf, grants: Grants) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(15))
+            .user_agent("agent-progress-overlay")
+            .build()
+            .unwrap_or_default();
+
+        Self {
+            config,
+            store,
+            todos_dir,
+            client,
+            bodies: Mutex::new(HashMap::new()),
+            grants,
+        }
+    }
+
+    /// Whether todos from this session should be synced
+    fn session_in_scope(&self, session_id: &str) -> bool {
+        if self.config.project_path.is_empty() {
+            return true;
+        }
+        todo_sync::session_project_path(&self.store, session_id)
+            .is_some_and(|path| todo_sync::path_matches(&path, &self.config.project_path))
+    }
+
+    // ========== Per-session mode ==========
+
+    async fn sync_sessions(&self, todos: &[GlobalTodoItem]) -> Result<(), PluginError> {
+        let links = self.store.get_todo_links(SESSION_SERVICE)
+            .map_err(|e| PluginError::EventError(e.to_string()))?;
+
+        // Sessions with live todos, plus open issues whose todos may have just finished
+        let mut sessions: HashSet<&str> = todos.iter().map(|t| t.session_id.as_str()).collect();
+        sessions.extend(links.iter().filter(|l| l.status == "open").map(|l| l.session_id.as_str()));
+
+        for session_id in sessions {
+            let link = links.iter().find(|l| l.session_id == session_id);
+            if link.is_some_and(|l| l.status != "open" && !has_pending(todos, session_id))
+                || (link.is_none() && !self.session_in_scope(session_id))
+            {
+                continue;
+            }
+            if let Err(e) = self.sync_session(session_id, link).await {
+                tracing::warn!("GitHub issue sync failed for session {}: {}", session_id, e);
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn sync_session(&self, session_id: &str, link: Option<&TodoLink>) -> Result<(), PluginError> {
+        self.grants.require(Capability::Filesystem)?;
+        let items = watcher::read_session_todos(&self.todos_dir, session_id);
+        if items.is_empty() {
+            // The session's todo file is gone, so its work is over
+            if let Some(link) = link {
+                self.close_issue(&link.remote_id).await?;
+                todo_sync::record_link(&self.store, SESSION_SERVICE, session_id, "", &link.remote_id, "completed")
+                    .map_err(|e| PluginError::EventError(e.to_string()))?;
+                self.bodies.lock().await.remove(session_id);
+            }
+            return Ok(());
+        }
+
+        let body = checklist_body(session_id, &items);
+        let done = items.iter().all(|t| t.status == "completed");
+
+        let number = match link {
+            Some(link) => {
+                let unchanged = self.bodies.lock().await.get(session_id) == Some(&body);
+                if let Some(update) = issue_update(&body, done, link.status == "open", unchanged) {
+                    self.request(reqwest::Method::PATCH, &format!("issues/{}", link.remote_id), update).await?;
+                }
+                link.remote_id.clone()
+            }
+            None => {
+                let title = format!("Agent session {}", session_title(&self.store, session_id));
+                self.create_issue(&title, &body).await?
+            }
+        };
+
+        let status = if done { "completed" } else { "open" };
+        todo_sync::record_link(&self.store, SESSION_SERVICE, session_id, "", &number, status)
+            .map_err(|e| PluginError::EventError(e.to_string()))?;
+        self.bodies.lock().await.insert(session_id.to_string(), body);
+
+        Ok(())
+    }
+
+    // ========== Per-todo mode ==========
+
+    async fn sync_todos(&self, todos: &[GlobalTodoItem]) -> Result<(), PluginError> {
+        let changes = todo_sync::pending_changes(&self.store, TODO_SERVICE, todos)
+            .map_err(|e| PluginError::EventError(e.to_string()))?;
+
+        for change in changes {
+            if let Err(e) = self.apply(change).await {
+                tracing::warn!("GitHub issue sync failed: {}", e);
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn apply(&self, change: TodoChange) -> Result<(), PluginError> {
+        let (link, status) = match change {
+            TodoChange::Create(todo) => {
+                if !self.session_in_scope(&todo.session_id) {
+                    return Ok(());
+                }
+                let body = format!("Tracked from agent session `{}`.", todo.session_id);
+                let number = self.create_issue(&todo.content, &body).await?;
+                (
+                    TodoLink {
+                        service: TODO_SERVICE.to_string(),
+                        session_id: todo.session_id,
+                        content: todo.content,
+                        remote_id: number,
+                        status: String::new(),
+                    },
+                    todo.status,
+                )
+            }
+            // Issues have no in-progress state; just remember the new status
+            TodoChange::Update { link, status } => (link, status),
+            TodoChange::Complete(link) => {
+                self.close_issue(&link.remote_id).await?;
+                (link, "completed".to_string())
+            }
+        };
+
+        todo_sync::record_link(&self.store, TODO_SERVICE, &link.session_id, &link.content, &link.remote_id, &status)
+            .map_err(|e| PluginError::EventError(e.to_string()))
+    }
+
+    // ========== API ==========
+
+    async fn create_issue(&self, title: &str, body: &str) -> Result<String, PluginError> {
+        let mut issue = json!({ "title": title, "body": body });
+        if !self.config.labels.is_empty() {
+            issue["labels"] = json!(self.config.labels);
+        }
+
+        let created = self.request(reqwest::Method::POST, "issues", issue).await?;
+        created["number"].as_u64()
+            .map(|n| n.to_string())
+            .ok_or_else(|| PluginError::EventError("GitHub did not return an issue number".to_string()))
+    }
+
+    async fn close_issue(&self, number: &str) -> Result<(), PluginError> {
+        self.request(
+            reqwest::Method::PATCH,
+            &format!("issues/{}", number),
+            json!({ "state": "closed", "state_reason": "completed" }),
+        ).await?;
+        Ok(())
+    }
+
+    async fn request(&self, method: reqwest::Method, path: &str, body: Value) -> Result<Value, PluginError> {
+        self.grants.require(Capability::Network)?;
+        self.client
+            .request(method, format!("{}/repos/{}/{}", API_URL, self.config.repo, path))
+            .bearer_auth(&self.config.token)
+            .header("Accept", "application/vnd.github+json")
+            .json(&body)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| PluginError::EventError(e.to_string()))?
+            .json()
+            .await
+            .map_err(|e| PluginError::EventError(e.to_string()))
+    }
+}
+
+/// Human-readable session label: project folder name plus a short session id
+fn session_title(store: &EventStore, session_id: &str) -> String {
+    let short_id: String = session_id.chars().take(8).collect();
+    match todo_sync::session_project_path(store, session_id) {
+        Some(path) => {
+            let project = path.rsplit(['/', '\\']).find(|s| !s.is_empty()).unwrap_or(&path).to_string();
+            format!("{} ({})", project, short_id)
+        }
+        None => short_id,
+    }
+}
+
+/// Whether the session has live todos that aren't completed
+///
+/// A session whose issue was closed can start a new todo list; this is what
+/// brings its issue back.
+fn has_pending(todos: &[GlobalTodoItem], session_id: &str) -> bool {
+    todos.iter().any(|t| t.session_id == session_id && t.status != "completed")
+}
+
+/// The PATCH that brings an existing session issue in line with its todos, if one is needed
+fn issue_update(body: &str, done: bool, open: bool, unchanged: bool) -> Option<Value> {
+    if done {
+        Some(json!({ "body": body, "state": "closed" }))
+    } else if !open {
+        Some(json!({ "body": body, "state": "open" }))
+    } else if !unchanged {
+        Some(json!({ "body": body }))
+    } else {
+        None
+    }
+}
+
+/// Render a session's todos as a GitHub task list
+fn checklist_body(session_id: &str, items: &[TodoItem]) -> String {
+    let mut body = format!("Todos from agent session `{}`:\n\n", session_id);
+    for item in items {
+        let mark = if item.status == "completed" { "x" } else { " " };
+        let note = if item.status == "in_progress" { " _(in progress)_" } else { "" };
+        body.push_str(&format!("- [{}] {}{}\n", mark, item.content, note));
+    }
+    body
+}
+
+#[async_trait::async_trait]
+impl Plugin for GitHubIssuesPlugin {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn version(&self) -> &str {
+        "1.0.0"
+    }
+
+    fn capabilities(&self) -> &[Capability] {
+        &[Capability::Network, Capability::Filesystem]
+    }
+
+    async fn on_init(&mut self) -> Result<(), PluginError> {
+        if self.config.token.is_empty() || !self.config.repo.contains('/') {
+            return Err(PluginError::InitError("GitHub token and repo (owner/name) must be set".to_string()));
+        }
+        Ok(())
+    }
+
+    async fn on_event(&self, event: &AppEvent) -> Result<(), PluginError> {
+        let AppEvent::TodosUpdated(todos) = event else {
+            return Ok(());
+        };
+
+        if self.config.mode == "todo" {
+            self.sync_todos(todos).await
+        } else {
+            self.sync_sessions(todos).await
+        }
+    }
+
+    async fn on_shutdown(&self) -> Result<(), PluginError> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checklist_body() {
+        let items = vec![
+            TodoItem {
+                content: "Add pagination".to_string(),
+                status: "completed".to_string(),
+                active_form: "Adding pagination".to_string(),
+            },
+            TodoItem {
+                content: "Write tests".to_string(),
+                status: "in_progress".to_string(),
+                active_form: "Writing tests".to_string(),
+            },
+        ];
+
+        let body = checklist_body("s1", &items);
+        assert!(body.contains("- [x] Add pagination\n"));
+        assert!(body.contains("- [ ] Write tests _(in progress)_\n"));
+    }
+
+    #[test]
+    fn test_closed_issue_reopens_for_new_todos() {
+        let todo = |session_id: &str, status: &str| GlobalTodoItem {
+            content: "Write tests".to_string(),
+            status: status.to_string(),
+            active_form: "Writing tests".to_string(),
+            session_id: session_id.to_string(),
+            profile: None,
+            also_in: Vec::new(),
+        };
+        assert!(!has_pending(&[todo("s1", "completed")], "s1"));
+        assert!(has_pending(&[todo("s1", "completed"), todo("s1", "pending")], "s1"));
+        assert!(!has_pending(&[todo("s2", "pending")], "s1"));
+
+        // A closed issue is reopened with the new list even if the body was seen before
+        assert_eq!(issue_update("b", false, false, true), Some(json!({ "body": "b", "state": "open" })));
+        assert_eq!(issue_update("b", true, true, true), Some(json!({ "body": "b", "state": "closed" })));
+        assert_eq!(issue_update("b", false, true, false), Some(json!({ "body": "b" })));
+        assert_eq!(issue_update("b", false, true, true), None);
+    }
+}
@@ -0,0 +1,275 @@
+//! Linear / Jira todo sync plugin
+//!
+//! Mirrors todos from sessions inside configured project roots into issues,
+//! moving them through the tracker's workflow as their status changes.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde_json::{json, Value};
+
+use crate::config::{Capability, IssueTrackerPluginConfig, IssueTrackerProject};
+use crate::plugins::todo_sync::{self, TodoChange};
+use crate::plugins::Grants;
+use crate::router::{AppEvent, Plugin, PluginError};
+use crate::store::EventStore;
+
+pub const NAME: &str = "issue-tracker";
+const LINEAR_API_URL: &str = "https://api.linear.app/graphql";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Provider {
+    Linear,
+    Jira,
+}
+
+impl Provider {
+    fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "linear" => Some(Provider::Linear),
+            "jira" => Some(Provider::Jira),
+            _ => None,
+        }
+    }
+
+    /// Service name used for the plugin's todo links
+    fn service(&self) -> &'static str {
+        match self {
+            Provider::Linear => "linear",
+            Provider::Jira => "jira",
+        }
+    }
+
+    /// Workflow category a todo status maps to
+    fn state_for(&self, status: &str) -> &'static str {
+        match (self, status) {
+            (Provider::Linear, "in_progress") => "started",
+            (Provider::Linear, "completed") => "completed",
+            (Provider::Linear, _) => "unstarted",
+            (Provider::Jira, "in_progress") => "indeterminate",
+            (Provider::Jira, "completed") => "done",
+            (Provider::Jira, _) => "new",
+        }
+    }
+}
+
+pub struct IssueTrackerPlugin {
+    config: IssueTrackerPluginConfig,
+    provider: Option<Provider>,
+    store: Arc<EventStore>,
+    client: reqwest::Client,
+    grants: Grants,
+}
+
+impl IssueTrackerPlugin {
+    pub fn new(config: IssueTrackerPluginConfig, store: Arc<EventStore>, grants: Grants) -> Self {
+        let provider = Provider::parse(&config.provider);
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(15))
+            .build()
+            .unwrap_or_default();
+
+        Self { config, provider, store, client, grants }
+    }
+
+    /// Configured project containing the todo's session, if any
+    fn project_for(&self, session_id: &str) -> Option<&IssueTrackerProject> {
+        let project_path = todo_sync::session_project_path(&self.store, session_id)?;
+        self.config.projects.iter().find(|p| todo_sync::path_matches(&project_path, &p.path))
+    }
+
+    async fn apply(&self, provider: Provider, change: TodoChange) -> Result<(), PluginError> {
+        let service = provider.service();
+
+        match change {
+            TodoChange::Create(todo) => {
+                let project = match self.project_for(&todo.session_id) {
+                    Some(p) => p,
+                    None => return Ok(()),
+                };
+
+                let remote_id = self.create_issue(provider, &project.key, &todo.content).await?;
+                if todo.status != "pending" {
+                    self.set_state(provider, &remote_id, &todo.status).await?;
+                }
+                todo_sync::record_link(&self.store, service, &todo.session_id, &todo.content, &remote_id, &todo.status)
+                    .map_err(|e| PluginError::EventError(e.to_string()))?;
+            }
+            TodoChange::Update { link, status } => {
+                self.set_state(provider, &link.remote_id, &status).await?;
+                todo_sync::record_link(&self.store, service, &link.session_id, &link.content, &link.remote_id, &status)
+                    .map_err(|e| PluginError::EventError(e.to_string()))?;
+            }
+            TodoChange::Complete(link) => {
+                self.set_state(provider, &link.remote_id, "completed").await?;
+                todo_sync::record_link(&self.store, service, &link.session_id, &link.content, &link.remote_id, "completed")
+                    .map_err(|e| PluginError::EventError(e.to_string()))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn create_issue(&self, provider: Provider, project_key: &str, title: &str) -> Result<String, PluginError> {
+        match provider {
+            Provider::Linear => {
+                let data = self.linear_request(
+                    "mutation($input: IssueCreateInput!) { issueCreate(input: $input) { issue { id } } }",
+                    json!({ "input": { "teamId": project_key, "title": title } }),
+                ).await?;
+
+                data["issueCreate"]["issue"]["id"].as_str()
+                    .map(|id| id.to_string())
+                    .ok_or_else(|| PluginError::EventError("Linear did not return an issue id".to_string()))
+            }
+            Provider::Jira => {
+                let body = json!({
+                    "fields": {
+                        "project": { "key": project_key },
+                        "summary": title,
+                        "issuetype": { "name": "Task" },
+                    }
+                });
+                let response = self.jira_request(reqwest::Method::POST, "issue", Some(body)).await?;
+
+                response["key"].as_str()
+                    .map(|key| key.to_string())
+                    .ok_or_else(|| PluginError::EventError("Jira did not return an issue key".to_string()))
+            }
+        }
+    }
+
+    async fn set_state(&self, provider: Provider, remote_id: &str, status: &str) -> Result<(), PluginError> {
+        let target = provider.state_for(status);
+
+        match provider {
+            Provider::Linear => {
+                let data = self.linear_request(
+                    "query($id: String!) { issue(id: $id) { team { states { nodes { id type } } } } }",
+                    json!({ "id": remote_id }),
+                ).await?;
+
+                let state_id = data["issue"]["team"]["states"]["nodes"].as_array()
+                    .and_then(|states| states.iter().find(|s| s["type"] == target))
+                    .and_then(|s| s["id"].as_str())
+                    .ok_or_else(|| PluginError::EventError(format!("No Linear state of type {}", target)))?;
+
+                self.linear_request(
+                    "mutation($id: String!, $stateId: String!) { issueUpdate(id: $id, input: { stateId: $stateId }) { success } }",
+                    json!({ "id": remote_id, "stateId": state_id }),
+                ).await?;
+            }
+            Provider::Jira => {
+                let path = format!("issue/{}/transitions", remote_id);
+                let response = self.jira_request(reqwest::Method::GET, &path, None).await?;
+
+                let transition_id = response["transitions"].as_array()
+                    .and_then(|t| t.iter().find(|t| t["to"]["statusCategory"]["key"] == target))
+                    .and_then(|t| t["id"].as_str())
+                    .ok_or_else(|| PluginError::EventError(format!("No Jira transition to {}", target)))?;
+
+                self.jira_request(
+                    reqwest::Method::POST,
+                    &path,
+                    Some(json!({ "transition": { "id": transition_id } })),
+                ).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn linear_request(&self, query: &str, variables: Value) -> Result<Value, PluginError> {
+        self.grants.require(Capability::Network)?;
+        let response: Value = self.client
+            .post(LINEAR_API_URL)
+            .header("Authorization", &self.config.api_token)
+            .json(&json!({ "query": query, "variables": variables }))
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| PluginError::EventError(e.to_string()))?
+            .json()
+            .await
+            .map_err(|e| PluginError::EventError(e.to_string()))?;
+
+        if let Some(errors) = response.get("errors") {
+            return Err(PluginError::EventError(format!("Linear error: {}", errors)));
+        }
+
+        Ok(response["data"].clone())
+    }
+
+    async fn jira_request(&self, method: reqwest::Method, path: &str, body: Option<Value>) -> Result<Value, PluginError> {
+        self.grants.require(Capability::Network)?;
+        let url = format!("{}/rest/api/3/{}", self.config.base_url.trim_end_matches('/'), path);
+
+        let mut request = self.client
+            .request(method, url)
+            .basic_auth(&self.config.email, Some(&self.config.api_token));
+        if let Some(body) = body {
+            request = request.json(&body);
+        }
+
+        let response = request.send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| PluginError::EventError(e.to_string()))?;
+
+        // Transition requests answer 204 No Content
+        let text = response.text().await.map_err(|e| PluginError::EventError(e.to_string()))?;
+        if text.is_empty() {
+            return Ok(Value::Null);
+        }
+        serde_json::from_str(&text).map_err(|e| PluginError::EventError(e.to_string()))
+    }
+}
+
+#[async_trait::async_trait]
+impl Plugin for IssueTrackerPlugin {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn version(&self) -> &str {
+        "1.0.0"
+    }
+
+    fn capabilities(&self) -> &[Capability] {
+        &[Capability::Network]
+    }
+
+    async fn on_init(&mut self) -> Result<(), PluginError> {
+        if self.provider.is_none() {
+            return Err(PluginError::InitError(format!("Unknown issue tracker provider: {}", self.config.provider)));
+        }
+        if self.config.api_token.is_empty() {
+            return Err(PluginError::InitError("Issue tracker api_token is not set".to_string()));
+        }
+        if self.provider == Some(Provider::Jira) && self.config.base_url.is_empty() {
+            return Err(PluginError::InitError("Jira base_url is not set".to_string()));
+        }
+        Ok(())
+    }
+
+    async fn on_event(&self, event: &AppEvent) -> Result<(), PluginError> {
+        let (AppEvent::TodosUpdated(todos), Some(provider)) = (event, self.provider) else {
+            return Ok(());
+        };
+
+        let changes = todo_sync::pending_changes(&self.store, provider.service(), todos)
+            .map_err(|e| PluginError::EventError(e.to_string()))?;
+
+        for change in changes {
+            if let Err(e) = self.apply(provider, change).await {
+                tracing::warn!("Issue tracker sync failed: {}", e);
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn on_shutdown(&self) -> Result<(), PluginError> {
+        Ok(())
+    }
+}
@@ -0,0 +1,140 @@
+//! Built-in plugins
+//!
+//! Each plugin implements [`Plugin`](crate::router::Plugin) and is enabled from
+//! the `[plugins]` section of the config file.
+
+pub mod automation;
+pub mod git_commits;
+pub mod github_issues;
+pub mod issue_tracker;
+pub mod notion;
+pub mod todo_sync;
+pub mod todoist;
+pub mod transcripts;
+pub mod tts;
+
+use std::sync::Arc;
+
+use crate::config::{Capability, Config, PluginsConfig};
+use crate::locale::Locale;
+use crate::redact::Redactor;
+use crate::router::{Plugin, PluginError, PluginManager, PluginPermissions};
+use crate::store::EventStore;
+use crate::timezone::{QuietHours, Zone};
+
+/// The capabilities the config grants a plugin, which it checks before each use
+///
+/// Declaring capabilities only decides whether a plugin is loaded; a plugin
+/// holding its grants fails at the point it would overstep them.
+#[derive(Debug, Clone)]
+pub struct Grants {
+    plugin: String,
+    granted: Vec<Capability>,
+}
+
+impl Grants {
+    pub fn new(plugin: &str, config: &PluginsConfig) -> Self {
+        Self {
+            plugin: plugin.to_string(),
+            granted: config.granted(plugin),
+        }
+    }
+
+    /// Fail unless the plugin has been granted `capability`
+    pub fn require(&self, capability: Capability) -> Result<(), PluginError> {
+        if self.granted.contains(&capability) {
+            Ok(())
+        } else {
+            Err(PluginError::PermissionDenied(format!(
+                "{} hasn't been granted {:?}; grant it in [plugins.permissions]",
+                self.plugin, capability
+            )))
+        }
+    }
+}
+
+/// Create all built-in plugins enabled in the config
+pub fn builtin_plugins(config: &Config, store: Arc<EventStore>) -> Vec<Box<dyn Plugin>> {
+    let mut plugins: Vec<Box<dyn Plugin>> = Vec::new();
+    let grants = |name: &str| Grants::new(name, &config.plugins);
+
+    if config.plugins.git_commits.enabled {
+        plugins.push(Box::new(git_commits::GitCommitsPlugin::new(store.clone(), grants(git_commits::NAME))));
+    }
+    if config.plugins.issue_tracker.enabled {
+        plugins.push(Box::new(issue_tracker::IssueTrackerPlugin::new(
+            config.plugins.issue_tracker.clone(),
+            store.clone(),
+            grants(issue_tracker::NAME),
+        )));
+    }
+    if config.plugins.todoist.enabled {
+        plugins.push(Box::new(todoist::TodoistPlugin::new(
+            config.plugins.todoist.clone(),
+            store.clone(),
+            grants(todoist::NAME),
+        )));
+    }
+    if config.plugins.notion.enabled {
+        plugins.push(Box::new(notion::NotionPlugin::new(
+            config.plugins.notion.clone(),
+            store.clone(),
+            config.todos_path(),
+            Locale::from_config(&config.behavior.locale),
+            grants(notion::NAME),
+        )));
+    }
+    if config.plugins.github_issues.enabled {
+        plugins.push(Box::new(github_issues::GitHubIssuesPlugin::new(
+            config.plugins.github_issues.clone(),
+            store.clone(),
+            config.todos_path(),
+            grants(github_issues::NAME),
+        )));
+    }
+    if config.plugins.automation.enabled {
+        plugins.push(Box::new(automation::AutomationPlugin::new(
+            config.plugins.automation.triggers.clone(),
+            config.rules.clone(),
+            store.clone(),
+            grants(automation::NAME),
+        )));
+    }
+    if config.plugins.transcripts.enabled {
+        plugins.push(Box::new(transcripts::TranscriptsPlugin::new(
+            store.clone(),
+            config.claude_dirs(),
+            Redactor::new(&config.redaction),
+            grants(transcripts::NAME),
+        )));
+    }
+    if config.plugins.tts.enabled {
+        let zone = Zone::from_config(&config.behavior.timezone);
+        plugins.push(Box::new(tts::TtsPlugin::new(
+            config.plugins.tts.templates.clone(),
+            QuietHours::parse(&config.behavior.quiet_hours, zone).ok().flatten(),
+            config.plugins.tts.speak_during_quiet_hours,
+            store.clone(),
+            grants(tts::NAME),
+        )));
+    }
+
+    plugins
+}
+
+/// Register all built-in plugins enabled in the config; plugins missing a grant are skipped
+pub fn register_builtin(manager: &mut PluginManager, config: &Config, store: Arc<EventStore>) {
+    for plugin in builtin_plugins(config, store) {
+        if let Err(e) = manager.register(plugin) {
+            tracing::error!("{}", e);
+        }
+    }
+}
+
+/// Requested and granted capabilities of each enabled built-in plugin
+pub fn permissions(config: &Config, store: Arc<EventStore>) -> Vec<PluginPermissions> {
+    builtin_plugins(config, store)
+        .iter()
+        .map(|plugin| PluginPermissions::new(plugin.as_ref(), config.plugins.granted(plugin.name())))
+        .collect()
+}
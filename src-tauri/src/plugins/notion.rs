@@ -0,0 +1,258 @@
+//! Notion session logbook plugin
+//!
+//! When a session ends, appends a page summarizing its tasks, durations,
+//! errors, and todos to a configured Notion database.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde_json::{json, Value};
+
+use crate::config::{Capability, NotionPluginConfig};
+use crate::locale::{Locale, Message};
+use crate::plugins::Grants;
+use crate::router::{AppEvent, Plugin, PluginError};
+use crate::store::{EventStore, SessionSummary, StoredTask};
+use crate::summary::format_duration;
+use crate::watcher::{self, TodoItem};
+
+pub const NAME: &str = "notion";
+const API_URL: &str = "https://api.notion.com/v1/pages";
+const NOTION_VERSION: &str = "2022-06-28";
+/// Notion accepts at most 100 child blocks per request
+const MAX_BLOCKS: usize = 100;
+/// Notion rejects rich text longer than 2000 characters
+const MAX_TEXT_CHARS: usize = 2000;
+
+pub struct NotionPlugin {
+    config: NotionPluginConfig,
+    store: Arc<EventStore>,
+    todos_dir: PathBuf,
+    /// Language the page is written in
+    locale: Locale,
+    client: reqwest::Client,
+    grants: Grants,
+}
+
+impl NotionPlugin {
+    pub fn new(config: NotionPluginConfig, store: Arc<EventStore>, todos_dir: PathBuf, locale: Locale, grants: Grants) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(15))
+            .build()
+            .unwrap_or_default();
+
+        Self { config, store, todos_dir, locale, client, grants }
+    }
+
+    async fn export_session(&self, session_id: &str) -> Result<(), PluginError> {
+        let summary = match self.store.get_session_summary(session_id)
+            .map_err(|e| PluginError::EventError(e.to_string()))?
+        {
+            Some(s) => s,
+            None => return Ok(()),
+        };
+        let tasks = self.store.get_tasks_by_session(session_id)
+            .map_err(|e| PluginError::EventError(e.to_string()))?;
+        self.grants.require(Capability::Filesystem)?;
+        let todos = watcher::read_session_todos(&self.todos_dir, session_id);
+
+        let body = json!({
+            "parent": { "database_id": self.config.database_id },
+            "properties": {
+                self.config.title_property.as_str(): {
+                    "title": [text(&page_title(&summary, self.locale))]
+                }
+            },
+            "children": summary_blocks(&summary, &tasks, &todos, self.locale),
+        });
+
+        self.grants.require(Capability::Network)?;
+        self.client
+            .post(API_URL)
+            .bearer_auth(&self.config.api_token)
+            .header("Notion-Version", NOTION_VERSION)
+            .json(&body)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| PluginError::EventError(e.to_string()))?;
+
+        tracing::info!("Exported session {} to Notion", session_id);
+        Ok(())
+    }
+}
+
+fn page_title(summary: &SessionSummary, locale: Locale) -> String {
+    let project = summary.session.project_path.as_deref()
+        .and_then(|p| p.rsplit(['/', '\\']).find(|s| !s.is_empty()))
+        .unwrap_or(locale.text(Message::ReportSession));
+
+    match &summary.session.branch {
+        Some(branch) => locale.format(
+            Message::ReportTitleBranch,
+            &[("project", &project), ("branch", branch), ("count", &summary.total_tasks)],
+        ),
+        None => locale.format(Message::ReportTitle, &[("project", &project), ("count", &summary.total_tasks)]),
+    }
+}
+
+/// Build the page body, keeping within Notion's block limit
+fn summary_blocks(summary: &SessionSummary, tasks: &[StoredTask], todos: &[TodoItem], locale: Locale) -> Vec<Value> {
+    let mut blocks = vec![heading(locale.text(Message::ReportSummary))];
+
+    if let Some(path) = &summary.session.project_path {
+        blocks.push(paragraph(&locale.format(Message::ReportProject, &[("path", path)])));
+    }
+    if let Some(ended_at) = summary.session.ended_at {
+        let elapsed = (ended_at - summary.session.started_at).max(0);
+        blocks.push(paragraph(&locale.format(Message::ReportSessionLength, &[("duration", &format_duration(elapsed))])));
+    }
+    blocks.push(paragraph(&locale.format(Message::ReportTaskCounts, &[
+        ("total", &summary.total_tasks),
+        ("completed", &summary.completed_tasks),
+        ("errors", &summary.error_tasks),
+        ("duration", &format_duration(summary.total_duration_ms)),
+    ])));
+
+    let errors: Vec<&StoredTask> = tasks.iter().filter(|t| t.status == "error").collect();
+    if !errors.is_empty() {
+        blocks.push(heading(locale.text(Message::ReportErrors)));
+        blocks.extend(errors.iter().map(|t| bullet(&task_line(t))));
+    }
+
+    if !summary.commits.is_empty() {
+        blocks.push(heading(locale.text(Message::ReportCommits)));
+        blocks.extend(summary.commits.iter().map(|c| {
+            bullet(&format!("{} {}", &c.commit_hash[..c.commit_hash.len().min(7)], c.message))
+        }));
+    }
+
+    if !todos.is_empty() {
+        blocks.push(heading(locale.text(Message::ReportTodos)));
+        blocks.extend(todos.iter().map(|t| json!({
+            "object": "block",
+            "type": "to_do",
+            "to_do": { "rich_text": [text(&t.content)], "checked": t.status == "completed" }
+        })));
+    }
+
+    if !tasks.is_empty() && blocks.len() + 1 < MAX_BLOCKS {
+        blocks.push(heading(locale.text(Message::ReportTasks)));
+        let room = MAX_BLOCKS - blocks.len();
+        // Tasks come newest first; list them in the order they ran
+        if tasks.len() <= room {
+            blocks.extend(tasks.iter().rev().map(|t| bullet(&task_line(t))));
+        } else {
+            blocks.extend(tasks.iter().rev().take(room - 1).map(|t| bullet(&task_line(t))));
+            blocks.push(paragraph(&locale.format(Message::ReportMore, &[("count", &(tasks.len() - (room - 1)))])));
+        }
+    }
+
+    blocks.truncate(MAX_BLOCKS);
+    blocks
+}
+
+fn task_line(task: &StoredTask) -> String {
+    let description = task.description.as_deref().unwrap_or("");
+    match task.duration_ms {
+        Some(ms) => format!("{}: {} ({})", task.tool, description, format_duration(ms)),
+        None => format!("{}: {}", task.tool, description),
+    }
+}
+
+fn text(content: &str) -> Value {
+    let content: String = content.chars().take(MAX_TEXT_CHARS).collect();
+    json!({ "type": "text", "text": { "content": content } })
+}
+
+fn heading(content: &str) -> Value {
+    json!({ "object": "block", "type": "heading_2", "heading_2": { "rich_text": [text(content)] } })
+}
+
+fn paragraph(content: &str) -> Value {
+    json!({ "object": "block", "type": "paragraph", "paragraph": { "rich_text": [text(content)] } })
+}
+
+fn bullet(content: &str) -> Value {
+    json!({
+        "object": "block",
+        "type": "bulleted_list_item",
+        "bulleted_list_item": { "rich_text": [text(content)] }
+    })
+}
+
+#[async_trait::async_trait]
+impl Plugin for NotionPlugin {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn version(&self) -> &str {
+        "1.0.0"
+    }
+
+    fn capabilities(&self) -> &[Capability] {
+        &[Capability::Network, Capability::Filesystem]
+    }
+
+    async fn on_init(&mut self) -> Result<(), PluginError> {
+        if self.config.api_token.is_empty() || self.config.database_id.is_empty() {
+            return Err(PluginError::InitError("Notion api_token and database_id must be set".to_string()));
+        }
+        Ok(())
+    }
+
+    async fn on_event(&self, event: &AppEvent) -> Result<(), PluginError> {
+        if let AppEvent::SessionStopped { session_id: Some(session_id) } = event {
+            self.export_session(session_id).await?;
+        }
+        Ok(())
+    }
+
+    async fn on_shutdown(&self) -> Result<(), PluginError> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::StoredSession;
+
+    fn task(id: usize, status: &str) -> StoredTask {
+        StoredTask {
+            session_id: "s1".to_string(),
+            description: Some(format!("step {}", id)),
+            status: status.to_string(),
+            duration_ms: Some(65_000),
+            ..StoredTask::test(&format!("task-{}", id), id as i64)
+        }
+    }
+
+    #[test]
+    fn test_summary_blocks_respect_limit() {
+        let summary = SessionSummary {
+            session: StoredSession {
+                id: "s1".to_string(),
+                started_at: 0,
+                ended_at: Some(3_600_000),
+                project_path: Some("/home/dev/app".to_string()),
+                branch: Some("main".to_string()),
+            },
+            total_tasks: 150,
+            completed_tasks: 149,
+            error_tasks: 1,
+            total_duration_ms: 150 * 65_000,
+            commits: Vec::new(),
+        };
+        let mut tasks: Vec<StoredTask> = (0..150).map(|i| task(i, "completed")).collect();
+        tasks[0].status = "error".to_string();
+
+        let blocks = summary_blocks(&summary, &tasks, &[], Locale::En);
+        assert_eq!(blocks.len(), MAX_BLOCKS);
+        assert_eq!(blocks[0]["type"], "heading_2");
+        assert_eq!(page_title(&summary, Locale::En), "app (main) - 150 tasks");
+        assert_eq!(format_duration(65_000), "1m 05s");
+    }
+}
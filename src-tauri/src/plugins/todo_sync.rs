@@ -0,0 +1,157 @@
+//! Shared change detection for plugins that mirror todos into external services
+//!
+//! Sync state lives in the store's `todo_links` table, so restarts don't
+//! re-create items that were already mirrored.
+
+use std::collections::HashSet;
+use std::path::Path;
+
+use crate::store::{EventStore, StoreError, TodoLink};
+use crate::watcher::GlobalTodoItem;
+
+/// Link status for items the remote side took ownership of; they are no longer synced
+pub const DETACHED: &str = "detached";
+
+/// Link status for todos whose file went stale; they are left open remotely
+/// and pick up again if the file changes
+pub const HIDDEN: &str = "hidden";
+
+/// A change that needs to be pushed to the external service
+#[derive(Debug, Clone)]
+pub enum TodoChange {
+    /// Todo has no external copy yet
+    Create(GlobalTodoItem),
+    /// Todo's status differs from what was last synced
+    Update { link: TodoLink, status: String },
+    /// Todo left the live list (completed, or its session finished)
+    Complete(TodoLink),
+}
+
+/// Compare the live todo list against previously synced links
+pub fn diff_todos(links: &[TodoLink], todos: &[GlobalTodoItem]) -> Vec<TodoChange> {
+    let mut changes = Vec::new();
+    let mut live = HashSet::new();
+
+    for todo in todos {
+        live.insert((todo.session_id.as_str(), todo.content.as_str()));
+
+        match links.iter().find(|l| l.session_id == todo.session_id && l.content == todo.content) {
+            None => changes.push(TodoChange::Create(todo.clone())),
+            Some(link) if link.status == DETACHED || link.status == todo.status => {}
+            Some(link) => changes.push(TodoChange::Update {
+                link: link.clone(),
+                status: todo.status.clone(),
+            }),
+        }
+    }
+
+    for link in links {
+        let finished = link.status == "completed" || link.status == DETACHED || link.status == HIDDEN;
+        if !finished && !live.contains(&(link.session_id.as_str(), link.content.as_str())) {
+            changes.push(TodoChange::Complete(link.clone()));
+        }
+    }
+
+    changes
+}
+
+/// Pending changes for a service, read from the store
+pub fn pending_changes(
+    store: &EventStore,
+    service: &str,
+    todos: &[GlobalTodoItem],
+) -> Result<Vec<TodoChange>, StoreError> {
+    let links = store.get_todo_links(service)?;
+    Ok(diff_todos(&links, todos))
+}
+
+/// Mark the links to todos whose file went stale, so they aren't completed remotely
+pub fn hide(store: &EventStore, todos: &[GlobalTodoItem]) -> Result<(), StoreError> {
+    let keys: Vec<(String, String)> = todos.iter().map(|t| (t.session_id.clone(), t.content.clone())).collect();
+    store.set_open_todo_links_status(&keys, HIDDEN)
+}
+
+/// Record that a todo now exists remotely with the given status
+pub fn record_link(
+    store: &EventStore,
+    service: &str,
+    session_id: &str,
+    content: &str,
+    remote_id: &str,
+    status: &str,
+) -> Result<(), StoreError> {
+    store.upsert_todo_link(&TodoLink {
+        service: service.to_string(),
+        session_id: session_id.to_string(),
+        content: content.to_string(),
+        remote_id: remote_id.to_string(),
+        status: status.to_string(),
+    })
+}
+
+/// Project path of the session a todo belongs to, if known
+pub fn session_project_path(store: &EventStore, session_id: &str) -> Option<String> {
+    store.get_session(session_id).ok().flatten().and_then(|s| s.project_path)
+}
+
+/// Whether `project_path` lies inside a configured project root
+pub fn path_matches(project_path: &str, root: &str) -> bool {
+    !root.is_empty() && Path::new(project_path).starts_with(root)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn todo(session_id: &str, content: &str, status: &str) -> GlobalTodoItem {
+        GlobalTodoItem {
+            content: content.to_string(),
+            status: status.to_string(),
+            active_form: content.to_string(),
+            session_id: session_id.to_string(),
+            profile: None,
+            also_in: Vec::new(),
+        }
+    }
+
+    fn link(content: &str, status: &str) -> TodoLink {
+        TodoLink {
+            service: "test".to_string(),
+            session_id: "s1".to_string(),
+            content: content.to_string(),
+            remote_id: format!("remote-{}", content),
+            status: status.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_diff_todos() {
+        let links = vec![
+            link("unchanged", "pending"),
+            link("started", "pending"),
+            link("finished", "in_progress"),
+            link("already done", "completed"),
+            link("closed remotely", DETACHED),
+            link("stale", HIDDEN),
+        ];
+        let todos = vec![
+            todo("s1", "unchanged", "pending"),
+            todo("s1", "started", "in_progress"),
+            todo("s1", "new", "pending"),
+            todo("s1", "closed remotely", "in_progress"),
+        ];
+
+        let changes = diff_todos(&links, &todos);
+        assert_eq!(changes.len(), 3);
+        assert!(matches!(&changes[0], TodoChange::Update { status, .. } if status == "in_progress"));
+        assert!(matches!(&changes[1], TodoChange::Create(t) if t.content == "new"));
+        assert!(matches!(&changes[2], TodoChange::Complete(l) if l.content == "finished"));
+    }
+
+    #[test]
+    fn test_path_matches() {
+        assert!(path_matches("/home/dev/app/src", "/home/dev/app"));
+        assert!(!path_matches("/home/dev/application", "/home/dev/app"));
+        assert!(!path_matches("/home/dev/app", ""));
+    }
+}
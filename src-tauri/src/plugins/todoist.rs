@@ -0,0 +1,189 @@
+//! Todoist sync plugin
+//!
+//! Mirrors pending/in-progress todos into a Todoist project and closes them when
+//! the agent finishes. Only status is pushed, so content edited in Todoist is
+//! never overwritten. When an item was closed or deleted in Todoist while the
+//! agent still has it open, `conflict_policy` decides who wins:
+//!
+//! - `"remote"` (default): the Todoist change stands and the item stops syncing
+//! - `"local"`: the item is reopened (or recreated) to match the agent
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use reqwest::StatusCode;
+use serde_json::{json, Value};
+
+use crate::config::{Capability, TodoistPluginConfig};
+use crate::plugins::todo_sync::{self, TodoChange, DETACHED};
+use crate::plugins::Grants;
+use crate::router::{AppEvent, Plugin, PluginError};
+use crate::store::{EventStore, TodoLink};
+
+pub const NAME: &str = "todoist";
+const SERVICE: &str = "todoist";
+const API_URL: &str = "https://api.todoist.com/rest/v2";
+
+pub struct TodoistPlugin {
+    config: TodoistPluginConfig,
+    store: Arc<EventStore>,
+    client: reqwest::Client,
+    grants: Grants,
+}
+
+impl TodoistPlugin {
+    pub fn new(config: TodoistPluginConfig, store: Arc<EventStore>, grants: Grants) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(15))
+            .build()
+            .unwrap_or_default();
+
+        Self { config, store, client, grants }
+    }
+
+    fn prefer_local(&self) -> bool {
+        self.config.conflict_policy == "local"
+    }
+
+    async fn apply(&self, change: TodoChange) -> Result<(), PluginError> {
+        match change {
+            TodoChange::Create(todo) => {
+                let remote_id = self.create_task(&todo.content).await?;
+                self.record(&todo.session_id, &todo.content, &remote_id, &todo.status)?;
+            }
+            TodoChange::Update { link, status } => {
+                // Todoist has no in-progress state, so only check the item is still open
+                if self.is_open(&link.remote_id).await? {
+                    self.record(&link.session_id, &link.content, &link.remote_id, &status)?;
+                } else if self.prefer_local() {
+                    let remote_id = self.restore_task(&link).await?;
+                    self.record(&link.session_id, &link.content, &remote_id, &status)?;
+                } else {
+                    tracing::info!("Todoist item closed remotely, keeping remote state: {}", link.content);
+                    self.record(&link.session_id, &link.content, &link.remote_id, DETACHED)?;
+                }
+            }
+            TodoChange::Complete(link) => {
+                if self.is_open(&link.remote_id).await? {
+                    self.post(&format!("tasks/{}/close", link.remote_id), None).await?;
+                }
+                self.record(&link.session_id, &link.content, &link.remote_id, "completed")?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn record(&self, session_id: &str, content: &str, remote_id: &str, status: &str) -> Result<(), PluginError> {
+        todo_sync::record_link(&self.store, SERVICE, session_id, content, remote_id, status)
+            .map_err(|e| PluginError::EventError(e.to_string()))
+    }
+
+    async fn create_task(&self, content: &str) -> Result<String, PluginError> {
+        let mut body = json!({ "content": content });
+        if !self.config.project_id.is_empty() {
+            body["project_id"] = json!(self.config.project_id);
+        }
+
+        let task = self.post("tasks", Some(body)).await?;
+        task["id"].as_str()
+            .map(|id| id.to_string())
+            .ok_or_else(|| PluginError::EventError("Todoist did not return a task id".to_string()))
+    }
+
+    /// Reopen a closed task, recreating it if it was deleted
+    async fn restore_task(&self, link: &TodoLink) -> Result<String, PluginError> {
+        match self.post(&format!("tasks/{}/reopen", link.remote_id), None).await {
+            Ok(_) => Ok(link.remote_id.clone()),
+            Err(_) => self.create_task(&link.content).await,
+        }
+    }
+
+    /// Whether the task exists and is still open (the REST API 404s closed tasks)
+    async fn is_open(&self, remote_id: &str) -> Result<bool, PluginError> {
+        self.grants.require(Capability::Network)?;
+        let response = self.client
+            .get(format!("{}/tasks/{}", API_URL, remote_id))
+            .bearer_auth(&self.config.api_token)
+            .send()
+            .await
+            .map_err(|e| PluginError::EventError(e.to_string()))?;
+
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(false);
+        }
+
+        let task: Value = response.error_for_status()
+            .map_err(|e| PluginError::EventError(e.to_string()))?
+            .json()
+            .await
+            .map_err(|e| PluginError::EventError(e.to_string()))?;
+
+        Ok(!task["is_completed"].as_bool().unwrap_or(false))
+    }
+
+    async fn post(&self, path: &str, body: Option<Value>) -> Result<Value, PluginError> {
+        self.grants.require(Capability::Network)?;
+        let mut request = self.client
+            .post(format!("{}/{}", API_URL, path))
+            .bearer_auth(&self.config.api_token);
+        if let Some(body) = body {
+            request = request.json(&body);
+        }
+
+        let response = request.send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| PluginError::EventError(e.to_string()))?;
+
+        // close/reopen answer 204 No Content
+        let text = response.text().await.map_err(|e| PluginError::EventError(e.to_string()))?;
+        if text.is_empty() {
+            return Ok(Value::Null);
+        }
+        serde_json::from_str(&text).map_err(|e| PluginError::EventError(e.to_string()))
+    }
+}
+
+#[async_trait::async_trait]
+impl Plugin for TodoistPlugin {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn version(&self) -> &str {
+        "1.0.0"
+    }
+
+    fn capabilities(&self) -> &[Capability] {
+        &[Capability::Network]
+    }
+
+    async fn on_init(&mut self) -> Result<(), PluginError> {
+        if self.config.api_token.is_empty() {
+            return Err(PluginError::InitError("Todoist api_token is not set".to_string()));
+        }
+        Ok(())
+    }
+
+    async fn on_event(&self, event: &AppEvent) -> Result<(), PluginError> {
+        let AppEvent::TodosUpdated(todos) = event else {
+            return Ok(());
+        };
+
+        let changes = todo_sync::pending_changes(&self.store, SERVICE, todos)
+            .map_err(|e| PluginError::EventError(e.to_string()))?;
+
+        for change in changes {
+            if let Err(e) = self.apply(change).await {
+                tracing::warn!("Todoist sync failed: {}", e);
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn on_shutdown(&self) -> Result<(), PluginError> {
+        Ok(())
+    }
+}
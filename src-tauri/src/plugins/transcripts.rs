@@ -0,0 +1,106 @@
+//! Transcript enrichment plugin
+//!
+//! When a session ends (Claude Code's `SessionEnd` hook, which the overlay
+//! turns into a `session_stopped` event), the plugin reads the session's
+//! transcript and records, for each task that doesn't have it yet, the
+//! prompt it ran for and the assistant's closing reply, so history search can
+//! find tasks by what was asked rather than only by the command that ran.
+//! The model that ran each task is recorded along the way.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::config::Capability;
+use crate::plugins::Grants;
+use crate::redact::Redactor;
+use crate::router::{AppEvent, Plugin, PluginError};
+use crate::store::{EventStore, TaskContext};
+use crate::text;
+use crate::transcript;
+
+pub const NAME: &str = "transcripts";
+
+pub struct TranscriptsPlugin {
+    store: Arc<EventStore>,
+    /// Claude's config directories, the main one first, searched for transcripts
+    claude_dirs: Vec<PathBuf>,
+    redactor: Redactor,
+    grants: Grants,
+}
+
+impl TranscriptsPlugin {
+    pub fn new(store: Arc<EventStore>, claude_dirs: Vec<PathBuf>, redactor: Redactor, grants: Grants) -> Self {
+        Self { store, claude_dirs, redactor, grants }
+    }
+
+    /// Prompt and reply, cleaned up and redacted like task descriptions
+    fn clean(&self, text: &str) -> String {
+        self.redactor.redact(&text::clean_description(text)).into_owned()
+    }
+
+    /// Fill in the context of the session's tasks from its transcript
+    fn enrich_session(&self, session_id: &str) -> Result<usize, PluginError> {
+        let tasks = self.store.get_tasks_missing_context(session_id)
+            .map_err(|e| PluginError::EventError(e.to_string()))?;
+        if tasks.is_empty() {
+            return Ok(0);
+        }
+        self.grants.require(Capability::Filesystem)?;
+        let Some(path) = self.claude_dirs.iter().find_map(|dir| transcript::find_transcript(dir, session_id)) else {
+            return Ok(0);
+        };
+
+        let turns = transcript::read_turns(&path);
+        let mut enriched = 0;
+        for (task_id, started_at) in tasks {
+            let Some(turn) = transcript::turn_for(&turns, &task_id, started_at) else {
+                continue;
+            };
+            let context = TaskContext {
+                prompt: self.clean(&turn.prompt),
+                summary: turn.summary.as_deref().map(|summary| self.clean(summary)),
+            };
+            self.store.set_task_context(&task_id, &context)
+                .map_err(|e| PluginError::EventError(e.to_string()))?;
+            if let Some(model) = turn.model_for(&task_id) {
+                self.store.set_task_model(&task_id, model)
+                    .map_err(|e| PluginError::EventError(e.to_string()))?;
+            }
+            enriched += 1;
+        }
+        Ok(enriched)
+    }
+}
+
+#[async_trait::async_trait]
+impl Plugin for TranscriptsPlugin {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn version(&self) -> &str {
+        "1.0.0"
+    }
+
+    fn capabilities(&self) -> &[Capability] {
+        &[Capability::Filesystem]
+    }
+
+    async fn on_init(&mut self) -> Result<(), PluginError> {
+        Ok(())
+    }
+
+    async fn on_event(&self, event: &AppEvent) -> Result<(), PluginError> {
+        if let AppEvent::SessionStopped { session_id: Some(session_id) } = event {
+            let count = self.enrich_session(session_id)?;
+            if count > 0 {
+                tracing::debug!("Added transcript context to {} tasks in session {}", count, session_id);
+            }
+        }
+        Ok(())
+    }
+
+    async fn on_shutdown(&self) -> Result<(), PluginError> {
+        Ok(())
+    }
+}
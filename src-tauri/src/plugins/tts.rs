@@ -0,0 +1,165 @@
+//! Spoken announcements
+//!
+//! Reads events aloud with the platform's speech engine: `say` on macOS,
+//! System.Speech through PowerShell on Windows, and `spd-say` or `espeak`
+//! elsewhere. Each event type is spoken from its own template, which may use
+//! the automation placeholders plus `{{tasks}}`, the number of tasks in the
+//! session. Nothing is spoken during quiet hours unless the config says so.
+
+use std::collections::HashMap;
+use std::process::Command;
+use std::sync::Arc;
+
+use crate::clock::{Clock, SystemClock};
+use crate::config::Capability;
+use crate::plugins::Grants;
+use crate::router::{AppEvent, Plugin, PluginError};
+use crate::rules::{self, Escape, EventContext};
+use crate::store::EventStore;
+use crate::timezone::QuietHours;
+
+pub const NAME: &str = "tts";
+
+pub struct TtsPlugin {
+    /// Template by event type; event types without one aren't spoken
+    templates: HashMap<String, String>,
+    /// Quiet hours to stay silent in, unless overridden
+    quiet_hours: Option<QuietHours>,
+    store: Arc<EventStore>,
+    grants: Grants,
+}
+
+impl TtsPlugin {
+    pub fn new(
+        templates: HashMap<String, String>,
+        quiet_hours: Option<QuietHours>,
+        speak_during_quiet_hours: bool,
+        store: Arc<EventStore>,
+        grants: Grants,
+    ) -> Self {
+        let quiet_hours = quiet_hours.filter(|_| !speak_during_quiet_hours);
+        Self { templates, quiet_hours, store, grants }
+    }
+
+    /// Number of tasks in the event's session, for `{{tasks}}`
+    fn session_tasks(&self, ctx: &EventContext) -> Option<i64> {
+        let session_id = ctx.session_id.as_deref()?;
+        self.store.get_session_summary(session_id).ok().flatten().map(|summary| summary.total_tasks)
+    }
+}
+
+/// Text to speak for an event, if its type has a template
+fn announcement(templates: &HashMap<String, String>, ctx: &EventContext, tasks: Option<i64>) -> Option<String> {
+    let template = templates.get(&ctx.event_type)?;
+    let template = template.replace("{{tasks}}", &tasks.unwrap_or(0).to_string());
+    let text = rules::render_template(&template, ctx, Escape::None);
+    // A leading dash would be read as an option by the speech commands
+    let text = text.trim().trim_start_matches('-').trim_start();
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+#[cfg(target_os = "macos")]
+fn speak(text: &str) -> std::io::Result<()> {
+    Command::new("say").arg(text).status().map(|_| ())
+}
+
+#[cfg(windows)]
+fn speak(text: &str) -> std::io::Result<()> {
+    use std::os::windows::process::CommandExt;
+    const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+
+    // The text goes through the environment so it is never parsed as PowerShell
+    Command::new("powershell")
+        .args([
+            "-NoProfile",
+            "-NonInteractive",
+            "-Command",
+            "Add-Type -AssemblyName System.Speech; \
+             (New-Object System.Speech.Synthesis.SpeechSynthesizer).Speak($env:PROGRESS_TTS_TEXT)",
+        ])
+        .env("PROGRESS_TTS_TEXT", text)
+        .creation_flags(CREATE_NO_WINDOW)
+        .status()
+        .map(|_| ())
+}
+
+#[cfg(not(any(windows, target_os = "macos")))]
+fn speak(text: &str) -> std::io::Result<()> {
+    match Command::new("spd-say").arg("--wait").arg(text).status() {
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            Command::new("espeak").arg(text).status().map(|_| ())
+        }
+        result => result.map(|_| ()),
+    }
+}
+
+#[async_trait::async_trait]
+impl Plugin for TtsPlugin {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn version(&self) -> &str {
+        "1.0.0"
+    }
+
+    fn capabilities(&self) -> &[Capability] {
+        &[Capability::Shell]
+    }
+
+    async fn on_init(&mut self) -> Result<(), PluginError> {
+        Ok(())
+    }
+
+    async fn on_event(&self, event: &AppEvent) -> Result<(), PluginError> {
+        if self.quiet_hours.is_some_and(|quiet| quiet.contains(SystemClock.now_ms())) {
+            return Ok(());
+        }
+        let Some(ctx) = EventContext::from_event(event, &self.store) else {
+            return Ok(());
+        };
+        let Some(text) = announcement(&self.templates, &ctx, self.session_tasks(&ctx)) else {
+            return Ok(());
+        };
+
+        self.grants.require(Capability::Shell)?;
+        tokio::task::spawn_blocking(move || speak(&text))
+            .await
+            .map_err(|e| PluginError::EventError(e.to_string()))?
+            .map_err(|e| PluginError::EventError(format!("Speech failed: {}", e)))
+    }
+
+    async fn on_shutdown(&self) -> Result<(), PluginError> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::TtsPluginConfig;
+
+    #[test]
+    fn test_announcement() {
+        let templates = TtsPluginConfig::default().templates;
+        let failed = EventContext {
+            event_type: "task_error".to_string(),
+            tool: Some("Bash".to_string()),
+            description: Some("npm test".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(announcement(&templates, &failed, None).as_deref(), Some("Bash failed: npm test"));
+
+        let stopped = EventContext {
+            event_type: "session_stopped".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(announcement(&templates, &stopped, Some(14)).as_deref(), Some("Session complete, 14 tasks"));
+
+        // Event types without a template stay silent, as do templates that render empty
+        let started = EventContext { event_type: "task_started".to_string(), ..Default::default() };
+        assert_eq!(announcement(&templates, &started, None), None);
+        let custom = HashMap::from([("task_error".to_string(), "{{source}}".to_string())]);
+        assert_eq!(announcement(&custom, &failed, None), None);
+    }
+}
@@ -0,0 +1,217 @@
+//! Event priorities, and how each one is delivered
+//!
+//! Every task event gets a priority: low, normal, high or urgent. A hook can
+//! set one on the event itself; otherwise the first `[[priority.rules]]`
+//! entry whose rule matches sets it, and failing that `priority.events`
+//! does by event type, which makes errors high and agents waiting on the
+//! user urgent. Below `priority.popup` an event is only recorded; from there
+//! it pops up, and from `priority.push` it pops up even during quiet hours
+//! and while the user is away, and keeps asking for attention. Automation
+//! rules can forward events past a priority with `min_priority`.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::{PriorityConfig, RuleConfig};
+use crate::rules::{self, EventContext};
+use crate::store::EventStore;
+use crate::watcher::TaskEvent;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Priority {
+    Low,
+    #[default]
+    Normal,
+    High,
+    Urgent,
+}
+
+impl Priority {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "low" => Some(Priority::Low),
+            "normal" => Some(Priority::Normal),
+            "high" => Some(Priority::High),
+            "urgent" => Some(Priority::Urgent),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Priority::Low => "low",
+            Priority::Normal => "normal",
+            Priority::High => "high",
+            Priority::Urgent => "urgent",
+        }
+    }
+
+    /// The priority a task event was given; normal before the router has seen it
+    pub fn of(event: &TaskEvent) -> Self {
+        event.priority.as_deref().and_then(Priority::parse).unwrap_or_default()
+    }
+
+    /// A priority from the config file, or `fallback` with a warning when it isn't one
+    fn from_config(value: &str, setting: &str, fallback: Priority) -> Self {
+        Priority::parse(value).unwrap_or_else(|| {
+            tracing::warn!("Unknown priority \"{}\" for {}, using {}", value, setting, fallback.name());
+            fallback
+        })
+    }
+}
+
+/// How an event reaches the user
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Delivery {
+    /// Recorded and listed, without a popup
+    Silent,
+    /// A popup, held back during quiet hours and while the user is away
+    Popup,
+    /// A popup at any time, which also keeps asking for attention
+    Push,
+}
+
+/// `[priority]` with its names resolved
+#[derive(Debug, Clone)]
+pub struct PriorityPolicy {
+    events: HashMap<String, Priority>,
+    rules: Vec<(RuleConfig, Priority)>,
+    popup: Priority,
+    push: Priority,
+}
+
+impl PriorityPolicy {
+    pub fn new(config: &PriorityConfig, rules: &[RuleConfig]) -> Self {
+        let events = config
+            .events
+            .iter()
+            .map(|(event_type, priority)| {
+                let setting = format!("{} events", event_type);
+                (event_type.clone(), Priority::from_config(priority, &setting, Priority::Normal))
+            })
+            .collect();
+        let rules = config
+            .rules
+            .iter()
+            .filter_map(|entry| {
+                let Some(rule) = rules::find(rules, &entry.rule) else {
+                    tracing::warn!("Priority rule \"{}\" isn't defined under [[rules]]", entry.rule);
+                    return None;
+                };
+                let setting = format!("rule {}", entry.rule);
+                Some((rule.clone(), Priority::from_config(&entry.priority, &setting, Priority::Normal)))
+            })
+            .collect();
+
+        Self {
+            events,
+            rules,
+            popup: Priority::from_config(&config.popup, "priority.popup", Priority::Normal),
+            push: Priority::from_config(&config.push, "priority.push", Priority::Urgent),
+        }
+    }
+
+    /// The event's own priority, else the first matching rule's, else its type's
+    pub fn assign(&self, event: &TaskEvent, store: &EventStore) -> Priority {
+        if let Some(own) = &event.priority {
+            match Priority::parse(own) {
+                Some(priority) => return priority,
+                None => tracing::warn!("Ignoring unknown priority \"{}\" on {}", own, event.task_id),
+            }
+        }
+        if !self.rules.is_empty() {
+            if let Some(ctx) = EventContext::from_task_event(event, store) {
+                if let Some((_, priority)) = self.rules.iter().find(|(rule, _)| rules::matches(rule, &ctx)) {
+                    return *priority;
+                }
+            }
+        }
+        self.events.get(&event.event_type).copied().unwrap_or_default()
+    }
+
+    pub fn delivery(&self, priority: Priority) -> Delivery {
+        if priority >= self.push {
+            Delivery::Push
+        } else if priority >= self.popup {
+            Delivery::Popup
+        } else {
+            Delivery::Silent
+        }
+    }
+}
+
+impl Default for PriorityPolicy {
+    fn default() -> Self {
+        Self::new(&PriorityConfig::default(), &[])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::PriorityRuleConfig;
+
+    fn rule(name: &str, tools: &[&str]) -> RuleConfig {
+        RuleConfig {
+            name: name.to_string(),
+            events: Vec::new(),
+            tools: tools.iter().map(|tool| tool.to_string()).collect(),
+            description_contains: None,
+            min_duration_ms: None,
+            min_priority: None,
+            custom_types: Vec::new(),
+            payload: HashMap::new(),
+        }
+    }
+
+    fn event(event_type: &str, tool: &str) -> TaskEvent {
+        let mut event = TaskEvent::new(event_type, "t1", 1_000);
+        event.tool = Some(tool.to_string());
+        event
+    }
+
+    #[test]
+    fn test_default_priorities() {
+        let store = EventStore::in_memory().unwrap();
+        let policy = PriorityPolicy::default();
+        assert_eq!(policy.assign(&event("task_started", "Bash"), &store), Priority::Normal);
+        assert_eq!(policy.assign(&event("task_error", "Bash"), &store), Priority::High);
+        assert_eq!(policy.assign(&event("awaiting_input", "Bash"), &store), Priority::Urgent);
+
+        let mut own = event("awaiting_input", "Bash");
+        own.priority = Some("Low".to_string());
+        assert_eq!(policy.assign(&own, &store), Priority::Low);
+        own.priority = Some("whenever".to_string());
+        assert_eq!(policy.assign(&own, &store), Priority::Urgent);
+    }
+
+    #[test]
+    fn test_rules_set_priority() {
+        let store = EventStore::in_memory().unwrap();
+        let config = PriorityConfig {
+            rules: vec![
+                PriorityRuleConfig { rule: "reads".to_string(), priority: "low".to_string() },
+                PriorityRuleConfig { rule: "missing".to_string(), priority: "urgent".to_string() },
+            ],
+            ..Default::default()
+        };
+        let policy = PriorityPolicy::new(&config, &[rule("reads", &["Read", "Glob"])]);
+        assert_eq!(policy.assign(&event("task_started", "Glob"), &store), Priority::Low);
+        assert_eq!(policy.assign(&event("task_started", "Bash"), &store), Priority::Normal);
+    }
+
+    #[test]
+    fn test_delivery() {
+        let config = PriorityConfig { popup: "high".to_string(), ..Default::default() };
+        let policy = PriorityPolicy::new(&config, &[]);
+        assert_eq!(policy.delivery(Priority::Normal), Delivery::Silent);
+        assert_eq!(policy.delivery(Priority::High), Delivery::Popup);
+        assert_eq!(policy.delivery(Priority::Urgent), Delivery::Push);
+
+        let default = PriorityPolicy::default();
+        assert_eq!(default.delivery(Priority::Low), Delivery::Silent);
+        assert_eq!(default.delivery(Priority::Normal), Delivery::Popup);
+    }
+}
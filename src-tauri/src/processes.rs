@@ -0,0 +1,342 @@
+//! Resource use of background shells
+//!
+//! A background Bash task keeps running after its tool call returns, in a
+//! shell whose process id the task's start event reports. Every
+//! `processes.sample_interval_secs` this samples that shell and everything it
+//! started, sums CPU and memory over the tree, and records it on the task
+//! along with whether the shell is still running. Sampling stops once the
+//! shell exits or the task ends; the exit code comes from the end event.
+//! A runaway tree, such as a dev server the agent left running, can also be
+//! stopped from here.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use sysinfo::{Pid, ProcessRefreshKind, ProcessStatus, ProcessesToUpdate, Signal, System};
+
+use crate::clock::Clock;
+use crate::config::ProcessesConfig;
+use crate::store::{EventStore, ProcessUsage};
+
+/// How far apart the task and its shell may have started; a process with the
+/// same id that started outside that window, before or after, is a different
+/// one reusing the id
+const PID_REUSE_SLACK_MS: i64 = 60_000;
+
+/// How long a tree gets to exit after SIGTERM before what's left is sent SIGKILL
+const KILL_GRACE: Duration = Duration::from_secs(3);
+/// How often a tree being killed is checked for survivors
+const KILL_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// A process as one refresh saw it
+#[derive(Debug, Clone, Copy)]
+struct Entry {
+    pid: u32,
+    parent: Option<u32>,
+    cpu_percent: f32,
+    rss_bytes: u64,
+    started_at_ms: i64,
+}
+
+/// What `kill_background_task` did
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum KillOutcome {
+    /// Nothing was killed; ask the user, then call again with `confirm_pid` set to `pid`
+    ConfirmationNeeded {
+        task_id: String,
+        pid: u32,
+        description: Option<String>,
+        usage: Option<ProcessUsage>,
+    },
+    /// The tree is gone; `forced` if some of it had to be killed outright
+    Killed { task_id: String, pid: u32, forced: bool },
+}
+
+/// Keeps the process table between samples, which CPU use is measured across
+pub struct Monitor {
+    system: System,
+}
+
+impl Monitor {
+    pub fn new() -> Self {
+        Self { system: System::new() }
+    }
+
+    fn refresh(&mut self) -> Vec<Entry> {
+        self.system.refresh_processes_specifics(
+            ProcessesToUpdate::All,
+            true,
+            ProcessRefreshKind::nothing().with_cpu().with_memory(),
+        );
+        self.system
+            .processes()
+            .values()
+            // Linux lists threads too, which share their process's memory
+            .filter(|process| process.thread_kind().is_none())
+            .map(|process| Entry {
+                pid: process.pid().as_u32(),
+                parent: process.parent().map(|parent| parent.as_u32()),
+                cpu_percent: process.cpu_usage(),
+                rss_bytes: process.memory(),
+                started_at_ms: process.start_time() as i64 * 1000,
+            })
+            .collect()
+    }
+
+    /// Which of `pids` are still running, zombies aside
+    fn running(&mut self, pids: &[u32]) -> Vec<u32> {
+        let watched: Vec<Pid> = pids.iter().map(|pid| Pid::from_u32(*pid)).collect();
+        self.system.refresh_processes_specifics(
+            ProcessesToUpdate::Some(&watched),
+            true,
+            ProcessRefreshKind::nothing(),
+        );
+        watched
+            .iter()
+            .filter(|pid| self.system.process(**pid).is_some_and(|process| process.status() != ProcessStatus::Zombie))
+            .map(|pid| pid.as_u32())
+            .collect()
+    }
+
+    /// Send `pids` a signal, or with none, kill them outright
+    fn send(&self, pids: &[u32], signal: Option<Signal>) {
+        for process in pids.iter().filter_map(|pid| self.system.process(Pid::from_u32(*pid))) {
+            let _ = match signal {
+                Some(signal) => process.kill_with(signal),
+                None => Some(process.kill()),
+            };
+        }
+    }
+}
+
+impl Default for Monitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// `root` and its descendants, root first
+///
+/// None once `root` has exited, or if the process with its id didn't start
+/// around when the task did, such as pid 1 or a long-running daemon.
+fn tree(entries: &[Entry], root: u32, task_started_at: i64) -> Option<Vec<&Entry>> {
+    let shell = entries.iter().find(|entry| entry.pid == root)?;
+    if (shell.started_at_ms - task_started_at).abs() > PID_REUSE_SLACK_MS {
+        return None;
+    }
+
+    let mut children: HashMap<u32, Vec<&Entry>> = HashMap::new();
+    for entry in entries {
+        if let Some(parent) = entry.parent.filter(|parent| *parent != entry.pid) {
+            children.entry(parent).or_default().push(entry);
+        }
+    }
+
+    let mut found = Vec::new();
+    let mut seen = HashSet::new();
+    let mut pending = vec![shell];
+    while let Some(entry) = pending.pop() {
+        if !seen.insert(entry.pid) {
+            continue;
+        }
+        found.push(entry);
+        pending.extend(children.get(&entry.pid).into_iter().flatten());
+    }
+    Some(found)
+}
+
+/// Usage summed over `root` and its descendants; none if it isn't running
+fn tree_usage(entries: &[Entry], root: u32, task_started_at: i64, now_ms: i64) -> Option<ProcessUsage> {
+    let mut usage = ProcessUsage { alive: true, cpu_percent: 0.0, rss_bytes: 0, processes: 0, sampled_at: now_ms };
+    for entry in tree(entries, root, task_started_at)? {
+        usage.cpu_percent += entry.cpu_percent as f64;
+        usage.rss_bytes += entry.rss_bytes;
+        usage.processes += 1;
+    }
+    Some(usage)
+}
+
+/// The tree `root` heads right now, for a confirmation prompt
+pub fn sample(root: u32, task_started_at: i64, now_ms: i64) -> Option<ProcessUsage> {
+    tree_usage(&Monitor::new().refresh(), root, task_started_at, now_ms)
+}
+
+/// Whether `root` is still the shell the task started, rather than an unrelated process reusing its id
+pub fn is_task_process(root: u32, task_started_at: i64) -> bool {
+    tree(&Monitor::new().refresh(), root, task_started_at).is_some()
+}
+
+/// Stop `root` and everything it started, returning whether any of it had to be forced
+///
+/// On Unix the tree gets SIGTERM and whatever is still running after
+/// `KILL_GRACE` gets SIGKILL. Windows console programs have no signal to
+/// exit cleanly on, so there the tree is terminated at once, as
+/// `taskkill /T /F` would. Blocks for up to `KILL_GRACE`.
+pub fn kill_tree(root: u32, task_started_at: i64) -> Result<bool, String> {
+    let mut monitor = Monitor::new();
+    let entries = monitor.refresh();
+    let pids: Vec<u32> = tree(&entries, root, task_started_at)
+        .ok_or_else(|| format!("Process {} isn't running, or is no longer the task's", root))?
+        .iter()
+        .map(|entry| entry.pid)
+        .collect();
+
+    if !cfg!(unix) {
+        monitor.send(&pids, None);
+        return Ok(true);
+    }
+
+    monitor.send(&pids, Some(Signal::Term));
+    let deadline = Instant::now() + KILL_GRACE;
+    loop {
+        std::thread::sleep(KILL_POLL_INTERVAL);
+        let left = monitor.running(&pids);
+        if left.is_empty() {
+            return Ok(false);
+        }
+        if Instant::now() >= deadline {
+            tracing::info!("{} processes of {} ignored SIGTERM; sending SIGKILL", left.len(), root);
+            monitor.send(&left, None);
+            return Ok(true);
+        }
+    }
+}
+
+/// Sample every monitored task's processes once
+fn run_once(store: &EventStore, monitor: &mut Monitor, clock: &dyn Clock) -> Result<(), String> {
+    let tasks = store.get_monitored_tasks().map_err(|e| e.to_string())?;
+    if tasks.is_empty() {
+        return Ok(());
+    }
+
+    let entries = monitor.refresh();
+    let now_ms = clock.now_ms();
+    for (task_id, pid, started_at) in tasks {
+        let usage = tree_usage(&entries, pid, started_at, now_ms);
+        if usage.is_none() {
+            tracing::debug!("Process {} of task {} has exited", pid, task_id);
+        }
+        store.set_task_process(&task_id, usage.as_ref(), now_ms).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Sample background shells every `sample_interval_secs` on the current runtime
+pub fn start(config: ProcessesConfig, store: Arc<EventStore>, clock: Arc<dyn Clock>) {
+    if !config.monitor {
+        return;
+    }
+    let interval = Duration::from_secs(config.sample_interval_secs.max(1));
+    let monitor = Arc::new(Mutex::new(Monitor::new()));
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            let (store, clock, monitor) = (store.clone(), clock.clone(), monitor.clone());
+            let sampled = tokio::task::spawn_blocking(move || {
+                let mut monitor = monitor.lock().map_err(|_| "Process monitor lock poisoned".to_string())?;
+                run_once(&store, &mut monitor, clock.as_ref())
+            }).await;
+            match sampled {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => tracing::warn!("Process sampling failed: {}", e),
+                Err(e) => tracing::error!("Process sampling task failed: {}", e),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::SystemClock;
+    use crate::store::StoredTask;
+
+    fn entry(pid: u32, parent: Option<u32>, rss_bytes: u64) -> Entry {
+        Entry { pid, parent, cpu_percent: 10.0, rss_bytes, started_at_ms: 1_000 }
+    }
+
+    #[test]
+    fn test_tree_usage() {
+        let entries = [
+            entry(1, None, 1),
+            entry(100, Some(1), 10),
+            entry(101, Some(100), 20),
+            entry(102, Some(101), 30),
+            entry(200, Some(1), 1000),
+        ];
+        let usage = tree_usage(&entries, 100, 1_000, 5_000).unwrap();
+        assert!(usage.alive);
+        assert_eq!(usage.processes, 3);
+        assert_eq!(usage.rss_bytes, 60);
+        assert_eq!(usage.cpu_percent, 30.0);
+        assert_eq!(usage.sampled_at, 5_000);
+
+        assert!(tree_usage(&entries, 300, 1_000, 5_000).is_none());
+    }
+
+    #[test]
+    fn test_reused_pid_counts_as_exited() {
+        let entries = [entry(100, None, 10)];
+        assert!(tree_usage(&entries, 100, 1_000 - PID_REUSE_SLACK_MS - 1, 5_000).is_none());
+        // A process that was already running long before the task isn't its shell either
+        assert!(tree_usage(&entries, 100, 1_000 + PID_REUSE_SLACK_MS + 1, 5_000).is_none());
+        assert!(tree_usage(&entries, 100, 1_000 + PID_REUSE_SLACK_MS, 5_000).is_some());
+    }
+
+    #[test]
+    fn test_tree_lists_root_first() {
+        let entries = [entry(101, Some(100), 20), entry(100, None, 10), entry(102, Some(100), 30)];
+        let pids: Vec<u32> = tree(&entries, 100, 1_000).unwrap().iter().map(|entry| entry.pid).collect();
+        assert_eq!(pids[0], 100);
+        assert_eq!(pids.len(), 3);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_kill_tree() {
+        let mut child = std::process::Command::new("sleep").arg("30").spawn().unwrap();
+        let forced = kill_tree(child.id(), SystemClock.now_ms()).unwrap();
+        assert!(!forced);
+        assert!(!child.wait().unwrap().success());
+        assert!(kill_tree(u32::MAX - 1, 0).is_err());
+    }
+
+    #[test]
+    fn test_run_once_samples_own_process() {
+        let store = EventStore::in_memory().unwrap();
+        let clock = SystemClock;
+        // The task has to have started along with the process it points at
+        let mut system = System::new();
+        let pid = Pid::from_u32(std::process::id());
+        system.refresh_processes(ProcessesToUpdate::Some(&[pid]), true);
+        let started_at = system.process(pid).unwrap().start_time() as i64 * 1000;
+        store.insert_task(&StoredTask {
+            id: "bash-1".to_string(),
+            session_id: "session-1".to_string(),
+            tool: "Bash".to_string(),
+            description: None,
+            status: "active".to_string(),
+            started_at,
+            ended_at: None,
+            duration_ms: None,
+            duration_suspect: false,
+            is_background: true,
+            subagent_type: None,
+            source: "claude-code".to_string(),
+            reviewed: false,
+            pinned: false,
+        }).unwrap();
+        store.set_task_pid("bash-1", std::process::id()).unwrap();
+
+        run_once(&store, &mut Monitor::new(), &clock).unwrap();
+        let usage = store.get_task_process("bash-1").unwrap().unwrap().usage.unwrap();
+        assert!(usage.alive);
+        assert!(usage.processes >= 1);
+        assert!(usage.rss_bytes > 0);
+    }
+}
@@ -0,0 +1,199 @@
+//! Relay listener - events forwarded from remote machines
+//!
+//! `agent-progress-relay` (the `relay/` crate) tails the events file on a
+//! remote dev box and streams it here as newline-delimited JSON. The listener
+//! binds to `127.0.0.1` only and requires a token, so remote machines reach it
+//! through an SSH tunnel (`ssh -R <port>:127.0.0.1:<port> devbox`).
+
+use std::time::Duration;
+
+use serde::Deserialize;
+use serde_json::json;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::tcp::OwnedWriteHalf;
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::config::RelayConfig;
+use crate::health;
+use crate::secret;
+use crate::watcher::{EventSender, Source, TaskEvent, WatcherError, WatcherEvent, CLAUDE_CODE_SOURCE};
+
+/// Bumped when a change would break existing relays
+pub const PROTOCOL_VERSION: u32 = 1;
+
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Messages sent by a relay
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum RelayMessage {
+    Hello {
+        version: u32,
+        #[serde(default)]
+        host: Option<String>,
+        #[serde(default)]
+        token: String,
+    },
+    Event {
+        event: Box<TaskEvent>,
+    },
+}
+
+/// Accepts connections from remote relays
+pub struct RelaySource {
+    port: u16,
+    token: String,
+}
+
+impl RelaySource {
+    pub fn new(config: &RelayConfig) -> Self {
+        Self {
+            port: config.port,
+            token: config.token.clone(),
+        }
+    }
+}
+
+impl Source for RelaySource {
+    fn name(&self) -> &str {
+        "relay"
+    }
+
+    fn start(self: Box<Self>, tx: EventSender) -> Result<(), WatcherError> {
+        if self.token.is_empty() {
+            return Err(WatcherError::WatchError("relay.token must be set to accept remote events".to_string()));
+        }
+
+        // Bind here rather than in the task so a taken port is reported as a start failure
+        let listener = std::net::TcpListener::bind(("127.0.0.1", self.port))
+            .and_then(|listener| {
+                listener.set_nonblocking(true)?;
+                TcpListener::from_std(listener)
+            })
+            .map_err(|e| WatcherError::IoError(format!("Failed to bind relay port {}: {}", self.port, e)))?;
+        tracing::info!("Relay listening on 127.0.0.1:{}", self.port);
+
+        tokio::spawn(async move {
+            loop {
+                let stream = match listener.accept().await {
+                    Ok((stream, _)) => stream,
+                    Err(e) => {
+                        tracing::warn!("Relay accept failed: {}", e);
+                        continue;
+                    }
+                };
+                let token = self.token.clone();
+                let tx = tx.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = handle_relay(stream, &token, tx).await {
+                        tracing::warn!("Relay connection closed: {}", e);
+                    }
+                });
+            }
+        });
+
+        Ok(())
+    }
+}
+
+/// Validate a relay's hello, returning the host it reported
+fn check_hello(line: &str, expected_token: &str) -> Result<String, String> {
+    match serde_json::from_str::<RelayMessage>(line) {
+        Ok(RelayMessage::Hello { version, host, token }) => {
+            if version != PROTOCOL_VERSION {
+                return Err(format!("Unsupported protocol version {}; expected {}", version, PROTOCOL_VERSION));
+            }
+            if !secret::tokens_match(&token, expected_token) {
+                return Err("Invalid token".to_string());
+            }
+            Ok(host.unwrap_or_else(|| "unknown".to_string()))
+        }
+        _ => Err("Expected hello".to_string()),
+    }
+}
+
+async fn reply(writer: &mut OwnedWriteHalf, message: serde_json::Value) -> Result<(), String> {
+    let line = format!("{}\n", message);
+    writer.write_all(line.as_bytes()).await.map_err(|e| e.to_string())
+}
+
+async fn handle_relay(stream: TcpStream, token: &str, tx: EventSender) -> Result<(), String> {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+
+    let first = tokio::time::timeout(HANDSHAKE_TIMEOUT, lines.next_line())
+        .await
+        .map_err(|_| "handshake timed out".to_string())?
+        .map_err(|e| e.to_string())?
+        .ok_or("closed before handshake")?;
+
+    let host = match check_hello(&first, token) {
+        Ok(host) => host,
+        Err(message) => {
+            let _ = reply(&mut writer, json!({ "type": "error", "message": message })).await;
+            return Err(message);
+        }
+    };
+    reply(&mut writer, json!({ "type": "welcome", "protocol_version": PROTOCOL_VERSION })).await?;
+    tracing::info!("Relay connected from {}", host);
+
+    // Relays send nothing while the remote agent is idle, so there's no read timeout here
+    while let Some(line) = lines.next_line().await.map_err(|e| e.to_string())? {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str::<RelayMessage>(&line) {
+            Ok(RelayMessage::Event { event }) => {
+                if tx.send(WatcherEvent::TaskEvent(remote_event(*event))).is_err() {
+                    return Err("event channel closed".to_string());
+                }
+            }
+            Ok(RelayMessage::Hello { .. }) => tracing::debug!("Ignoring repeated hello from {}", host),
+            Err(e) => {
+                tracing::warn!("Invalid relay message from {}: {}", host, e);
+                health::metrics().record_parse_error();
+            }
+        }
+    }
+
+    tracing::info!("Relay from {} disconnected", host);
+    Ok(())
+}
+
+/// A relayed event as it is handed on locally
+///
+/// Its pid names a process on the remote machine, so it is dropped rather
+/// than sampled, shown or killed here.
+fn remote_event(mut event: TaskEvent) -> TaskEvent {
+    event.source.get_or_insert_with(|| CLAUDE_CODE_SOURCE.to_string());
+    event.pid = None;
+    event
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_hello() {
+        let hello = r#"{"type":"hello","version":1,"role":"relay","host":"devbox","token":"secret"}"#;
+        assert_eq!(check_hello(hello, "secret").unwrap(), "devbox");
+        assert_eq!(check_hello(hello, "other").unwrap_err(), "Invalid token");
+
+        let old = r#"{"type":"hello","version":0,"token":"secret"}"#;
+        assert!(check_hello(old, "secret").unwrap_err().contains("Unsupported protocol version"));
+
+        let event = r#"{"type":"event","event":{"type":"task_started","task_id":"t1","timestamp":1}}"#;
+        assert_eq!(check_hello(event, "secret").unwrap_err(), "Expected hello");
+    }
+
+    #[test]
+    fn test_remote_events_drop_pid() {
+        let mut event = TaskEvent::new("task_started", "t1", 1);
+        event.background = Some(true);
+        event.pid = Some(4242);
+        let event = remote_event(event);
+        assert_eq!(event.pid, None);
+        assert_eq!(event.source.as_deref(), Some(CLAUDE_CODE_SOURCE));
+    }
+}
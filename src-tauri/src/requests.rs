@@ -0,0 +1,175 @@
+//! Requests from the overlay to hooks and agents
+//!
+//! The overlay never runs commands itself. A "retry" button appends a request
+//! to `overlay-requests.jsonl` in Claude's config directory, one JSON object
+//! per line, and whatever hook or agent follows that file decides what to do
+//! with it. Each request carries an `id` so a consumer can tell which ones it
+//! has already handled.
+//!
+//! A cancel request can also signal the process of a background shell, when
+//! its hook reported one and it is still running, since nothing else may be
+//! listening for it.
+
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::process::Command;
+
+use serde::{Deserialize, Serialize};
+
+use crate::store::StoredTask;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RequestKind {
+    /// Run the task's command again
+    Rerun,
+    /// Stop the task if it is still running
+    Cancel,
+}
+
+/// One line of the requests file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OverlayRequest {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub kind: RequestKind,
+    pub task_id: String,
+    pub session_id: String,
+    pub tool: String,
+    pub description: Option<String>,
+    /// Folder the task's session ran in, when known
+    pub project_path: Option<String>,
+    /// Process of a background shell, on cancel requests where it is known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pid: Option<u32>,
+    pub requested_at: i64,
+}
+
+impl OverlayRequest {
+    /// A request to run `task` again in the same project
+    pub fn rerun(task: &StoredTask, project_path: Option<String>, now_ms: i64) -> Self {
+        Self::new(RequestKind::Rerun, task, project_path, None, now_ms)
+    }
+
+    /// A request to stop `task`, with the process it runs in if known
+    pub fn cancel(task: &StoredTask, project_path: Option<String>, pid: Option<u32>, now_ms: i64) -> Self {
+        Self::new(RequestKind::Cancel, task, project_path, pid, now_ms)
+    }
+
+    fn new(kind: RequestKind, task: &StoredTask, project_path: Option<String>, pid: Option<u32>, now_ms: i64) -> Self {
+        let prefix = match kind {
+            RequestKind::Rerun => "rerun",
+            RequestKind::Cancel => "cancel",
+        };
+        Self {
+            id: format!("{}-{}-{}", prefix, task.id, now_ms),
+            kind,
+            task_id: task.id.clone(),
+            session_id: task.session_id.clone(),
+            tool: task.tool.clone(),
+            description: task.description.clone(),
+            project_path,
+            pid,
+            requested_at: now_ms,
+        }
+    }
+}
+
+/// Append a request to the file at `path`, creating it if needed
+pub fn append(path: &Path, request: &OverlayRequest) -> Result<(), String> {
+    let mut line = serde_json::to_string(request).map_err(|e| e.to_string())?;
+    line.push('\n');
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| format!("Couldn't open {}: {}", path.display(), e))?;
+    // One write per line, so a reader never sees half a request from us
+    file.write_all(line.as_bytes()).map_err(|e| e.to_string())
+}
+
+/// Ask a process to stop: SIGTERM on Unix, `taskkill` for its tree on Windows
+///
+/// Check with `processes::is_task_process` first that `pid` is still the task's.
+#[cfg(unix)]
+pub fn signal(pid: u32) -> Result<(), String> {
+    let status = Command::new("kill").arg("-TERM").arg(pid.to_string()).status().map_err(|e| e.to_string())?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(format!("Couldn't signal process {}", pid))
+    }
+}
+
+#[cfg(windows)]
+pub fn signal(pid: u32) -> Result<(), String> {
+    use std::os::windows::process::CommandExt;
+    const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+
+    let status = Command::new("taskkill")
+        .args(["/PID", &pid.to_string(), "/T"])
+        .creation_flags(CREATE_NO_WINDOW)
+        .status()
+        .map_err(|e| e.to_string())?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(format!("Couldn't signal process {}", pid))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task() -> StoredTask {
+        StoredTask {
+            session_id: "s1".to_string(),
+            description: Some("cargo test".to_string()),
+            status: "error".to_string(),
+            ended_at: Some(2_000),
+            duration_ms: Some(1_000),
+            ..StoredTask::test("t1", 1_000)
+        }
+    }
+
+    #[test]
+    fn test_append_requests() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("claude").join("overlay-requests.jsonl");
+
+        let first = OverlayRequest::rerun(&task(), Some("/repo".to_string()), 5_000);
+        let second = OverlayRequest::rerun(&task(), None, 6_000);
+        append(&path, &first).unwrap();
+        append(&path, &second).unwrap();
+
+        let contents = fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = contents.lines().collect();
+        assert_eq!(lines.len(), 2);
+
+        let value: serde_json::Value = serde_json::from_str(lines[0]).unwrap();
+        assert_eq!(value["type"], "rerun");
+        assert_eq!(value["description"], "cargo test");
+        assert_eq!(value["project_path"], "/repo");
+        assert_eq!(serde_json::from_str::<OverlayRequest>(lines[1]).unwrap(), second);
+        assert_ne!(first.id, second.id);
+    }
+
+    #[test]
+    fn test_cancel_request() {
+        let cancel = OverlayRequest::cancel(&task(), None, Some(4242), 5_000);
+        let value = serde_json::to_value(&cancel).unwrap();
+        assert_eq!(value["type"], "cancel");
+        assert_eq!(value["pid"], 4242);
+        assert_eq!(cancel.id, "cancel-t1-5000");
+
+        // Re-run requests leave the field out
+        let rerun = serde_json::to_value(OverlayRequest::rerun(&task(), None, 5_000)).unwrap();
+        assert!(rerun.get("pid").is_none());
+    }
+}
@@ -2,67 +2,427 @@
 //!
 //! Connects the file watcher to the store and Tauri frontend.
 
-use std::sync::Arc;
-use tokio::sync::broadcast;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{broadcast, mpsc, watch};
 
-use crate::config::Config;
-use crate::store::{EventStore, StoredTask, StoreError};
-use crate::watcher::{TaskEvent, GlobalTodoItem, DownloadProgress, WatcherEvent};
+use crate::clock::{Clock, SystemClock};
+use crate::config::{Capability, Config, PluginLimitConfig};
+use crate::git;
+use crate::health;
+use crate::plugins::todo_sync;
+use crate::priority::{Priority, PriorityPolicy};
+use crate::redact::Redactor;
+use crate::text;
+use crate::transcript;
+use crate::store::{CustomEvent, EventStore, StoredSession, StoredTask, StoreError, TaskFilter, TodoTransition, UsageWarning};
+use crate::watcher::{self, TaskEvent, GlobalTodoItem, DownloadProgress, GenericProgress, TodoGroup, TodosDiff, WatcherEvent, CLAUDE_CODE_SOURCE};
 
 /// Application events that can be broadcast
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", content = "data", rename_all = "snake_case")]
 pub enum AppEvent {
     TaskStarted(TaskEvent),
-    TaskCompleted { task_id: String, timestamp: u64 },
-    TaskError { task_id: String, timestamp: u64 },
+    TaskCompleted {
+        task_id: String,
+        timestamp: u64,
+        #[serde(default)]
+        priority: Priority,
+    },
+    TaskError {
+        task_id: String,
+        timestamp: u64,
+        #[serde(default)]
+        priority: Priority,
+    },
     TaskCanceled { task_id: String },
     SessionStopped { session_id: Option<String> },
+    /// The agent is blocked on a permission prompt or question
+    AwaitingInput(TaskEvent),
     TodosUpdated(Vec<GlobalTodoItem>),
+    TodosChanged(TodosDiff),
     DownloadProgress(DownloadProgress),
+    /// Progress a script reported in the progress directory
+    GenericProgress(GenericProgress),
+    /// The agent hit a rate or usage limit
+    UsageWarning(UsageWarning),
+    /// A task has run past its tool's escalation threshold
+    TaskOverdue(OverdueTask),
+    /// An event of a type the user's own hooks made up; see `TaskEvent::custom_type`
+    Custom(TaskEvent),
+}
+
+impl AppEvent {
+    /// Progress updates are superseded by the next one, so they aren't logged for catch-up
+    fn is_transient(&self) -> bool {
+        matches!(self, AppEvent::DownloadProgress(_) | AppEvent::GenericProgress(_))
+    }
+
+    /// Task lifecycle and todo events are never rate limited, since sync plugins
+    /// can't recover the state they carry once one is dropped
+    fn is_rate_limited(&self) -> bool {
+        !matches!(
+            self,
+            AppEvent::TaskStarted(_)
+                | AppEvent::TaskCompleted { .. }
+                | AppEvent::TaskError { .. }
+                | AppEvent::TaskCanceled { .. }
+                | AppEvent::SessionStopped { .. }
+                | AppEvent::TodosUpdated(_)
+                | AppEvent::TodosChanged(_)
+        )
+    }
+}
+
+/// An event as subscribers receive it, with its place in the event log
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoggedEvent {
+    /// Position in the log, for `events_since`; 0 for progress updates, which aren't logged
+    pub seq: i64,
+    /// When it was routed, in millis
+    pub at: i64,
+    pub event: AppEvent,
+}
+
+/// Events a subscriber missed, from `events_since`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EventPage {
+    pub events: Vec<LoggedEvent>,
+    /// Pass this to the next `events_since`
+    pub cursor: i64,
+    /// More events are waiting after this page
+    pub more: bool,
+    /// Events after the given cursor have already fallen out of the log, so
+    /// the subscriber should reload its state rather than rely on the page
+    pub missed: bool,
+}
+
+/// Most events `events_since` returns at once
+pub const EVENT_PAGE_LIMIT: usize = 500;
+
+/// Logged events after `cursor`, or none and the newest cursor when there is no cursor yet
+///
+/// Subscribers keep the cursor of the last event they handled and, after
+/// lagging behind or reconnecting, ask for what came after it.
+pub fn events_since(store: &EventStore, cursor: Option<i64>, limit: usize) -> Result<EventPage, StoreError> {
+    let (oldest, newest) = store.event_log_bounds()?;
+    let Some(cursor) = cursor else {
+        return Ok(EventPage { cursor: newest, ..Default::default() });
+    };
+    let limit = limit.clamp(1, EVENT_PAGE_LIMIT);
+
+    let rows = store.get_logged_events(cursor, limit)?;
+    // Rows that don't parse are skipped over rather than asked for again
+    let last_seq = rows.last().map(|(seq, _, _)| *seq).unwrap_or(cursor);
+    let more = rows.len() == limit && last_seq < newest;
+    let events: Vec<LoggedEvent> = rows
+        .into_iter()
+        .filter_map(|(seq, at, payload)| match serde_json::from_str(&payload) {
+            Ok(event) => Some(LoggedEvent { seq, at, event }),
+            Err(e) => {
+                tracing::warn!("Skipping logged event {}: {}", seq, e);
+                None
+            }
+        })
+        .collect();
+    Ok(EventPage {
+        cursor: last_seq,
+        more,
+        missed: oldest > 0 && cursor + 1 < oldest,
+        events,
+    })
+}
+
+/// Task state after reconciling the store at startup
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReconciledSnapshot {
+    /// Tasks still active, which the UI should show as running
+    pub active: Vec<StoredTask>,
+    /// Tasks a previous run left active that have now been canceled
+    pub expired: Vec<String>,
+}
+
+/// A task running longer than its tool's threshold, sent as `task-overdue`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OverdueTask {
+    pub task_id: String,
+    pub session_id: String,
+    pub tool: String,
+    pub description: Option<String>,
+    pub elapsed_ms: i64,
+    /// The threshold the task passed
+    pub threshold_ms: u64,
+    /// Past the second threshold, so plugins were told too
+    pub escalated: bool,
+    /// Whether the UI should play a sound
+    pub sound: bool,
+}
+
+/// How far past its thresholds a task has been reported
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum OverdueLevel {
+    Warned,
+    Escalated,
 }
 
 /// Event router that processes events and dispatches to subscribers
 pub struct EventRouter {
     store: Arc<EventStore>,
-    #[allow(dead_code)]
-    config: Arc<Config>,  // Reserved for future plugin configuration
-    sender: broadcast::Sender<AppEvent>,
+    config: Arc<Config>,
+    clock: Arc<dyn Clock>,
+    redactor: Redactor,
+    priorities: PriorityPolicy,
+    sender: broadcast::Sender<LoggedEvent>,
+    /// Active tasks already reported overdue, so each level is reported once
+    overdue: Mutex<HashMap<String, OverdueLevel>>,
 }
 
 impl EventRouter {
     /// Create a new event router
     pub fn new(store: Arc<EventStore>, config: Arc<Config>) -> Self {
+        Self::with_clock(store, config, Arc::new(SystemClock))
+    }
+
+    /// Create a router that reads the time from `clock`
+    pub fn with_clock(store: Arc<EventStore>, config: Arc<Config>, clock: Arc<dyn Clock>) -> Self {
         let (sender, _) = broadcast::channel(256);
-        Self { store, config, sender }
+        let redactor = Redactor::new(&config.redaction);
+        let priorities = PriorityPolicy::new(&config.priority, &config.rules);
+        Self { store, config, clock, redactor, priorities, sender, overdue: Mutex::new(HashMap::new()) }
+    }
+
+    /// The clock the router reads the time from
+    pub fn clock(&self) -> Arc<dyn Clock> {
+        self.clock.clone()
     }
 
     /// Get a receiver for subscribing to events
-    pub fn subscribe(&self) -> broadcast::Receiver<AppEvent> {
+    pub fn subscribe(&self) -> broadcast::Receiver<LoggedEvent> {
         self.sender.subscribe()
     }
 
+    /// Log an event for catch-up, then broadcast it
+    fn publish(&self, event: AppEvent) {
+        let at = self.clock.now_ms();
+        let seq = if event.is_transient() {
+            0
+        } else {
+            let logged = serde_json::to_value(&event).map_err(|e| e.to_string()).and_then(|value| {
+                let kind = value["type"].as_str().unwrap_or_default().to_string();
+                timed_write(|| self.store.log_event(&kind, &value.to_string(), at)).map_err(|e| e.to_string())
+            });
+            logged.unwrap_or_else(|e| {
+                tracing::error!("Failed to log event: {}", e);
+                0
+            })
+        };
+        let _ = self.sender.send(LoggedEvent { seq, at, event });
+    }
+
+    /// Logged events after `cursor`; see [`events_since`]
+    pub fn events_since(&self, cursor: Option<i64>, limit: usize) -> Result<EventPage, StoreError> {
+        events_since(&self.store, cursor, limit)
+    }
+
     /// Process a watcher event and dispatch to subscribers
-    pub fn process_watcher_event(&self, event: WatcherEvent) {
-        match event {
+    ///
+    /// Returns the event with secrets redacted, which is what callers should
+    /// pass on to the UI.
+    pub fn process_watcher_event(&self, event: WatcherEvent) -> WatcherEvent {
+        health::metrics().record_event();
+        let event = match event {
+            WatcherEvent::TaskEvent(task_event) if task_event.event_type == "rate_limited" => {
+                WatcherEvent::UsageWarning(usage_warning_from_hook(task_event))
+            }
+            WatcherEvent::TaskEvent(mut task_event) => {
+                task_event.description = task_event.description.as_deref().map(text::clean_description);
+                WatcherEvent::TaskEvent(task_event)
+            }
+            other => other,
+        };
+        let mut event = self.redactor.redact_event(event);
+        if let WatcherEvent::TaskEvent(task_event) = &mut event {
+            if task_event.event_type == "task_started" && task_event.eta_ms.is_none() {
+                task_event.eta_ms = self.estimate_duration(task_event);
+            }
+            let priority = self.priorities.assign(task_event, &self.store);
+            task_event.priority = Some(priority.name().to_string());
+        }
+        if let WatcherEvent::TodosUpdated(todos) = &mut event {
+            // Keep the list in the order the user put it in
+            match self.store.get_todo_order() {
+                Ok(order) => watcher::sort_todos(todos, &order),
+                Err(e) => tracing::warn!("Failed to read todo order: {}", e),
+            }
+        }
+        match event.clone() {
             WatcherEvent::TaskEvent(task_event) => {
                 self.handle_task_event(task_event);
             }
             WatcherEvent::TodosUpdated(todos) => {
-                let _ = self.sender.send(AppEvent::TodosUpdated(todos));
+                self.publish(AppEvent::TodosUpdated(todos));
+            }
+            WatcherEvent::TodosChanged(diff) => {
+                self.record_todo_transitions(&diff);
+                if !diff.hidden.is_empty() {
+                    if let Err(e) = timed_write(|| todo_sync::hide(&self.store, &diff.hidden)) {
+                        tracing::error!("Failed to mark hidden todos: {}", e);
+                    }
+                }
+                self.publish(AppEvent::TodosChanged(diff));
             }
             WatcherEvent::DownloadProgress(progress) => {
-                let _ = self.sender.send(AppEvent::DownloadProgress(progress));
+                self.publish(AppEvent::DownloadProgress(progress));
+            }
+            WatcherEvent::GenericProgress(progress) => {
+                self.publish(AppEvent::GenericProgress(progress));
+            }
+            WatcherEvent::ContextUsage(usage) => {
+                if let Err(e) = timed_write(|| self.store.insert_context_sample(&usage.sample)) {
+                    tracing::error!("Failed to store context sample: {}", e);
+                }
+            }
+            WatcherEvent::TokenUsage(usage) => {
+                if let Err(e) = timed_write(|| self.store.insert_token_usage(&usage)) {
+                    tracing::error!("Failed to store token usage: {}", e);
+                }
+            }
+            WatcherEvent::UsageWarning(warning) => {
+                tracing::warn!("Agent hit a {}: {}", warning.kind, warning.message);
+                if let Err(e) = timed_write(|| self.store.insert_usage_warning(&warning)) {
+                    tracing::error!("Failed to store usage warning: {}", e);
+                }
+                self.publish(AppEvent::UsageWarning(warning));
             }
             WatcherEvent::Error(e) => {
                 tracing::error!("Watcher error: {}", e);
             }
         }
+        event
+    }
+
+    /// Cancel tasks that have been active for longer than `stale_task_threshold_ms`
+    ///
+    /// A task whose end event never arrived (the agent crashed or the hook
+    /// didn't fire) would otherwise stay active forever. Returns the cancel
+    /// events, which callers pass on to the UI like any other event.
+    pub fn expire_stale_tasks(&self) -> Vec<TaskEvent> {
+        let threshold = self.config.behavior.stale_task_threshold_ms;
+        if threshold == 0 {
+            return Vec::new();
+        }
+
+        let now = self.clock.now_ms();
+        let stale = match self.store.get_active_tasks_before(now - threshold as i64) {
+            Ok(tasks) => tasks,
+            Err(e) => {
+                tracing::error!("Failed to load stale tasks: {}", e);
+                return Vec::new();
+            }
+        };
+
+        stale
+            .into_iter()
+            .filter_map(|task| {
+                tracing::info!("Canceling stale task {} ({})", task.id, task.tool);
+                let mut event = TaskEvent::new("task_canceled", &task.id, now.max(0) as u64);
+                event.session_id = Some(task.session_id);
+                event.source = Some(task.source);
+                match self.process_watcher_event(WatcherEvent::TaskEvent(event)) {
+                    WatcherEvent::TaskEvent(event) => Some(event),
+                    _ => None,
+                }
+            })
+            .collect()
+    }
+
+    /// Report active tasks that have run past their tool's thresholds
+    ///
+    /// Each task is reported once on passing the first threshold and once
+    /// more on passing the second, when it is also handed to plugins. Returns
+    /// the reports for the UI.
+    pub fn check_overdue_tasks(&self) -> Vec<OverdueTask> {
+        let config = &self.config.overdue;
+        if !config.enabled {
+            return Vec::new();
+        }
+
+        let active = match self.store.get_active_tasks() {
+            Ok(tasks) => tasks,
+            Err(e) => {
+                tracing::error!("Failed to load active tasks: {}", e);
+                return Vec::new();
+            }
+        };
+        let Ok(mut reported) = self.overdue.lock() else {
+            return Vec::new();
+        };
+        reported.retain(|id, _| active.iter().any(|task| task.id == *id));
+
+        let now = self.clock.now_ms();
+        let mut overdue = Vec::new();
+        for task in active {
+            let Some(threshold) = config.threshold_for(&task.tool) else {
+                continue;
+            };
+            let elapsed_ms = now - task.started_at;
+            let passed = |secs: u64| secs > 0 && elapsed_ms >= (secs * 1000) as i64;
+            let (level, threshold_secs) = if passed(threshold.escalate_secs) {
+                (OverdueLevel::Escalated, threshold.escalate_secs)
+            } else if passed(threshold.warn_secs) {
+                (OverdueLevel::Warned, threshold.warn_secs)
+            } else {
+                continue;
+            };
+            if reported.get(&task.id).is_some_and(|previous| *previous >= level) {
+                continue;
+            }
+            reported.insert(task.id.clone(), level);
+
+            tracing::info!("Task {} ({}) has been running for {}s", task.id, task.tool, elapsed_ms / 1000);
+            let report = OverdueTask {
+                task_id: task.id,
+                session_id: task.session_id,
+                tool: task.tool,
+                description: task.description,
+                elapsed_ms,
+                threshold_ms: threshold_secs * 1000,
+                escalated: level == OverdueLevel::Escalated,
+                sound: config.sound,
+            };
+            if report.escalated {
+                self.publish(AppEvent::TaskOverdue(report.clone()));
+            }
+            overdue.push(report);
+        }
+        overdue
+    }
+
+    /// Bring the store up to date with a fresh start
+    ///
+    /// A previous run that crashed or was killed leaves its unfinished tasks
+    /// active. Those past the stale threshold are canceled; the rest may still
+    /// be running in an agent that outlived the overlay, so they are kept.
+    pub fn reconcile(&self) -> ReconciledSnapshot {
+        let expired = self.expire_stale_tasks().into_iter().map(|event| event.task_id).collect();
+        let active = self.store.get_active_tasks().unwrap_or_else(|e| {
+            tracing::error!("Failed to load active tasks: {}", e);
+            Vec::new()
+        });
+        ReconciledSnapshot { active, expired }
     }
 
     /// Handle a task event
     fn handle_task_event(&self, event: TaskEvent) {
         match event.event_type.as_str() {
             "task_started" => {
+                // Record where the session is running
+                if let (Some(session_id), Some(project_path)) = (&event.session_id, &event.project_path) {
+                    self.record_session_location(session_id, project_path, event.timestamp as i64);
+                }
+
                 // Store the task
                 let stored_task = StoredTask {
                     id: event.task_id.clone(),
@@ -73,28 +433,44 @@ impl EventRouter {
                     started_at: event.timestamp as i64,
                     ended_at: None,
                     duration_ms: None,
+                    duration_suspect: false,
                     is_background: event.background.unwrap_or(false),
                     subagent_type: event.subagent_type.clone(),
+                    source: event.source.clone().unwrap_or_else(|| CLAUDE_CODE_SOURCE.to_string()),
+                    reviewed: false,
+                    pinned: false,
                 };
 
-                if let Err(e) = self.store.insert_task(&stored_task) {
+                if let Err(e) = timed_write(|| self.store.insert_task(&stored_task)) {
                     tracing::error!("Failed to store task: {}", e);
                 }
+                if let Some(model) = &event.model {
+                    if let Err(e) = timed_write(|| self.store.set_task_model(&event.task_id, model)) {
+                        tracing::error!("Failed to store task model: {}", e);
+                    }
+                }
+                if let Some(pid) = event.pid.filter(|_| stored_task.is_background) {
+                    if let Err(e) = timed_write(|| self.store.set_task_pid(&event.task_id, pid)) {
+                        tracing::error!("Failed to store task pid: {}", e);
+                    }
+                }
 
-                let _ = self.sender.send(AppEvent::TaskStarted(event));
+                self.publish(AppEvent::TaskStarted(event));
             }
 
             "task_complete" => {
                 // Update task in store
-                if let Err(e) = self.store.update_task_status(
+                if let Err(e) = timed_write(|| self.store.update_task_status(
                     &event.task_id,
                     "completed",
                     event.timestamp as i64,
-                ) {
+                )) {
                     tracing::error!("Failed to update task: {}", e);
                 }
+                self.record_exit_code(&event);
 
-                let _ = self.sender.send(AppEvent::TaskCompleted {
+                self.publish(AppEvent::TaskCompleted {
+                    priority: Priority::of(&event),
                     task_id: event.task_id,
                     timestamp: event.timestamp,
                 });
@@ -102,15 +478,17 @@ impl EventRouter {
 
             "task_error" => {
                 // Update task in store
-                if let Err(e) = self.store.update_task_status(
+                if let Err(e) = timed_write(|| self.store.update_task_status(
                     &event.task_id,
                     "error",
                     event.timestamp as i64,
-                ) {
+                )) {
                     tracing::error!("Failed to update task: {}", e);
                 }
+                self.record_exit_code(&event);
 
-                let _ = self.sender.send(AppEvent::TaskError {
+                self.publish(AppEvent::TaskError {
+                    priority: Priority::of(&event),
                     task_id: event.task_id,
                     timestamp: event.timestamp,
                 });
@@ -118,44 +496,240 @@ impl EventRouter {
 
             "task_canceled" => {
                 // Update task in store
-                if let Err(e) = self.store.update_task_status(
+                if let Err(e) = timed_write(|| self.store.update_task_status(
                     &event.task_id,
                     "canceled",
                     event.timestamp as i64,
-                ) {
+                )) {
                     tracing::error!("Failed to update task: {}", e);
                 }
 
-                let _ = self.sender.send(AppEvent::TaskCanceled {
+                self.publish(AppEvent::TaskCanceled {
                     task_id: event.task_id,
                 });
             }
 
             "session_stopped" => {
-                let _ = self.sender.send(AppEvent::SessionStopped {
+                if let Some(session_id) = &event.session_id {
+                    if let Err(e) = timed_write(|| self.store.upsert_session(&StoredSession {
+                        id: session_id.clone(),
+                        started_at: event.timestamp as i64,
+                        ended_at: Some(event.timestamp as i64),
+                        project_path: event.project_path.clone(),
+                        branch: None,
+                    })) {
+                        tracing::error!("Failed to end session: {}", e);
+                    }
+                }
+
+                self.publish(AppEvent::SessionStopped {
                     session_id: event.session_id,
                 });
             }
 
+            "awaiting_input" => {
+                self.publish(AppEvent::AwaitingInput(event));
+            }
+
+            "custom" => {
+                let Some(custom_type) = event.custom_type.clone().filter(|t| !t.trim().is_empty()) else {
+                    tracing::warn!("Ignoring custom event {} without a custom_type", event.task_id);
+                    return;
+                };
+                let custom = CustomEvent {
+                    id: 0,
+                    custom_type,
+                    task_id: Some(event.task_id.clone()).filter(|id| !id.is_empty()),
+                    session_id: event.session_id.clone(),
+                    payload: event.payload.clone().unwrap_or(serde_json::Value::Null),
+                    source: event.source.clone(),
+                    timestamp: event.timestamp as i64,
+                };
+                if let Err(e) = timed_write(|| self.store.insert_custom_event(&custom)) {
+                    tracing::error!("Failed to store custom event: {}", e);
+                }
+
+                self.publish(AppEvent::Custom(event));
+            }
+
             other => {
                 tracing::warn!("Unknown event type: {}", other);
             }
         }
     }
 
+    /// How long a starting task will likely take, judging by similar ones
+    fn estimate_duration(&self, event: &TaskEvent) -> Option<u64> {
+        let (Some(tool), Some(description)) = (&event.tool, &event.description) else {
+            return None;
+        };
+        match self.store.estimate_duration(tool, &text::description_prefix(description)) {
+            Ok(estimate) => estimate.map(|ms| ms.max(0) as u64),
+            Err(e) => {
+                tracing::error!("Failed to estimate task duration: {}", e);
+                None
+            }
+        }
+    }
+
+    /// The flat todo list for the UI, with duplicates collapsed if the config says so
+    pub fn collapse_todos(&self, todos: Vec<GlobalTodoItem>) -> Vec<GlobalTodoItem> {
+        if self.config.behavior.collapse_duplicate_todos {
+            watcher::collapse_duplicate_todos(todos)
+        } else {
+            todos
+        }
+    }
+
+    /// Todos grouped by session and labeled with each session's project
+    pub fn todo_groups(&self, todos: &[GlobalTodoItem]) -> Vec<TodoGroup> {
+        let mut sessions = HashMap::new();
+        for todo in todos {
+            if sessions.contains_key(&todo.session_id) {
+                continue;
+            }
+            match self.store.get_session(&todo.session_id) {
+                Ok(Some(session)) => {
+                    sessions.insert(todo.session_id.clone(), session);
+                }
+                Ok(None) => {}
+                Err(e) => tracing::warn!("Failed to load session {}: {}", todo.session_id, e),
+            }
+        }
+        watcher::group_todos(todos, &sessions)
+    }
+
+    /// Store each todo's new status, for burndown charts
+    fn record_todo_transitions(&self, diff: &TodosDiff) {
+        let at = self.clock.now_ms();
+        let completed: HashSet<(&str, &str)> = diff.completed.iter()
+            .map(|todo| (todo.session_id.as_str(), todo.content.as_str()))
+            .collect();
+        let transition = |todo: &GlobalTodoItem, status: &str| TodoTransition {
+            session_id: todo.session_id.clone(),
+            content: todo.content.clone(),
+            status: status.to_string(),
+            at,
+        };
+
+        let mut transitions: Vec<TodoTransition> = diff.added.iter().chain(&diff.updated)
+            .map(|todo| transition(todo, &todo.status))
+            .collect();
+        transitions.extend(diff.removed.iter().map(|todo| {
+            let done = completed.contains(&(todo.session_id.as_str(), todo.content.as_str()));
+            transition(todo, if done { "completed" } else { "removed" })
+        }));
+        if transitions.is_empty() {
+            return;
+        }
+
+        if let Err(e) = timed_write(|| self.store.insert_todo_transitions(&transitions)) {
+            tracing::error!("Failed to store todo changes: {}", e);
+        }
+
+        // Completed todos leave the live view, so keep them in the archive
+        let done: Vec<(String, String, String)> = diff.completed.iter()
+            .map(|todo| (todo.session_id.clone(), todo.content.clone(), todo.active_form.clone()))
+            .collect();
+        if done.is_empty() {
+            return;
+        }
+        if let Err(e) = timed_write(|| self.store.archive_completed_todos(&done, at)) {
+            tracing::error!("Failed to archive completed todos: {}", e);
+        }
+    }
+
+    /// Store the session's project path and current git branch the first time it is seen
+    fn record_session_location(&self, session_id: &str, project_path: &str, timestamp: i64) {
+        match self.store.get_session(session_id) {
+            Ok(Some(session)) if session.project_path.is_some() => return,
+            Err(e) => {
+                tracing::error!("Failed to load session: {}", e);
+                return;
+            }
+            _ => {}
+        }
+
+        let branch = git::find_git_dir(std::path::Path::new(project_path))
+            .and_then(|git_dir| git::current_branch(&git_dir));
+
+        if let Err(e) = timed_write(|| self.store.upsert_session(&StoredSession {
+            id: session_id.to_string(),
+            started_at: timestamp,
+            ended_at: None,
+            project_path: Some(project_path.to_string()),
+            branch,
+        })) {
+            tracing::error!("Failed to store session: {}", e);
+        }
+    }
+
+    /// Store the exit code an end event reports, if it reports one
+    fn record_exit_code(&self, event: &TaskEvent) {
+        if let Some(exit_code) = event.exit_code {
+            if let Err(e) = timed_write(|| self.store.set_task_exit_code(&event.task_id, exit_code)) {
+                tracing::error!("Failed to store task exit code: {}", e);
+            }
+        }
+    }
+
     /// Get task statistics from the store
     pub fn get_stats(&self) -> Result<crate::store::TaskStats, StoreError> {
         self.store.get_task_stats()
     }
 
     /// Search tasks
-    pub fn search_tasks(&self, query: &str, limit: usize) -> Result<Vec<StoredTask>, StoreError> {
-        self.store.search_tasks(query, limit)
+    pub fn search_tasks(&self, query: &str, filter: &TaskFilter, limit: usize) -> Result<Vec<StoredTask>, StoreError> {
+        self.store.search_tasks(query, filter, limit)
     }
 
     /// Get recent tasks from history
-    pub fn get_recent_tasks(&self, limit: usize) -> Result<Vec<StoredTask>, StoreError> {
-        self.store.get_recent_tasks(limit)
+    pub fn get_recent_tasks(&self, filter: &TaskFilter, limit: usize) -> Result<Vec<StoredTask>, StoreError> {
+        self.store.get_recent_tasks(filter, limit)
+    }
+}
+
+/// The warning a `rate_limited` hook event stands for, its description the message
+fn usage_warning_from_hook(event: TaskEvent) -> UsageWarning {
+    let message = event.description.as_deref().map(text::clean_description).unwrap_or_default();
+    UsageWarning {
+        session_id: event.session_id,
+        kind: transcript::limit_kind(&message).unwrap_or("rate_limit").to_string(),
+        message,
+        resets_at: None,
+        timestamp: event.timestamp as i64,
+        source: Some(event.source.unwrap_or_else(|| CLAUDE_CODE_SOURCE.to_string())),
+    }
+}
+
+/// Run a store write, recording how long it took
+fn timed_write<T>(write: impl FnOnce() -> T) -> T {
+    let started = Instant::now();
+    let result = write();
+    health::metrics().record_db_write(started.elapsed());
+    result
+}
+
+/// A plugin's declared capabilities next to what the config grants it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginPermissions {
+    pub name: String,
+    pub requested: Vec<Capability>,
+    pub granted: Vec<Capability>,
+    /// Requested but not granted; the plugin is not loaded while this is non-empty
+    pub missing: Vec<Capability>,
+}
+
+impl PluginPermissions {
+    pub fn new(plugin: &dyn Plugin, granted: Vec<Capability>) -> Self {
+        let requested = plugin.capabilities().to_vec();
+        let missing = requested.iter().filter(|c| !granted.contains(c)).copied().collect();
+        Self {
+            name: plugin.name().to_string(),
+            requested,
+            granted,
+            missing,
+        }
     }
 }
 
@@ -168,6 +742,10 @@ pub trait Plugin: Send + Sync {
     /// Plugin version
     fn version(&self) -> &str;
 
+    /// Capabilities the plugin needs; each must be granted in `[plugins.permissions]`,
+    /// and the plugin checks its `Grants` before each use
+    fn capabilities(&self) -> &[Capability];
+
     /// Called when the plugin is initialized
     async fn on_init(&mut self) -> Result<(), PluginError>;
 
@@ -184,6 +762,7 @@ pub enum PluginError {
     InitError(String),
     EventError(String),
     ShutdownError(String),
+    PermissionDenied(String),
 }
 
 impl std::fmt::Display for PluginError {
@@ -192,66 +771,294 @@ impl std::fmt::Display for PluginError {
             PluginError::InitError(e) => write!(f, "Plugin init error: {}", e),
             PluginError::EventError(e) => write!(f, "Plugin event error: {}", e),
             PluginError::ShutdownError(e) => write!(f, "Plugin shutdown error: {}", e),
+            PluginError::PermissionDenied(e) => write!(f, "Plugin permission denied: {}", e),
         }
     }
 }
 
 impl std::error::Error for PluginError {}
 
+/// Token bucket limiting how often a plugin receives events
+struct TokenBucket {
+    capacity: f64,
+    tokens: f64,
+    rate_per_sec: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    fn new(limit: &PluginLimitConfig, now: Instant) -> Self {
+        let capacity = limit.burst.max(1) as f64;
+        Self {
+            capacity,
+            tokens: capacity,
+            rate_per_sec: limit.rate_per_sec.max(0.0),
+            updated: now,
+        }
+    }
+
+    /// Take a token if one is available
+    fn try_take(&mut self, now: Instant) -> bool {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate_per_sec).min(self.capacity);
+        self.updated = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Events waiting for one plugin before further ones are dead-lettered
+///
+/// Larger than a page of the event log, so catching up after a lag fits.
+const PLUGIN_QUEUE_CAPACITY: usize = 2 * EVENT_PAGE_LIMIT;
+
+/// A registered plugin with its delivery limits
+struct PluginSlot {
+    plugin: Box<dyn Plugin>,
+    timeout: Duration,
+    bucket: TokenBucket,
+}
+
+impl PluginSlot {
+    /// Hand an event to the plugin within its limits, dead-lettering it if that fails
+    async fn deliver(&mut self, store: &EventStore, event: &AppEvent) {
+        let name = self.plugin.name();
+        let reason = if event.is_rate_limited() && !self.bucket.try_take(Instant::now()) {
+            tracing::debug!("Plugin {} rate limited", name);
+            "rate limited".to_string()
+        } else {
+            let started = Instant::now();
+            let result = tokio::time::timeout(self.timeout, self.plugin.on_event(event)).await;
+            health::metrics().record_plugin(name, started.elapsed(), !matches!(result, Ok(Ok(()))));
+            match result {
+                Ok(Ok(())) => return,
+                Ok(Err(e)) => {
+                    tracing::error!("Plugin {} error: {}", name, e);
+                    e.to_string()
+                }
+                Err(_) => {
+                    tracing::warn!("Plugin {} timed out after {}ms", name, self.timeout.as_millis());
+                    format!("timed out after {}ms", self.timeout.as_millis())
+                }
+            }
+        };
+        dead_letter(store, name, event, &reason);
+    }
+
+    /// Deliver queued events until the queue closes, then shut the plugin down
+    async fn work(mut self, mut queue: mpsc::Receiver<Arc<AppEvent>>, store: Arc<EventStore>) {
+        while let Some(event) = queue.recv().await {
+            self.deliver(&store, &event).await;
+        }
+        if let Err(e) = self.plugin.on_shutdown().await {
+            tracing::error!("Plugin {} shutdown error: {}", self.plugin.name(), e);
+        }
+    }
+}
+
+fn dead_letter(store: &EventStore, name: &str, event: &AppEvent, reason: &str) {
+    let payload = serde_json::to_string(event).unwrap_or_default();
+    if let Err(e) = store.insert_dead_letter(name, &payload, reason) {
+        tracing::error!("Failed to record dead letter for {}: {}", name, e);
+    }
+}
+
+/// A running plugin's queue
+struct PluginQueue {
+    name: String,
+    sender: mpsc::Sender<Arc<AppEvent>>,
+}
+
 /// Plugin manager for loading and running plugins
+///
+/// While running, each plugin receives events on its own task from a bounded
+/// queue, and each delivery is bounded by the plugin's timeout and rate limit,
+/// so one slow or noisy plugin can't hold up the others. Events that are
+/// dropped, because a queue is full or a plugin is rate limited, or whose
+/// delivery fails are recorded in the store's dead-letter queue. When the
+/// manager falls so far behind that the broadcast drops events, the missed
+/// ones are read back from the event log; those the log can't return are
+/// dead-lettered too.
 pub struct PluginManager {
-    plugins: Vec<Box<dyn Plugin>>,
-    event_rx: broadcast::Receiver<AppEvent>,
+    plugins: Vec<PluginSlot>,
+    queues: Vec<PluginQueue>,
+    event_rx: broadcast::Receiver<LoggedEvent>,
+    /// The last logged event delivered
+    cursor: i64,
+    config: Arc<Config>,
+    store: Arc<EventStore>,
 }
 
 impl PluginManager {
-    /// Create a new plugin manager
+    /// Create a new plugin manager using the router's permission grants and limits
     pub fn new(router: &EventRouter) -> Self {
         Self {
             plugins: Vec::new(),
+            queues: Vec::new(),
             event_rx: router.subscribe(),
+            cursor: router.store.event_log_bounds().map(|(_, newest)| newest).unwrap_or(0),
+            config: router.config.clone(),
+            store: router.store.clone(),
         }
     }
 
-    /// Register a plugin
-    pub fn register(&mut self, plugin: Box<dyn Plugin>) {
+    /// Register a plugin, refusing it unless every capability it declares is granted
+    pub fn register(&mut self, plugin: Box<dyn Plugin>) -> Result<(), PluginError> {
+        let permissions = PluginPermissions::new(plugin.as_ref(), self.config.plugins.granted(plugin.name()));
+        if !permissions.missing.is_empty() {
+            return Err(PluginError::PermissionDenied(format!(
+                "{} needs {:?}; grant it in [plugins.permissions]",
+                permissions.name, permissions.missing
+            )));
+        }
+
+        let limit = self.config.plugins.limit_for(plugin.name());
         tracing::info!("Registered plugin: {} v{}", plugin.name(), plugin.version());
-        self.plugins.push(plugin);
+        self.plugins.push(PluginSlot {
+            plugin,
+            timeout: Duration::from_millis(limit.timeout_ms),
+            bucket: TokenBucket::new(&limit, Instant::now()),
+        });
+        Ok(())
     }
 
     /// Initialize all plugins
     pub async fn init_all(&mut self) -> Result<(), PluginError> {
-        for plugin in &mut self.plugins {
-            plugin.on_init().await?;
+        for slot in &mut self.plugins {
+            slot.plugin.on_init().await?;
         }
         Ok(())
     }
 
-    /// Start processing events (runs in a loop)
-    pub async fn run(&mut self) {
+    /// Process events until the router goes away or `stop` is signaled
+    ///
+    /// On a stop, events already queued are still delivered before the plugins
+    /// shut down; callers decide how long to wait for that.
+    pub async fn run(&mut self, mut stop: watch::Receiver<bool>) {
+        let mut workers = Vec::new();
+        for slot in self.plugins.drain(..) {
+            let (sender, queue) = mpsc::channel(PLUGIN_QUEUE_CAPACITY);
+            self.queues.push(PluginQueue { name: slot.plugin.name().to_string(), sender });
+            workers.push(tokio::spawn(slot.work(queue, self.store.clone())));
+        }
+
         loop {
-            match self.event_rx.recv().await {
-                Ok(event) => {
-                    for plugin in &self.plugins {
-                        if let Err(e) = plugin.on_event(&event).await {
-                            tracing::error!("Plugin {} error: {}", plugin.name(), e);
-                        }
+            tokio::select! {
+                received = self.event_rx.recv() => match received {
+                    Ok(logged) => self.receive(logged),
+                    Err(broadcast::error::RecvError::Lagged(n)) => self.catch_up(n).await,
+                    Err(broadcast::error::RecvError::Closed) => {
+                        tracing::info!("Event channel closed, shutting down plugins");
+                        break;
                     }
-                }
-                Err(broadcast::error::RecvError::Lagged(n)) => {
-                    tracing::warn!("Plugin manager lagged by {} events", n);
-                }
-                Err(broadcast::error::RecvError::Closed) => {
-                    tracing::info!("Event channel closed, shutting down plugins");
+                },
+                _ = stop.changed() => {
+                    self.drain().await;
                     break;
                 }
             }
         }
 
-        // Shutdown plugins
-        for plugin in &self.plugins {
-            if let Err(e) = plugin.on_shutdown().await {
-                tracing::error!("Plugin {} shutdown error: {}", plugin.name(), e);
+        // Closing the queues shuts each plugin down once it has delivered what's in its queue
+        self.queues.clear();
+        for worker in workers {
+            if let Err(e) = worker.await {
+                tracing::error!("Plugin task failed: {}", e);
+            }
+        }
+    }
+
+    /// Queue the events already broadcast
+    async fn drain(&mut self) {
+        loop {
+            match self.event_rx.try_recv() {
+                Ok(logged) => self.receive(logged),
+                Err(broadcast::error::TryRecvError::Lagged(n)) => self.catch_up(n).await,
+                Err(_) => break,
+            }
+        }
+    }
+
+    /// Queue a broadcast event, unless catching up already queued it
+    fn receive(&mut self, logged: LoggedEvent) {
+        if logged.seq > 0 {
+            if logged.seq <= self.cursor {
+                return;
+            }
+            self.cursor = logged.seq;
+        }
+        self.deliver(logged.event);
+    }
+
+    /// Queue the logged events after the last one queued, once the broadcast dropped `skipped`
+    ///
+    /// Progress updates aren't logged, and the oldest events may have been
+    /// pruned from the log, so a plugin is told about those through its dead letters.
+    async fn catch_up(&mut self, skipped: u64) {
+        tracing::warn!("Plugin manager lagged by {} events; catching up from the log", skipped);
+        let lost = serde_json::json!({ "type": "lagged", "skipped": skipped });
+        let mut missed = false;
+        loop {
+            let (store, cursor) = (self.store.clone(), self.cursor);
+            let read = tokio::task::spawn_blocking(move || events_since(&store, Some(cursor), EVENT_PAGE_LIMIT))
+                .await
+                .map_err(|e| e.to_string())
+                .and_then(|page| page.map_err(|e| e.to_string()));
+            let page = match read {
+                Ok(page) => page,
+                Err(e) => {
+                    tracing::error!("Failed to read missed events: {}", e);
+                    self.dead_letter_all(&lost, &format!("lagged; the event log couldn't be read: {}", e));
+                    return;
+                }
+            };
+            missed |= page.missed;
+            for logged in page.events {
+                self.deliver(logged.event);
+            }
+            self.cursor = page.cursor;
+            if !page.more {
+                break;
+            }
+        }
+
+        let reason = if missed {
+            tracing::warn!("Some events plugins missed are no longer in the log");
+            format!("lagged by {} events; progress updates and events no longer in the log were lost", skipped)
+        } else {
+            format!("lagged by {} events; any progress updates among them were lost", skipped)
+        };
+        self.dead_letter_all(&lost, &reason);
+    }
+
+    /// Record a dead letter for every running plugin
+    fn dead_letter_all(&self, payload: &serde_json::Value, reason: &str) {
+        let payload = payload.to_string();
+        for queue in &self.queues {
+            if let Err(e) = self.store.insert_dead_letter(&queue.name, &payload, reason) {
+                tracing::error!("Failed to record dead letter for {}: {}", queue.name, e);
+            }
+        }
+    }
+
+    /// Queue an event for every running plugin, dead-lettering it for those whose queue is full
+    fn deliver(&self, event: AppEvent) {
+        let event = Arc::new(event);
+        for queue in &self.queues {
+            match queue.sender.try_send(event.clone()) {
+                Ok(()) => {}
+                Err(mpsc::error::TrySendError::Full(_)) => {
+                    tracing::warn!("Plugin {} queue is full", queue.name);
+                    dead_letter(&self.store, &queue.name, &event, "queue full");
+                }
+                Err(mpsc::error::TrySendError::Closed(_)) => {
+                    dead_letter(&self.store, &queue.name, &event, "plugin stopped");
+                }
             }
         }
     }
@@ -260,6 +1067,7 @@ impl PluginManager {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::plugins::Grants;
     use crate::store::EventStore;
 
     #[test]
@@ -271,4 +1079,329 @@ mod tests {
         // Should be able to subscribe
         let _rx = router.subscribe();
     }
+
+    #[test]
+    fn test_expire_stale_tasks() {
+        use crate::clock::ManualClock;
+
+        let store = Arc::new(EventStore::in_memory().unwrap());
+        let mut config = Config::default();
+        config.behavior.stale_task_threshold_ms = 60_000;
+        let clock = Arc::new(ManualClock::new(1_000_000));
+        let router = EventRouter::with_clock(store.clone(), Arc::new(config), clock.clone());
+
+        let mut started = TaskEvent::new("task_started", "task-1", 1_000_000);
+        started.tool = Some("Bash".to_string());
+        router.process_watcher_event(WatcherEvent::TaskEvent(started));
+
+        clock.advance(Duration::from_secs(59));
+        assert!(router.expire_stale_tasks().is_empty());
+
+        clock.advance(Duration::from_secs(2));
+        let expired = router.expire_stale_tasks();
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].event_type, "task_canceled");
+        assert_eq!(expired[0].timestamp, 1_061_000);
+
+        let task = store.get_task("task-1").unwrap().unwrap();
+        assert_eq!(task.status, "canceled");
+        assert_eq!(task.duration_ms, Some(61_000));
+        assert!(router.expire_stale_tasks().is_empty());
+    }
+
+    #[test]
+    fn test_overdue_tasks() {
+        use crate::clock::ManualClock;
+        use crate::config::OverdueThreshold;
+
+        let store = Arc::new(EventStore::in_memory().unwrap());
+        let mut config = Config::default();
+        config.overdue.thresholds.insert("Read".to_string(), OverdueThreshold { warn_secs: 0, escalate_secs: 0 });
+        let clock = Arc::new(ManualClock::new(0));
+        let router = EventRouter::with_clock(store, Arc::new(config), clock.clone());
+        let mut rx = router.subscribe();
+
+        for (id, tool) in [("test", "Bash"), ("read", "Read")] {
+            let mut started = TaskEvent::new("task_started", id, 0);
+            started.tool = Some(tool.to_string());
+            router.process_watcher_event(WatcherEvent::TaskEvent(started));
+        }
+        while rx.try_recv().is_ok() {}
+
+        clock.advance(Duration::from_secs(119));
+        assert!(router.check_overdue_tasks().is_empty());
+
+        // Flagged once at the first threshold, without telling plugins
+        clock.advance(Duration::from_secs(1));
+        let overdue = router.check_overdue_tasks();
+        assert_eq!(overdue.len(), 1);
+        assert_eq!((overdue[0].task_id.as_str(), overdue[0].escalated), ("test", false));
+        assert!(router.check_overdue_tasks().is_empty());
+        assert!(rx.try_recv().is_err());
+
+        clock.advance(Duration::from_secs(120));
+        let overdue = router.check_overdue_tasks();
+        assert_eq!(overdue.len(), 1);
+        assert!(overdue[0].escalated);
+        assert!(matches!(rx.try_recv().map(|logged| logged.event), Ok(AppEvent::TaskOverdue(task)) if task.threshold_ms == 240_000));
+        assert!(router.check_overdue_tasks().is_empty());
+    }
+
+    #[test]
+    fn test_reconcile_after_restart() {
+        use crate::clock::ManualClock;
+
+        let store = Arc::new(EventStore::in_memory().unwrap());
+        let mut config = Config::default();
+        config.behavior.stale_task_threshold_ms = 60_000;
+        let config = Arc::new(config);
+
+        // The previous run saw two tasks start and then went away
+        let previous = EventRouter::with_clock(store.clone(), config.clone(), Arc::new(ManualClock::new(0)));
+        previous.process_watcher_event(WatcherEvent::TaskEvent(TaskEvent::new("task_started", "old", 0)));
+        previous.process_watcher_event(WatcherEvent::TaskEvent(TaskEvent::new("task_started", "recent", 50_000)));
+
+        let router = EventRouter::with_clock(store, config, Arc::new(ManualClock::new(70_000)));
+        let snapshot = router.reconcile();
+        assert_eq!(snapshot.expired, ["old"]);
+        let active: Vec<&str> = snapshot.active.iter().map(|t| t.id.as_str()).collect();
+        assert_eq!(active, ["recent"]);
+    }
+
+    #[test]
+    fn test_eta_from_similar_tasks() {
+        let store = Arc::new(EventStore::in_memory().unwrap());
+        let router = EventRouter::new(store, Arc::new(Config::default()));
+        let start = |id: &str, description: &str, timestamp: u64| {
+            let mut event = TaskEvent::new("task_started", id, timestamp);
+            event.tool = Some("Bash".to_string());
+            event.description = Some(description.to_string());
+            match router.process_watcher_event(WatcherEvent::TaskEvent(event)) {
+                WatcherEvent::TaskEvent(event) => event.eta_ms,
+                _ => None,
+            }
+        };
+
+        for (index, duration) in [40_000, 45_000, 90_000].into_iter().enumerate() {
+            let started = index as u64 * 100_000;
+            assert_eq!(start(&format!("run-{}", index), "npm test -- --watch", started), None);
+            router.process_watcher_event(WatcherEvent::TaskEvent(TaskEvent::new("task_complete", &format!("run-{}", index), started + duration)));
+        }
+        // "npm testing" is different work that only shares the characters
+        start("other", "npm testing", 400_000);
+        router.process_watcher_event(WatcherEvent::TaskEvent(TaskEvent::new("task_complete", "other", 900_000)));
+
+        assert_eq!(start("next", "npm test", 1_000_000), Some(45_000));
+        assert_eq!(start("build", "npm run build", 1_000_000), None);
+    }
+
+    struct ShellPlugin;
+
+    #[async_trait::async_trait]
+    impl Plugin for ShellPlugin {
+        fn name(&self) -> &str { "shell" }
+        fn version(&self) -> &str { "0.1.0" }
+        fn capabilities(&self) -> &[Capability] { &[Capability::Shell, Capability::Network] }
+        async fn on_init(&mut self) -> Result<(), PluginError> { Ok(()) }
+        async fn on_event(&self, _event: &AppEvent) -> Result<(), PluginError> { Ok(()) }
+        async fn on_shutdown(&self) -> Result<(), PluginError> { Ok(()) }
+    }
+
+    #[test]
+    fn test_plugin_permissions() {
+        let store = Arc::new(EventStore::in_memory().unwrap());
+        let mut config = Config::default();
+        config.plugins.permissions.insert("shell".to_string(), vec![Capability::Network]);
+
+        let router = EventRouter::new(store.clone(), Arc::new(config.clone()));
+        let mut manager = PluginManager::new(&router);
+        assert!(matches!(manager.register(Box::new(ShellPlugin)), Err(PluginError::PermissionDenied(_))));
+
+        config.plugins.permissions.insert("shell".to_string(), vec![Capability::Network, Capability::Shell]);
+        let router = EventRouter::new(store, Arc::new(config.clone()));
+        let mut manager = PluginManager::new(&router);
+        manager.register(Box::new(ShellPlugin)).unwrap();
+
+        // What a plugin does is checked against its grants too, not only what it declares
+        let grants = Grants::new("shell", &config.plugins);
+        assert!(grants.require(Capability::Shell).is_ok());
+        assert!(matches!(grants.require(Capability::Filesystem), Err(PluginError::PermissionDenied(_))));
+    }
+
+    #[test]
+    fn test_token_bucket() {
+        let start = Instant::now();
+        let limit = PluginLimitConfig { rate_per_sec: 2.0, burst: 2, ..Default::default() };
+        let mut bucket = TokenBucket::new(&limit, start);
+
+        assert!(bucket.try_take(start));
+        assert!(bucket.try_take(start));
+        assert!(!bucket.try_take(start));
+        assert!(bucket.try_take(start + Duration::from_millis(500)));
+        assert!(!bucket.try_take(start + Duration::from_millis(600)));
+    }
+
+    struct CountingPlugin(Arc<std::sync::atomic::AtomicUsize>);
+
+    #[async_trait::async_trait]
+    impl Plugin for CountingPlugin {
+        fn name(&self) -> &str { "counting" }
+        fn version(&self) -> &str { "0.1.0" }
+        fn capabilities(&self) -> &[Capability] { &[] }
+        async fn on_init(&mut self) -> Result<(), PluginError> { Ok(()) }
+        async fn on_event(&self, _event: &AppEvent) -> Result<(), PluginError> {
+            self.0.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Ok(())
+        }
+        async fn on_shutdown(&self) -> Result<(), PluginError> { Ok(()) }
+    }
+
+    #[test]
+    fn test_stop_delivers_queued_events() {
+        let store = Arc::new(EventStore::in_memory().unwrap());
+        let router = EventRouter::new(store, Arc::new(Config::default()));
+        let mut manager = PluginManager::new(&router);
+        let delivered = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        manager.register(Box::new(CountingPlugin(delivered.clone()))).unwrap();
+
+        for i in 0..3 {
+            router.process_watcher_event(WatcherEvent::TaskEvent(TaskEvent::new("task_canceled", &format!("t{}", i), 0)));
+        }
+        let (stop_tx, stop_rx) = watch::channel(false);
+        stop_tx.send(true).unwrap();
+
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_time().build().unwrap();
+        runtime.block_on(manager.run(stop_rx));
+        assert_eq!(delivered.load(std::sync::atomic::Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn test_lagged_plugins_catch_up_from_the_log() {
+        let store = Arc::new(EventStore::in_memory().unwrap());
+        let router = EventRouter::new(store.clone(), Arc::new(Config::default()));
+        let mut manager = PluginManager::new(&router);
+        let delivered = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        manager.register(Box::new(CountingPlugin(delivered.clone()))).unwrap();
+
+        // More than the broadcast holds, so the oldest are dropped from it
+        for i in 0..300 {
+            router.process_watcher_event(WatcherEvent::TaskEvent(TaskEvent::new("task_canceled", &format!("t{}", i), 0)));
+        }
+        let (stop_tx, stop_rx) = watch::channel(false);
+        stop_tx.send(true).unwrap();
+
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_time().build().unwrap();
+        runtime.block_on(manager.run(stop_rx));
+        assert_eq!(delivered.load(std::sync::atomic::Ordering::SeqCst), 300);
+        let letters = store.get_dead_letters(Some("counting"), 10).unwrap();
+        assert_eq!(letters.len(), 1);
+        assert!(letters[0].reason.starts_with("lagged by"));
+    }
+
+    #[test]
+    fn test_events_since() {
+        let store = Arc::new(EventStore::in_memory().unwrap());
+        let router = EventRouter::new(store, Arc::new(Config::default()));
+        for i in 0..3 {
+            router.process_watcher_event(WatcherEvent::TaskEvent(TaskEvent::new("task_canceled", &format!("t{}", i), 0)));
+        }
+        router.process_watcher_event(WatcherEvent::GenericProgress(GenericProgress {
+            id: "build".to_string(),
+            label: None,
+            percent: Some(50.0),
+            message: None,
+        }));
+
+        // A new subscriber starts from the newest event
+        let page = router.events_since(None, 10).unwrap();
+        assert!(page.events.is_empty());
+        assert_eq!(page.cursor, 3);
+
+        let page = router.events_since(Some(1), 1).unwrap();
+        assert_eq!(page.events.len(), 1);
+        assert!(matches!(&page.events[0].event, AppEvent::TaskCanceled { task_id } if task_id == "t1"));
+        assert!(page.more);
+        let page = router.events_since(Some(page.cursor), 10).unwrap();
+        assert_eq!((page.events.len(), page.cursor, page.more, page.missed), (1, 3, false, false));
+    }
+
+    #[test]
+    fn test_events_are_given_a_priority() {
+        let store = Arc::new(EventStore::in_memory().unwrap());
+        let router = EventRouter::new(store, Arc::new(Config::default()));
+        let mut rx = router.subscribe();
+
+        let WatcherEvent::TaskEvent(error) = router.process_watcher_event(WatcherEvent::TaskEvent(TaskEvent::new("task_error", "t1", 0))) else {
+            panic!("expected a task event");
+        };
+        assert_eq!(error.priority.as_deref(), Some("high"));
+        assert!(matches!(rx.try_recv().unwrap().event, AppEvent::TaskError { priority: Priority::High, .. }));
+    }
+
+    #[test]
+    fn test_custom_events_are_stored_and_published() {
+        let store = Arc::new(EventStore::in_memory().unwrap());
+        let router = EventRouter::new(store.clone(), Arc::new(Config::default()));
+        let mut rx = router.subscribe();
+
+        let mut event = TaskEvent::new("custom", "", 1_000);
+        event.custom_type = Some("deploy_finished".to_string());
+        event.payload = Some(serde_json::json!({"env": "prod"}));
+        router.process_watcher_event(WatcherEvent::TaskEvent(event));
+        // Without a type there's nothing to match it on
+        router.process_watcher_event(WatcherEvent::TaskEvent(TaskEvent::new("custom", "", 2_000)));
+
+        let stored = store.get_custom_events(None, 10).unwrap();
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[0].custom_type, "deploy_finished");
+        assert_eq!(stored[0].task_id, None);
+        assert_eq!(stored[0].payload["env"], "prod");
+        assert!(matches!(rx.try_recv().unwrap().event, AppEvent::Custom(e) if e.custom_type.as_deref() == Some("deploy_finished")));
+        assert!(rx.try_recv().is_err());
+    }
+
+    struct SlowPlugin;
+
+    #[async_trait::async_trait]
+    impl Plugin for SlowPlugin {
+        fn name(&self) -> &str { "slow" }
+        fn version(&self) -> &str { "0.1.0" }
+        fn capabilities(&self) -> &[Capability] { &[] }
+        async fn on_init(&mut self) -> Result<(), PluginError> { Ok(()) }
+        async fn on_event(&self, _event: &AppEvent) -> Result<(), PluginError> {
+            tokio::time::sleep(Duration::from_secs(5)).await;
+            Ok(())
+        }
+        async fn on_shutdown(&self) -> Result<(), PluginError> { Ok(()) }
+    }
+
+    #[test]
+    fn test_slow_and_limited_deliveries_are_dead_lettered() {
+        let store = Arc::new(EventStore::in_memory().unwrap());
+        let mut config = Config::default();
+        config.plugins.limits.insert("slow".to_string(), PluginLimitConfig {
+            timeout_ms: 10,
+            rate_per_sec: 0.0,
+            burst: 1,
+        });
+        let router = EventRouter::new(store.clone(), Arc::new(config));
+        let mut manager = PluginManager::new(&router);
+        manager.register(Box::new(SlowPlugin)).unwrap();
+
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_time().build().unwrap();
+        runtime.block_on(async {
+            let slot = &mut manager.plugins[0];
+            let event = AppEvent::Custom(TaskEvent::new("custom", "task-1", 0));
+            slot.deliver(&store, &event).await;
+            slot.deliver(&store, &event).await;
+            // Lifecycle events aren't rate limited
+            slot.deliver(&store, &AppEvent::TaskCanceled { task_id: "task-1".to_string() }).await;
+        });
+
+        let letters = store.get_dead_letters(Some("slow"), 10).unwrap();
+        let reasons: Vec<&str> = letters.iter().map(|l| l.reason.as_str()).collect();
+        assert_eq!(reasons, ["timed out after 10ms", "rate limited", "timed out after 10ms"]);
+        assert!(letters[0].event.contains("task_canceled"));
+        assert!(letters[1].event.contains("custom"));
+    }
 }
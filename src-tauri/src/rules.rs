@@ -0,0 +1,364 @@
+//! Rules engine for matching router events
+//!
+//! Rules are declared under `[[rules]]` in the config file and referenced by
+//! name from plugins. Events are flattened into an [`EventContext`] (looking up
+//! the stored task where the event itself only carries an id) before matching.
+//! Custom events also match on their type and on keys of their payload.
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::config::RuleConfig;
+use crate::priority::Priority;
+use crate::router::AppEvent;
+use crate::store::EventStore;
+use crate::watcher::TaskEvent;
+
+/// Flattened view of an event that rules and templates operate on
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct EventContext {
+    pub event_type: String,
+    pub task_id: Option<String>,
+    pub session_id: Option<String>,
+    pub tool: Option<String>,
+    pub description: Option<String>,
+    pub duration_ms: Option<i64>,
+    pub timestamp: u64,
+    pub source: Option<String>,
+    /// Set for task and custom events once the router has assigned one
+    pub priority: Option<Priority>,
+    /// Set for custom events only
+    pub custom_type: Option<String>,
+    pub payload: Option<Value>,
+}
+
+impl EventContext {
+    /// Build a context for task, session, overdue, usage warning and custom events; other events aren't matchable
+    pub fn from_event(event: &AppEvent, store: &EventStore) -> Option<Self> {
+        let (event_type, task_id, timestamp, priority) = match event {
+            AppEvent::TaskStarted(e) | AppEvent::AwaitingInput(e) => {
+                return Some(Self {
+                    event_type: e.event_type.clone(),
+                    task_id: Some(e.task_id.clone()),
+                    session_id: e.session_id.clone(),
+                    tool: e.tool.clone(),
+                    description: e.description.clone(),
+                    duration_ms: None,
+                    timestamp: e.timestamp,
+                    source: e.source.clone(),
+                    priority: e.priority.as_deref().and_then(Priority::parse),
+                    ..Default::default()
+                });
+            }
+            AppEvent::Custom(e) => {
+                return Some(Self {
+                    event_type: e.event_type.clone(),
+                    task_id: Some(e.task_id.clone()).filter(|id| !id.is_empty()),
+                    session_id: e.session_id.clone(),
+                    description: e.description.clone(),
+                    timestamp: e.timestamp,
+                    source: e.source.clone(),
+                    priority: e.priority.as_deref().and_then(Priority::parse),
+                    custom_type: e.custom_type.clone(),
+                    payload: e.payload.clone(),
+                    ..Default::default()
+                });
+            }
+            AppEvent::TaskCompleted { task_id, timestamp, priority } => ("task_complete", task_id, *timestamp, Some(*priority)),
+            AppEvent::TaskError { task_id, timestamp, priority } => ("task_error", task_id, *timestamp, Some(*priority)),
+            AppEvent::TaskCanceled { task_id } => ("task_canceled", task_id, 0, None),
+            AppEvent::SessionStopped { session_id } => {
+                return Some(Self {
+                    event_type: "session_stopped".to_string(),
+                    session_id: session_id.clone(),
+                    ..Default::default()
+                });
+            }
+            AppEvent::TaskOverdue(task) => {
+                return Some(Self {
+                    event_type: "task_overdue".to_string(),
+                    task_id: Some(task.task_id.clone()),
+                    session_id: Some(task.session_id.clone()),
+                    tool: Some(task.tool.clone()),
+                    description: task.description.clone(),
+                    duration_ms: Some(task.elapsed_ms),
+                    ..Default::default()
+                });
+            }
+            AppEvent::UsageWarning(warning) => {
+                return Some(Self {
+                    event_type: "usage_warning".to_string(),
+                    session_id: warning.session_id.clone(),
+                    description: Some(warning.message.clone()),
+                    timestamp: warning.timestamp.max(0) as u64,
+                    source: warning.source.clone(),
+                    ..Default::default()
+                });
+            }
+            _ => return None,
+        };
+
+        let task = store.get_task(task_id).ok().flatten();
+
+        Some(Self {
+            event_type: event_type.to_string(),
+            task_id: Some(task_id.clone()),
+            session_id: task.as_ref().map(|t| t.session_id.clone()),
+            tool: task.as_ref().map(|t| t.tool.clone()),
+            description: task.as_ref().and_then(|t| t.description.clone()),
+            duration_ms: task.as_ref().and_then(|t| t.duration_ms),
+            timestamp,
+            source: task.as_ref().map(|t| t.source.clone()),
+            priority,
+            ..Default::default()
+        })
+    }
+
+    /// Build a context for an event as a source reported it, before the router turns it into an [`AppEvent`]
+    pub fn from_task_event(event: &TaskEvent, store: &EventStore) -> Option<Self> {
+        let (task_id, timestamp) = (event.task_id.clone(), event.timestamp);
+        let priority = Priority::of(event);
+        let routed = match event.event_type.as_str() {
+            "task_complete" => AppEvent::TaskCompleted { task_id, timestamp, priority },
+            "task_error" => AppEvent::TaskError { task_id, timestamp, priority },
+            "task_canceled" => AppEvent::TaskCanceled { task_id },
+            "session_stopped" => AppEvent::SessionStopped { session_id: event.session_id.clone() },
+            "custom" => AppEvent::Custom(event.clone()),
+            _ => AppEvent::TaskStarted(event.clone()),
+        };
+        let mut ctx = Self::from_event(&routed, store)?;
+        // Only a priority the event really has counts, not the default filled in above
+        ctx.priority = event.priority.as_deref().and_then(Priority::parse);
+        Some(ctx)
+    }
+
+    /// Value substituted for a `{{name}}` template placeholder
+    fn field(&self, name: &str) -> Option<String> {
+        match name {
+            "event" => Some(self.event_type.clone()),
+            "task_id" => self.task_id.clone(),
+            "session_id" => self.session_id.clone(),
+            "tool" => self.tool.clone(),
+            "description" => self.description.clone(),
+            "duration_ms" => self.duration_ms.map(|d| d.to_string()),
+            "timestamp" => Some(self.timestamp.to_string()),
+            "source" => self.source.clone(),
+            "priority" => self.priority.map(|p| p.name().to_string()),
+            "custom_type" => self.custom_type.clone(),
+            _ => name.strip_prefix("payload.").and_then(|key| self.payload_value(key)),
+        }
+    }
+
+    /// A payload value by dotted key, as text
+    fn payload_value(&self, key: &str) -> Option<String> {
+        let value = key.split('.').try_fold(self.payload.as_ref()?, |value, part| value.get(part))?;
+        match value {
+            Value::Null => None,
+            Value::String(text) => Some(text.clone()),
+            other => Some(other.to_string()),
+        }
+    }
+}
+
+/// Whether an event satisfies every condition of a rule
+pub fn matches(rule: &RuleConfig, ctx: &EventContext) -> bool {
+    if !rule.events.is_empty() && !rule.events.iter().any(|e| e == &ctx.event_type) {
+        return false;
+    }
+
+    if !rule.tools.is_empty() {
+        match &ctx.tool {
+            Some(tool) if rule.tools.iter().any(|t| t.eq_ignore_ascii_case(tool)) => {}
+            _ => return false,
+        }
+    }
+
+    if let Some(needle) = &rule.description_contains {
+        let haystack = ctx.description.as_deref().unwrap_or("").to_lowercase();
+        if !haystack.contains(&needle.to_lowercase()) {
+            return false;
+        }
+    }
+
+    if let Some(min) = rule.min_duration_ms {
+        if ctx.duration_ms.is_none_or(|d| d < min as i64) {
+            return false;
+        }
+    }
+
+    if let Some(min) = &rule.min_priority {
+        match (Priority::parse(min), ctx.priority) {
+            (Some(min), Some(priority)) if priority >= min => {}
+            _ => return false,
+        }
+    }
+
+    if !rule.custom_types.is_empty() {
+        match &ctx.custom_type {
+            Some(custom_type) if rule.custom_types.iter().any(|t| t.eq_ignore_ascii_case(custom_type)) => {}
+            _ => return false,
+        }
+    }
+
+    rule.payload.iter().all(|(key, expected)| match ctx.payload_value(key) {
+        Some(value) => expected == "*" || &value == expected,
+        None => false,
+    })
+}
+
+/// Look up a rule by name
+pub fn find<'a>(rules: &'a [RuleConfig], name: &str) -> Option<&'a RuleConfig> {
+    rules.iter().find(|r| r.name == name)
+}
+
+/// How `render_template` escapes the values it substitutes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Escape {
+    /// Plain text, as is
+    None,
+    /// URL-encoded, for URLs and query strings
+    Url,
+    /// Escaped for the inside of a JSON string, for JSON bodies
+    Json,
+}
+
+impl Escape {
+    fn apply(self, value: &str) -> String {
+        match self {
+            Escape::None => value.to_string(),
+            Escape::Url => urlencoding::encode(value).into_owned(),
+            Escape::Json => {
+                let quoted = serde_json::Value::String(value.to_string()).to_string();
+                quoted[1..quoted.len() - 1].to_string()
+            }
+        }
+    }
+}
+
+/// Replace `{{field}}` placeholders, escaping the values as `escape` says
+pub fn render_template(template: &str, ctx: &EventContext, escape: Escape) -> String {
+    let mut output = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find("{{") {
+        output.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+
+        match after.find("}}") {
+            Some(end) => {
+                let value = ctx.field(after[..end].trim()).unwrap_or_default();
+                output.push_str(&escape.apply(&value));
+                rest = &after[end + 2..];
+            }
+            None => {
+                output.push_str(&rest[start..]);
+                rest = "";
+            }
+        }
+    }
+
+    output.push_str(rest);
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    fn rule() -> RuleConfig {
+        RuleConfig {
+            name: "tests-failed".to_string(),
+            events: vec!["task_error".to_string()],
+            tools: vec!["bash".to_string()],
+            description_contains: Some("TEST".to_string()),
+            min_duration_ms: None,
+            min_priority: None,
+            custom_types: Vec::new(),
+            payload: HashMap::new(),
+        }
+    }
+
+    fn ctx(event_type: &str, description: &str) -> EventContext {
+        EventContext {
+            event_type: event_type.to_string(),
+            task_id: Some("t1".to_string()),
+            tool: Some("Bash".to_string()),
+            description: Some(description.to_string()),
+            duration_ms: Some(1500),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_rule_matching() {
+        assert!(matches(&rule(), &ctx("task_error", "npm test")));
+        assert!(!matches(&rule(), &ctx("task_complete", "npm test")));
+        assert!(!matches(&rule(), &ctx("task_error", "npm install")));
+
+        let mut slow = rule();
+        slow.min_duration_ms = Some(2000);
+        assert!(!matches(&slow, &ctx("task_error", "npm test")));
+
+        let mut urgent = rule();
+        urgent.min_priority = Some("urgent".to_string());
+        let mut high = ctx("task_error", "npm test");
+        assert!(!matches(&urgent, &high));
+        high.priority = Some(Priority::High);
+        assert!(!matches(&urgent, &high));
+        urgent.min_priority = Some("high".to_string());
+        assert!(matches(&urgent, &high));
+    }
+
+    #[test]
+    fn test_render_template() {
+        let ctx = ctx("task_error", "npm test & lint");
+        assert_eq!(
+            render_template("{{tool}} failed: {{description}} ({{ duration_ms }}ms){{missing}}", &ctx, Escape::None),
+            "Bash failed: npm test & lint (1500ms)"
+        );
+        assert_eq!(render_template("q={{description}}", &ctx, Escape::Url), "q=npm%20test%20%26%20lint");
+        assert_eq!(render_template("open {{tool", &ctx, Escape::None), "open {{tool");
+    }
+
+    #[test]
+    fn test_render_json_body() {
+        let ctx = ctx("task_error", "grep \"a\\b\"\nfailed");
+        let body = render_template(r#"{"text": "{{description}}"}"#, &ctx, Escape::Json);
+        let parsed: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(parsed["text"], "grep \"a\\b\"\nfailed");
+    }
+
+    #[test]
+    fn test_custom_event_matching() {
+        let mut event = crate::watcher::TaskEvent::new("custom", "", 1_000);
+        event.custom_type = Some("deploy_finished".to_string());
+        event.payload = Some(serde_json::json!({"env": "prod", "build": {"ok": false, "minutes": 4}}));
+        let store = EventStore::in_memory().unwrap();
+        let ctx = EventContext::from_event(&AppEvent::Custom(event), &store).unwrap();
+        assert_eq!(ctx.task_id, None);
+
+        let mut failed_deploys = RuleConfig {
+            name: "failed-deploys".to_string(),
+            events: vec!["custom".to_string()],
+            tools: Vec::new(),
+            description_contains: None,
+            min_duration_ms: None,
+            min_priority: None,
+            custom_types: vec!["Deploy_Finished".to_string()],
+            payload: HashMap::from([("build.ok".to_string(), "false".to_string()), ("env".to_string(), "*".to_string())]),
+        };
+        assert!(matches(&failed_deploys, &ctx));
+        failed_deploys.payload.insert("region".to_string(), "*".to_string());
+        assert!(!matches(&failed_deploys, &ctx));
+        failed_deploys.payload.clear();
+        failed_deploys.custom_types = vec!["lint_report".to_string()];
+        assert!(!matches(&failed_deploys, &ctx));
+
+        assert_eq!(
+            render_template("{{custom_type}} to {{payload.env}} took {{payload.build.minutes}}m{{payload.build.missing}}", &ctx, Escape::None),
+            "deploy_finished to prod took 4m"
+        );
+    }
+}
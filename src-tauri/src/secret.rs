@@ -0,0 +1,92 @@
+//! Per-install tokens local clients present before the core trusts them
+//!
+//! The core's sockets listen on `127.0.0.1`, where any local process, including
+//! a web page's, can reach them. A token kept in a file only the user can read
+//! shows a client runs as that user. It is made on first use and stays the same
+//! afterwards, so every process of the install agrees on it.
+
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+use std::time::Duration;
+
+/// Random bytes in a token
+const TOKEN_BYTES: usize = 32;
+/// How long to wait for another process that is writing the token file
+const WRITE_WAIT: Duration = Duration::from_millis(10);
+const WRITE_ATTEMPTS: usize = 100;
+
+/// A new random token, as hex
+pub fn generate() -> io::Result<String> {
+    let mut bytes = [0u8; TOKEN_BYTES];
+    getrandom::getrandom(&mut bytes).map_err(io::Error::other)?;
+    Ok(bytes.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+/// The token in the file at `path`, creating it with a new one if there is none
+///
+/// A new file is readable by the user only on Unix. Two processes starting
+/// together end up with the same token: only one of them can create the file.
+pub fn load_or_create(path: &Path) -> io::Result<String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    match options.open(path) {
+        Ok(mut file) => {
+            let token = generate()?;
+            file.write_all(token.as_bytes())?;
+            Ok(token)
+        }
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+            // The process that created it may still be writing it
+            for _ in 0..WRITE_ATTEMPTS {
+                let token = fs::read_to_string(path)?.trim().to_string();
+                if !token.is_empty() {
+                    return Ok(token);
+                }
+                std::thread::sleep(WRITE_WAIT);
+            }
+            Err(io::Error::other(format!("{} is empty", path.display())))
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Compare without stopping at the first differing byte
+pub fn tokens_match(given: &str, expected: &str) -> bool {
+    given.len() == expected.len()
+        && given.bytes().zip(expected.bytes()).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_is_created_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nested").join("core-token");
+
+        let token = load_or_create(&path).unwrap();
+        assert_eq!(token.len(), TOKEN_BYTES * 2);
+        assert_eq!(load_or_create(&path).unwrap(), token);
+        assert_ne!(generate().unwrap(), token);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        }
+
+        assert!(tokens_match(&token, &token));
+        assert!(!tokens_match(&token, &token[1..]));
+        assert!(!tokens_match("", &token));
+    }
+}
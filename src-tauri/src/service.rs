@@ -0,0 +1,321 @@
+//! Login service - keeps the headless core running while the user is logged in
+//!
+//! `--install-service` registers `<this executable> --headless` with the
+//! platform's service manager, which starts it at login and restarts it when
+//! it fails:
+//!
+//! - Linux: a systemd user unit
+//! - macOS: a LaunchAgent
+//! - Windows: a Task Scheduler task run at logon
+//!
+//! A Windows service proper runs in a session of its own, where it can't see
+//! the user's `.claude` directory without their password, so a logon task with
+//! restart on failure stands in for one. `--uninstall-service` removes
+//! whichever was installed. Overlays launched afterwards attach to the core.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Command-line flags for installing and removing the service
+const INSTALL_FLAG: &str = "--install-service";
+const UNINSTALL_FLAG: &str = "--uninstall-service";
+
+/// Name the service is registered under
+const SERVICE_NAME: &str = "agent-progress-core";
+/// LaunchAgent label, which launchd expects in reverse-DNS form
+const LAUNCHD_LABEL: &str = "com.agent-progress.core";
+/// Seconds the service manager waits before restarting a failed core
+const RESTART_DELAY_SECS: u32 = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ServiceAction {
+    Install,
+    Uninstall,
+}
+
+/// The service action the app was started with, if any
+pub fn requested(mut args: impl Iterator<Item = String>) -> Option<ServiceAction> {
+    args.find_map(|arg| match arg.as_str() {
+        INSTALL_FLAG => Some(ServiceAction::Install),
+        UNINSTALL_FLAG => Some(ServiceAction::Uninstall),
+        _ => None,
+    })
+}
+
+/// Run `action`, returning what was done
+pub fn run(action: ServiceAction) -> Result<String, String> {
+    let exe = std::env::current_exe().map_err(|e| format!("Can't find this executable: {}", e))?;
+    match action {
+        ServiceAction::Install => install(&exe),
+        ServiceAction::Uninstall => uninstall(),
+    }
+}
+
+/// Run a service manager command, failing with its stderr when it fails
+fn command(program: &str, args: &[&str]) -> Result<(), String> {
+    let output = Command::new(program)
+        .args(args)
+        .output()
+        .map_err(|e| format!("Failed to run {}: {}", program, e))?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(format!(
+            "{} {} failed: {}",
+            program,
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+    }
+}
+
+fn write_file(path: &Path, contents: &str) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    std::fs::write(path, contents).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+#[cfg_attr(windows, allow(dead_code))]
+fn remove_file(path: &Path) -> Result<(), String> {
+    match std::fs::remove_file(path) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(format!("Failed to remove {}: {}", path.display(), e)),
+    }
+}
+
+#[cfg_attr(windows, allow(dead_code))]
+fn home() -> Result<PathBuf, String> {
+    dirs::home_dir().ok_or_else(|| "Can't find the home directory".to_string())
+}
+
+// ============================================================================
+// Linux: systemd user unit
+// ============================================================================
+
+/// The unit file installed on Linux
+pub fn systemd_unit(exe: &Path) -> String {
+    format!(
+        "[Unit]\n\
+         Description=Agent Progress Overlay core\n\
+         \n\
+         [Service]\n\
+         ExecStart=\"{}\" --headless\n\
+         Restart=on-failure\n\
+         RestartSec={}\n\
+         \n\
+         [Install]\n\
+         WantedBy=default.target\n",
+        exe.display(),
+        RESTART_DELAY_SECS
+    )
+}
+
+#[cfg(target_os = "linux")]
+fn unit_path() -> Result<PathBuf, String> {
+    Ok(home()?.join(".config/systemd/user").join(format!("{}.service", SERVICE_NAME)))
+}
+
+#[cfg(target_os = "linux")]
+fn install(exe: &Path) -> Result<String, String> {
+    let path = unit_path()?;
+    write_file(&path, &systemd_unit(exe))?;
+    let unit = format!("{}.service", SERVICE_NAME);
+    command("systemctl", &["--user", "daemon-reload"])?;
+    command("systemctl", &["--user", "enable", "--now", &unit])?;
+    Ok(format!("Installed and started {}", path.display()))
+}
+
+#[cfg(target_os = "linux")]
+fn uninstall() -> Result<String, String> {
+    let path = unit_path()?;
+    let unit = format!("{}.service", SERVICE_NAME);
+    // Not enabled is fine; the unit file may be all that is left
+    let _ = command("systemctl", &["--user", "disable", "--now", &unit]);
+    remove_file(&path)?;
+    command("systemctl", &["--user", "daemon-reload"])?;
+    Ok(format!("Removed {}", path.display()))
+}
+
+// ============================================================================
+// macOS: LaunchAgent
+// ============================================================================
+
+/// The LaunchAgent installed on macOS, logging to `log_dir`
+pub fn launch_agent_plist(exe: &Path, log_dir: &Path) -> String {
+    let log = xml_escape(&log_dir.join("core.log").to_string_lossy());
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+    <key>Label</key>
+    <string>{label}</string>
+    <key>ProgramArguments</key>
+    <array>
+        <string>{exe}</string>
+        <string>--headless</string>
+    </array>
+    <key>RunAtLoad</key>
+    <true/>
+    <key>KeepAlive</key>
+    <dict>
+        <key>SuccessfulExit</key>
+        <false/>
+    </dict>
+    <key>ThrottleInterval</key>
+    <integer>{delay}</integer>
+    <key>StandardErrorPath</key>
+    <string>{log}</string>
+</dict>
+</plist>
+"#,
+        label = LAUNCHD_LABEL,
+        exe = xml_escape(&exe.to_string_lossy()),
+        delay = RESTART_DELAY_SECS,
+        log = log,
+    )
+}
+
+#[cfg(target_os = "macos")]
+fn plist_path() -> Result<PathBuf, String> {
+    Ok(home()?.join("Library/LaunchAgents").join(format!("{}.plist", LAUNCHD_LABEL)))
+}
+
+#[cfg(target_os = "macos")]
+fn install(exe: &Path) -> Result<String, String> {
+    let path = plist_path()?;
+    let log_dir = home()?.join("Library/Logs/agent-progress-overlay");
+    std::fs::create_dir_all(&log_dir).map_err(|e| format!("Failed to create {}: {}", log_dir.display(), e))?;
+    write_file(&path, &launch_agent_plist(exe, &log_dir))?;
+    let path_arg = path.to_string_lossy();
+    // Reinstalling replaces an agent that is already loaded
+    let _ = command("launchctl", &["unload", &path_arg]);
+    command("launchctl", &["load", "-w", &path_arg])?;
+    Ok(format!("Installed and started {}", path.display()))
+}
+
+#[cfg(target_os = "macos")]
+fn uninstall() -> Result<String, String> {
+    let path = plist_path()?;
+    let _ = command("launchctl", &["unload", "-w", &path.to_string_lossy()]);
+    remove_file(&path)?;
+    Ok(format!("Removed {}", path.display()))
+}
+
+// ============================================================================
+// Windows: Task Scheduler logon task
+// ============================================================================
+
+/// The Task Scheduler definition registered on Windows
+pub fn scheduled_task_xml(exe: &Path) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<Task version="1.2" xmlns="http://schemas.microsoft.com/windows/2004/02/mit/task">
+  <RegistrationInfo>
+    <Description>Agent Progress Overlay core</Description>
+  </RegistrationInfo>
+  <Triggers>
+    <LogonTrigger>
+      <Enabled>true</Enabled>
+    </LogonTrigger>
+  </Triggers>
+  <Principals>
+    <Principal id="Author">
+      <LogonType>InteractiveToken</LogonType>
+      <RunLevel>LeastPrivilege</RunLevel>
+    </Principal>
+  </Principals>
+  <Settings>
+    <MultipleInstancesPolicy>IgnoreNew</MultipleInstancesPolicy>
+    <DisallowStartIfOnBatteries>false</DisallowStartIfOnBatteries>
+    <StopIfGoingOnBatteries>false</StopIfGoingOnBatteries>
+    <ExecutionTimeLimit>PT0S</ExecutionTimeLimit>
+    <RestartOnFailure>
+      <Interval>PT1M</Interval>
+      <Count>999</Count>
+    </RestartOnFailure>
+    <Hidden>true</Hidden>
+  </Settings>
+  <Actions Context="Author">
+    <Exec>
+      <Command>{exe}</Command>
+      <Arguments>--headless</Arguments>
+    </Exec>
+  </Actions>
+</Task>
+"#,
+        exe = xml_escape(&exe.to_string_lossy()),
+    )
+}
+
+#[cfg(windows)]
+fn install(exe: &Path) -> Result<String, String> {
+    let xml_path = std::env::temp_dir().join(format!("{}.xml", SERVICE_NAME));
+    write_file(&xml_path, &scheduled_task_xml(exe))?;
+    let created = command(
+        "schtasks",
+        &["/Create", "/TN", SERVICE_NAME, "/XML", &xml_path.to_string_lossy(), "/F"],
+    );
+    let _ = std::fs::remove_file(&xml_path);
+    created?;
+    command("schtasks", &["/Run", "/TN", SERVICE_NAME])?;
+    Ok(format!("Installed and started the {} logon task", SERVICE_NAME))
+}
+
+#[cfg(windows)]
+fn uninstall() -> Result<String, String> {
+    // Not running is fine; the task may be registered but idle
+    let _ = command("schtasks", &["/End", "/TN", SERVICE_NAME]);
+    command("schtasks", &["/Delete", "/TN", SERVICE_NAME, "/F"])?;
+    Ok(format!("Removed the {} logon task", SERVICE_NAME))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+fn install(_exe: &Path) -> Result<String, String> {
+    Err("Installing the core as a service isn't supported on this platform".to_string())
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+fn uninstall() -> Result<String, String> {
+    Err("Installing the core as a service isn't supported on this platform".to_string())
+}
+
+fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_requested() {
+        let args = |list: &[&str]| list.iter().map(|s| s.to_string()).collect::<Vec<_>>().into_iter();
+        assert_eq!(requested(args(&["--install-service"])), Some(ServiceAction::Install));
+        assert_eq!(requested(args(&["--portable", "--uninstall-service"])), Some(ServiceAction::Uninstall));
+        assert_eq!(requested(args(&["--headless"])), None);
+    }
+
+    #[test]
+    fn test_definitions_run_headless_and_restart() {
+        let exe = Path::new("/opt/Agent Progress & Co/overlay");
+
+        let unit = systemd_unit(exe);
+        assert!(unit.contains("ExecStart=\"/opt/Agent Progress & Co/overlay\" --headless"));
+        assert!(unit.contains("Restart=on-failure"));
+
+        let plist = launch_agent_plist(exe, Path::new("/tmp/logs"));
+        assert!(plist.contains("<string>/opt/Agent Progress &amp; Co/overlay</string>"));
+        assert!(plist.contains("<key>SuccessfulExit</key>"));
+
+        let task = scheduled_task_xml(exe);
+        assert!(task.contains("<Command>/opt/Agent Progress &amp; Co/overlay</Command>"));
+        assert!(task.contains("<RestartOnFailure>"));
+    }
+}
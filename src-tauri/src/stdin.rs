@@ -0,0 +1,113 @@
+//! Stdin ingestion - events piped into the process
+//!
+//! Started with `--stdin`, the overlay reads events in the events file format,
+//! one JSON object per line, from its standard input:
+//!
+//! ```text
+//! $ ./ci-progress.sh | agent-progress-overlay --stdin
+//! ```
+//!
+//! That drives the overlay from a script or a test without touching
+//! `~/.claude`. Lines that aren't events are logged and skipped.
+
+use std::io::BufRead;
+
+use crate::health;
+use crate::watcher::{EventSender, Source, TaskEvent, WatcherError, WatcherEvent, CLAUDE_CODE_SOURCE};
+
+/// Command-line flag for stdin ingestion
+const STDIN_FLAG: &str = "--stdin";
+
+/// Whether the app was started with `--stdin`
+pub fn requested(mut args: impl Iterator<Item = String>) -> bool {
+    args.any(|arg| arg == STDIN_FLAG)
+}
+
+/// Reads events from standard input
+pub struct StdinSource;
+
+impl Source for StdinSource {
+    fn name(&self) -> &str {
+        "stdin"
+    }
+
+    fn start(self: Box<Self>, tx: EventSender) -> Result<(), WatcherError> {
+        // Reading stdin blocks, so it gets a thread of its own rather than a runtime worker
+        std::thread::Builder::new()
+            .name("stdin".to_string())
+            .spawn(move || read_events(std::io::stdin().lock(), &tx))
+            .map_err(|e| WatcherError::IoError(e.to_string()))?;
+        Ok(())
+    }
+}
+
+/// Send each event line from `input` until it ends or the event channel closes
+fn read_events(input: impl BufRead, tx: &EventSender) {
+    for line in input.lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(e) => {
+                tracing::error!("Failed to read stdin: {}", e);
+                return;
+            }
+        };
+        let Some(event) = parse_line(&line) else {
+            continue;
+        };
+        if tx.send(WatcherEvent::TaskEvent(event)).is_err() {
+            return;
+        }
+    }
+    tracing::info!("Stdin closed; no more events will be read from it");
+}
+
+fn parse_line(line: &str) -> Option<TaskEvent> {
+    let line = line.trim();
+    if line.is_empty() {
+        return None;
+    }
+    match serde_json::from_str::<TaskEvent>(line) {
+        Ok(mut event) => {
+            event.source.get_or_insert_with(|| CLAUDE_CODE_SOURCE.to_string());
+            Some(event)
+        }
+        Err(e) => {
+            tracing::warn!("Invalid event on stdin: {} - line: {}", e, line);
+            health::metrics().record_parse_error();
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::mpsc;
+
+    #[test]
+    fn test_read_events() {
+        let input = concat!(
+            r#"{"type":"task_started","task_id":"t1","tool":"Bash","timestamp":1000}"#,
+            "\n\nnot an event\n",
+            r#"{"type":"task_complete","task_id":"t1","timestamp":2000,"source":"ci"}"#,
+            "\n",
+        );
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        read_events(input.as_bytes(), &tx);
+
+        let Ok(WatcherEvent::TaskEvent(started)) = rx.try_recv() else { panic!("expected an event") };
+        assert_eq!(started.event_type, "task_started");
+        assert_eq!(started.source.as_deref(), Some(CLAUDE_CODE_SOURCE));
+
+        let Ok(WatcherEvent::TaskEvent(completed)) = rx.try_recv() else { panic!("expected an event") };
+        assert_eq!(completed.source.as_deref(), Some("ci"));
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_requested() {
+        let args = |list: &[&str]| list.iter().map(|s| s.to_string()).collect::<Vec<_>>().into_iter();
+        assert!(requested(args(&["--portable", "--stdin"])));
+        assert!(!requested(args(&["--portable"])));
+    }
+}
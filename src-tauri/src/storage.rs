@@ -0,0 +1,215 @@
+//! Disk space taken by what the overlay watches and keeps
+//!
+//! Claude Code's events file, todo files and session transcripts only ever
+//! grow, as does the history database. Every `storage.check_interval_mins`
+//! this adds them up across every Claude directory, and when the total
+//! crosses `storage.warn_mb` the user gets a popup suggesting what to clean
+//! up. It doesn't warn again until the total has dropped back below.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::clock::Clock;
+use crate::config::{Config, EVENTS_FILE_NAME, TODOS_DIR_NAME};
+use crate::core::CoreSink;
+use crate::transcript::PROJECTS_DIR;
+
+const MB: u64 = 1024 * 1024;
+
+/// Areas smaller than this aren't worth suggesting a cleanup of
+const SUGGEST_MIN_BYTES: u64 = 50 * MB;
+
+/// What each kind of file adds up to
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct StorageUsage {
+    pub events_bytes: u64,
+    pub todos_bytes: u64,
+    pub transcripts_bytes: u64,
+    /// The history database with its write-ahead log
+    pub database_bytes: u64,
+    pub total_bytes: u64,
+    /// `storage.warn_mb` in bytes; 0 when warnings are off
+    pub warn_bytes: u64,
+    pub measured_at: i64,
+    /// What could be cleaned up, biggest first
+    pub suggestions: Vec<String>,
+}
+
+impl StorageUsage {
+    pub fn over_threshold(&self) -> bool {
+        self.warn_bytes > 0 && self.total_bytes >= self.warn_bytes
+    }
+}
+
+/// Add up the files now
+pub fn measure(config: &Config, now_ms: i64) -> StorageUsage {
+    let profile_dirs: Vec<PathBuf> = config.profile_dirs().into_iter().map(|(_, dir)| dir).collect();
+    let events_bytes = file_size(&config.events_path())
+        + profile_dirs.iter().map(|dir| file_size(&dir.join(EVENTS_FILE_NAME))).sum::<u64>();
+    let todos_bytes = dir_size(&config.todos_path())
+        + profile_dirs.iter().map(|dir| dir_size(&dir.join(TODOS_DIR_NAME))).sum::<u64>();
+    let transcripts_bytes = config.claude_dirs().iter().map(|dir| dir_size(&dir.join(PROJECTS_DIR))).sum();
+    let database = config.database_path();
+    let database_bytes = ["", "-wal", "-shm"]
+        .iter()
+        .map(|suffix| file_size(Path::new(&format!("{}{}", database.display(), suffix))))
+        .sum();
+
+    let mut usage = StorageUsage {
+        events_bytes,
+        todos_bytes,
+        transcripts_bytes,
+        database_bytes,
+        total_bytes: events_bytes + todos_bytes + transcripts_bytes + database_bytes,
+        warn_bytes: config.storage.warn_mb * MB,
+        measured_at: now_ms,
+        suggestions: Vec::new(),
+    };
+    usage.suggestions = suggestions(&usage, config);
+    usage
+}
+
+/// Cleanups worth making, for the areas that take up the most
+fn suggestions(usage: &StorageUsage, config: &Config) -> Vec<String> {
+    let retention = if config.database.retention_days == 0 {
+        "Set database.retention_days to delete old history".to_string()
+    } else {
+        format!("Lower database.retention_days from {} to keep less history", config.database.retention_days)
+    };
+    let mut areas = vec![
+        (usage.transcripts_bytes, format!(
+            "Delete old session transcripts in {}, or lower cleanupPeriodDays in Claude's settings",
+            config.claude_dir().join(PROJECTS_DIR).display()
+        )),
+        (usage.database_bytes, retention),
+        (usage.events_bytes, "Clear the events file".to_string()),
+        (usage.todos_bytes, "Turn on behavior.archive_completed_todos, or delete old todo files".to_string()),
+    ];
+    areas.retain(|(bytes, _)| *bytes >= SUGGEST_MIN_BYTES);
+    areas.sort_by(|a, b| b.0.cmp(&a.0));
+    areas.into_iter().map(|(bytes, text)| format!("{} ({})", text, format_size(bytes))).collect()
+}
+
+/// A size as people read it, e.g. "340 MB" or "2.1 GB"
+pub fn format_size(bytes: u64) -> String {
+    if bytes >= 1024 * MB {
+        format!("{:.1} GB", bytes as f64 / (1024 * MB) as f64)
+    } else {
+        format!("{} MB", bytes / MB)
+    }
+}
+
+fn file_size(path: &Path) -> u64 {
+    fs::symlink_metadata(path).map(|m| m.len()).unwrap_or(0)
+}
+
+/// Everything under `dir`, without following links out of it
+fn dir_size(dir: &Path) -> u64 {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return 0;
+    };
+    entries
+        .filter_map(Result::ok)
+        .map(|entry| match entry.file_type() {
+            Ok(kind) if kind.is_dir() => dir_size(&entry.path()),
+            Ok(kind) if kind.is_file() => entry.metadata().map(|m| m.len()).unwrap_or(0),
+            _ => 0,
+        })
+        .sum()
+}
+
+/// Measure every `check_interval_mins` on the current runtime, warning `sink` on crossing the threshold
+pub fn start(config: Arc<Config>, sink: Arc<dyn CoreSink>, clock: Arc<dyn Clock>) {
+    if config.storage.check_interval_mins == 0 {
+        return;
+    }
+    let interval = Duration::from_secs(config.storage.check_interval_mins * 60);
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        let mut warned = false;
+        loop {
+            ticker.tick().await;
+            let (config, clock) = (config.clone(), clock.clone());
+            let usage = match tokio::task::spawn_blocking(move || measure(&config, clock.now_ms())).await {
+                Ok(usage) => usage,
+                Err(e) => {
+                    tracing::error!("Storage check failed: {}", e);
+                    continue;
+                }
+            };
+            tracing::debug!("Claude's files and the history take {}", format_size(usage.total_bytes));
+
+            let over = usage.over_threshold();
+            if over && !warned {
+                tracing::info!("Storage use of {} crossed the warning threshold", format_size(usage.total_bytes));
+                sink.storage_warning(&usage);
+            }
+            warned = over;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(path: &Path, bytes: usize) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, vec![b'x'; bytes]).unwrap();
+    }
+
+    #[test]
+    fn test_measure() {
+        let dir = tempfile::tempdir().unwrap();
+        let claude = dir.path().join("claude");
+        let mut config = Config::default();
+        config.paths.claude_dir = claude.to_string_lossy().to_string();
+        config.paths.events_file = claude.join(EVENTS_FILE_NAME).to_string_lossy().to_string();
+        config.paths.todos_dir = claude.join(TODOS_DIR_NAME).to_string_lossy().to_string();
+        config.paths.database_file = dir.path().join("history.db").to_string_lossy().to_string();
+        config.paths.profiles.clear();
+        config.storage.warn_mb = 1;
+
+        write(&claude.join(EVENTS_FILE_NAME), 100);
+        write(&claude.join(TODOS_DIR_NAME).join("a.json"), 20);
+        write(&claude.join(TODOS_DIR_NAME).join("archive").join("b.json"), 30);
+        write(&claude.join(PROJECTS_DIR).join("-home-me-app").join("s1.jsonl"), MB as usize);
+        write(&dir.path().join("history.db"), 1000);
+        write(&dir.path().join("history.db-wal"), 24);
+
+        let usage = measure(&config, 5_000);
+        assert_eq!(usage.events_bytes, 100);
+        assert_eq!(usage.todos_bytes, 50);
+        assert_eq!(usage.transcripts_bytes, MB);
+        assert_eq!(usage.database_bytes, 1024);
+        assert_eq!(usage.total_bytes, MB + 1174);
+        assert_eq!(usage.measured_at, 5_000);
+        assert!(usage.over_threshold());
+        // Nothing is big enough to be worth cleaning up
+        assert!(usage.suggestions.is_empty());
+
+        config.storage.warn_mb = 0;
+        assert!(!measure(&config, 5_000).over_threshold());
+    }
+
+    #[test]
+    fn test_suggestions_biggest_first() {
+        let config = Config::default();
+        let usage = StorageUsage {
+            transcripts_bytes: 900 * MB,
+            database_bytes: 3 * 1024 * MB,
+            events_bytes: 10 * MB,
+            ..Default::default()
+        };
+        let suggestions = suggestions(&usage, &config);
+        assert_eq!(suggestions.len(), 2);
+        assert!(suggestions[0].starts_with("Set database.retention_days"));
+        assert!(suggestions[0].ends_with("(3.0 GB)"));
+        assert!(suggestions[1].ends_with("(900 MB)"));
+    }
+}
@@ -2,11 +2,16 @@
 //!
 //! Provides persistent storage for tasks, sessions, and todos with full search capability.
 
-use rusqlite::{Connection, params, OptionalExtension};
+use rusqlite::{Connection, params, params_from_iter, OptionalExtension};
+use rusqlite::types::Value;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::Path;
 use std::sync::Mutex;
 
+use crate::text;
+use crate::timezone::Bucket;
+
 /// Task record stored in the database
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StoredTask {
@@ -18,8 +23,60 @@ pub struct StoredTask {
     pub started_at: i64,
     pub ended_at: Option<i64>,
     pub duration_ms: Option<i64>,
+    /// The end came before the start or implausibly long after it, so
+    /// `duration_ms` was clamped and the task is left out of averages
+    #[serde(default)]
+    pub duration_suspect: bool,
     pub is_background: bool,
     pub subagent_type: Option<String>,
+    /// Agent tool the task came from, e.g. "claude-code" or "aider"
+    pub source: String,
+    /// The user has looked at the task, e.g. while triaging failures
+    #[serde(default)]
+    pub reviewed: bool,
+    /// Kept at the top of the history and out of retention cleanup
+    #[serde(default)]
+    pub pinned: bool,
+}
+
+impl StoredTask {
+    /// Cursor for the page of history after this task
+    pub fn cursor(&self) -> TaskCursor {
+        TaskCursor {
+            started_at: self.started_at,
+            id: self.id.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+impl StoredTask {
+    /// A running Bash task, for tests to adjust with `..StoredTask::test(id, started_at)`
+    pub fn test(id: &str, started_at: i64) -> Self {
+        Self {
+            id: id.to_string(),
+            session_id: format!("session-{}", id),
+            tool: "Bash".to_string(),
+            description: Some(format!("cargo test {}", id)),
+            status: "active".to_string(),
+            started_at,
+            ended_at: None,
+            duration_ms: None,
+            duration_suspect: false,
+            is_background: false,
+            subagent_type: None,
+            source: "claude-code".to_string(),
+            reviewed: false,
+            pinned: false,
+        }
+    }
+}
+
+/// Position in the history, newest first; ties on `started_at` are broken by id
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TaskCursor {
+    pub started_at: i64,
+    pub id: String,
 }
 
 /// Session record stored in the database
@@ -29,6 +86,7 @@ pub struct StoredSession {
     pub started_at: i64,
     pub ended_at: Option<i64>,
     pub project_path: Option<String>,
+    pub branch: Option<String>,
 }
 
 /// Todo record stored in the database
@@ -43,6 +101,71 @@ pub struct StoredTodo {
     pub updated_at: i64,
 }
 
+/// Commit created during a session
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionCommit {
+    pub session_id: String,
+    pub commit_hash: String,
+    pub message: String,
+    pub committed_at: i64,
+}
+
+/// Aggregated view of a single session
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionSummary {
+    pub session: StoredSession,
+    pub total_tasks: i64,
+    pub completed_tasks: i64,
+    pub error_tasks: i64,
+    pub total_duration_ms: i64,
+    pub commits: Vec<SessionCommit>,
+}
+
+/// Link between a todo and the item mirroring it in an external service
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TodoLink {
+    pub service: String,
+    pub session_id: String,
+    pub content: String,
+    pub remote_id: String,
+    pub status: String,
+}
+
+/// Plugin delivery that was dropped or failed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeadLetter {
+    pub id: i64,
+    pub plugin: String,
+    /// The event as JSON
+    pub event: String,
+    pub reason: String,
+    pub created_at: i64,
+}
+
+/// A locally made change exchanged with other machines by history sync
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SyncRecord {
+    Session {
+        #[serde(flatten)]
+        session: StoredSession,
+        updated_at: i64,
+    },
+    Task {
+        #[serde(flatten)]
+        task: StoredTask,
+        updated_at: i64,
+    },
+}
+
+impl SyncRecord {
+    pub fn updated_at(&self) -> i64 {
+        match self {
+            SyncRecord::Session { updated_at, .. } | SyncRecord::Task { updated_at, .. } => *updated_at,
+        }
+    }
+}
+
 /// SQLite-based event store
 pub struct EventStore {
     conn: Mutex<Connection>,
@@ -84,10 +207,27 @@ CREATE TABLE IF NOT EXISTS todos (
     FOREIGN KEY (session_id) REFERENCES sessions(id)
 );
 
--- Indexes for common queries
-CREATE INDEX IF NOT EXISTS idx_tasks_session ON tasks(session_id);
-CREATE INDEX IF NOT EXISTS idx_tasks_status ON tasks(status);
-CREATE INDEX IF NOT EXISTS idx_tasks_started ON tasks(started_at DESC);
+-- Commits created during a session
+CREATE TABLE IF NOT EXISTS session_commits (
+    session_id TEXT NOT NULL,
+    commit_hash TEXT NOT NULL,
+    message TEXT NOT NULL,
+    committed_at INTEGER NOT NULL,
+    PRIMARY KEY (session_id, commit_hash),
+    FOREIGN KEY (session_id) REFERENCES sessions(id)
+);
+
+-- Todos mirrored into external services by sync plugins
+CREATE TABLE IF NOT EXISTS todo_links (
+    service TEXT NOT NULL,
+    session_id TEXT NOT NULL,
+    content TEXT NOT NULL,
+    remote_id TEXT NOT NULL,
+    status TEXT NOT NULL,
+    PRIMARY KEY (service, session_id, content)
+);
+
+-- Indexes for common queries (task history indexes are added by migration 5)
 CREATE INDEX IF NOT EXISTS idx_tasks_tool ON tasks(tool);
 CREATE INDEX IF NOT EXISTS idx_todos_session ON todos(session_id);
 CREATE INDEX IF NOT EXISTS idx_todos_status ON todos(status);
@@ -120,6 +260,595 @@ CREATE TRIGGER IF NOT EXISTS tasks_au AFTER UPDATE ON tasks BEGIN
 END;
 "#;
 
+/// Schema migrations applied in order on top of `SCHEMA`, tracked via `PRAGMA user_version`
+const MIGRATIONS: &[&str] = &[
+    // 1: git branch captured at session start
+    r#"
+    ALTER TABLE sessions ADD COLUMN branch TEXT;
+    CREATE INDEX IF NOT EXISTS idx_sessions_branch ON sessions(branch);
+    "#,
+    // 2: agent tool that produced each task; existing history is all Claude Code
+    r#"
+    ALTER TABLE tasks ADD COLUMN source TEXT NOT NULL DEFAULT 'claude-code';
+    CREATE INDEX IF NOT EXISTS idx_tasks_source ON tasks(source);
+    "#,
+    // 3: change tracking for history sync; origin is the machine a row was imported from
+    r#"
+    ALTER TABLE tasks ADD COLUMN updated_at INTEGER NOT NULL DEFAULT 0;
+    ALTER TABLE tasks ADD COLUMN origin TEXT;
+    ALTER TABLE sessions ADD COLUMN updated_at INTEGER NOT NULL DEFAULT 0;
+    ALTER TABLE sessions ADD COLUMN origin TEXT;
+    CREATE INDEX IF NOT EXISTS idx_tasks_updated ON tasks(updated_at);
+    CREATE INDEX IF NOT EXISTS idx_sessions_updated ON sessions(updated_at);
+    CREATE TABLE IF NOT EXISTS sync_state (
+        key TEXT PRIMARY KEY,
+        value INTEGER NOT NULL
+    );
+    "#,
+    // 4: plugin deliveries that were rate limited, timed out, or failed
+    r#"
+    CREATE TABLE IF NOT EXISTS dead_letters (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        plugin TEXT NOT NULL,
+        event TEXT NOT NULL,
+        reason TEXT NOT NULL,
+        created_at INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS idx_dead_letters_plugin ON dead_letters(plugin, created_at DESC);
+    "#,
+    // 5: indexes that serve history pages and stats without sorting or
+    // visiting table rows, and search triggers keyed by rowid so the FTS
+    // index can be joined on it
+    r#"
+    CREATE INDEX IF NOT EXISTS idx_tasks_started_id ON tasks(started_at DESC, id DESC);
+    CREATE INDEX IF NOT EXISTS idx_tasks_status_started ON tasks(status, started_at DESC, id DESC);
+    CREATE INDEX IF NOT EXISTS idx_tasks_source_started ON tasks(source, started_at DESC, id DESC);
+    CREATE INDEX IF NOT EXISTS idx_tasks_session_started ON tasks(session_id, started_at DESC, id DESC);
+    CREATE INDEX IF NOT EXISTS idx_tasks_status_duration ON tasks(status, duration_ms);
+    DROP INDEX IF EXISTS idx_tasks_status;
+    DROP INDEX IF EXISTS idx_tasks_source;
+    DROP INDEX IF EXISTS idx_tasks_session;
+    DROP INDEX IF EXISTS idx_tasks_started;
+    DROP TRIGGER IF EXISTS tasks_ai;
+    DROP TRIGGER IF EXISTS tasks_ad;
+    DROP TRIGGER IF EXISTS tasks_au;
+    CREATE TRIGGER tasks_ai AFTER INSERT ON tasks BEGIN
+        INSERT INTO tasks_fts(rowid, id, description, tool)
+        VALUES (new.rowid, new.id, new.description, new.tool);
+    END;
+    CREATE TRIGGER tasks_ad AFTER DELETE ON tasks BEGIN
+        INSERT INTO tasks_fts(tasks_fts, rowid, id, description, tool)
+        VALUES ('delete', old.rowid, old.id, old.description, old.tool);
+    END;
+    CREATE TRIGGER tasks_au AFTER UPDATE ON tasks BEGIN
+        INSERT INTO tasks_fts(tasks_fts, rowid, id, description, tool)
+        VALUES ('delete', old.rowid, old.id, old.description, old.tool);
+        INSERT INTO tasks_fts(rowid, id, description, tool)
+        VALUES (new.rowid, new.id, new.description, new.tool);
+    END;
+    INSERT INTO tasks_fts(tasks_fts) VALUES ('rebuild');
+    "#,
+    // 6: durations thrown off by clock jumps or sleep, clamped to
+    // 0..=MAX_TASK_DURATION_MS and flagged so averages skip them
+    r#"
+    ALTER TABLE tasks ADD COLUMN duration_suspect INTEGER NOT NULL DEFAULT 0;
+    UPDATE tasks SET duration_suspect = 1, duration_ms = MIN(MAX(duration_ms, 0), 86400000)
+        WHERE duration_ms < 0 OR duration_ms > 86400000;
+    DROP INDEX IF EXISTS idx_tasks_status_duration;
+    CREATE INDEX idx_tasks_status_duration ON tasks(status, duration_suspect, duration_ms);
+    "#,
+    // 7: the prompt a task ran for and the assistant's reply, from Claude's
+    // transcripts; the search index is rebuilt to cover them
+    r#"
+    ALTER TABLE tasks ADD COLUMN prompt TEXT;
+    ALTER TABLE tasks ADD COLUMN summary TEXT;
+    DROP TRIGGER IF EXISTS tasks_ai;
+    DROP TRIGGER IF EXISTS tasks_ad;
+    DROP TRIGGER IF EXISTS tasks_au;
+    DROP TABLE IF EXISTS tasks_fts;
+    CREATE VIRTUAL TABLE tasks_fts USING fts5(
+        id,
+        description,
+        tool,
+        prompt,
+        summary,
+        content='tasks',
+        content_rowid='rowid'
+    );
+    CREATE TRIGGER tasks_ai AFTER INSERT ON tasks BEGIN
+        INSERT INTO tasks_fts(rowid, id, description, tool, prompt, summary)
+        VALUES (new.rowid, new.id, new.description, new.tool, new.prompt, new.summary);
+    END;
+    CREATE TRIGGER tasks_ad AFTER DELETE ON tasks BEGIN
+        INSERT INTO tasks_fts(tasks_fts, rowid, id, description, tool, prompt, summary)
+        VALUES ('delete', old.rowid, old.id, old.description, old.tool, old.prompt, old.summary);
+    END;
+    CREATE TRIGGER tasks_au AFTER UPDATE ON tasks BEGIN
+        INSERT INTO tasks_fts(tasks_fts, rowid, id, description, tool, prompt, summary)
+        VALUES ('delete', old.rowid, old.id, old.description, old.tool, old.prompt, old.summary);
+        INSERT INTO tasks_fts(rowid, id, description, tool, prompt, summary)
+        VALUES (new.rowid, new.id, new.description, new.tool, new.prompt, new.summary);
+    END;
+    INSERT INTO tasks_fts(tasks_fts) VALUES ('rebuild');
+    "#,
+    // 8: how full each session's context window was after each reply
+    r#"
+    CREATE TABLE IF NOT EXISTS context_samples (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        session_id TEXT NOT NULL,
+        timestamp INTEGER NOT NULL,
+        context_tokens INTEGER NOT NULL,
+        context_window INTEGER NOT NULL,
+        model TEXT
+    );
+    CREATE INDEX IF NOT EXISTS idx_context_samples_session ON context_samples(session_id, timestamp);
+    CREATE INDEX IF NOT EXISTS idx_context_samples_timestamp ON context_samples(timestamp);
+    "#,
+    // 9: rate-limit and usage-limit warnings, which explain why an agent stalled
+    r#"
+    CREATE TABLE IF NOT EXISTS usage_warnings (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        session_id TEXT,
+        kind TEXT NOT NULL,
+        message TEXT NOT NULL,
+        resets_at INTEGER,
+        timestamp INTEGER NOT NULL,
+        source TEXT
+    );
+    CREATE INDEX IF NOT EXISTS idx_usage_warnings_timestamp ON usage_warnings(timestamp);
+    "#,
+    // 10: the model behind each task, and the tokens each reply used
+    r#"
+    ALTER TABLE tasks ADD COLUMN model TEXT;
+    CREATE INDEX IF NOT EXISTS idx_tasks_model ON tasks(model, started_at);
+    CREATE TABLE IF NOT EXISTS token_usage (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        session_id TEXT NOT NULL,
+        timestamp INTEGER NOT NULL,
+        model TEXT NOT NULL,
+        input_tokens INTEGER NOT NULL,
+        output_tokens INTEGER NOT NULL,
+        cache_read_tokens INTEGER NOT NULL,
+        cache_creation_tokens INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS idx_token_usage_timestamp ON token_usage(timestamp, model);
+    "#,
+    // 11: recent tasks of a tool, for estimating how long the next one takes
+    r#"
+    CREATE INDEX IF NOT EXISTS idx_tasks_tool_started ON tasks(tool, started_at DESC);
+    "#,
+    // 12: focus (pomodoro) sessions and what the agent got done in each
+    r#"
+    CREATE TABLE IF NOT EXISTS focus_sessions (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        label TEXT,
+        started_at INTEGER NOT NULL,
+        ended_at INTEGER,
+        planned_ms INTEGER,
+        completed_tasks INTEGER NOT NULL DEFAULT 0,
+        failed_tasks INTEGER NOT NULL DEFAULT 0,
+        task_time_ms INTEGER NOT NULL DEFAULT 0
+    );
+    CREATE INDEX IF NOT EXISTS idx_focus_sessions_started ON focus_sessions(started_at DESC);
+    CREATE INDEX IF NOT EXISTS idx_tasks_ended ON tasks(ended_at);
+    "#,
+    // 13: notification popups shown and how each one went away; `window` is
+    // the popup's window label, which is only unique among open popups
+    r#"
+    CREATE TABLE IF NOT EXISTS notifications (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        window TEXT NOT NULL,
+        task_id TEXT NOT NULL,
+        kind TEXT NOT NULL,
+        shown_at INTEGER NOT NULL,
+        dismissed_at INTEGER,
+        dismissed_by TEXT
+    );
+    CREATE INDEX IF NOT EXISTS idx_notifications_shown ON notifications(shown_at DESC);
+    CREATE INDEX IF NOT EXISTS idx_notifications_open ON notifications(window) WHERE dismissed_at IS NULL;
+    "#,
+    // 14: when the user marked a task as reviewed
+    r#"
+    ALTER TABLE tasks ADD COLUMN reviewed_at INTEGER;
+    "#,
+    // 15: tasks the user pinned, which retention cleanup leaves alone
+    r#"
+    ALTER TABLE tasks ADD COLUMN pinned_at INTEGER;
+    CREATE INDEX IF NOT EXISTS idx_tasks_pinned ON tasks(pinned_at DESC) WHERE pinned_at IS NOT NULL;
+    "#,
+    // 16: user-defined tags on sessions and tasks
+    r#"
+    CREATE TABLE IF NOT EXISTS tags (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        name TEXT NOT NULL UNIQUE COLLATE NOCASE,
+        created_at INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS session_tags (
+        session_id TEXT NOT NULL,
+        tag_id INTEGER NOT NULL,
+        PRIMARY KEY (session_id, tag_id)
+    );
+    CREATE TABLE IF NOT EXISTS task_tags (
+        task_id TEXT NOT NULL,
+        tag_id INTEGER NOT NULL,
+        PRIMARY KEY (task_id, tag_id)
+    );
+    CREATE INDEX IF NOT EXISTS idx_session_tags_tag ON session_tags(tag_id);
+    CREATE INDEX IF NOT EXISTS idx_task_tags_tag ON task_tags(tag_id);
+    "#,
+    // 17: process id of a background shell, for cancel requests
+    r#"
+    ALTER TABLE tasks ADD COLUMN pid INTEGER;
+    "#,
+    // 18: the user's order for the todo list, by content hash
+    r#"
+    CREATE TABLE IF NOT EXISTS todo_order (
+        content_hash TEXT PRIMARY KEY,
+        position INTEGER NOT NULL,
+        updated_at INTEGER NOT NULL
+    );
+    "#,
+    // 19: todo status changes over time, for burndown charts
+    r#"
+    CREATE TABLE IF NOT EXISTS todo_transitions (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        session_id TEXT NOT NULL,
+        content TEXT NOT NULL,
+        status TEXT NOT NULL,
+        at INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS idx_todo_transitions_session ON todo_transitions(session_id, at);
+    "#,
+    // 20: completed todos are archived in `todos`, once per session and content
+    r#"
+    CREATE UNIQUE INDEX IF NOT EXISTS idx_todos_session_content ON todos(session_id, content);
+    CREATE INDEX IF NOT EXISTS idx_todos_completed ON todos(updated_at) WHERE status = 'completed';
+    "#,
+    // 21: what a background shell's processes were last seen using, and how it exited
+    r#"
+    ALTER TABLE tasks ADD COLUMN exit_code INTEGER;
+    ALTER TABLE tasks ADD COLUMN process_alive INTEGER;
+    ALTER TABLE tasks ADD COLUMN process_cpu REAL;
+    ALTER TABLE tasks ADD COLUMN process_rss INTEGER;
+    ALTER TABLE tasks ADD COLUMN process_count INTEGER;
+    ALTER TABLE tasks ADD COLUMN process_sampled_at INTEGER;
+    "#,
+    // 22: recent router events, for subscribers catching up on what they missed
+    r#"
+    CREATE TABLE IF NOT EXISTS event_log (
+        seq INTEGER PRIMARY KEY AUTOINCREMENT,
+        kind TEXT NOT NULL,
+        payload TEXT NOT NULL,
+        at INTEGER NOT NULL
+    );
+    "#,
+    // 23: events of kinds the overlay doesn't know, sent by the user's own hooks
+    r#"
+    CREATE TABLE IF NOT EXISTS custom_events (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        custom_type TEXT NOT NULL,
+        task_id TEXT,
+        session_id TEXT,
+        payload TEXT NOT NULL,
+        source TEXT,
+        timestamp INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS idx_custom_events_type ON custom_events(custom_type, timestamp);
+    "#,
+];
+
+/// Events kept in the event log; older ones are dropped as new ones come in
+pub const EVENT_LOG_CAPACITY: i64 = 2000;
+
+/// Most recent similar tasks a duration estimate looks at
+const ESTIMATE_SAMPLE_LIMIT: i64 = 50;
+/// Fewer similar tasks than this are too few to estimate from
+const ESTIMATE_MIN_SAMPLES: usize = 3;
+
+/// Longest duration taken at face value; anything longer is most likely the
+/// machine sleeping or the clock jumping mid-task
+pub const MAX_TASK_DURATION_MS: i64 = 24 * 60 * 60 * 1000;
+
+/// Columns selected for a `StoredTask`, in the order expected by `row_to_task`
+const TASK_COLUMNS: &str =
+    "t.id, t.session_id, t.tool, t.description, t.status, t.started_at, t.ended_at, t.duration_ms, t.is_background, t.subagent_type, t.source, t.duration_suspect, t.reviewed_at IS NOT NULL, t.pinned_at IS NOT NULL";
+
+/// Filters applied to history queries
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TaskFilter {
+    /// Only tasks from sessions started on this git branch
+    pub branch: Option<String>,
+    /// Only tasks from this agent tool
+    pub source: Option<String>,
+    /// Only tasks in this status ("active", "completed", ...)
+    pub status: Option<String>,
+    /// Only tasks from sessions in this directory or below it
+    pub project_path: Option<String>,
+    /// Only tasks with this tag, on the task itself or on its session
+    #[serde(default)]
+    pub tag: Option<String>,
+    /// Only tasks that have (true) or haven't (false) been marked as reviewed
+    #[serde(default)]
+    pub reviewed: Option<bool>,
+    /// Only tasks older than this one; pass the last task's cursor to get the next page
+    pub before: Option<TaskCursor>,
+}
+
+impl TaskFilter {
+    /// Build SQL conditions (joined with AND) and their parameters
+    fn conditions(&self) -> (Vec<&'static str>, Vec<Value>) {
+        let mut conditions = Vec::new();
+        let mut values = Vec::new();
+
+        if let Some(branch) = &self.branch {
+            conditions.push("s.branch = ?");
+            values.push(Value::Text(branch.clone()));
+        }
+
+        if let Some(source) = &self.source {
+            conditions.push("t.source = ?");
+            values.push(Value::Text(source.clone()));
+        }
+
+        if let Some(status) = &self.status {
+            conditions.push("t.status = ?");
+            values.push(Value::Text(status.clone()));
+        }
+
+        if let Some(root) = &self.project_path {
+            let root = root.trim_end_matches(['/', '\\']);
+            let escaped = escape_like(root);
+            conditions.push("(s.project_path = ? OR s.project_path LIKE ? ESCAPE '^' OR s.project_path LIKE ? ESCAPE '^')");
+            values.push(Value::Text(root.to_string()));
+            values.push(Value::Text(format!("{}/%", escaped)));
+            values.push(Value::Text(format!("{}\\%", escaped)));
+        }
+
+        if let Some(tag) = &self.tag {
+            conditions.push("t.id IN (SELECT task_id FROM task_tags WHERE tag_id IN (SELECT id FROM tags WHERE name = ?)
+                UNION SELECT x.id FROM tasks x JOIN session_tags st ON st.session_id = x.session_id
+                WHERE st.tag_id IN (SELECT id FROM tags WHERE name = ?))");
+            values.push(Value::Text(tag.trim().to_string()));
+            values.push(Value::Text(tag.trim().to_string()));
+        }
+
+        match self.reviewed {
+            Some(true) => conditions.push("t.reviewed_at IS NOT NULL"),
+            Some(false) => conditions.push("t.reviewed_at IS NULL"),
+            None => {}
+        }
+
+        if let Some(cursor) = &self.before {
+            conditions.push("(t.started_at, t.id) < (?, ?)");
+            values.push(Value::Integer(cursor.started_at));
+            values.push(Value::Text(cursor.id.clone()));
+        }
+
+        (conditions, values)
+    }
+}
+
+/// The filter's WHERE clause, or nothing if it has no conditions
+fn where_clause(conditions: &[&str]) -> String {
+    if conditions.is_empty() {
+        String::new()
+    } else {
+        format!("WHERE {}", conditions.join(" AND "))
+    }
+}
+
+/// SQL for `get_recent_tasks`; walks `idx_tasks_started_id` (or a filter's
+/// composite index) in order, so a page costs the same at any depth
+fn recent_tasks_query(filter: &TaskFilter, limit: usize) -> (String, Vec<Value>) {
+    let (conditions, mut values) = filter.conditions();
+    values.push(Value::Integer(limit as i64));
+
+    let sql = format!(
+        "SELECT {} FROM tasks t
+         LEFT JOIN sessions s ON s.id = t.session_id
+         {}
+         ORDER BY t.started_at DESC, t.id DESC
+         LIMIT ?",
+        TASK_COLUMNS,
+        where_clause(&conditions)
+    );
+    (sql, values)
+}
+
+/// SQL for `search_tasks`; matches come from the FTS index by rowid
+fn search_tasks_query(query: &str, filter: &TaskFilter, limit: usize) -> (String, Vec<Value>) {
+    let (mut conditions, filter_values) = filter.conditions();
+    conditions.insert(0, "t.rowid IN (SELECT rowid FROM tasks_fts WHERE tasks_fts MATCH ?)");
+
+    // Descriptions are indexed normalized, so the query has to be too
+    let mut values = vec![Value::Text(text::normalize(query))];
+    values.extend(filter_values);
+    values.push(Value::Integer(limit as i64));
+
+    let sql = format!(
+        "SELECT {} FROM tasks t
+         LEFT JOIN sessions s ON s.id = t.session_id
+         {}
+         ORDER BY t.started_at DESC, t.id DESC
+         LIMIT ?",
+        TASK_COLUMNS,
+        where_clause(&conditions)
+    );
+    (sql, values)
+}
+
+const STATS_QUERY: &str = "SELECT
+    COUNT(*),
+    COALESCE(SUM(status = 'completed'), 0),
+    COALESCE(SUM(status = 'error'), 0),
+    AVG(CASE WHEN duration_suspect = 0 THEN duration_ms END),
+    COALESCE(SUM(duration_suspect), 0)
+    FROM tasks";
+
+/// Task statistics per model, in the column order `row_to_stats` expects
+const MODEL_STATS_QUERY: &str = "SELECT
+    COUNT(*),
+    COALESCE(SUM(status = 'completed'), 0),
+    COALESCE(SUM(status = 'error'), 0),
+    AVG(CASE WHEN duration_suspect = 0 THEN duration_ms END),
+    COALESCE(SUM(duration_suspect), 0),
+    model,
+    COALESCE(SUM(CASE WHEN duration_suspect = 0 THEN duration_ms END), 0)
+    FROM tasks
+    WHERE started_at >= ?1
+    GROUP BY model";
+
+/// Tokens per model, summed over the replies since a cutoff
+const MODEL_TOKENS_QUERY: &str = "SELECT
+    model,
+    COUNT(*),
+    SUM(input_tokens),
+    SUM(output_tokens),
+    SUM(cache_read_tokens),
+    SUM(cache_creation_tokens)
+    FROM token_usage
+    WHERE timestamp >= ?1
+    GROUP BY model";
+
+fn row_to_stats(row: &rusqlite::Row) -> rusqlite::Result<TaskStats> {
+    Ok(TaskStats {
+        total_tasks: row.get(0)?,
+        completed_tasks: row.get(1)?,
+        error_tasks: row.get(2)?,
+        avg_duration_ms: row.get(3)?,
+        suspect_durations: row.get(4)?,
+    })
+}
+
+/// Tasks carrying tag `?1`, on the task itself or on its session
+const TAGGED_TASKS: &str = "SELECT task_id FROM task_tags WHERE tag_id = ?1
+    UNION SELECT t.id FROM tasks t JOIN session_tags st ON st.session_id = t.session_id WHERE st.tag_id = ?1";
+
+fn row_to_tag(row: &rusqlite::Row) -> rusqlite::Result<Tag> {
+    Ok(Tag {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        created_at: row.get(2)?,
+    })
+}
+
+const FOCUS_SESSION_COLUMNS: &str =
+    "id, label, started_at, ended_at, planned_ms, completed_tasks, failed_tasks, task_time_ms";
+
+fn row_to_focus_session(row: &rusqlite::Row) -> rusqlite::Result<FocusSession> {
+    Ok(FocusSession {
+        id: row.get(0)?,
+        label: row.get(1)?,
+        started_at: row.get(2)?,
+        ended_at: row.get(3)?,
+        planned_ms: row.get(4)?,
+        completed_tasks: row.get(5)?,
+        failed_tasks: row.get(6)?,
+        task_time_ms: row.get(7)?,
+    })
+}
+
+fn query_active_focus_session(conn: &Connection) -> Result<Option<FocusSession>, StoreError> {
+    conn.query_row(
+        &format!(
+            "SELECT {} FROM focus_sessions WHERE ended_at IS NULL ORDER BY started_at DESC LIMIT 1",
+            FOCUS_SESSION_COLUMNS
+        ),
+        [],
+        row_to_focus_session,
+    ).optional().map_err(|e| StoreError::QueryError(e.to_string()))
+}
+
+/// Escape `LIKE` wildcards using `^` as the escape character
+fn escape_like(value: &str) -> String {
+    value.replace('^', "^^").replace('%', "^%").replace('_', "^_")
+}
+
+fn row_to_task(row: &rusqlite::Row) -> rusqlite::Result<StoredTask> {
+    Ok(StoredTask {
+        id: row.get(0)?,
+        session_id: row.get(1)?,
+        tool: row.get(2)?,
+        description: row.get(3)?,
+        status: row.get(4)?,
+        started_at: row.get(5)?,
+        ended_at: row.get(6)?,
+        duration_ms: row.get(7)?,
+        is_background: row.get::<_, i32>(8)? != 0,
+        subagent_type: row.get(9)?,
+        source: row.get(10)?,
+        duration_suspect: row.get::<_, i32>(11)? != 0,
+        reviewed: row.get::<_, i32>(12)? != 0,
+        pinned: row.get::<_, i32>(13)? != 0,
+    })
+}
+
+fn integrity_check(conn: &Connection) -> Result<Vec<String>, StoreError> {
+    let mut stmt = conn.prepare("PRAGMA integrity_check")
+        .map_err(|e| StoreError::QueryError(e.to_string()))?;
+    let rows = stmt.query_map([], |row| row.get::<_, String>(0))
+        .map_err(|e| StoreError::QueryError(e.to_string()))?;
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| StoreError::QueryError(e.to_string()))
+}
+
+fn schema_version(conn: &Connection) -> Result<i64, StoreError> {
+    conn.query_row("PRAGMA user_version", [], |row| row.get(0))
+        .map_err(|e| StoreError::QueryError(e.to_string()))
+}
+
+/// State of a database file, checked without changing it
+#[derive(Debug, Clone, Serialize)]
+pub struct DatabaseCheck {
+    pub schema_version: i64,
+    /// Migrations this version would apply; negative when a newer version wrote the database
+    pub pending_migrations: i64,
+    /// What `PRAGMA integrity_check` found; just "ok" when healthy
+    pub integrity: Vec<String>,
+}
+
+/// Check the database at `path` read-only, so nothing is migrated or created
+pub fn check_database(path: &Path) -> Result<DatabaseCheck, StoreError> {
+    let conn = Connection::open_with_flags(path, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| StoreError::ConnectionError(e.to_string()))?;
+    let schema_version = schema_version(&conn)?;
+    Ok(DatabaseCheck {
+        schema_version,
+        pending_migrations: MIGRATIONS.len() as i64 - schema_version,
+        integrity: integrity_check(&conn)?,
+    })
+}
+
+/// Copy the database at `path` into a new file at `to`, reading it read-only
+///
+/// The copy includes changes still in the source's write-ahead log.
+pub fn snapshot_database(path: &Path, to: &Path) -> Result<(), StoreError> {
+    let conn = Connection::open_with_flags(path, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| StoreError::ConnectionError(e.to_string()))?;
+    conn.execute("VACUUM INTO ?1", params![to.to_string_lossy().to_string()])
+        .map_err(|e| StoreError::QueryError(e.to_string()))?;
+    Ok(())
+}
+
+/// Create the base schema and apply any pending migrations
+fn init_schema(conn: &Connection) -> Result<(), StoreError> {
+    conn.execute_batch(SCHEMA)
+        .map_err(|e| StoreError::SchemaError(e.to_string()))?;
+
+    let version: i64 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))
+        .map_err(|e| StoreError::SchemaError(e.to_string()))?;
+
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        conn.execute_batch(&format!(
+            "BEGIN; {} PRAGMA user_version = {}; COMMIT;",
+            migration,
+            index + 1
+        )).map_err(|e| StoreError::SchemaError(format!("migration {}: {}", index + 1, e)))?;
+    }
+
+    Ok(())
+}
+
 impl EventStore {
     /// Create a new event store at the given path
     pub fn new(path: &Path) -> Result<Self, StoreError> {
@@ -137,8 +866,7 @@ impl EventStore {
             .map_err(|e| StoreError::SchemaError(e.to_string()))?;
 
         // Initialize schema
-        conn.execute_batch(SCHEMA)
-            .map_err(|e| StoreError::SchemaError(e.to_string()))?;
+        init_schema(&conn)?;
 
         tracing::info!("Initialized event store at {:?}", path);
 
@@ -152,8 +880,7 @@ impl EventStore {
         let conn = Connection::open_in_memory()
             .map_err(|e| StoreError::ConnectionError(e.to_string()))?;
 
-        conn.execute_batch(SCHEMA)
-            .map_err(|e| StoreError::SchemaError(e.to_string()))?;
+        init_schema(&conn)?;
 
         Ok(Self {
             conn: Mutex::new(conn),
@@ -167,50 +894,222 @@ impl EventStore {
         let conn = self.conn.lock().map_err(|_| StoreError::LockError)?;
 
         conn.execute(
-            "INSERT INTO sessions (id, started_at, ended_at, project_path)
-             VALUES (?1, ?2, ?3, ?4)
+            "INSERT INTO sessions (id, started_at, ended_at, project_path, branch, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)
              ON CONFLICT(id) DO UPDATE SET
                 ended_at = COALESCE(?3, ended_at),
-                project_path = COALESCE(?4, project_path)",
-            params![session.id, session.started_at, session.ended_at, session.project_path],
+                project_path = COALESCE(?4, project_path),
+                branch = COALESCE(branch, ?5),
+                updated_at = ?6",
+            params![session.id, session.started_at, session.ended_at, session.project_path, session.branch, chrono_now_ms()],
         ).map_err(|e| StoreError::QueryError(e.to_string()))?;
 
         Ok(())
     }
 
+    /// End every session that hasn't ended, returning how many there were
+    pub fn end_open_sessions(&self, ended_at: i64) -> Result<usize, StoreError> {
+        let conn = self.conn.lock().map_err(|_| StoreError::LockError)?;
+
+        conn.execute(
+            "UPDATE sessions SET ended_at = ?1, updated_at = ?2 WHERE ended_at IS NULL",
+            params![ended_at, chrono_now_ms()],
+        ).map_err(|e| StoreError::QueryError(e.to_string()))
+    }
+
     /// Get a session by ID
     pub fn get_session(&self, id: &str) -> Result<Option<StoredSession>, StoreError> {
         let conn = self.conn.lock().map_err(|_| StoreError::LockError)?;
 
         let result = conn.query_row(
-            "SELECT id, started_at, ended_at, project_path FROM sessions WHERE id = ?1",
+            "SELECT id, started_at, ended_at, project_path, branch FROM sessions WHERE id = ?1",
             params![id],
             |row| Ok(StoredSession {
                 id: row.get(0)?,
                 started_at: row.get(1)?,
                 ended_at: row.get(2)?,
                 project_path: row.get(3)?,
+                branch: row.get(4)?,
             }),
         ).optional().map_err(|e| StoreError::QueryError(e.to_string()))?;
 
         Ok(result)
     }
 
+    /// Sessions started since `since_ms`, oldest first
+    pub fn get_sessions_since(&self, since_ms: i64) -> Result<Vec<StoredSession>, StoreError> {
+        let conn = self.conn.lock().map_err(|_| StoreError::LockError)?;
+
+        let mut stmt = conn.prepare(
+            "SELECT id, started_at, ended_at, project_path, branch FROM sessions
+             WHERE started_at >= ?1
+             ORDER BY started_at, id"
+        ).map_err(|e| StoreError::QueryError(e.to_string()))?;
+
+        let sessions = stmt.query_map(params![since_ms], |row| Ok(StoredSession {
+            id: row.get(0)?,
+            started_at: row.get(1)?,
+            ended_at: row.get(2)?,
+            project_path: row.get(3)?,
+            branch: row.get(4)?,
+        }))
+        .map_err(|e| StoreError::QueryError(e.to_string()))?
+        .filter_map(|r| r.ok())
+        .collect();
+
+        Ok(sessions)
+    }
+
+    /// Get a summary of a session's tasks and commits
+    pub fn get_session_summary(&self, id: &str) -> Result<Option<SessionSummary>, StoreError> {
+        let session = match self.get_session(id)? {
+            Some(s) => s,
+            None => return Ok(None),
+        };
+
+        let (total, completed, errors, duration) = {
+            let conn = self.conn.lock().map_err(|_| StoreError::LockError)?;
+
+            conn.query_row(
+                "SELECT COUNT(*),
+                        COALESCE(SUM(status = 'completed'), 0),
+                        COALESCE(SUM(status = 'error'), 0),
+                        COALESCE(SUM(duration_ms), 0)
+                 FROM tasks WHERE session_id = ?1",
+                params![id],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+            ).map_err(|e| StoreError::QueryError(e.to_string()))?
+        };
+
+        Ok(Some(SessionSummary {
+            session,
+            total_tasks: total,
+            completed_tasks: completed,
+            error_tasks: errors,
+            total_duration_ms: duration,
+            commits: self.get_session_commits(id)?,
+        }))
+    }
+
+    // ========== Commit Operations ==========
+
+    /// Record commits created during a session (duplicates are ignored)
+    pub fn insert_session_commits(&self, commits: &[SessionCommit]) -> Result<(), StoreError> {
+        let conn = self.conn.lock().map_err(|_| StoreError::LockError)?;
+
+        for commit in commits {
+            conn.execute(
+                "INSERT OR IGNORE INTO session_commits (session_id, commit_hash, message, committed_at)
+                 VALUES (?1, ?2, ?3, ?4)",
+                params![commit.session_id, commit.commit_hash, commit.message, commit.committed_at],
+            ).map_err(|e| StoreError::QueryError(e.to_string()))?;
+        }
+
+        Ok(())
+    }
+
+    /// Get commits recorded for a session, oldest first
+    pub fn get_session_commits(&self, session_id: &str) -> Result<Vec<SessionCommit>, StoreError> {
+        let conn = self.conn.lock().map_err(|_| StoreError::LockError)?;
+
+        let mut stmt = conn.prepare(
+            "SELECT session_id, commit_hash, message, committed_at
+             FROM session_commits
+             WHERE session_id = ?1
+             ORDER BY committed_at ASC"
+        ).map_err(|e| StoreError::QueryError(e.to_string()))?;
+
+        let commits = stmt.query_map(params![session_id], |row| {
+            Ok(SessionCommit {
+                session_id: row.get(0)?,
+                commit_hash: row.get(1)?,
+                message: row.get(2)?,
+                committed_at: row.get(3)?,
+            })
+        }).map_err(|e| StoreError::QueryError(e.to_string()))?
+        .filter_map(|r| r.ok())
+        .collect();
+
+        Ok(commits)
+    }
+
+    // ========== Todo Link Operations ==========
+
+    /// Insert or update the link between a todo and its external copy
+    pub fn upsert_todo_link(&self, link: &TodoLink) -> Result<(), StoreError> {
+        let conn = self.conn.lock().map_err(|_| StoreError::LockError)?;
+
+        conn.execute(
+            "INSERT INTO todo_links (service, session_id, content, remote_id, status)
+             VALUES (?1, ?2, ?3, ?4, ?5)
+             ON CONFLICT(service, session_id, content) DO UPDATE SET
+                remote_id = ?4,
+                status = ?5",
+            params![link.service, link.session_id, link.content, link.remote_id, link.status],
+        ).map_err(|e| StoreError::QueryError(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// Set the status of every service's link to a todo that is still open there
+    pub fn set_open_todo_links_status(&self, todos: &[(String, String)], status: &str) -> Result<(), StoreError> {
+        let mut conn = self.conn.lock().map_err(|_| StoreError::LockError)?;
+        let tx = conn.transaction().map_err(|e| StoreError::QueryError(e.to_string()))?;
+        {
+            let mut stmt = tx.prepare(
+                "UPDATE todo_links SET status = ?3
+                 WHERE session_id = ?1 AND content = ?2 AND status NOT IN ('completed', 'detached')",
+            ).map_err(|e| StoreError::QueryError(e.to_string()))?;
+            for (session_id, content) in todos {
+                stmt.execute(params![session_id, content, status])
+                    .map_err(|e| StoreError::QueryError(e.to_string()))?;
+            }
+        }
+        tx.commit().map_err(|e| StoreError::QueryError(e.to_string()))
+    }
+
+    /// Get all todo links for a service
+    pub fn get_todo_links(&self, service: &str) -> Result<Vec<TodoLink>, StoreError> {
+        let conn = self.conn.lock().map_err(|_| StoreError::LockError)?;
+
+        let mut stmt = conn.prepare(
+            "SELECT service, session_id, content, remote_id, status
+             FROM todo_links
+             WHERE service = ?1"
+        ).map_err(|e| StoreError::QueryError(e.to_string()))?;
+
+        let links = stmt.query_map(params![service], |row| {
+            Ok(TodoLink {
+                service: row.get(0)?,
+                session_id: row.get(1)?,
+                content: row.get(2)?,
+                remote_id: row.get(3)?,
+                status: row.get(4)?,
+            })
+        }).map_err(|e| StoreError::QueryError(e.to_string()))?
+        .filter_map(|r| r.ok())
+        .collect();
+
+        Ok(links)
+    }
+
     // ========== Task Operations ==========
 
     /// Insert a new task
     pub fn insert_task(&self, task: &StoredTask) -> Result<(), StoreError> {
         let conn = self.conn.lock().map_err(|_| StoreError::LockError)?;
 
+        let now = chrono_now_ms();
+
         // Ensure session exists
         conn.execute(
-            "INSERT OR IGNORE INTO sessions (id, started_at) VALUES (?1, ?2)",
-            params![task.session_id, task.started_at],
+            "INSERT OR IGNORE INTO sessions (id, started_at, updated_at) VALUES (?1, ?2, ?3)",
+            params![task.session_id, task.started_at, now],
         ).map_err(|e| StoreError::QueryError(e.to_string()))?;
 
         conn.execute(
-            "INSERT INTO tasks (id, session_id, tool, description, status, started_at, is_background, subagent_type)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            "INSERT INTO tasks (id, session_id, tool, description, status, started_at, is_background, subagent_type, source, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
             params![
                 task.id,
                 task.session_id,
@@ -219,7 +1118,9 @@ impl EventStore {
                 task.status,
                 task.started_at,
                 task.is_background as i32,
-                task.subagent_type
+                task.subagent_type,
+                task.source,
+                now
             ],
         ).map_err(|e| StoreError::QueryError(e.to_string()))?;
 
@@ -239,9 +1140,11 @@ impl EventStore {
             "UPDATE tasks SET
                 status = ?1,
                 ended_at = ?2,
-                duration_ms = ?2 - started_at
+                duration_ms = MIN(MAX(?2 - started_at, 0), ?5),
+                duration_suspect = (?2 - started_at < 0 OR ?2 - started_at > ?5),
+                updated_at = ?4
              WHERE id = ?3",
-            params![status, ended_at, task_id],
+            params![status, ended_at, task_id, chrono_now_ms(), MAX_TASK_DURATION_MS],
         ).map_err(|e| StoreError::QueryError(e.to_string()))?;
 
         Ok(())
@@ -252,192 +1155,1799 @@ impl EventStore {
         let conn = self.conn.lock().map_err(|_| StoreError::LockError)?;
 
         let result = conn.query_row(
-            "SELECT id, session_id, tool, description, status, started_at, ended_at, duration_ms, is_background, subagent_type
-             FROM tasks WHERE id = ?1",
+            &format!("SELECT {} FROM tasks t WHERE t.id = ?1", TASK_COLUMNS),
             params![id],
-            |row| Ok(StoredTask {
-                id: row.get(0)?,
-                session_id: row.get(1)?,
-                tool: row.get(2)?,
-                description: row.get(3)?,
-                status: row.get(4)?,
-                started_at: row.get(5)?,
-                ended_at: row.get(6)?,
-                duration_ms: row.get(7)?,
-                is_background: row.get::<_, i32>(8)? != 0,
-                subagent_type: row.get(9)?,
-            }),
+            row_to_task,
         ).optional().map_err(|e| StoreError::QueryError(e.to_string()))?;
 
         Ok(result)
     }
 
-    /// Get recent tasks
-    pub fn get_recent_tasks(&self, limit: usize) -> Result<Vec<StoredTask>, StoreError> {
+    /// Get recent tasks matching a filter, newest first
+    pub fn get_recent_tasks(&self, filter: &TaskFilter, limit: usize) -> Result<Vec<StoredTask>, StoreError> {
+        let (sql, values) = recent_tasks_query(filter, limit);
+        self.query_tasks(&sql, &values)
+    }
+
+    /// Get tasks by session
+    pub fn get_tasks_by_session(&self, session_id: &str) -> Result<Vec<StoredTask>, StoreError> {
+        self.query_tasks(
+            &format!(
+                "SELECT {} FROM tasks t
+                 WHERE t.session_id = ?
+                 ORDER BY t.started_at DESC, t.id DESC",
+                TASK_COLUMNS
+            ),
+            &[Value::Text(session_id.to_string())],
+        )
+    }
+
+    /// Tasks started since `since_ms`, oldest first
+    pub fn get_tasks_since(&self, since_ms: i64) -> Result<Vec<StoredTask>, StoreError> {
+        self.query_tasks(
+            &format!(
+                "SELECT {} FROM tasks t
+                 WHERE t.started_at >= ?
+                 ORDER BY t.started_at, t.id",
+                TASK_COLUMNS
+            ),
+            &[Value::Integer(since_ms)],
+        )
+    }
+
+    /// Tasks in a session that have no prompt recorded yet, as (id, started_at)
+    pub fn get_tasks_missing_context(&self, session_id: &str) -> Result<Vec<(String, i64)>, StoreError> {
         let conn = self.conn.lock().map_err(|_| StoreError::LockError)?;
 
         let mut stmt = conn.prepare(
-            "SELECT id, session_id, tool, description, status, started_at, ended_at, duration_ms, is_background, subagent_type
-             FROM tasks
-             ORDER BY started_at DESC
-             LIMIT ?1"
+            "SELECT id, started_at FROM tasks WHERE session_id = ?1 AND prompt IS NULL"
         ).map_err(|e| StoreError::QueryError(e.to_string()))?;
 
-        let tasks = stmt.query_map(params![limit as i64], |row| {
-            Ok(StoredTask {
-                id: row.get(0)?,
-                session_id: row.get(1)?,
-                tool: row.get(2)?,
-                description: row.get(3)?,
-                status: row.get(4)?,
-                started_at: row.get(5)?,
-                ended_at: row.get(6)?,
-                duration_ms: row.get(7)?,
-                is_background: row.get::<_, i32>(8)? != 0,
-                subagent_type: row.get(9)?,
-            })
-        }).map_err(|e| StoreError::QueryError(e.to_string()))?
-        .filter_map(|r| r.ok())
-        .collect();
+        let tasks = stmt.query_map(params![session_id], |row| Ok((row.get(0)?, row.get(1)?)))
+            .map_err(|e| StoreError::QueryError(e.to_string()))?
+            .filter_map(|r| r.ok())
+            .collect();
 
         Ok(tasks)
     }
 
-    /// Get tasks by session
-    pub fn get_tasks_by_session(&self, session_id: &str) -> Result<Vec<StoredTask>, StoreError> {
+    /// Record the prompt a task ran for and the assistant's reply
+    ///
+    /// This doesn't count as a change for history sync; transcripts stay on
+    /// the machine they were written on.
+    pub fn set_task_context(&self, task_id: &str, context: &TaskContext) -> Result<(), StoreError> {
         let conn = self.conn.lock().map_err(|_| StoreError::LockError)?;
 
-        let mut stmt = conn.prepare(
-            "SELECT id, session_id, tool, description, status, started_at, ended_at, duration_ms, is_background, subagent_type
-             FROM tasks
-             WHERE session_id = ?1
-             ORDER BY started_at DESC"
+        conn.execute(
+            "UPDATE tasks SET prompt = ?1, summary = ?2 WHERE id = ?3",
+            params![context.prompt, context.summary, task_id],
         ).map_err(|e| StoreError::QueryError(e.to_string()))?;
 
-        let tasks = stmt.query_map(params![session_id], |row| {
-            Ok(StoredTask {
-                id: row.get(0)?,
-                session_id: row.get(1)?,
-                tool: row.get(2)?,
-                description: row.get(3)?,
-                status: row.get(4)?,
-                started_at: row.get(5)?,
-                ended_at: row.get(6)?,
-                duration_ms: row.get(7)?,
-                is_background: row.get::<_, i32>(8)? != 0,
-                subagent_type: row.get(9)?,
-            })
-        }).map_err(|e| StoreError::QueryError(e.to_string()))?
-        .filter_map(|r| r.ok())
-        .collect();
-
-        Ok(tasks)
+        Ok(())
     }
 
-    /// Search tasks by description (full-text search)
-    pub fn search_tasks(&self, query: &str, limit: usize) -> Result<Vec<StoredTask>, StoreError> {
+    /// Record the model that ran a task, unless one is already known
+    ///
+    /// Like the task's context, this stays out of history sync.
+    pub fn set_task_model(&self, task_id: &str, model: &str) -> Result<(), StoreError> {
         let conn = self.conn.lock().map_err(|_| StoreError::LockError)?;
 
-        let mut stmt = conn.prepare(
-            "SELECT t.id, t.session_id, t.tool, t.description, t.status, t.started_at, t.ended_at, t.duration_ms, t.is_background, t.subagent_type
-             FROM tasks t
-             JOIN tasks_fts fts ON t.id = fts.id
-             WHERE tasks_fts MATCH ?1
-             ORDER BY t.started_at DESC
-             LIMIT ?2"
+        conn.execute(
+            "UPDATE tasks SET model = ?1 WHERE id = ?2 AND model IS NULL",
+            params![model, task_id],
         ).map_err(|e| StoreError::QueryError(e.to_string()))?;
 
-        let tasks = stmt.query_map(params![query, limit as i64], |row| {
-            Ok(StoredTask {
-                id: row.get(0)?,
-                session_id: row.get(1)?,
-                tool: row.get(2)?,
-                description: row.get(3)?,
-                status: row.get(4)?,
-                started_at: row.get(5)?,
-                ended_at: row.get(6)?,
-                duration_ms: row.get(7)?,
-                is_background: row.get::<_, i32>(8)? != 0,
-                subagent_type: row.get(9)?,
-            })
-        }).map_err(|e| StoreError::QueryError(e.to_string()))?
-        .filter_map(|r| r.ok())
-        .collect();
+        Ok(())
+    }
 
-        Ok(tasks)
+    /// Record the process a background task runs in
+    pub fn set_task_pid(&self, task_id: &str, pid: u32) -> Result<(), StoreError> {
+        let conn = self.conn.lock().map_err(|_| StoreError::LockError)?;
+
+        conn.execute("UPDATE tasks SET pid = ?1 WHERE id = ?2", params![pid, task_id])
+            .map_err(|e| StoreError::QueryError(e.to_string()))?;
+
+        Ok(())
     }
 
-    /// Get active tasks count
-    pub fn get_active_task_count(&self) -> Result<i64, StoreError> {
+    /// The process a task runs in, if it is still active and one was recorded
+    pub fn get_active_task_pid(&self, task_id: &str) -> Result<Option<u32>, StoreError> {
         let conn = self.conn.lock().map_err(|_| StoreError::LockError)?;
 
-        let count: i64 = conn.query_row(
-            "SELECT COUNT(*) FROM tasks WHERE status = 'active'",
-            [],
+        let pid = conn.query_row(
+            "SELECT pid FROM tasks WHERE id = ?1 AND status = 'active'",
+            params![task_id],
             |row| row.get(0),
-        ).map_err(|e| StoreError::QueryError(e.to_string()))?;
+        ).optional().map_err(|e| StoreError::QueryError(e.to_string()))?;
 
-        Ok(count)
+        Ok(pid.flatten())
     }
 
-    /// Get task statistics
-    pub fn get_task_stats(&self) -> Result<TaskStats, StoreError> {
+    /// Active tasks with a process that hasn't been seen to exit, as (id, pid, started_at)
+    pub fn get_monitored_tasks(&self) -> Result<Vec<(String, u32, i64)>, StoreError> {
         let conn = self.conn.lock().map_err(|_| StoreError::LockError)?;
 
-        let total: i64 = conn.query_row(
-            "SELECT COUNT(*) FROM tasks",
-            [],
-            |row| row.get(0),
+        let mut stmt = conn.prepare(
+            "SELECT id, pid, started_at FROM tasks
+             WHERE status = 'active' AND pid IS NOT NULL AND (process_alive IS NULL OR process_alive = 1)"
         ).map_err(|e| StoreError::QueryError(e.to_string()))?;
 
-        let completed: i64 = conn.query_row(
-            "SELECT COUNT(*) FROM tasks WHERE status = 'completed'",
+        let tasks = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+            .map_err(|e| StoreError::QueryError(e.to_string()))?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        Ok(tasks)
+    }
+
+    /// Record what a task's processes are using, or that they are gone
+    ///
+    /// An exited process keeps the usage it was last seen with. Like the
+    /// task's context, none of this goes out with history sync.
+    pub fn set_task_process(&self, task_id: &str, usage: Option<&ProcessUsage>, now_ms: i64) -> Result<(), StoreError> {
+        let conn = self.conn.lock().map_err(|_| StoreError::LockError)?;
+
+        match usage {
+            Some(usage) => conn.execute(
+                "UPDATE tasks SET process_alive = 1, process_cpu = ?1, process_rss = ?2, process_count = ?3,
+                    process_sampled_at = ?4
+                 WHERE id = ?5",
+                params![usage.cpu_percent, usage.rss_bytes as i64, usage.processes, now_ms, task_id],
+            ),
+            None => conn.execute(
+                "UPDATE tasks SET process_alive = 0, process_sampled_at = ?1 WHERE id = ?2",
+                params![now_ms, task_id],
+            ),
+        }.map_err(|e| StoreError::QueryError(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// Record the exit code a task's command finished with
+    pub fn set_task_exit_code(&self, task_id: &str, exit_code: i32) -> Result<(), StoreError> {
+        let conn = self.conn.lock().map_err(|_| StoreError::LockError)?;
+
+        conn.execute("UPDATE tasks SET exit_code = ?1 WHERE id = ?2", params![exit_code, task_id])
+            .map_err(|e| StoreError::QueryError(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// A task's process as last sampled, and its exit code; none for tasks without either
+    pub fn get_task_process(&self, task_id: &str) -> Result<Option<ProcessInfo>, StoreError> {
+        let conn = self.conn.lock().map_err(|_| StoreError::LockError)?;
+
+        let info = conn.query_row(
+            "SELECT pid, exit_code, process_alive, process_cpu, process_rss, process_count, process_sampled_at
+             FROM tasks WHERE id = ?1 AND (pid IS NOT NULL OR exit_code IS NOT NULL)",
+            params![task_id],
+            |row| {
+                let alive: Option<bool> = row.get(2)?;
+                let usage = match alive {
+                    Some(alive) => Some(ProcessUsage {
+                        alive,
+                        cpu_percent: row.get::<_, Option<f64>>(3)?.unwrap_or(0.0),
+                        rss_bytes: row.get::<_, Option<i64>>(4)?.unwrap_or(0) as u64,
+                        processes: row.get::<_, Option<u32>>(5)?.unwrap_or(0),
+                        sampled_at: row.get(6)?,
+                    }),
+                    None => None,
+                };
+                Ok(ProcessInfo { pid: row.get(0)?, exit_code: row.get(1)?, usage })
+            },
+        ).optional().map_err(|e| StoreError::QueryError(e.to_string()))?;
+
+        Ok(info)
+    }
+
+    /// Append an event to the event log, returning its sequence number
+    ///
+    /// Events that fell out of the last `EVENT_LOG_CAPACITY` are dropped.
+    pub fn log_event(&self, kind: &str, payload: &str, at: i64) -> Result<i64, StoreError> {
+        let conn = self.conn.lock().map_err(|_| StoreError::LockError)?;
+
+        conn.execute(
+            "INSERT INTO event_log (kind, payload, at) VALUES (?1, ?2, ?3)",
+            params![kind, payload, at],
+        ).map_err(|e| StoreError::QueryError(e.to_string()))?;
+        let seq = conn.last_insert_rowid();
+        conn.execute("DELETE FROM event_log WHERE seq <= ?1", params![seq - EVENT_LOG_CAPACITY])
+            .map_err(|e| StoreError::QueryError(e.to_string()))?;
+
+        Ok(seq)
+    }
+
+    /// Up to `limit` logged events after `cursor`, oldest first, as (seq, at, payload)
+    pub fn get_logged_events(&self, cursor: i64, limit: usize) -> Result<Vec<(i64, i64, String)>, StoreError> {
+        let conn = self.conn.lock().map_err(|_| StoreError::LockError)?;
+
+        let mut stmt = conn.prepare(
+            "SELECT seq, at, payload FROM event_log WHERE seq > ?1 ORDER BY seq LIMIT ?2"
+        ).map_err(|e| StoreError::QueryError(e.to_string()))?;
+
+        let events = stmt.query_map(params![cursor, limit as i64], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+            .map_err(|e| StoreError::QueryError(e.to_string()))?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        Ok(events)
+    }
+
+    /// The oldest and newest sequence numbers in the event log; (0, 0) while it is empty
+    ///
+    /// The newest survives the log being emptied, since sequence numbers are never reused.
+    pub fn event_log_bounds(&self) -> Result<(i64, i64), StoreError> {
+        let conn = self.conn.lock().map_err(|_| StoreError::LockError)?;
+
+        let oldest: Option<i64> = conn.query_row("SELECT MIN(seq) FROM event_log", [], |row| row.get(0))
+            .map_err(|e| StoreError::QueryError(e.to_string()))?;
+        let newest: Option<i64> = conn.query_row(
+            "SELECT seq FROM sqlite_sequence WHERE name = 'event_log'",
             [],
             |row| row.get(0),
+        ).optional().map_err(|e| StoreError::QueryError(e.to_string()))?;
+
+        Ok((oldest.unwrap_or(0), newest.unwrap_or(0)))
+    }
+
+    /// The prompt and reply recorded for a task, if any
+    pub fn get_task_context(&self, task_id: &str) -> Result<Option<TaskContext>, StoreError> {
+        let conn = self.conn.lock().map_err(|_| StoreError::LockError)?;
+
+        let context = conn.query_row(
+            "SELECT prompt, summary FROM tasks WHERE id = ?1 AND prompt IS NOT NULL",
+            params![task_id],
+            |row| Ok(TaskContext { prompt: row.get(0)?, summary: row.get(1)? }),
+        ).optional().map_err(|e| StoreError::QueryError(e.to_string()))?;
+
+        Ok(context)
+    }
+
+    /// Mark tasks as reviewed at `now_ms`, or clear the mark, returning how many changed
+    ///
+    /// Marking a task that is already reviewed keeps its first review time.
+    /// Reviews stay on this machine rather than going out with history sync.
+    pub fn set_reviewed(&self, task_ids: &[String], reviewed: bool, now_ms: i64) -> Result<usize, StoreError> {
+        let mut conn = self.conn.lock().map_err(|_| StoreError::LockError)?;
+        let tx = conn.transaction().map_err(|e| StoreError::QueryError(e.to_string()))?;
+        let mut changed = 0;
+
+        {
+            let mut stmt = tx.prepare(
+                "UPDATE tasks SET reviewed_at = CASE WHEN ?1 THEN COALESCE(reviewed_at, ?2) END
+                 WHERE id = ?3 AND (reviewed_at IS NULL) = ?1"
+            ).map_err(|e| StoreError::QueryError(e.to_string()))?;
+            for task_id in task_ids {
+                changed += stmt.execute(params![reviewed, now_ms, task_id])
+                    .map_err(|e| StoreError::QueryError(e.to_string()))?;
+            }
+        }
+
+        tx.commit().map_err(|e| StoreError::QueryError(e.to_string()))?;
+        Ok(changed)
+    }
+
+    /// Pin a task at `now_ms`, or unpin it, returning whether it changed
+    ///
+    /// Like reviews, pins stay on this machine.
+    pub fn set_pinned(&self, task_id: &str, pinned: bool, now_ms: i64) -> Result<bool, StoreError> {
+        let conn = self.conn.lock().map_err(|_| StoreError::LockError)?;
+
+        let changed = conn.execute(
+            "UPDATE tasks SET pinned_at = CASE WHEN ?1 THEN ?2 END
+             WHERE id = ?3 AND (pinned_at IS NULL) = ?1",
+            params![pinned, now_ms, task_id],
+        ).map_err(|e| StoreError::QueryError(e.to_string()))?;
+
+        Ok(changed > 0)
+    }
+
+    /// Pinned tasks, most recently pinned first
+    pub fn get_pinned_tasks(&self) -> Result<Vec<StoredTask>, StoreError> {
+        self.query_tasks(
+            &format!(
+                "SELECT {} FROM tasks t
+                 WHERE t.pinned_at IS NOT NULL
+                 ORDER BY t.pinned_at DESC, t.id DESC",
+                TASK_COLUMNS
+            ),
+            &[],
+        )
+    }
+
+    /// Mark every finished task matching a filter as reviewed, returning how many changed
+    pub fn mark_all_reviewed(&self, filter: &TaskFilter, now_ms: i64) -> Result<usize, StoreError> {
+        let (mut conditions, mut values) = filter.conditions();
+        conditions.push("t.status != 'active'");
+        conditions.push("t.reviewed_at IS NULL");
+        values.insert(0, Value::Integer(now_ms));
+
+        let conn = self.conn.lock().map_err(|_| StoreError::LockError)?;
+        conn.execute(
+            &format!(
+                "UPDATE tasks SET reviewed_at = ? WHERE id IN (
+                    SELECT t.id FROM tasks t
+                    LEFT JOIN sessions s ON s.id = t.session_id
+                    {}
+                 )",
+                where_clause(&conditions)
+            ),
+            params_from_iter(values),
+        ).map_err(|e| StoreError::QueryError(e.to_string()))
+    }
+
+    /// Search tasks by description, prompt, and reply (full-text search) matching a filter, newest first
+    pub fn search_tasks(&self, query: &str, filter: &TaskFilter, limit: usize) -> Result<Vec<StoredTask>, StoreError> {
+        let (sql, values) = search_tasks_query(query, filter, limit);
+        self.query_tasks(&sql, &values)
+    }
+
+    /// Run a task query with positional parameters
+    fn query_tasks(&self, sql: &str, values: &[Value]) -> Result<Vec<StoredTask>, StoreError> {
+        let conn = self.conn.lock().map_err(|_| StoreError::LockError)?;
+
+        let mut stmt = conn.prepare(sql)
+            .map_err(|e| StoreError::QueryError(e.to_string()))?;
+
+        let tasks = stmt.query_map(params_from_iter(values), row_to_task)
+            .map_err(|e| StoreError::QueryError(e.to_string()))?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        Ok(tasks)
+    }
+
+    /// Tasks still active, oldest first
+    pub fn get_active_tasks(&self) -> Result<Vec<StoredTask>, StoreError> {
+        self.get_active_tasks_before(i64::MAX)
+    }
+
+    /// Tasks still active that started before `cutoff_ms`
+    pub fn get_active_tasks_before(&self, cutoff_ms: i64) -> Result<Vec<StoredTask>, StoreError> {
+        self.query_tasks(
+            &format!(
+                "SELECT {} FROM tasks t
+                 WHERE t.status = 'active' AND t.started_at < ?
+                 ORDER BY t.started_at, t.id",
+                TASK_COLUMNS
+            ),
+            &[Value::Integer(cutoff_ms)],
+        )
+    }
+
+    /// Get active tasks count
+    pub fn get_active_task_count(&self) -> Result<i64, StoreError> {
+        let conn = self.conn.lock().map_err(|_| StoreError::LockError)?;
+
+        let count: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM tasks WHERE status = 'active'",
+            [],
+            |row| row.get(0),
+        ).map_err(|e| StoreError::QueryError(e.to_string()))?;
+
+        Ok(count)
+    }
+
+    /// How long a task usually takes: the median duration of recent
+    /// completed tasks with the same tool whose description starts with `prefix`
+    ///
+    /// The prefix must end on a word boundary, so "npm test" doesn't match
+    /// "npm testing". Returns None when there are too few such tasks.
+    pub fn estimate_duration(&self, tool: &str, prefix: &str) -> Result<Option<i64>, StoreError> {
+        let conn = self.conn.lock().map_err(|_| StoreError::LockError)?;
+
+        let mut stmt = conn.prepare(
+            "SELECT duration_ms FROM tasks
+             WHERE tool = ?1 AND (description = ?2 OR description LIKE ?3 ESCAPE '^')
+               AND status = 'completed' AND duration_suspect = 0 AND duration_ms IS NOT NULL
+             ORDER BY started_at DESC
+             LIMIT ?4"
+        ).map_err(|e| StoreError::QueryError(e.to_string()))?;
+
+        let pattern = format!("{} %", escape_like(prefix));
+        let mut durations: Vec<i64> = stmt.query_map(params![tool, prefix, pattern, ESTIMATE_SAMPLE_LIMIT], |row| row.get(0))
+            .map_err(|e| StoreError::QueryError(e.to_string()))?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        if durations.len() < ESTIMATE_MIN_SAMPLES {
+            return Ok(None);
+        }
+        durations.sort_unstable();
+        Ok(Some(durations[durations.len() / 2]))
+    }
+
+    /// Get task statistics
+    pub fn get_task_stats(&self) -> Result<TaskStats, StoreError> {
+        let conn = self.conn.lock().map_err(|_| StoreError::LockError)?;
+
+        // One pass over idx_tasks_status_duration rather than a scan per figure
+        conn.query_row(STATS_QUERY, [], row_to_stats)
+            .map_err(|e| StoreError::QueryError(e.to_string()))
+    }
+
+    /// Task statistics for each bucket, counting tasks by when they started
+    pub fn get_bucketed_stats(&self, buckets: &[Bucket]) -> Result<Vec<BucketStats>, StoreError> {
+        let conn = self.conn.lock().map_err(|_| StoreError::LockError)?;
+        let mut stmt = conn.prepare(&format!("{} WHERE started_at >= ?1 AND started_at < ?2", STATS_QUERY))
+            .map_err(|e| StoreError::QueryError(e.to_string()))?;
+
+        buckets.iter().map(|bucket| {
+            let stats = stmt.query_row(params![bucket.start_ms, bucket.end_ms], row_to_stats)
+                .map_err(|e| StoreError::QueryError(e.to_string()))?;
+            Ok(BucketStats { bucket: bucket.clone(), stats })
+        }).collect()
+    }
+
+    /// Size of the write-ahead log in bytes (0 for in-memory stores)
+    pub fn wal_size(&self) -> Result<u64, StoreError> {
+        let conn = self.conn.lock().map_err(|_| StoreError::LockError)?;
+        let Some(path) = conn.path().filter(|p| !p.is_empty()) else {
+            return Ok(0);
+        };
+        Ok(std::fs::metadata(format!("{}-wal", path)).map(|m| m.len()).unwrap_or(0))
+    }
+
+    /// Copy the write-ahead log into the database and truncate it
+    ///
+    /// Returns false if readers kept part of the log from being copied; the
+    /// next checkpoint picks up where this one stopped.
+    pub fn wal_checkpoint(&self) -> Result<bool, StoreError> {
+        let conn = self.conn.lock().map_err(|_| StoreError::LockError)?;
+        let busy: i64 = conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |row| row.get(0))
+            .map_err(|e| StoreError::QueryError(e.to_string()))?;
+        Ok(busy == 0)
+    }
+
+    /// Problems `PRAGMA integrity_check` finds; a healthy database reports just "ok"
+    pub fn integrity_check(&self) -> Result<Vec<String>, StoreError> {
+        let conn = self.conn.lock().map_err(|_| StoreError::LockError)?;
+        integrity_check(&conn)
+    }
+
+    /// Number of migrations applied to the database
+    pub fn schema_version(&self) -> Result<i64, StoreError> {
+        let conn = self.conn.lock().map_err(|_| StoreError::LockError)?;
+        schema_version(&conn)
+    }
+
+    /// Delete finished tasks that started before `cutoff_ms`, except pinned ones
+    pub fn cleanup_old_tasks(&self, cutoff_ms: i64) -> Result<i64, StoreError> {
+        let conn = self.conn.lock().map_err(|_| StoreError::LockError)?;
+
+        let deleted = conn.execute(
+            "DELETE FROM tasks WHERE started_at < ?1 AND status != 'active' AND pinned_at IS NULL",
+            params![cutoff_ms],
+        ).map_err(|e| StoreError::QueryError(e.to_string()))?;
+        conn.execute("DELETE FROM context_samples WHERE timestamp < ?1", params![cutoff_ms])
+            .map_err(|e| StoreError::QueryError(e.to_string()))?;
+        conn.execute("DELETE FROM usage_warnings WHERE timestamp < ?1", params![cutoff_ms])
+            .map_err(|e| StoreError::QueryError(e.to_string()))?;
+        conn.execute("DELETE FROM custom_events WHERE timestamp < ?1", params![cutoff_ms])
+            .map_err(|e| StoreError::QueryError(e.to_string()))?;
+        conn.execute("DELETE FROM token_usage WHERE timestamp < ?1", params![cutoff_ms])
+            .map_err(|e| StoreError::QueryError(e.to_string()))?;
+        conn.execute("DELETE FROM notifications WHERE shown_at < ?1", params![cutoff_ms])
+            .map_err(|e| StoreError::QueryError(e.to_string()))?;
+        conn.execute("DELETE FROM todo_transitions WHERE at < ?1", params![cutoff_ms])
+            .map_err(|e| StoreError::QueryError(e.to_string()))?;
+        conn.execute("DELETE FROM todos WHERE updated_at < ?1", params![cutoff_ms])
+            .map_err(|e| StoreError::QueryError(e.to_string()))?;
+        conn.execute("DELETE FROM task_tags WHERE task_id NOT IN (SELECT id FROM tasks)", [])
+            .map_err(|e| StoreError::QueryError(e.to_string()))?;
+
+        Ok(deleted as i64)
+    }
+
+    // ========== Todo History Operations ==========
+
+    /// Record todo status changes; "removed" marks a todo that was dropped
+    pub fn insert_todo_transitions(&self, transitions: &[TodoTransition]) -> Result<(), StoreError> {
+        let mut conn = self.conn.lock().map_err(|_| StoreError::LockError)?;
+        let tx = conn.transaction().map_err(|e| StoreError::QueryError(e.to_string()))?;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT INTO todo_transitions (session_id, content, status, at) VALUES (?1, ?2, ?3, ?4)",
+            ).map_err(|e| StoreError::QueryError(e.to_string()))?;
+            for t in transitions {
+                stmt.execute(params![t.session_id, t.content, t.status, t.at])
+                    .map_err(|e| StoreError::QueryError(e.to_string()))?;
+            }
+        }
+        tx.commit().map_err(|e| StoreError::QueryError(e.to_string()))
+    }
+
+    /// Archive todos completed at `at`; one already archived keeps its first completion time
+    ///
+    /// A todo's creation time is when it was first seen, if its history goes back that far.
+    pub fn archive_completed_todos(&self, todos: &[(String, String, String)], at: i64) -> Result<(), StoreError> {
+        let mut conn = self.conn.lock().map_err(|_| StoreError::LockError)?;
+        let tx = conn.transaction().map_err(|e| StoreError::QueryError(e.to_string()))?;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT OR IGNORE INTO todos (session_id, content, active_form, status, created_at, updated_at)
+                 VALUES (?1, ?2, ?3, 'completed',
+                    COALESCE((SELECT MIN(at) FROM todo_transitions WHERE session_id = ?1 AND content = ?2), ?4), ?4)",
+            ).map_err(|e| StoreError::QueryError(e.to_string()))?;
+            for (session_id, content, active_form) in todos {
+                stmt.execute(params![session_id, content, active_form, at])
+                    .map_err(|e| StoreError::QueryError(e.to_string()))?;
+            }
+        }
+        tx.commit().map_err(|e| StoreError::QueryError(e.to_string()))
+    }
+
+    /// Todos completed in `[since_ms, until_ms)`, most recent first
+    pub fn get_completed_todos(&self, since_ms: i64, until_ms: i64) -> Result<Vec<StoredTodo>, StoreError> {
+        let conn = self.conn.lock().map_err(|_| StoreError::LockError)?;
+
+        let mut stmt = conn.prepare(
+            "SELECT id, session_id, content, active_form, status, created_at, updated_at FROM todos
+             WHERE status = 'completed' AND updated_at >= ?1 AND updated_at < ?2
+             ORDER BY updated_at DESC, id DESC",
+        ).map_err(|e| StoreError::QueryError(e.to_string()))?;
+
+        let todos = stmt.query_map(params![since_ms, until_ms], |row| {
+            Ok(StoredTodo {
+                id: row.get(0)?,
+                session_id: row.get(1)?,
+                content: row.get(2)?,
+                active_form: row.get(3)?,
+                status: row.get(4)?,
+                created_at: row.get(5)?,
+                updated_at: row.get(6)?,
+            })
+        }).map_err(|e| StoreError::QueryError(e.to_string()))?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        Ok(todos)
+    }
+
+    /// Completed and remaining todos in a session after each recorded change
+    pub fn get_todo_burndown(&self, session_id: &str) -> Result<Vec<BurndownPoint>, StoreError> {
+        let conn = self.conn.lock().map_err(|_| StoreError::LockError)?;
+
+        let mut stmt = conn.prepare(
+            "SELECT content, status, at FROM todo_transitions WHERE session_id = ?1 ORDER BY at, id",
+        ).map_err(|e| StoreError::QueryError(e.to_string()))?;
+
+        let rows = stmt.query_map(params![session_id], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, i64>(2)?))
+        }).map_err(|e| StoreError::QueryError(e.to_string()))?;
+
+        let mut statuses: HashMap<String, String> = HashMap::new();
+        let mut points: Vec<BurndownPoint> = Vec::new();
+        for (content, status, at) in rows.filter_map(|r| r.ok()) {
+            if status == "removed" {
+                statuses.remove(&content);
+            } else {
+                statuses.insert(content, status);
+            }
+            let completed = statuses.values().filter(|s| *s == "completed").count() as i64;
+            let point = BurndownPoint { at, completed, remaining: statuses.len() as i64 - completed };
+            // Changes read in the same scan make one point
+            match points.last_mut() {
+                Some(last) if last.at == at => *last = point,
+                _ => points.push(point),
+            }
+        }
+
+        Ok(points)
+    }
+
+    // ========== Todo Order Operations ==========
+
+    /// Replace the todo order with `hashes`, first to last
+    pub fn set_todo_order(&self, hashes: &[String], now_ms: i64) -> Result<(), StoreError> {
+        let mut conn = self.conn.lock().map_err(|_| StoreError::LockError)?;
+        let tx = conn.transaction().map_err(|e| StoreError::QueryError(e.to_string()))?;
+
+        tx.execute("DELETE FROM todo_order", [])
+            .map_err(|e| StoreError::QueryError(e.to_string()))?;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT OR IGNORE INTO todo_order (content_hash, position, updated_at) VALUES (?1, ?2, ?3)",
+            ).map_err(|e| StoreError::QueryError(e.to_string()))?;
+            for (position, hash) in hashes.iter().enumerate() {
+                stmt.execute(params![hash, position as i64, now_ms])
+                    .map_err(|e| StoreError::QueryError(e.to_string()))?;
+            }
+        }
+
+        tx.commit().map_err(|e| StoreError::QueryError(e.to_string()))
+    }
+
+    /// Position of each todo the user has ordered, by content hash
+    pub fn get_todo_order(&self) -> Result<HashMap<String, i64>, StoreError> {
+        let conn = self.conn.lock().map_err(|_| StoreError::LockError)?;
+
+        let mut stmt = conn.prepare("SELECT content_hash, position FROM todo_order")
+            .map_err(|e| StoreError::QueryError(e.to_string()))?;
+
+        let order = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .map_err(|e| StoreError::QueryError(e.to_string()))?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        Ok(order)
+    }
+
+    // ========== Tag Operations ==========
+
+    /// Create a tag, or return the existing one with the same name (ignoring case)
+    pub fn create_tag(&self, name: &str, now_ms: i64) -> Result<Tag, StoreError> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(StoreError::QueryError("tag name is empty".to_string()));
+        }
+        let conn = self.conn.lock().map_err(|_| StoreError::LockError)?;
+
+        conn.execute(
+            "INSERT OR IGNORE INTO tags (name, created_at) VALUES (?1, ?2)",
+            params![name, now_ms],
+        ).map_err(|e| StoreError::QueryError(e.to_string()))?;
+
+        conn.query_row("SELECT id, name, created_at FROM tags WHERE name = ?1", params![name], row_to_tag)
+            .map_err(|e| StoreError::QueryError(e.to_string()))
+    }
+
+    /// Rename a tag, returning false if it doesn't exist
+    pub fn rename_tag(&self, id: i64, name: &str) -> Result<bool, StoreError> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(StoreError::QueryError("tag name is empty".to_string()));
+        }
+        let conn = self.conn.lock().map_err(|_| StoreError::LockError)?;
+
+        let changed = conn.execute("UPDATE tags SET name = ?1 WHERE id = ?2", params![name, id])
+            .map_err(|e| StoreError::QueryError(e.to_string()))?;
+        Ok(changed > 0)
+    }
+
+    /// Delete a tag and take it off every session and task, returning false if it doesn't exist
+    pub fn delete_tag(&self, id: i64) -> Result<bool, StoreError> {
+        let mut conn = self.conn.lock().map_err(|_| StoreError::LockError)?;
+        let tx = conn.transaction().map_err(|e| StoreError::QueryError(e.to_string()))?;
+
+        tx.execute("DELETE FROM session_tags WHERE tag_id = ?1", params![id])
+            .map_err(|e| StoreError::QueryError(e.to_string()))?;
+        tx.execute("DELETE FROM task_tags WHERE tag_id = ?1", params![id])
+            .map_err(|e| StoreError::QueryError(e.to_string()))?;
+        let deleted = tx.execute("DELETE FROM tags WHERE id = ?1", params![id])
+            .map_err(|e| StoreError::QueryError(e.to_string()))?;
+
+        tx.commit().map_err(|e| StoreError::QueryError(e.to_string()))?;
+        Ok(deleted > 0)
+    }
+
+    /// Every tag, by name
+    pub fn list_tags(&self) -> Result<Vec<Tag>, StoreError> {
+        let conn = self.conn.lock().map_err(|_| StoreError::LockError)?;
+
+        let mut stmt = conn.prepare("SELECT id, name, created_at FROM tags ORDER BY name")
+            .map_err(|e| StoreError::QueryError(e.to_string()))?;
+
+        let tags = stmt.query_map([], row_to_tag)
+            .map_err(|e| StoreError::QueryError(e.to_string()))?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        Ok(tags)
+    }
+
+    /// Put the tag named `name` on a session, creating the tag if needed, or take it off
+    ///
+    /// Returns whether anything changed. Tags stay on this machine.
+    pub fn set_session_tag(&self, session_id: &str, name: &str, tagged: bool, now_ms: i64) -> Result<bool, StoreError> {
+        self.set_tag("session_tags", "session_id", session_id, name, tagged, now_ms)
+    }
+
+    /// Put the tag named `name` on a task, creating the tag if needed, or take it off
+    pub fn set_task_tag(&self, task_id: &str, name: &str, tagged: bool, now_ms: i64) -> Result<bool, StoreError> {
+        self.set_tag("task_tags", "task_id", task_id, name, tagged, now_ms)
+    }
+
+    fn set_tag(&self, table: &str, column: &str, id: &str, name: &str, tagged: bool, now_ms: i64) -> Result<bool, StoreError> {
+        let tag = if tagged {
+            self.create_tag(name, now_ms)?
+        } else {
+            match self.list_tags()?.into_iter().find(|tag| tag.name.eq_ignore_ascii_case(name.trim())) {
+                Some(tag) => tag,
+                None => return Ok(false),
+            }
+        };
+        let conn = self.conn.lock().map_err(|_| StoreError::LockError)?;
+
+        let sql = if tagged {
+            format!("INSERT OR IGNORE INTO {} ({}, tag_id) VALUES (?1, ?2)", table, column)
+        } else {
+            format!("DELETE FROM {} WHERE {} = ?1 AND tag_id = ?2", table, column)
+        };
+        let changed = conn.execute(&sql, params![id, tag.id])
+            .map_err(|e| StoreError::QueryError(e.to_string()))?;
+        Ok(changed > 0)
+    }
+
+    /// Tags on a session, by name
+    pub fn get_session_tags(&self, session_id: &str) -> Result<Vec<Tag>, StoreError> {
+        self.query_tags("SELECT g.id, g.name, g.created_at FROM tags g
+             JOIN session_tags st ON st.tag_id = g.id
+             WHERE st.session_id = ?1
+             ORDER BY g.name", session_id)
+    }
+
+    /// Tags on a task, including those on its session, by name
+    pub fn get_task_tags(&self, task_id: &str) -> Result<Vec<Tag>, StoreError> {
+        self.query_tags("SELECT g.id, g.name, g.created_at FROM tags g
+             WHERE g.id IN (SELECT tag_id FROM task_tags WHERE task_id = ?1)
+                OR g.id IN (SELECT st.tag_id FROM session_tags st JOIN tasks t ON t.session_id = st.session_id WHERE t.id = ?1)
+             ORDER BY g.name", task_id)
+    }
+
+    fn query_tags(&self, sql: &str, id: &str) -> Result<Vec<Tag>, StoreError> {
+        let conn = self.conn.lock().map_err(|_| StoreError::LockError)?;
+
+        let mut stmt = conn.prepare(sql)
+            .map_err(|e| StoreError::QueryError(e.to_string()))?;
+
+        let tags = stmt.query_map(params![id], row_to_tag)
+            .map_err(|e| StoreError::QueryError(e.to_string()))?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        Ok(tags)
+    }
+
+    /// Task statistics for each tag, counting tasks tagged directly or through their session
+    pub fn get_tag_stats(&self) -> Result<Vec<TagStats>, StoreError> {
+        let tags = self.list_tags()?;
+        let conn = self.conn.lock().map_err(|_| StoreError::LockError)?;
+        let mut stmt = conn.prepare(&format!("{} WHERE id IN ({})", STATS_QUERY, TAGGED_TASKS))
+            .map_err(|e| StoreError::QueryError(e.to_string()))?;
+
+        tags.into_iter().map(|tag| {
+            let stats = stmt.query_row(params![tag.id], row_to_stats)
+                .map_err(|e| StoreError::QueryError(e.to_string()))?;
+            Ok(TagStats { tag, stats })
+        }).collect()
+    }
+
+    // ========== Context Usage Operations ==========
+
+    /// Record how full a session's context window was
+    pub fn insert_context_sample(&self, sample: &ContextSample) -> Result<(), StoreError> {
+        let conn = self.conn.lock().map_err(|_| StoreError::LockError)?;
+
+        conn.execute(
+            "INSERT INTO context_samples (session_id, timestamp, context_tokens, context_window, model)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![sample.session_id, sample.timestamp, sample.context_tokens, sample.context_window, sample.model],
         ).map_err(|e| StoreError::QueryError(e.to_string()))?;
 
-        let errors: i64 = conn.query_row(
-            "SELECT COUNT(*) FROM tasks WHERE status = 'error'",
-            [],
-            |row| row.get(0),
-        ).map_err(|e| StoreError::QueryError(e.to_string()))?;
+        Ok(())
+    }
+
+    /// A session's context samples, oldest first
+    pub fn get_context_samples(&self, session_id: &str) -> Result<Vec<ContextSample>, StoreError> {
+        let conn = self.conn.lock().map_err(|_| StoreError::LockError)?;
+
+        let mut stmt = conn.prepare(
+            "SELECT session_id, timestamp, context_tokens, context_window, model FROM context_samples
+             WHERE session_id = ?1
+             ORDER BY timestamp, id"
+        ).map_err(|e| StoreError::QueryError(e.to_string()))?;
+
+        let samples = stmt.query_map(params![session_id], |row| {
+            Ok(ContextSample {
+                session_id: row.get(0)?,
+                timestamp: row.get(1)?,
+                context_tokens: row.get(2)?,
+                context_window: row.get(3)?,
+                model: row.get(4)?,
+            })
+        })
+        .map_err(|e| StoreError::QueryError(e.to_string()))?
+        .filter_map(|r| r.ok())
+        .collect();
+
+        Ok(samples)
+    }
+
+    // ========== Model Usage Operations ==========
+
+    /// Record the tokens one of the assistant's replies used
+    pub fn insert_token_usage(&self, usage: &TokenUsage) -> Result<(), StoreError> {
+        let conn = self.conn.lock().map_err(|_| StoreError::LockError)?;
+
+        conn.execute(
+            "INSERT INTO token_usage (session_id, timestamp, model, input_tokens, output_tokens, cache_read_tokens, cache_creation_tokens)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                usage.session_id,
+                usage.timestamp,
+                usage.model,
+                usage.input_tokens,
+                usage.output_tokens,
+                usage.cache_read_tokens,
+                usage.cache_creation_tokens,
+            ],
+        ).map_err(|e| StoreError::QueryError(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// Tasks and tokens per model since `since_ms`, the busiest model first
+    ///
+    /// Tasks whose model isn't known yet are grouped under `None`. Costs
+    /// depend on prices from the config, so they are left for the caller.
+    pub fn get_model_usage(&self, since_ms: i64) -> Result<Vec<ModelUsage>, StoreError> {
+        let conn = self.conn.lock().map_err(|_| StoreError::LockError)?;
+
+        let mut usage: Vec<ModelUsage> = Vec::new();
+        let mut stmt = conn.prepare(MODEL_STATS_QUERY)
+            .map_err(|e| StoreError::QueryError(e.to_string()))?;
+        let rows = stmt.query_map(params![since_ms], |row| {
+            Ok(ModelUsage {
+                model: row.get(5)?,
+                stats: row_to_stats(row)?,
+                total_duration_ms: row.get(6)?,
+                ..ModelUsage::default()
+            })
+        }).map_err(|e| StoreError::QueryError(e.to_string()))?;
+        usage.extend(rows.filter_map(|r| r.ok()));
+
+        let mut stmt = conn.prepare(MODEL_TOKENS_QUERY)
+            .map_err(|e| StoreError::QueryError(e.to_string()))?;
+        let rows = stmt.query_map(params![since_ms], |row| {
+            Ok((row.get::<_, String>(0)?, [row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?, row.get(5)?]))
+        }).map_err(|e| StoreError::QueryError(e.to_string()))?;
+        for (model, [requests, input, output, cache_read, cache_creation]) in rows.filter_map(|r| r.ok()) {
+            let index = match usage.iter().position(|u| u.model.as_deref() == Some(model.as_str())) {
+                Some(index) => index,
+                None => {
+                    usage.push(ModelUsage { model: Some(model), ..ModelUsage::default() });
+                    usage.len() - 1
+                }
+            };
+            let entry = &mut usage[index];
+            entry.requests = requests;
+            entry.input_tokens = input;
+            entry.output_tokens = output;
+            entry.cache_read_tokens = cache_read;
+            entry.cache_creation_tokens = cache_creation;
+        }
+
+        usage.sort_by(|a, b| {
+            b.stats.total_tasks.cmp(&a.stats.total_tasks).then(b.requests.cmp(&a.requests))
+        });
+        Ok(usage)
+    }
+
+    // ========== Focus Session Operations ==========
+
+    /// Start a focus session at `now_ms`, ending any that is still running
+    pub fn start_focus_session(&self, label: Option<&str>, planned_ms: Option<i64>, now_ms: i64) -> Result<FocusSession, StoreError> {
+        self.stop_focus_session(now_ms)?;
+        let conn = self.conn.lock().map_err(|_| StoreError::LockError)?;
+
+        conn.execute(
+            "INSERT INTO focus_sessions (label, started_at, planned_ms) VALUES (?1, ?2, ?3)",
+            params![label, now_ms, planned_ms],
+        ).map_err(|e| StoreError::QueryError(e.to_string()))?;
+
+        Ok(FocusSession {
+            id: conn.last_insert_rowid(),
+            label: label.map(str::to_string),
+            started_at: now_ms,
+            ended_at: None,
+            planned_ms,
+            completed_tasks: 0,
+            failed_tasks: 0,
+            task_time_ms: 0,
+        })
+    }
+
+    /// End the running focus session at `now_ms`, summarizing the tasks that finished during it
+    ///
+    /// Returns the finished session, or None if none was running.
+    pub fn stop_focus_session(&self, now_ms: i64) -> Result<Option<FocusSession>, StoreError> {
+        let conn = self.conn.lock().map_err(|_| StoreError::LockError)?;
+
+        let Some(mut session) = query_active_focus_session(&conn)? else {
+            return Ok(None);
+        };
+        let (completed, failed, task_time): (i64, i64, i64) = conn.query_row(
+            "SELECT
+                COALESCE(SUM(status = 'completed'), 0),
+                COALESCE(SUM(status = 'error'), 0),
+                COALESCE(SUM(CASE WHEN duration_suspect = 0 THEN duration_ms END), 0)
+             FROM tasks
+             WHERE ended_at >= ?1 AND ended_at <= ?2 AND status IN ('completed', 'error')",
+            params![session.started_at, now_ms],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        ).map_err(|e| StoreError::QueryError(e.to_string()))?;
+
+        conn.execute(
+            "UPDATE focus_sessions SET ended_at = ?1, completed_tasks = ?2, failed_tasks = ?3, task_time_ms = ?4 WHERE id = ?5",
+            params![now_ms, completed, failed, task_time, session.id],
+        ).map_err(|e| StoreError::QueryError(e.to_string()))?;
+
+        session.ended_at = Some(now_ms);
+        session.completed_tasks = completed;
+        session.failed_tasks = failed;
+        session.task_time_ms = task_time;
+        Ok(Some(session))
+    }
+
+    /// The focus session still running, if any
+    pub fn get_active_focus_session(&self) -> Result<Option<FocusSession>, StoreError> {
+        let conn = self.conn.lock().map_err(|_| StoreError::LockError)?;
+        query_active_focus_session(&conn)
+    }
+
+    /// Recent focus sessions, newest first
+    pub fn get_focus_sessions(&self, limit: i64) -> Result<Vec<FocusSession>, StoreError> {
+        let conn = self.conn.lock().map_err(|_| StoreError::LockError)?;
+
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM focus_sessions ORDER BY started_at DESC, id DESC LIMIT ?1",
+            FOCUS_SESSION_COLUMNS
+        )).map_err(|e| StoreError::QueryError(e.to_string()))?;
+
+        let sessions = stmt.query_map(params![limit], row_to_focus_session)
+            .map_err(|e| StoreError::QueryError(e.to_string()))?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        Ok(sessions)
+    }
+
+    // ========== Usage Warning Operations ==========
+
+    /// Record a rate-limit or usage-limit warning
+    pub fn insert_usage_warning(&self, warning: &UsageWarning) -> Result<(), StoreError> {
+        let conn = self.conn.lock().map_err(|_| StoreError::LockError)?;
+
+        conn.execute(
+            "INSERT INTO usage_warnings (session_id, kind, message, resets_at, timestamp, source)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![warning.session_id, warning.kind, warning.message, warning.resets_at, warning.timestamp, warning.source],
+        ).map_err(|e| StoreError::QueryError(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// The most recent usage warnings, newest first
+    pub fn get_usage_warnings(&self, limit: i64) -> Result<Vec<UsageWarning>, StoreError> {
+        let conn = self.conn.lock().map_err(|_| StoreError::LockError)?;
+
+        let mut stmt = conn.prepare(
+            "SELECT session_id, kind, message, resets_at, timestamp, source FROM usage_warnings
+             ORDER BY timestamp DESC, id DESC
+             LIMIT ?1"
+        ).map_err(|e| StoreError::QueryError(e.to_string()))?;
+
+        let warnings = stmt.query_map(params![limit], |row| {
+            Ok(UsageWarning {
+                session_id: row.get(0)?,
+                kind: row.get(1)?,
+                message: row.get(2)?,
+                resets_at: row.get(3)?,
+                timestamp: row.get(4)?,
+                source: row.get(5)?,
+            })
+        })
+        .map_err(|e| StoreError::QueryError(e.to_string()))?
+        .filter_map(|r| r.ok())
+        .collect();
+
+        Ok(warnings)
+    }
+
+    // ========== Custom Event Operations ==========
+
+    pub fn insert_custom_event(&self, event: &CustomEvent) -> Result<i64, StoreError> {
+        let conn = self.conn.lock().map_err(|_| StoreError::LockError)?;
+
+        conn.execute(
+            "INSERT INTO custom_events (custom_type, task_id, session_id, payload, source, timestamp)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                event.custom_type,
+                event.task_id,
+                event.session_id,
+                event.payload.to_string(),
+                event.source,
+                event.timestamp,
+            ],
+        ).map_err(|e| StoreError::QueryError(e.to_string()))?;
+
+        Ok(conn.last_insert_rowid())
+    }
+
+    /// The most recent custom events, of one type or all, newest first
+    pub fn get_custom_events(&self, custom_type: Option<&str>, limit: i64) -> Result<Vec<CustomEvent>, StoreError> {
+        let conn = self.conn.lock().map_err(|_| StoreError::LockError)?;
+
+        let mut stmt = conn.prepare(
+            "SELECT id, custom_type, task_id, session_id, payload, source, timestamp FROM custom_events
+             WHERE ?1 IS NULL OR custom_type = ?1
+             ORDER BY timestamp DESC, id DESC
+             LIMIT ?2"
+        ).map_err(|e| StoreError::QueryError(e.to_string()))?;
+
+        let events = stmt.query_map(params![custom_type, limit], |row| {
+            let payload: String = row.get(4)?;
+            Ok(CustomEvent {
+                id: row.get(0)?,
+                custom_type: row.get(1)?,
+                task_id: row.get(2)?,
+                session_id: row.get(3)?,
+                payload: serde_json::from_str(&payload).unwrap_or(serde_json::Value::Null),
+                source: row.get(5)?,
+                timestamp: row.get(6)?,
+            })
+        })
+        .map_err(|e| StoreError::QueryError(e.to_string()))?
+        .filter_map(|r| r.ok())
+        .collect();
+
+        Ok(events)
+    }
+
+    // ========== Notification Operations ==========
+
+    /// Record a notification popup opened in `window`
+    pub fn insert_notification(&self, window: &str, task_id: &str, kind: &str, shown_at: i64) -> Result<(), StoreError> {
+        let conn = self.conn.lock().map_err(|_| StoreError::LockError)?;
+
+        conn.execute(
+            "INSERT INTO notifications (window, task_id, kind, shown_at) VALUES (?1, ?2, ?3, ?4)",
+            params![window, task_id, kind, shown_at],
+        ).map_err(|e| StoreError::QueryError(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// Record that the popup open in `window` went away, and why
+    pub fn dismiss_notification(&self, window: &str, dismissed_at: i64, dismissed_by: &str) -> Result<(), StoreError> {
+        let conn = self.conn.lock().map_err(|_| StoreError::LockError)?;
+
+        conn.execute(
+            "UPDATE notifications SET dismissed_at = ?2, dismissed_by = ?3
+             WHERE window = ?1 AND dismissed_at IS NULL",
+            params![window, dismissed_at, dismissed_by],
+        ).map_err(|e| StoreError::QueryError(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// Mark every popup still open as closed, returning how many there were
+    ///
+    /// Run when the app exits, so window labels reused by the next run can't
+    /// match a popup from this one.
+    pub fn close_open_notifications(&self, closed_at: i64) -> Result<usize, StoreError> {
+        let conn = self.conn.lock().map_err(|_| StoreError::LockError)?;
+
+        conn.execute(
+            "UPDATE notifications SET dismissed_at = ?1, dismissed_by = 'closed' WHERE dismissed_at IS NULL",
+            params![closed_at],
+        ).map_err(|e| StoreError::QueryError(e.to_string()))
+    }
+
+    /// Notifications shown since `since_ms`, newest first
+    pub fn get_notification_history(&self, since_ms: i64, limit: i64) -> Result<Vec<NotificationRecord>, StoreError> {
+        let conn = self.conn.lock().map_err(|_| StoreError::LockError)?;
+
+        let mut stmt = conn.prepare(
+            "SELECT id, task_id, kind, shown_at, dismissed_at, dismissed_by FROM notifications
+             WHERE shown_at >= ?1
+             ORDER BY shown_at DESC, id DESC
+             LIMIT ?2"
+        ).map_err(|e| StoreError::QueryError(e.to_string()))?;
+
+        let notifications = stmt.query_map(params![since_ms, limit], |row| {
+            Ok(NotificationRecord {
+                id: row.get(0)?,
+                task_id: row.get(1)?,
+                kind: row.get(2)?,
+                shown_at: row.get(3)?,
+                dismissed_at: row.get(4)?,
+                dismissed_by: row.get(5)?,
+            })
+        })
+        .map_err(|e| StoreError::QueryError(e.to_string()))?
+        .filter_map(|r| r.ok())
+        .collect();
+
+        Ok(notifications)
+    }
+
+    /// How popups of each kind went away since `since_ms`, the most shown kind first
+    pub fn get_notification_stats(&self, since_ms: i64) -> Result<Vec<NotificationStats>, StoreError> {
+        let conn = self.conn.lock().map_err(|_| StoreError::LockError)?;
+
+        let mut stmt = conn.prepare(
+            "SELECT kind,
+                    COUNT(*),
+                    COALESCE(SUM(dismissed_by = 'clicked'), 0),
+                    COALESCE(SUM(dismissed_by = 'user'), 0),
+                    COALESCE(SUM(dismissed_by IN ('timeout', 'finished', 'replaced')), 0)
+             FROM notifications
+             WHERE shown_at >= ?1
+             GROUP BY kind
+             ORDER BY COUNT(*) DESC, kind"
+        ).map_err(|e| StoreError::QueryError(e.to_string()))?;
+
+        let stats = stmt.query_map(params![since_ms], |row| {
+            Ok(NotificationStats::new(row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?))
+        })
+        .map_err(|e| StoreError::QueryError(e.to_string()))?
+        .filter_map(|r| r.ok())
+        .collect();
+
+        Ok(stats)
+    }
+
+    // ========== Dead Letter Operations ==========
+
+    /// Record an event a plugin did not receive
+    pub fn insert_dead_letter(&self, plugin: &str, event: &str, reason: &str) -> Result<(), StoreError> {
+        let conn = self.conn.lock().map_err(|_| StoreError::LockError)?;
+
+        conn.execute(
+            "INSERT INTO dead_letters (plugin, event, reason, created_at) VALUES (?1, ?2, ?3, ?4)",
+            params![plugin, event, reason, chrono_now_ms()],
+        ).map_err(|e| StoreError::QueryError(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// Most recent dead letters, optionally for a single plugin
+    pub fn get_dead_letters(&self, plugin: Option<&str>, limit: usize) -> Result<Vec<DeadLetter>, StoreError> {
+        let conn = self.conn.lock().map_err(|_| StoreError::LockError)?;
+
+        let mut stmt = conn.prepare(
+            "SELECT id, plugin, event, reason, created_at FROM dead_letters
+             WHERE ?1 IS NULL OR plugin = ?1
+             ORDER BY created_at DESC, id DESC
+             LIMIT ?2"
+        ).map_err(|e| StoreError::QueryError(e.to_string()))?;
+
+        let letters = stmt.query_map(params![plugin, limit as i64], |row| Ok(DeadLetter {
+            id: row.get(0)?,
+            plugin: row.get(1)?,
+            event: row.get(2)?,
+            reason: row.get(3)?,
+            created_at: row.get(4)?,
+        })).map_err(|e| StoreError::QueryError(e.to_string()))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| StoreError::QueryError(e.to_string()))?;
+
+        Ok(letters)
+    }
+
+    // ========== Sync Operations ==========
+
+    /// Get a saved position (sync cursors and peer file positions, the watcher's events offset)
+    pub fn get_sync_state(&self, key: &str) -> Result<Option<i64>, StoreError> {
+        let conn = self.conn.lock().map_err(|_| StoreError::LockError)?;
+
+        conn.query_row("SELECT value FROM sync_state WHERE key = ?1", params![key], |row| row.get(0))
+            .optional()
+            .map_err(|e| StoreError::QueryError(e.to_string()))
+    }
+
+    pub fn set_sync_state(&self, key: &str, value: i64) -> Result<(), StoreError> {
+        let conn = self.conn.lock().map_err(|_| StoreError::LockError)?;
+
+        conn.execute(
+            "INSERT INTO sync_state (key, value) VALUES (?1, ?2)
+             ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+            params![key, value],
+        ).map_err(|e| StoreError::QueryError(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// Sessions and tasks created on this machine and changed after `since`, oldest change first
+    pub fn export_changes(&self, since: i64) -> Result<Vec<SyncRecord>, StoreError> {
+        let conn = self.conn.lock().map_err(|_| StoreError::LockError)?;
+
+        let mut stmt = conn.prepare(
+            "SELECT id, started_at, ended_at, project_path, branch, updated_at FROM sessions
+             WHERE origin IS NULL AND updated_at > ?1
+             ORDER BY updated_at"
+        ).map_err(|e| StoreError::QueryError(e.to_string()))?;
+
+        let mut records = stmt.query_map(params![since], |row| Ok(SyncRecord::Session {
+            session: StoredSession {
+                id: row.get(0)?,
+                started_at: row.get(1)?,
+                ended_at: row.get(2)?,
+                project_path: row.get(3)?,
+                branch: row.get(4)?,
+            },
+            updated_at: row.get(5)?,
+        })).map_err(|e| StoreError::QueryError(e.to_string()))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| StoreError::QueryError(e.to_string()))?;
+
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {}, t.updated_at FROM tasks t
+             WHERE t.origin IS NULL AND t.updated_at > ?1
+             ORDER BY t.updated_at",
+            TASK_COLUMNS
+        )).map_err(|e| StoreError::QueryError(e.to_string()))?;
+
+        let tasks = stmt.query_map(params![since], |row| Ok(SyncRecord::Task {
+            task: row_to_task(row)?,
+            updated_at: row.get(14)?,
+        })).map_err(|e| StoreError::QueryError(e.to_string()))?;
+
+        for task in tasks {
+            records.push(task.map_err(|e| StoreError::QueryError(e.to_string()))?);
+        }
+
+        Ok(records)
+    }
+
+    /// Merge changes exported by another machine, returning how many rows changed
+    ///
+    /// Rows are matched by id and the most recent change wins, so importing the
+    /// same records again is a no-op.
+    pub fn import_changes(&self, records: &[SyncRecord], origin: &str) -> Result<usize, StoreError> {
+        let mut conn = self.conn.lock().map_err(|_| StoreError::LockError)?;
+        let tx = conn.transaction().map_err(|e| StoreError::QueryError(e.to_string()))?;
+        let mut changed = 0;
+
+        for record in records {
+            changed += match record {
+                SyncRecord::Session { session, updated_at } => tx.execute(
+                    "INSERT INTO sessions (id, started_at, ended_at, project_path, branch, updated_at, origin)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+                     ON CONFLICT(id) DO UPDATE SET
+                        started_at = excluded.started_at,
+                        ended_at = excluded.ended_at,
+                        project_path = COALESCE(excluded.project_path, project_path),
+                        branch = COALESCE(excluded.branch, branch),
+                        updated_at = excluded.updated_at,
+                        origin = excluded.origin
+                     WHERE excluded.updated_at > sessions.updated_at",
+                    params![session.id, session.started_at, session.ended_at, session.project_path, session.branch, updated_at, origin],
+                ),
+                SyncRecord::Task { task, updated_at } => tx.execute(
+                    "INSERT OR IGNORE INTO sessions (id, started_at, origin) VALUES (?1, ?2, ?3)",
+                    params![task.session_id, task.started_at, origin],
+                ).and_then(|_| tx.execute(
+                    "INSERT INTO tasks (id, session_id, tool, description, status, started_at, ended_at, duration_ms, is_background, subagent_type, source, updated_at, origin, duration_suspect)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)
+                     ON CONFLICT(id) DO UPDATE SET
+                        session_id = excluded.session_id,
+                        tool = excluded.tool,
+                        description = excluded.description,
+                        status = excluded.status,
+                        started_at = excluded.started_at,
+                        ended_at = excluded.ended_at,
+                        duration_ms = excluded.duration_ms,
+                        duration_suspect = excluded.duration_suspect,
+                        is_background = excluded.is_background,
+                        subagent_type = excluded.subagent_type,
+                        source = excluded.source,
+                        updated_at = excluded.updated_at,
+                        origin = excluded.origin
+                     WHERE excluded.updated_at > tasks.updated_at",
+                    params![
+                        task.id,
+                        task.session_id,
+                        task.tool,
+                        task.description,
+                        task.status,
+                        task.started_at,
+                        task.ended_at,
+                        task.duration_ms,
+                        task.is_background as i32,
+                        task.subagent_type,
+                        task.source,
+                        updated_at,
+                        origin,
+                        task.duration_suspect as i32
+                    ],
+                )),
+            }.map_err(|e| StoreError::QueryError(e.to_string()))?;
+        }
+
+        tx.commit().map_err(|e| StoreError::QueryError(e.to_string()))?;
+        Ok(changed)
+    }
+
+    /// Merge another machine's history database into this one
+    ///
+    /// The other database must already have this version's schema. Sessions
+    /// and tasks are matched by id. For a task both have, an ended status
+    /// wins over one still active, which the other machine may never have
+    /// seen end; otherwise the more recent change wins. Rows only the other
+    /// has are marked as coming from `origin`. Todos, commits and tags are
+    /// added where missing, with tags matched by name. Merging the same
+    /// database again changes nothing.
+    pub fn merge_database(&self, other: &Path, origin: &str) -> Result<MergeReport, StoreError> {
+        let mut conn = self.conn.lock().map_err(|_| StoreError::LockError)?;
+        with_attached(&mut conn, other, "other", |conn| merge_attached(conn, origin))
+    }
+
+    // ========== Archive Operations ==========
+
+    /// Move sessions that started before `before_ms`, with everything recorded
+    /// for them, into the database at `archive`
+    ///
+    /// The archive must already have this version's schema, e.g. from opening
+    /// it as an `EventStore`. Sessions with an active or pinned task stay. The
+    /// tags themselves are copied rather than moved, since live sessions may
+    /// use them too. Afterwards the live database is vacuumed so the file
+    /// shrinks.
+    pub fn archive_sessions(&self, before_ms: i64, archive: &Path) -> Result<ArchiveReport, StoreError> {
+        let mut conn = self.conn.lock().map_err(|_| StoreError::LockError)?;
+
+        let report = with_attached(&mut conn, archive, "archive", |conn| move_sessions(conn, before_ms))?;
+        if report.sessions > 0 {
+            conn.execute_batch("VACUUM").map_err(|e| StoreError::QueryError(e.to_string()))?;
+        }
+        Ok(report)
+    }
+}
+
+/// What `merge_database` brought in
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MergeReport {
+    pub sessions_added: usize,
+    pub tasks_added: usize,
+    /// Tasks both had, updated from the other database
+    pub tasks_updated: usize,
+    /// Tasks both had in different statuses, where the other database's status was taken
+    pub conflicts: usize,
+}
+
+/// Task columns describing a process on the machine that recorded the task,
+/// which mean nothing on this one
+const PROCESS_COLUMNS: &[&str] = &[
+    "pid",
+    "exit_code",
+    "process_alive",
+    "process_cpu",
+    "process_rss",
+    "process_count",
+    "process_sampled_at",
+];
+
+/// SQL for whether the `other` row of a task replaces the `live` one: an
+/// ended status wins over one still active, and otherwise the later change does
+fn merge_takes_other(live: &str, other: &str) -> String {
+    format!(
+        "({live}.status = 'active' AND {other}.status != 'active')
+         OR (({live}.status = 'active') = ({other}.status = 'active') AND {other}.updated_at > {live}.updated_at)"
+    )
+}
+
+/// Merge the attached `other` database into the live one, in one transaction
+fn merge_attached(conn: &mut Connection, origin: &str) -> Result<MergeReport, StoreError> {
+    let query = |e: rusqlite::Error| StoreError::QueryError(e.to_string());
+    let tx = conn.transaction().map_err(query)?;
+    let count = |sql: &str| tx.query_row(sql, [], |row| row.get::<_, i64>(0)).map(|n| n as usize).map_err(query);
+
+    let mut report = MergeReport {
+        sessions_added: count("SELECT COUNT(*) FROM other.sessions WHERE id NOT IN (SELECT id FROM main.sessions)")?,
+        tasks_added: count("SELECT COUNT(*) FROM other.tasks WHERE id NOT IN (SELECT id FROM main.tasks)")?,
+        tasks_updated: 0,
+        conflicts: count(&format!(
+            "SELECT COUNT(*) FROM other.tasks o JOIN main.tasks t ON t.id = o.id
+             WHERE o.status != t.status AND ({})",
+            merge_takes_other("t", "o")
+        ))?,
+    };
+
+    // A session spans the earliest start and latest end either saw
+    tx.execute(
+        "INSERT INTO main.sessions (id, started_at, ended_at, project_path, branch, updated_at, origin)
+         SELECT id, started_at, ended_at, project_path, branch, updated_at, COALESCE(origin, ?1) FROM other.sessions WHERE true
+         ON CONFLICT(id) DO UPDATE SET
+            started_at = MIN(sessions.started_at, excluded.started_at),
+            ended_at = MAX(COALESCE(sessions.ended_at, excluded.ended_at), COALESCE(excluded.ended_at, sessions.ended_at)),
+            project_path = COALESCE(sessions.project_path, excluded.project_path),
+            branch = COALESCE(sessions.branch, excluded.branch),
+            updated_at = MAX(sessions.updated_at, excluded.updated_at)",
+        params![origin],
+    ).map_err(query)?;
+
+    // Whether a task was reviewed or pinned is kept from either side. The
+    // other machine's processes aren't copied, so they are never sampled or
+    // killed here.
+    let columns = table_columns(&tx, "tasks", true)?;
+    let select: Vec<String> = columns
+        .iter()
+        .map(|column| match column.as_str() {
+            "origin" => "COALESCE(origin, ?1)".to_string(),
+            column if PROCESS_COLUMNS.contains(&column) => "NULL".to_string(),
+            column => column.to_string(),
+        })
+        .collect();
+    let updates: Vec<String> = columns
+        .iter()
+        .filter(|column| !matches!(column.as_str(), "id" | "origin") && !PROCESS_COLUMNS.contains(&column.as_str()))
+        .map(|column| match column.as_str() {
+            "reviewed_at" | "pinned_at" => format!("{column} = COALESCE(tasks.{column}, excluded.{column})"),
+            _ => format!("{column} = excluded.{column}"),
+        })
+        .collect();
+    let changed = tx.execute(
+        &format!(
+            "INSERT INTO main.tasks ({}) SELECT {} FROM other.tasks WHERE true
+             ON CONFLICT(id) DO UPDATE SET {}
+             WHERE {}",
+            columns.join(", "),
+            select.join(", "),
+            updates.join(", "),
+            merge_takes_other("tasks", "excluded"),
+        ),
+        params![origin],
+    ).map_err(query)?;
+    report.tasks_updated = changed - report.tasks_added;
+
+    tx.execute_batch(
+        "INSERT OR IGNORE INTO main.todos (session_id, content, active_form, status, created_at, updated_at)
+         SELECT session_id, content, active_form, status, created_at, updated_at FROM other.todos;
+         INSERT OR IGNORE INTO main.session_commits (session_id, commit_hash, message, committed_at)
+         SELECT session_id, commit_hash, message, committed_at FROM other.session_commits;
+         INSERT OR IGNORE INTO main.tags (name, created_at) SELECT name, created_at FROM other.tags;
+         INSERT OR IGNORE INTO main.task_tags (task_id, tag_id)
+         SELECT tt.task_id, t.id FROM other.task_tags tt
+         JOIN other.tags ot ON ot.id = tt.tag_id JOIN main.tags t ON t.name = ot.name;
+         INSERT OR IGNORE INTO main.session_tags (session_id, tag_id)
+         SELECT st.session_id, t.id FROM other.session_tags st
+         JOIN other.tags ot ON ot.id = st.tag_id JOIN main.tags t ON t.name = ot.name;",
+    ).map_err(query)?;
+
+    tx.commit().map_err(query)?;
+    Ok(report)
+}
+
+/// What `archive_sessions` moved
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ArchiveReport {
+    pub sessions: usize,
+    pub tasks: usize,
+    pub todos: usize,
+}
+
+/// Tables holding one session's records, by the column naming the session
+const SESSION_TABLES: &[(&str, &str)] = &[
+    ("session_commits", "session_id"),
+    ("session_tags", "session_id"),
+    ("todos", "session_id"),
+    ("todo_transitions", "session_id"),
+    ("context_samples", "session_id"),
+    ("token_usage", "session_id"),
+    ("usage_warnings", "session_id"),
+    ("custom_events", "session_id"),
+    ("tasks", "session_id"),
+    ("sessions", "id"),
+];
+
+/// Run `f` with the database at `path` attached as `schema`, detaching it afterwards even on failure
+fn with_attached<T>(
+    conn: &mut Connection,
+    path: &Path,
+    schema: &str,
+    f: impl FnOnce(&mut Connection) -> Result<T, StoreError>,
+) -> Result<T, StoreError> {
+    conn.execute(&format!("ATTACH DATABASE ?1 AS {}", schema), params![path.to_string_lossy().to_string()])
+        .map_err(|e| StoreError::QueryError(e.to_string()))?;
+    let result = f(conn);
+    let detached = conn.execute_batch(&format!("DETACH DATABASE {}", schema))
+        .map_err(|e| StoreError::QueryError(e.to_string()));
+    let value = result?;
+    detached?;
+    Ok(value)
+}
 
-        let avg_duration: Option<f64> = conn.query_row(
-            "SELECT AVG(duration_ms) FROM tasks WHERE duration_ms IS NOT NULL",
-            [],
-            |row| row.get(0),
-        ).map_err(|e| StoreError::QueryError(e.to_string()))?;
+/// The columns of a table in the live database
+///
+/// With `keep_row_id` false an integer primary key is left out, so copied
+/// rows get new ids rather than clashing with rows already in the target.
+fn table_columns(conn: &Connection, table: &str, keep_row_id: bool) -> Result<Vec<String>, StoreError> {
+    let mut stmt = conn.prepare(&format!("PRAGMA main.table_info({})", table))
+        .map_err(|e| StoreError::QueryError(e.to_string()))?;
+    let columns: Vec<(String, String, i64)> = stmt.query_map([], |row| Ok((row.get(1)?, row.get(2)?, row.get(5)?)))
+        .map_err(|e| StoreError::QueryError(e.to_string()))?
+        .collect::<Result<_, _>>()
+        .map_err(|e| StoreError::QueryError(e.to_string()))?;
+    Ok(columns
+        .into_iter()
+        .filter(|(_, kind, pk)| keep_row_id || *pk != 1 || !kind.eq_ignore_ascii_case("INTEGER"))
+        .map(|(name, _, _)| name)
+        .collect())
+}
 
-        Ok(TaskStats {
-            total_tasks: total,
-            completed_tasks: completed,
-            error_tasks: errors,
-            avg_duration_ms: avg_duration,
-        })
+/// Copy the archived sessions' rows into the attached `archive` and delete them, in one transaction
+fn move_sessions(conn: &mut Connection, before_ms: i64) -> Result<ArchiveReport, StoreError> {
+    let query = |e: rusqlite::Error| StoreError::QueryError(e.to_string());
+    let tx = conn.transaction().map_err(query)?;
+
+    tx.execute(
+        "CREATE TEMP TABLE archiving AS
+         SELECT id FROM main.sessions s
+         WHERE s.started_at < ?1
+           AND NOT EXISTS (
+               SELECT 1 FROM main.tasks t
+               WHERE t.session_id = s.id AND (t.status = 'active' OR t.pinned_at IS NOT NULL)
+           )",
+        params![before_ms],
+    ).map_err(query)?;
+
+    // Task tags go by task, so they are copied while the tasks are still here
+    let columns = table_columns(&tx, "task_tags", true)?.join(", ");
+    tx.execute_batch(&format!(
+        "INSERT OR IGNORE INTO archive.task_tags ({columns})
+         SELECT {columns} FROM main.task_tags
+         WHERE task_id IN (SELECT id FROM main.tasks WHERE session_id IN (SELECT id FROM temp.archiving));
+         DELETE FROM main.task_tags
+         WHERE task_id IN (SELECT id FROM main.tasks WHERE session_id IN (SELECT id FROM temp.archiving));"
+    )).map_err(query)?;
+
+    let mut report = ArchiveReport::default();
+    for (table, column) in SESSION_TABLES {
+        let columns = table_columns(&tx, table, false)?.join(", ");
+        tx.execute(
+            &format!(
+                "INSERT OR IGNORE INTO archive.{table} ({columns})
+                 SELECT {columns} FROM main.{table} WHERE {column} IN (SELECT id FROM temp.archiving)"
+            ),
+            [],
+        ).map_err(query)?;
+        let deleted = tx.execute(
+            &format!("DELETE FROM main.{table} WHERE {column} IN (SELECT id FROM temp.archiving)"),
+            [],
+        ).map_err(query)?;
+        match *table {
+            "sessions" => report.sessions = deleted,
+            "tasks" => report.tasks = deleted,
+            "todos" => report.todos = deleted,
+            _ => {}
+        }
     }
 
-    /// Cleanup old tasks (older than days_to_keep)
-    pub fn cleanup_old_tasks(&self, days_to_keep: i64) -> Result<i64, StoreError> {
-        let conn = self.conn.lock().map_err(|_| StoreError::LockError)?;
+    // The tags themselves stay, since live sessions may use them too
+    let columns = table_columns(&tx, "tags", true)?.join(", ");
+    tx.execute_batch(&format!(
+        "INSERT OR IGNORE INTO archive.tags ({columns})
+         SELECT {columns} FROM main.tags
+         WHERE id IN (SELECT tag_id FROM archive.task_tags UNION SELECT tag_id FROM archive.session_tags);
+         DROP TABLE temp.archiving;"
+    )).map_err(query)?;
+
+    tx.commit().map_err(query)?;
+    Ok(report)
+}
 
-        let cutoff_ms = chrono_now_ms() - (days_to_keep * 24 * 60 * 60 * 1000);
+/// What a task was for, from the session transcript
+///
+/// Kept apart from [`StoredTask`] so prompts aren't synced to other machines
+/// or handed to plugins along with the task.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TaskContext {
+    pub prompt: String,
+    pub summary: Option<String>,
+}
 
-        let deleted = conn.execute(
-            "DELETE FROM tasks WHERE started_at < ?1 AND status != 'active'",
-            params![cutoff_ms],
-        ).map_err(|e| StoreError::QueryError(e.to_string()))?;
+/// What a background task's process tree was using when last sampled
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProcessUsage {
+    /// False once the process has exited; the usage is then what it was last seen with
+    pub alive: bool,
+    /// Summed over the tree, with 100 being one core
+    pub cpu_percent: f64,
+    pub rss_bytes: u64,
+    /// Processes in the tree, the shell included
+    pub processes: u32,
+    pub sampled_at: i64,
+}
 
-        Ok(deleted as i64)
+/// A background task's process and how it ended
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProcessInfo {
+    pub pid: Option<u32>,
+    /// Set once the task's end event reports one
+    pub exit_code: Option<i32>,
+    /// None until the process is first sampled
+    pub usage: Option<ProcessUsage>,
+}
+
+/// Tokens in a session's context window after one of the assistant's replies
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContextSample {
+    pub session_id: String,
+    pub timestamp: i64,
+    pub context_tokens: i64,
+    /// Size of the window the model has
+    pub context_window: i64,
+    pub model: Option<String>,
+}
+
+/// Tokens one of the assistant's replies used, from the session transcript
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TokenUsage {
+    pub session_id: String,
+    pub timestamp: i64,
+    pub model: String,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub cache_read_tokens: i64,
+    pub cache_creation_tokens: i64,
+}
+
+/// What one model did: the tasks it ran and the tokens it used
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ModelUsage {
+    /// None for tasks whose model isn't known
+    pub model: Option<String>,
+    pub stats: TaskStats,
+    /// Time spent in the model's tasks, leaving out suspect durations
+    pub total_duration_ms: i64,
+    /// Replies the model sent
+    pub requests: i64,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub cache_read_tokens: i64,
+    pub cache_creation_tokens: i64,
+    /// Estimated from the configured prices; None when the model has no price
+    pub cost_usd: Option<f64>,
+}
+
+/// A stretch of focused work, with what the agent finished during it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FocusSession {
+    pub id: i64,
+    pub label: Option<String>,
+    pub started_at: i64,
+    /// None while the session is running
+    pub ended_at: Option<i64>,
+    /// How long the session was meant to last, for timers
+    pub planned_ms: Option<i64>,
+    /// Tasks that finished during the session; filled in when it ends
+    pub completed_tasks: i64,
+    pub failed_tasks: i64,
+    /// Time the finished tasks took, leaving out suspect durations
+    pub task_time_ms: i64,
+}
+
+/// An agent held back by its provider: rate limited, out of quota or overloaded
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UsageWarning {
+    pub session_id: Option<String>,
+    /// "rate_limit", "usage_limit" or "overloaded"
+    pub kind: String,
+    pub message: String,
+    /// When the limit lifts, in millis, if the provider said
+    pub resets_at: Option<i64>,
+    pub timestamp: i64,
+    pub source: Option<String>,
+}
+
+/// An event of a type the user's hooks made up, e.g. "deploy_finished"
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CustomEvent {
+    /// Assigned by the store; ignored on insert
+    pub id: i64,
+    pub custom_type: String,
+    pub task_id: Option<String>,
+    pub session_id: Option<String>,
+    /// Whatever the hook sent along; null if nothing
+    pub payload: serde_json::Value,
+    pub source: Option<String>,
+    pub timestamp: i64,
+}
+
+/// A notification popup that was shown, and how it went away
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NotificationRecord {
+    pub id: i64,
+    /// The task the popup was about, or the key of a popup that isn't about a task
+    pub task_id: String,
+    /// "task", "usage_warning" or "away_summary"
+    pub kind: String,
+    pub shown_at: i64,
+    /// None while the popup is still up
+    pub dismissed_at: Option<i64>,
+    /// "clicked" through to the overlay, "finished" when its task or session
+    /// ended, "timeout", "user", "replaced" by a newer popup, or "closed" with the app
+    pub dismissed_by: Option<String>,
+}
+
+/// A todo reaching a status, or "removed" when it was dropped
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TodoTransition {
+    pub session_id: String,
+    pub content: String,
+    pub status: String,
+    pub at: i64,
+}
+
+/// Todo counts in a session at one point in time
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BurndownPoint {
+    pub at: i64,
+    pub completed: i64,
+    /// Pending or in progress
+    pub remaining: i64,
+}
+
+/// How popups of one kind went away, to tell which are worth showing
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NotificationStats {
+    pub kind: String,
+    pub shown: i64,
+    /// Clicked through to the overlay
+    pub clicked: i64,
+    /// Dismissed by the user without looking further
+    pub dismissed: i64,
+    /// Went away on their own: timed out, replaced, or their task ended
+    pub auto_dismissed: i64,
+    /// Shares of `shown`, from 0 to 1
+    pub click_through_rate: f64,
+    pub manual_dismiss_rate: f64,
+    pub auto_dismiss_rate: f64,
+}
+
+impl NotificationStats {
+    fn new(kind: String, shown: i64, clicked: i64, dismissed: i64, auto_dismissed: i64) -> Self {
+        let rate = |count: i64| if shown > 0 { count as f64 / shown as f64 } else { 0.0 };
+        Self {
+            kind,
+            shown,
+            clicked,
+            dismissed,
+            auto_dismissed,
+            click_through_rate: rate(clicked),
+            manual_dismiss_rate: rate(dismissed),
+            auto_dismiss_rate: rate(auto_dismissed),
+        }
     }
 }
 
 /// Task statistics
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct TaskStats {
     pub total_tasks: i64,
     pub completed_tasks: i64,
     pub error_tasks: i64,
+    /// Average over tasks whose duration isn't suspect
     pub avg_duration_ms: Option<f64>,
+    /// Finished tasks whose duration was clamped
+    #[serde(default)]
+    pub suspect_durations: i64,
+}
+
+/// A user-defined label for sessions and tasks, e.g. "release" or "client-x"
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Tag {
+    pub id: i64,
+    pub name: String,
+    pub created_at: i64,
+}
+
+/// Task statistics for the tasks carrying one tag
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TagStats {
+    pub tag: Tag,
+    pub stats: TaskStats,
+}
+
+/// Task statistics for one bucket of history
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BucketStats {
+    #[serde(flatten)]
+    pub bucket: Bucket,
+    pub stats: TaskStats,
 }
 
 /// Store errors
@@ -496,8 +3006,12 @@ mod tests {
             started_at: 1000,
             ended_at: None,
             duration_ms: None,
+            duration_suspect: false,
             is_background: false,
             subagent_type: None,
+            source: "claude-code".to_string(),
+            reviewed: false,
+            pinned: false,
         };
 
         store.insert_task(&task).unwrap();
@@ -514,6 +3028,498 @@ mod tests {
         assert_eq!(retrieved.duration_ms, Some(1000));
     }
 
+    #[test]
+    fn test_bucketed_stats() {
+        let store = EventStore::in_memory().unwrap();
+        for (id, started_at) in [("a", 100), ("b", 999), ("c", 1000)] {
+            store.insert_task(&StoredTask {
+                id: id.to_string(),
+                session_id: "session-1".to_string(),
+                tool: "Bash".to_string(),
+                description: None,
+                status: "active".to_string(),
+                started_at,
+                ended_at: None,
+                duration_ms: None,
+                duration_suspect: false,
+                is_background: false,
+                subagent_type: None,
+                source: "claude-code".to_string(),
+                reviewed: false,
+                pinned: false,
+            }).unwrap();
+        }
+        store.update_task_status("b", "completed", 1999).unwrap();
+
+        let buckets = [
+            Bucket { label: "first".to_string(), start_ms: 0, end_ms: 1000 },
+            Bucket { label: "second".to_string(), start_ms: 1000, end_ms: 2000 },
+        ];
+        let stats = store.get_bucketed_stats(&buckets).unwrap();
+        assert_eq!(stats[0].stats.total_tasks, 2);
+        assert_eq!(stats[0].stats.avg_duration_ms, Some(1000.0));
+        assert_eq!(stats[1].stats.total_tasks, 1);
+        assert_eq!(stats[1].stats.avg_duration_ms, None);
+    }
+
+    #[test]
+    fn test_suspect_durations() {
+        let store = EventStore::in_memory().unwrap();
+        for (id, ended_at) in [("normal", 5_000), ("backwards", 500), ("slept", 1_000 + MAX_TASK_DURATION_MS * 3)] {
+            store.insert_task(&StoredTask {
+                id: id.to_string(),
+                session_id: "session-1".to_string(),
+                tool: "Bash".to_string(),
+                description: None,
+                status: "active".to_string(),
+                started_at: 1_000,
+                ended_at: None,
+                duration_ms: None,
+                duration_suspect: false,
+                is_background: false,
+                subagent_type: None,
+                source: "claude-code".to_string(),
+                reviewed: false,
+                pinned: false,
+            }).unwrap();
+            store.update_task_status(id, "completed", ended_at).unwrap();
+        }
+
+        let backwards = store.get_task("backwards").unwrap().unwrap();
+        assert_eq!(backwards.duration_ms, Some(0));
+        assert!(backwards.duration_suspect);
+        let slept = store.get_task("slept").unwrap().unwrap();
+        assert_eq!(slept.duration_ms, Some(MAX_TASK_DURATION_MS));
+        assert!(slept.duration_suspect);
+        assert!(!store.get_task("normal").unwrap().unwrap().duration_suspect);
+
+        // Only the plausible duration counts toward the average
+        let stats = store.get_task_stats().unwrap();
+        assert_eq!(stats.avg_duration_ms, Some(4_000.0));
+        assert_eq!(stats.suspect_durations, 2);
+    }
+
+    #[test]
+    fn test_model_usage() {
+        let store = EventStore::in_memory().unwrap();
+        for (id, model) in [("t1", Some("claude-sonnet-4-5")), ("t2", Some("claude-sonnet-4-5")), ("t3", None)] {
+            store.insert_task(&StoredTask {
+                id: id.to_string(),
+                session_id: "session-1".to_string(),
+                tool: "Bash".to_string(),
+                description: None,
+                status: "active".to_string(),
+                started_at: 1_000,
+                ended_at: None,
+                duration_ms: None,
+                duration_suspect: false,
+                is_background: false,
+                subagent_type: None,
+                source: "claude-code".to_string(),
+                reviewed: false,
+                pinned: false,
+            }).unwrap();
+            store.update_task_status(id, "completed", 4_000).unwrap();
+            if let Some(model) = model {
+                store.set_task_model(id, model).unwrap();
+            }
+        }
+        // The first model recorded for a task sticks
+        store.set_task_model("t1", "claude-opus-4-1").unwrap();
+
+        for (model, output_tokens) in [("claude-sonnet-4-5", 100), ("claude-sonnet-4-5", 50), ("claude-haiku-4-5", 10)] {
+            store.insert_token_usage(&TokenUsage {
+                session_id: "session-1".to_string(),
+                timestamp: 2_000,
+                model: model.to_string(),
+                input_tokens: 10,
+                output_tokens,
+                cache_read_tokens: 1_000,
+                cache_creation_tokens: 0,
+            }).unwrap();
+        }
+
+        let usage = store.get_model_usage(0).unwrap();
+        let models: Vec<_> = usage.iter().map(|u| u.model.as_deref()).collect();
+        assert_eq!(models, [Some("claude-sonnet-4-5"), None, Some("claude-haiku-4-5")]);
+        assert_eq!(usage[0].stats.total_tasks, 2);
+        assert_eq!(usage[0].total_duration_ms, 6_000);
+        assert_eq!((usage[0].requests, usage[0].output_tokens, usage[0].cache_read_tokens), (2, 150, 2_000));
+        assert_eq!(usage[2].stats.total_tasks, 0);
+        assert_eq!(usage[2].requests, 1);
+        assert!(store.get_model_usage(3_000).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_focus_sessions() {
+        let store = EventStore::in_memory().unwrap();
+        for (id, status, ended_at) in [("before", "completed", 500), ("done", "completed", 2_000), ("broke", "error", 3_000), ("after", "completed", 9_000)] {
+            store.insert_task(&StoredTask {
+                id: id.to_string(),
+                session_id: "session-1".to_string(),
+                tool: "Bash".to_string(),
+                description: None,
+                status: "active".to_string(),
+                started_at: 100,
+                ended_at: None,
+                duration_ms: None,
+                duration_suspect: false,
+                is_background: false,
+                subagent_type: None,
+                source: "claude-code".to_string(),
+                reviewed: false,
+                pinned: false,
+            }).unwrap();
+            store.update_task_status(id, status, ended_at).unwrap();
+        }
+
+        assert_eq!(store.stop_focus_session(1_000).unwrap(), None);
+        let first = store.start_focus_session(Some("pomodoro"), Some(1_500_000), 1_000).unwrap();
+        assert_eq!(store.get_active_focus_session().unwrap(), Some(first.clone()));
+
+        // Starting another ends the first, counting only tasks that finished in between
+        let second = store.start_focus_session(None, None, 5_000).unwrap();
+        let sessions = store.get_focus_sessions(10).unwrap();
+        assert_eq!(sessions.len(), 2);
+        assert_eq!(sessions[0], second);
+        assert_eq!(sessions[1].ended_at, Some(5_000));
+        assert_eq!((sessions[1].completed_tasks, sessions[1].failed_tasks), (1, 1));
+        assert_eq!(sessions[1].task_time_ms, 1_900 + 2_900);
+
+        let stopped = store.stop_focus_session(10_000).unwrap().unwrap();
+        assert_eq!((stopped.id, stopped.completed_tasks), (second.id, 1));
+        assert_eq!(store.get_active_focus_session().unwrap(), None);
+    }
+
+    #[test]
+    fn test_reviewed_tasks() {
+        let store = EventStore::in_memory().unwrap();
+        insert_history(&store, 20);
+        let errors = |reviewed| TaskFilter { status: Some("error".to_string()), reviewed, ..Default::default() };
+
+        let ids = ["task-0000000".to_string(), "task-0000003".to_string()];
+        assert_eq!(store.set_reviewed(&ids, true, 1_000).unwrap(), 2);
+        // Already reviewed, so nothing changes
+        assert_eq!(store.set_reviewed(&ids[..1], true, 2_000).unwrap(), 0);
+        assert!(store.get_task("task-0000003").unwrap().unwrap().reviewed);
+
+        let unreviewed = store.get_recent_tasks(&errors(Some(false)), 10).unwrap();
+        assert_eq!(unreviewed.iter().map(|t| t.id.as_str()).collect::<Vec<_>>(), ["task-0000010"]);
+
+        assert_eq!(store.mark_all_reviewed(&errors(None), 3_000).unwrap(), 1);
+        assert!(store.get_recent_tasks(&errors(Some(false)), 10).unwrap().is_empty());
+        assert_eq!(store.get_recent_tasks(&errors(Some(true)), 10).unwrap().len(), 2);
+
+        assert_eq!(store.set_reviewed(&ids, false, 4_000).unwrap(), 2);
+        assert!(!store.get_task("task-0000000").unwrap().unwrap().reviewed);
+    }
+
+    #[test]
+    fn test_pinned_tasks() {
+        let store = EventStore::in_memory().unwrap();
+        insert_history(&store, 10);
+
+        assert!(store.set_pinned("task-0000002", true, 1_000).unwrap());
+        assert!(store.set_pinned("task-0000005", true, 2_000).unwrap());
+        assert!(!store.set_pinned("task-0000005", true, 3_000).unwrap());
+        let pinned = store.get_pinned_tasks().unwrap();
+        assert_eq!(pinned.iter().map(|t| t.id.as_str()).collect::<Vec<_>>(), ["task-0000005", "task-0000002"]);
+        assert!(pinned[0].pinned);
+
+        // Cleanup leaves pinned tasks, however old
+        assert_eq!(store.cleanup_old_tasks(100).unwrap(), 8);
+        assert_eq!(store.get_recent_tasks(&TaskFilter::default(), 10).unwrap().len(), 2);
+
+        assert!(store.set_pinned("task-0000002", false, 4_000).unwrap());
+        assert_eq!(store.get_pinned_tasks().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_task_pid() {
+        let store = EventStore::in_memory().unwrap();
+        let mut task = history_task(1, 1_000);
+        task.status = "active".to_string();
+        store.insert_task(&task).unwrap();
+
+        assert_eq!(store.get_active_task_pid(&task.id).unwrap(), None);
+        store.set_task_pid(&task.id, 4242).unwrap();
+        assert_eq!(store.get_active_task_pid(&task.id).unwrap(), Some(4242));
+
+        // Once the task ends the process id may belong to something else
+        store.update_task_status(&task.id, "completed", 2_000).unwrap();
+        assert_eq!(store.get_active_task_pid(&task.id).unwrap(), None);
+    }
+
+    #[test]
+    fn test_event_log() {
+        let store = EventStore::in_memory().unwrap();
+        assert_eq!(store.event_log_bounds().unwrap(), (0, 0));
+
+        for i in 0..EVENT_LOG_CAPACITY + 5 {
+            let seq = store.log_event("task_started", &format!("{{\"n\":{}}}", i), i).unwrap();
+            assert_eq!(seq, i + 1);
+        }
+        // The oldest five fell out of the log
+        assert_eq!(store.event_log_bounds().unwrap(), (6, EVENT_LOG_CAPACITY + 5));
+
+        let events = store.get_logged_events(EVENT_LOG_CAPACITY, 10).unwrap();
+        assert_eq!(events.len(), 5);
+        assert_eq!(events[0], (EVENT_LOG_CAPACITY + 1, EVENT_LOG_CAPACITY, format!("{{\"n\":{}}}", EVENT_LOG_CAPACITY)));
+        assert_eq!(store.get_logged_events(0, 3).unwrap()[0].0, 6);
+    }
+
+    #[test]
+    fn test_tasks_and_sessions_since() {
+        let store = EventStore::in_memory().unwrap();
+        for (id, started_at) in [(3, 3_000), (1, 1_000), (2, 2_000)] {
+            store.insert_task(&history_task(id, started_at)).unwrap();
+            store.upsert_session(&StoredSession {
+                id: format!("session-{}", id),
+                started_at,
+                ended_at: None,
+                project_path: None,
+                branch: None,
+            }).unwrap();
+        }
+
+        let tasks: Vec<i64> = store.get_tasks_since(2_000).unwrap().iter().map(|t| t.started_at).collect();
+        assert_eq!(tasks, [2_000, 3_000]);
+        let sessions: Vec<String> = store.get_sessions_since(0).unwrap().into_iter().map(|s| s.id).collect();
+        assert_eq!(sessions, ["session-1", "session-2", "session-3"]);
+    }
+
+    #[test]
+    fn test_custom_events() {
+        let store = EventStore::in_memory().unwrap();
+        let event = |custom_type: &str, timestamp: i64| CustomEvent {
+            id: 0,
+            custom_type: custom_type.to_string(),
+            task_id: None,
+            session_id: Some("s1".to_string()),
+            payload: serde_json::json!({"env": "prod", "ok": true}),
+            source: None,
+            timestamp,
+        };
+        store.insert_custom_event(&event("deploy_finished", 1_000)).unwrap();
+        store.insert_custom_event(&event("lint_report", 2_000)).unwrap();
+        let id = store.insert_custom_event(&event("deploy_finished", 3_000)).unwrap();
+
+        let deploys = store.get_custom_events(Some("deploy_finished"), 10).unwrap();
+        assert_eq!(deploys.len(), 2);
+        assert_eq!(deploys[0].id, id);
+        assert_eq!(deploys[0].payload["env"], "prod");
+        assert_eq!(store.get_custom_events(None, 10).unwrap().len(), 3);
+
+        store.cleanup_old_tasks(2_500).unwrap();
+        assert_eq!(store.get_custom_events(None, 10).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_archive_sessions() {
+        let store = EventStore::in_memory().unwrap();
+        for (id, started_at) in [(1, 1_000), (2, 2_000), (3, 3_000)] {
+            store.insert_task(&history_task(id, started_at)).unwrap();
+        }
+        let mut active = history_task(4, 500);
+        active.status = "active".to_string();
+        store.insert_task(&active).unwrap();
+        store.set_task_tag("task-0000001", "flaky", true, 1_000).unwrap();
+        store.archive_completed_todos(&[("session-1".to_string(), "Write tests".to_string(), "Writing tests".to_string())], 1_500).unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("archive.db");
+        drop(EventStore::new(&path).unwrap());
+
+        let report = store.archive_sessions(2_500, &path).unwrap();
+        assert_eq!(report, ArchiveReport { sessions: 2, tasks: 2, todos: 1 });
+        let live: Vec<String> = store.get_tasks_since(0).unwrap().into_iter().map(|t| t.id).collect();
+        assert_eq!(live, ["task-0000004", "task-0000003"]);
+        assert!(store.get_completed_todos(0, i64::MAX).unwrap().is_empty());
+        assert_eq!(store.list_tags().unwrap().len(), 1);
+
+        let archive = EventStore::new(&path).unwrap();
+        assert_eq!(archive.get_sessions_since(0).unwrap().len(), 2);
+        assert_eq!(archive.search_tasks("crate1", &TaskFilter::default(), 10).unwrap().len(), 1);
+        assert_eq!(archive.get_task_tags("task-0000001").unwrap()[0].name, "flaky");
+        assert_eq!(archive.get_completed_todos(0, i64::MAX).unwrap()[0].content, "Write tests");
+        drop(archive);
+
+        assert_eq!(store.archive_sessions(2_500, &path).unwrap(), ArchiveReport::default());
+    }
+
+    #[test]
+    fn test_task_process() {
+        let store = EventStore::in_memory().unwrap();
+        let mut task = history_task(1, 1_000);
+        task.status = "active".to_string();
+        store.insert_task(&task).unwrap();
+        assert_eq!(store.get_task_process(&task.id).unwrap(), None);
+
+        store.set_task_pid(&task.id, 4242).unwrap();
+        assert_eq!(store.get_monitored_tasks().unwrap(), vec![(task.id.clone(), 4242, 1_000)]);
+        let info = store.get_task_process(&task.id).unwrap().unwrap();
+        assert_eq!(info.pid, Some(4242));
+        assert_eq!(info.usage, None);
+
+        let usage = ProcessUsage { alive: true, cpu_percent: 150.0, rss_bytes: 64 << 20, processes: 3, sampled_at: 2_000 };
+        store.set_task_process(&task.id, Some(&usage), 2_000).unwrap();
+        assert_eq!(store.get_task_process(&task.id).unwrap().unwrap().usage, Some(usage.clone()));
+
+        // The exited process keeps its last usage and drops out of monitoring
+        store.set_task_process(&task.id, None, 3_000).unwrap();
+        let exited = store.get_task_process(&task.id).unwrap().unwrap().usage.unwrap();
+        assert!(!exited.alive);
+        assert_eq!(exited.rss_bytes, usage.rss_bytes);
+        assert_eq!(exited.sampled_at, 3_000);
+        assert!(store.get_monitored_tasks().unwrap().is_empty());
+
+        store.set_task_exit_code(&task.id, 137).unwrap();
+        assert_eq!(store.get_task_process(&task.id).unwrap().unwrap().exit_code, Some(137));
+    }
+
+    #[test]
+    fn test_todo_burndown() {
+        let store = EventStore::in_memory().unwrap();
+        let t = |content: &str, status: &str, at| TodoTransition {
+            session_id: "s1".to_string(),
+            content: content.to_string(),
+            status: status.to_string(),
+            at,
+        };
+        store.insert_todo_transitions(&[t("a", "pending", 1_000), t("b", "pending", 1_000), t("c", "pending", 1_000)]).unwrap();
+        store.insert_todo_transitions(&[t("a", "in_progress", 2_000)]).unwrap();
+        store.insert_todo_transitions(&[t("a", "completed", 3_000), t("b", "in_progress", 3_000)]).unwrap();
+        store.insert_todo_transitions(&[t("c", "removed", 4_000)]).unwrap();
+        store.insert_todo_transitions(&[TodoTransition { session_id: "s2".to_string(), ..t("x", "pending", 5_000) }]).unwrap();
+
+        let counts: Vec<_> = store.get_todo_burndown("s1").unwrap().iter().map(|p| (p.at, p.completed, p.remaining)).collect();
+        assert_eq!(counts, [(1_000, 0, 3), (2_000, 0, 3), (3_000, 1, 2), (4_000, 1, 1)]);
+        assert!(store.get_todo_burndown("none").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_completed_todos() {
+        let store = EventStore::in_memory().unwrap();
+        let todo = |session: &str, content: &str| (session.to_string(), content.to_string(), format!("{}ing", content));
+        store.insert_todo_transitions(&[TodoTransition {
+            session_id: "s1".to_string(),
+            content: "Test".to_string(),
+            status: "pending".to_string(),
+            at: 500,
+        }]).unwrap();
+
+        store.archive_completed_todos(&[todo("s1", "Test"), todo("s2", "Test")], 1_000).unwrap();
+        store.archive_completed_todos(&[todo("s1", "Ship")], 2_000).unwrap();
+        // Completing it again doesn't move it
+        store.archive_completed_todos(&[todo("s1", "Test")], 3_000).unwrap();
+
+        let done = store.get_completed_todos(0, i64::MAX).unwrap();
+        let summary: Vec<_> = done.iter().map(|t| (t.session_id.as_str(), t.content.as_str(), t.created_at, t.updated_at)).collect();
+        assert_eq!(summary, [("s1", "Ship", 2_000, 2_000), ("s2", "Test", 1_000, 1_000), ("s1", "Test", 500, 1_000)]);
+        assert_eq!(store.get_completed_todos(1_500, 2_500).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_todo_order() {
+        let store = EventStore::in_memory().unwrap();
+        assert!(store.get_todo_order().unwrap().is_empty());
+
+        let hashes = |names: &[&str]| names.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+        store.set_todo_order(&hashes(&["b", "a", "c"]), 1_000).unwrap();
+        store.set_todo_order(&hashes(&["c", "a", "a"]), 2_000).unwrap();
+
+        let order = store.get_todo_order().unwrap();
+        assert_eq!(order.len(), 2);
+        assert_eq!((order["c"], order["a"]), (0, 1));
+    }
+
+    #[test]
+    fn test_tags() {
+        let store = EventStore::in_memory().unwrap();
+        insert_history(&store, 300);
+
+        let release = store.create_tag(" release ", 1_000).unwrap();
+        assert_eq!(release.name, "release");
+        assert_eq!(store.create_tag("Release", 2_000).unwrap(), release);
+        assert!(store.create_tag("  ", 2_000).is_err());
+
+        // Three tasks through their session, one directly, one on both
+        assert!(store.set_session_tag("session-7", "release", true, 3_000).unwrap());
+        assert!(store.set_task_tag("task-0000001", "release", true, 3_000).unwrap());
+        assert!(store.set_task_tag("task-0000007", "release", true, 3_000).unwrap());
+        assert!(store.set_task_tag("task-0000001", "experiment", true, 3_000).unwrap());
+        assert!(!store.set_task_tag("task-0000001", "experiment", true, 3_000).unwrap());
+
+        let filter = TaskFilter { tag: Some("release".to_string()), ..Default::default() };
+        assert_eq!(store.get_recent_tasks(&filter, 10).unwrap().len(), 4);
+        assert_eq!(store.search_tasks("crate7", &filter, 10).unwrap().len(), 1);
+
+        let stats = store.get_tag_stats().unwrap();
+        let names: Vec<_> = stats.iter().map(|s| s.tag.name.as_str()).collect();
+        assert_eq!(names, ["experiment", "release"]);
+        assert_eq!((stats[0].stats.total_tasks, stats[1].stats.total_tasks), (1, 4));
+
+        assert_eq!(store.get_task_tags("task-0000107").unwrap(), [release.clone()]);
+        assert!(store.rename_tag(release.id, "shipped").unwrap());
+        assert_eq!(store.get_session_tags("session-7").unwrap()[0].name, "shipped");
+
+        assert!(store.set_task_tag("task-0000001", "experiment", false, 4_000).unwrap());
+        assert!(store.delete_tag(release.id).unwrap());
+        assert!(store.get_recent_tasks(&filter, 10).unwrap().is_empty());
+        assert!(store.get_task_tags("task-0000007").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_notification_history() {
+        let store = EventStore::in_memory().unwrap();
+        store.insert_notification("notification-1", "task-1", "task", 1_000).unwrap();
+        store.insert_notification("notification-2", "usage-warning:s1", "usage_warning", 2_000).unwrap();
+        store.dismiss_notification("notification-1", 1_500, "finished").unwrap();
+        // Already dismissed; the first reason sticks
+        store.dismiss_notification("notification-1", 1_800, "user").unwrap();
+
+        assert_eq!(store.close_open_notifications(3_000).unwrap(), 1);
+        // A later run reuses the label for a new popup
+        store.insert_notification("notification-1", "task-2", "task", 4_000).unwrap();
+        store.dismiss_notification("notification-1", 4_500, "timeout").unwrap();
+
+        let history = store.get_notification_history(0, 10).unwrap();
+        let seen: Vec<_> = history.iter()
+            .map(|n| (n.task_id.as_str(), n.dismissed_at, n.dismissed_by.as_deref()))
+            .collect();
+        assert_eq!(seen, [
+            ("task-2", Some(4_500), Some("timeout")),
+            ("usage-warning:s1", Some(3_000), Some("closed")),
+            ("task-1", Some(1_500), Some("finished")),
+        ]);
+        assert_eq!(store.get_notification_history(2_000, 10).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_notification_stats() {
+        let store = EventStore::in_memory().unwrap();
+        for (window, kind, how) in [
+            ("n1", "task", Some("clicked")),
+            ("n2", "task", Some("user")),
+            ("n3", "task", Some("finished")),
+            ("n4", "task", None),
+            ("n5", "away_summary", Some("timeout")),
+        ] {
+            store.insert_notification(window, "task", kind, 1_000).unwrap();
+            if let Some(how) = how {
+                store.dismiss_notification(window, 2_000, how).unwrap();
+            }
+        }
+
+        let stats = store.get_notification_stats(0).unwrap();
+        assert_eq!(stats.len(), 2);
+        assert_eq!((stats[0].kind.as_str(), stats[0].shown), ("task", 4));
+        assert_eq!((stats[0].clicked, stats[0].dismissed, stats[0].auto_dismissed), (1, 1, 1));
+        assert_eq!(stats[0].click_through_rate, 0.25);
+        assert_eq!(stats[1].auto_dismiss_rate, 1.0);
+        assert!(store.get_notification_stats(5_000).unwrap().is_empty());
+    }
+
     #[test]
     fn test_search_tasks() {
         let store = EventStore::in_memory().unwrap();
@@ -527,8 +3533,12 @@ mod tests {
             started_at: 1000,
             ended_at: Some(2000),
             duration_ms: Some(1000),
+            duration_suspect: false,
             is_background: false,
             subagent_type: None,
+            source: "claude-code".to_string(),
+            reviewed: false,
+            pinned: false,
         }).unwrap();
 
         store.insert_task(&StoredTask {
@@ -540,16 +3550,316 @@ mod tests {
             started_at: 2000,
             ended_at: Some(3000),
             duration_ms: Some(1000),
+            duration_suspect: false,
             is_background: false,
             subagent_type: None,
+            source: "claude-code".to_string(),
+            reviewed: false,
+            pinned: false,
         }).unwrap();
 
-        let results = store.search_tasks("npm", 10).unwrap();
+        let results = store.search_tasks("npm", &TaskFilter::default(), 10).unwrap();
         assert_eq!(results.len(), 1);
         assert_eq!(results[0].id, "task-1");
 
-        let results = store.search_tasks("cargo", 10).unwrap();
+        let results = store.search_tasks("cargo", &TaskFilter::default(), 10).unwrap();
         assert_eq!(results.len(), 1);
         assert_eq!(results[0].id, "task-2");
+
+        // The prompt from the transcript is searchable too, and survives later updates
+        let context = TaskContext {
+            prompt: "fix pagination on the orders page".to_string(),
+            summary: Some("Fixed the page offset".to_string()),
+        };
+        store.set_task_context("task-2", &context).unwrap();
+        store.update_task_status("task-2", "error", 4000).unwrap();
+        let results = store.search_tasks("pagination", &TaskFilter::default(), 10).unwrap();
+        assert_eq!(results.iter().map(|t| t.id.as_str()).collect::<Vec<_>>(), ["task-2"]);
+        assert_eq!(store.get_task_context("task-2").unwrap(), Some(context));
+        assert_eq!(store.get_tasks_missing_context("session-1").unwrap(), [("task-1".to_string(), 1000)]);
+    }
+
+    #[test]
+    fn test_session_summary_with_commits() {
+        let store = EventStore::in_memory().unwrap();
+
+        store.insert_task(&StoredTask {
+            id: "task-1".to_string(),
+            session_id: "session-1".to_string(),
+            tool: "Bash".to_string(),
+            description: Some("cargo test".to_string()),
+            status: "active".to_string(),
+            started_at: 1000,
+            ended_at: None,
+            duration_ms: None,
+            duration_suspect: false,
+            is_background: false,
+            subagent_type: None,
+            source: "claude-code".to_string(),
+            reviewed: false,
+            pinned: false,
+        }).unwrap();
+        store.update_task_status("task-1", "error", 1500).unwrap();
+
+        let commit = SessionCommit {
+            session_id: "session-1".to_string(),
+            commit_hash: "abc123".to_string(),
+            message: "Fix tests".to_string(),
+            committed_at: 2000,
+        };
+        store.insert_session_commits(&[commit.clone(), commit]).unwrap();
+
+        let summary = store.get_session_summary("session-1").unwrap().unwrap();
+        assert_eq!(summary.total_tasks, 1);
+        assert_eq!(summary.error_tasks, 1);
+        assert_eq!(summary.total_duration_ms, 500);
+        assert_eq!(summary.commits.len(), 1);
+        assert_eq!(summary.commits[0].message, "Fix tests");
+
+        assert!(store.get_session_summary("missing").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_branch_and_source_filters() {
+        let store = EventStore::in_memory().unwrap();
+
+        for (session, branch, source) in [("session-1", "main", "claude-code"), ("session-2", "feature/pagination", "aider")] {
+            store.upsert_session(&StoredSession {
+                id: session.to_string(),
+                started_at: 1000,
+                ended_at: None,
+                project_path: Some("/repo".to_string()),
+                branch: Some(branch.to_string()),
+            }).unwrap();
+
+            store.insert_task(&StoredTask {
+                id: format!("{}-task", session),
+                session_id: session.to_string(),
+                tool: "Bash".to_string(),
+                description: Some("cargo test".to_string()),
+                status: "active".to_string(),
+                started_at: 1000,
+                ended_at: None,
+                duration_ms: None,
+                duration_suspect: false,
+                is_background: false,
+                subagent_type: None,
+                source: source.to_string(),
+                reviewed: false,
+                pinned: false,
+            }).unwrap();
+        }
+
+        // Branch is captured once and not overwritten by later upserts
+        store.upsert_session(&StoredSession {
+            id: "session-1".to_string(),
+            started_at: 2000,
+            ended_at: Some(3000),
+            project_path: None,
+            branch: Some("other".to_string()),
+        }).unwrap();
+        assert_eq!(store.get_session("session-1").unwrap().unwrap().branch.as_deref(), Some("main"));
+
+        let filter = TaskFilter { branch: Some("main".to_string()), ..Default::default() };
+        let recent = store.get_recent_tasks(&filter, 10).unwrap();
+        assert_eq!(recent.len(), 1);
+        assert_eq!(recent[0].id, "session-1-task");
+
+        let results = store.search_tasks("cargo", &filter, 10).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(store.get_recent_tasks(&TaskFilter::default(), 10).unwrap().len(), 2);
+
+        let filter = TaskFilter { source: Some("aider".to_string()), ..Default::default() };
+        let recent = store.get_recent_tasks(&filter, 10).unwrap();
+        assert_eq!(recent.len(), 1);
+        assert_eq!(recent[0].source, "aider");
+        let in_project = |path: &str| TaskFilter { project_path: Some(path.to_string()), ..Default::default() };
+        assert_eq!(store.get_recent_tasks(&in_project("/repo/"), 10).unwrap().len(), 2);
+        assert_eq!(store.get_recent_tasks(&in_project("/"), 10).unwrap().len(), 2);
+        assert!(store.get_recent_tasks(&in_project("/re"), 10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_integrity_check_and_schema_version() {
+        let store = EventStore::in_memory().unwrap();
+        assert_eq!(store.integrity_check().unwrap(), ["ok"]);
+        assert_eq!(store.schema_version().unwrap(), MIGRATIONS.len() as i64);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("history.db");
+        assert!(check_database(&path).is_err());
+        drop(EventStore::new(&path).unwrap());
+        let check = check_database(&path).unwrap();
+        assert_eq!(check.pending_migrations, 0);
+        assert_eq!(check.integrity, ["ok"]);
+    }
+
+    #[test]
+    fn test_sync_export_import() {
+        let laptop = EventStore::in_memory().unwrap();
+        let desktop = EventStore::in_memory().unwrap();
+
+        laptop.insert_task(&StoredTask {
+            id: "task-1".to_string(),
+            session_id: "session-1".to_string(),
+            tool: "Bash".to_string(),
+            description: Some("cargo build".to_string()),
+            status: "active".to_string(),
+            started_at: 1000,
+            ended_at: None,
+            duration_ms: None,
+            duration_suspect: false,
+            is_background: false,
+            subagent_type: None,
+            source: "claude-code".to_string(),
+            reviewed: false,
+            pinned: false,
+        }).unwrap();
+
+        let changes = laptop.export_changes(-1).unwrap();
+        assert_eq!(changes.len(), 2);
+        assert_eq!(desktop.import_changes(&changes, "laptop").unwrap(), 2);
+        assert_eq!(desktop.search_tasks("cargo", &TaskFilter::default(), 10).unwrap().len(), 1);
+
+        // Importing again changes nothing, and imported rows are not exported again
+        assert_eq!(desktop.import_changes(&changes, "laptop").unwrap(), 0);
+        assert!(desktop.export_changes(-1).unwrap().is_empty());
+
+        // A later completion replaces the imported active row
+        let cursor = changes.iter().map(SyncRecord::updated_at).max().unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        laptop.update_task_status("task-1", "completed", 5000).unwrap();
+        let changes = laptop.export_changes(cursor).unwrap();
+        assert_eq!(changes.len(), 1);
+        desktop.import_changes(&changes, "laptop").unwrap();
+        assert_eq!(desktop.get_task("task-1").unwrap().unwrap().status, "completed");
+    }
+
+    fn history_task(id: usize, started_at: i64) -> StoredTask {
+        StoredTask {
+            id: format!("task-{:07}", id),
+            session_id: format!("session-{}", id % 100),
+            tool: "Bash".to_string(),
+            description: Some(format!("cargo test --package crate{}", id % 1000)),
+            status: if id.is_multiple_of(10) { "error" } else { "completed" }.to_string(),
+            started_at,
+            ended_at: Some(started_at + 500),
+            duration_ms: Some(500),
+            duration_suspect: false,
+            is_background: false,
+            subagent_type: None,
+            source: "claude-code".to_string(),
+            reviewed: false,
+            pinned: false,
+        }
+    }
+
+    /// Bulk insert synthetic history in one transaction
+    fn insert_history(store: &EventStore, count: usize) {
+        let mut conn = store.conn.lock().unwrap();
+        let tx = conn.transaction().unwrap();
+        for session in 0..100 {
+            tx.execute("INSERT INTO sessions (id, started_at) VALUES (?1, 0)", params![format!("session-{}", session)]).unwrap();
+        }
+        {
+            let mut stmt = tx.prepare(
+                "INSERT INTO tasks (id, session_id, tool, description, status, started_at, ended_at, duration_ms, source)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            ).unwrap();
+            for i in 0..count {
+                // Consecutive pairs share a start time, so pages have ties to break
+                let task = history_task(i, (i / 2) as i64);
+                stmt.execute(params![
+                    task.id, task.session_id, task.tool, task.description, task.status,
+                    task.started_at, task.ended_at, task.duration_ms, task.source
+                ]).unwrap();
+            }
+        }
+        tx.commit().unwrap();
+    }
+
+    fn query_plan(store: &EventStore, sql: &str, values: &[Value]) -> String {
+        let conn = store.conn.lock().unwrap();
+        let mut stmt = conn.prepare(&format!("EXPLAIN QUERY PLAN {}", sql)).unwrap();
+        let details: Vec<String> = stmt
+            .query_map(params_from_iter(values), |row| row.get(3))
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect();
+        details.join("; ")
+    }
+
+    #[test]
+    fn test_history_pages_and_query_plans() {
+        let store = EventStore::in_memory().unwrap();
+        insert_history(&store, 25);
+
+        // Walking pages by cursor visits every task once, newest first
+        let mut filter = TaskFilter::default();
+        let mut seen = Vec::new();
+        loop {
+            let page = store.get_recent_tasks(&filter, 10).unwrap();
+            let Some(last) = page.last() else { break };
+            filter.before = Some(last.cursor());
+            seen.extend(page.into_iter().map(|t| t.id));
+        }
+        assert_eq!(seen.len(), 25);
+        assert_eq!(seen[0], "task-0000024");
+        assert!(seen.windows(2).all(|w| w[0] > w[1]));
+
+        let page = store.search_tasks("crate3", &TaskFilter { before: Some(history_task(13, 6).cursor()), ..Default::default() }, 10).unwrap();
+        assert_eq!(page.iter().map(|t| t.id.as_str()).collect::<Vec<_>>(), ["task-0000003"]);
+
+        let stats = store.get_task_stats().unwrap();
+        assert_eq!((stats.total_tasks, stats.completed_tasks, stats.error_tasks), (25, 22, 3));
+
+        // History pages read the index in order instead of sorting the table
+        let cursor = TaskFilter { before: Some(history_task(5, 2).cursor()), ..Default::default() };
+        for filter in [TaskFilter::default(), cursor] {
+            let (sql, values) = recent_tasks_query(&filter, 50);
+            let plan = query_plan(&store, &sql, &values);
+            assert!(plan.contains("USING INDEX idx_tasks_started_id"), "{}", plan);
+            assert!(!plan.contains("TEMP B-TREE"), "{}", plan);
+        }
+
+        let filter = TaskFilter { status: Some("error".to_string()), ..Default::default() };
+        let (sql, values) = recent_tasks_query(&filter, 50);
+        assert!(query_plan(&store, &sql, &values).contains("USING INDEX idx_tasks_status_started"));
+
+        // Search looks matches up by rowid rather than scanning tasks
+        let (sql, values) = search_tasks_query("cargo", &TaskFilter::default(), 50);
+        let plan = query_plan(&store, &sql, &values);
+        assert!(plan.contains("VIRTUAL TABLE") && plan.contains("INTEGER PRIMARY KEY"), "{}", plan);
+
+        let plan = query_plan(&store, STATS_QUERY, &[]);
+        assert!(plan.contains("COVERING INDEX idx_tasks_status_duration"), "{}", plan);
     }
+
+    /// Run with `cargo test --release -- --ignored` before changing history queries
+    #[test]
+    #[ignore]
+    fn test_million_task_history() {
+        let store = EventStore::in_memory().unwrap();
+        insert_history(&store, 1_000_000);
+
+        fn timed<T>(name: &str, run: impl FnOnce() -> T) -> T {
+            let started = std::time::Instant::now();
+            let result = run();
+            let elapsed = started.elapsed();
+            assert!(elapsed < std::time::Duration::from_millis(100), "{} took {:?}", name, elapsed);
+            result
+        }
+
+        let deep = TaskFilter { before: Some(history_task(1_000, 500).cursor()), ..Default::default() };
+        let errors = TaskFilter { status: Some("error".to_string()), ..Default::default() };
+        timed("first page", || store.get_recent_tasks(&TaskFilter::default(), 50).unwrap());
+        timed("deep page", || store.get_recent_tasks(&deep, 50).unwrap());
+        timed("status page", || store.get_recent_tasks(&errors, 50).unwrap());
+        timed("search", || store.search_tasks("crate42", &TaskFilter::default(), 50).unwrap());
+        timed("session", || store.get_tasks_by_session("session-7").unwrap());
+
+        let stats = timed("stats", || store.get_task_stats().unwrap());
+        assert_eq!(stats.total_tasks, 1_000_000);
+    }
+
 }
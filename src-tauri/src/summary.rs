@@ -0,0 +1,170 @@
+//! Task and todo summaries for pasting into PRs or chat
+//!
+//! `copy_task_summary` puts one of these on the clipboard. Markdown is for
+//! people; JSON keeps every field for tools. Descriptions were already
+//! redacted when the task was stored. Todos are written as GitHub task lists.
+
+use serde_json::json;
+
+use crate::store::{StoredTask, TaskContext};
+use crate::watcher::TodoItem;
+
+/// How a task summary is written
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SummaryFormat {
+    Markdown,
+    Json,
+}
+
+impl SummaryFormat {
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "markdown" | "md" => Ok(SummaryFormat::Markdown),
+            "json" => Ok(SummaryFormat::Json),
+            _ => Err(format!("Unsupported summary format \"{}\"", name.trim())),
+        }
+    }
+}
+
+/// Summarize a task's tool, description, status and duration, plus the
+/// assistant's reply when the transcript had one
+pub fn task_summary(task: &StoredTask, context: Option<&TaskContext>, format: SummaryFormat) -> String {
+    let reply = context.and_then(|c| c.summary.as_deref()).filter(|s| !s.trim().is_empty());
+    match format {
+        SummaryFormat::Markdown => markdown(task, reply),
+        SummaryFormat::Json => {
+            let value = json!({
+                "id": task.id,
+                "session_id": task.session_id,
+                "tool": task.tool,
+                "description": task.description,
+                "status": task.status,
+                "started_at": task.started_at,
+                "ended_at": task.ended_at,
+                "duration_ms": task.duration_ms,
+                "source": task.source,
+                "reply": reply,
+            });
+            serde_json::to_string_pretty(&value).unwrap_or_default()
+        }
+    }
+}
+
+fn markdown(task: &StoredTask, reply: Option<&str>) -> String {
+    let mut heading = format!("**{}** · {}", task.tool, task.status);
+    if let Some(ms) = task.duration_ms {
+        heading.push_str(&format!(" · {}", format_duration(ms)));
+    }
+
+    let mut parts = vec![heading];
+    if let Some(description) = task.description.as_deref().filter(|d| !d.is_empty()) {
+        parts.push(code_block(description));
+    }
+    if let Some(reply) = reply {
+        let quoted: Vec<String> = reply.lines().map(|line| format!("> {}", line).trim_end().to_string()).collect();
+        parts.push(quoted.join("\n"));
+    }
+    parts.join("\n\n")
+}
+
+/// Todos as GitHub-flavored checklists, one section per (heading, todos) group
+pub fn todos_markdown(groups: &[(String, Vec<TodoItem>)]) -> String {
+    let sections: Vec<String> = groups
+        .iter()
+        .filter(|(_, todos)| !todos.is_empty())
+        .map(|(heading, todos)| {
+            let mut lines = vec![format!("## {}", heading), String::new()];
+            lines.extend(todos.iter().map(|todo| {
+                let content = todo.content.replace('\n', " ");
+                match todo.status.as_str() {
+                    "completed" => format!("- [x] {}", content),
+                    "in_progress" => format!("- [ ] {} _(in progress)_", content),
+                    _ => format!("- [ ] {}", content),
+                }
+            }));
+            lines.join("\n")
+        })
+        .collect();
+    sections.join("\n\n")
+}
+
+/// Fence `text` with more backticks than any run inside it
+fn code_block(text: &str) -> String {
+    let longest_run = text.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    let fence = "`".repeat(longest_run.max(2) + 1);
+    format!("{}\n{}\n{}", fence, text, fence)
+}
+
+/// A duration as "42s", "3m 05s" or "1h 02m"
+pub fn format_duration(ms: i64) -> String {
+    let secs = ms / 1000;
+    match secs {
+        0..=59 => format!("{}s", secs),
+        60..=3599 => format!("{}m {:02}s", secs / 60, secs % 60),
+        _ => format!("{}h {:02}m", secs / 3600, (secs % 3600) / 60),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(status: &str, description: &str) -> StoredTask {
+        StoredTask {
+            session_id: "s1".to_string(),
+            description: Some(description.to_string()),
+            status: status.to_string(),
+            ended_at: Some(66_000),
+            duration_ms: Some(65_000),
+            ..StoredTask::test("t1", 1_000)
+        }
+    }
+
+    #[test]
+    fn test_markdown_summary() {
+        let context = TaskContext {
+            prompt: "run the tests".to_string(),
+            summary: Some("Two tests failed:\n\n- parse_empty".to_string()),
+        };
+        let text = task_summary(&task("error", "cargo test"), Some(&context), SummaryFormat::Markdown);
+        assert_eq!(text, "**Bash** · error · 1m 05s\n\n```\ncargo test\n```\n\n> Two tests failed:\n>\n> - parse_empty");
+
+        // A description with its own fence gets a longer one
+        let text = task_summary(&task("completed", "echo ```"), None, SummaryFormat::Markdown);
+        assert_eq!(text, "**Bash** · completed · 1m 05s\n\n````\necho ```\n````");
+    }
+
+    #[test]
+    fn test_json_summary() {
+        let text = task_summary(&task("completed", "cargo build"), None, SummaryFormat::Json);
+        let value: serde_json::Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(value["tool"], "Bash");
+        assert_eq!(value["description"], "cargo build");
+        assert_eq!(value["duration_ms"], 65_000);
+        assert!(value["reply"].is_null());
+    }
+
+    #[test]
+    fn test_todos_markdown() {
+        let todo = |content: &str, status: &str| TodoItem {
+            content: content.to_string(),
+            status: status.to_string(),
+            active_form: content.to_string(),
+        };
+        let groups = vec![
+            ("/repo (s1)".to_string(), vec![todo("Write tests", "completed"), todo("Ship it", "in_progress"), todo("Celebrate", "pending")]),
+            ("s2".to_string(), vec![]),
+        ];
+        assert_eq!(
+            todos_markdown(&groups),
+            "## /repo (s1)\n\n- [x] Write tests\n- [ ] Ship it _(in progress)_\n- [ ] Celebrate"
+        );
+    }
+
+    #[test]
+    fn test_format_names() {
+        assert_eq!(SummaryFormat::from_name(" Markdown ").unwrap(), SummaryFormat::Markdown);
+        assert_eq!(SummaryFormat::from_name("json").unwrap(), SummaryFormat::Json);
+        assert!(SummaryFormat::from_name("html").is_err());
+    }
+}
@@ -0,0 +1,142 @@
+//! Restarting long-running tasks that fail
+//!
+//! The watcher and the plugin manager each run as one long task; if either
+//! panics or gives up on an error, the overlay silently stops updating. A
+//! supervisor restarts the task with exponential backoff, and marks it
+//! degraded in the health metrics once it keeps failing, which the tray and
+//! the window show until a restart stays up.
+
+use std::future::Future;
+use std::time::Duration;
+
+use crate::health;
+
+/// When to restart a failed task and when to call it degraded
+#[derive(Debug, Clone)]
+pub struct RestartPolicy {
+    /// Backoff after the first failure; doubles with each failure in a row
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    /// A run that lasts this long counts as recovered
+    pub stable_after: Duration,
+    /// Failures in a row before the task is reported as degraded
+    pub degraded_after: u32,
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        Self {
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+            stable_after: Duration::from_secs(60),
+            degraded_after: 3,
+        }
+    }
+}
+
+impl RestartPolicy {
+    /// Backoff before the restart following `failures` failures in a row
+    fn backoff(&self, failures: u32) -> Duration {
+        self.initial_backoff
+            .saturating_mul(2u32.saturating_pow(failures.saturating_sub(1)))
+            .min(self.max_backoff)
+    }
+}
+
+/// Run the task made by `start` until it finishes with `Ok`, restarting it after errors and panics
+///
+/// Must be called from within a tokio runtime.
+pub async fn supervise<F, Fut>(name: &str, policy: RestartPolicy, mut start: F)
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<(), String>> + Send + 'static,
+{
+    let mut failures = 0;
+
+    loop {
+        let mut run = tokio::spawn(start());
+
+        // Wait for the run to end, forgiving earlier failures once it has stayed up a while
+        let result = tokio::select! {
+            result = &mut run => result,
+            _ = tokio::time::sleep(policy.stable_after) => {
+                if failures > 0 {
+                    tracing::info!("{} recovered", name);
+                    failures = 0;
+                    health::metrics().set_degraded(name, false);
+                }
+                run.await
+            }
+        };
+
+        let reason = match result {
+            Ok(Ok(())) => {
+                health::metrics().set_degraded(name, false);
+                return;
+            }
+            Ok(Err(e)) => e,
+            Err(e) if e.is_panic() => "panicked".to_string(),
+            Err(e) => e.to_string(),
+        };
+
+        failures += 1;
+        health::metrics().record_restart(name);
+        if failures >= policy.degraded_after {
+            health::metrics().set_degraded(name, true);
+        }
+        let delay = policy.backoff(failures);
+        tracing::error!("{} stopped ({}); restarting in {}ms", name, reason, delay.as_millis());
+        tokio::time::sleep(delay).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_backoff() {
+        let policy = RestartPolicy::default();
+        assert_eq!(policy.backoff(1), Duration::from_secs(1));
+        assert_eq!(policy.backoff(3), Duration::from_secs(4));
+        assert_eq!(policy.backoff(20), policy.max_backoff);
+    }
+
+    #[test]
+    fn test_restarts_until_done() {
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_time().build().unwrap();
+        let attempts = Arc::new(AtomicU32::new(0));
+        let policy = RestartPolicy {
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(4),
+            ..Default::default()
+        };
+
+        let was_degraded = Arc::new(AtomicBool::new(false));
+
+        let (counter, degraded) = (attempts.clone(), was_degraded.clone());
+        runtime.block_on(supervise("flaky", policy, move || {
+            let attempt = counter.fetch_add(1, Ordering::SeqCst);
+            if attempt == 4 {
+                let snapshot = health::metrics().snapshot();
+                degraded.store(snapshot.degraded.contains(&"flaky".to_string()), Ordering::SeqCst);
+            }
+            async move {
+                match attempt {
+                    0 => panic!("first run"),
+                    1..=3 => Err("not yet".to_string()),
+                    _ => Ok(()),
+                }
+            }
+        }));
+
+        // Degraded after the third failure, cleared once a run finished cleanly
+        assert_eq!(attempts.load(Ordering::SeqCst), 5);
+        assert!(was_degraded.load(Ordering::SeqCst));
+        let snapshot = health::metrics().snapshot();
+        assert!(!snapshot.degraded.contains(&"flaky".to_string()));
+        assert_eq!(snapshot.restarts.get("flaky"), Some(&4));
+    }
+}
@@ -0,0 +1,354 @@
+//! History sync between machines through a shared folder
+//!
+//! Each machine appends the sessions and tasks it records to
+//! `<folder>/<machine_id>.jsonl` and merges every other machine's file into its
+//! own store. Rows are keyed by id and the most recent change wins, so a file
+//! can be re-read safely. Copying files between machines is left to whatever
+//! replicates the folder (Dropbox, Syncthing, a network share, ...).
+//!
+//! `merge_database` is the one-off alternative: it merges another machine's
+//! whole history database file, for consolidating histories without syncing.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::adapters::expand_home;
+use crate::config::SyncConfig;
+use crate::store::{self, EventStore, MergeReport, SyncRecord};
+
+const EXTENSION: &str = "jsonl";
+
+/// Rows written and merged by one sync pass
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SyncReport {
+    pub exported: usize,
+    pub imported: usize,
+}
+
+pub struct HistorySync {
+    folder: PathBuf,
+    machine_id: String,
+    store: Arc<EventStore>,
+}
+
+impl HistorySync {
+    pub fn new(config: &SyncConfig, store: Arc<EventStore>) -> Result<Self, SyncError> {
+        if config.folder.trim().is_empty() {
+            return Err(SyncError::ConfigError("sync.folder is not set".to_string()));
+        }
+
+        let machine_id = if config.machine_id.trim().is_empty() {
+            hostname()
+        } else {
+            config.machine_id.clone()
+        };
+
+        Ok(Self {
+            folder: expand_home(config.folder.trim()),
+            machine_id: sanitize_machine_id(&machine_id),
+            store,
+        })
+    }
+
+    /// Write local changes, then merge other machines' changes
+    pub fn run_once(&self) -> Result<SyncReport, SyncError> {
+        fs::create_dir_all(&self.folder).map_err(|e| SyncError::IoError(e.to_string()))?;
+
+        let exported = self.export()?;
+        let mut imported = 0;
+
+        let entries = fs::read_dir(&self.folder).map_err(|e| SyncError::IoError(e.to_string()))?;
+        for path in entries.flatten().map(|entry| entry.path()) {
+            if path.extension().is_none_or(|e| e != EXTENSION) {
+                continue;
+            }
+            let Some(peer) = path.file_stem().and_then(|s| s.to_str()) else {
+                continue;
+            };
+            if peer == self.machine_id {
+                continue;
+            }
+            match self.import_peer(&path, peer) {
+                Ok(count) => imported += count,
+                Err(e) => tracing::warn!("Failed to import history from {}: {}", peer, e),
+            }
+        }
+
+        Ok(SyncReport { exported, imported })
+    }
+
+    /// Append changes made since the last export to this machine's file
+    fn export(&self) -> Result<usize, SyncError> {
+        let key = format!("export:{}", self.machine_id);
+        let since = self.store.get_sync_state(&key)
+            .map_err(|e| SyncError::StoreError(e.to_string()))?
+            .unwrap_or(-1);
+        let started = now_ms();
+
+        let records = self.store.export_changes(since).map_err(|e| SyncError::StoreError(e.to_string()))?;
+        if records.is_empty() {
+            return Ok(0);
+        }
+
+        let mut lines = String::new();
+        for record in &records {
+            let line = serde_json::to_string(record).map_err(|e| SyncError::IoError(e.to_string()))?;
+            lines.push_str(&line);
+            lines.push('\n');
+        }
+
+        let path = self.folder.join(format!("{}.{}", self.machine_id, EXTENSION));
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .map_err(|e| SyncError::IoError(e.to_string()))?;
+        file.write_all(lines.as_bytes()).map_err(|e| SyncError::IoError(e.to_string()))?;
+
+        // Stop just short of the export time so changes later in the same
+        // millisecond are picked up next time
+        let latest = records.iter().map(SyncRecord::updated_at).max().unwrap_or(since);
+        self.store.set_sync_state(&key, latest.min(started - 1))
+            .map_err(|e| SyncError::StoreError(e.to_string()))?;
+
+        Ok(records.len())
+    }
+
+    /// Merge complete lines appended to a peer's file since the last import
+    fn import_peer(&self, path: &Path, peer: &str) -> Result<usize, SyncError> {
+        let key = format!("import:{}", peer);
+        let mut position = self.store.get_sync_state(&key)
+            .map_err(|e| SyncError::StoreError(e.to_string()))?
+            .unwrap_or(0)
+            .max(0) as u64;
+
+        let mut file = File::open(path).map_err(|e| SyncError::IoError(e.to_string()))?;
+        let len = file.metadata().map_err(|e| SyncError::IoError(e.to_string()))?.len();
+
+        // The file was replaced; merging is idempotent, so read it all again
+        if len < position {
+            position = 0;
+        }
+        if len == position {
+            return Ok(0);
+        }
+
+        file.seek(SeekFrom::Start(position)).map_err(|e| SyncError::IoError(e.to_string()))?;
+        let mut buffer = String::new();
+        file.take(len - position)
+            .read_to_string(&mut buffer)
+            .map_err(|e| SyncError::IoError(e.to_string()))?;
+
+        // A partially replicated last line is left for the next pass
+        let complete = buffer.rfind('\n').map(|i| i + 1).unwrap_or(0);
+        let records: Vec<SyncRecord> = buffer[..complete]
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .filter_map(|line| match serde_json::from_str(line) {
+                Ok(record) => Some(record),
+                Err(e) => {
+                    tracing::warn!("Skipping invalid sync record from {}: {}", peer, e);
+                    None
+                }
+            })
+            .collect();
+
+        let imported = self.store.import_changes(&records, peer)
+            .map_err(|e| SyncError::StoreError(e.to_string()))?;
+        self.store.set_sync_state(&key, (position + complete as u64) as i64)
+            .map_err(|e| SyncError::StoreError(e.to_string()))?;
+
+        Ok(imported)
+    }
+}
+
+/// Sync every `interval_secs` on the current runtime
+///
+/// Each pass does blocking file and database work, so it runs on the
+/// runtime's blocking pool.
+pub fn start(config: SyncConfig, store: Arc<EventStore>) {
+    let sync = match HistorySync::new(&config, store) {
+        Ok(sync) => Arc::new(sync),
+        Err(e) => {
+            tracing::error!("History sync disabled: {}", e);
+            return;
+        }
+    };
+    let interval = Duration::from_secs(config.interval_secs.max(10));
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            let sync = sync.clone();
+            match tokio::task::spawn_blocking(move || sync.run_once()).await {
+                Ok(Ok(report)) if report.exported + report.imported > 0 => {
+                    tracing::info!("History sync: exported {}, imported {}", report.exported, report.imported);
+                }
+                Ok(Ok(_)) => {}
+                Ok(Err(e)) => tracing::warn!("History sync failed: {}", e),
+                Err(e) => tracing::error!("History sync task failed: {}", e),
+            }
+        }
+    });
+}
+
+/// Merge the history database at `path`, copied from another machine, into `store`
+///
+/// Its rows are marked as coming from `origin`, by default the file's name.
+/// The file is read into a temporary copy, which is brought up to this
+/// version's schema, so the original is left as it was.
+pub fn merge_database(store: &EventStore, path: &Path, origin: Option<&str>) -> Result<MergeReport, SyncError> {
+    let check = store::check_database(path).map_err(|e| SyncError::StoreError(e.to_string()))?;
+    if check.pending_migrations < 0 {
+        return Err(SyncError::StoreError(format!("{} was written by a newer version of the overlay", path.display())));
+    }
+    let origin = origin
+        .filter(|origin| !origin.trim().is_empty())
+        .map(str::to_string)
+        .or_else(|| path.file_stem().map(|stem| stem.to_string_lossy().to_string()))
+        .unwrap_or_else(|| "merged".to_string());
+    let origin = sanitize_machine_id(&origin);
+
+    let copy = std::env::temp_dir().join(format!("agent-progress-merge-{}-{}.db", std::process::id(), now_ms()));
+    let merged = store::snapshot_database(path, &copy)
+        .and_then(|_| EventStore::new(&copy).map(drop))
+        .and_then(|_| store.merge_database(&copy, &origin))
+        .map_err(|e| SyncError::StoreError(e.to_string()));
+    for suffix in ["", "-wal", "-shm"] {
+        let _ = fs::remove_file(format!("{}{}", copy.display(), suffix));
+    }
+
+    let report = merged?;
+    tracing::info!(
+        "Merged {:?} as {}: {} sessions and {} tasks added, {} tasks updated, {} status conflicts",
+        path, origin, report.sessions_added, report.tasks_added, report.tasks_updated, report.conflicts
+    );
+    Ok(report)
+}
+
+fn hostname() -> String {
+    std::env::var("COMPUTERNAME")
+        .or_else(|_| std::env::var("HOSTNAME"))
+        .ok()
+        .or_else(|| fs::read_to_string("/etc/hostname").ok())
+        .map(|h| h.trim().to_string())
+        .filter(|h| !h.is_empty())
+        .unwrap_or_else(|| "machine".to_string())
+}
+
+/// Keep machine ids usable as file names
+fn sanitize_machine_id(id: &str) -> String {
+    id.trim()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '-' })
+        .collect()
+}
+
+fn now_ms() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+/// Sync errors
+#[derive(Debug, Clone)]
+pub enum SyncError {
+    ConfigError(String),
+    IoError(String),
+    StoreError(String),
+}
+
+impl std::fmt::Display for SyncError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SyncError::ConfigError(e) => write!(f, "Sync config error: {}", e),
+            SyncError::IoError(e) => write!(f, "IO error: {}", e),
+            SyncError::StoreError(e) => write!(f, "Store error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for SyncError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::{StoredTask, TaskFilter};
+    use tempfile::tempdir;
+
+    fn machine(folder: &Path, id: &str) -> HistorySync {
+        let config = SyncConfig {
+            enabled: true,
+            folder: folder.to_string_lossy().to_string(),
+            machine_id: id.to_string(),
+            ..Default::default()
+        };
+        HistorySync::new(&config, Arc::new(EventStore::in_memory().unwrap())).unwrap()
+    }
+
+    #[test]
+    fn test_shared_folder_sync() {
+        let dir = tempdir().unwrap();
+        let laptop = machine(dir.path(), "laptop");
+        let desktop = machine(dir.path(), "my desktop");
+        assert_eq!(desktop.machine_id, "my-desktop");
+
+        laptop.store.insert_task(&StoredTask {
+            description: Some("npm test".to_string()),
+            ..StoredTask::test("task-1", 1000)
+        }).unwrap();
+
+        let report = laptop.run_once().unwrap();
+        assert_eq!(report.exported, 2);
+        assert!(dir.path().join("laptop.jsonl").exists());
+
+        let report = desktop.run_once().unwrap();
+        assert_eq!(report.exported, 0);
+        assert_eq!(report.imported, 2);
+        assert_eq!(desktop.store.search_tasks("npm", &TaskFilter::default(), 10).unwrap().len(), 1);
+
+        // Nothing new on either side
+        assert_eq!(laptop.run_once().unwrap().imported, 0);
+        assert_eq!(desktop.run_once().unwrap().imported, 0);
+    }
+
+    #[test]
+    fn test_merge_database() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("overlay-history.db");
+        let laptop = EventStore::new(&path).unwrap();
+        laptop.insert_task(&StoredTask::test("shared", 1_000)).unwrap();
+        laptop.update_task_status("shared", "completed", 2_000).unwrap();
+        laptop.insert_task(&StoredTask::test("laptop-only", 3_000)).unwrap();
+        laptop.set_task_tag("laptop-only", "deploy", true, 3_000).unwrap();
+        laptop.set_task_pid("laptop-only", 4242).unwrap();
+        laptop.insert_task(&StoredTask::test("ended-here", 4_000)).unwrap();
+
+        let desktop = EventStore::in_memory().unwrap();
+        desktop.insert_task(&StoredTask::test("shared", 1_000)).unwrap();
+        desktop.insert_task(&StoredTask::test("ended-here", 4_000)).unwrap();
+        desktop.update_task_status("ended-here", "error", 5_000).unwrap();
+
+        let report = merge_database(&desktop, &path, Some("laptop")).unwrap();
+        assert_eq!(report, MergeReport { sessions_added: 1, tasks_added: 1, tasks_updated: 1, conflicts: 1 });
+        // The task ended on the laptop, which the desktop never saw, and the other way round
+        assert_eq!(desktop.get_task("shared").unwrap().unwrap().status, "completed");
+        assert_eq!(desktop.get_task("ended-here").unwrap().unwrap().status, "error");
+        assert_eq!(desktop.get_task_tags("laptop-only").unwrap()[0].name, "deploy");
+        // The laptop's shell isn't a process on this machine
+        assert_eq!(desktop.get_active_task_pid("laptop-only").unwrap(), None);
+        assert!(desktop.get_monitored_tasks().unwrap().is_empty());
+        // Merged rows belong to the laptop, so sync doesn't send them on
+        assert_eq!(desktop.export_changes(-1).unwrap().len(), 4);
+
+        assert_eq!(merge_database(&desktop, &path, None).unwrap(), MergeReport::default());
+        assert!(merge_database(&desktop, &dir.path().join("missing.db"), None).is_err());
+    }
+}
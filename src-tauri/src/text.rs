@@ -0,0 +1,108 @@
+//! Cleaning up free text before it is stored or displayed
+//!
+//! Descriptions come straight from agent tool input: whole scripts, pasted
+//! logs, text in any script and any Unicode normal form. They are normalized
+//! once when they enter the router, so search matches however the text was
+//! composed, and shortened on grapheme boundaries wherever a size limit
+//! applies, so a cut never splits a character or an emoji sequence.
+
+use unicode_normalization::UnicodeNormalization;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Longest description kept in history and the search index
+pub const MAX_DESCRIPTION_GRAPHEMES: usize = 2000;
+
+const ELLIPSIS: &str = "…";
+
+/// NFC-normalize `text`, turning control characters into spaces and collapsing whitespace runs
+pub fn normalize(text: &str) -> String {
+    let mut normalized = String::with_capacity(text.len());
+    let mut pending_space = false;
+    for c in text.nfc() {
+        if c.is_whitespace() || c.is_control() {
+            pending_space = !normalized.is_empty();
+        } else {
+            if pending_space {
+                normalized.push(' ');
+                pending_space = false;
+            }
+            normalized.push(c);
+        }
+    }
+    normalized
+}
+
+/// Normalize a task description and cap it at [`MAX_DESCRIPTION_GRAPHEMES`]
+pub fn clean_description(description: &str) -> String {
+    truncate(&normalize(description), MAX_DESCRIPTION_GRAPHEMES)
+}
+
+/// Words at the start of a description that similar tasks share
+const PREFIX_WORDS: usize = 2;
+
+/// The start of a normalized description, e.g. "npm test" for "npm test -- --watch"
+///
+/// Tasks with the same tool and prefix are taken to be the same kind of
+/// work when estimating how long one will take.
+pub fn description_prefix(description: &str) -> String {
+    description.split(' ').take(PREFIX_WORDS).collect::<Vec<_>>().join(" ")
+}
+
+/// Shorten `text` to at most `max` graphemes, ending with an ellipsis if anything was cut
+pub fn truncate(text: &str, max: usize) -> String {
+    truncate_by(text, max, |_| 1)
+}
+
+/// Shorten `text` to at most `max` UTF-16 code units, for OS limits such as tray tooltips
+pub fn truncate_utf16(text: &str, max: usize) -> String {
+    truncate_by(text, max, |grapheme| grapheme.encode_utf16().count())
+}
+
+fn truncate_by(text: &str, max: usize, size: impl Fn(&str) -> usize) -> String {
+    if text.graphemes(true).map(&size).sum::<usize>() <= max {
+        return text.to_string();
+    }
+
+    let budget = max.saturating_sub(size(ELLIPSIS));
+    let mut used = 0;
+    let mut end = 0;
+    for (offset, grapheme) in text.grapheme_indices(true) {
+        used += size(grapheme);
+        if used > budget {
+            break;
+        }
+        end = offset + grapheme.len();
+    }
+    format!("{}{}", text[..end].trim_end(), ELLIPSIS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize() {
+        // "é" as e + combining acute becomes the single precomposed character
+        assert_eq!(normalize("cafe\u{301}"), "caf\u{e9}");
+        assert_eq!(normalize("  npm\tinstall\n\n--save\u{7}  "), "npm install --save");
+        assert_eq!(description_prefix("npm test -- --watch"), "npm test");
+        assert_eq!(description_prefix("ls"), "ls");
+    }
+
+    #[test]
+    fn test_truncate_on_grapheme_boundaries() {
+        assert_eq!(truncate("short", 10), "short");
+        assert_eq!(truncate("hello world", 8), "hello w…");
+        assert_eq!(truncate("hello world", 7), "hello…");
+
+        // A family emoji is one grapheme of several code points
+        let family = "👨\u{200d}👩\u{200d}👧";
+        assert_eq!(truncate(&format!("{}{}{}", family, family, family), 2), format!("{}…", family));
+        assert_eq!(truncate("日本語のテキスト", 4), "日本語…");
+
+        // Eight UTF-16 units per family, so only one fits in ten with the ellipsis
+        let tooltip = truncate_utf16(&format!("{}{}", family, family), 10);
+        assert_eq!(tooltip, format!("{}…", family));
+        assert!(tooltip.encode_utf16().count() <= 10);
+    }
+}
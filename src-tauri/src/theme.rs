@@ -0,0 +1,129 @@
+//! Built-in color themes, selected by `window.theme`
+//!
+//! The windows style themselves from what the theme command returns, so the
+//! palettes live in one place. Each status also carries a symbol, a label
+//! and a border style, so in the high-contrast theme a failed task differs
+//! from a finished one by more than its color.
+
+use serde::Serialize;
+
+/// Colors of the overlay, as CSS hex values
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Palette {
+    pub bg: &'static str,
+    pub card: &'static str,
+    pub border: &'static str,
+    pub text: &'static str,
+    pub muted: &'static str,
+    pub accent: &'static str,
+    pub success: &'static str,
+    pub error: &'static str,
+    pub warning: &'static str,
+}
+
+/// How one status is shown on task cards and popups
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StatusIndicator {
+    pub color: &'static str,
+    /// Shown before the label; empty leaves the status to its icon and color
+    pub symbol: &'static str,
+    pub label: &'static str,
+    /// CSS border style: "solid", "dashed", "dotted" or "double"
+    pub border: &'static str,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Theme {
+    pub name: &'static str,
+    pub colors: Palette,
+    pub active: StatusIndicator,
+    pub complete: StatusIndicator,
+    pub error: StatusIndicator,
+    pub overdue: StatusIndicator,
+    pub warning: StatusIndicator,
+}
+
+impl Theme {
+    /// Look up a built-in theme
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        match name.trim() {
+            "" | "dark" => Ok(Self::dark()),
+            "high-contrast" => Ok(Self::high_contrast()),
+            other => Err(format!("Unknown theme \"{}\"", other)),
+        }
+    }
+
+    /// The configured theme, falling back to dark
+    pub fn from_config(name: &str) -> Self {
+        Self::from_name(name).unwrap_or_else(|e| {
+            tracing::warn!("{}, using the dark theme", e);
+            Self::dark()
+        })
+    }
+
+    fn dark() -> Self {
+        let status = |color, label| StatusIndicator { color, symbol: "", label, border: "solid" };
+        Self {
+            name: "dark",
+            colors: Palette {
+                bg: "#0f172a",
+                card: "#1e293b",
+                border: "#334155",
+                text: "#f8fafc",
+                muted: "#94a3b8",
+                accent: "#f59e0b",
+                success: "#22c55e",
+                error: "#ef4444",
+                warning: "#eab308",
+            },
+            active: status("#a5b4fc", "Running"),
+            complete: status("#86efac", "Done"),
+            error: status("#fca5a5", "Failed"),
+            overdue: status("#fdba74", "Overdue"),
+            warning: status("#fde047", "Warning"),
+        }
+    }
+
+    fn high_contrast() -> Self {
+        Self {
+            name: "high-contrast",
+            colors: Palette {
+                bg: "#000000",
+                card: "#000000",
+                border: "#ffffff",
+                text: "#ffffff",
+                muted: "#e0e0e0",
+                accent: "#ffff00",
+                success: "#00ff7f",
+                error: "#ff6b6b",
+                warning: "#ffd400",
+            },
+            active: StatusIndicator { color: "#ffff00", symbol: "▶", label: "Running", border: "solid" },
+            complete: StatusIndicator { color: "#00ff7f", symbol: "✓", label: "Done", border: "solid" },
+            error: StatusIndicator { color: "#ff6b6b", symbol: "✕", label: "Failed", border: "double" },
+            overdue: StatusIndicator { color: "#ffa500", symbol: "⏱", label: "Overdue", border: "dashed" },
+            warning: StatusIndicator { color: "#ffd400", symbol: "⚠", label: "Warning", border: "dotted" },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_high_contrast_statuses_differ_beyond_color() {
+        let theme = Theme::from_name("high-contrast").unwrap();
+        let statuses = [&theme.active, &theme.complete, &theme.error, &theme.overdue, &theme.warning];
+        for (index, status) in statuses.iter().enumerate() {
+            assert!(!status.symbol.is_empty());
+            for other in &statuses[index + 1..] {
+                assert_ne!(status.symbol, other.symbol);
+                assert_ne!(status.label, other.label);
+            }
+        }
+
+        assert!(Theme::from_name("solarized").is_err());
+        assert_eq!(Theme::from_config("solarized"), Theme::from_name("dark").unwrap());
+    }
+}
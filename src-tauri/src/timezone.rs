@@ -0,0 +1,238 @@
+//! Local time for bucketing and schedules
+//!
+//! Timestamps are stored as UTC milliseconds everywhere; only the code that
+//! groups them into days and hours or compares them against a time of day
+//! converts to local time, through a [`Zone`]. Buckets are computed from the
+//! zone's actual midnights and hour starts, so a day can be 23 or 25 hours
+//! long and the repeated hour when clocks go back is two separate buckets
+//! rather than one hour counted twice.
+
+use chrono::{DateTime, Duration, LocalResult, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Timelike};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+
+/// The timezone local times are read in
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Zone {
+    /// The system's timezone
+    Local,
+    Named(Tz),
+}
+
+impl Zone {
+    /// Parse an IANA name like "Europe/Berlin"; empty means the system timezone
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        let name = name.trim();
+        if name.is_empty() || name.eq_ignore_ascii_case("local") {
+            return Ok(Zone::Local);
+        }
+        name.parse::<Tz>()
+            .map(Zone::Named)
+            .map_err(|_| format!("Unknown timezone: {}", name))
+    }
+
+    /// Like [`Zone::from_name`], falling back to the system timezone
+    pub fn from_config(name: &str) -> Self {
+        Self::from_name(name).unwrap_or_else(|e| {
+            tracing::warn!("{}, using the system timezone", e);
+            Zone::Local
+        })
+    }
+
+    /// Wall-clock time at `ms`
+    pub fn local_datetime(&self, ms: i64) -> NaiveDateTime {
+        match self {
+            Zone::Local => to_local(&chrono::Local, ms),
+            Zone::Named(tz) => to_local(tz, ms),
+        }
+    }
+
+    /// UTC millis of the first instant of `date`
+    ///
+    /// Usually midnight; where clocks skip midnight, the first hour that exists.
+    pub fn start_of_day(&self, date: NaiveDate) -> i64 {
+        match self {
+            Zone::Local => first_instant(&chrono::Local, date),
+            Zone::Named(tz) => first_instant(tz, date),
+        }
+    }
+
+    /// UTC millis of the start of the local hour containing `ms`
+    pub fn start_of_hour(&self, ms: i64) -> i64 {
+        let local = self.local_datetime(ms);
+        let into_hour = local.minute() as i64 * 60_000
+            + local.second() as i64 * 1_000
+            + (local.nanosecond() / 1_000_000) as i64;
+        ms - into_hour
+    }
+
+    /// The `count` most recent buckets up to and including the one containing `now_ms`, oldest first
+    pub fn buckets(&self, size: BucketSize, count: usize, now_ms: i64) -> Vec<Bucket> {
+        let mut buckets = Vec::with_capacity(count);
+        match size {
+            BucketSize::Day => {
+                let today = self.local_datetime(now_ms).date();
+                for days_back in (0..count as i64).rev() {
+                    let date = today - Duration::days(days_back);
+                    buckets.push(Bucket {
+                        label: date.format("%Y-%m-%d").to_string(),
+                        start_ms: self.start_of_day(date),
+                        end_ms: self.start_of_day(date + Duration::days(1)),
+                    });
+                }
+            }
+            BucketSize::Hour => {
+                let mut end_ms = self.start_of_hour(now_ms) + HOUR_MS;
+                for _ in 0..count {
+                    let start_ms = self.start_of_hour(end_ms - 1);
+                    buckets.push(Bucket {
+                        label: self.hour_label(start_ms),
+                        start_ms,
+                        end_ms,
+                    });
+                    end_ms = start_ms;
+                }
+                buckets.reverse();
+            }
+        }
+        buckets
+    }
+
+    /// "2024-11-03 01:00 -04:00"; the offset tells a repeated hour apart
+    fn hour_label(&self, ms: i64) -> String {
+        const FORMAT: &str = "%Y-%m-%d %H:00 %:z";
+        match self {
+            Zone::Local => to_zoned(&chrono::Local, ms).format(FORMAT).to_string(),
+            Zone::Named(tz) => to_zoned(tz, ms).format(FORMAT).to_string(),
+        }
+    }
+}
+
+const HOUR_MS: i64 = 3_600_000;
+
+fn to_zoned<T: TimeZone>(tz: &T, ms: i64) -> DateTime<T> {
+    tz.timestamp_millis_opt(ms).earliest().unwrap_or_else(|| tz.timestamp_millis_opt(0).unwrap())
+}
+
+fn to_local<T: TimeZone>(tz: &T, ms: i64) -> NaiveDateTime {
+    to_zoned(tz, ms).naive_local()
+}
+
+fn first_instant<T: TimeZone>(tz: &T, date: NaiveDate) -> i64 {
+    for hour in 0..24 {
+        let Some(time) = NaiveTime::from_hms_opt(hour, 0, 0) else {
+            break;
+        };
+        match tz.from_local_datetime(&date.and_time(time)) {
+            LocalResult::Single(t) | LocalResult::Ambiguous(t, _) => return t.timestamp_millis(),
+            LocalResult::None => continue,
+        }
+    }
+    date.and_time(NaiveTime::MIN).and_utc().timestamp_millis()
+}
+
+/// How history is grouped
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BucketSize {
+    Hour,
+    Day,
+}
+
+/// A span of local time, as UTC millis `[start_ms, end_ms)`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Bucket {
+    pub label: String,
+    pub start_ms: i64,
+    pub end_ms: i64,
+}
+
+/// A daily window of local time, which may wrap past midnight
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QuietHours {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+    pub zone: Zone,
+}
+
+impl QuietHours {
+    /// Parse "22:00-07:00"; empty means no quiet hours
+    pub fn parse(spec: &str, zone: Zone) -> Result<Option<Self>, String> {
+        let spec = spec.trim();
+        if spec.is_empty() {
+            return Ok(None);
+        }
+        let parse_time = |s: &str| NaiveTime::parse_from_str(s.trim(), "%H:%M")
+            .map_err(|_| format!("Invalid quiet hours \"{}\": expected HH:MM-HH:MM", spec));
+        let (start, end) = spec.split_once('-')
+            .ok_or_else(|| format!("Invalid quiet hours \"{}\": expected HH:MM-HH:MM", spec))?;
+        Ok(Some(Self { start: parse_time(start)?, end: parse_time(end)?, zone }))
+    }
+
+    /// Whether `ms` falls in the window, by the wall clock in the zone
+    pub fn contains(&self, ms: i64) -> bool {
+        let time = self.zone.local_datetime(ms).time();
+        if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc_ms(s: &str) -> i64 {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap().and_utc().timestamp_millis()
+    }
+
+    #[test]
+    fn test_buckets_across_dst() {
+        let zone = Zone::from_name("America/New_York").unwrap();
+
+        // Clocks went back at 2024-11-03 06:00 UTC, making that day 25 hours long
+        let days = zone.buckets(BucketSize::Day, 3, utc_ms("2024-11-04 15:00"));
+        let labels: Vec<&str> = days.iter().map(|b| b.label.as_str()).collect();
+        assert_eq!(labels, ["2024-11-02", "2024-11-03", "2024-11-04"]);
+        assert_eq!(days[1].end_ms - days[1].start_ms, 25 * HOUR_MS);
+        assert_eq!(days[1].start_ms, days[0].end_ms);
+
+        // The repeated 1 AM hour is two buckets, and buckets tile without overlap
+        let hours = zone.buckets(BucketSize::Hour, 4, utc_ms("2024-11-03 07:30"));
+        let labels: Vec<&str> = hours.iter().map(|b| b.label.as_str()).collect();
+        assert_eq!(labels, [
+            "2024-11-03 00:00 -04:00",
+            "2024-11-03 01:00 -04:00",
+            "2024-11-03 01:00 -05:00",
+            "2024-11-03 02:00 -05:00",
+        ]);
+        assert!(hours.windows(2).all(|w| w[0].end_ms == w[1].start_ms));
+
+        // Spring forward: 2024-03-10 is 23 hours long
+        let day = zone.buckets(BucketSize::Day, 1, utc_ms("2024-03-10 12:00"));
+        assert_eq!(day[0].end_ms - day[0].start_ms, 23 * HOUR_MS);
+
+        assert!(Zone::from_name("Mars/Olympus").is_err());
+        assert_eq!(Zone::from_name("").unwrap(), Zone::Local);
+    }
+
+    #[test]
+    fn test_quiet_hours() {
+        let zone = Zone::from_name("Europe/Berlin").unwrap();
+        let quiet = QuietHours::parse("22:00-07:00", zone).unwrap().unwrap();
+
+        // 23:30 local in summer (UTC+2) and in winter (UTC+1)
+        assert!(quiet.contains(utc_ms("2024-07-01 21:30")));
+        assert!(quiet.contains(utc_ms("2024-01-15 22:30")));
+        // 12:00 local
+        assert!(!quiet.contains(utc_ms("2024-07-01 10:00")));
+        // 06:59 and 07:00 local
+        assert!(quiet.contains(utc_ms("2024-01-15 05:59")));
+        assert!(!quiet.contains(utc_ms("2024-01-15 06:00")));
+
+        assert_eq!(QuietHours::parse("", zone).unwrap(), None);
+        assert!(QuietHours::parse("late", zone).is_err());
+    }
+}
@@ -0,0 +1,460 @@
+//! Reading Claude Code's session transcripts
+//!
+//! Claude Code writes every session to `projects/<project>/<session id>.jsonl`
+//! under its config directory, one JSON object per message. The format is
+//! Claude's own and undocumented, so anything unexpected in a line is skipped
+//! rather than treated as an error.
+//!
+//! Besides being read whole for enrichment, transcripts are tailed by
+//! [`TranscriptSource`] as they are written, for the token usage each of the
+//! assistant's replies reports and for API errors saying the session hit a
+//! rate or usage limit.
+
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+
+use chrono::DateTime;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::adapters::{self, TargetWatch};
+use crate::config::{ContextUsageConfig, UsageWarningsConfig};
+use crate::store::{ContextSample, TokenUsage, UsageWarning};
+use crate::watcher::{self, EventSender, FileState, Source, WatcherError, WatcherEvent};
+
+/// Directory under Claude's config directory that holds the transcripts
+pub const PROJECTS_DIR: &str = "projects";
+
+/// One prompt from the user and what the assistant did about it
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Turn {
+    pub prompt: String,
+    /// When the prompt was sent, in millis
+    pub started_at: i64,
+    /// The assistant's last reply in the turn
+    pub summary: Option<String>,
+    /// Tool calls made during the turn, including by subagents
+    pub tool_use_ids: HashSet<String>,
+    /// Model that made each tool call, by tool call id
+    pub tool_models: HashMap<String, String>,
+    /// Model behind the main conversation's last reply in the turn
+    pub model: Option<String>,
+}
+
+impl Turn {
+    /// The model that ran a task: the one that made its tool call, or else the turn's
+    pub fn model_for(&self, task_id: &str) -> Option<&str> {
+        self.tool_models.get(task_id).map(String::as_str).or(self.model.as_deref())
+    }
+}
+
+/// Model id of a reply; error replies Claude Code writes itself have a placeholder
+fn reply_model(entry: &Value) -> Option<&str> {
+    entry["message"]["model"].as_str().filter(|model| !model.is_empty() && !model.starts_with('<'))
+}
+
+/// Find the transcript of `session_id` in any of Claude's project directories
+pub fn find_transcript(claude_dir: &Path, session_id: &str) -> Option<PathBuf> {
+    // The id becomes a file name, so it mustn't be able to leave the directory
+    if session_id.is_empty() || session_id.contains(['/', '\\']) || session_id.contains("..") {
+        return None;
+    }
+    let file_name = format!("{}.jsonl", session_id);
+    fs::read_dir(claude_dir.join(PROJECTS_DIR))
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path().join(&file_name))
+        .find(|path| path.is_file())
+}
+
+/// Split a transcript into turns, oldest first
+pub fn read_turns(path: &Path) -> Vec<Turn> {
+    let Ok(file) = File::open(path) else {
+        return Vec::new();
+    };
+
+    let mut turns: Vec<Turn> = Vec::new();
+    for line in BufReader::new(file).lines().map_while(Result::ok) {
+        let Ok(entry) = serde_json::from_str::<Value>(&line) else {
+            continue;
+        };
+        // Subagents' messages belong to the turn that started them
+        let sidechain = entry["isSidechain"].as_bool().unwrap_or(false);
+        let content = &entry["message"]["content"];
+
+        match entry["type"].as_str() {
+            Some("user") if !sidechain && !entry["isMeta"].as_bool().unwrap_or(false) => {
+                if let Some(prompt) = prompt_text(content) {
+                    turns.push(Turn {
+                        prompt,
+                        started_at: timestamp_ms(&entry).unwrap_or(0),
+                        ..Turn::default()
+                    });
+                }
+            }
+            Some("assistant") => {
+                let Some(turn) = turns.last_mut() else {
+                    continue;
+                };
+                let model = reply_model(&entry);
+                if !sidechain && model.is_some() {
+                    turn.model = model.map(str::to_string);
+                }
+                for block in content.as_array().into_iter().flatten() {
+                    match block["type"].as_str() {
+                        Some("tool_use") => {
+                            if let Some(id) = block["id"].as_str() {
+                                turn.tool_use_ids.insert(id.to_string());
+                                if let Some(model) = model {
+                                    turn.tool_models.insert(id.to_string(), model.to_string());
+                                }
+                            }
+                        }
+                        Some("text") if !sidechain => {
+                            if let Some(text) = block["text"].as_str().filter(|t| !t.trim().is_empty()) {
+                                turn.summary = Some(text.to_string());
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    turns
+}
+
+/// The turn a task ran in: the one that made its tool call, or else the last
+/// one started before it
+pub fn turn_for<'a>(turns: &'a [Turn], task_id: &str, started_at: i64) -> Option<&'a Turn> {
+    turns
+        .iter()
+        .find(|turn| turn.tool_use_ids.contains(task_id))
+        .or_else(|| turns.iter().rev().find(|turn| turn.started_at <= started_at))
+}
+
+/// Text the user typed; tool results also arrive as user messages but have no text block
+fn prompt_text(content: &Value) -> Option<String> {
+    let text = match content {
+        Value::String(text) => text.clone(),
+        Value::Array(blocks) => blocks
+            .iter()
+            .filter(|block| block["type"] == "text")
+            .filter_map(|block| block["text"].as_str())
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => return None,
+    };
+    Some(text).filter(|text| !text.trim().is_empty())
+}
+
+/// How full a session's context window is, sent as `context-usage`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContextUsage {
+    #[serde(flatten)]
+    pub sample: ContextSample,
+    pub percent: f64,
+    /// Full enough that auto-compaction is close
+    pub warning: bool,
+}
+
+impl ContextUsage {
+    fn new(sample: ContextSample, warning_percent: f64) -> Self {
+        let percent = if sample.context_window > 0 {
+            sample.context_tokens as f64 * 100.0 / sample.context_window as f64
+        } else {
+            0.0
+        };
+        Self { sample, percent, warning: percent >= warning_percent }
+    }
+}
+
+/// The context window use reported by an assistant reply in the main conversation
+///
+/// Everything sent to the model counts, cached or not, plus the reply itself,
+/// which is part of the context from the next request on.
+fn context_sample(session_id: &str, entry: &Value, window_tokens: u64) -> Option<ContextSample> {
+    if entry["type"] != "assistant" || entry["isSidechain"].as_bool().unwrap_or(false) {
+        return None;
+    }
+    let usage = &entry["message"]["usage"];
+    if !usage.is_object() {
+        return None;
+    }
+    let tokens: u64 = ["input_tokens", "cache_creation_input_tokens", "cache_read_input_tokens", "output_tokens"]
+        .iter()
+        .filter_map(|key| usage[key].as_u64())
+        .sum();
+    Some(ContextSample {
+        session_id: session_id.to_string(),
+        timestamp: timestamp_ms(entry).unwrap_or_else(|| adapters::now_ms() as i64),
+        context_tokens: tokens as i64,
+        context_window: window_tokens as i64,
+        model: entry["message"]["model"].as_str().map(str::to_string),
+    })
+}
+
+/// The tokens an assistant reply used, subagents' replies included
+fn token_usage(session_id: &str, entry: &Value) -> Option<TokenUsage> {
+    if entry["type"] != "assistant" {
+        return None;
+    }
+    let usage = &entry["message"]["usage"];
+    if !usage.is_object() {
+        return None;
+    }
+    let tokens = |key: &str| usage[key].as_i64().unwrap_or(0);
+    Some(TokenUsage {
+        session_id: session_id.to_string(),
+        timestamp: timestamp_ms(entry).unwrap_or_else(|| adapters::now_ms() as i64),
+        model: reply_model(entry)?.to_string(),
+        input_tokens: tokens("input_tokens"),
+        output_tokens: tokens("output_tokens"),
+        cache_read_tokens: tokens("cache_read_input_tokens"),
+        cache_creation_tokens: tokens("cache_creation_input_tokens"),
+    })
+}
+
+/// What kind of limit an API error message reports, if any
+///
+/// Also used for the messages of `rate_limited` hook events, which are
+/// assumed to be about a rate limit when nothing more specific matches.
+pub fn limit_kind(message: &str) -> Option<&'static str> {
+    let message = message.to_lowercase();
+    if message.contains("usage limit") || message.contains("limit reached") || message.contains("quota") {
+        Some("usage_limit")
+    } else if message.contains("rate limit") || message.contains("rate_limit") || message.contains("429") {
+        Some("rate_limit")
+    } else if message.contains("overloaded") || message.contains("529") {
+        Some("overloaded")
+    } else {
+        None
+    }
+}
+
+/// A rate or usage limit the assistant ran into, reported in place of a reply
+///
+/// Claude Code writes the API's error as the reply's text. A usage limit
+/// message ends in `|<unix seconds>`, the time the limit resets.
+fn usage_warning(session_id: &str, entry: &Value) -> Option<UsageWarning> {
+    if entry["type"] != "assistant" {
+        return None;
+    }
+    let text = entry["message"]["content"]
+        .as_array()?
+        .iter()
+        .filter(|block| block["type"] == "text")
+        .find_map(|block| block["text"].as_str())?;
+    if !entry["isApiErrorMessage"].as_bool().unwrap_or(false) && !text.starts_with("API Error") {
+        return None;
+    }
+    let (message, resets_at) = match text.rsplit_once('|') {
+        Some((message, secs)) => match secs.trim().parse::<i64>() {
+            Ok(secs) => (message, Some(secs * 1000)),
+            Err(_) => (text, None),
+        },
+        None => (text, None),
+    };
+    Some(UsageWarning {
+        session_id: Some(session_id.to_string()),
+        kind: limit_kind(message)?.to_string(),
+        message: message.trim().to_string(),
+        resets_at,
+        timestamp: timestamp_ms(entry).unwrap_or_else(|| adapters::now_ms() as i64),
+        source: Some(watcher::CLAUDE_CODE_SOURCE.to_string()),
+    })
+}
+
+/// Tails the transcripts in Claude's `projects` directories for context usage
+/// and usage warnings
+///
+/// Each change to a transcript produces at most one `ContextUsage`, for the
+/// latest reply in it, a `TokenUsage` for every reply while context usage is
+/// tracked, and a `UsageWarning` for each limit error.
+pub struct TranscriptSource {
+    targets: Vec<PathBuf>,
+    debounce_ms: u64,
+    /// None when context usage isn't tracked
+    context_usage: Option<ContextUsageConfig>,
+    usage_warnings: bool,
+}
+
+impl TranscriptSource {
+    pub fn new(
+        claude_dirs: &[PathBuf],
+        context_usage: &ContextUsageConfig,
+        usage_warnings: &UsageWarningsConfig,
+        debounce_ms: u64,
+    ) -> Self {
+        Self {
+            targets: claude_dirs.iter().map(|dir| dir.join(PROJECTS_DIR)).collect(),
+            debounce_ms,
+            context_usage: Some(context_usage.clone()).filter(|config| config.enabled),
+            usage_warnings: usage_warnings.enabled,
+        }
+    }
+}
+
+fn is_transcript(path: &Path) -> bool {
+    path.extension().is_some_and(|e| e == "jsonl")
+}
+
+impl Source for TranscriptSource {
+    fn name(&self) -> &str {
+        "transcripts"
+    }
+
+    fn start(self: Box<Self>, tx: EventSender) -> Result<(), WatcherError> {
+        let Self { targets, debounce_ms, context_usage, usage_warnings } = *self;
+        for target in &targets {
+            // Watching needs the directory to exist before Claude's first session
+            fs::create_dir_all(target).map_err(|e| WatcherError::IoError(e.to_string()))?;
+        }
+
+        let mut watch = TargetWatch::new("transcripts", targets.clone(), debounce_ms)?;
+        tokio::spawn(async move {
+            // Only replies written from now on count; there can be years of transcripts
+            let walk = tokio::task::spawn_blocking(move || {
+                targets.iter().flat_map(|target| adapters::existing_files(target, is_transcript)).collect::<Vec<_>>()
+            });
+            let mut states: HashMap<PathBuf, FileState> = walk
+                .await
+                .unwrap_or_default()
+                .into_iter()
+                .map(|file| {
+                    let state = FileState::at_end(&file);
+                    (file, state)
+                })
+                .collect();
+
+            while let Some(path) = watch.next_change().await {
+                if !is_transcript(&path) {
+                    continue;
+                }
+                let Some(session_id) = path.file_stem().map(|s| s.to_string_lossy().to_string()) else {
+                    continue;
+                };
+                let state = states.entry(path.clone()).or_insert_with(FileState::at_start);
+                let entries: Vec<Value> = watcher::read_new_lines(&path, state)
+                    .await
+                    .iter()
+                    .filter_map(|line| serde_json::from_str(line).ok())
+                    .collect();
+                if usage_warnings {
+                    for warning in entries.iter().filter_map(|entry| usage_warning(&session_id, entry)) {
+                        let _ = tx.send(WatcherEvent::UsageWarning(warning));
+                    }
+                }
+                if let Some(config) = &context_usage {
+                    for usage in entries.iter().filter_map(|entry| token_usage(&session_id, entry)) {
+                        let _ = tx.send(WatcherEvent::TokenUsage(usage));
+                    }
+                    let latest = entries
+                        .iter()
+                        .rev()
+                        .find_map(|entry| context_sample(&session_id, entry, config.window_tokens));
+                    if let Some(sample) = latest {
+                        let _ = tx.send(WatcherEvent::ContextUsage(ContextUsage::new(sample, config.warning_percent)));
+                    }
+                }
+            }
+        });
+        Ok(())
+    }
+}
+
+fn timestamp_ms(entry: &Value) -> Option<i64> {
+    let timestamp = entry["timestamp"].as_str()?;
+    DateTime::parse_from_rfc3339(timestamp).ok().map(|t| t.timestamp_millis())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_turns() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().join(PROJECTS_DIR).join("-home-me-app");
+        fs::create_dir_all(&project).unwrap();
+        let lines = [
+            r#"{"type":"user","isMeta":true,"message":{"role":"user","content":"<command-name>/clear</command-name>"},"timestamp":"2025-06-01T09:59:00Z"}"#,
+            r#"{"type":"user","message":{"role":"user","content":"fix pagination on the orders page"},"timestamp":"2025-06-01T10:00:00Z"}"#,
+            r#"{"type":"assistant","message":{"role":"assistant","model":"claude-opus-4-1","content":[{"type":"text","text":"Looking at the query."},{"type":"tool_use","id":"toolu_1","name":"Bash","input":{}}]},"timestamp":"2025-06-01T10:00:05Z"}"#,
+            r#"{"type":"user","message":{"role":"user","content":[{"type":"tool_result","tool_use_id":"toolu_1","content":"ok"}]},"timestamp":"2025-06-01T10:00:09Z"}"#,
+            r#"{"type":"assistant","isSidechain":true,"message":{"role":"assistant","model":"claude-haiku-4-5","content":[{"type":"text","text":"Subagent notes"},{"type":"tool_use","id":"toolu_2","name":"Read","input":{}}]},"timestamp":"2025-06-01T10:00:20Z"}"#,
+            r#"{"type":"assistant","message":{"role":"assistant","content":[{"type":"text","text":"Fixed the off-by-one in the page offset."}]},"timestamp":"2025-06-01T10:01:00Z"}"#,
+            "not json",
+            r#"{"type":"user","message":{"role":"user","content":[{"type":"text","text":"now add a test"}]},"timestamp":"2025-06-01T10:05:00Z"}"#,
+        ];
+        fs::write(project.join("s1.jsonl"), lines.join("\n")).unwrap();
+
+        let path = find_transcript(dir.path(), "s1").unwrap();
+        assert_eq!(find_transcript(dir.path(), "../s1"), None);
+
+        let turns = read_turns(&path);
+        assert_eq!(turns.len(), 2);
+        assert_eq!(turns[0].prompt, "fix pagination on the orders page");
+        assert_eq!(turns[0].summary.as_deref(), Some("Fixed the off-by-one in the page offset."));
+        assert!(turns[0].tool_use_ids.contains("toolu_2"));
+        assert_eq!(turns[0].model_for("toolu_2"), Some("claude-haiku-4-5"));
+        assert_eq!(turns[0].model_for("other"), Some("claude-opus-4-1"));
+        assert_eq!(turns[1].summary, None);
+
+        // By tool call id, then by start time
+        let started = turns[1].started_at;
+        assert_eq!(turn_for(&turns, "toolu_2", started + 1).unwrap().prompt, turns[0].prompt);
+        assert_eq!(turn_for(&turns, "other", started + 1).unwrap().prompt, "now add a test");
+        assert_eq!(turn_for(&turns, "other", 0), None);
+    }
+
+    #[test]
+    fn test_context_sample() {
+        let reply: Value = serde_json::from_str(r#"{"type":"assistant","timestamp":"2025-06-01T10:00:05Z","message":{
+            "model":"claude-sonnet-4-5","content":[],
+            "usage":{"input_tokens":12,"cache_creation_input_tokens":3000,"cache_read_input_tokens":160000,"output_tokens":988}
+        }}"#).unwrap();
+        let sample = context_sample("s1", &reply, 200_000).unwrap();
+        assert_eq!(sample.context_tokens, 164_000);
+        let tokens = token_usage("s1", &reply).unwrap();
+        assert_eq!((tokens.input_tokens, tokens.cache_read_tokens, tokens.output_tokens), (12, 160_000, 988));
+        assert_eq!(sample.model.as_deref(), Some("claude-sonnet-4-5"));
+
+        let usage = ContextUsage::new(sample, 80.0);
+        assert_eq!(usage.percent, 82.0);
+        assert!(usage.warning);
+
+        // Subagents have their own context, and prompts carry no usage
+        let mut subagent = reply.clone();
+        subagent["isSidechain"] = Value::Bool(true);
+        assert_eq!(context_sample("s1", &subagent, 200_000), None);
+        assert!(token_usage("s1", &subagent).is_some());
+        let prompt: Value = serde_json::from_str(r#"{"type":"user","message":{"content":"hi"}}"#).unwrap();
+        assert_eq!(context_sample("s1", &prompt, 200_000), None);
+    }
+
+    #[test]
+    fn test_usage_warning() {
+        let limit: Value = serde_json::from_str(r#"{"type":"assistant","isApiErrorMessage":true,"timestamp":"2025-06-01T10:00:05Z",
+            "message":{"model":"<synthetic>","content":[{"type":"text","text":"Claude AI usage limit reached|1748775600"}]}}"#).unwrap();
+        let warning = usage_warning("s1", &limit).unwrap();
+        assert_eq!(warning.kind, "usage_limit");
+        assert_eq!(warning.message, "Claude AI usage limit reached");
+        assert_eq!(warning.resets_at, Some(1_748_775_600_000));
+        assert_eq!(warning.session_id.as_deref(), Some("s1"));
+
+        let overloaded: Value = serde_json::from_str(r#"{"type":"assistant","message":{"content":[
+            {"type":"text","text":"API Error: 529 {\"type\":\"error\",\"error\":{\"type\":\"overloaded_error\"}}"}]}}"#).unwrap();
+        assert_eq!(usage_warning("s1", &overloaded).unwrap().kind, "overloaded");
+        assert_eq!(limit_kind("API Error: 429 rate_limit_error"), Some("rate_limit"));
+
+        // An ordinary reply that happens to mention limits isn't an error
+        let reply: Value = serde_json::from_str(r#"{"type":"assistant","message":{"content":[
+            {"type":"text","text":"The rate limit middleware is in place."}]}}"#).unwrap();
+        assert_eq!(usage_warning("s1", &reply), None);
+        assert_eq!(limit_kind("API Error: 500 internal"), None);
+    }
+}
@@ -0,0 +1,212 @@
+//! Updates - new releases from the configured channel
+//!
+//! The overlay checks its release channel in the background and on
+//! `check_for_updates`. A newer build is held until the user installs it from
+//! the tray or with `install_update`; the tray item and tooltip say when one
+//! is ready. Releases are signed, and the updater only installs builds that
+//! match the public key in `tauri.conf.json`.
+
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::Serialize;
+use tauri::menu::MenuItem;
+use tauri::{AppHandle, Emitter, Manager, Url, Wry};
+use tauri_plugin_updater::{Update, UpdaterExt};
+
+use crate::config::{Config, UpdatesConfig};
+use crate::ipc;
+use crate::locale::{self, Message};
+
+/// Where each channel's release manifest is published
+const STABLE_ENDPOINT: &str = "https://github.com/abrown84/agent-progress-overlay/releases/latest/download/latest.json";
+/// Pre-releases are published to a rolling `beta` release
+const BETA_ENDPOINT: &str = "https://github.com/abrown84/agent-progress-overlay/releases/download/beta/latest.json";
+
+/// Wait after launch before the first background check, so it doesn't slow startup
+const FIRST_CHECK_DELAY: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Channel {
+    Stable,
+    Beta,
+}
+
+impl Channel {
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "stable" => Ok(Channel::Stable),
+            "beta" => Ok(Channel::Beta),
+            _ => Err(format!("Unknown update channel \"{}\"", name.trim())),
+        }
+    }
+
+    /// The configured channel, falling back to stable
+    pub fn from_config(name: &str) -> Self {
+        Self::from_name(name).unwrap_or_else(|e| {
+            tracing::warn!("{}, using the stable channel", e);
+            Channel::Stable
+        })
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Channel::Stable => "stable",
+            Channel::Beta => "beta",
+        }
+    }
+
+    fn endpoint(self) -> &'static str {
+        match self {
+            Channel::Stable => STABLE_ENDPOINT,
+            Channel::Beta => BETA_ENDPOINT,
+        }
+    }
+}
+
+/// A newer release, sent as `update-available`
+#[derive(Debug, Clone, Serialize)]
+pub struct UpdateInfo {
+    pub version: String,
+    pub current_version: String,
+    pub channel: &'static str,
+    /// Release notes
+    pub notes: Option<String>,
+    pub date: Option<String>,
+}
+
+/// The update found by the last check, and the tray item that installs it
+pub struct UpdateState {
+    pending: Mutex<Option<Update>>,
+    tray_item: MenuItem<Wry>,
+}
+
+impl UpdateState {
+    pub fn new(tray_item: MenuItem<Wry>) -> Self {
+        Self { pending: Mutex::new(None), tray_item }
+    }
+
+    /// Version of the update waiting to be installed
+    pub fn ready_version(&self) -> Option<String> {
+        let pending = self.pending.lock().ok()?;
+        pending.as_ref().map(|update| update.version.clone())
+    }
+
+    fn set(&self, update: Option<Update>) {
+        let locale = locale::current();
+        let text = match &update {
+            Some(update) => locale.format(Message::TrayInstallUpdate, &[("version", &update.version)]),
+            None => locale.text(Message::TrayCheckUpdates).to_string(),
+        };
+        let _ = self.tray_item.set_text(text);
+        if let Ok(mut pending) = self.pending.lock() {
+            *pending = update;
+        }
+    }
+
+    fn take(&self) -> Option<Update> {
+        self.pending.lock().ok()?.take()
+    }
+}
+
+/// Whether this build has the public key updates are verified against
+///
+/// Builds made without one, e.g. from source, can check for updates but not install them.
+fn signing_key_configured(app: &AppHandle) -> bool {
+    app.config()
+        .plugins
+        .0
+        .get("updater")
+        .and_then(|updater| updater.get("pubkey"))
+        .and_then(|pubkey| pubkey.as_str())
+        .is_some_and(|pubkey| !pubkey.trim().is_empty())
+}
+
+/// Ask the configured channel for a newer release, holding it for install
+pub async fn check(app: &AppHandle) -> Result<Option<UpdateInfo>, String> {
+    // Read each time, so a channel switched in the settings applies to the next check
+    let channel = Channel::from_config(&Config::load().unwrap_or_default().updates.channel);
+    let endpoint = Url::parse(channel.endpoint()).map_err(|e| e.to_string())?;
+    let updater = app
+        .updater_builder()
+        .endpoints(vec![endpoint])
+        .and_then(|builder| builder.build())
+        .map_err(|e| e.to_string())?;
+    let update = updater.check().await.map_err(|e| e.to_string())?;
+
+    let info = update.as_ref().map(|update| UpdateInfo {
+        version: update.version.clone(),
+        current_version: update.current_version.clone(),
+        channel: channel.name(),
+        notes: update.body.clone(),
+        date: update.date.map(|date| date.to_string()),
+    });
+    app.state::<UpdateState>().set(update);
+    crate::refresh_tray_tooltip(app);
+    if let Some(info) = &info {
+        tracing::info!("Update {} is available on the {} channel", info.version, info.channel);
+        if let Err(e) = app.emit("update-available", info) {
+            tracing::error!("Failed to emit update: {}", e);
+        }
+    }
+    Ok(info)
+}
+
+/// Download and install the update the last check found, then restart
+///
+/// The core is stopped first: it runs from the same executable, which some
+/// platforms can't replace while it runs. The new overlay starts a new core.
+pub async fn install(app: &AppHandle, port: u16) -> Result<(), String> {
+    if !signing_key_configured(app) {
+        return Err("This build has no update signing key, so updates can't be verified".to_string());
+    }
+    let Some(update) = app.state::<UpdateState>().take() else {
+        return Err("No update is ready; check for updates first".to_string());
+    };
+    tracing::info!("Installing update {}", update.version);
+    let stopped = match ipc::core_token() {
+        Ok(token) => ipc::request_stop(port, &token).await,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = stopped {
+        tracing::warn!("Failed to stop the core before updating: {}", e);
+    }
+    update
+        .download_and_install(|_, _| {}, || tracing::info!("Update downloaded"))
+        .await
+        .map_err(|e| e.to_string())?;
+    app.restart();
+}
+
+/// Check in the background every `check_interval_hours`, if enabled
+pub fn start(app: AppHandle, config: &UpdatesConfig) {
+    if !config.enabled || config.check_interval_hours == 0 || !signing_key_configured(&app) {
+        return;
+    }
+    let interval = Duration::from_secs(config.check_interval_hours * 60 * 60);
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(FIRST_CHECK_DELAY).await;
+        loop {
+            if app.state::<UpdateState>().ready_version().is_none() {
+                if let Err(e) = check(&app).await {
+                    tracing::warn!("Update check failed: {}", e);
+                }
+            }
+            tokio::time::sleep(interval).await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_channels() {
+        assert_eq!(Channel::from_name(" Beta ").unwrap(), Channel::Beta);
+        assert!(Channel::from_name("nightly").is_err());
+        assert_eq!(Channel::from_config("nightly"), Channel::Stable);
+        assert_ne!(Channel::Stable.endpoint(), Channel::Beta.endpoint());
+        assert!(Url::parse(Channel::Beta.endpoint()).is_ok());
+    }
+}
@@ -1,17 +1,34 @@
 //! File watching system using the notify crate
 //!
 //! Replaces polling with event-driven file watching for reduced CPU usage.
+//! Sources run as tasks on the app's shared tokio runtime and send their
+//! events over an unbounded channel, so none of them block an OS thread.
 
 use notify::RecursiveMode;
-use notify_debouncer_mini::{new_debouncer, DebouncedEvent};
+use notify_debouncer_mini::{new_debouncer, DebounceEventResult, Debouncer};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs::{self, File};
-use std::io::{BufRead, BufReader, Seek, SeekFrom};
+use std::hash::{Hash, Hasher};
+use std::io::SeekFrom;
 use std::path::{Path, PathBuf};
-use std::sync::mpsc::{self, Receiver};
-use std::sync::{Arc, Mutex};
-use std::time::Duration;
-
-use crate::config::Config;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+use tokio::io::{AsyncBufReadExt, AsyncSeekExt, BufReader};
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+
+use crate::adapters;
+use crate::config::{Config, DOWNLOADS_DIR_NAME, EVENTS_FILE_NAME, PROGRESS_DIR_NAME, TODOS_DIR_NAME};
+use crate::health;
+use crate::relay::RelaySource;
+use crate::stdin::{self, StdinSource};
+use crate::store::{StoredSession, TokenUsage, UsageWarning};
+use crate::supervisor::{supervise, RestartPolicy};
+use crate::text;
+use crate::transcript::{ContextUsage, TranscriptSource};
+
+/// Source name for events from Claude Code hooks
+pub const CLAUDE_CODE_SOURCE: &str = "claude-code";
 
 /// Event types that can be parsed from the JSONL file
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -26,6 +43,63 @@ pub struct TaskEvent {
     pub background: Option<bool>,
     pub subagent_type: Option<String>,
     pub duration_ms: Option<u64>,
+    #[serde(default, alias = "cwd")]
+    pub project_path: Option<String>,
+    /// Agent tool that produced the event (e.g. "aider"); filled in by the source that read it
+    #[serde(default)]
+    pub source: Option<String>,
+    /// Claude profile whose directory the event was read from; none for the main directory
+    #[serde(default)]
+    pub profile: Option<String>,
+    /// Model running the task, for tools that report it; Claude's is read from its transcripts
+    #[serde(default)]
+    pub model: Option<String>,
+    /// How long the task usually takes, from similar tasks in history; set on `task_started`
+    #[serde(default)]
+    pub eta_ms: Option<u64>,
+    /// Process a background shell runs in, for local hooks that report it; lets a cancel request signal it.
+    /// Dropped from relayed events, whose pid is on another machine
+    #[serde(default)]
+    pub pid: Option<u32>,
+    /// Exit code of the task's command, for hooks that report it; set on `task_complete` and `task_error`
+    #[serde(default)]
+    pub exit_code: Option<i32>,
+    /// "low", "normal", "high" or "urgent"; the router fills it in for events sent without one
+    #[serde(default)]
+    pub priority: Option<String>,
+    /// What a `custom` event is about, named by the hook that sent it, e.g. "deploy_finished"
+    #[serde(default)]
+    pub custom_type: Option<String>,
+    /// Free-form data a `custom` event carries
+    #[serde(default)]
+    pub payload: Option<serde_json::Value>,
+}
+
+impl TaskEvent {
+    /// Create an event with only the required fields set
+    pub fn new(event_type: &str, task_id: &str, timestamp: u64) -> Self {
+        Self {
+            event_type: event_type.to_string(),
+            task_id: task_id.to_string(),
+            tool: None,
+            description: None,
+            session_id: None,
+            timestamp,
+            background: None,
+            subagent_type: None,
+            duration_ms: None,
+            project_path: None,
+            source: None,
+            profile: None,
+            model: None,
+            eta_ms: None,
+            pid: None,
+            exit_code: None,
+            priority: None,
+            custom_type: None,
+            payload: None,
+        }
+    }
 }
 
 /// Todo item from JSON files
@@ -45,11 +119,124 @@ pub struct GlobalTodoItem {
     #[serde(rename = "activeForm")]
     pub active_form: String,
     pub session_id: String,
+    /// Claude profile the todo file belongs to; none for the main directory
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+    /// Other sessions with the same todo, when duplicates are collapsed into this one
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub also_in: Vec<String>,
+}
+
+/// One session's active todos, labeled for display
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct TodoGroup {
+    pub session_id: String,
+    /// The project folder's name and branch, or a short session id when unknown
+    pub name: String,
+    pub project_path: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+    pub todos: Vec<GlobalTodoItem>,
+}
+
+/// Group todos by session, in the order sessions first appear in the list
+///
+/// Sessions are named after their project folder and branch. Sessions that
+/// would share a name get their short id added, so every name is distinct.
+pub fn group_todos(todos: &[GlobalTodoItem], sessions: &HashMap<String, StoredSession>) -> Vec<TodoGroup> {
+    let short_id = |id: &str| id.chars().take(8).collect::<String>();
+    let mut groups: Vec<TodoGroup> = Vec::new();
+    for todo in todos {
+        if let Some(group) = groups.iter_mut().find(|g| g.session_id == todo.session_id) {
+            group.todos.push(todo.clone());
+            continue;
+        }
+        let session = sessions.get(&todo.session_id);
+        let project_path = session.and_then(|s| s.project_path.clone());
+        let folder = project_path.as_deref()
+            .and_then(|path| Path::new(path).file_name())
+            .map(|name| name.to_string_lossy().to_string());
+        let name = match (folder, session.and_then(|s| s.branch.as_deref())) {
+            (Some(folder), Some(branch)) => format!("{} ({})", folder, branch),
+            (Some(folder), None) => folder,
+            (None, _) => format!("Session {}", short_id(&todo.session_id)),
+        };
+        groups.push(TodoGroup {
+            session_id: todo.session_id.clone(),
+            name,
+            project_path,
+            profile: todo.profile.clone(),
+            todos: vec![todo.clone()],
+        });
+    }
+
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for group in &groups {
+        *counts.entry(group.name.clone()).or_default() += 1;
+    }
+    for group in &mut groups {
+        if counts[&group.name] > 1 && group.project_path.is_some() {
+            group.name = format!("{} · {}", group.name, short_id(&group.session_id));
+        }
+    }
+    groups
+}
+
+/// Show each todo once, however many sessions have it
+///
+/// Todos match when their contents are the same ignoring case and spacing.
+/// The first copy is kept, marked in progress if any copy is, and lists the
+/// other sessions in `also_in`.
+pub fn collapse_duplicate_todos(todos: Vec<GlobalTodoItem>) -> Vec<GlobalTodoItem> {
+    let mut collapsed: Vec<GlobalTodoItem> = Vec::with_capacity(todos.len());
+    let mut index: HashMap<String, usize> = HashMap::new();
+    for todo in todos {
+        let key = text::normalize(&todo.content).to_lowercase();
+        let Some(&i) = index.get(&key) else {
+            index.insert(key, collapsed.len());
+            collapsed.push(todo);
+            continue;
+        };
+        let first = &mut collapsed[i];
+        if todo.status == "in_progress" && first.status != "in_progress" {
+            first.status = todo.status;
+            first.active_form = todo.active_form;
+        }
+        if todo.session_id != first.session_id && !first.also_in.contains(&todo.session_id) {
+            first.also_in.push(todo.session_id);
+        }
+    }
+    collapsed
+}
+
+/// Changes to the active todos since the last scan
+///
+/// Todos are matched by session and content. One that is completed, or whose
+/// file is deleted, is reported as removed.
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct TodosDiff {
+    pub added: Vec<GlobalTodoItem>,
+    pub updated: Vec<GlobalTodoItem>,
+    pub removed: Vec<GlobalTodoItem>,
+    /// The removed todos that were completed rather than dropped, with their new status
+    #[serde(default)]
+    pub completed: Vec<GlobalTodoItem>,
+    /// Todos of files that went stale; they leave the list but aren't finished or dropped
+    #[serde(default)]
+    pub hidden: Vec<GlobalTodoItem>,
+}
+
+impl TodosDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.updated.is_empty() && self.removed.is_empty() && self.hidden.is_empty()
+    }
 }
 
 /// Download progress event
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct DownloadProgress {
+    /// Defaults to the file name in the downloads directory
+    #[serde(default)]
     pub task_id: String,
     pub percent: f64,
     pub speed: Option<String>,
@@ -57,302 +244,1212 @@ pub struct DownloadProgress {
     pub timestamp: u64,
 }
 
+/// One line of a `.jsonl` file in the progress directory
+///
+/// Any script can append these to report progress; each `id` gets its own bar,
+/// and later lines with the same id update it.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct GenericProgress {
+    pub id: String,
+    pub label: Option<String>,
+    /// 0 to 100; none for a bar that only shows activity
+    pub percent: Option<f64>,
+    pub message: Option<String>,
+}
+
 /// State for tracking file positions
-struct FileState {
+pub(crate) struct FileState {
     last_position: u64,
     last_size: u64,
 }
 
+impl FileState {
+    /// Start tracking from the current end of the file (only new content is read)
+    pub(crate) fn at_end(path: &Path) -> Self {
+        let size = fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+        Self {
+            last_position: size,
+            last_size: size,
+        }
+    }
+
+    /// Start tracking from the beginning of the file
+    pub(crate) fn at_start() -> Self {
+        Self {
+            last_position: 0,
+            last_size: 0,
+        }
+    }
+
+    /// Resume from a position saved by an earlier run, or the end if the file has since shrunk
+    pub(crate) fn resume(path: &Path, position: u64) -> Self {
+        let size = fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+        if position > size {
+            return Self::at_end(path);
+        }
+        Self {
+            last_position: position,
+            last_size: size,
+        }
+    }
+}
+
 /// Events emitted by the file watcher
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type", content = "data", rename_all = "snake_case")]
 pub enum WatcherEvent {
     TaskEvent(TaskEvent),
+    /// Every active todo, sent whenever any of them changes
     TodosUpdated(Vec<GlobalTodoItem>),
+    /// What changed, sent right before the matching `TodosUpdated` so the
+    /// changes are recorded before plugins see the new list
+    TodosChanged(TodosDiff),
     DownloadProgress(DownloadProgress),
+    /// Progress a script reported in the progress directory
+    GenericProgress(GenericProgress),
+    /// How full a Claude session's context window is
+    ContextUsage(ContextUsage),
+    /// Tokens one of the assistant's replies used
+    TokenUsage(TokenUsage),
+    /// The agent hit a rate or usage limit and will stall until it lifts
+    UsageWarning(UsageWarning),
     Error(String),
 }
 
-/// File watcher that uses notify for event-driven watching
+/// Sending half of the channel the sources share
+pub type EventSender = UnboundedSender<WatcherEvent>;
+
+/// An agent tool whose activity is watched for events
+pub trait Source: Send {
+    /// Name stamped onto events that don't name their own source
+    fn name(&self) -> &str;
+
+    /// Spawn the source's tasks onto the current runtime, sending events to `tx`
+    fn start(self: Box<Self>, tx: EventSender) -> Result<(), WatcherError>;
+}
+
+/// Runs every configured source and merges their events into one stream
 pub struct FileWatcher {
-    events_path: PathBuf,
-    todos_path: PathBuf,
-    download_progress_path: PathBuf,
-    debounce_ms: u64,
+    sources: Vec<Box<dyn Source>>,
+    events_offsets: Vec<(Option<String>, Arc<AtomicU64>)>,
 }
 
 impl FileWatcher {
-    /// Create a new file watcher from config
-    pub fn new(config: &Config) -> Self {
-        Self {
-            events_path: config.events_path(),
-            todos_path: config.todos_path(),
-            download_progress_path: config.events_path().parent()
-                .unwrap_or(Path::new("."))
-                .join("download-progress.json"),
-            debounce_ms: config.behavior.file_watch_debounce_ms,
+    /// Create a watcher for Claude Code, each profile in `[[paths.profiles]]`,
+    /// the sources enabled in `[sources]`, and stdin when started with `--stdin`
+    ///
+    /// Each events file is read from its position in `resume_at` if there is
+    /// one (saved at the last clean exit, keyed by profile), and otherwise from
+    /// its current end.
+    pub fn new(config: &Config, resume_at: &HashMap<Option<String>, u64>) -> Self {
+        let debounce_ms = config.behavior.file_watch_debounce_ms;
+        let todos = TodoBoard::default();
+        let mut claude_sources = vec![ClaudeCodeSource::new(config, resume_at.get(&None).copied())];
+        for (name, dir) in config.profile_dirs() {
+            let resume = resume_at.get(&Some(name.clone())).copied();
+            claude_sources.push(ClaudeCodeSource::for_profile(config, name, &dir, resume));
         }
-    }
 
-    /// Start watching files and return a receiver for events
-    pub fn start(&self) -> Result<Receiver<WatcherEvent>, WatcherError> {
-        let (tx, rx) = mpsc::channel::<WatcherEvent>();
+        let mut sources: Vec<Box<dyn Source>> = Vec::new();
+        let mut events_offsets = Vec::new();
+        for mut source in claude_sources {
+            source.todo_board = todos.clone();
+            events_offsets.push((source.profile.clone(), source.events_offset.clone()));
+            sources.push(Box::new(source));
+        }
+        sources.extend(adapters::enabled_sources(&config.sources, debounce_ms));
+        if config.relay.enabled {
+            sources.push(Box::new(RelaySource::new(&config.relay)));
+        }
+        if stdin::requested(std::env::args().skip(1)) {
+            sources.push(Box::new(StdinSource));
+        }
+        if config.context_usage.enabled || config.usage_warnings.enabled {
+            sources.push(Box::new(TranscriptSource::new(
+                &config.claude_dirs(),
+                &config.context_usage,
+                &config.usage_warnings,
+                debounce_ms,
+            )));
+        }
+        Self { sources, events_offsets }
+    }
 
-        // Ensure files exist
-        self.ensure_files_exist()?;
+    /// How far into each profile's events file the watcher has sent events, kept current while it runs
+    pub fn events_offsets(&self) -> Vec<(Option<String>, Arc<AtomicU64>)> {
+        self.events_offsets.clone()
+    }
 
-        // Initialize file state (start from end of events file)
-        let initial_size = fs::metadata(&self.events_path)
-            .map(|m| m.len())
-            .unwrap_or(0);
-
-        let file_state = Arc::new(Mutex::new(FileState {
-            last_position: initial_size,
-            last_size: initial_size,
-        }));
-
-        // Clone paths for the watcher thread
-        let events_path = self.events_path.clone();
-        let todos_path = self.todos_path.clone();
-        let download_path = self.download_progress_path.clone();
-        let debounce_ms = self.debounce_ms;
-        let tx_clone = tx.clone();
-
-        // Spawn watcher thread
-        std::thread::spawn(move || {
-            if let Err(e) = run_watcher(
-                events_path,
-                todos_path,
-                download_path,
-                file_state,
-                tx_clone,
-                debounce_ms,
-            ) {
-                tracing::error!("File watcher error: {}", e);
+    /// Start all sources and return a receiver for their events
+    ///
+    /// Must be called from within a tokio runtime. A source that fails to
+    /// start is logged and skipped; this only fails if none of them could start.
+    pub fn start(self) -> Result<UnboundedReceiver<WatcherEvent>, WatcherError> {
+        let (tx, rx) = mpsc::unbounded_channel::<WatcherEvent>();
+        let mut last_error = None;
+        let mut started = 0;
+
+        for source in self.sources {
+            let name = source.name().to_string();
+            match source.start(tx.clone()) {
+                Ok(()) => {
+                    tracing::info!("Started source: {}", name);
+                    started += 1;
+                }
+                Err(e) => {
+                    tracing::error!("Failed to start source {}: {}", name, e);
+                    last_error = Some(e);
+                }
             }
-        });
+        }
 
-        // Emit initial todos
-        let initial_todos = read_all_todos(&self.todos_path);
-        if !initial_todos.is_empty() {
-            let _ = tx.send(WatcherEvent::TodosUpdated(initial_todos));
+        match last_error {
+            Some(e) if started == 0 => Err(e),
+            _ => Ok(rx),
         }
+    }
+}
+
+/// Claude Code hook events, todos, and download progress from one Claude directory
+pub struct ClaudeCodeSource {
+    /// Profile the directory belongs to; none for the main directory
+    profile: Option<String>,
+    paths: WatchedPaths,
+    resume_at: Option<u64>,
+    events_offset: Arc<AtomicU64>,
+    debounce_ms: u64,
+    watch_mode: WatchMode,
+    todo_max_age: Option<Duration>,
+    archive_completed_todos: bool,
+    todo_board: TodoBoard,
+}
+
+/// The files the Claude Code source reads
+#[derive(Debug, Clone)]
+struct WatchedPaths {
+    events: PathBuf,
+    todos: PathBuf,
+    download: PathBuf,
+    /// One progress file per task, `<task id>.json`
+    downloads: PathBuf,
+    /// Generic progress lines from any script, in `.jsonl` files
+    progress: PathBuf,
+}
+
+impl ClaudeCodeSource {
+    /// Watch the main Claude directory, at the paths set in `[paths]`
+    pub fn new(config: &Config, resume_at: Option<u64>) -> Self {
+        Self::with_paths(config, None, config.events_path(), config.todos_path(), resume_at)
+    }
 
-        Ok(rx)
+    /// Watch a profile's Claude directory, at the default paths inside it
+    pub fn for_profile(config: &Config, name: String, dir: &Path, resume_at: Option<u64>) -> Self {
+        let (events, todos) = (dir.join(EVENTS_FILE_NAME), dir.join(TODOS_DIR_NAME));
+        Self::with_paths(config, Some(name), events, todos, resume_at)
+    }
+
+    fn with_paths(
+        config: &Config,
+        profile: Option<String>,
+        events: PathBuf,
+        todos: PathBuf,
+        resume_at: Option<u64>,
+    ) -> Self {
+        let dir = events.parent().unwrap_or(Path::new("."));
+        let (download, downloads) = (dir.join("download-progress.json"), dir.join(DOWNLOADS_DIR_NAME));
+        let progress = dir.join(PROGRESS_DIR_NAME);
+        Self {
+            profile,
+            paths: WatchedPaths { events, todos, download, downloads, progress },
+            resume_at,
+            events_offset: Arc::new(AtomicU64::new(0)),
+            debounce_ms: config.behavior.file_watch_debounce_ms,
+            watch_mode: WatchMode::from_config(&config.behavior.watch_mode),
+            todo_max_age: Some(config.behavior.todo_max_age_days)
+                .filter(|days| *days > 0)
+                .map(|days| Duration::from_secs(days * 24 * 60 * 60)),
+            archive_completed_todos: config.behavior.archive_completed_todos,
+            todo_board: TodoBoard::default(),
+        }
     }
 
     fn ensure_files_exist(&self) -> Result<(), WatcherError> {
         // Ensure parent directory exists
-        if let Some(parent) = self.events_path.parent() {
+        if let Some(parent) = self.paths.events.parent() {
             fs::create_dir_all(parent)
                 .map_err(|e| WatcherError::IoError(e.to_string()))?;
         }
 
         // Create events file if it doesn't exist
-        if !self.events_path.exists() {
-            File::create(&self.events_path)
+        if !self.paths.events.exists() {
+            File::create(&self.paths.events)
                 .map_err(|e| WatcherError::IoError(e.to_string()))?;
         }
 
         // Create todos directory if it doesn't exist
-        fs::create_dir_all(&self.todos_path)
+        fs::create_dir_all(&self.paths.todos)
+            .map_err(|e| WatcherError::IoError(e.to_string()))?;
+
+        // And the per-task downloads and generic progress directories, so they can be watched
+        fs::create_dir_all(&self.paths.downloads)
+            .map_err(|e| WatcherError::IoError(e.to_string()))?;
+        fs::create_dir_all(&self.paths.progress)
             .map_err(|e| WatcherError::IoError(e.to_string()))?;
 
         Ok(())
     }
 }
 
-/// Run the file watcher (called in a separate thread)
-fn run_watcher(
-    events_path: PathBuf,
-    todos_path: PathBuf,
-    download_path: PathBuf,
-    file_state: Arc<Mutex<FileState>>,
-    tx: mpsc::Sender<WatcherEvent>,
-    debounce_ms: u64,
-) -> Result<(), WatcherError> {
-    // Create debounced watcher
-    let (notify_tx, notify_rx) = mpsc::channel();
+impl Source for ClaudeCodeSource {
+    fn name(&self) -> &str {
+        CLAUDE_CODE_SOURCE
+    }
 
+    fn start(self: Box<Self>, tx: EventSender) -> Result<(), WatcherError> {
+        // Ensure files exist
+        self.ensure_files_exist()?;
+
+        // Pick up where the last run stopped, or start from the end of the events file
+        let events = match self.resume_at {
+            Some(position) => FileState::resume(&self.paths.events, position),
+            None => FileState::at_end(&self.paths.events),
+        };
+        self.events_offset.store(events.last_position, Ordering::SeqCst);
+
+        // A restart after a failure carries on from the last event sent
+        let source = *self;
+        let task_name = match &source.profile {
+            Some(profile) => format!("watcher:{}", profile),
+            None => "watcher".to_string(),
+        };
+        tokio::spawn(async move {
+            supervise(&task_name, RestartPolicy::default(), move || {
+                let paths = source.paths.clone();
+                let mut state = ReadState {
+                    profile: source.profile.clone(),
+                    events: FileState::resume(&paths.events, source.events_offset.load(Ordering::SeqCst)),
+                    events_offset: source.events_offset.clone(),
+                    todos: TodoTracker::new(&paths.todos, source.todo_max_age, source.archive_completed_todos)
+                        .with_profile(source.profile.clone()),
+                    todo_board: source.todo_board.clone(),
+                    downloads: DownloadTracker::new(&paths.downloads),
+                    progress: ProgressTracker::new(&paths.progress),
+                };
+                let tx = tx.clone();
+                let (debounce_ms, watch_mode) = (source.debounce_ms, source.watch_mode);
+
+                async move {
+                    // The first scan reports every existing todo as added
+                    on_todos_changed(&mut state, &tx).await;
+
+                    run_watcher(paths, state, tx, debounce_ms, watch_mode)
+                        .await
+                        .map_err(|e| format!("file watcher error: {}", e))
+                }
+            })
+            .await;
+        });
+
+        Ok(())
+    }
+}
+
+/// What the Claude Code source has read so far
+struct ReadState {
+    profile: Option<String>,
+    events: FileState,
+    /// Position in the events file up to which events have been sent
+    events_offset: Arc<AtomicU64>,
+    todos: TodoTracker,
+    todo_board: TodoBoard,
+    downloads: DownloadTracker,
+    progress: ProgressTracker,
+}
+
+/// Per-task download progress files, and when each was last read
+///
+/// Scripts downloading at the same time each write their own file, so a
+/// change is only sent for the files that were modified since the last scan.
+struct DownloadTracker {
+    dir: PathBuf,
+    seen: HashMap<PathBuf, SystemTime>,
+}
+
+impl DownloadTracker {
+    fn new(dir: &Path) -> Self {
+        Self { dir: dir.to_path_buf(), seen: HashMap::new() }
+    }
+
+    /// Progress from every file modified since the last refresh
+    async fn refresh(&mut self) -> Vec<DownloadProgress> {
+        let mut current = HashMap::new();
+        let mut changed = Vec::new();
+        if let Ok(mut entries) = tokio::fs::read_dir(&self.dir).await {
+            while let Ok(Some(entry)) = entries.next_entry().await {
+                let path = entry.path();
+                if path.extension().is_none_or(|e| e != "json") {
+                    continue;
+                }
+                let Some(modified) = entry.metadata().await.and_then(|m| m.modified()).ok() else {
+                    continue;
+                };
+                if self.seen.get(&path) != Some(&modified) {
+                    changed.push(path.clone());
+                }
+                current.insert(path, modified);
+            }
+        }
+        self.seen = current;
+
+        let mut progress = Vec::new();
+        for path in changed {
+            if let Some(mut update) = read_download_progress(&path).await {
+                if update.task_id.is_empty() {
+                    update.task_id = path.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+                }
+                progress.push(update);
+            }
+        }
+        progress
+    }
+}
+
+/// Generic progress files, and how far each has been read
+struct ProgressTracker {
+    dir: PathBuf,
+    files: HashMap<PathBuf, FileState>,
+}
+
+impl ProgressTracker {
+    /// Files already there are read from their end, so old progress isn't shown again
+    fn new(dir: &Path) -> Self {
+        let files = fs::read_dir(dir)
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.extension().is_some_and(|e| e == "jsonl"))
+            .map(|path| {
+                let state = FileState::at_end(&path);
+                (path, state)
+            })
+            .collect();
+        Self { dir: dir.to_path_buf(), files }
+    }
+
+    /// Lines appended to any progress file since the last refresh
+    async fn refresh(&mut self) -> Vec<GenericProgress> {
+        let mut present = HashSet::new();
+        let mut progress = Vec::new();
+        if let Ok(mut entries) = tokio::fs::read_dir(&self.dir).await {
+            while let Ok(Some(entry)) = entries.next_entry().await {
+                let path = entry.path();
+                if path.extension().is_none_or(|e| e != "jsonl") {
+                    continue;
+                }
+                let state = self.files.entry(path.clone()).or_insert_with(FileState::at_start);
+                for line in read_new_lines(&path, state).await {
+                    match serde_json::from_str::<GenericProgress>(&line) {
+                        Ok(update) => progress.push(update),
+                        Err(e) => {
+                            tracing::warn!("Failed to parse progress in {:?}: {} - line: {}", path, e, line);
+                            health::metrics().record_parse_error();
+                        }
+                    }
+                }
+                present.insert(path);
+            }
+        }
+        self.files.retain(|path, _| present.contains(path));
+        progress
+    }
+}
+
+/// The latest active todos from every Claude directory
+///
+/// Each `TodosUpdated` carries the whole list, so a directory's update has to
+/// include the others' todos or it would replace them.
+#[derive(Clone, Default)]
+struct TodoBoard(Arc<std::sync::Mutex<BTreeMap<Option<String>, Vec<GlobalTodoItem>>>>);
+
+impl TodoBoard {
+    /// Replace one profile's todos and return everyone's, the main directory's first
+    fn update(&self, profile: Option<String>, todos: Vec<GlobalTodoItem>) -> Vec<GlobalTodoItem> {
+        let Ok(mut board) = self.0.lock() else {
+            return todos;
+        };
+        board.insert(profile, todos);
+        board.values().flatten().cloned().collect()
+    }
+}
+
+/// How often notify mode checks the watched paths for updates it wasn't told about
+const VERIFY_INTERVAL: Duration = Duration::from_secs(2);
+/// Polling interval right after a change
+const POLL_FAST: Duration = Duration::from_millis(250);
+/// Polling interval once things have been quiet for a while
+const POLL_SLOW: Duration = Duration::from_secs(5);
+/// Polling stays fast for this long after the last change
+const POLL_ACTIVE_WINDOW: Duration = Duration::from_secs(30);
+
+/// How the Claude Code source notices file changes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchMode {
+    /// Native notifications, falling back to polling if they fail or miss updates
+    Auto,
+    /// Native notifications only
+    Notify,
+    /// Always poll
+    Poll,
+}
+
+impl WatchMode {
+    pub fn from_config(value: &str) -> Self {
+        match value.to_ascii_lowercase().as_str() {
+            "notify" => WatchMode::Notify,
+            "poll" => WatchMode::Poll,
+            _ => WatchMode::Auto,
+        }
+    }
+}
+
+/// Size and modification times of the watched paths, compared to detect changes
+#[derive(Debug, Clone, Default, PartialEq)]
+struct PathSnapshot {
+    events: Option<(u64, SystemTime)>,
+    /// Number of todo files, their total size, and the newest modification time
+    todos: (usize, u64, Option<SystemTime>),
+    download: Option<SystemTime>,
+    /// The same for the per-task download files
+    downloads: (usize, u64, Option<SystemTime>),
+    /// And for the generic progress files
+    progress: (usize, u64, Option<SystemTime>),
+}
+
+impl PathSnapshot {
+    async fn take(paths: &WatchedPaths) -> Self {
+        let events = tokio::fs::metadata(&paths.events)
+            .await
+            .ok()
+            .map(|m| (m.len(), m.modified().unwrap_or(SystemTime::UNIX_EPOCH)));
+
+        let todos = Self::files(&paths.todos, "json").await;
+        let download = tokio::fs::metadata(&paths.download).await.and_then(|m| m.modified()).ok();
+        let downloads = Self::files(&paths.downloads, "json").await;
+        let progress = Self::files(&paths.progress, "jsonl").await;
+
+        Self { events, todos, download, downloads, progress }
+    }
+
+    /// Number of files in `dir` with `extension`, their total size, and the newest modification time
+    async fn files(dir: &Path, extension: &str) -> (usize, u64, Option<SystemTime>) {
+        let mut files = (0, 0, None);
+        if let Ok(mut entries) = tokio::fs::read_dir(dir).await {
+            while let Ok(Some(entry)) = entries.next_entry().await {
+                if entry.path().extension().is_some_and(|e| e == extension) {
+                    if let Ok(meta) = entry.metadata().await {
+                        files.0 += 1;
+                        files.1 += meta.len();
+                        files.2 = files.2.max(meta.modified().ok());
+                    }
+                }
+            }
+        }
+        files
+    }
+}
+
+/// Start native notifications for the watched paths, forwarding batches to `notify_tx`
+fn start_debouncer(
+    paths: &WatchedPaths,
+    debounce_ms: u64,
+    notify_tx: UnboundedSender<DebounceEventResult>,
+) -> Result<Debouncer<notify::RecommendedWatcher>, WatcherError> {
     let mut debouncer = new_debouncer(
         Duration::from_millis(debounce_ms),
-        notify_tx,
+        move |result| {
+            let _ = notify_tx.send(result);
+        },
     ).map_err(|e| WatcherError::WatchError(e.to_string()))?;
 
     // Watch events file
     debouncer.watcher()
-        .watch(&events_path, RecursiveMode::NonRecursive)
+        .watch(&paths.events, RecursiveMode::NonRecursive)
         .map_err(|e| WatcherError::WatchError(e.to_string()))?;
 
     // Watch todos directory
     debouncer.watcher()
-        .watch(&todos_path, RecursiveMode::Recursive)
+        .watch(&paths.todos, RecursiveMode::Recursive)
         .map_err(|e| WatcherError::WatchError(e.to_string()))?;
 
     // Watch download progress file if it exists
-    if download_path.exists() {
+    if paths.download.exists() {
+        let _ = debouncer.watcher()
+            .watch(&paths.download, RecursiveMode::NonRecursive);
+    }
+
+    // Watch per-task download progress files if the directory exists
+    if paths.downloads.is_dir() {
+        let _ = debouncer.watcher()
+            .watch(&paths.downloads, RecursiveMode::NonRecursive);
+    }
+
+    // Watch generic progress files if the directory exists
+    if paths.progress.is_dir() {
         let _ = debouncer.watcher()
-            .watch(&download_path, RecursiveMode::NonRecursive);
+            .watch(&paths.progress, RecursiveMode::NonRecursive);
+    }
+
+    Ok(debouncer)
+}
+
+/// Run the file watcher until the event channel closes
+async fn run_watcher(
+    paths: WatchedPaths,
+    mut state: ReadState,
+    tx: EventSender,
+    debounce_ms: u64,
+    mode: WatchMode,
+) -> Result<(), WatcherError> {
+    tracing::debug!("Watching: {:?}", paths.events);
+    tracing::debug!("Watching: {:?}", paths.todos);
+
+    if mode == WatchMode::Poll {
+        tracing::info!("File watcher started in polling mode");
+        let seen = PathSnapshot::take(&paths).await;
+        run_polling(&paths, &mut state, &tx, seen).await;
+        return Ok(());
     }
 
-    tracing::info!("File watcher started");
-    tracing::debug!("Watching: {:?}", events_path);
-    tracing::debug!("Watching: {:?}", todos_path);
+    let (notify_tx, mut notify_rx) = mpsc::unbounded_channel();
+    let debouncer = match start_debouncer(&paths, debounce_ms, notify_tx) {
+        Ok(debouncer) => debouncer,
+        Err(e) if mode == WatchMode::Auto => {
+            tracing::warn!("Native file notifications unavailable ({}); falling back to polling", e);
+            let seen = PathSnapshot::take(&paths).await;
+            run_polling(&paths, &mut state, &tx, seen).await;
+            return Ok(());
+        }
+        Err(e) => return Err(e),
+    };
 
-    // Process events
-    for result in notify_rx {
-        match result {
-            Ok(events) => {
+    tracing::info!("File watcher started using native notifications");
+    health::metrics().set_watch_mode("notify");
+
+    // What the paths looked like after the last notification, and whether they
+    // changed since without one
+    let mut seen = PathSnapshot::take(&paths).await;
+    let mut suspect = false;
+
+    loop {
+        match tokio::time::timeout(VERIFY_INTERVAL, notify_rx.recv()).await {
+            Ok(Some(Ok(events))) => {
                 for event in events {
-                    handle_file_event(
-                        &event,
-                        &events_path,
-                        &todos_path,
-                        &download_path,
-                        &file_state,
-                        &tx,
-                    );
+                    handle_file_event(&event.path, &paths, &mut state, &tx).await;
                 }
+                seen = PathSnapshot::take(&paths).await;
+                suspect = false;
             }
-            Err(e) => {
+            Ok(Some(Err(e))) => {
                 tracing::error!("Watch error: {:?}", e);
                 let _ = tx.send(WatcherEvent::Error(format!("{:?}", e)));
             }
+            Ok(None) => return Ok(()),
+            Err(_) if mode == WatchMode::Auto => {
+                if PathSnapshot::take(&paths).await == seen {
+                    suspect = false;
+                } else if !suspect {
+                    // Give a pending notification one more interval to arrive
+                    suspect = true;
+                } else {
+                    tracing::warn!("Native file notifications missed updates; falling back to polling");
+                    drop(debouncer);
+                    run_polling(&paths, &mut state, &tx, seen).await;
+                    return Ok(());
+                }
+            }
+            Err(_) => {}
         }
-    }
 
-    Ok(())
+        if tx.is_closed() {
+            return Ok(());
+        }
+    }
 }
 
-/// Handle a single file event
-fn handle_file_event(
-    event: &DebouncedEvent,
-    events_path: &Path,
-    todos_path: &Path,
-    download_path: &Path,
-    file_state: &Arc<Mutex<FileState>>,
-    tx: &mpsc::Sender<WatcherEvent>,
+/// Poll the watched paths, quickly while they are changing and slowly when idle
+async fn run_polling(
+    paths: &WatchedPaths,
+    state: &mut ReadState,
+    tx: &EventSender,
+    mut previous: PathSnapshot,
 ) {
-    let path = &event.path;
-
-    if path == events_path {
-        // Events file changed - read new events
-        if let Ok(mut state) = file_state.lock() {
-            let events = read_new_events(events_path, &mut state);
-            for event in events {
-                tracing::debug!("Task event: {} - {}", event.event_type, event.task_id);
-                let _ = tx.send(WatcherEvent::TaskEvent(event));
+    health::metrics().set_watch_mode("poll");
+    let mut interval = POLL_FAST;
+    let mut last_change = Instant::now();
+
+    while !tx.is_closed() {
+        tokio::time::sleep(interval).await;
+        let current = PathSnapshot::take(paths).await;
+
+        if current != previous {
+            if current.events != previous.events {
+                on_events_changed(&paths.events, state, tx).await;
             }
-        }
-    } else if path.starts_with(todos_path) && path.extension().map_or(false, |e| e == "json") {
-        // Todos file changed - read all todos
-        let todos = read_all_todos(todos_path);
-        tracing::debug!("Todos updated: {} items", todos.len());
-        let _ = tx.send(WatcherEvent::TodosUpdated(todos));
-    } else if path == download_path {
-        // Download progress changed
-        if let Some(progress) = read_download_progress(download_path) {
-            tracing::debug!("Download progress: {}%", progress.percent);
-            let _ = tx.send(WatcherEvent::DownloadProgress(progress));
+            if current.todos != previous.todos {
+                on_todos_changed(state, tx).await;
+            }
+            if current.download != previous.download {
+                on_download_changed(&paths.download, tx).await;
+            }
+            if current.downloads != previous.downloads {
+                on_downloads_changed(state, tx).await;
+            }
+            if current.progress != previous.progress {
+                on_progress_changed(state, tx).await;
+            }
+            previous = current;
+            last_change = Instant::now();
+            interval = POLL_FAST;
+        } else if last_change.elapsed() > POLL_ACTIVE_WINDOW && interval < POLL_SLOW {
+            interval = (interval * 2).min(POLL_SLOW);
+            tracing::debug!("Idle; polling every {}ms", interval.as_millis());
         }
     }
 }
 
-/// Read new events from the JSONL file (incremental)
-fn read_new_events(path: &Path, state: &mut FileState) -> Vec<TaskEvent> {
-    let mut events = Vec::new();
+/// Handle a single file event
+async fn handle_file_event(
+    path: &Path,
+    paths: &WatchedPaths,
+    state: &mut ReadState,
+    tx: &EventSender,
+) {
+    if path == paths.events {
+        on_events_changed(&paths.events, state, tx).await;
+    } else if path.starts_with(&paths.todos) && path.extension().map_or(false, |e| e == "json") {
+        on_todos_changed(state, tx).await;
+    } else if path == paths.download {
+        on_download_changed(&paths.download, tx).await;
+    } else if path.starts_with(&paths.downloads) && path.extension().is_some_and(|e| e == "json") {
+        on_downloads_changed(state, tx).await;
+    } else if path.starts_with(&paths.progress) && path.extension().is_some_and(|e| e == "jsonl") {
+        on_progress_changed(state, tx).await;
+    }
+}
+
+/// Events file changed - read new events
+async fn on_events_changed(events_path: &Path, state: &mut ReadState, tx: &EventSender) {
+    for mut event in read_new_events(events_path, &mut state.events).await {
+        event.source.get_or_insert_with(|| CLAUDE_CODE_SOURCE.to_string());
+        event.profile.clone_from(&state.profile);
+        tracing::debug!("Task event: {} - {}", event.event_type, event.task_id);
+        let _ = tx.send(WatcherEvent::TaskEvent(event));
+    }
+    state.events_offset.store(state.events.last_position, Ordering::SeqCst);
+}
+
+/// Todos changed - re-read the files that changed and send the result
+async fn on_todos_changed(state: &mut ReadState, tx: &EventSender) {
+    let diff = state.todos.refresh().await;
+    if diff.is_empty() {
+        return;
+    }
+
+    let todos = state.todo_board.update(state.profile.clone(), state.todos.todos());
+    tracing::debug!(
+        "Todos updated: {} items (+{} ~{} -{})",
+        todos.len(),
+        diff.added.len(),
+        diff.updated.len(),
+        diff.removed.len()
+    );
+    let _ = tx.send(WatcherEvent::TodosChanged(diff));
+    let _ = tx.send(WatcherEvent::TodosUpdated(todos));
+}
+
+/// Download progress changed
+async fn on_download_changed(download_path: &Path, tx: &EventSender) {
+    if let Some(progress) = read_download_progress(download_path).await {
+        tracing::debug!("Download progress: {}%", progress.percent);
+        let _ = tx.send(WatcherEvent::DownloadProgress(progress));
+    }
+}
+
+/// Per-task download progress changed - send each file that was modified
+async fn on_downloads_changed(state: &mut ReadState, tx: &EventSender) {
+    for progress in state.downloads.refresh().await {
+        tracing::debug!("Download progress for {}: {}%", progress.task_id, progress.percent);
+        let _ = tx.send(WatcherEvent::DownloadProgress(progress));
+    }
+}
 
-    if !path.exists() {
-        return events;
+/// Generic progress files changed - send the lines appended to them
+async fn on_progress_changed(state: &mut ReadState, tx: &EventSender) {
+    for progress in state.progress.refresh().await {
+        tracing::debug!("Progress for {}: {:?}%", progress.id, progress.percent);
+        let _ = tx.send(WatcherEvent::GenericProgress(progress));
     }
+}
+
+/// Read new events from the JSONL file (incremental)
+async fn read_new_events(path: &Path, state: &mut FileState) -> Vec<TaskEvent> {
+    read_new_lines(path, state)
+        .await
+        .into_iter()
+        .filter_map(|line| match serde_json::from_str::<TaskEvent>(&line) {
+            Ok(event) => Some(event),
+            Err(e) => {
+                tracing::warn!("Failed to parse event: {} - line: {}", e, line);
+                health::metrics().record_parse_error();
+                None
+            }
+        })
+        .collect()
+}
+
+/// Read complete lines appended to a file since the last read
+pub(crate) async fn read_new_lines(path: &Path, state: &mut FileState) -> Vec<String> {
+    let mut lines = Vec::new();
 
     // Check current file size
-    let current_size = fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+    let current_size = match tokio::fs::metadata(path).await {
+        Ok(meta) => meta.len(),
+        Err(_) => return lines,
+    };
 
     // Handle file truncation (e.g., when cleared)
     if current_size < state.last_size {
-        tracing::debug!("Events file truncated, resetting position");
+        tracing::debug!("{:?} truncated, resetting position", path);
         state.last_position = 0;
     }
 
     // No new data
     if current_size <= state.last_position {
         state.last_size = current_size;
-        return events;
+        return lines;
     }
 
     // Open and seek to last position
-    let file = match File::open(path) {
+    let mut file = match tokio::fs::File::open(path).await {
         Ok(f) => f,
         Err(e) => {
-            tracing::error!("Failed to open events file: {}", e);
-            return events;
+            tracing::error!("Failed to open {:?}: {}", path, e);
+            return lines;
         }
     };
 
-    let mut reader = BufReader::new(file);
-
-    if reader.seek(SeekFrom::Start(state.last_position)).is_err() {
-        return events;
+    if file.seek(SeekFrom::Start(state.last_position)).await.is_err() {
+        return lines;
     }
 
     // Read new lines
+    let mut reader = BufReader::new(file);
     let mut line = String::new();
-    while reader.read_line(&mut line).unwrap_or(0) > 0 {
+    let mut position = state.last_position;
+    while let Ok(read) = reader.read_line(&mut line).await {
+        if read == 0 {
+            break;
+        }
+        position += read as u64;
         let trimmed = line.trim();
         if !trimmed.is_empty() {
-            match serde_json::from_str::<TaskEvent>(trimmed) {
-                Ok(event) => events.push(event),
-                Err(e) => tracing::warn!("Failed to parse event: {} - line: {}", e, trimmed),
-            }
+            lines.push(trimmed.to_string());
         }
         line.clear();
     }
 
     // Update position
-    state.last_position = reader.stream_position().unwrap_or(state.last_position);
+    state.last_position = position;
     state.last_size = current_size;
 
-    events
+    lines
+}
+
+/// Active todos from one todo file, with what was last read from disk
+struct TodoFile {
+    modified: Option<SystemTime>,
+    len: u64,
+    hash: u64,
+    todos: Vec<GlobalTodoItem>,
 }
 
-/// Read all todos from the todos directory
-fn read_all_todos(todos_dir: &Path) -> Vec<GlobalTodoItem> {
-    let mut all_todos = Vec::new();
+/// Where completed-only todo files are moved, under the todos directory
+pub const TODO_ARCHIVE_DIR: &str = "archive";
+
+/// Incrementally maintained view of the todos directory
+///
+/// A scan only re-reads files whose size or modification time changed, and
+/// only re-parses those whose contents hash differently, so old todo files
+/// cost a `stat` each. Files older than `max_age` are left out, their todos
+/// reported as hidden once, and with `archive_completed` moved aside once
+/// every todo in them is done.
+pub(crate) struct TodoTracker {
+    dir: PathBuf,
+    profile: Option<String>,
+    max_age: Option<Duration>,
+    archive_completed: bool,
+    files: BTreeMap<PathBuf, TodoFile>,
+    /// Stale files already looked at, so each is only checked for archiving once
+    stale: HashMap<PathBuf, Option<SystemTime>>,
+}
 
-    if !todos_dir.exists() {
-        return all_todos;
+impl TodoTracker {
+    pub(crate) fn new(dir: &Path, max_age: Option<Duration>, archive_completed: bool) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+            profile: None,
+            max_age,
+            archive_completed,
+            files: BTreeMap::new(),
+            stale: HashMap::new(),
+        }
     }
 
-    let entries = match fs::read_dir(todos_dir) {
-        Ok(e) => e,
-        Err(_) => return all_todos,
-    };
+    /// Tag the todos read from now on with a Claude profile
+    pub(crate) fn with_profile(mut self, profile: Option<String>) -> Self {
+        self.profile = profile;
+        self
+    }
 
-    for entry in entries.flatten() {
-        let path = entry.path();
-        if path.extension().map_or(false, |e| e == "json") {
-            if let Some(todos) = read_todos_file(&path) {
-                // Only include files with active todos
-                let has_active = todos.iter().any(|t| t.status != "completed");
-                if has_active {
-                    let session_id = extract_session_id(&path);
-
-                    for todo in todos {
-                        if todo.status != "completed" {
-                            all_todos.push(GlobalTodoItem {
-                                content: todo.content,
-                                status: todo.status,
-                                active_form: todo.active_form,
-                                session_id: session_id.clone(),
-                            });
+    /// Every active (not completed) todo, ordered by file
+    pub(crate) fn todos(&self) -> Vec<GlobalTodoItem> {
+        self.files.values().flat_map(|file| file.todos.iter().cloned()).collect()
+    }
+
+    /// Rescan the directory and return what changed since the last scan
+    pub(crate) async fn refresh(&mut self) -> TodosDiff {
+        let mut diff = TodosDiff::default();
+        let mut present = HashSet::new();
+        let mut stale_seen = HashSet::new();
+        let cutoff = self.max_age.and_then(|age| SystemTime::now().checked_sub(age));
+
+        if let Ok(mut entries) = tokio::fs::read_dir(&self.dir).await {
+            while let Ok(Some(entry)) = entries.next_entry().await {
+                let path = entry.path();
+                if path.extension().is_none_or(|e| e != "json") {
+                    continue;
+                }
+                let Ok(meta) = entry.metadata().await else {
+                    continue;
+                };
+                let (modified, len) = (meta.modified().ok(), meta.len());
+
+                // Stale files drop out of the list, but their todos weren't finished
+                if cutoff.is_some_and(|cutoff| modified.is_some_and(|m| m < cutoff)) {
+                    stale_seen.insert(path.clone());
+                    if self.stale.get(&path) != Some(&modified) {
+                        self.stale.insert(path.clone(), modified);
+                        match self.files.remove(&path) {
+                            Some(file) => diff.hidden.extend(file.todos),
+                            None => diff.hidden.extend(self.read_active_todos(&path).await),
+                        }
+                        if self.archive_completed {
+                            self.archive_if_completed(&path).await;
                         }
                     }
+                    continue;
+                }
+                self.stale.remove(&path);
+                present.insert(path.clone());
+
+                if self.files.get(&path).is_some_and(|f| f.modified == modified && f.len == len) {
+                    continue;
+                }
+
+                let Ok(contents) = tokio::fs::read(&path).await else {
+                    continue;
+                };
+                let hash = hash_contents(&contents);
+                if let Some(file) = self.files.get_mut(&path).filter(|f| f.hash == hash) {
+                    file.modified = modified;
+                    file.len = len;
+                    continue;
                 }
+
+                // A half-written file keeps its old todos and is retried on the next scan
+                let Ok(items) = serde_json::from_slice::<Vec<TodoItem>>(&contents) else {
+                    continue;
+                };
+                let completed: HashSet<String> = items
+                    .iter()
+                    .filter(|todo| todo.status == "completed")
+                    .map(|todo| todo.content.clone())
+                    .collect();
+                let todos = self.active_todos(items, &path);
+
+                let previous = self.files.get(&path).map(|f| f.todos.as_slice()).unwrap_or(&[]);
+                let removed_before = diff.removed.len();
+                diff_todos(previous, &todos, &mut diff);
+                let newly_completed: Vec<GlobalTodoItem> = diff.removed[removed_before..]
+                    .iter()
+                    .filter(|todo| completed.contains(&todo.content))
+                    .map(|todo| GlobalTodoItem { status: "completed".to_string(), ..todo.clone() })
+                    .collect();
+                diff.completed.extend(newly_completed);
+                self.files.insert(path, TodoFile { modified, len, hash, todos });
+            }
+        }
+
+        let deleted: Vec<PathBuf> = self.files.keys().filter(|p| !present.contains(*p)).cloned().collect();
+        for path in deleted {
+            if let Some(file) = self.files.remove(&path) {
+                diff.removed.extend(file.todos);
             }
         }
+        self.stale.retain(|path, _| stale_seen.contains(path));
+
+        diff
+    }
+
+    /// The todos in a file's items that aren't completed
+    fn active_todos(&self, items: Vec<TodoItem>, path: &Path) -> Vec<GlobalTodoItem> {
+        let session_id = extract_session_id(path);
+        items
+            .into_iter()
+            .filter(|todo| todo.status != "completed")
+            .map(|todo| GlobalTodoItem {
+                content: todo.content,
+                status: todo.status,
+                active_form: todo.active_form,
+                session_id: session_id.clone(),
+                profile: self.profile.clone(),
+                also_in: Vec::new(),
+            })
+            .collect()
+    }
+
+    /// The active todos in a file not read before, or none if it can't be read
+    async fn read_active_todos(&self, path: &Path) -> Vec<GlobalTodoItem> {
+        let Ok(contents) = tokio::fs::read(path).await else {
+            return Vec::new();
+        };
+        match serde_json::from_slice::<Vec<TodoItem>>(&contents) {
+            Ok(items) => self.active_todos(items, path),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    /// Move a stale file into the archive directory if all of its todos are done
+    async fn archive_if_completed(&self, path: &Path) {
+        let Ok(contents) = tokio::fs::read(path).await else {
+            return;
+        };
+        let Ok(items) = serde_json::from_slice::<Vec<TodoItem>>(&contents) else {
+            return;
+        };
+        if items.iter().any(|todo| todo.status != "completed") {
+            return;
+        }
+        let Some(name) = path.file_name() else {
+            return;
+        };
+
+        let archive = self.dir.join(TODO_ARCHIVE_DIR);
+        let moved = match tokio::fs::create_dir_all(&archive).await {
+            Ok(()) => tokio::fs::rename(path, archive.join(name)).await,
+            Err(e) => Err(e),
+        };
+        match moved {
+            Ok(()) => tracing::debug!("Archived completed todo file {:?}", path),
+            Err(e) => tracing::warn!("Failed to archive {:?}: {}", path, e),
+        }
     }
+}
+
+fn hash_contents(contents: &[u8]) -> u64 {
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    contents.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Key a todo's place in the user's order by; stable across runs and Rust versions
+pub fn todo_order_key(content: &str) -> String {
+    // 64-bit FNV-1a
+    let hash = content.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    });
+    format!("{:016x}", hash)
+}
 
-    all_todos
+/// Put todos in the user's order, keyed by [`todo_order_key`]
+///
+/// Todos the user hasn't placed keep their file order, after the ones they have.
+pub fn sort_todos(todos: &mut [GlobalTodoItem], order: &HashMap<String, i64>) {
+    todos.sort_by_cached_key(|todo| order.get(&todo_order_key(&todo.content)).copied().unwrap_or(i64::MAX));
+}
+
+/// Compare one file's todos before and after a change
+fn diff_todos(previous: &[GlobalTodoItem], current: &[GlobalTodoItem], diff: &mut TodosDiff) {
+    // Repeated contents are numbered so each copy is matched once
+    fn keyed(todos: &[GlobalTodoItem]) -> Vec<((&str, usize), &GlobalTodoItem)> {
+        let mut seen: HashMap<&str, usize> = HashMap::new();
+        todos
+            .iter()
+            .map(|todo| {
+                let n = seen.entry(todo.content.as_str()).or_default();
+                *n += 1;
+                ((todo.content.as_str(), *n), todo)
+            })
+            .collect()
+    }
+
+    let before: HashMap<_, _> = keyed(previous).into_iter().collect();
+    let after = keyed(current);
+    let after_keys: HashSet<_> = after.iter().map(|(key, _)| *key).collect();
+
+    for (key, todo) in &after {
+        match before.get(key) {
+            None => diff.added.push((*todo).clone()),
+            Some(old) if old.status != todo.status || old.active_form != todo.active_form => {
+                diff.updated.push((*todo).clone());
+            }
+            Some(_) => {}
+        }
+    }
+    for (key, todo) in keyed(previous) {
+        if !after_keys.contains(&key) {
+            diff.removed.push(todo.clone());
+        }
+    }
+}
+
+/// Read every todo (including completed ones) belonging to a session
+pub fn read_session_todos(todos_dir: &Path, session_id: &str) -> Vec<TodoItem> {
+    let mut todos = Vec::new();
+
+    // Archived files still count towards the session's history
+    let archive = todos_dir.join(TODO_ARCHIVE_DIR);
+    let entries = fs::read_dir(todos_dir)
+        .into_iter()
+        .chain(fs::read_dir(&archive))
+        .flatten();
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().is_some_and(|e| e == "json") && extract_session_id(&path) == session_id {
+            if let Some(items) = read_todos_file(&path) {
+                todos.extend(items);
+            }
+        }
+    }
+
+    todos
+}
+
+/// Every todo in the directory and its archive, grouped by session
+pub fn read_all_todos(todos_dir: &Path) -> BTreeMap<String, Vec<TodoItem>> {
+    let mut sessions: BTreeMap<String, Vec<TodoItem>> = BTreeMap::new();
+
+    let archive = todos_dir.join(TODO_ARCHIVE_DIR);
+    let mut paths: Vec<PathBuf> = fs::read_dir(todos_dir)
+        .into_iter()
+        .chain(fs::read_dir(&archive))
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|e| e == "json"))
+        .collect();
+    paths.sort();
+
+    for path in paths {
+        if let Some(items) = read_todos_file(&path) {
+            sessions.entry(extract_session_id(&path)).or_default().extend(items);
+        }
+    }
+
+    sessions
+}
+
+/// Statuses Claude gives todos
+pub const TODO_STATUSES: &[&str] = &["pending", "in_progress", "completed"];
+
+/// Set the status of a session's todo, writing its file back atomically
+///
+/// The first todo with this content in the session's files is changed. The
+/// file's previous contents are kept next to it as `<name>.bak`, and fields
+/// the overlay doesn't know about are written back as they were. Returns
+/// false if no todo matched.
+pub fn set_todo_status(todos_dir: &Path, session_id: &str, content: &str, status: &str) -> Result<bool, WatcherError> {
+    for path in session_todo_files(todos_dir, session_id)? {
+        let Ok(original) = fs::read(&path) else {
+            continue;
+        };
+        let Ok(serde_json::Value::Array(mut items)) = serde_json::from_slice(&original) else {
+            continue;
+        };
+        let Some(item) = items.iter_mut().find(|item| item["content"] == content) else {
+            continue;
+        };
+        if item["status"] == status {
+            return Ok(true);
+        }
+        item["status"] = serde_json::Value::String(status.to_string());
+
+        let updated = serde_json::to_vec(&items).map_err(|e| WatcherError::IoError(e.to_string()))?;
+        replace_file(&path, &original, &updated)?;
+        return Ok(true);
+    }
+    Ok(false)
+}
+
+/// Add a pending todo to the end of a session's todo file, creating it if needed
+///
+/// The todo goes into the session's own file rather than a subagent's. Returns
+/// false if the session already has a todo with this content.
+pub fn add_todo(todos_dir: &Path, session_id: &str, content: &str) -> Result<bool, WatcherError> {
+    // The id becomes a file name, so it must not reach outside the todos directory
+    let valid = !session_id.is_empty()
+        && session_id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        return Err(WatcherError::InvalidSessionId(session_id.to_string()));
+    }
+    let files = session_todo_files(todos_dir, session_id)?;
+    if files.iter().filter_map(|path| read_todos_file(path)).flatten().any(|todo| todo.content == content) {
+        return Ok(false);
+    }
+
+    let path = todos_dir.join(format!("{}-agent-{}.json", session_id, session_id));
+    let original = fs::read(&path).ok();
+    let mut items = match &original {
+        Some(original) => match serde_json::from_slice(original) {
+            Ok(serde_json::Value::Array(items)) => items,
+            _ => return Err(WatcherError::IoError(format!("{} is not a todo list", path.display()))),
+        },
+        None => Vec::new(),
+    };
+    items.push(serde_json::json!({ "content": content, "status": "pending", "activeForm": content }));
+
+    let updated = serde_json::to_vec(&items).map_err(|e| WatcherError::IoError(e.to_string()))?;
+    match original {
+        Some(original) => replace_file(&path, &original, &updated)?,
+        None => {
+            fs::create_dir_all(todos_dir).map_err(|e| WatcherError::IoError(e.to_string()))?;
+            fs::write(&path, updated).map_err(|e| WatcherError::IoError(e.to_string()))?;
+        }
+    }
+    Ok(true)
+}
+
+/// A session's todo files outside the archive, in name order
+fn session_todo_files(todos_dir: &Path, session_id: &str) -> Result<Vec<PathBuf>, WatcherError> {
+    let mut paths: Vec<PathBuf> = fs::read_dir(todos_dir)
+        .map_err(|e| WatcherError::IoError(e.to_string()))?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|e| e == "json") && extract_session_id(path) == session_id)
+        .collect();
+    paths.sort();
+    Ok(paths)
+}
+
+/// Write `contents` over the file at `path` through a temporary file and a
+/// rename, so readers see the old file or the new one, never half of it
+fn replace_file(path: &Path, original: &[u8], contents: &[u8]) -> Result<(), WatcherError> {
+    let backup = path.with_extension("json.bak");
+    let temp = path.with_extension("json.tmp");
+    fs::write(&backup, original).map_err(|e| WatcherError::IoError(e.to_string()))?;
+    fs::write(&temp, contents).map_err(|e| WatcherError::IoError(e.to_string()))?;
+    fs::rename(&temp, path).map_err(|e| WatcherError::IoError(e.to_string()))
 }
 
 /// Read a single todos JSON file
@@ -376,8 +1473,8 @@ fn extract_session_id(path: &Path) -> String {
 }
 
 /// Read download progress from file
-fn read_download_progress(path: &Path) -> Option<DownloadProgress> {
-    let content = fs::read_to_string(path).ok()?;
+async fn read_download_progress(path: &Path) -> Option<DownloadProgress> {
+    let content = tokio::fs::read_to_string(path).await.ok()?;
     serde_json::from_str(&content).ok()
 }
 
@@ -386,6 +1483,8 @@ fn read_download_progress(path: &Path) -> Option<DownloadProgress> {
 pub enum WatcherError {
     IoError(String),
     WatchError(String),
+    /// A session id that can't safely be part of a file name
+    InvalidSessionId(String),
 }
 
 impl std::fmt::Display for WatcherError {
@@ -393,6 +1492,7 @@ impl std::fmt::Display for WatcherError {
         match self {
             WatcherError::IoError(e) => write!(f, "IO error: {}", e),
             WatcherError::WatchError(e) => write!(f, "Watch error: {}", e),
+            WatcherError::InvalidSessionId(id) => write!(f, "Invalid session id: {:?}", id),
         }
     }
 }
@@ -405,8 +1505,13 @@ mod tests {
     use std::io::Write;
     use tempfile::tempdir;
 
+    fn runtime() -> tokio::runtime::Runtime {
+        tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap()
+    }
+
     #[test]
     fn test_read_new_events() {
+        let runtime = runtime();
         let dir = tempdir().unwrap();
         let events_path = dir.path().join("events.jsonl");
 
@@ -419,18 +1524,276 @@ mod tests {
             last_size: 0,
         };
 
-        let events = read_new_events(&events_path, &mut state);
+        let events = runtime.block_on(read_new_events(&events_path, &mut state));
         assert_eq!(events.len(), 1);
         assert_eq!(events[0].task_id, "t1");
 
         // Add another event
         writeln!(file, r#"{{"type":"task_complete","task_id":"t1","timestamp":2000}}"#).unwrap();
 
-        let events = read_new_events(&events_path, &mut state);
+        let events = runtime.block_on(read_new_events(&events_path, &mut state));
         assert_eq!(events.len(), 1);
         assert_eq!(events[0].event_type, "task_complete");
     }
 
+    #[test]
+    fn test_read_session_todos() {
+        let dir = tempdir().unwrap();
+        fs::write(
+            dir.path().join("abc123-agent-abc123.json"),
+            r#"[{"content":"Write tests","status":"completed","activeForm":"Writing tests"}]"#,
+        ).unwrap();
+        fs::write(
+            dir.path().join("other-agent-other.json"),
+            r#"[{"content":"Unrelated","status":"pending","activeForm":"Unrelated"}]"#,
+        ).unwrap();
+
+        let todos = read_session_todos(dir.path(), "abc123");
+        assert_eq!(todos.len(), 1);
+        assert_eq!(todos[0].status, "completed");
+    }
+
+    #[test]
+    fn test_collapse_duplicate_todos() {
+        let todo = |content: &str, session_id: &str, status: &str| GlobalTodoItem {
+            content: content.to_string(),
+            status: status.to_string(),
+            active_form: format!("{} now", content),
+            session_id: session_id.to_string(),
+            profile: None,
+            also_in: Vec::new(),
+        };
+        let todos = vec![
+            todo("Run tests", "s1", "pending"),
+            todo("Write docs", "s1", "pending"),
+            todo("run  Tests", "s2", "in_progress"),
+            todo("Run tests", "s3", "pending"),
+            todo("Run tests", "s2", "pending"),
+        ];
+
+        let collapsed = collapse_duplicate_todos(todos);
+        assert_eq!(collapsed.len(), 2);
+        assert_eq!(collapsed[0].content, "Run tests");
+        assert_eq!((collapsed[0].status.as_str(), collapsed[0].active_form.as_str()), ("in_progress", "run  Tests now"));
+        assert_eq!(collapsed[0].also_in, ["s2", "s3"]);
+        assert!(collapsed[1].also_in.is_empty());
+    }
+
+    #[test]
+    fn test_group_todos() {
+        let todo = |content: &str, session_id: &str| GlobalTodoItem {
+            content: content.to_string(),
+            status: "pending".to_string(),
+            active_form: content.to_string(),
+            session_id: session_id.to_string(),
+            profile: None,
+            also_in: Vec::new(),
+        };
+        let session = |id: &str, path: &str, branch: Option<&str>| (id.to_string(), StoredSession {
+            id: id.to_string(),
+            started_at: 0,
+            ended_at: None,
+            project_path: Some(path.to_string()),
+            branch: branch.map(str::to_string),
+        });
+        let sessions = HashMap::from([
+            session("aaaaaaaa-1", "/home/me/app", Some("main")),
+            session("bbbbbbbb-2", "/home/me/api", None),
+            session("cccccccc-3", "/work/api", None),
+        ]);
+        let todos = [
+            todo("a", "bbbbbbbb-2"),
+            todo("b", "aaaaaaaa-1"),
+            todo("c", "bbbbbbbb-2"),
+            todo("d", "cccccccc-3"),
+            todo("e", "dddddddd-4"),
+        ];
+
+        let groups = group_todos(&todos, &sessions);
+        let names: Vec<_> = groups.iter().map(|g| (g.name.as_str(), g.todos.len())).collect();
+        assert_eq!(names, [("api · bbbbbbbb", 2), ("app (main)", 1), ("api · cccccccc", 1), ("Session dddddddd", 1)]);
+        assert_eq!(groups[1].project_path.as_deref(), Some("/home/me/app"));
+    }
+
+    #[test]
+    fn test_read_all_todos() {
+        let dir = tempdir().unwrap();
+        fs::create_dir(dir.path().join(TODO_ARCHIVE_DIR)).unwrap();
+        fs::write(
+            dir.path().join("a-agent-a.json"),
+            r#"[{"content":"Write tests","status":"pending","activeForm":"Writing tests"}]"#,
+        ).unwrap();
+        fs::write(
+            dir.path().join(TODO_ARCHIVE_DIR).join("b-agent-b.json"),
+            r#"[{"content":"Ship it","status":"completed","activeForm":"Shipping it"}]"#,
+        ).unwrap();
+
+        let sessions = read_all_todos(dir.path());
+        assert_eq!(sessions.keys().collect::<Vec<_>>(), ["a", "b"]);
+        assert_eq!(sessions["b"][0].status, "completed");
+    }
+
+    #[test]
+    fn test_set_todo_status() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("abc123-agent-abc123.json");
+        let original = r#"[{"content":"Write tests","status":"pending","activeForm":"Writing tests","id":"1"}]"#;
+        fs::write(&path, original).unwrap();
+
+        assert!(set_todo_status(dir.path(), "abc123", "Write tests", "completed").unwrap());
+        assert!(!set_todo_status(dir.path(), "abc123", "Ship it", "completed").unwrap());
+        assert!(!set_todo_status(dir.path(), "other", "Write tests", "completed").unwrap());
+
+        let todos = read_session_todos(dir.path(), "abc123");
+        assert_eq!(todos[0].status, "completed");
+        // Fields the overlay doesn't model survive, and the old file is kept
+        assert!(fs::read_to_string(&path).unwrap().contains(r#""id":"1""#));
+        assert_eq!(fs::read_to_string(dir.path().join("abc123-agent-abc123.json.bak")).unwrap(), original);
+        assert!(!dir.path().join("abc123-agent-abc123.json.tmp").exists());
+    }
+
+    #[test]
+    fn test_sort_todos() {
+        let todo = |content: &str| GlobalTodoItem {
+            content: content.to_string(),
+            status: "pending".to_string(),
+            active_form: content.to_string(),
+            session_id: "s1".to_string(),
+            profile: None,
+            also_in: Vec::new(),
+        };
+        let mut todos = vec![todo("a"), todo("b"), todo("c"), todo("d")];
+        let order = HashMap::from([(todo_order_key("c"), 0), (todo_order_key("a"), 1)]);
+
+        sort_todos(&mut todos, &order);
+        let contents: Vec<&str> = todos.iter().map(|t| t.content.as_str()).collect();
+        assert_eq!(contents, ["c", "a", "b", "d"]);
+        assert_eq!(todo_order_key("a"), "af63dc4c8601ec8c");
+    }
+
+    #[test]
+    fn test_add_todo() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("abc123-agent-abc123.json");
+
+        // A session without a todo file gets one
+        assert!(add_todo(dir.path(), "abc123", "Write tests").unwrap());
+        assert!(add_todo(dir.path(), "abc123", "Update the changelog").unwrap());
+        assert!(!add_todo(dir.path(), "abc123", "Write tests").unwrap());
+
+        let todos = read_session_todos(dir.path(), "abc123");
+        assert_eq!(todos.iter().map(|t| t.content.as_str()).collect::<Vec<_>>(), ["Write tests", "Update the changelog"]);
+        assert!(todos.iter().all(|t| t.status == "pending"));
+        assert!(dir.path().join("abc123-agent-abc123.json.bak").exists());
+
+        fs::write(&path, "not json").unwrap();
+        assert!(add_todo(dir.path(), "abc123", "Ship it").is_err());
+
+        for id in ["", "..", "../abc123", "a/b", "a\\b", "a.b"] {
+            assert!(matches!(add_todo(dir.path(), id, "Escape"), Err(WatcherError::InvalidSessionId(_))));
+        }
+    }
+
+    #[test]
+    fn test_todo_tracker_diffs() {
+        let dir = tempdir().unwrap();
+        let runtime = runtime();
+        let mut tracker = TodoTracker::new(dir.path(), None, false);
+        let write = |name: &str, json: &str| fs::write(dir.path().join(name), json).unwrap();
+
+        write("s1-agent-s1.json", r#"[
+            {"content":"Write tests","status":"pending","activeForm":"Writing tests"},
+            {"content":"Ship it","status":"pending","activeForm":"Shipping it"}
+        ]"#);
+        write("old-agent-old.json", r#"[{"content":"Done","status":"completed","activeForm":"Done"}]"#);
+        let diff = runtime.block_on(tracker.refresh());
+        assert_eq!(diff.added.len(), 2);
+        assert_eq!(tracker.todos().len(), 2);
+
+        // Nothing changed on disk
+        assert!(runtime.block_on(tracker.refresh()).is_empty());
+
+        write("s1-agent-s1.json", r#"[
+            {"content":"Write tests","status":"completed","activeForm":"Writing tests"},
+            {"content":"Ship it","status":"in_progress","activeForm":"Shipping it"},
+            {"content":"Celebrate","status":"pending","activeForm":"Celebrating"}
+        ]"#);
+        let diff = runtime.block_on(tracker.refresh());
+        assert_eq!(diff.added.iter().map(|t| t.content.as_str()).collect::<Vec<_>>(), ["Celebrate"]);
+        assert_eq!(diff.updated.iter().map(|t| t.status.as_str()).collect::<Vec<_>>(), ["in_progress"]);
+        assert_eq!(diff.removed.iter().map(|t| t.content.as_str()).collect::<Vec<_>>(), ["Write tests"]);
+        assert_eq!(diff.completed.iter().map(|t| (t.content.as_str(), t.status.as_str())).collect::<Vec<_>>(), [("Write tests", "completed")]);
+
+        // Todos whose file goes away were dropped, not completed
+        fs::remove_file(dir.path().join("s1-agent-s1.json")).unwrap();
+        let diff = runtime.block_on(tracker.refresh());
+        assert_eq!(diff.removed.len(), 2);
+        assert!(diff.completed.is_empty());
+        assert!(tracker.todos().is_empty());
+    }
+
+    #[test]
+    fn test_todo_board_merges_profiles() {
+        let (main, work) = (tempdir().unwrap(), tempdir().unwrap());
+        let runtime = runtime();
+        let board = TodoBoard::default();
+        fs::write(main.path().join("a-agent-a.json"), r#"[{"content":"Main","status":"pending","activeForm":"Main"}]"#).unwrap();
+        fs::write(work.path().join("b-agent-b.json"), r#"[{"content":"Work","status":"pending","activeForm":"Work"}]"#).unwrap();
+
+        let mut work_tracker = TodoTracker::new(work.path(), None, false).with_profile(Some("work".to_string()));
+        runtime.block_on(work_tracker.refresh());
+        let mut main_tracker = TodoTracker::new(main.path(), None, false);
+        runtime.block_on(main_tracker.refresh());
+
+        board.update(Some("work".to_string()), work_tracker.todos());
+        let todos = board.update(None, main_tracker.todos());
+        let tagged: Vec<_> = todos.iter().map(|t| (t.content.as_str(), t.profile.as_deref())).collect();
+        assert_eq!(tagged, [("Main", None), ("Work", Some("work"))]);
+
+        // An update from one profile keeps the other's todos
+        assert_eq!(board.update(None, Vec::new()).len(), 1);
+    }
+
+    #[test]
+    fn test_stale_todo_files() {
+        let dir = tempdir().unwrap();
+        let runtime = runtime();
+        let day = Duration::from_secs(24 * 60 * 60);
+        let write_old = |name: &str, json: &str| {
+            let path = dir.path().join(name);
+            fs::write(&path, json).unwrap();
+            File::options().write(true).open(&path).unwrap()
+                .set_modified(SystemTime::now() - 30 * day).unwrap();
+        };
+
+        write_old("done-agent-done.json", r#"[{"content":"Old","status":"completed","activeForm":"Old"}]"#);
+        write_old("left-agent-left.json", r#"[{"content":"Abandoned","status":"pending","activeForm":"Abandoned"}]"#);
+        fs::write(
+            dir.path().join("new-agent-new.json"),
+            r#"[{"content":"Current","status":"pending","activeForm":"Current"}]"#,
+        ).unwrap();
+
+        let mut tracker = TodoTracker::new(dir.path(), Some(7 * day), true);
+        let diff = runtime.block_on(tracker.refresh());
+        assert_eq!(diff.added.iter().map(|t| t.content.as_str()).collect::<Vec<_>>(), ["Current"]);
+        // Stale todos are hidden, not finished, so sync plugins leave them open
+        assert_eq!(diff.hidden.iter().map(|t| t.content.as_str()).collect::<Vec<_>>(), ["Abandoned"]);
+        assert!(diff.removed.is_empty());
+        assert!(runtime.block_on(tracker.refresh()).is_empty());
+
+        // A listed file that goes stale is hidden too
+        write_old("new-agent-new.json", r#"[{"content":"Current","status":"pending","activeForm":"Current"}]"#);
+        let diff = runtime.block_on(tracker.refresh());
+        assert_eq!(diff.hidden.iter().map(|t| t.content.as_str()).collect::<Vec<_>>(), ["Current"]);
+        assert!(diff.removed.is_empty());
+        assert!(tracker.todos().is_empty());
+
+        // Only the completed-only file is archived, and it stays part of its session
+        assert!(dir.path().join("archive/done-agent-done.json").exists());
+        assert!(dir.path().join("left-agent-left.json").exists());
+        assert_eq!(read_session_todos(dir.path(), "done").len(), 1);
+    }
+
     #[test]
     fn test_extract_session_id() {
         let path = PathBuf::from("/tmp/abc123-agent-def456.json");
@@ -439,4 +1802,80 @@ mod tests {
         let path = PathBuf::from("/tmp/session-id.json");
         assert_eq!(extract_session_id(&path), "session-id");
     }
+
+    #[test]
+    fn test_polling_picks_up_changes() {
+        let dir = tempdir().unwrap();
+        let paths = WatchedPaths {
+            events: dir.path().join("events.jsonl"),
+            todos: dir.path().join("todos"),
+            download: dir.path().join("download-progress.json"),
+            downloads: dir.path().join("downloads"),
+            progress: dir.path().join("progress"),
+        };
+        File::create(&paths.events).unwrap();
+        fs::create_dir(&paths.todos).unwrap();
+        fs::create_dir(&paths.downloads).unwrap();
+        fs::create_dir(&paths.progress).unwrap();
+        // Progress written before the watcher started isn't shown
+        fs::write(paths.progress.join("old.jsonl"), "{\"id\":\"old\",\"percent\":10}\n").unwrap();
+
+        let runtime = runtime();
+        let previous = runtime.block_on(PathSnapshot::take(&paths));
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let offset = Arc::new(AtomicU64::new(0));
+        {
+            let paths = paths.clone();
+            let mut state = ReadState {
+                profile: None,
+                events: FileState::at_end(&paths.events),
+                events_offset: offset.clone(),
+                todos: TodoTracker::new(&paths.todos, None, false),
+                todo_board: TodoBoard::default(),
+                downloads: DownloadTracker::new(&paths.downloads),
+                progress: ProgressTracker::new(&paths.progress),
+            };
+            runtime.spawn(async move { run_polling(&paths, &mut state, &tx, previous).await });
+        }
+        let next = |rx: &mut UnboundedReceiver<WatcherEvent>| {
+            runtime.block_on(async { tokio::time::timeout(Duration::from_secs(5), rx.recv()).await }).unwrap().unwrap()
+        };
+
+        let mut file = fs::OpenOptions::new().append(true).open(&paths.events).unwrap();
+        let line = r#"{"type":"task_started","task_id":"t1","timestamp":1000}"#;
+        writeln!(file, "{}", line).unwrap();
+        assert!(matches!(next(&mut rx), WatcherEvent::TaskEvent(e) if e.task_id == "t1"));
+        assert_eq!(offset.load(Ordering::SeqCst), line.len() as u64 + 1);
+
+        // A saved position is resumed unless the file has since shrunk below it
+        assert_eq!(FileState::resume(&paths.events, 10).last_position, 10);
+        assert_eq!(FileState::resume(&paths.events, 10_000).last_position, line.len() as u64 + 1);
+
+        fs::write(
+            paths.todos.join("s1-agent-s1.json"),
+            r#"[{"content":"Write tests","status":"pending","activeForm":"Writing tests"}]"#,
+        ).unwrap();
+        assert!(matches!(next(&mut rx), WatcherEvent::TodosChanged(diff) if diff.added.len() == 1));
+        assert!(matches!(next(&mut rx), WatcherEvent::TodosUpdated(todos) if todos.len() == 1));
+
+        // Per-task download files take their task id from the file name
+        fs::write(paths.downloads.join("t2.json"), r#"{"percent":40.0,"speed":null,"eta":null,"timestamp":2000}"#).unwrap();
+        assert!(matches!(next(&mut rx), WatcherEvent::DownloadProgress(p) if p.task_id == "t2" && p.percent == 40.0));
+
+        fs::write(
+            paths.progress.join("backup.jsonl"),
+            "{\"id\":\"backup\",\"label\":\"Backup\",\"percent\":25,\"message\":\"1 of 4\"}\nnot json\n{\"id\":\"backup\"}\n",
+        ).unwrap();
+        let first = GenericProgress {
+            id: "backup".to_string(),
+            label: Some("Backup".to_string()),
+            percent: Some(25.0),
+            message: Some("1 of 4".to_string()),
+        };
+        assert!(matches!(next(&mut rx), WatcherEvent::GenericProgress(p) if p == first));
+        assert!(matches!(next(&mut rx), WatcherEvent::GenericProgress(p) if p.id == "backup" && p.percent.is_none()));
+
+        assert_eq!(WatchMode::from_config("POLL"), WatchMode::Poll);
+        assert_eq!(WatchMode::from_config("bogus"), WatchMode::Auto);
+    }
 }
@@ -0,0 +1,142 @@
+//! Which workspaces the overlay shows on
+//!
+//! `window.all_workspaces` pins the overlay to every workspace, where the
+//! platform allows it. On Linux under X11, `window.workspaces` narrows that
+//! down: the overlay is hidden while another workspace is current and comes
+//! back when one of the listed ones is. The overlay is also marked as a
+//! utility window there, which tiling window managers such as i3 float
+//! instead of tiling. Wayland leaves workspaces to the compositor, so there
+//! only `all_workspaces` applies, if the compositor honors it.
+
+use tauri::{AppHandle, WebviewWindow};
+
+use crate::config::WindowConfig;
+
+/// How often the current workspace is checked
+#[cfg(target_os = "linux")]
+const WORKSPACE_CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_millis(500);
+
+/// Apply the workspace settings to the overlay window
+pub fn apply(app: &AppHandle, window: &WebviewWindow, config: &WindowConfig) {
+    let all = config.all_workspaces || !config.workspaces.is_empty();
+    if all {
+        if let Err(e) = window.set_visible_on_all_workspaces(true) {
+            tracing::warn!("Failed to show the overlay on all workspaces: {}", e);
+        }
+    }
+    platform::apply(app, window, config);
+}
+
+/// Whether the overlay belongs on `current`, counting workspaces from 0 as X11 does
+///
+/// `workspaces` counts from 1, as workspace switchers show them.
+pub fn shown_on(workspaces: &[u32], current: u32) -> bool {
+    workspaces.is_empty() || workspaces.contains(&(current + 1))
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    use gtk::prelude::*;
+    use tauri::{AppHandle, WebviewWindow};
+
+    use super::{shown_on, WORKSPACE_CHECK_INTERVAL};
+    use crate::config::WindowConfig;
+
+    pub fn apply(app: &AppHandle, window: &WebviewWindow, config: &WindowConfig) {
+        // Layer surfaces aren't tiled to begin with
+        if config.float_in_tiling_wms && !crate::layer_shell::enabled() {
+            float(window);
+        }
+        if config.workspaces.is_empty() {
+            return;
+        }
+        if !is_x11() {
+            tracing::warn!("window.workspaces only works under X11; the overlay shows on every workspace");
+            return;
+        }
+        follow_workspaces(app.clone(), window.clone(), config.workspaces.clone());
+    }
+
+    fn is_x11() -> bool {
+        gtk::gdk::Display::default().is_some_and(|display| display.is::<gdkx11::X11Display>())
+    }
+
+    /// Mark the window as a utility window, which tiling window managers float
+    fn float(window: &WebviewWindow) {
+        let Ok(gtk_window) = window.gtk_window() else {
+            return;
+        };
+        // Window managers read the type when the window is mapped
+        let visible = gtk_window.is_visible();
+        gtk_window.hide();
+        gtk_window.set_type_hint(gtk::gdk::WindowTypeHint::Utility);
+        if visible {
+            gtk_window.show();
+        }
+    }
+
+    /// Hide the overlay while a workspace it doesn't belong on is current
+    fn follow_workspaces(app: AppHandle, window: WebviewWindow, workspaces: Vec<u32>) {
+        // Only undo our own hiding, never the user's
+        let hidden_here = Arc::new(AtomicBool::new(false));
+        tauri::async_runtime::spawn(async move {
+            let mut ticker = tokio::time::interval(WORKSPACE_CHECK_INTERVAL);
+            loop {
+                ticker.tick().await;
+                let (window, workspaces, hidden_here) = (window.clone(), workspaces.clone(), hidden_here.clone());
+                // GDK is only usable from the main thread
+                let checked = app.run_on_main_thread(move || {
+                    let Some(current) = current_workspace() else {
+                        return;
+                    };
+                    let visible = window.is_visible().unwrap_or(false);
+                    if shown_on(&workspaces, current) {
+                        if !visible && hidden_here.swap(false, Ordering::Relaxed) {
+                            let _ = window.show();
+                        }
+                    } else if visible {
+                        hidden_here.store(true, Ordering::Relaxed);
+                        let _ = window.hide();
+                    }
+                });
+                if checked.is_err() {
+                    return;
+                }
+            }
+        });
+    }
+
+    fn current_workspace() -> Option<u32> {
+        let screen = gtk::gdk::Screen::default()?.downcast::<gdkx11::X11Screen>().ok()?;
+        Some(screen.current_desktop())
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod platform {
+    use tauri::{AppHandle, WebviewWindow};
+
+    use crate::config::WindowConfig;
+
+    pub fn apply(_app: &AppHandle, _window: &WebviewWindow, config: &WindowConfig) {
+        if !config.workspaces.is_empty() {
+            tracing::warn!("window.workspaces only works on Linux under X11");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shown_on() {
+        assert!(shown_on(&[], 4));
+        assert!(shown_on(&[1, 3], 0));
+        assert!(shown_on(&[1, 3], 2));
+        assert!(!shown_on(&[1, 3], 1));
+    }
+}
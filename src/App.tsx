@@ -1,19 +1,38 @@
 import { useState, useEffect } from "react";
 import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
 import { Header } from "./components/Header";
 import { TaskList } from "./components/TaskList";
 import { TodoSection } from "./components/TodoSection";
+import { ProgressBars } from "./components/ProgressBars";
 import { Settings } from "./components/Settings";
+import { ProfileFilter, ProfileSelection, matchesProfile } from "./components/ProfileFilter";
 import { useTasks } from "./hooks/useTasks";
 import { useTodos } from "./hooks/useTodos";
 import { useSettings } from "./hooks/useSettings";
+import { useContextUsage } from "./hooks/useContextUsage";
+import { useProgress } from "./hooks/useProgress";
 
 function App() {
   const { activeTasks, completedTasks, isVisible, clearCompleted, setIsVisible } =
     useTasks();
   const { todos } = useTodos();
+  const { warning: contextWarning } = useContextUsage();
+  const { bars } = useProgress();
   const { settings, updateSetting, resetSettings } = useSettings();
   const [showSettings, setShowSettings] = useState(false);
+  const [profile, setProfile] = useState<ProfileSelection>(null);
+  // Read out by screen readers on platforms without a native announcement API
+  const [announcement, setAnnouncement] = useState("");
+
+  useEffect(() => {
+    const unlisten = listen<string>("a11y-announcement", (event) => {
+      setAnnouncement(event.payload);
+    });
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, []);
 
   // Close settings with Escape key
   useEffect(() => {
@@ -47,8 +66,22 @@ function App() {
     await invoke("clear_events");
   };
 
+  // Profiles seen so far, for filtering by Claude directory
+  const profiles = Array.from(
+    new Set(
+      [...activeTasks, ...completedTasks, ...todos]
+        .map((item) => item.profile)
+        .filter((name): name is string => !!name)
+    )
+  ).sort();
+
+  const shownActiveTasks = activeTasks.filter((t) => matchesProfile(t.profile, profile));
+  const shownTodos = todos.filter((t) => matchesProfile(t.profile, profile));
+
   // Filter completed tasks based on settings
-  const displayedCompletedTasks = completedTasks.slice(0, settings.maxRecentTasks);
+  const displayedCompletedTasks = completedTasks
+    .filter((t) => matchesProfile(t.profile, profile))
+    .slice(0, settings.maxRecentTasks);
 
   // Calculate opacity (0-1 range)
   const windowOpacity = isVisible ? settings.opacity / 100 : 0;
@@ -62,12 +95,18 @@ function App() {
     >
       <Header
         activeTasks={activeTasks.length}
+        contextWarning={contextWarning}
         onClear={handleClear}
         onMinimize={handleMinimize}
         onSettings={() => setShowSettings(true)}
       />
-      <TodoSection todos={todos} />
-      <TaskList activeTasks={activeTasks} completedTasks={displayedCompletedTasks} />
+      <ProfileFilter profiles={profiles} selected={profile} onSelect={setProfile} />
+      <TodoSection todos={shownTodos} />
+      <ProgressBars bars={bars} />
+      <TaskList activeTasks={shownActiveTasks} completedTasks={displayedCompletedTasks} />
+      <div role="status" aria-live="polite" className="sr-only">
+        {announcement}
+      </div>
 
       {showSettings && (
         <Settings
@@ -1,17 +1,39 @@
-import { useState, useEffect } from "react";
+import { useState, useEffect, useRef } from "react";
 import { invoke } from "@tauri-apps/api/core";
 import { listen } from "@tauri-apps/api/event";
 import { getCurrentWindow } from "@tauri-apps/api/window";
+import { statusText, themeFromUrl } from "./theme";
 import "./styles/notification.css";
 
+const theme = themeFromUrl();
+
 interface TaskData {
+  /** Set for popups that aren't about a running task */
+  kind?: "usage_warning" | "away_summary" | "awaiting_input" | "storage_warning";
   task_id: string;
   tool: string;
   description: string;
   subagent_type?: string;
   background?: boolean;
+  /** How long similar tasks usually take */
+  eta_ms?: number | null;
+  /** When a usage limit lifts, in millis */
+  resets_at?: number | null;
+  /** Tasks that finished while the user was away */
+  completed?: number;
+  failed?: number;
+  /** Chime as the popup opens */
+  sound?: boolean;
+  /** Disk taken by Claude's files and the history, e.g. "2.1 GB" */
+  total?: string;
 }
 
+const WARNING_LABELS: Record<string, string> = {
+  rate_limit: "Rate limited",
+  usage_limit: "Usage limit reached",
+  overloaded: "API overloaded",
+};
+
 interface ProgressUpdate {
   task_id: string;
   percent: number; // 0-100
@@ -19,6 +41,11 @@ interface ProgressUpdate {
   eta?: string;
 }
 
+interface TaskOverdue {
+  task_id: string;
+  sound: boolean;
+}
+
 interface TaskComplete {
   task_id: string;
   status: "complete" | "error";
@@ -43,7 +70,7 @@ function getIcon(tool: string, desc: string): JSX.Element {
   const isDownload = desc.includes("curl") || desc.includes("wget") ||
                      desc.toLowerCase().includes("download");
 
-  const iconType = isDownload ? "download" : (TOOL_ICONS[tool] || "info");
+  const iconType = tool in WARNING_LABELS || tool === "awaiting_input" ? "alert" : isDownload ? "download" : (TOOL_ICONS[tool] || "info");
 
   const icons: Record<string, JSX.Element> = {
     terminal: (
@@ -77,6 +104,12 @@ function getIcon(tool: string, desc: string): JSX.Element {
         <circle cx="11" cy="11" r="8" /><path d="m21 21-4.3-4.3" />
       </svg>
     ),
+    alert: (
+      <svg xmlns="http://www.w3.org/2000/svg" width="16" height="16" viewBox="0 0 24 24" fill="none" stroke="currentColor" strokeWidth="2">
+        <path d="m21.73 18-8-14a2 2 0 0 0-3.48 0l-8 14A2 2 0 0 0 4 21h16a2 2 0 0 0 1.73-3" />
+        <path d="M12 9v4" /><path d="M12 17h.01" />
+      </svg>
+    ),
     info: (
       <svg xmlns="http://www.w3.org/2000/svg" width="16" height="16" viewBox="0 0 24 24" fill="none" stroke="currentColor" strokeWidth="2">
         <circle cx="12" cy="12" r="10" /><path d="M12 16v-4" /><path d="M12 8h.01" />
@@ -97,6 +130,23 @@ function getIcon(tool: string, desc: string): JSX.Element {
   return icons[iconType] || icons.info;
 }
 
+/** A short two-tone chime, so a hung or blocked task is heard as well as seen */
+function playAlert() {
+  const context = new AudioContext();
+  [880, 660].forEach((frequency, index) => {
+    const oscillator = context.createOscillator();
+    const gain = context.createGain();
+    const start = context.currentTime + index * 0.18;
+    oscillator.frequency.value = frequency;
+    gain.gain.setValueAtTime(0.15, start);
+    gain.gain.exponentialRampToValueAtTime(0.001, start + 0.16);
+    oscillator.connect(gain).connect(context.destination);
+    oscillator.start(start);
+    oscillator.stop(start + 0.16);
+  });
+  setTimeout(() => context.close().catch(console.error), 500);
+}
+
 function formatDuration(ms: number): string {
   const seconds = Math.floor(ms / 1000);
   if (seconds < 60) return `${seconds}s`;
@@ -109,6 +159,8 @@ export function NotificationWindow() {
   const [task, setTask] = useState<TaskData | null>(null);
   const [progress, setProgress] = useState<number | null>(null);
   const [status, setStatus] = useState<"active" | "complete" | "error">("active");
+  const [overdue, setOverdue] = useState(false);
+  const alerted = useRef(false);
   const [elapsed, setElapsed] = useState(0);
   const [startTime] = useState(Date.now());
 
@@ -125,6 +177,13 @@ export function NotificationWindow() {
     }
   }, []);
 
+  // A blocked agent is announced once, as its popup opens
+  useEffect(() => {
+    if (task?.kind === "awaiting_input" && task.sound) {
+      playAlert();
+    }
+  }, [task]);
+
   // Show window once content is ready (prevents flash)
   useEffect(() => {
     if (task) {
@@ -158,9 +217,21 @@ export function NotificationWindow() {
       }
     });
 
+    const unlistenOverdue = listen<TaskOverdue>("task-overdue", (event) => {
+      if (event.payload.task_id === task.task_id) {
+        // The second threshold reports the task again; chime only once
+        if (event.payload.sound && !alerted.current) {
+          alerted.current = true;
+          playAlert();
+        }
+        setOverdue(true);
+      }
+    });
+
     return () => {
       unlistenProgress.then((fn) => fn());
       unlistenComplete.then((fn) => fn());
+      unlistenOverdue.then((fn) => fn());
     };
   }, [task]);
 
@@ -177,13 +248,118 @@ export function NotificationWindow() {
 
   if (!task) return null;
 
+  // Clicking a popup closes it and brings up the overlay
+  const open = () => invoke("open_notification").catch(console.error);
+
+  // What finished while the user was away, in place of the popups they missed
+  if (task.kind === "away_summary") {
+    const failed = task.failed ?? 0;
+    const counts = [`${task.completed ?? 0} done`, failed > 0 ? `${failed} failed` : null]
+      .filter(Boolean)
+      .join(" · ");
+
+    return (
+      <div className={`notification ${failed > 0 ? "error" : "complete"}`} onClick={open}>
+        <div className="notification-content">
+          <div className={`notification-icon ${failed > 0 ? "error" : "complete"}`}>
+            {getIcon("", "")}
+          </div>
+
+          <div className="notification-body">
+            <div className="notification-title">While you were away: {counts}</div>
+            {task.description && (
+              <div className="notification-meta">
+                <span className="notification-away-last">Last: {task.description}</span>
+              </div>
+            )}
+          </div>
+        </div>
+      </div>
+    );
+  }
+
+  // An agent blocked on the user; stays up until it moves again or is clicked
+  if (task.kind === "awaiting_input") {
+    return (
+      <div className="notification awaiting" onClick={open} role="alert">
+        <div className="notification-content">
+          <div className="notification-icon awaiting">
+            {getIcon("awaiting_input", "")}
+          </div>
+
+          <div className="notification-body">
+            <div className="notification-title">{task.description || "Waiting for your input"}</div>
+            <div className="notification-meta">
+              <span className="notification-status awaiting">
+                {theme?.error.symbol && `${theme.error.symbol} `}
+                Needs your input{task.tool ? ` · ${task.tool}` : ""}
+              </span>
+            </div>
+          </div>
+        </div>
+      </div>
+    );
+  }
+
+  // Usage warnings stay up until the backend closes them
+  if (task.kind === "usage_warning") {
+    const resetsAt = task.resets_at
+      ? new Date(task.resets_at).toLocaleTimeString([], { hour: "2-digit", minute: "2-digit" })
+      : null;
+
+    return (
+      <div className="notification warning" onClick={open}>
+        <div className="notification-content">
+          <div className="notification-icon warning">
+            {getIcon(task.tool, task.description || "")}
+          </div>
+
+          <div className="notification-body">
+            <div className="notification-title">
+              {WARNING_LABELS[task.tool] || "Agent stalled"}
+            </div>
+            <div className="notification-meta">
+              <span className="notification-status warning">
+                {theme?.warning.symbol && `${theme.warning.symbol} `}
+                {resetsAt ? `Resets at ${resetsAt}` : task.description}
+              </span>
+            </div>
+          </div>
+        </div>
+      </div>
+    );
+  }
+
+  // Disk usage crossed the threshold; closes on its own
+  if (task.kind === "storage_warning") {
+    return (
+      <div className="notification warning" onClick={open}>
+        <div className="notification-content">
+          <div className="notification-icon warning">
+            {getIcon("", "")}
+          </div>
+
+          <div className="notification-body">
+            <div className="notification-title">Claude's files take {task.total}</div>
+            {task.description && (
+              <div className="notification-meta">
+                <span className="notification-status warning">{task.description}</span>
+              </div>
+            )}
+          </div>
+        </div>
+      </div>
+    );
+  }
+
   // Only show progress bar when we have real progress data
   const hasRealProgress = progress !== null && progress > 0;
+  const statusClass = status === "active" && overdue ? "overdue" : status;
 
   return (
-    <div className={`notification ${status}`}>
+    <div className={`notification ${statusClass}`} onClick={open}>
       <div className="notification-content">
-        <div className={`notification-icon ${status}`}>
+        <div className={`notification-icon ${statusClass}`}>
           {getIcon(task.tool, task.description || "")}
         </div>
 
@@ -204,12 +380,20 @@ export function NotificationWindow() {
 
           <div className="notification-meta">
             <span className="notification-time">{formatDuration(elapsed)}</span>
+            {task.eta_ms != null && status === "active" && (
+              <span className="notification-eta">usually ~{formatDuration(task.eta_ms)}</span>
+            )}
+            {overdue && status === "active" && (
+              <span className="notification-status overdue">{statusText(theme, "overdue", "Overdue")}</span>
+            )}
             {hasRealProgress && (
               <span className="notification-progress">{Math.round(progress)}%</span>
             )}
             {status !== "active" && (
               <span className={`notification-status ${status}`}>
-                {status === "complete" ? "Done" : "Failed"}
+                {status === "complete"
+                  ? statusText(theme, "complete", "Done")
+                  : statusText(theme, "error", "Failed")}
               </span>
             )}
           </div>
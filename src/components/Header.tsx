@@ -1,13 +1,30 @@
+import { useEffect, useState } from "react";
 import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
+import type { ContextUsage } from "../hooks/useContextUsage";
 
 interface HeaderProps {
   activeTasks: number;
+  /** A session whose context window is nearly full */
+  contextWarning?: ContextUsage;
   onClear: () => void;
   onMinimize: () => void;
   onSettings: () => void;
 }
 
-export function Header({ activeTasks, onClear, onMinimize, onSettings }: HeaderProps) {
+export function Header({ activeTasks, contextWarning, onClear, onMinimize, onSettings }: HeaderProps) {
+  // Background services that keep failing and restarting
+  const [degraded, setDegraded] = useState<string[]>([]);
+
+  useEffect(() => {
+    const unlisten = listen<string[]>("health-status", (event) => {
+      setDegraded(event.payload);
+    });
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, []);
+
   const handleClose = async () => {
     await invoke("close_app");
   };
@@ -19,7 +36,14 @@ export function Header({ activeTasks, onClear, onMinimize, onSettings }: HeaderP
   return (
     <div className="drag-region flex items-center justify-between px-3 py-2 border-b border-overlay-border bg-overlay-bg/95">
       <div className="flex items-center gap-2">
-        <div className="w-2 h-2 rounded-full bg-overlay-accent animate-pulse-slow" />
+        {degraded.length > 0 ? (
+          <div
+            className="w-2 h-2 rounded-full bg-red-400"
+            title={`Degraded: ${degraded.join(", ")} keeps restarting`}
+          />
+        ) : (
+          <div className="w-2 h-2 rounded-full bg-overlay-accent animate-pulse-slow" />
+        )}
         <span className="text-xs font-medium text-overlay-text">
           Agent Progress
         </span>
@@ -28,6 +52,14 @@ export function Header({ activeTasks, onClear, onMinimize, onSettings }: HeaderP
             {activeTasks}
           </span>
         )}
+        {contextWarning && (
+          <span
+            className="px-1.5 py-0.5 text-[10px] font-bold bg-yellow-400/20 text-yellow-400 rounded"
+            title={`Context ${contextWarning.context_tokens.toLocaleString()} of ${contextWarning.context_window.toLocaleString()} tokens; Claude will compact soon`}
+          >
+            {Math.round(contextWarning.percent)}%
+          </span>
+        )}
       </div>
 
       <div className="no-drag flex items-center gap-1">
@@ -0,0 +1,44 @@
+/** Which Claude profile to show: null for all, "" for the main Claude directory */
+export type ProfileSelection = string | null;
+
+interface ProfileFilterProps {
+  profiles: string[];
+  selected: ProfileSelection;
+  onSelect: (profile: ProfileSelection) => void;
+}
+
+/** Whether an item tagged with `profile` is shown under `selected` */
+export function matchesProfile(profile: string | undefined, selected: ProfileSelection): boolean {
+  return selected === null || (profile ?? "") === selected;
+}
+
+export function ProfileFilter({ profiles, selected, onSelect }: ProfileFilterProps) {
+  // Nothing to choose between until a second profile has shown up
+  if (profiles.length === 0) {
+    return null;
+  }
+
+  const options: { label: string; value: ProfileSelection }[] = [
+    { label: "All", value: null },
+    { label: "Main", value: "" },
+    ...profiles.map((profile) => ({ label: profile, value: profile })),
+  ];
+
+  return (
+    <div className="no-drag flex items-center gap-1 px-3 py-1 border-b border-overlay-border">
+      {options.map((option) => (
+        <button
+          key={option.label}
+          onClick={() => onSelect(option.value)}
+          className={`px-1.5 py-0.5 text-[10px] rounded transition-colors ${
+            selected === option.value
+              ? "bg-overlay-accent/20 text-overlay-accent"
+              : "text-overlay-muted hover:text-overlay-text"
+          }`}
+        >
+          {option.label}
+        </button>
+      ))}
+    </div>
+  );
+}
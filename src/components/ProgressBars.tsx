@@ -0,0 +1,45 @@
+import { GenericProgress } from "../hooks/useProgress";
+
+interface ProgressBarsProps {
+  bars: GenericProgress[];
+}
+
+export function ProgressBars({ bars }: ProgressBarsProps) {
+  if (bars.length === 0) return null;
+
+  return (
+    <div className="px-3 py-2 border-b border-overlay-border/50 space-y-1.5">
+      {bars.map((bar) => {
+        const percent = bar.percent == null ? null : Math.max(0, Math.min(bar.percent, 100));
+        return (
+          <div key={bar.id}>
+            <div className="flex items-center justify-between gap-2 text-[11px]">
+              <span className="text-overlay-text truncate">{bar.label || bar.id}</span>
+              {percent !== null && (
+                <span className="text-overlay-muted/70 flex-shrink-0">{Math.round(percent)}%</span>
+              )}
+            </div>
+            <div
+              className="mt-1 w-full h-1.5 bg-overlay-border/30 rounded-full overflow-hidden"
+              role="progressbar"
+              aria-label={bar.label || bar.id}
+              aria-valuemin={0}
+              aria-valuemax={100}
+              aria-valuenow={percent ?? undefined}
+            >
+              <div
+                className={`h-full bg-gradient-to-r from-purple-500 to-indigo-500 rounded-full transition-all duration-100 ${
+                  percent === null ? "animate-pulse" : ""
+                }`}
+                style={{ width: `${percent ?? 100}%` }}
+              />
+            </div>
+            {bar.message && (
+              <div className="mt-0.5 text-[10px] text-overlay-muted/70 truncate">{bar.message}</div>
+            )}
+          </div>
+        );
+      })}
+    </div>
+  );
+}
@@ -1,5 +1,16 @@
+import { useEffect, useState } from "react";
+import { invoke } from "@tauri-apps/api/core";
 import { Settings as SettingsType, WindowPosition } from "../hooks/useSettings";
 
+type Capability = "network" | "filesystem" | "shell";
+
+interface PluginPermissions {
+  name: string;
+  requested: Capability[];
+  granted: Capability[];
+  missing: Capability[];
+}
+
 interface SettingsProps {
   settings: SettingsType;
   onUpdate: <K extends keyof SettingsType>(key: K, value: SettingsType[K]) => void;
@@ -15,6 +26,12 @@ const POSITION_OPTIONS: { value: WindowPosition; label: string }[] = [
 ];
 
 export function Settings({ settings, onUpdate, onReset, onClose }: SettingsProps) {
+  const [plugins, setPlugins] = useState<PluginPermissions[]>([]);
+
+  useEffect(() => {
+    invoke<PluginPermissions[]>("get_plugin_permissions").then(setPlugins).catch(console.error);
+  }, []);
+
   return (
     <div className="absolute inset-0 bg-overlay-bg z-50 flex flex-col">
       {/* Header */}
@@ -122,6 +139,24 @@ export function Settings({ settings, onUpdate, onReset, onClose }: SettingsProps
             />
           </button>
         </div>
+
+        {/* Plugin Permissions */}
+        {plugins.length > 0 && (
+          <div className="space-y-1.5">
+            <label className="text-xs text-overlay-muted block">Plugin Permissions</label>
+            {plugins.map((plugin) => (
+              <div key={plugin.name} className="flex items-center justify-between text-xs">
+                <span className="text-overlay-text">{plugin.name}</span>
+                <span className={plugin.missing.length > 0 ? "text-red-400" : "text-overlay-muted"}>
+                  {plugin.missing.length > 0
+                    ? `Blocked: needs ${plugin.missing.join(", ")}`
+                    : plugin.requested.join(", ") || "none"}
+                </span>
+              </div>
+            ))}
+            <span className="text-[10px] text-overlay-muted/60">Grant access in [plugins.permissions]</span>
+          </div>
+        )}
       </div>
 
       {/* Footer */}
@@ -319,13 +319,14 @@ export function TaskCard({ task }: TaskCardProps) {
   useEffect(() => {
     if (task.status !== "active") {
       if (task.endTime) {
-        setElapsed(task.endTime - task.startTime);
+        // Hook timestamps can run backwards across a clock adjustment
+        setElapsed(Math.max(0, task.endTime - task.startTime));
       }
       return;
     }
 
     const interval = setInterval(() => {
-      setElapsed(Date.now() - task.startTime);
+      setElapsed(Math.max(0, Date.now() - task.startTime));
     }, 1000);
 
     return () => clearInterval(interval);
@@ -393,6 +394,16 @@ export function TaskCard({ task }: TaskCardProps) {
                 BG
               </span>
             )}
+            {task.source && task.source !== "claude-code" && (
+              <span className="px-1 py-0.5 text-[9px] bg-overlay-muted/10 text-overlay-muted rounded">
+                {task.source}
+              </span>
+            )}
+            {task.profile && (
+              <span className="px-1 py-0.5 text-[9px] bg-overlay-muted/10 text-overlay-muted rounded">
+                {task.profile}
+              </span>
+            )}
           </div>
           {/* Summary description */}
           <div className="mt-1">
@@ -419,10 +430,17 @@ export function TaskCard({ task }: TaskCardProps) {
           <div className="flex items-center justify-between mt-1">
             <span
               className={`text-[10px] ${
-                isActive ? "text-overlay-accent" : "text-overlay-muted/70"
+                isActive && task.overdue
+                  ? "text-overlay-error font-medium"
+                  : isActive ? "text-overlay-accent" : "text-overlay-muted/70"
               }`}
             >
               {formatDuration(elapsed)}
+              {isActive && task.overdue && " · overdue"}
+              {isActive && task.cancelPending && " · canceling…"}
+              {isActive && task.etaMs !== undefined && (
+                <span className="text-overlay-muted/70"> · usually ~{formatDuration(task.etaMs)}</span>
+              )}
             </span>
 
             {!isActive && (
@@ -84,6 +84,14 @@ export function TodoSection({ todos }: TodoSectionProps) {
             >
               {todo.status === "in_progress" ? todo.activeForm : todo.content}
             </span>
+            {todo.also_in && todo.also_in.length > 0 && (
+              <span
+                className="flex-shrink-0 text-[9px] px-1 rounded bg-overlay-card text-overlay-muted"
+                title={`In ${todo.also_in.length + 1} sessions`}
+              >
+                ×{todo.also_in.length + 1}
+              </span>
+            )}
           </div>
         ))}
       </div>
@@ -0,0 +1,35 @@
+import { useState, useEffect } from "react";
+import { listen } from "@tauri-apps/api/event";
+
+/** How full a Claude session's context window is */
+export interface ContextUsage {
+  session_id: string;
+  timestamp: number;
+  context_tokens: number;
+  context_window: number;
+  model?: string;
+  percent: number;
+  /** Close enough to full that auto-compaction is near */
+  warning: boolean;
+}
+
+export function useContextUsage() {
+  const [sessions, setSessions] = useState<Map<string, ContextUsage>>(new Map());
+
+  useEffect(() => {
+    const unlisten = listen<ContextUsage>("context-usage", (event) => {
+      setSessions((prev) => new Map(prev).set(event.payload.session_id, event.payload));
+    });
+
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, []);
+
+  // The session closest to compaction, if any is past the warning threshold
+  const warning = Array.from(sessions.values())
+    .filter((usage) => usage.warning)
+    .sort((a, b) => b.percent - a.percent)[0];
+
+  return { sessions, warning };
+}
@@ -0,0 +1,60 @@
+import { useState, useEffect } from "react";
+import { listen } from "@tauri-apps/api/event";
+
+/** Progress a script reported in ~/.claude/progress/ */
+export interface GenericProgress {
+  id: string;
+  label?: string | null;
+  /** 0-100; missing for a bar that only shows activity */
+  percent?: number | null;
+  message?: string | null;
+}
+
+// How long a finished bar stays before it is removed
+const DONE_LINGER_MS = 3000;
+
+export function useProgress() {
+  const [bars, setBars] = useState<Map<string, GenericProgress>>(new Map());
+
+  useEffect(() => {
+    const timers = new Map<string, ReturnType<typeof setTimeout>>();
+
+    const unlisten = listen<GenericProgress>("generic-progress", (event) => {
+      const progress = event.payload;
+      // A later line for the same id replaces the earlier one, keeping fields it leaves out
+      setBars((prev) => {
+        const next = new Map(prev);
+        const current = prev.get(progress.id);
+        next.set(progress.id, {
+          id: progress.id,
+          label: progress.label ?? current?.label,
+          percent: progress.percent ?? current?.percent,
+          message: progress.message ?? current?.message,
+        });
+        return next;
+      });
+
+      clearTimeout(timers.get(progress.id));
+      if ((progress.percent ?? 0) >= 100) {
+        timers.set(
+          progress.id,
+          setTimeout(() => {
+            timers.delete(progress.id);
+            setBars((prev) => {
+              const next = new Map(prev);
+              next.delete(progress.id);
+              return next;
+            });
+          }, DONE_LINGER_MS)
+        );
+      }
+    });
+
+    return () => {
+      unlisten.then((fn) => fn());
+      timers.forEach((timer) => clearTimeout(timer));
+    };
+  }, []);
+
+  return { bars: Array.from(bars.values()) };
+}
@@ -1,5 +1,6 @@
 import { useState, useEffect, useCallback } from "react";
 import { listen } from "@tauri-apps/api/event";
+import { invoke } from "@tauri-apps/api/core";
 
 export interface Task {
   id: string;
@@ -11,11 +12,20 @@ export interface Task {
   background: boolean;
   subagentType?: string;
   sessionId?: string;
+  source?: string;
+  /** Claude profile the task came from; unset for the main Claude directory */
+  profile?: string;
+  /** How long similar tasks usually take */
+  etaMs?: number;
+  /** Running longer than its tool's threshold */
+  overdue?: boolean;
+  /** A cancel was requested and the task hasn't ended yet */
+  cancelPending?: boolean;
   downloadProgress?: number;
 }
 
 interface TaskEvent {
-  type: "task_started" | "task_complete" | "task_error" | "session_stopped";
+  type: "task_started" | "task_complete" | "task_error" | "task_canceled" | "session_stopped" | "awaiting_input";
   task_id: string;
   tool?: string;
   description?: string;
@@ -24,6 +34,19 @@ interface TaskEvent {
   background?: boolean;
   subagent_type?: string;
   duration_ms?: number;
+  source?: string;
+  profile?: string;
+  eta_ms?: number | null;
+}
+
+interface TaskOverdueEvent {
+  task_id: string;
+  elapsed_ms: number;
+  escalated: boolean;
+}
+
+interface TaskCancelEvent {
+  task_id: string;
 }
 
 interface DownloadProgressEvent {
@@ -34,8 +57,54 @@ interface DownloadProgressEvent {
   timestamp: number;
 }
 
+// A task as stored in the history database
+interface StoredTask {
+  id: string;
+  session_id: string;
+  tool: string;
+  description?: string;
+  status: string;
+  started_at: number;
+  is_background: boolean;
+  subagent_type?: string;
+  source: string;
+}
+
+interface ReconciledSnapshot {
+  active: StoredTask[];
+  expired: string[];
+}
+
 const MAX_COMPLETED_TASKS = 5;
 
+function fromStored(task: StoredTask): Task {
+  return {
+    id: task.id,
+    tool: task.tool,
+    description: task.description || "Running...",
+    startTime: task.started_at,
+    status: "active",
+    background: task.is_background,
+    subagentType: task.subagent_type,
+    sessionId: task.session_id,
+    source: task.source,
+  };
+}
+
+// Add still-running tasks the overlay doesn't know about and drop expired ones
+function mergeSnapshot(prev: Map<string, Task>, snapshot: ReconciledSnapshot): Map<string, Task> {
+  const next = new Map(prev);
+  for (const id of snapshot.expired) {
+    next.delete(id);
+  }
+  for (const task of snapshot.active) {
+    if (!next.has(task.id)) {
+      next.set(task.id, fromStored(task));
+    }
+  }
+  return next;
+}
+
 export function useTasks() {
   const [tasks, setTasks] = useState<Map<string, Task>>(new Map());
   const [isVisible, setIsVisible] = useState(true);
@@ -97,6 +166,9 @@ export function useTasks() {
             background: data.background || false,
             subagentType: data.subagent_type,
             sessionId: data.session_id,
+            source: data.source,
+            profile: data.profile ?? undefined,
+            etaMs: data.eta_ms ?? undefined,
           });
         } else if (data.type === "task_complete" || data.type === "task_error") {
           const existing = next.get(data.task_id);
@@ -107,6 +179,8 @@ export function useTasks() {
               endTime: data.timestamp,
             });
           }
+        } else if (data.type === "task_canceled") {
+          next.delete(data.task_id);
         } else if (data.type === "session_stopped") {
           // Clear all active tasks when session is stopped (user hit Escape/canceled)
           for (const [id, task] of next) {
@@ -128,6 +202,67 @@ export function useTasks() {
     };
   }, [resetHideTimer]);
 
+  // Pick up tasks that were running before the overlay started. The snapshot
+  // may be emitted before this listener exists, so also ask for it directly
+  useEffect(() => {
+    invoke<StoredTask[]>("get_active_tasks")
+      .then((active) => setTasks((prev) => mergeSnapshot(prev, { active, expired: [] })))
+      .catch(console.error);
+
+    const unlisten = listen<ReconciledSnapshot>("tasks-reconciled", (event) => {
+      setTasks((prev) => mergeSnapshot(prev, event.payload));
+    });
+
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, []);
+
+  // Flag tasks that have run past their tool's threshold
+  useEffect(() => {
+    const unlisten = listen<TaskOverdueEvent>("task-overdue", (event) => {
+      setTasks((prev) => {
+        const task = prev.get(event.payload.task_id);
+        if (!task || task.status !== "active" || task.overdue) {
+          return prev;
+        }
+        const next = new Map(prev);
+        next.set(task.id, { ...task, overdue: true });
+        return next;
+      });
+    });
+
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, []);
+
+  // Show tasks as canceling from the request until they end
+  useEffect(() => {
+    const setPending = (taskId: string, cancelPending: boolean) =>
+      setTasks((prev) => {
+        const task = prev.get(taskId);
+        if (!task || !!task.cancelPending === cancelPending) {
+          return prev;
+        }
+        const next = new Map(prev);
+        next.set(taskId, { ...task, cancelPending });
+        return next;
+      });
+
+    const unlistenPending = listen<TaskCancelEvent>("task-cancel-pending", (event) => {
+      setPending(event.payload.task_id, true);
+    });
+    const unlistenResolved = listen<TaskCancelEvent>("task-cancel-resolved", (event) => {
+      setPending(event.payload.task_id, false);
+    });
+
+    return () => {
+      unlistenPending.then((fn) => fn());
+      unlistenResolved.then((fn) => fn());
+    };
+  }, []);
+
   // Listen for download progress updates
   useEffect(() => {
     const unlisten = listen<DownloadProgressEvent>("download-progress", (event) => {
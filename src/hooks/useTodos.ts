@@ -1,14 +1,41 @@
-import { useState, useEffect } from "react";
+import { useState, useEffect, useCallback } from "react";
 import { listen } from "@tauri-apps/api/event";
+import { invoke } from "@tauri-apps/api/core";
 
 export interface TodoItem {
   content: string;
   status: "pending" | "in_progress" | "completed";
   activeForm: string;
+  /** Claude profile the todo came from; unset for the main Claude directory */
+  profile?: string;
+  /** Other sessions with the same todo, when duplicates are collapsed */
+  also_in?: string[];
+}
+
+/** One session's todos, labeled with the project it runs in */
+export interface TodoGroup {
+  session_id: string;
+  name: string;
+  project_path: string | null;
+  profile?: string;
+  todos: TodoItem[];
+}
+
+/** What changed in the latest update, for animating items in and out */
+export interface TodosDiff {
+  added: TodoItem[];
+  updated: TodoItem[];
+  removed: TodoItem[];
+  /** The removed todos that were completed rather than dropped */
+  completed?: TodoItem[];
+  /** Todos of files that went stale, which left the list without finishing */
+  hidden?: TodoItem[];
 }
 
 export function useTodos() {
   const [todos, setTodos] = useState<TodoItem[]>([]);
+  const [lastChange, setLastChange] = useState<TodosDiff | null>(null);
+  const [groups, setGroups] = useState<TodoGroup[]>([]);
 
   useEffect(() => {
     console.log("[useTodos] Setting up event listener...");
@@ -18,11 +45,28 @@ export function useTodos() {
       setTodos(event.payload || []);
     });
 
+    const unlistenDiff = listen<TodosDiff>("todos-diff", (event) => {
+      setLastChange(event.payload);
+    });
+
+    const unlistenGroups = listen<TodoGroup[]>("todos-grouped", (event) => {
+      setGroups(event.payload || []);
+    });
+    invoke<TodoGroup[]>("get_todo_groups").then(setGroups).catch(console.error);
+
     return () => {
       unlisten.then((fn) => fn());
+      unlistenDiff.then((fn) => fn());
+      unlistenGroups.then((fn) => fn());
     };
   }, []);
 
+  // Show the new order right away; the saved order applies to later updates
+  const reorder = useCallback((ordered: TodoItem[]) => {
+    setTodos(ordered);
+    invoke("reorder_todos", { contents: ordered.map((t) => t.content) }).catch(console.error);
+  }, []);
+
   const pendingTodos = todos.filter((t) => t.status === "pending");
   const inProgressTodos = todos.filter((t) => t.status === "in_progress");
   const completedTodos = todos.filter((t) => t.status === "completed");
@@ -32,6 +76,9 @@ export function useTodos() {
     pendingTodos,
     inProgressTodos,
     completedTodos,
+    lastChange,
+    groups,
+    reorder,
     hasTodos: todos.length > 0,
   };
 }
@@ -1,8 +1,24 @@
 import React from "react";
 import ReactDOM from "react-dom/client";
+import { invoke } from "@tauri-apps/api/core";
 import App from "./App";
+import { applyTheme, Theme } from "./theme";
 import "./styles/index.css";
 
+// behavior.reduced_motion turns animations off on top of the OS setting
+invoke<boolean>("get_reduced_motion")
+  .then((reduced) => document.documentElement.classList.toggle("reduce-motion", reduced))
+  .catch((e) => console.error("Failed to read motion preference:", e));
+
+// Let window.backdrop's material show through the background
+invoke<string>("get_backdrop")
+  .then((backdrop) => document.documentElement.classList.toggle("backdrop", backdrop !== "none"))
+  .catch((e) => console.error("Failed to read backdrop:", e));
+
+invoke<Theme>("get_theme")
+  .then(applyTheme)
+  .catch((e) => console.error("Failed to read theme:", e));
+
 ReactDOM.createRoot(document.getElementById("root")!).render(
   <React.StrictMode>
     <App />
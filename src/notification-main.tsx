@@ -1,6 +1,17 @@
 import React from "react";
 import ReactDOM from "react-dom/client";
 import { NotificationWindow } from "./NotificationWindow";
+import { applyTheme, themeFromUrl } from "./theme";
+
+// Passed in the URL so the popup is still before its first paint
+if (new URLSearchParams(window.location.search).get("motion") === "reduced") {
+  document.documentElement.classList.add("reduce-motion");
+}
+
+const theme = themeFromUrl();
+if (theme) {
+  applyTheme(theme);
+}
 
 ReactDOM.createRoot(document.getElementById("root")!).render(
   <React.StrictMode>
@@ -0,0 +1,56 @@
+/** How one status is shown, beyond its color */
+export interface StatusIndicator {
+  color: string;
+  /** Empty when the theme leaves the status to its icon and color */
+  symbol: string;
+  label: string;
+  border: string;
+}
+
+export type StatusName = "active" | "complete" | "error" | "overdue" | "warning";
+
+const STATUSES: StatusName[] = ["active", "complete", "error", "overdue", "warning"];
+
+/** A built-in theme, as returned by the get_theme command */
+export interface Theme extends Record<StatusName, StatusIndicator> {
+  name: string;
+  colors: Record<string, string>;
+}
+
+/** "#0f172a" as "15 23 42", so Tailwind can add opacity to it */
+function channels(hex: string): string {
+  const value = parseInt(hex.replace("#", ""), 16);
+  return `${(value >> 16) & 255} ${(value >> 8) & 255} ${value & 255}`;
+}
+
+/** Expose the theme to the stylesheets as CSS variables */
+export function applyTheme(theme: Theme) {
+  const root = document.documentElement;
+  root.dataset.theme = theme.name;
+  for (const [name, hex] of Object.entries(theme.colors)) {
+    root.style.setProperty(`--overlay-${name}`, channels(hex));
+  }
+  for (const status of STATUSES) {
+    root.style.setProperty(`--status-${status}`, theme[status].color);
+    root.style.setProperty(`--status-${status}-border`, theme[status].border);
+  }
+}
+
+/** The theme a popup was opened with, passed in its URL */
+export function themeFromUrl(): Theme | null {
+  const theme = new URLSearchParams(window.location.search).get("theme");
+  if (!theme) return null;
+  try {
+    return JSON.parse(theme);
+  } catch (e) {
+    console.error("Failed to parse theme:", e);
+    return null;
+  }
+}
+
+/** Status text, with the theme's symbol in front when it has one */
+export function statusText(theme: Theme | null, status: StatusName, fallback: string): string {
+  const indicator = theme?.[status];
+  if (!indicator) return fallback;
+  return indicator.symbol ? `${indicator.symbol} ${indicator.label}` : indicator.label;
+}
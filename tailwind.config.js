@@ -5,14 +5,16 @@ export default {
     extend: {
       colors: {
         overlay: {
-          bg: "#0f172a",
-          card: "#1e293b",
-          border: "#334155",
-          text: "#f8fafc",
-          muted: "#94a3b8",
-          accent: "#f59e0b",
-          success: "#22c55e",
-          error: "#ef4444",
+          // Set from the theme command; the fallbacks are the dark theme
+          bg: "rgb(var(--overlay-bg, 15 23 42) / <alpha-value>)",
+          card: "rgb(var(--overlay-card, 30 41 59) / <alpha-value>)",
+          border: "rgb(var(--overlay-border, 51 65 85) / <alpha-value>)",
+          text: "rgb(var(--overlay-text, 248 250 252) / <alpha-value>)",
+          muted: "rgb(var(--overlay-muted, 148 163 184) / <alpha-value>)",
+          accent: "rgb(var(--overlay-accent, 245 158 11) / <alpha-value>)",
+          success: "rgb(var(--overlay-success, 34 197 94) / <alpha-value>)",
+          error: "rgb(var(--overlay-error, 239 68 68) / <alpha-value>)",
+          warning: "rgb(var(--overlay-warning, 234 179 8) / <alpha-value>)",
         },
       },
       animation: {